@@ -0,0 +1,70 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Inlay hints of LSP 3.17 which are not part of languageserver-types
+
+use languageserver_types::{Position, Range, TextDocumentIdentifier};
+use serde_json::Value;
+use vhdl_parser::inlay_hints::InlayHintKind;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHintParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: String,
+    /// 1 for types and 2 for parameters
+    pub kind: u32,
+    pub padding_left: bool,
+    pub padding_right: bool,
+}
+
+pub fn kind_number(kind: InlayHintKind) -> u32 {
+    match kind {
+        InlayHintKind::Type => 1,
+        InlayHintKind::Parameter => 2,
+    }
+}
+
+/// The kinds of hints which are shown, set by the inlayHints initialization option
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintsConfig {
+    pub parameter_names: bool,
+    pub types: bool,
+}
+
+impl Default for InlayHintsConfig {
+    fn default() -> InlayHintsConfig {
+        InlayHintsConfig {
+            parameter_names: true,
+            types: true,
+        }
+    }
+}
+
+impl InlayHintsConfig {
+    /// All kinds are shown when the option is missing or invalid
+    pub fn from_initialization_options(options: Option<&Value>) -> InlayHintsConfig {
+        options
+            .and_then(|options| options.get("inlayHints"))
+            .and_then(|config| serde_json::from_value(config.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn shows(&self, kind: InlayHintKind) -> bool {
+        match kind {
+            InlayHintKind::Parameter => self.parameter_names,
+            InlayHintKind::Type => self.types,
+        }
+    }
+}
@@ -6,253 +6,353 @@
 
 extern crate jsonrpc_core;
 extern crate languageserver_types;
-
-use jsonrpc_core::request::Notification;
-use jsonrpc_core::*;
-use languageserver_types::{
-    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, InitializeResult, Position,
-    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
-    TextDocumentSyncKind,
-};
-use std::io::prelude::*;
-use std::io::{self, BufRead};
-
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+extern crate url;
+extern crate url_serde;
 extern crate vhdl_parser;
-use vhdl_parser::message::{Message, Severity};
-use vhdl_parser::source::{Source, SrcPos};
-use vhdl_parser::{ParserError, VHDLParser};
-
-fn srcpos_to_range(srcpos: SrcPos) -> Range {
-    let contents = srcpos.source.contents().unwrap();
-    let mut start = None;
-    let mut end = None;
-
-    let mut cursor = Position {
-        line: 0,
-        character: 0,
-    };
-    for (i, byte) in contents.bytes.iter().enumerate() {
-        if i == srcpos.start {
-            start = Some(cursor);
-        }
 
-        if i == srcpos.start + srcpos.length {
-            end = Some(cursor);
-        }
-
-        if *byte == b'\n' {
-            cursor.line += 1;
-            cursor.character = 0;
-        } else {
-            cursor.character += 1;
-        };
-    }
-
-    Range {
-        start: start.unwrap_or(cursor),
-        end: end.unwrap_or(cursor),
-    }
+mod call_hierarchy;
+mod inlay_hints;
+mod progress;
+mod rpc_channel;
+mod semantic_tokens;
+mod settings;
+mod type_hierarchy;
+mod vhdl_server;
+
+use jsonrpc_core::{Error, IoHandler, Params};
+use rpc_channel::{read_message, response, write_message, StdoutChannel};
+use std::io;
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use vhdl_server::{AnalysisResult, VHDLServer};
+
+type SharedServer = Arc<Mutex<VHDLServer<StdoutChannel>>>;
+
+/// The input of the main loop
+enum Event {
+    Message(io::Result<Option<String>>),
+    Analyzed(Box<AnalysisResult>),
 }
 
-fn read_header(reader: &mut BufRead) -> u64 {
-    let mut buffer = String::new();
-    reader.read_line(&mut buffer).unwrap();
-    let fields = buffer.trim_end().clone().split(": ").collect::<Vec<&str>>();
-    if fields.get(0) != Some(&"Content-Length") {
-        eprintln!("{:?}", fields);
-        panic!();
-    }
-    let content_length = fields.get(1).unwrap().parse::<u64>().unwrap();
-
-    let mut buffer = String::new();
-    reader.read_line(&mut buffer).unwrap();
-    if buffer == "\r\n" {
-        return content_length;
-    }
-
-    let fields = buffer.trim_end().clone().split(": ").collect::<Vec<&str>>();
-    if fields.get(0) != Some(&"Content-Type") {
-        eprintln!("{:?}", fields);
-        panic!();
-    } else {
-        eprintln!("got Content-Type: {}", fields.get(1).unwrap());
-    }
-
-    let mut buffer = String::new();
-    reader.read_line(&mut buffer).unwrap();
-    if buffer != "\r\n" {
-        eprintln!("{:?}", buffer);
-        panic!();
-    }
+/// Register a notification which is forwarded to the server
+fn add_notification<P, F>(
+    io: &mut IoHandler,
+    server: &SharedServer,
+    method: &'static str,
+    handler: F,
+) where
+    P: serde::de::DeserializeOwned,
+    F: Fn(&mut VHDLServer<StdoutChannel>, P) + Send + Sync + 'static,
+{
+    let server = server.clone();
+    io.add_notification(method, move |params: Params| match params.parse() {
+        Ok(params) => handler(&mut server.lock().unwrap(), params),
+        Err(err) => eprintln!("Invalid {} notification: {}", method, err.message),
+    });
+}
 
-    return content_length;
+/// Register a request which is forwarded to the server
+fn add_method<P, R, F>(io: &mut IoHandler, server: &SharedServer, method: &'static str, handler: F)
+where
+    P: serde::de::DeserializeOwned,
+    R: serde::Serialize,
+    F: Fn(&mut VHDLServer<StdoutChannel>, P) -> R + Send + Sync + 'static,
+{
+    let server = server.clone();
+    io.add_method(method, move |params: Params| {
+        let params = params.parse()?;
+        let result = handler(&mut server.lock().unwrap(), params);
+        Ok(serde_json::to_value(result).unwrap())
+    });
 }
 
-fn to_diagnostic(message: Message) -> Diagnostic {
-    let severity = match message.severity {
-        Severity::Error => DiagnosticSeverity::Error,
-        Severity::Warning => DiagnosticSeverity::Warning,
-    };
-    Diagnostic {
-        range: srcpos_to_range(message.pos),
-        severity: Some(severity),
-        code: None,
-        source: Some("vhdl ls".to_owned()),
-        message: message.message,
-        related_information: None,
-    }
+/// Register a request which is forwarded to the server where a failure is shown to the user
+fn add_fallible_method<P, R, F>(
+    io: &mut IoHandler,
+    server: &SharedServer,
+    method: &'static str,
+    handler: F,
+) where
+    P: serde::de::DeserializeOwned,
+    R: serde::Serialize,
+    F: Fn(&mut VHDLServer<StdoutChannel>, P) -> Result<R, String> + Send + Sync + 'static,
+{
+    let server = server.clone();
+    io.add_method(method, move |params: Params| {
+        let params = params.parse()?;
+        match handler(&mut server.lock().unwrap(), params) {
+            Ok(result) => Ok(serde_json::to_value(result).unwrap()),
+            Err(message) => Err(Error::invalid_params(message)),
+        }
+    });
 }
 
 fn main() -> io::Result<()> {
-    let mut io: IoHandler<()> = IoHandler::default();
-    io.add_method("initialize", |_params| {
-        let result = InitializeResult {
-            capabilities: ServerCapabilities {
-                /// Defines how text documents are synced.
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::Full,
-                )),
-
-                /// The server provides hover support.
-                hover_provider: None,
-
-                /// The server provides completion support.
-                completion_provider: None,
-
-                /// The server provides signature help support.
-                signature_help_provider: None,
-
-                /// The server provides goto definition support.
-                definition_provider: None,
-
-                /// The server provides goto type definition support.
-                type_definition_provider: None,
-
-                /// the server provides goto implementation support.
-                implementation_provider: None,
-
-                /// The server provides find references support.
-                references_provider: None,
-
-                /// The server provides document highlight support.
-                document_highlight_provider: None,
-
-                /// The server provides document symbol support.
-                document_symbol_provider: None,
-
-                /// The server provides workspace symbol support.
-                workspace_symbol_provider: None,
-
-                /// The server provides code actions.
-                code_action_provider: None,
-
-                /// The server provides code lens.
-                code_lens_provider: None,
-
-                /// The server provides document formatting.
-                document_formatting_provider: None,
-
-                /// The server provides document range formatting.
-                document_range_formatting_provider: None,
-
-                /// The server provides document formatting on typing.
-                document_on_type_formatting_provider: None,
-
-                /// The server provides rename support.
-                rename_provider: None,
-
-                /// The server provides color provider support.
-                color_provider: None,
-
-                /// The server provides folding provider support.
-                folding_range_provider: None,
-
-                /// The server provides execute command support.
-                execute_command_provider: None,
-
-                /// Workspace specific server capabilities
-                workspace: None,
-            },
-        };
+    let server: SharedServer = Arc::new(Mutex::new(VHDLServer::new(StdoutChannel)));
+    let mut io = IoHandler::new();
+
+    add_method(
+        &mut io,
+        &server,
+        "initialize",
+        VHDLServer::initialize_request,
+    );
+
+    let shutdown_server = server.clone();
+    io.add_method("shutdown", move |_params| {
+        shutdown_server.lock().unwrap().shutdown_request();
+        Ok(serde_json::Value::Null)
+    });
 
-        // Ok(serde_json::from_str(&serde_json::to_string(&result).unwrap()).unwrap())
-        Ok(serde_json::to_value(result).unwrap())
+    let initialized_server = server.clone();
+    io.add_notification("initialized", move |_params| {
+        initialized_server
+            .lock()
+            .unwrap()
+            .initialized_notification();
     });
-    io.add_notification("initialized", |_params| {});
-    io.add_notification("textDocument/didChange", |params: jsonrpc_core::Params| {
-        let params: DidChangeTextDocumentParams = params.parse().unwrap();
-        eprintln!("textDocument/didChange params: {:#?}", params);
 
-        let parser = VHDLParser::new();
-        let mut messages = Vec::new();
+    let exit_server = server.clone();
+    io.add_notification("exit", move |_params| {
+        std::process::exit(exit_server.lock().unwrap().exit_notification());
+    });
 
-        // @TODO return error to client
-        let source = Source::from_str(&params.content_changes.get(0).unwrap().text)
-            .expect("Source was not legal latin-1");
-        let mut diagnostics = Vec::new();
-        match parser.parse_design_source(&source, &mut messages) {
-            Err(ParserError::Message(message)) => {
-                eprintln!("{}", message.pretty_string());
-                diagnostics.push(to_diagnostic(message));
+    add_notification(
+        &mut io,
+        &server,
+        "textDocument/didOpen",
+        VHDLServer::text_document_did_open_notification,
+    );
+    add_notification(
+        &mut io,
+        &server,
+        "textDocument/didChange",
+        VHDLServer::text_document_did_change_notification,
+    );
+    add_notification(
+        &mut io,
+        &server,
+        "textDocument/didSave",
+        VHDLServer::text_document_did_save_notification,
+    );
+    add_notification(
+        &mut io,
+        &server,
+        "textDocument/didClose",
+        VHDLServer::text_document_did_close_notification,
+    );
+    add_notification(
+        &mut io,
+        &server,
+        "workspace/didChangeConfiguration",
+        VHDLServer::workspace_did_change_configuration_notification,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/definition",
+        VHDLServer::text_document_definition_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/implementation",
+        VHDLServer::text_document_implementation_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/references",
+        VHDLServer::text_document_references_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/documentHighlight",
+        VHDLServer::text_document_document_highlight_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/hover",
+        VHDLServer::text_document_hover_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/documentSymbol",
+        VHDLServer::text_document_document_symbol_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/signatureHelp",
+        VHDLServer::text_document_signature_help_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/codeAction",
+        VHDLServer::text_document_code_action_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/codeLens",
+        VHDLServer::text_document_code_lens_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/formatting",
+        VHDLServer::text_document_formatting_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/rangeFormatting",
+        VHDLServer::text_document_range_formatting_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/onTypeFormatting",
+        VHDLServer::text_document_on_type_formatting_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/semanticTokens/full",
+        VHDLServer::text_document_semantic_tokens_full_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/semanticTokens/full/delta",
+        VHDLServer::text_document_semantic_tokens_full_delta_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/inlayHint",
+        VHDLServer::text_document_inlay_hint_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/prepareCallHierarchy",
+        VHDLServer::text_document_prepare_call_hierarchy_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "callHierarchy/incomingCalls",
+        VHDLServer::call_hierarchy_incoming_calls_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "callHierarchy/outgoingCalls",
+        VHDLServer::call_hierarchy_outgoing_calls_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "textDocument/prepareTypeHierarchy",
+        VHDLServer::text_document_prepare_type_hierarchy_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "typeHierarchy/supertypes",
+        VHDLServer::type_hierarchy_supertypes_request,
+    );
+    add_method(
+        &mut io,
+        &server,
+        "typeHierarchy/subtypes",
+        VHDLServer::type_hierarchy_subtypes_request,
+    );
+    add_fallible_method(
+        &mut io,
+        &server,
+        "textDocument/prepareRename",
+        VHDLServer::text_document_prepare_rename_request,
+    );
+    add_fallible_method(
+        &mut io,
+        &server,
+        "textDocument/rename",
+        VHDLServer::text_document_rename_request,
+    );
+    add_fallible_method(
+        &mut io,
+        &server,
+        "workspace/executeCommand",
+        VHDLServer::workspace_execute_command_request,
+    );
+
+    // Messages are read by another thread such that background work is started
+    // only while no message is waiting
+    let (sender, receiver) = mpsc::channel();
+    let message_sender = sender.clone();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        loop {
+            let message = read_message(&mut stdin);
+            let done = !matches!(message, Ok(Some(_)));
+            if message_sender.send(Event::Message(message)).is_err() || done {
+                break;
             }
-            Err(ParserError::IOError(error)) => eprintln!("{}", error),
-            Ok(..) => {}
-        };
-
-        for message in messages {
-            eprintln!("{}", message.pretty_string());
-            diagnostics.push(to_diagnostic(message));
         }
+    });
 
-        let publish_diagnostics = PublishDiagnosticsParams {
-            uri: params.text_document.uri,
-            diagnostics: diagnostics,
+    loop {
+        let event = match receiver.try_recv() {
+            Ok(event) => event,
+            Err(TryRecvError::Empty) => {
+                // The project is analyzed by another thread such that requests such as
+                // hover and completion are answered while the analysis is running
+                let analysis = server.lock().unwrap().background_analysis();
+                if let Some(analysis) = analysis {
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        let _ = sender.send(Event::Analyzed(Box::new(analysis.run())));
+                    });
+                }
+                match receiver.recv() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                }
+            }
+            Err(TryRecvError::Disconnected) => break,
         };
-
-        let publish_diagnostics_json = match serde_json::to_value(publish_diagnostics).unwrap() {
-            serde_json::Value::Object(map) => map,
-            map => panic!("{:?}", map),
+        let message = match event {
+            Event::Message(message) => message,
+            Event::Analyzed(result) => {
+                server.lock().unwrap().finish_background_analysis(*result);
+                continue;
+            }
         };
-
-        let notification = Notification {
-            jsonrpc: Some(Version::V2),
-            method: "textDocument/publishDiagnostics".to_owned(),
-            params: Params::Map(publish_diagnostics_json),
+        let message = match message? {
+            Some(message) => message,
+            None => break,
         };
 
-        let serialized = serde_json::to_string(&notification).unwrap();
-
-        eprintln!("{:?}", serialized);
-        print!("Content-Length: {}\r\n", serialized.len());
-        print!("\r\n");
-        print!("{}", serialized);
-        io::stdout().flush().ok().expect("Could not flush stdout");
-    });
-
-    // ServerBuilder::new(io).build();
-
-    let mut stdin = io::stdin();
-
-    loop {
-        let content_length = read_header(&mut stdin.lock());
-        eprintln!("content_length = {}", content_length);
-
-        let request = {
-            let handle = &mut stdin;
-            let mut buffer = String::new();
-            handle
-                .take(content_length)
-                .read_to_string(&mut buffer)
-                .unwrap();
-            buffer
-        };
-        eprintln!("{:?}", request);
-        if let Some(response) = io.handle_request_sync(&request) {
-            eprintln!("{:?}", response);
-            print!("Content-Length: {}\r\n", response.len());
-            print!("\r\n");
-            print!("{}", response);
-            io::stdout().flush().ok().expect("Could not flush stdout");
+        // Responses of the client to requests of the server such as workspace/configuration
+        if let Some(output) = response(&message) {
+            server.lock().unwrap().client_response(output);
+        } else if let Some(response) = io.handle_request_sync(&message) {
+            let stdout = io::stdout();
+            write_message(&mut stdout.lock(), &response)?;
         }
     }
+    Ok(())
 }
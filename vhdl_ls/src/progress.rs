@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Work done progress of LSP 3.15 which is not part of languageserver-types
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkDoneProgressCreateParams {
+    pub token: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WorkDoneProgress {
+    Begin {
+        title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        percentage: u32,
+    },
+    Report {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        percentage: u32,
+    },
+    End {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProgressParams {
+    pub token: String,
+    pub value: WorkDoneProgress,
+}
+
+/// The percentage of files done where no files at all is complete
+pub fn percentage(done: usize, total: usize) -> u32 {
+    (100 * done).checked_div(total).unwrap_or(100) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, Value};
+
+    #[test]
+    fn serializes_kind_as_tag() {
+        let value = serde_json::to_value(ProgressParams {
+            token: "token".to_owned(),
+            value: WorkDoneProgress::Report {
+                message: Some("1/3 files".to_owned()),
+                percentage: percentage(1, 3),
+            },
+        })
+        .unwrap();
+        let expected: Value = serde_json::from_str(
+            r#"{"token": "token", "value": {"kind": "report", "message": "1/3 files", "percentage": 33}}"#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+        assert_eq!(percentage(0, 0), 100);
+    }
+}
@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Framing of JSON-RPC messages with Content-Length headers as used by the Language Server Protocol
+
+use jsonrpc_core::request::{MethodCall, Notification};
+use jsonrpc_core::{Id, Output, Params, Version};
+use serde_json::{self, Value};
+use std::io;
+use std::io::prelude::*;
+
+/// A channel to the client for messages which are not responses to requests
+pub trait RpcChannel {
+    fn send_notification(&self, method: &str, params: Value);
+    /// The response of the client is identified by the id
+    fn send_request(&self, id: u64, method: &str, params: Value);
+}
+
+/// Read the header lines of a message and return the content length
+/// Returns None when the input has ended
+fn read_header(reader: &mut dyn BufRead) -> io::Result<Option<usize>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let mut fields = line.splitn(2, ':');
+        let name = fields.next().unwrap_or("").trim();
+        let value = fields.next().unwrap_or("").trim();
+        // Other headers such as Content-Type are ignored
+        if name.eq_ignore_ascii_case("Content-Length") {
+            let length = value.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid Content-Length '{}'", value),
+                )
+            })?;
+            content_length = Some(length);
+        }
+    }
+
+    content_length
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header"))
+}
+
+/// Read the content of the next message, returns None when the input has ended
+pub fn read_message(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let content_length = match read_header(reader)? {
+        Some(content_length) => content_length,
+        None => {
+            return Ok(None);
+        }
+    };
+    let mut buffer = vec![0; content_length];
+    reader.read_exact(&mut buffer)?;
+    String::from_utf8(buffer)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+pub fn write_message(writer: &mut dyn Write, content: &str) -> io::Result<()> {
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+fn to_params(params: Value) -> Params {
+    match params {
+        Value::Object(map) => Params::Map(map),
+        Value::Array(array) => Params::Array(array),
+        _ => Params::None,
+    }
+}
+
+pub fn notification(method: &str, params: Value) -> Notification {
+    Notification {
+        jsonrpc: Some(Version::V2),
+        method: method.to_owned(),
+        params: to_params(params),
+    }
+}
+
+pub fn request(id: u64, method: &str, params: Value) -> MethodCall {
+    MethodCall {
+        jsonrpc: Some(Version::V2),
+        method: method.to_owned(),
+        params: to_params(params),
+        id: Id::Num(id),
+    }
+}
+
+/// The response of the client when the message is one rather than a request or notification
+pub fn response(content: &str) -> Option<Output> {
+    serde_json::from_str(content).ok()
+}
+
+/// Sends messages to the client on stdout
+pub struct StdoutChannel;
+
+impl RpcChannel for StdoutChannel {
+    fn send_notification(&self, method: &str, params: Value) {
+        let serialized = serde_json::to_string(&notification(method, params)).unwrap();
+        let stdout = io::stdout();
+        if let Err(err) = write_message(&mut stdout.lock(), &serialized) {
+            eprintln!("Could not send notification {}: {}", method, err);
+        }
+    }
+
+    fn send_request(&self, id: u64, method: &str, params: Value) {
+        let serialized = serde_json::to_string(&request(id, method, params)).unwrap();
+        let stdout = io::stdout();
+        if let Err(err) = write_message(&mut stdout.lock(), &serialized) {
+            eprintln!("Could not send request {}: {}", method, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_messages() {
+        let mut input = Cursor::new(
+            "Content-Length: 2\r\n\r\n{}\
+             content-length: 3\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n[1]",
+        );
+        assert_eq!(read_message(&mut input).unwrap(), Some("{}".to_owned()));
+        assert_eq!(read_message(&mut input).unwrap(), Some("[1]".to_owned()));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_headers() {
+        assert!(read_message(&mut Cursor::new("Content-Type: x\r\n\r\n")).is_err());
+        assert!(read_message(&mut Cursor::new("Content-Length: x\r\n\r\n")).is_err());
+        assert!(read_message(&mut Cursor::new("Content-Length: 10\r\n\r\n{}")).is_err());
+    }
+
+    #[test]
+    fn write_messages() {
+        let mut output = Vec::new();
+        write_message(&mut output, "{}").unwrap();
+        assert_eq!(output, b"Content-Length: 2\r\n\r\n{}");
+    }
+
+    #[test]
+    fn responses_are_distinguished_from_requests() {
+        assert!(response(r#"{"jsonrpc": "2.0", "id": 1, "result": [null]}"#).is_some());
+        assert!(response(
+            r#"{"jsonrpc": "2.0", "id": 1, "error": {"code": -32601, "message": "x"}}"#
+        )
+        .is_some());
+        assert!(response(r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#).is_none());
+        assert!(response(r#"{"jsonrpc": "2.0", "method": "initialized"}"#).is_none());
+    }
+}
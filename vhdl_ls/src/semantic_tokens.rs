@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Semantic tokens of LSP 3.16 which are not part of languageserver-types
+//! Names are classified by the kind of the named entity they denote
+
+use languageserver_types::TextDocumentIdentifier;
+use vhdl_parser::name_resolution::{NameResolution, NamedEntityKind};
+use vhdl_parser::source::{Source, SrcPos};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SemanticTokensFullOptions {
+    pub delta: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SemanticTokensOptions {
+    pub legend: SemanticTokensLegend,
+    pub full: SemanticTokensFullOptions,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensDeltaParams {
+    pub text_document: TextDocumentIdentifier,
+    pub previous_result_id: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokens {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_id: Option<String>,
+    pub data: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensEdit {
+    pub start: u32,
+    pub delete_count: u32,
+    pub data: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_id: Option<String>,
+    pub edits: Vec<SemanticTokensEdit>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SemanticTokensDeltaResult {
+    Tokens(SemanticTokens),
+    Delta(SemanticTokensDelta),
+}
+
+/// The standard token types which are used, the index is the encoded token type
+const TOKEN_TYPES: &[&str] = &[
+    "namespace",
+    "class",
+    "type",
+    "parameter",
+    "variable",
+    "property",
+    "enumMember",
+    "function",
+];
+
+const DECLARATION: u32 = 1;
+const READONLY: u32 = 1 << 1;
+const TOKEN_MODIFIERS: &[&str] = &["declaration", "readonly"];
+
+/// The number of integers which encode a token
+const TOKEN_LENGTH: usize = 5;
+
+pub fn legend() -> SemanticTokensLegend {
+    let to_strings = |names: &[&str]| names.iter().map(|name| (*name).to_owned()).collect();
+    SemanticTokensLegend {
+        token_types: to_strings(TOKEN_TYPES),
+        token_modifiers: to_strings(TOKEN_MODIFIERS),
+    }
+}
+
+/// The token type and modifiers of a name denoting a named entity of the kind
+/// Labels are left to lexical highlighting
+fn classify(kind: NamedEntityKind) -> Option<(u32, u32)> {
+    use self::NamedEntityKind as K;
+    let (token_type, modifiers) = match kind {
+        K::Architecture | K::Configuration | K::Package | K::PackageInstance | K::Context => {
+            ("namespace", 0)
+        }
+        K::Entity | K::Component => ("class", 0),
+        K::Type | K::Subtype => ("type", 0),
+        K::Generic => ("parameter", READONLY),
+        K::Port | K::Parameter => ("parameter", 0),
+        K::Constant | K::LoopParameter => ("variable", READONLY),
+        K::Signal | K::Variable | K::SharedVariable | K::File | K::Alias => ("variable", 0),
+        K::Attribute => ("property", 0),
+        K::PhysicalUnit | K::EnumerationLiteral => ("enumMember", 0),
+        K::Function | K::Procedure => ("function", 0),
+        K::Label => {
+            return None;
+        }
+    };
+    let index = TOKEN_TYPES.iter().position(|name| *name == token_type)?;
+    Some((index as u32, modifiers))
+}
+
+/// The line and character of byte offsets of a source
+struct Lines {
+    starts: Vec<usize>,
+}
+
+impl Lines {
+    fn new(bytes: &[u8]) -> Lines {
+        let mut starts = vec![0];
+        starts.extend(
+            bytes
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        Lines { starts }
+    }
+
+    fn position(&self, offset: usize) -> (u32, u32) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        (line as u32, (offset - self.starts[line]) as u32)
+    }
+}
+
+/// The relative encoding of the declarations and references within the source
+pub fn encode(resolution: &NameResolution, source: &Source) -> Vec<u32> {
+    let (file_name, contents) = match (source.file_name(), source.contents().ok()) {
+        (Some(file_name), Some(contents)) => (file_name, contents),
+        _ => {
+            return Vec::new();
+        }
+    };
+    let in_file = |pos: &SrcPos| pos.source.file_name() == Some(file_name);
+
+    let mut names: Vec<(&SrcPos, NamedEntityKind, u32)> = Vec::new();
+    for entity in resolution.declarations() {
+        if in_file(&entity.pos) {
+            names.push((&entity.pos, entity.kind, DECLARATION));
+        }
+    }
+    for reference in resolution.references() {
+        if let Some(entity) = reference.entities.first() {
+            if in_file(&reference.pos) {
+                names.push((&reference.pos, entity.kind, 0));
+            }
+        }
+    }
+    // A name which both declares and references is highlighted as a declaration
+    names.sort_by_key(|(pos, _, modifiers)| (pos.start, !*modifiers & DECLARATION));
+    names.dedup_by_key(|(pos, _, _)| pos.start);
+
+    let lines = Lines::new(&contents.bytes);
+    let mut data = Vec::with_capacity(names.len() * TOKEN_LENGTH);
+    let mut previous = (0, 0);
+    for (pos, kind, declaration) in names {
+        let (token_type, modifiers) = match classify(kind) {
+            Some(classification) => classification,
+            None => continue,
+        };
+        let (line, character) = lines.position(pos.start);
+        let delta_start = if line == previous.0 {
+            character - previous.1
+        } else {
+            character
+        };
+        data.extend_from_slice(&[
+            line - previous.0,
+            delta_start,
+            pos.length as u32,
+            token_type,
+            modifiers | declaration,
+        ]);
+        previous = (line, character);
+    }
+    data
+}
+
+/// The edits of the tokens which differ between the previous and the current encoding
+pub fn delta(previous: &[u32], data: &[u32]) -> Vec<SemanticTokensEdit> {
+    if previous == data {
+        return Vec::new();
+    }
+    let previous_tokens: Vec<&[u32]> = previous.chunks(TOKEN_LENGTH).collect();
+    let tokens: Vec<&[u32]> = data.chunks(TOKEN_LENGTH).collect();
+    let common = previous_tokens.len().min(tokens.len());
+    let prefix = (0..common)
+        .find(|i| previous_tokens[*i] != tokens[*i])
+        .unwrap_or(common);
+    let suffix = (0..common - prefix)
+        .find(|i| previous_tokens[previous_tokens.len() - 1 - i] != tokens[tokens.len() - 1 - i])
+        .unwrap_or(common - prefix);
+
+    let start = prefix * TOKEN_LENGTH;
+    vec![SemanticTokensEdit {
+        start: start as u32,
+        delete_count: (previous.len() - suffix * TOKEN_LENGTH - start) as u32,
+        data: data[start..data.len() - suffix * TOKEN_LENGTH].to_vec(),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_replaces_differing_tokens() {
+        let previous = vec![0, 1, 2, 3, 0, 1, 0, 2, 4, 0, 0, 5, 2, 3, 0];
+        let data = vec![0, 1, 2, 3, 0, 1, 0, 3, 4, 1, 1, 2, 1, 3, 0, 0, 5, 2, 3, 0];
+        assert_eq!(
+            delta(&previous, &data),
+            vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 5,
+                data: vec![1, 0, 3, 4, 1, 1, 2, 1, 3, 0],
+            }]
+        );
+        assert_eq!(
+            delta(&data, &previous),
+            vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 10,
+                data: vec![1, 0, 2, 4, 0],
+            }]
+        );
+        assert_eq!(delta(&data, &data), Vec::new());
+        assert_eq!(
+            delta(&[], &previous[..5]),
+            vec![SemanticTokensEdit {
+                start: 0,
+                delete_count: 0,
+                data: previous[..5].to_vec(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Editor settings of the vhdl_ls section and the workspace configuration
+//! of LSP 3.6 which is not part of languageserver-types
+
+use inlay_hints::InlayHintsConfig;
+use languageserver_types::{self as lsp, DiagnosticSeverity, FormattingOptions};
+use serde::de::{Deserialize, Deserializer, Error};
+use serde_json::Value;
+use std::collections::HashMap;
+use vhdl_parser::formatter::{Case, FormatStyle, Indentation};
+use vhdl_parser::message::Message;
+
+/// The section of the editor settings read by the server
+pub const SECTION: &str = "vhdl_ls";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfigurationParams {
+    pub items: Vec<ConfigurationItem>,
+}
+
+/// The initialize parameters together with the capabilities which languageserver-types lacks
+pub struct InitializeParams {
+    pub params: lsp::InitializeParams,
+    /// The client supports workspace/configuration requests
+    pub configuration: bool,
+    /// The client supports progress created by window/workDoneProgress/create requests
+    pub work_done_progress: bool,
+}
+
+impl<'de> Deserialize<'de> for InitializeParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<InitializeParams, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let capability = |pointer| {
+            value
+                .pointer(pointer)
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        };
+        let configuration = capability("/capabilities/workspace/configuration");
+        let work_done_progress = capability("/capabilities/window/workDoneProgress");
+        let params = lsp::InitializeParams::deserialize(value).map_err(D::Error::custom)?;
+        Ok(InitializeParams {
+            params,
+            configuration,
+            work_done_progress,
+        })
+    }
+}
+
+/// The severity of the messages with a code
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+    Ignore,
+}
+
+/// Overrides of the indentation given by the editor when formatting and the layout
+/// within lines which is kept unless enabled
+#[derive(Debug, PartialEq, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatterSettings {
+    pub indent_size: Option<usize>,
+    pub tabs: Option<bool>,
+    pub operator_spacing: bool,
+    pub comma_spacing: bool,
+    pub one_item_per_line: bool,
+    pub align_colons: bool,
+    pub align_arrows: bool,
+    pub align_assignments: bool,
+    pub keyword_case: Option<Case>,
+    pub standard_case: Option<Case>,
+    pub declared_spelling: bool,
+    pub align_comments: bool,
+    pub comment_width: Option<usize>,
+    pub max_width: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// The severity by message code
+    pub lint: HashMap<String, LintSeverity>,
+    pub formatter: FormatterSettings,
+    /// Libraries with the same layout as in the configuration file which
+    /// replace those of the same name
+    pub libraries: Option<Value>,
+    pub inlay_hints: Option<InlayHintsConfig>,
+    /// Only the interface of the design units is parsed when the project is loaded, the contents
+    /// of architectures and package bodies are parsed once a request may refer to them
+    pub defer_bodies: bool,
+}
+
+impl Settings {
+    /// The settings of the section where a missing section gives the defaults
+    pub fn from_section(section: Option<&Value>) -> Result<Settings, String> {
+        match section {
+            Some(Value::Null) | None => Ok(Settings::default()),
+            Some(section) => serde_json::from_value(section.clone())
+                .map_err(|err| format!("Invalid {} settings: {}", SECTION, err)),
+        }
+    }
+
+    /// The severity of the diagnostic of a message, None when it is ignored
+    pub fn severity(
+        &self,
+        message: &Message,
+        severity: DiagnosticSeverity,
+    ) -> Option<DiagnosticSeverity> {
+        let lint = match message.code.as_ref().and_then(|code| self.lint.get(code)) {
+            Some(lint) => lint,
+            None => {
+                return Some(severity);
+            }
+        };
+        match lint {
+            LintSeverity::Error => Some(DiagnosticSeverity::Error),
+            LintSeverity::Warning => Some(DiagnosticSeverity::Warning),
+            LintSeverity::Information => Some(DiagnosticSeverity::Information),
+            LintSeverity::Hint => Some(DiagnosticSeverity::Hint),
+            LintSeverity::Ignore => None,
+        }
+    }
+
+    /// The indentation of the editor unless overridden by the settings
+    pub fn indentation(&self, options: &FormattingOptions) -> Indentation {
+        let size = self
+            .formatter
+            .indent_size
+            .unwrap_or(options.tab_size as usize);
+        Indentation {
+            size: size.max(1),
+            tabs: self.formatter.tabs.unwrap_or(!options.insert_spaces),
+        }
+    }
+
+    /// The style of formatting a document or a range with the indentation of the editor
+    pub fn format_style(&self, options: &FormattingOptions) -> FormatStyle {
+        FormatStyle {
+            indentation: self.indentation(options),
+            operator_spacing: self.formatter.operator_spacing,
+            comma_spacing: self.formatter.comma_spacing,
+            one_item_per_line: self.formatter.one_item_per_line,
+            align_colons: self.formatter.align_colons,
+            align_arrows: self.formatter.align_arrows,
+            align_assignments: self.formatter.align_assignments,
+            keyword_case: self.formatter.keyword_case,
+            standard_case: self.formatter.standard_case,
+            declared_spelling: self.formatter.declared_spelling,
+            align_comments: self.formatter.align_comments,
+            comment_width: self.formatter.comment_width.filter(|width| *width > 0),
+            max_width: self.formatter.max_width.filter(|width| *width > 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_of_section() {
+        let section: Value = serde_json::from_str(
+            r#"{
+                "lint": {"undeclared": "hint", "incomplete-sensitivity-list": "ignore"},
+                "formatter": {
+                    "indentSize": 4,
+                    "operatorSpacing": true,
+                    "alignColons": true,
+                    "keywordCase": "upper",
+                    "commentWidth": 80,
+                    "maxWidth": 100
+                },
+                "inlayHints": {"types": false}
+            }"#,
+        )
+        .unwrap();
+        let settings = Settings::from_section(Some(&section)).unwrap();
+        assert_eq!(settings.lint["undeclared"], LintSeverity::Hint);
+        assert_eq!(
+            settings.lint["incomplete-sensitivity-list"],
+            LintSeverity::Ignore
+        );
+        assert_eq!(settings.formatter.indent_size, Some(4));
+        assert_eq!(settings.formatter.tabs, None);
+        assert!(settings.formatter.operator_spacing);
+        assert!(!settings.formatter.comma_spacing);
+        assert!(settings.formatter.align_colons);
+        assert_eq!(settings.formatter.keyword_case, Some(Case::Upper));
+        assert_eq!(settings.formatter.comment_width, Some(80));
+        assert_eq!(settings.formatter.max_width, Some(100));
+        assert_eq!(settings.libraries, None);
+        assert!(!settings.inlay_hints.unwrap().types);
+
+        assert_eq!(Settings::from_section(None), Ok(Settings::default()));
+        assert!(Settings::from_section(Some(
+            &serde_json::from_str(r#"{"lint": {"undeclared": "fatal"}}"#).unwrap()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn initialize_params_with_newer_capabilities() {
+        let params: InitializeParams = serde_json::from_str(
+            r#"{
+                "processId": null,
+                "rootUri": null,
+                "capabilities": {"workspace": {"configuration": true}}
+            }"#,
+        )
+        .unwrap();
+        assert!(params.configuration);
+        assert!(!params.work_done_progress);
+
+        let params: InitializeParams =
+            serde_json::from_str(r#"{"processId": null, "rootUri": null, "capabilities": {}}"#)
+                .unwrap();
+        assert!(!params.configuration);
+
+        let params: InitializeParams = serde_json::from_str(
+            r#"{
+                "processId": null,
+                "rootUri": null,
+                "capabilities": {"window": {"workDoneProgress": true}}
+            }"#,
+        )
+        .unwrap();
+        assert!(params.work_done_progress);
+    }
+}
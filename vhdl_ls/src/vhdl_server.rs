@@ -0,0 +1,3485 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use call_hierarchy::{
+    CallHierarchyCallsParams, CallHierarchyIncomingCall, CallHierarchyItem,
+    CallHierarchyOutgoingCall,
+};
+use inlay_hints::{self, InlayHint, InlayHintParams, InlayHintsConfig};
+use jsonrpc_core::{Id, Output};
+use languageserver_types::*;
+use progress::{self, ProgressParams, WorkDoneProgress, WorkDoneProgressCreateParams};
+use rpc_channel::RpcChannel;
+use semantic_tokens::{
+    self, SemanticTokens, SemanticTokensDelta, SemanticTokensDeltaParams,
+    SemanticTokensDeltaResult, SemanticTokensFullOptions, SemanticTokensOptions,
+    SemanticTokensParams,
+};
+use serde_json::{self, Value};
+use settings::{self, ConfigurationItem, ConfigurationParams, Settings};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::thread;
+use type_hierarchy::{TypeHierarchyItem, TypeHierarchyParams};
+use url::Url;
+
+use vhdl_parser::call_hierarchy::{calls, incoming_calls, is_callable, outgoing_calls};
+use vhdl_parser::config::Config;
+use vhdl_parser::documentation::{declaration_text, DeclarationText};
+use vhdl_parser::format_config::FormatConfig;
+use vhdl_parser::formatter::{self, FormatStyle};
+use vhdl_parser::inlay_hints::{inlay_hints as hints_of, InlayHintKind};
+use vhdl_parser::message::{Message, Severity};
+use vhdl_parser::name_resolution::{NameResolution, NamedEntity, NamedEntityKind, TypeRelation};
+use vhdl_parser::outline::{outline, OutlineItem, OutlineKind};
+use vhdl_parser::project::{Project, ProjectSnapshot};
+use vhdl_parser::rename::{can_rename, rename_positions};
+use vhdl_parser::signature::{signatures_at, Signatures};
+use vhdl_parser::source::{Source, SrcPos};
+use vhdl_parser::vunit::{testbenches, Testbench};
+
+/// The project configuration file within the workspace root
+pub const CONFIG_FILE_NAME: &str = "vhdl_ls.toml";
+
+/// The library of opened files which are not part of the configuration
+const DEFAULT_LIBRARY: &str = "work";
+
+/// The client command which shows a list of locations
+const SHOW_REFERENCES: &str = "editor.action.showReferences";
+
+/// The server command which runs a testbench with the configured testbench command
+const RUN_TESTBENCH: &str = "vhdl_ls.runTestbench";
+
+/// The progress of parsing and analyzing the project when the server starts
+const INDEXING_TOKEN: &str = "vhdl_ls/indexing";
+
+pub fn srcpos_to_range(srcpos: &SrcPos) -> Range {
+    let contents = match srcpos.source.contents() {
+        Ok(contents) => contents,
+        Err(_) => {
+            return Range::default();
+        }
+    };
+    let mut start = None;
+    let mut end = None;
+
+    let mut cursor = Position {
+        line: 0,
+        character: 0,
+    };
+    for (i, byte) in contents.bytes.iter().enumerate() {
+        if i == srcpos.start {
+            start = Some(cursor);
+        }
+
+        if i == srcpos.start + srcpos.length {
+            end = Some(cursor);
+        }
+
+        if *byte == b'\n' {
+            cursor.line += 1;
+            cursor.character = 0;
+        } else {
+            cursor.character += 1;
+        };
+    }
+
+    Range {
+        start: start.unwrap_or(cursor),
+        end: end.unwrap_or(cursor),
+    }
+}
+
+/// The character offset of a position, positions beyond the end of a line are clamped
+pub fn position_to_offset(bytes: &[u8], position: Position) -> usize {
+    let mut line = 0;
+    let mut character = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if line == position.line && (character == position.character || *byte == b'\n') {
+            return i;
+        }
+        if *byte == b'\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// The byte index of a position within text where a character may be several bytes
+fn position_to_index(text: &str, position: Position) -> usize {
+    let mut line = 0;
+    let mut character = 0;
+    for (i, chr) in text.char_indices() {
+        if line == position.line && (character == position.character || chr == '\n') {
+            return i;
+        }
+        if chr == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += 1;
+        }
+    }
+    text.len()
+}
+
+/// Replaces the range of a change or the whole text when the change has no range
+pub fn apply_change(text: &mut String, change: &TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start = position_to_index(text, range.start);
+            let end = position_to_index(text, range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => {
+            *text = change.text.clone();
+        }
+    }
+}
+
+fn srcpos_to_location(srcpos: &SrcPos) -> Option<Location> {
+    let uri = file_name_to_uri(srcpos.source.file_name()?)?;
+    Some(Location {
+        uri,
+        range: srcpos_to_range(srcpos),
+    })
+}
+
+/// Related information is only included when supported by the client
+pub fn to_diagnostic(message: &Message, related_information: bool) -> Diagnostic {
+    let severity = match message.severity {
+        Severity::Error => DiagnosticSeverity::Error,
+        Severity::Warning => DiagnosticSeverity::Warning,
+        Severity::Hint => DiagnosticSeverity::Hint,
+    };
+    let related_information = if related_information && !message.related.is_empty() {
+        Some(
+            message
+                .related
+                .iter()
+                .filter_map(|(pos, related_message)| {
+                    Some(DiagnosticRelatedInformation {
+                        location: srcpos_to_location(pos)?,
+                        message: related_message.clone(),
+                    })
+                })
+                .collect(),
+        )
+    } else {
+        None
+    };
+    Diagnostic {
+        range: srcpos_to_range(&message.pos),
+        severity: Some(severity),
+        code: message.code.clone().map(NumberOrString::String),
+        source: Some("vhdl ls".to_owned()),
+        message: message.message.clone(),
+        related_information,
+    }
+}
+
+/// A code block with the declaration followed by its comment
+fn to_markdown(declaration: &DeclarationText) -> String {
+    let mut markdown = format!("```vhdl\n{}\n```", declaration.text);
+    if let Some(ref comment) = declaration.comment {
+        markdown.push_str("\n\n");
+        markdown.push_str(comment);
+    }
+    markdown
+}
+
+fn to_signature_help(signatures: Signatures) -> SignatureHelp {
+    SignatureHelp {
+        signatures: signatures
+            .signatures
+            .into_iter()
+            .map(|signature| SignatureInformation {
+                label: signature.label,
+                documentation: signature.documentation.map(Documentation::String),
+                parameters: Some(
+                    signature
+                        .parameters
+                        .into_iter()
+                        .map(|parameter| ParameterInformation {
+                            label: ParameterLabel::Simple(parameter.label),
+                            documentation: parameter.documentation.map(Documentation::String),
+                        })
+                        .collect(),
+                ),
+            })
+            .collect(),
+        active_signature: Some(signatures.active_signature as u64),
+        active_parameter: Some(signatures.active_parameter as u64),
+    }
+}
+
+fn symbol_kind(kind: OutlineKind) -> SymbolKind {
+    use self::NamedEntityKind as K;
+    match kind {
+        OutlineKind::Declaration(kind) => match kind {
+            K::Entity | K::Component => SymbolKind::Interface,
+            K::Architecture => SymbolKind::Class,
+            K::Configuration => SymbolKind::Module,
+            K::Package | K::PackageInstance => SymbolKind::Package,
+            K::Context => SymbolKind::Namespace,
+            K::Signal | K::Variable | K::SharedVariable | K::Parameter | K::LoopParameter => {
+                SymbolKind::Variable
+            }
+            K::Constant | K::Generic => SymbolKind::Constant,
+            K::File => SymbolKind::File,
+            K::Type | K::Subtype => SymbolKind::TypeParameter,
+            K::Attribute => SymbolKind::Property,
+            K::Alias => SymbolKind::Key,
+            K::Procedure | K::Function => SymbolKind::Function,
+            K::Port => SymbolKind::Field,
+            K::PhysicalUnit | K::EnumerationLiteral => SymbolKind::EnumMember,
+            K::Label => SymbolKind::Key,
+        },
+        OutlineKind::PackageBody => SymbolKind::Package,
+        OutlineKind::Process => SymbolKind::Method,
+        OutlineKind::Block | OutlineKind::Generate => SymbolKind::Namespace,
+        OutlineKind::Instance => SymbolKind::Object,
+    }
+}
+
+fn to_call_hierarchy_item(entity: &NamedEntity) -> Option<CallHierarchyItem> {
+    let location = srcpos_to_location(&entity.pos)?;
+    Some(CallHierarchyItem {
+        name: entity.designator.clone(),
+        kind: symbol_kind(OutlineKind::Declaration(entity.kind)),
+        detail: None,
+        uri: location.uri,
+        range: location.range,
+        selection_range: location.range,
+    })
+}
+
+/// The detail of a supertype describes what it is to the type of the request
+fn supertype_detail(relation: TypeRelation) -> &'static str {
+    match relation {
+        TypeRelation::Subtype => "type mark",
+        TypeRelation::Resolved => "unresolved type",
+        TypeRelation::Element => "element type",
+    }
+}
+
+/// The detail of a subtype describes what it is to the type of the request
+fn subtype_detail(relation: TypeRelation) -> &'static str {
+    match relation {
+        TypeRelation::Subtype => "subtype",
+        TypeRelation::Resolved => "resolved subtype",
+        TypeRelation::Element => "composite type",
+    }
+}
+
+fn to_type_hierarchy_item(entity: &NamedEntity, detail: Option<&str>) -> Option<TypeHierarchyItem> {
+    let location = srcpos_to_location(&entity.pos)?;
+    Some(TypeHierarchyItem {
+        name: entity.designator.clone(),
+        kind: symbol_kind(OutlineKind::Declaration(entity.kind)),
+        detail: detail.map(str::to_owned),
+        uri: location.uri,
+        range: location.range,
+        selection_range: location.range,
+    })
+}
+
+fn is_type(entity: &NamedEntity) -> bool {
+    entity.kind == NamedEntityKind::Type || entity.kind == NamedEntityKind::Subtype
+}
+
+fn to_document_symbol(item: &OutlineItem) -> DocumentSymbol {
+    DocumentSymbol {
+        name: item.name.clone(),
+        detail: None,
+        kind: symbol_kind(item.kind),
+        deprecated: None,
+        range: srcpos_to_range(&item.range),
+        selection_range: srcpos_to_range(&item.pos),
+        children: if item.children.is_empty() {
+            None
+        } else {
+            Some(item.children.iter().map(to_document_symbol).collect())
+        },
+    }
+}
+
+/// Flattens the outline for clients without support for hierarchical document symbols
+fn to_symbol_information(
+    items: &[OutlineItem],
+    container_name: Option<&str>,
+    symbols: &mut Vec<SymbolInformation>,
+) {
+    for item in items {
+        if let Some(location) = srcpos_to_location(&item.range) {
+            symbols.push(SymbolInformation {
+                name: item.name.clone(),
+                kind: symbol_kind(item.kind),
+                deprecated: None,
+                location,
+                container_name: container_name.map(str::to_owned),
+            });
+        }
+        to_symbol_information(&item.children, Some(&item.name), symbols);
+    }
+}
+
+fn uri_to_file_name(uri: &Url) -> Option<String> {
+    uri.to_file_path()
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+fn to_text_edits(edits: Vec<(SrcPos, String)>) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .map(|(pos, new_text)| TextEdit {
+            range: srcpos_to_range(&pos),
+            new_text,
+        })
+        .collect()
+}
+
+/// The testbench command with the placeholders replaced by those of the testbench
+fn testbench_command(template: &str, testbench: &Testbench) -> String {
+    template
+        .replace("{library}", &testbench.library_name)
+        .replace("{entity}", &testbench.entity_name)
+        .replace("{file}", &testbench.file_name)
+}
+
+/// Runs the command by the shell without waiting for it to finish
+/// where its output goes to the log of the server on stderr
+fn spawn_shell_command(command: &str, directory: Option<&Path>) -> io::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(io::stderr()))
+        .stderr(Stdio::from(io::stderr()));
+    if let Some(directory) = directory {
+        shell.current_dir(directory);
+    }
+    let mut child = shell.spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn send_progress<T: RpcChannel>(rpc_channel: &T, value: WorkDoneProgress) {
+    let params = ProgressParams {
+        token: INDEXING_TOKEN.to_owned(),
+        value,
+    };
+    rpc_channel.send_notification("$/progress", serde_json::to_value(params).unwrap());
+}
+
+/// The messages within files grouped by file name
+fn group_by_file(messages: Vec<Message>) -> BTreeMap<String, Vec<Message>> {
+    let mut messages_by_file = BTreeMap::new();
+    for message in messages {
+        if let Some(file_name) = message.pos.source.file_name().map(str::to_owned) {
+            messages_by_file
+                .entry(file_name)
+                .or_insert_with(Vec::new)
+                .push(message);
+        }
+    }
+    messages_by_file
+}
+
+fn file_name_to_uri(file_name: &str) -> Option<Url> {
+    Url::from_file_path(file_name).ok()
+}
+
+/// Syntax, semantic and name resolution messages of all files grouped by file name
+fn snapshot_messages(
+    snapshot: &ProjectSnapshot,
+    resolution: &NameResolution,
+) -> BTreeMap<String, Vec<Message>> {
+    let mut messages = snapshot.messages();
+    messages.extend(snapshot.analyze());
+    messages.extend(resolution.messages().iter().cloned());
+    group_by_file(messages)
+}
+
+/// The analysis of a snapshot of the project which is run by another thread such
+/// that the server answers requests while the project is analyzed
+pub struct BackgroundAnalysis {
+    revision: u64,
+    snapshot: ProjectSnapshot,
+}
+
+/// The messages and name resolution of the project at the revision of the analysis
+pub struct AnalysisResult {
+    revision: u64,
+    messages_by_file: BTreeMap<String, Vec<Message>>,
+    resolution: NameResolution,
+}
+
+impl BackgroundAnalysis {
+    pub fn run(self) -> AnalysisResult {
+        let resolution = self.snapshot.resolve();
+        AnalysisResult {
+            revision: self.revision,
+            messages_by_file: snapshot_messages(&self.snapshot, &resolution),
+            resolution,
+        }
+    }
+}
+
+/// The state of the language server, independent of how messages are transported
+pub struct VHDLServer<T: RpcChannel> {
+    rpc_channel: T,
+    project: Project,
+    shutdown_requested: bool,
+    related_information: bool,
+    hierarchical_symbols: bool,
+    inlay_hints: InlayHintsConfig,
+    root_path: Option<PathBuf>,
+    /// The configuration file of the workspace root
+    file_config: Config,
+    testbench_command: Option<String>,
+    /// The client supports workspace/configuration requests
+    configuration_requests: bool,
+    settings: Settings,
+    /// The id of the configuration request awaiting its response
+    configuration_request: Option<u64>,
+    /// The client supports progress created by the server
+    work_done_progress: bool,
+    /// The id of the progress creation request after which the project is loaded
+    progress_request: Option<u64>,
+    next_request_id: u64,
+    /// Files which currently have diagnostics in the client
+    files_with_diagnostics: BTreeSet<String>,
+    /// The diagnostics of files other than the last edited file and its dependents are
+    /// outdated until they are published by background work
+    outdated_diagnostics: bool,
+    /// Incremented whenever the project changes such that the results of background
+    /// analysis of a previous revision are discarded
+    revision: u64,
+    analysis_running: bool,
+    /// Computed on demand and cleared whenever a file changes
+    resolution: Option<NameResolution>,
+    /// The text of each open document with all changes applied
+    documents: HashMap<Url, String>,
+    /// The semantic tokens last sent for each document to compute deltas from
+    semantic_tokens: HashMap<Url, SemanticTokens>,
+    semantic_tokens_results: u64,
+}
+
+/// The server capabilities extended with those which languageserver-types does not support
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    #[serde(flatten)]
+    pub server: ServerCapabilities,
+    pub semantic_tokens_provider: SemanticTokensOptions,
+    pub call_hierarchy_provider: bool,
+    pub type_hierarchy_provider: bool,
+    pub inlay_hint_provider: bool,
+}
+
+#[derive(Serialize)]
+pub struct InitializeResponse {
+    pub capabilities: Capabilities,
+}
+
+impl<T: RpcChannel> VHDLServer<T> {
+    pub fn new(rpc_channel: T) -> VHDLServer<T> {
+        VHDLServer {
+            rpc_channel,
+            project: Project::new(),
+            shutdown_requested: false,
+            related_information: false,
+            hierarchical_symbols: false,
+            inlay_hints: InlayHintsConfig::default(),
+            root_path: None,
+            file_config: Config::default(),
+            testbench_command: None,
+            configuration_requests: false,
+            settings: Settings::default(),
+            configuration_request: None,
+            work_done_progress: false,
+            progress_request: None,
+            next_request_id: 0,
+            files_with_diagnostics: BTreeSet::new(),
+            outdated_diagnostics: false,
+            revision: 0,
+            analysis_running: false,
+            resolution: None,
+            documents: HashMap::new(),
+            semantic_tokens: HashMap::new(),
+            semantic_tokens_results: 0,
+        }
+    }
+
+    fn show_message(&self, typ: MessageType, message: String) {
+        let params = ShowMessageParams { typ, message };
+        self.rpc_channel
+            .send_notification("window/showMessage", serde_json::to_value(params).unwrap());
+    }
+
+    /// Read the project configuration of the workspace root if there is one
+    fn load_config(&mut self, root_path: &Path) {
+        let config_file = root_path.join(CONFIG_FILE_NAME);
+        if !config_file.exists() {
+            return;
+        }
+
+        match Config::read_file_path(&config_file) {
+            Ok(config) => {
+                self.file_config = config;
+            }
+            Err(err) => {
+                self.show_message(MessageType::Error, err.to_string());
+            }
+        }
+    }
+
+    /// The configuration file where the libraries of the settings replace those of the same name
+    fn config(&self) -> Result<Config, String> {
+        let mut config = self.file_config.clone();
+        if let Some(ref libraries) = self.settings.libraries {
+            let mut table = serde_json::Map::new();
+            table.insert("libraries".to_owned(), libraries.clone());
+            let value: toml::Value = serde_json::from_value(Value::Object(table))
+                .map_err(|err| format!("Invalid libraries setting: {}", err))?;
+            let parent = self.root_path.clone().unwrap_or_default();
+            config.merge(
+                Config::from_value(&value, &parent)
+                    .map_err(|err| format!("Invalid libraries setting: {}", err))?,
+            );
+        }
+        Ok(config)
+    }
+
+    /// The progress of the files is reported when indexing progress has been created
+    fn add_config(&mut self, progress: bool) {
+        let config = match self.config() {
+            Ok(config) => config,
+            Err(err) => {
+                self.show_message(MessageType::Error, err);
+                self.file_config.clone()
+            }
+        };
+        self.testbench_command = config.testbench_command().map(str::to_owned);
+
+        let rpc_channel = &self.rpc_channel;
+        let mut reported = None;
+        let mut report = |done, total| {
+            let percentage = progress::percentage(done, total);
+            if !progress || reported == Some(percentage) {
+                return;
+            }
+            let message = Some(format!("{}/{} files", done, total));
+            send_progress(
+                rpc_channel,
+                if reported.is_none() {
+                    WorkDoneProgress::Begin {
+                        title: "Indexing".to_owned(),
+                        message,
+                        percentage,
+                    }
+                } else {
+                    WorkDoneProgress::Report {
+                        message,
+                        percentage,
+                    }
+                },
+            );
+            reported = Some(percentage);
+        };
+        self.project.set_defer_bodies(self.settings.defer_bodies);
+        let errors = self.project.add_config_with_progress(&config, &mut report);
+        for (file_name, err) in errors {
+            self.show_message(
+                MessageType::Warning,
+                format!("Could not add {}: {}", file_name, err),
+            );
+        }
+    }
+
+    /// Recreates the project from the configuration where open documents keep their text
+    fn reload_project(&mut self, progress: bool) {
+        self.project = Project::new();
+        self.add_config(progress);
+        let documents: Vec<(Url, String)> = self
+            .documents
+            .iter()
+            .map(|(uri, text)| (uri.clone(), text.clone()))
+            .collect();
+        for (uri, text) in documents {
+            if let Some(source) = self.document_source(&uri, &text) {
+                self.add_source(source);
+            }
+        }
+    }
+
+    /// Applies the settings of the vhdl_ls section, the project is only
+    /// reloaded when the libraries have changed
+    fn apply_settings(&mut self, section: Option<&Value>) {
+        let settings = match Settings::from_section(section) {
+            Ok(settings) => settings,
+            Err(err) => {
+                self.show_message(MessageType::Error, err);
+                return;
+            }
+        };
+        let reload = settings.libraries != self.settings.libraries;
+        self.settings = settings;
+        if let Some(inlay_hints) = self.settings.inlay_hints.take() {
+            self.inlay_hints = inlay_hints;
+        }
+        // A pending initial load uses the new settings
+        if reload && self.progress_request.is_none() {
+            self.reload_project(false);
+        }
+        if !self.settings.defer_bodies {
+            self.project.parse_deferred_bodies();
+        }
+        self.publish_diagnostics();
+    }
+
+    /// Returns the id which identifies the response
+    fn send_request(&mut self, method: &str, params: Value) -> u64 {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.rpc_channel.send_request(id, method, params);
+        id
+    }
+
+    /// Requests the settings which are applied when the client responds
+    fn request_configuration(&mut self) {
+        let params = ConfigurationParams {
+            items: vec![ConfigurationItem {
+                section: Some(settings::SECTION.to_owned()),
+            }],
+        };
+        let id = self.send_request(
+            "workspace/configuration",
+            serde_json::to_value(params).unwrap(),
+        );
+        self.configuration_request = Some(id);
+    }
+
+    /// Parses and analyzes the project where the progress is reported when it could be created
+    fn load_project(&mut self, progress: bool) {
+        self.reload_project(progress);
+        if progress {
+            send_progress(
+                &self.rpc_channel,
+                WorkDoneProgress::Report {
+                    message: Some("Analyzing".to_owned()),
+                    percentage: 100,
+                },
+            );
+        }
+        self.publish_diagnostics();
+        if progress {
+            send_progress(&self.rpc_channel, WorkDoneProgress::End { message: None });
+        }
+    }
+
+    /// Settings are pulled when the client supports it, otherwise they are part of the notification
+    pub fn workspace_did_change_configuration_notification(
+        &mut self,
+        params: DidChangeConfigurationParams,
+    ) {
+        match params.settings.get(settings::SECTION) {
+            Some(section) => self.apply_settings(Some(section)),
+            None if self.configuration_requests => self.request_configuration(),
+            None => {}
+        }
+    }
+
+    /// Handles the response of the client to a request of the server
+    pub fn client_response(&mut self, output: Output) {
+        let id = match *output.id() {
+            Id::Num(id) => Some(id),
+            _ => None,
+        };
+        if id.is_some() && self.progress_request == id {
+            self.progress_request = None;
+            self.load_project(matches!(output, Output::Success(..)));
+            return;
+        }
+
+        match output {
+            Output::Success(success) => {
+                if id.is_some() && self.configuration_request == id {
+                    self.configuration_request = None;
+                    // One result for each requested item
+                    let section = success.result.get(0).cloned();
+                    self.apply_settings(section.as_ref());
+                }
+            }
+            Output::Failure(failure) => {
+                eprintln!("Request {:?} failed: {}", failure.id, failure.error.message);
+            }
+        }
+    }
+
+    pub fn initialize_request(&mut self, params: settings::InitializeParams) -> InitializeResponse {
+        self.configuration_requests = params.configuration;
+        self.work_done_progress = params.work_done_progress;
+        let InitializeParams {
+            root_uri,
+            root_path,
+            capabilities,
+            initialization_options,
+            ..
+        } = params.params;
+        self.inlay_hints =
+            InlayHintsConfig::from_initialization_options(initialization_options.as_ref());
+        let text_document = capabilities.text_document.as_ref();
+        self.related_information = text_document
+            .and_then(|text_document| text_document.publish_diagnostics.as_ref())
+            .and_then(|publish_diagnostics| publish_diagnostics.related_information)
+            .unwrap_or(false);
+        let prepare_rename = text_document
+            .and_then(|text_document| text_document.rename.as_ref())
+            .and_then(|rename| rename.prepare_support)
+            .unwrap_or(false);
+        self.hierarchical_symbols = text_document
+            .and_then(|text_document| text_document.document_symbol.as_ref())
+            .and_then(|document_symbol| document_symbol.hierarchical_document_symbol_support)
+            .unwrap_or(false);
+        let root_path = root_uri
+            .and_then(|uri| uri.to_file_path().ok())
+            .or_else(|| root_path.map(PathBuf::from));
+        if let Some(ref root_path) = root_path {
+            self.load_config(root_path);
+        }
+        self.root_path = root_path;
+        // Otherwise the project is loaded once the indexing progress has been created
+        if !self.work_done_progress {
+            self.add_config(false);
+        }
+
+        let text_document_sync = TextDocumentSyncOptions {
+            open_close: Some(true),
+            change: Some(TextDocumentSyncKind::Incremental),
+            will_save: None,
+            will_save_wait_until: None,
+            save: Some(SaveOptions {
+                include_text: Some(false),
+            }),
+        };
+
+        InitializeResponse {
+            capabilities: Capabilities {
+                server: ServerCapabilities {
+                    text_document_sync: Some(TextDocumentSyncCapability::Options(
+                        text_document_sync,
+                    )),
+                    definition_provider: Some(true),
+                    implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
+                    references_provider: Some(true),
+                    document_highlight_provider: Some(true),
+                    hover_provider: Some(true),
+                    document_symbol_provider: Some(true),
+                    code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                    code_lens_provider: Some(CodeLensOptions {
+                        resolve_provider: Some(false),
+                    }),
+                    execute_command_provider: Some(ExecuteCommandOptions {
+                        commands: vec![RUN_TESTBENCH.to_owned()],
+                    }),
+                    document_formatting_provider: Some(true),
+                    document_range_formatting_provider: Some(true),
+                    document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                        first_trigger_character: ";".to_owned(),
+                        more_trigger_character: Some(vec!["\n".to_owned()]),
+                    }),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec!["(".to_owned(), ",".to_owned()]),
+                    }),
+                    rename_provider: Some(if prepare_rename {
+                        RenameProviderCapability::Options(RenameOptions {
+                            prepare_provider: Some(true),
+                        })
+                    } else {
+                        RenameProviderCapability::Simple(true)
+                    }),
+                    ..ServerCapabilities::default()
+                },
+                semantic_tokens_provider: SemanticTokensOptions {
+                    legend: semantic_tokens::legend(),
+                    full: SemanticTokensFullOptions { delta: true },
+                },
+                call_hierarchy_provider: true,
+                type_hierarchy_provider: true,
+                inlay_hint_provider: true,
+            },
+        }
+    }
+
+    /// Diagnostics of the configured project are published once the client is initialized
+    /// The settings are requested as well when the client supports it
+    pub fn initialized_notification(&mut self) {
+        if self.configuration_requests {
+            self.request_configuration();
+        }
+        if self.work_done_progress {
+            let params = WorkDoneProgressCreateParams {
+                token: INDEXING_TOKEN.to_owned(),
+            };
+            let id = self.send_request(
+                "window/workDoneProgress/create",
+                serde_json::to_value(params).unwrap(),
+            );
+            self.progress_request = Some(id);
+        } else {
+            self.publish_diagnostics();
+        }
+    }
+
+    pub fn shutdown_request(&mut self) {
+        self.shutdown_requested = true;
+    }
+
+    /// The exit code of the process, a client must request shutdown before exit
+    pub fn exit_notification(&self) -> i32 {
+        if self.shutdown_requested {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Files keep their library from the configuration
+    fn library_name(&self, file_name: &str) -> String {
+        self.project
+            .get_file(file_name)
+            .map(|file| file.library_name.clone())
+            .unwrap_or_else(|| DEFAULT_LIBRARY.to_owned())
+    }
+
+    /// Returns false when the source could not be added to the project
+    fn add_source(&mut self, source: Source) -> bool {
+        let file_name = match source.file_name() {
+            Some(file_name) => file_name.to_owned(),
+            None => {
+                return false;
+            }
+        };
+        let library_name = self.library_name(&file_name);
+        if let Err(err) = self.project.update_source(&library_name, source) {
+            self.show_message(
+                MessageType::Error,
+                format!("Could not read {}: {}", file_name, err),
+            );
+            return false;
+        }
+        true
+    }
+
+    fn update_document(&mut self, source: Source) {
+        let file_name = match source.file_name() {
+            Some(file_name) => file_name.to_owned(),
+            None => {
+                return;
+            }
+        };
+        // Files which depended on the previous contents are affected as well
+        let mut file_names = self.project.dependent_files(&file_name);
+        if self.add_source(source) {
+            file_names.extend(self.project.dependent_files(&file_name));
+            self.publish_edit_diagnostics(file_names);
+        }
+    }
+
+    fn document_source(&self, uri: &Url, text: &str) -> Option<Source> {
+        let file_name = uri_to_file_name(uri)?;
+        match Source::from_file_contents(&file_name, text) {
+            Ok(source) => Some(source),
+            Err(err) => {
+                self.show_message(
+                    MessageType::Error,
+                    format!("Could not read {}: {}", file_name, err),
+                );
+                None
+            }
+        }
+    }
+
+    fn update_document_text(&mut self, uri: &Url, text: &str) {
+        if let Some(source) = self.document_source(uri, text) {
+            self.update_document(source);
+        }
+    }
+
+    /// Syntax, semantic and name resolution messages of all files grouped by file name
+    fn messages_by_file(&mut self) -> BTreeMap<String, Vec<Message>> {
+        let snapshot = self.project.snapshot();
+        snapshot_messages(&snapshot, self.resolution())
+    }
+
+    /// The diagnostics of the messages which are not ignored by the settings
+    fn to_diagnostics(&self, messages: &[Message]) -> Vec<Diagnostic> {
+        messages
+            .iter()
+            .filter_map(|message| {
+                let mut diagnostic = to_diagnostic(message, self.related_information);
+                diagnostic.severity = self.settings.severity(message, diagnostic.severity?);
+                diagnostic.severity.map(|_| diagnostic)
+            })
+            .collect()
+    }
+
+    fn send_diagnostics(&self, file_name: &str, diagnostics: Vec<Diagnostic>) {
+        let uri = match file_name_to_uri(file_name) {
+            Some(uri) => uri,
+            None => {
+                return;
+            }
+        };
+        let params = PublishDiagnosticsParams { uri, diagnostics };
+        self.rpc_channel.send_notification(
+            "textDocument/publishDiagnostics",
+            serde_json::to_value(params).unwrap(),
+        );
+    }
+
+    /// The name resolution and the diagnostics of the project are outdated
+    fn project_changed(&mut self) {
+        self.resolution = None;
+        self.revision += 1;
+    }
+
+    fn analysis(&self) -> BackgroundAnalysis {
+        BackgroundAnalysis {
+            revision: self.revision,
+            snapshot: self.project.snapshot(),
+        }
+    }
+
+    /// A change of one file may affect the diagnostics of any file so all are published
+    fn publish_diagnostics(&mut self) {
+        self.project_changed();
+        let result = self.analysis().run();
+        self.finish_background_analysis(result);
+    }
+
+    /// Publish the diagnostics of the analysis unless the project changed since it started
+    /// Files which no longer have any messages get an empty list to clear their diagnostics
+    pub fn finish_background_analysis(&mut self, result: AnalysisResult) {
+        self.analysis_running = false;
+        if result.revision != self.revision {
+            return;
+        }
+        self.outdated_diagnostics = false;
+        if self.resolution.is_none() {
+            self.resolution = Some(result.resolution);
+        }
+        let messages_by_file = result.messages_by_file;
+
+        for file_name in self.files_with_diagnostics.iter() {
+            if !messages_by_file.contains_key(file_name) {
+                self.send_diagnostics(file_name, Vec::new());
+            }
+        }
+
+        for (file_name, messages) in messages_by_file.iter() {
+            self.send_diagnostics(file_name, self.to_diagnostics(messages));
+        }
+
+        self.files_with_diagnostics = messages_by_file.into_keys().collect();
+    }
+
+    /// The diagnostics of an edited file and the files which directly depend on it are
+    /// published at once with only the design units they depend on resolved, those of
+    /// the rest of the project are published by background work
+    fn publish_edit_diagnostics(&mut self, file_names: BTreeSet<String>) {
+        self.project_changed();
+        self.outdated_diagnostics = true;
+
+        let mut messages = self.project.messages();
+        messages.extend(self.project.analyze());
+        let resolution = self.project.resolve_files(&file_names);
+        messages.extend(resolution.messages().iter().cloned());
+        let mut messages_by_file = group_by_file(messages);
+
+        for file_name in file_names {
+            match messages_by_file.remove(&file_name) {
+                Some(messages) => {
+                    self.send_diagnostics(&file_name, self.to_diagnostics(&messages));
+                    self.files_with_diagnostics.insert(file_name);
+                }
+                None => {
+                    if self.files_with_diagnostics.remove(&file_name) {
+                        self.send_diagnostics(&file_name, Vec::new());
+                    }
+                }
+            }
+        }
+    }
+
+    /// The analysis to run while there are no messages from the client, returns None
+    /// when the diagnostics are up to date or an analysis is already running
+    pub fn background_analysis(&mut self) -> Option<BackgroundAnalysis> {
+        if self.outdated_diagnostics && !self.analysis_running {
+            self.analysis_running = true;
+            Some(self.analysis())
+        } else {
+            None
+        }
+    }
+
+    /// Run the background analysis on the calling thread,
+    /// returns false when there is nothing left to do
+    #[cfg(test)]
+    pub fn background_work(&mut self) -> bool {
+        match self.background_analysis() {
+            Some(analysis) => {
+                let result = analysis.run();
+                self.finish_background_analysis(result);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn text_document_did_open_notification(&mut self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.update_document_text(&document.uri, &document.text);
+        self.documents.insert(document.uri, document.text);
+    }
+
+    /// The changes are applied in order to the text of the open document
+    pub fn text_document_did_change_notification(&mut self, params: DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let mut text = self.documents.remove(&uri).unwrap_or_default();
+        for change in params.content_changes.iter() {
+            apply_change(&mut text, change);
+        }
+        self.update_document_text(&uri, &text);
+        self.documents.insert(uri, text);
+    }
+
+    pub fn text_document_did_save_notification(&mut self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+        if let Some(file_name) = uri_to_file_name(&uri) {
+            self.update_document(Source::from_file(&file_name));
+        }
+    }
+
+    /// Closed files revert to the contents on disk, files outside of the configuration are removed
+    pub fn text_document_did_close_notification(&mut self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let file_name = match uri_to_file_name(&uri) {
+            Some(file_name) => file_name,
+            None => {
+                return;
+            }
+        };
+
+        let library_name = self.library_name(&file_name);
+        let reverted = library_name != DEFAULT_LIBRARY
+            && self.project.update_file(&library_name, &file_name).is_ok();
+        if !reverted {
+            self.project.remove_file(&file_name);
+        }
+        self.documents.remove(&uri);
+        self.semantic_tokens.remove(&uri);
+        self.publish_diagnostics();
+    }
+
+    /// Requests whose result may be within any file first parse the deferred bodies of all files
+    fn parse_deferred_bodies(&mut self) {
+        if self.project.parse_deferred_bodies() > 0 {
+            self.publish_diagnostics();
+        }
+    }
+
+    fn resolution(&mut self) -> &NameResolution {
+        if self.resolution.is_none() {
+            self.resolution = Some(self.project.resolve());
+        }
+        self.resolution.as_ref().unwrap()
+    }
+
+    /// The source of a document and the character offset of a position within it
+    fn source_offset(&self, params: &TextDocumentPositionParams) -> Option<(Source, usize)> {
+        let file_name = uri_to_file_name(&params.text_document.uri)?;
+        let source = self.project.get_file(&file_name)?.source.clone();
+        let offset = position_to_offset(&source.contents().ok()?.bytes, params.position);
+        Some((source, offset))
+    }
+
+    /// The named entities denoted by the name or declaration at a position of a document
+    fn entities_at(&mut self, params: &TextDocumentPositionParams) -> Vec<NamedEntity> {
+        self.name_at(params)
+            .map(|(_, entities)| entities)
+            .unwrap_or_default()
+    }
+
+    /// The position of the name or declaration at a position of a document
+    /// together with the named entities it denotes
+    fn name_at(
+        &mut self,
+        params: &TextDocumentPositionParams,
+    ) -> Option<(SrcPos, Vec<NamedEntity>)> {
+        let (source, offset) = self.source_offset(params)?;
+        let file_name = source.file_name()?;
+
+        self.resolution()
+            .name_at(file_name, offset)
+            .map(|(pos, entities)| (pos.clone(), entities.into_iter().cloned().collect()))
+    }
+
+    /// The name at a position of a document when it denotes a single named entity
+    fn name_to_rename(
+        &mut self,
+        params: &TextDocumentPositionParams,
+    ) -> Result<Option<(SrcPos, NamedEntity)>, String> {
+        let (pos, mut entities) = match self.name_at(params) {
+            Some(name) => name,
+            None => {
+                return Ok(None);
+            }
+        };
+        if entities.len() > 1 {
+            return Err("The name denotes more than one declaration".to_owned());
+        }
+        match entities.pop() {
+            Some(entity) => {
+                can_rename(&entity)?;
+                Ok(Some((pos, entity)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn text_document_definition_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Vec<Location> {
+        self.entities_at(&params)
+            .iter()
+            .filter_map(|entity| srcpos_to_location(&entity.pos))
+            .collect()
+    }
+
+    /// The bound entity and architectures of a component or the architectures of an entity
+    pub fn text_document_implementation_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Vec<Location> {
+        self.parse_deferred_bodies();
+        let entities = self.entities_at(&params);
+        let resolution = self.resolution();
+
+        let mut locations: Vec<Location> = Vec::new();
+        for entity in entities.iter() {
+            for implementation in resolution.implementations(entity) {
+                if let Some(location) = srcpos_to_location(&implementation.pos) {
+                    if !locations.contains(&location) {
+                        locations.push(location);
+                    }
+                }
+            }
+        }
+        locations
+    }
+
+    pub fn text_document_references_request(&mut self, params: ReferenceParams) -> Vec<Location> {
+        self.parse_deferred_bodies();
+        let position = TextDocumentPositionParams {
+            text_document: params.text_document,
+            position: params.position,
+        };
+        let entities = self.entities_at(&position);
+        let resolution = self.resolution();
+
+        let mut positions = Vec::new();
+        for entity in entities.iter() {
+            if params.context.include_declaration {
+                positions.push(&entity.pos);
+            }
+            positions.extend(resolution.references_to(entity));
+        }
+
+        let mut locations: Vec<Location> = Vec::new();
+        for location in positions.into_iter().filter_map(srcpos_to_location) {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+        locations
+    }
+
+    /// The declarations and names within the document of the named entities at a position
+    /// where names which are assigned are writes
+    pub fn text_document_document_highlight_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Vec<DocumentHighlight> {
+        let entities = self.entities_at(&params);
+        let file_name = match uri_to_file_name(&params.text_document.uri) {
+            Some(file_name) => file_name,
+            None => {
+                return Vec::new();
+            }
+        };
+        let in_document = |pos: &SrcPos| pos.source.file_name() == Some(file_name.as_str());
+
+        let mut highlights = Vec::new();
+        for entity in entities.iter().filter(|entity| in_document(&entity.pos)) {
+            highlights.push(DocumentHighlight {
+                range: srcpos_to_range(&entity.pos),
+                kind: Some(DocumentHighlightKind::Text),
+            });
+        }
+        for reference in self.resolution().references() {
+            let denotes = entities
+                .iter()
+                .any(|entity| reference.entities.contains(entity));
+            if denotes && in_document(&reference.pos) {
+                highlights.push(DocumentHighlight {
+                    range: srcpos_to_range(&reference.pos),
+                    kind: Some(if reference.is_write {
+                        DocumentHighlightKind::Write
+                    } else {
+                        DocumentHighlightKind::Read
+                    }),
+                });
+            }
+        }
+        highlights
+    }
+
+    /// The declarations of all overloads are separated by horizontal rules
+    pub fn text_document_hover_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Option<Hover> {
+        let sections: Vec<String> = self
+            .entities_at(&params)
+            .iter()
+            .filter_map(declaration_text)
+            .map(|declaration| to_markdown(&declaration))
+            .collect();
+        if sections.is_empty() {
+            return None;
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: sections.join("\n\n---\n\n"),
+            }),
+            range: None,
+        })
+    }
+
+    pub fn text_document_signature_help_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Option<SignatureHelp> {
+        let (source, offset) = self.source_offset(&params)?;
+        signatures_at(self.resolution(), &source, offset).map(to_signature_help)
+    }
+
+    /// The hints within the range of a document of the kinds which are configured
+    pub fn text_document_inlay_hint_request(
+        &mut self,
+        params: InlayHintParams,
+    ) -> Option<Vec<InlayHint>> {
+        let file_name = uri_to_file_name(&params.text_document.uri)?;
+        let source = self.project.get_file(&file_name)?.source.clone();
+        let range = params.range;
+        let hints = hints_of(self.resolution(), &source)
+            .into_iter()
+            .filter(|hint| self.inlay_hints.shows(hint.kind))
+            .map(|hint| (srcpos_to_range(&hint.pos).start, hint))
+            .filter(|(position, _)| range.start <= *position && *position <= range.end)
+            .map(|(position, hint)| InlayHint {
+                position,
+                label: hint.label,
+                kind: inlay_hints::kind_number(hint.kind),
+                padding_left: false,
+                // Types follow the designator as if they were written
+                padding_right: hint.kind == InlayHintKind::Parameter,
+            })
+            .collect();
+        Some(hints)
+    }
+
+    /// The semantic tokens of a document which are remembered until the next request
+    fn semantic_tokens(&mut self, uri: &Url) -> Option<SemanticTokens> {
+        let file_name = uri_to_file_name(uri)?;
+        let source = self.project.get_file(&file_name)?.source.clone();
+        let data = semantic_tokens::encode(self.resolution(), &source);
+
+        self.semantic_tokens_results += 1;
+        let result_id = self.semantic_tokens_results.to_string();
+        self.semantic_tokens.insert(
+            uri.clone(),
+            SemanticTokens {
+                result_id: Some(result_id.clone()),
+                data: data.clone(),
+            },
+        );
+        Some(SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        })
+    }
+
+    pub fn text_document_semantic_tokens_full_request(
+        &mut self,
+        params: SemanticTokensParams,
+    ) -> Option<SemanticTokens> {
+        self.semantic_tokens(&params.text_document.uri)
+    }
+
+    /// The full tokens are sent when the previous result is not remembered
+    pub fn text_document_semantic_tokens_full_delta_request(
+        &mut self,
+        params: SemanticTokensDeltaParams,
+    ) -> Option<SemanticTokensDeltaResult> {
+        let SemanticTokensDeltaParams {
+            text_document,
+            previous_result_id,
+        } = params;
+        let uri = text_document.uri;
+        let previous = self
+            .semantic_tokens
+            .remove(&uri)
+            .filter(|previous| previous.result_id.as_ref() == Some(&previous_result_id));
+        let tokens = self.semantic_tokens(&uri)?;
+        Some(match previous {
+            Some(previous) => SemanticTokensDeltaResult::Delta(SemanticTokensDelta {
+                edits: semantic_tokens::delta(&previous.data, &tokens.data),
+                result_id: tokens.result_id,
+            }),
+            None => SemanticTokensDeltaResult::Tokens(tokens),
+        })
+    }
+
+    pub fn text_document_prepare_rename_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>, String> {
+        Ok(self.name_to_rename(&params)?.map(|(pos, entity)| {
+            PrepareRenameResponse::RangeWithPlaceholder {
+                range: srcpos_to_range(&pos),
+                placeholder: entity.designator,
+            }
+        }))
+    }
+
+    /// Renames the declaration and all names which denote it in all files of the project
+    pub fn text_document_rename_request(
+        &mut self,
+        params: RenameParams,
+    ) -> Result<Option<WorkspaceEdit>, String> {
+        self.parse_deferred_bodies();
+        let position = TextDocumentPositionParams {
+            text_document: params.text_document,
+            position: params.position,
+        };
+        let entity = match self.name_to_rename(&position)? {
+            Some((_, entity)) => entity,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for pos in rename_positions(self.resolution(), &entity, &params.new_name)? {
+            if let Some(location) = srcpos_to_location(&pos) {
+                changes.entry(location.uri).or_default().push(TextEdit {
+                    range: location.range,
+                    new_text: params.new_name.clone(),
+                });
+            }
+        }
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        }))
+    }
+
+    /// Quick fixes of the messages of a document which overlap the range and the declaration
+    /// of all undeclared signals of the document
+    pub fn text_document_code_action_request(
+        &mut self,
+        params: CodeActionParams,
+    ) -> CodeActionResponse {
+        let file_name = uri_to_file_name(&params.text_document.uri);
+        let related_information = self.related_information;
+        let messages = self
+            .messages_by_file()
+            .remove(file_name.as_ref().map_or("", String::as_str))
+            .unwrap_or_default();
+
+        let mut actions = Vec::new();
+        for message in messages.iter() {
+            let range = srcpos_to_range(&message.pos);
+            if range.end < params.range.start || params.range.end < range.start {
+                continue;
+            }
+            for fix in message.fixes.iter() {
+                let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+                for (pos, new_text) in fix.edits.iter() {
+                    if let Some(location) = srcpos_to_location(pos) {
+                        changes.entry(location.uri).or_default().push(TextEdit {
+                            range: location.range,
+                            new_text: new_text.clone(),
+                        });
+                    }
+                }
+                actions.push(CodeAction {
+                    title: fix.title.clone(),
+                    kind: Some(code_action_kind::QUICKFIX.to_owned()),
+                    diagnostics: Some(vec![to_diagnostic(message, related_information)]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(changes),
+                        ..WorkspaceEdit::default()
+                    }),
+                    command: None,
+                });
+            }
+        }
+
+        let declarations = match file_name {
+            Some(ref file_name) => self.resolution().signal_declarations(file_name),
+            None => Vec::new(),
+        };
+        if !declarations.is_empty() {
+            let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+            for (pos, new_text) in declarations.iter() {
+                if let Some(location) = srcpos_to_location(pos) {
+                    changes.entry(location.uri).or_default().push(TextEdit {
+                        range: location.range,
+                        new_text: new_text.clone(),
+                    });
+                }
+            }
+            actions.push(CodeAction {
+                title: "Declare all undeclared signals".to_owned(),
+                kind: Some(code_action_kind::SOURCE.to_owned()),
+                diagnostics: None,
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..WorkspaceEdit::default()
+                }),
+                command: None,
+            });
+        }
+        CodeActionResponse::Actions(actions)
+    }
+
+    /// The number of instantiations of each entity of a document and a command
+    /// to run each testbench when a testbench command is configured
+    pub fn text_document_code_lens_request(&mut self, params: CodeLensParams) -> Vec<CodeLens> {
+        self.parse_deferred_bodies();
+        let uri = params.text_document.uri;
+        let file_name = match uri_to_file_name(&uri) {
+            Some(file_name) => file_name,
+            None => {
+                return Vec::new();
+            }
+        };
+        let testbenches: Vec<Testbench> = if self.testbench_command.is_some() {
+            testbenches(&self.project)
+                .into_iter()
+                .filter(|testbench| testbench.file_name == file_name)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let resolution = self.resolution();
+        let calls = calls(resolution);
+        let mut lenses = Vec::new();
+        for entity in resolution.declarations().iter() {
+            if entity.kind != NamedEntityKind::Entity
+                || entity.pos.source.file_name() != Some(file_name.as_str())
+            {
+                continue;
+            }
+            let range = srcpos_to_range(&entity.pos);
+            let locations: Vec<Location> = calls
+                .iter()
+                .filter(|call| call.callee == *entity)
+                .filter_map(|call| srcpos_to_location(&call.pos))
+                .collect();
+            let title = match locations.len() {
+                1 => "1 instantiation".to_owned(),
+                count => format!("{} instantiations", count),
+            };
+            lenses.push(CodeLens {
+                range,
+                command: Some(Command {
+                    title,
+                    command: SHOW_REFERENCES.to_owned(),
+                    arguments: Some(vec![
+                        Value::String(uri.to_string()),
+                        serde_json::to_value(range.start).unwrap(),
+                        serde_json::to_value(locations).unwrap(),
+                    ]),
+                }),
+                data: None,
+            });
+
+            let testbench = testbenches.iter().find(|testbench| {
+                testbench
+                    .entity_name
+                    .eq_ignore_ascii_case(&entity.designator)
+            });
+            if let Some(testbench) = testbench {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(Command {
+                        title: "Run testbench".to_owned(),
+                        command: RUN_TESTBENCH.to_owned(),
+                        arguments: Some(vec![
+                            Value::String(testbench.library_name.clone()),
+                            Value::String(testbench.entity_name.clone()),
+                            Value::String(testbench.file_name.clone()),
+                        ]),
+                    }),
+                    data: None,
+                });
+            }
+        }
+        lenses
+    }
+
+    /// Runs a testbench given its library, entity and file name
+    pub fn workspace_execute_command_request(
+        &mut self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<Value>, String> {
+        if params.command != RUN_TESTBENCH {
+            return Err(format!("Unknown command {}", params.command));
+        }
+        let template = self
+            .testbench_command
+            .clone()
+            .ok_or_else(|| format!("No testbench command in {}", CONFIG_FILE_NAME))?;
+        let arguments: Vec<String> = params
+            .arguments
+            .iter()
+            .filter_map(|argument| argument.as_str().map(str::to_owned))
+            .collect();
+        let testbench = match arguments.as_slice() {
+            [library_name, entity_name, file_name] => Testbench {
+                library_name: library_name.clone(),
+                entity_name: entity_name.clone(),
+                file_name: file_name.clone(),
+                test_cases: Vec::new(),
+            },
+            _ => {
+                return Err("Expected the library, entity and file of a testbench".to_owned());
+            }
+        };
+
+        let command = testbench_command(&template, &testbench);
+        spawn_shell_command(&command, self.root_path.as_deref())
+            .map_err(|err| format!("Could not run {}: {}", command, err))?;
+        self.show_message(MessageType::Info, format!("Running {}", command));
+        Ok(None)
+    }
+
+    /// The style of the nearest formatter configuration file of the document, otherwise of
+    /// the settings and the options of the editor
+    fn format_style(&self, file_name: &str, options: &FormattingOptions) -> FormatStyle {
+        match FormatConfig::find(Path::new(file_name)) {
+            Ok(Some(config)) => config.style(Path::new(file_name)),
+            Ok(None) => self.settings.format_style(options),
+            Err(err) => {
+                self.show_message(MessageType::Error, err);
+                self.settings.format_style(options)
+            }
+        }
+    }
+
+    fn format_document(
+        &mut self,
+        uri: &Url,
+        options: &FormattingOptions,
+        range: Option<Range>,
+    ) -> Option<Vec<TextEdit>> {
+        let file_name = uri_to_file_name(uri)?;
+        let source = self.project.get_file(&file_name)?.source.clone();
+        let style = self.format_style(&file_name, options);
+        let spellings = if style.standard_case.is_some() || style.declared_spelling {
+            self.resolution();
+            let resolution = self.resolution.as_ref()?;
+            formatter::spellings(&self.project, resolution, &file_name, &style)
+        } else {
+            Vec::new()
+        };
+        let edits = match range {
+            Some(range) => {
+                let bytes = &source.contents().ok()?.bytes;
+                formatter::format_selection(
+                    &source,
+                    &style,
+                    &spellings,
+                    position_to_offset(bytes, range.start),
+                    position_to_offset(bytes, range.end),
+                )
+            }
+            None => formatter::format(&source, &style, &spellings, None),
+        };
+        Some(to_text_edits(edits))
+    }
+
+    /// Completes an end when typing its semi colon and indents a new line
+    pub fn text_document_on_type_formatting_request(
+        &mut self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Option<Vec<TextEdit>> {
+        let file_name = uri_to_file_name(&params.text_document.uri)?;
+        let source = &self.project.get_file(&file_name)?.source;
+        let edits = match params.ch.as_str() {
+            ";" => {
+                let offset = position_to_offset(&source.contents().ok()?.bytes, params.position);
+                formatter::complete_end(source, offset)
+                    .into_iter()
+                    .collect()
+            }
+            "\n" => formatter::indent_line(
+                source,
+                &self.format_style(&file_name, &params.options).indentation,
+                params.position.line as usize,
+            ),
+            _ => Vec::new(),
+        };
+        Some(to_text_edits(edits))
+    }
+
+    pub fn text_document_formatting_request(
+        &mut self,
+        params: DocumentFormattingParams,
+    ) -> Option<Vec<TextEdit>> {
+        self.format_document(&params.text_document.uri, &params.options, None)
+    }
+
+    pub fn text_document_range_formatting_request(
+        &mut self,
+        params: DocumentRangeFormattingParams,
+    ) -> Option<Vec<TextEdit>> {
+        self.format_document(
+            &params.text_document.uri,
+            &params.options,
+            Some(params.range),
+        )
+    }
+
+    /// The subprograms and design units denoted by the name or declaration at a position
+    pub fn text_document_prepare_call_hierarchy_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Option<Vec<CallHierarchyItem>> {
+        let items: Vec<CallHierarchyItem> = self
+            .entities_at(&params)
+            .iter()
+            .filter(|entity| is_callable(entity.kind))
+            .filter_map(to_call_hierarchy_item)
+            .collect();
+        if items.is_empty() {
+            None
+        } else {
+            Some(items)
+        }
+    }
+
+    /// The named entity of a hierarchy item is found by the position of its declaration
+    fn declaration_at(&mut self, uri: &Url, range: Range) -> Option<NamedEntity> {
+        let location = Location {
+            uri: uri.clone(),
+            range,
+        };
+        self.resolution()
+            .declarations()
+            .iter()
+            .find(|entity| srcpos_to_location(&entity.pos).as_ref() == Some(&location))
+            .cloned()
+    }
+
+    pub fn call_hierarchy_incoming_calls_request(
+        &mut self,
+        params: CallHierarchyCallsParams,
+    ) -> Option<Vec<CallHierarchyIncomingCall>> {
+        self.parse_deferred_bodies();
+        let callee = self.declaration_at(&params.item.uri, params.item.selection_range)?;
+        Some(
+            incoming_calls(self.resolution(), &callee)
+                .iter()
+                .filter_map(|(caller, positions)| {
+                    Some(CallHierarchyIncomingCall {
+                        from: to_call_hierarchy_item(caller)?,
+                        from_ranges: positions.iter().map(srcpos_to_range).collect(),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn call_hierarchy_outgoing_calls_request(
+        &mut self,
+        params: CallHierarchyCallsParams,
+    ) -> Option<Vec<CallHierarchyOutgoingCall>> {
+        let caller = self.declaration_at(&params.item.uri, params.item.selection_range)?;
+        Some(
+            outgoing_calls(self.resolution(), &caller)
+                .iter()
+                .filter_map(|(callee, positions)| {
+                    Some(CallHierarchyOutgoingCall {
+                        to: to_call_hierarchy_item(callee)?,
+                        from_ranges: positions.iter().map(srcpos_to_range).collect(),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// The types and subtypes denoted by the name or declaration at a position
+    pub fn text_document_prepare_type_hierarchy_request(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        let items: Vec<TypeHierarchyItem> = self
+            .entities_at(&params)
+            .iter()
+            .filter(|entity| is_type(entity))
+            .filter_map(|entity| to_type_hierarchy_item(entity, None))
+            .collect();
+        if items.is_empty() {
+            None
+        } else {
+            Some(items)
+        }
+    }
+
+    /// The type marks of a subtype and the element types of an array or record type
+    pub fn type_hierarchy_supertypes_request(
+        &mut self,
+        params: TypeHierarchyParams,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        let typ = self.declaration_at(&params.item.uri, params.item.selection_range)?;
+        Some(
+            self.resolution()
+                .supertypes_of(&typ)
+                .into_iter()
+                .filter_map(|(supertype, relation)| {
+                    to_type_hierarchy_item(supertype, Some(supertype_detail(relation)))
+                })
+                .collect(),
+        )
+    }
+
+    /// The subtypes of a type and the array or record types with elements of it
+    pub fn type_hierarchy_subtypes_request(
+        &mut self,
+        params: TypeHierarchyParams,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        self.parse_deferred_bodies();
+        let typ = self.declaration_at(&params.item.uri, params.item.selection_range)?;
+        Some(
+            self.resolution()
+                .subtypes_of(&typ)
+                .into_iter()
+                .filter_map(|(subtype, relation)| {
+                    to_type_hierarchy_item(subtype, Some(subtype_detail(relation)))
+                })
+                .collect(),
+        )
+    }
+
+    /// The outline of the design units of a document, nested when supported by the client
+    pub fn text_document_document_symbol_request(
+        &mut self,
+        params: DocumentSymbolParams,
+    ) -> DocumentSymbolResponse {
+        let items = uri_to_file_name(&params.text_document.uri)
+            .and_then(|file_name| self.project.get_file(&file_name))
+            .map(|file| outline(&file.source, &file.design_file))
+            .unwrap_or_default();
+
+        if self.hierarchical_symbols {
+            DocumentSymbolResponse::Nested(items.iter().map(to_document_symbol).collect())
+        } else {
+            let mut symbols = Vec::new();
+            to_symbol_information(&items, None, &mut symbols);
+            DocumentSymbolResponse::Flat(symbols)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use semantic_tokens::SemanticTokensEdit;
+    use serde_json::Value;
+    use std::cell::RefCell;
+    use std::fs;
+    use vhdl_parser::formatter::Case;
+
+    #[derive(Default)]
+    struct RecordingChannel {
+        notifications: RefCell<Vec<(String, Value)>>,
+        requests: RefCell<Vec<(u64, String, Value)>>,
+    }
+
+    impl RpcChannel for RecordingChannel {
+        fn send_notification(&self, method: &str, params: Value) {
+            self.notifications
+                .borrow_mut()
+                .push((method.to_owned(), params));
+        }
+
+        fn send_request(&self, id: u64, method: &str, params: Value) {
+            self.requests
+                .borrow_mut()
+                .push((id, method.to_owned(), params));
+        }
+    }
+
+    fn initialize(root: &Path) -> VHDLServer<RecordingChannel> {
+        initialize_with_progress(root, false)
+    }
+
+    fn initialize_with_progress(
+        root: &Path,
+        work_done_progress: bool,
+    ) -> VHDLServer<RecordingChannel> {
+        let mut server = VHDLServer::new(RecordingChannel::default());
+        let params = InitializeParams {
+            process_id: None,
+            root_path: None,
+            root_uri: Some(Url::from_directory_path(root).unwrap()),
+            initialization_options: None,
+            capabilities: ClientCapabilities {
+                text_document: Some(TextDocumentClientCapabilities {
+                    publish_diagnostics: Some(PublishDiagnosticsCapability {
+                        related_information: Some(true),
+                    }),
+                    document_symbol: Some(DocumentSymbolCapability {
+                        dynamic_registration: None,
+                        symbol_kind: None,
+                        hierarchical_document_symbol_support: Some(true),
+                    }),
+                    ..TextDocumentClientCapabilities::default()
+                }),
+                ..ClientCapabilities::default()
+            },
+            trace: None,
+            workspace_folders: None,
+        };
+        server.initialize_request(settings::InitializeParams {
+            params,
+            configuration: false,
+            work_done_progress,
+        });
+        server
+    }
+
+    /// The published diagnostics, all other notifications are discarded
+    fn take_diagnostics(server: &VHDLServer<RecordingChannel>) -> Vec<PublishDiagnosticsParams> {
+        server
+            .rpc_channel
+            .notifications
+            .borrow_mut()
+            .drain(..)
+            .filter(|(method, _)| method == "textDocument/publishDiagnostics")
+            .map(|(_, params)| serde_json::from_value(params).unwrap())
+            .collect()
+    }
+
+    fn open(server: &mut VHDLServer<RecordingChannel>, uri: &Url, text: &str) {
+        server.text_document_did_open_notification(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "vhdl".to_owned(),
+                version: 0,
+                text: text.to_owned(),
+            },
+        });
+    }
+
+    fn change(server: &mut VHDLServer<RecordingChannel>, uri: &Url, text: &str) {
+        server.text_document_did_change_notification(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: Some(1),
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_owned(),
+            }],
+        });
+    }
+
+    #[test]
+    fn initialize_loads_config_of_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is end entity;").unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+
+        let server = initialize(dir.path());
+        let file = server
+            .project
+            .get_file(file_name.to_str().unwrap())
+            .unwrap();
+        assert_eq!(file.library_name, "lib");
+    }
+
+    /// The severities of the diagnostics of each file
+    fn severities(diagnostics: Vec<PublishDiagnosticsParams>) -> Vec<Vec<DiagnosticSeverity>> {
+        diagnostics
+            .into_iter()
+            .map(|params| {
+                params
+                    .diagnostics
+                    .into_iter()
+                    .filter_map(|diagnostic| diagnostic.severity)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn did_change_configuration_applies_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = dir.path().join("a.vhd");
+        let file2 = dir.path().join("b.vhd");
+        fs::write(&file1, "entity a is end entity;").unwrap();
+        fs::write(
+            &file2,
+            "package b is\ncomponent c is\nend component d;\nend package;",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['a.vhd']",
+        )
+        .unwrap();
+        let mut server = initialize(dir.path());
+        server.initialized_notification();
+        assert!(server.project.get_file(file2.to_str().unwrap()).is_none());
+
+        let settings = |settings: &str| DidChangeConfigurationParams {
+            settings: serde_json::from_str(settings).unwrap(),
+        };
+        server.workspace_did_change_configuration_notification(settings(
+            r#"{"vhdl_ls": {"libraries": {"lib": {"files": ["b.vhd"]}}, "lint": {"end-identifier-mismatch": "hint"}}}"#,
+        ));
+        assert!(server.project.get_file(file1.to_str().unwrap()).is_none());
+        assert_eq!(
+            server
+                .project
+                .get_file(file2.to_str().unwrap())
+                .unwrap()
+                .library_name,
+            "lib"
+        );
+        assert_eq!(
+            severities(take_diagnostics(&server)),
+            vec![vec![DiagnosticSeverity::Hint]]
+        );
+
+        server.workspace_did_change_configuration_notification(settings(
+            r#"{"vhdl_ls": {"libraries": {"lib": {"files": ["b.vhd"]}}, "lint": {"end-identifier-mismatch": "ignore"}}}"#,
+        ));
+        assert_eq!(severities(take_diagnostics(&server)), vec![vec![]]);
+
+        server.workspace_did_change_configuration_notification(settings(
+            r#"{"vhdl_ls": {"lint": {"end-identifier-mismatch": "fatal"}}}"#,
+        ));
+        let messages = server.rpc_channel.notifications.borrow();
+        assert_eq!(messages.last().unwrap().0, "window/showMessage");
+    }
+
+    #[test]
+    fn configuration_is_requested_when_supported() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        server.configuration_requests = true;
+        server.initialized_notification();
+        let (id, method, params) = server.rpc_channel.requests.borrow_mut().remove(0);
+        assert_eq!(method, "workspace/configuration");
+        assert_eq!(
+            params,
+            serde_json::to_value(ConfigurationParams {
+                items: vec![ConfigurationItem {
+                    section: Some("vhdl_ls".to_owned()),
+                }],
+            })
+            .unwrap()
+        );
+
+        server.client_response(
+            serde_json::from_str(&format!(
+                r#"{{"jsonrpc": "2.0", "id": {}, "result": [{{"formatter": {{"indentSize": 2}}}}]}}"#,
+                id
+            ))
+            .unwrap(),
+        );
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "entity ent is\nport (clk : in bit);\nend entity;",
+        );
+        let edits = server.text_document_formatting_request(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: true,
+                properties: HashMap::new(),
+            },
+        });
+        assert_eq!(edits.unwrap()[0].new_text, "  ");
+
+        server.workspace_did_change_configuration_notification(DidChangeConfigurationParams {
+            settings: Value::Null,
+        });
+        assert_eq!(server.rpc_channel.requests.borrow().len(), 1);
+    }
+
+    #[test]
+    fn indexing_progress_of_initial_load() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.vhd"), "entity a is end entity;").unwrap();
+        fs::write(dir.path().join("b.vhd"), "entity b is").unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+        let mut server = initialize_with_progress(dir.path(), true);
+        assert!(server.project.files().is_empty());
+
+        server.initialized_notification();
+        let (id, method, params) = server.rpc_channel.requests.borrow_mut().remove(0);
+        assert_eq!(method, "window/workDoneProgress/create");
+        assert_eq!(params["token"], INDEXING_TOKEN);
+        assert_eq!(take_diagnostics(&server), vec![]);
+
+        server.client_response(
+            serde_json::from_str(&format!(
+                r#"{{"jsonrpc": "2.0", "id": {}, "result": null}}"#,
+                id
+            ))
+            .unwrap(),
+        );
+        assert_eq!(server.project.files().len(), 2);
+        let progress: Vec<(String, Value)> = server
+            .rpc_channel
+            .notifications
+            .borrow()
+            .iter()
+            .filter(|(method, _)| method == "$/progress")
+            .map(|(_, params)| {
+                (
+                    params["value"]["kind"].as_str().unwrap().to_owned(),
+                    params["value"]["message"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            progress,
+            vec![
+                ("begin".to_owned(), Value::from("0/2 files")),
+                ("report".to_owned(), Value::from("1/2 files")),
+                ("report".to_owned(), Value::from("2/2 files")),
+                ("report".to_owned(), Value::from("Analyzing")),
+                ("end".to_owned(), Value::Null),
+            ]
+        );
+        assert_eq!(take_diagnostics(&server).len(), 1);
+    }
+
+    #[test]
+    fn code_lenses_of_instantiations_and_testbenches() {
+        let dir = tempfile::tempdir().unwrap();
+        let child = dir.path().join("child.vhd");
+        let tb = dir.path().join("tb_top.vhd");
+        fs::write(&child, "entity child is\nend entity;").unwrap();
+        fs::write(
+            &tb,
+            "entity tb_top is\n  generic (runner_cfg : string);\nend entity;\narchitecture tb of tb_top is\nbegin\n  first : entity work.child;\n  second : entity work.child;\nend architecture;",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']\n\n[testbench]\ncommand = 'true {library}.{entity}'",
+        )
+        .unwrap();
+        let mut server = initialize(dir.path());
+
+        let lenses = |server: &mut VHDLServer<RecordingChannel>, path: &Path| {
+            server
+                .text_document_code_lens_request(CodeLensParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Url::from_file_path(path).unwrap(),
+                    },
+                })
+                .into_iter()
+                .map(|lens| lens.command.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let commands = lenses(&mut server, &child);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].title, "2 instantiations");
+        assert_eq!(commands[0].command, SHOW_REFERENCES);
+        let locations = &commands[0].arguments.as_ref().unwrap()[2];
+        assert_eq!(locations.as_array().unwrap().len(), 2);
+
+        let commands = lenses(&mut server, &tb);
+        let titles: Vec<&str> = commands
+            .iter()
+            .map(|command| command.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["0 instantiations", "Run testbench"]);
+        let arguments = commands[1].arguments.clone().unwrap();
+        assert_eq!(arguments[0], "lib");
+        assert_eq!(arguments[1], "tb_top");
+        assert_eq!(
+            testbench_command(
+                "run {library}.{entity} {file}",
+                &Testbench {
+                    library_name: "lib".to_owned(),
+                    entity_name: "tb_top".to_owned(),
+                    file_name: "tb_top.vhd".to_owned(),
+                    test_cases: Vec::new(),
+                }
+            ),
+            "run lib.tb_top tb_top.vhd"
+        );
+
+        assert_eq!(
+            server.workspace_execute_command_request(ExecuteCommandParams {
+                command: RUN_TESTBENCH.to_owned(),
+                arguments,
+            }),
+            Ok(None)
+        );
+        assert!(server
+            .workspace_execute_command_request(ExecuteCommandParams {
+                command: "unknown".to_owned(),
+                arguments: Vec::new(),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_config_is_shown() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(CONFIG_FILE_NAME), "[libraries]\nlib = 1").unwrap();
+
+        let server = initialize(dir.path());
+        let notifications = server.rpc_channel.notifications.borrow();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].0, "window/showMessage");
+    }
+
+    #[test]
+    fn open_and_change_publish_diagnostics() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+
+        open(&mut server, &uri, "entity ent is\nend entity");
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].uri, uri);
+        assert_eq!(diagnostics[0].diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].diagnostics[0].range.start,
+            Position {
+                line: 1,
+                character: 10
+            }
+        );
+
+        change(&mut server, &uri, "entity ent is\nend entity;");
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].diagnostics, vec![]);
+    }
+
+    #[test]
+    fn incremental_changes_of_open_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(&mut server, &uri, "entity ent is\nend entity;");
+        take_diagnostics(&server);
+
+        let change = |line, start, end, text: &str| TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line,
+                    character: start,
+                },
+                end: Position {
+                    line,
+                    character: end,
+                },
+            }),
+            range_length: None,
+            text: text.to_owned(),
+        };
+        server.text_document_did_change_notification(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version: Some(1),
+            },
+            content_changes: vec![change(1, 4, 10, "package"), change(0, 0, 6, "package")],
+        });
+        assert_eq!(server.documents[&uri], "package ent is\nend package;");
+        let file = server
+            .project
+            .get_file(&uri_to_file_name(&uri).unwrap())
+            .unwrap();
+        assert_eq!(
+            file.source.contents().unwrap().to_string(),
+            "package ent is\nend package;"
+        );
+    }
+
+    #[test]
+    fn apply_changes_with_multibyte_characters() {
+        let mut text = "-- åäö\nentity".to_owned();
+        apply_change(
+            &mut text,
+            &TextDocumentContentChangeEvent {
+                range: Some(Range {
+                    start: Position {
+                        line: 0,
+                        character: 4,
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 0,
+                    },
+                }),
+                range_length: None,
+                text: "x\n".to_owned(),
+            },
+        );
+        assert_eq!(text, "-- åx\nentity");
+
+        // Positions beyond the end of a line are clamped
+        apply_change(
+            &mut text,
+            &TextDocumentContentChangeEvent {
+                range: Some(Range {
+                    start: Position {
+                        line: 0,
+                        character: 80,
+                    },
+                    end: Position {
+                        line: 0,
+                        character: 80,
+                    },
+                }),
+                range_length: None,
+                text: "y".to_owned(),
+            },
+        );
+        assert_eq!(text, "-- åxy\nentity");
+    }
+
+    #[test]
+    fn close_reverts_to_file_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is end entity;").unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(&file_name).unwrap();
+
+        open(&mut server, &uri, "entity ent is");
+        assert_eq!(take_diagnostics(&server)[0].diagnostics.len(), 1);
+        server.text_document_did_close_notification(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+        });
+        assert_eq!(take_diagnostics(&server)[0].diagnostics, vec![]);
+        let file = server
+            .project
+            .get_file(file_name.to_str().unwrap())
+            .unwrap();
+        assert_eq!(file.library_name, "lib");
+
+        // Files outside of the configuration are removed when closed
+        let other = Url::from_file_path(dir.path().join("other.vhd")).unwrap();
+        open(&mut server, &other, "entity other is end entity;");
+        server.text_document_did_close_notification(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: other },
+        });
+        assert_eq!(server.project.files().len(), 1);
+    }
+
+    #[test]
+    fn initialized_publishes_diagnostics_of_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is").unwrap();
+        fs::write(dir.path().join("pkg.vhd"), "package pkg is end package;").unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+
+        let mut server = initialize(dir.path());
+        assert_eq!(take_diagnostics(&server), vec![]);
+        server.initialized_notification();
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].uri, Url::from_file_path(&file_name).unwrap());
+        assert_eq!(diagnostics[0].diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn semantic_diagnostics_with_code_and_related_information() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let first = Url::from_file_path(dir.path().join("first.vhd")).unwrap();
+        let second = Url::from_file_path(dir.path().join("second.vhd")).unwrap();
+
+        open(&mut server, &first, "entity ent is end entity;");
+        assert_eq!(take_diagnostics(&server), vec![]);
+        open(&mut server, &second, "\nentity ent is end entity;");
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].uri, second);
+
+        let diagnostic = &diagnostics[0].diagnostics[0];
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Error));
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("duplicate-unit".to_owned()))
+        );
+        assert_eq!(
+            diagnostic.range.start,
+            Position {
+                line: 1,
+                character: 7
+            }
+        );
+        let related = diagnostic.related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].location.uri, first);
+        assert_eq!(related[0].message, "Previously declared here");
+
+        // Removing the duplicate clears the diagnostics of the other file by background work
+        // since the other file does not depend on the edited file
+        change(&mut server, &first, "entity other is end entity;");
+        assert_eq!(take_diagnostics(&server), vec![]);
+        assert!(server.background_work());
+        assert!(!server.background_work());
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].uri, second);
+        assert_eq!(diagnostics[0].diagnostics, vec![]);
+    }
+
+    #[test]
+    fn background_analysis_of_changed_project_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let first = Url::from_file_path(dir.path().join("first.vhd")).unwrap();
+        let second = Url::from_file_path(dir.path().join("second.vhd")).unwrap();
+        open(&mut server, &first, "entity dup is end entity;");
+        while server.background_work() {}
+        take_diagnostics(&server);
+
+        // The analysis runs on another thread while the project changes
+        change(&mut server, &first, "entity ent is end entity;");
+        let analysis = server.background_analysis().unwrap();
+        assert!(server.background_analysis().is_none());
+        let analysis = thread::spawn(move || analysis.run());
+        open(&mut server, &second, "entity ent is end entity;");
+        take_diagnostics(&server);
+
+        server.finish_background_analysis(analysis.join().unwrap());
+        assert_eq!(take_diagnostics(&server), vec![]);
+
+        // The stale analysis would have cleared the duplicate of the second file which
+        // the next analysis reports again
+        assert!(server.background_work());
+        let diagnostics = take_diagnostics(&server);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].uri, second);
+        assert_eq!(diagnostics[0].diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn diagnostics_of_edited_file_and_dependents_are_published_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let pkg = Url::from_file_path(dir.path().join("a.vhd")).unwrap();
+        let ent = Url::from_file_path(dir.path().join("b.vhd")).unwrap();
+        let other = Url::from_file_path(dir.path().join("z.vhd")).unwrap();
+        open(&mut server, &pkg, "package pkg is end package;");
+        open(
+            &mut server,
+            &ent,
+            "use work.pkg.all;\nentity ent is end entity;",
+        );
+        open(&mut server, &other, "entity dup is end entity;");
+        while server.background_work() {}
+        assert_eq!(take_diagnostics(&server), vec![]);
+
+        let files_with_diagnostics = |server: &VHDLServer<RecordingChannel>| -> Vec<Url> {
+            take_diagnostics(server)
+                .into_iter()
+                .filter(|params| !params.diagnostics.is_empty())
+                .map(|params| params.uri)
+                .collect()
+        };
+
+        // The file which used the renamed package is published at once
+        // while the duplicate of the unrelated file is published by background work
+        change(
+            &mut server,
+            &pkg,
+            "package pkg2 is end package;\nentity dup is end entity;",
+        );
+        assert_eq!(files_with_diagnostics(&server), vec![ent.clone()]);
+        assert!(server.background_work());
+        assert_eq!(files_with_diagnostics(&server), vec![ent, other]);
+    }
+
+    #[test]
+    fn code_action_fixes_message_within_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ctx.vhd")).unwrap();
+        open(&mut server, &uri, "context ctx is\nend context other;");
+
+        // The range of the whole line
+        let code_action = |server: &mut VHDLServer<RecordingChannel>, line| {
+            server.text_document_code_action_request(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: Range {
+                    start: Position { line, character: 0 },
+                    end: Position {
+                        line,
+                        character: 80,
+                    },
+                },
+                context: CodeActionContext {
+                    diagnostics: Vec::new(),
+                    only: None,
+                },
+            })
+        };
+
+        let actions = match code_action(&mut server, 1) {
+            CodeActionResponse::Actions(actions) => actions,
+            CodeActionResponse::Commands(..) => panic!("Expected actions"),
+        };
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.title, "Replace with ctx");
+        assert_eq!(action.kind, Some(code_action_kind::QUICKFIX.to_owned()));
+        assert_eq!(
+            action.diagnostics.as_ref().unwrap()[0].code,
+            Some(NumberOrString::String("end-identifier-mismatch".to_owned()))
+        );
+        let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+        assert_eq!(
+            changes[&uri],
+            vec![TextEdit {
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 12
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 17
+                    },
+                },
+                new_text: "ctx".to_owned(),
+            }]
+        );
+
+        match code_action(&mut server, 0) {
+            CodeActionResponse::Actions(actions) => assert!(actions.is_empty()),
+            CodeActionResponse::Commands(..) => panic!("Expected actions"),
+        }
+    }
+
+    #[test]
+    fn code_action_declares_all_undeclared_signals() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("std")).unwrap();
+        fs::write(
+            dir.path().join("std").join("standard.vhd"),
+            "package standard is\n  type bit is ('0', '1');\nend package;",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nstd.files = ['std/*.vhd']\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "entity child is
+  port (clk : in bit);
+end entity;
+
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  inst0: entity work.child port map (clk => clk0);
+  inst1: entity work.child port map (clk => clk1);
+end architecture;",
+        );
+
+        let actions = match server.text_document_code_action_request(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range {
+                start: Position {
+                    line: 0,
+                    character: 0,
+                },
+                end: Position {
+                    line: 0,
+                    character: 0,
+                },
+            },
+            context: CodeActionContext {
+                diagnostics: Vec::new(),
+                only: None,
+            },
+        }) {
+            CodeActionResponse::Actions(actions) => actions,
+            CodeActionResponse::Commands(..) => panic!("Expected actions"),
+        };
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.title, "Declare all undeclared signals");
+        assert_eq!(action.kind, Some(code_action_kind::SOURCE.to_owned()));
+        let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+        assert_eq!(changes[&uri].len(), 1);
+        assert_eq!(
+            changes[&uri][0].new_text,
+            "  signal clk0 : bit;\n  signal clk1 : bit;\n"
+        );
+    }
+
+    #[test]
+    fn formatting_of_document_and_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "entity ent is\nport (clk : in bit);\nend entity;\narchitecture a of ent is\nbegin \nend;",
+        );
+        let options = || FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+            properties: HashMap::new(),
+        };
+        let edit = |line, character, length, new_text: &str| TextEdit {
+            range: Range {
+                start: Position { line, character },
+                end: Position {
+                    line,
+                    character: character + length,
+                },
+            },
+            new_text: new_text.to_owned(),
+        };
+
+        let edits = server.text_document_formatting_request(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            options: options(),
+        });
+        assert_eq!(edits, Some(vec![edit(1, 0, 0, "    "), edit(4, 5, 1, "")]));
+
+        let edits = server.text_document_range_formatting_request(DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range {
+                start: Position {
+                    line: 3,
+                    character: 0,
+                },
+                end: Position {
+                    line: 5,
+                    character: 0,
+                },
+            },
+            options: options(),
+        });
+        assert_eq!(edits, Some(vec![edit(4, 5, 1, "")]));
+
+        // The document is replaced when the style changes more than the indentation
+        server.settings.formatter.keyword_case = Some(Case::Upper);
+        server.settings.formatter.declared_spelling = true;
+        let edits = server
+            .text_document_formatting_request(DocumentFormattingParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                options: options(),
+            })
+            .unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position::new(0, 0));
+        assert_eq!(
+            edits[0].new_text,
+            "ENTITY ent IS\n    PORT (clk : IN bit);\nEND ENTITY;\nARCHITECTURE a OF ent IS\nBEGIN\nEND;"
+        );
+
+        // Only the changes within a selection of part of a line are made
+        let edits = server.text_document_range_formatting_request(DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range {
+                start: Position::new(1, 6),
+                end: Position::new(1, 16),
+            },
+            options: options(),
+        });
+        assert_eq!(edits, Some(vec![edit(1, 12, 2, "IN")]));
+
+        // A formatter configuration file replaces the settings and the editor options
+        fs::write(dir.path().join(".vhdlfmt.toml"), "indent_size = 3").unwrap();
+        let edits = server.text_document_formatting_request(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            options: options(),
+        });
+        assert_eq!(edits, Some(vec![edit(1, 0, 0, "   "), edit(4, 5, 1, "")]));
+    }
+
+    #[test]
+    fn on_type_formatting_completes_end_and_indents() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "architecture rtl of ent is\nbegin\n  main : process\n  begin\n\n  end;",
+        );
+        let on_type = |server: &mut VHDLServer<RecordingChannel>, line, character, ch: &str| {
+            server.text_document_on_type_formatting_request(DocumentOnTypeFormattingParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position { line, character },
+                ch: ch.to_owned(),
+                options: FormattingOptions {
+                    tab_size: 2,
+                    insert_spaces: true,
+                    properties: HashMap::new(),
+                },
+            })
+        };
+        let insert = |line, character, new_text: &str| TextEdit {
+            range: Range {
+                start: Position { line, character },
+                end: Position { line, character },
+            },
+            new_text: new_text.to_owned(),
+        };
+
+        assert_eq!(
+            on_type(&mut server, 5, 6, ";"),
+            Some(vec![insert(5, 5, " process main")])
+        );
+        assert_eq!(
+            on_type(&mut server, 4, 0, "\n"),
+            Some(vec![insert(4, 0, "    ")])
+        );
+        assert_eq!(on_type(&mut server, 1, 5, ";"), Some(vec![]));
+    }
+
+    fn position(line: u64, character: u64) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::parse("file:///unused.vhd").unwrap(),
+            },
+            position: Position { line, character },
+        }
+    }
+
+    fn position_in(uri: &Url, line: u64, character: u64) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            ..position(line, character)
+        }
+    }
+
+    #[test]
+    fn offset_of_position() {
+        let bytes = b"ab\ncd\n";
+        assert_eq!(position_to_offset(bytes, position(0, 0).position), 0);
+        assert_eq!(position_to_offset(bytes, position(1, 1).position), 4);
+        // Beyond the end of the line
+        assert_eq!(position_to_offset(bytes, position(0, 7).position), 2);
+        assert_eq!(position_to_offset(bytes, position(5, 0).position), 6);
+    }
+
+    #[test]
+    fn definition_in_other_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let pkg = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        let ent = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+
+        open(
+            &mut server,
+            &pkg,
+            "package pkg is\n  constant width : natural := 8;\n  constant depth : natural := 4;\nend package;",
+        );
+        open(
+            &mut server,
+            &ent,
+            "use work.pkg.all;\nentity ent is\n  generic (g : natural := width);\nend entity;",
+        );
+
+        let locations = server.text_document_definition_request(position_in(&ent, 2, 28));
+        assert_eq!(
+            locations,
+            vec![Location {
+                uri: pkg.clone(),
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 11
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 16
+                    }
+                }
+            }]
+        );
+
+        // A change invalidates the previous resolution
+        change(
+            &mut server,
+            &ent,
+            "use work.pkg.all;\nentity ent is\n  generic (g : natural := 0);\nend entity;",
+        );
+        assert_eq!(
+            server.text_document_definition_request(position_in(&ent, 2, 28)),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn document_highlights_of_reads_and_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "entity ent is\nend entity;\narchitecture rtl of ent is\n  signal data : bit;\nbegin\n  data <= not data;\nend architecture;",
+        );
+
+        let highlight = |line, start, kind| DocumentHighlight {
+            range: Range {
+                start: Position {
+                    line,
+                    character: start,
+                },
+                end: Position {
+                    line,
+                    character: start + 4,
+                },
+            },
+            kind: Some(kind),
+        };
+        let expected = vec![
+            highlight(3, 9, DocumentHighlightKind::Text),
+            highlight(5, 2, DocumentHighlightKind::Write),
+            highlight(5, 14, DocumentHighlightKind::Read),
+        ];
+        assert_eq!(
+            server.text_document_document_highlight_request(position_in(&uri, 5, 15)),
+            expected
+        );
+        assert_eq!(
+            server.text_document_document_highlight_request(position_in(&uri, 3, 10)),
+            expected
+        );
+    }
+
+    #[test]
+    fn implementation_of_component_and_entity() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let child = Url::from_file_path(dir.path().join("child.vhd")).unwrap();
+        let top = Url::from_file_path(dir.path().join("top.vhd")).unwrap();
+
+        open(
+            &mut server,
+            &child,
+            "entity child is\nend entity;\narchitecture rtl of child is\nbegin\nend architecture;",
+        );
+        open(
+            &mut server,
+            &top,
+            "entity top is\nend entity;\narchitecture rtl of top is\n  component child is\n  end component;\nbegin\n  first : child;\n  second : entity work.child;\nend architecture;",
+        );
+
+        let range = |line, start, end| Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: end,
+            },
+        };
+        let entity = Location {
+            uri: child.clone(),
+            range: range(0, 7, 12),
+        };
+        let architecture = Location {
+            uri: child.clone(),
+            range: range(2, 13, 16),
+        };
+
+        assert_eq!(
+            server.text_document_implementation_request(position_in(&top, 6, 11)),
+            vec![entity.clone(), architecture.clone()]
+        );
+        assert_eq!(
+            server.text_document_implementation_request(position_in(&top, 7, 24)),
+            vec![architecture.clone()]
+        );
+        assert_eq!(
+            server.text_document_implementation_request(position_in(&child, 0, 8)),
+            vec![architecture]
+        );
+        assert_eq!(
+            server.text_document_implementation_request(position_in(&top, 2, 13)),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn inlay_hints_of_configured_kinds_within_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "package pkg is\n  function inc(value : natural) return natural;\nend package;\npackage body pkg is\n  function inc(value : natural) return natural is\n  begin\n    for i in 0 to 1 loop\n    end loop;\n    return inc(value);\n  end function;\nend package body;",
+        );
+
+        let request = |server: &mut VHDLServer<RecordingChannel>, first, last| {
+            server
+                .text_document_inlay_hint_request(InlayHintParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    range: Range {
+                        start: Position {
+                            line: first,
+                            character: 0,
+                        },
+                        end: Position {
+                            line: last,
+                            character: 0,
+                        },
+                    },
+                })
+                .unwrap()
+        };
+        let type_hint = InlayHint {
+            position: Position {
+                line: 6,
+                character: 9,
+            },
+            label: ": integer".to_owned(),
+            kind: 1,
+            padding_left: false,
+            padding_right: false,
+        };
+        let parameter_hint = InlayHint {
+            position: Position {
+                line: 8,
+                character: 15,
+            },
+            label: "value:".to_owned(),
+            kind: 2,
+            padding_left: false,
+            padding_right: true,
+        };
+
+        assert_eq!(request(&mut server, 0, 11), vec![type_hint, parameter_hint]);
+        assert_eq!(request(&mut server, 8, 9).len(), 1);
+
+        let options = serde_json::from_str(r#"{"inlayHints": {"parameterNames": false}}"#).unwrap();
+        server.inlay_hints = InlayHintsConfig::from_initialization_options(Some(&options));
+        assert_eq!(request(&mut server, 0, 11)[0].kind, 1);
+        assert_eq!(request(&mut server, 0, 11).len(), 1);
+    }
+
+    #[test]
+    fn call_hierarchy_of_function() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let pkg = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        let ent = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+
+        open(
+            &mut server,
+            &pkg,
+            "package pkg is\n  function inc(value : natural) return natural;\nend package;",
+        );
+        open(
+            &mut server,
+            &ent,
+            "use work.pkg.all;\nentity ent is\nend entity;\narchitecture rtl of ent is\n  constant width : natural := inc(1);\nbegin\nend architecture;",
+        );
+
+        let items = server
+            .text_document_prepare_call_hierarchy_request(position_in(&ent, 4, 30))
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "inc");
+        assert_eq!(items[0].uri, pkg);
+        assert_eq!(items[0].kind, SymbolKind::Function);
+
+        let item = items.into_iter().next().unwrap();
+        let incoming = server
+            .call_hierarchy_incoming_calls_request(CallHierarchyCallsParams { item })
+            .unwrap();
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].from.name, "rtl");
+        assert_eq!(incoming[0].from.uri, ent);
+        assert_eq!(
+            incoming[0].from_ranges,
+            vec![Range {
+                start: Position {
+                    line: 4,
+                    character: 30
+                },
+                end: Position {
+                    line: 4,
+                    character: 33
+                }
+            }]
+        );
+
+        let outgoing = server
+            .call_hierarchy_outgoing_calls_request(CallHierarchyCallsParams {
+                item: incoming.into_iter().next().unwrap().from,
+            })
+            .unwrap();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].to.name, "inc");
+
+        // Signals and constants have no call hierarchy
+        assert_eq!(
+            server.text_document_prepare_call_hierarchy_request(position_in(&ent, 4, 12)),
+            None
+        );
+    }
+
+    #[test]
+    fn type_hierarchy_of_resolved_subtype() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "package pkg is\n  type std_ulogic is ('U', '0', '1');\n  type std_ulogic_vector is array (natural range <>) of std_ulogic;\n  subtype std_logic is resolved std_ulogic;\n  constant c : std_logic := '0';\nend package;",
+        );
+
+        let items = server
+            .text_document_prepare_type_hierarchy_request(position_in(&uri, 4, 15))
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "std_logic");
+        assert_eq!(items[0].detail, None);
+
+        let item = items.into_iter().next().unwrap();
+        let supertypes = server
+            .type_hierarchy_supertypes_request(TypeHierarchyParams { item })
+            .unwrap();
+        assert_eq!(supertypes.len(), 1);
+        assert_eq!(supertypes[0].name, "std_ulogic");
+        assert_eq!(supertypes[0].detail, Some("unresolved type".to_owned()));
+
+        let subtypes = server
+            .type_hierarchy_subtypes_request(TypeHierarchyParams {
+                item: supertypes.into_iter().next().unwrap(),
+            })
+            .unwrap();
+        let names: Vec<(String, Option<String>)> = subtypes
+            .into_iter()
+            .map(|item| (item.name, item.detail))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (
+                    "std_ulogic_vector".to_owned(),
+                    Some("composite type".to_owned())
+                ),
+                ("std_logic".to_owned(), Some("resolved subtype".to_owned())),
+            ]
+        );
+
+        // Constants have no type hierarchy
+        assert_eq!(
+            server.text_document_prepare_type_hierarchy_request(position_in(&uri, 4, 11)),
+            None
+        );
+    }
+
+    #[test]
+    fn references_with_and_without_declaration() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "architecture rtl of ent is\n  signal foo : bit;\nbegin\n  foo <= not foo;\nend architecture;",
+        );
+
+        let references = |server: &mut VHDLServer<RecordingChannel>, include_declaration| {
+            let params = ReferenceParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: Position {
+                    line: 3,
+                    character: 2,
+                },
+                context: ReferenceContext {
+                    include_declaration,
+                },
+            };
+            server
+                .text_document_references_request(params)
+                .into_iter()
+                .map(|location| (location.range.start.line, location.range.start.character))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(references(&mut server, false), vec![(3, 2), (3, 13)]);
+        assert_eq!(references(&mut server, true), vec![(1, 9), (3, 2), (3, 13)]);
+    }
+
+    #[test]
+    fn deferred_bodies_are_parsed_for_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkg = dir.path().join("pkg.vhd");
+        let ent = dir.path().join("ent.vhd");
+        fs::write(
+            &pkg,
+            "package pkg is\n  constant c : natural := 0;\nend package;",
+        )
+        .unwrap();
+        fs::write(
+            &ent,
+            "use work.pkg.all;
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal s : natural := c;
+begin
+end architecture;",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[libraries]\nlib.files = ['*.vhd']",
+        )
+        .unwrap();
+        let mut server = initialize_with_progress(dir.path(), true);
+        server.settings.defer_bodies = true;
+        server.initialized_notification();
+        let (id, _, _) = server.rpc_channel.requests.borrow_mut().remove(0);
+        server.client_response(
+            serde_json::from_str(&format!(
+                r#"{{"jsonrpc": "2.0", "id": {}, "result": null}}"#,
+                id
+            ))
+            .unwrap(),
+        );
+        let deferred_bodies = |server: &VHDLServer<RecordingChannel>| {
+            let file = server.project.get_file(ent.to_str().unwrap()).unwrap();
+            file.design_file.deferred_bodies.len()
+        };
+        assert_eq!(deferred_bodies(&server), 1);
+
+        let references = server.text_document_references_request(ReferenceParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(&pkg).unwrap(),
+            },
+            position: Position {
+                line: 1,
+                character: 11,
+            },
+            context: ReferenceContext {
+                include_declaration: false,
+            },
+        });
+        assert_eq!(deferred_bodies(&server), 0);
+        assert_eq!(
+            references,
+            vec![Location {
+                uri: Url::from_file_path(&ent).unwrap(),
+                range: Range {
+                    start: Position {
+                        line: 5,
+                        character: 24,
+                    },
+                    end: Position {
+                        line: 5,
+                        character: 25,
+                    },
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn hover_shows_declaration_and_comment() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "architecture rtl of ent is
+  -- The counter
+  signal count : natural := 0;
+begin
+  count <= count + 1;
+end architecture;",
+        );
+
+        let hover = server.text_document_hover_request(position_in(&uri, 4, 3));
+        assert_eq!(
+            hover.map(|hover| hover.contents),
+            Some(HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: "```vhdl\nsignal count : natural := 0;\n```\n\nThe counter".to_owned(),
+            }))
+        );
+        assert_eq!(
+            server.text_document_hover_request(position_in(&uri, 3, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn signature_help_of_call_being_typed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let pkg = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &pkg,
+            "package pkg is
+  -- Scales a value
+  function scale(value : natural; factor : natural := 2) return natural;
+end package;",
+        );
+        open(
+            &mut server,
+            &uri,
+            "use work.pkg.all;
+architecture rtl of ent is
+  constant c : natural := scale(4, ",
+        );
+
+        let help = server
+            .text_document_signature_help_request(position_in(&uri, 2, 35))
+            .unwrap();
+        assert_eq!(help.active_signature, Some(0));
+        assert_eq!(help.active_parameter, Some(1));
+        assert_eq!(help.signatures.len(), 1);
+        let signature = &help.signatures[0];
+        assert_eq!(
+            signature.label,
+            "function scale(value : natural; factor : natural := 2) return natural"
+        );
+        assert_eq!(
+            signature.documentation,
+            Some(Documentation::String("Scales a value".to_owned()))
+        );
+        assert_eq!(
+            signature.parameters.as_ref().unwrap()[1],
+            ParameterInformation {
+                label: ParameterLabel::Simple("factor : natural := 2".to_owned()),
+                documentation: None,
+            }
+        );
+        assert_eq!(
+            server.text_document_signature_help_request(position_in(&uri, 1, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn semantic_tokens_and_delta() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        let code = "architecture rtl of ent is
+  constant width : natural := 8;
+  signal count : natural;
+begin
+  count <= width;
+end architecture;";
+        open(&mut server, &uri, code);
+
+        let text_document = TextDocumentIdentifier { uri: uri.clone() };
+        let tokens = server
+            .text_document_semantic_tokens_full_request(SemanticTokensParams {
+                text_document: text_document.clone(),
+            })
+            .unwrap();
+        // The namespace, readonly variable, variable and the references of variables
+        assert_eq!(
+            tokens.data,
+            vec![
+                0, 13, 3, 0, 1, //
+                1, 11, 5, 4, 3, //
+                1, 9, 5, 4, 1, //
+                2, 2, 5, 4, 0, //
+                0, 9, 5, 4, 2, //
+            ]
+        );
+
+        change(
+            &mut server,
+            &uri,
+            &code.replace("count <= width;", "count <= width;\n  count <= 0;"),
+        );
+        let delta =
+            server.text_document_semantic_tokens_full_delta_request(SemanticTokensDeltaParams {
+                text_document: text_document.clone(),
+                previous_result_id: tokens.result_id.unwrap(),
+            });
+        match delta {
+            Some(SemanticTokensDeltaResult::Delta(delta)) => {
+                assert_eq!(
+                    delta.edits,
+                    vec![SemanticTokensEdit {
+                        start: 25,
+                        delete_count: 0,
+                        data: vec![1, 2, 5, 4, 0],
+                    }]
+                );
+            }
+            other => panic!("Expected a delta, got {:?}", other),
+        }
+
+        // The previous result is not remembered
+        let full =
+            server.text_document_semantic_tokens_full_delta_request(SemanticTokensDeltaParams {
+                text_document,
+                previous_result_id: "unknown".to_owned(),
+            });
+        match full {
+            Some(SemanticTokensDeltaResult::Tokens(tokens)) => assert_eq!(tokens.data.len(), 30),
+            other => panic!("Expected tokens, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nested_and_flat_document_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let uri = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &uri,
+            "entity ent is
+  port (clk : in bit);
+end entity;
+
+architecture rtl of ent is
+begin
+  main : process
+  begin
+  end process;
+end architecture;",
+        );
+        let params = || DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+        };
+        let range = |start_line, start_character, end_line, end_character| Range {
+            start: Position {
+                line: start_line,
+                character: start_character,
+            },
+            end: Position {
+                line: end_line,
+                character: end_character,
+            },
+        };
+
+        let symbols = match server.text_document_document_symbol_request(params()) {
+            DocumentSymbolResponse::Nested(symbols) => symbols,
+            DocumentSymbolResponse::Flat(..) => panic!("Expected nested symbols"),
+        };
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].name, "ent");
+        assert_eq!(symbols[0].kind, SymbolKind::Interface);
+        assert_eq!(symbols[0].range, range(0, 0, 2, 11));
+        assert_eq!(symbols[0].selection_range, range(0, 7, 0, 10));
+        let clk = &symbols[0].children.as_ref().unwrap()[0];
+        assert_eq!(clk.name, "clk");
+        assert_eq!(clk.kind, SymbolKind::Field);
+        let main = &symbols[1].children.as_ref().unwrap()[0];
+        assert_eq!(main.name, "main");
+        assert_eq!(main.kind, SymbolKind::Method);
+        assert_eq!(main.range, range(6, 2, 8, 14));
+
+        server.hierarchical_symbols = false;
+        let symbols = match server.text_document_document_symbol_request(params()) {
+            DocumentSymbolResponse::Flat(symbols) => symbols,
+            DocumentSymbolResponse::Nested(..) => panic!("Expected flat symbols"),
+        };
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|symbol| (symbol.name.as_str(), symbol.container_name.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ent", None),
+                ("clk", Some("ent")),
+                ("rtl", None),
+                ("main", Some("rtl"))
+            ]
+        );
+    }
+
+    fn rename(
+        server: &mut VHDLServer<RecordingChannel>,
+        uri: &Url,
+        line: u64,
+        character: u64,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, String> {
+        server.text_document_rename_request(RenameParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position { line, character },
+            new_name: new_name.to_owned(),
+        })
+    }
+
+    #[test]
+    fn prepare_rename_and_rename_across_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = initialize(dir.path());
+        let pkg = Url::from_file_path(dir.path().join("pkg.vhd")).unwrap();
+        let ent = Url::from_file_path(dir.path().join("ent.vhd")).unwrap();
+        open(
+            &mut server,
+            &pkg,
+            "package pkg is\n  constant width : natural := 8;\n  constant depth : natural := 4;\nend package;",
+        );
+        open(
+            &mut server,
+            &ent,
+            "use work.pkg.all;\nentity ent is\n  generic (g : natural := width);\nend entity;",
+        );
+
+        assert_eq!(
+            server.text_document_prepare_rename_request(position_in(&ent, 2, 27)),
+            Ok(Some(PrepareRenameResponse::RangeWithPlaceholder {
+                range: Range {
+                    start: Position {
+                        line: 2,
+                        character: 26
+                    },
+                    end: Position {
+                        line: 2,
+                        character: 31
+                    }
+                },
+                placeholder: "width".to_owned()
+            }))
+        );
+        assert_eq!(
+            server.text_document_prepare_rename_request(position_in(&ent, 3, 0)),
+            Ok(None)
+        );
+
+        let changes = rename(&mut server, &ent, 2, 27, "bits")
+            .unwrap()
+            .unwrap()
+            .changes
+            .unwrap();
+        let starts = |uri: &Url| {
+            changes[uri]
+                .iter()
+                .map(|edit| {
+                    assert_eq!(edit.new_text, "bits");
+                    (edit.range.start.line, edit.range.start.character)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changes.len(), 2);
+        assert_eq!(starts(&pkg), vec![(1, 11)]);
+        assert_eq!(starts(&ent), vec![(2, 26)]);
+
+        assert_eq!(
+            rename(&mut server, &ent, 2, 27, "Depth"),
+            Err("'depth' is already declared in the same declarative region".to_owned())
+        );
+        assert_eq!(
+            rename(&mut server, &ent, 2, 27, "not valid"),
+            Err("'not valid' is not a valid identifier".to_owned())
+        );
+    }
+
+    #[test]
+    fn exit_code_depends_on_shutdown() {
+        let mut server = VHDLServer::new(RecordingChannel::default());
+        assert_eq!(server.exit_notification(), 1);
+        server.shutdown_request();
+        assert_eq!(server.exit_notification(), 0);
+    }
+}
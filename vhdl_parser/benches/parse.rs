@@ -0,0 +1,140 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Throughput of tokenizing and parsing generated code, and the files of the project
+//! file given by RUST_HDL_BENCH_PROJECT
+//!
+//! The time of parsing a generated file again after an edit in the middle is measured
+//! with and without the incremental parsing of only the changed design unit
+//!
+//! The number of allocations of parsing and the memory of the design files are printed
+//! before the throughput is measured
+//!
+//! cargo bench -p vhdl_parser
+//! RUST_HDL_BENCH_PROJECT=vhdl_ls.toml cargo bench -p vhdl_parser
+
+#[macro_use]
+extern crate criterion;
+extern crate vhdl_parser;
+
+use criterion::{Criterion, Throughput};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vhdl_parser::benchmark::{generated_code, Corpus, GENERATED_UNITS};
+use vhdl_parser::config::Config;
+use vhdl_parser::project::Project;
+use vhdl_parser::source::Source;
+
+/// Counts the allocations and the allocated bytes which are not yet freed
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn print_memory(name: &str, corpus: &Corpus) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+    let design_files = corpus.design_files();
+    println!(
+        "{}/memory: {} allocations, {:.2} MB of design files from {:.2} MB of code",
+        name,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        LIVE_BYTES
+            .load(Ordering::Relaxed)
+            .saturating_sub(live_bytes) as f64
+            / 1e6,
+        corpus.bytes() as f64 / 1e6
+    );
+    drop(design_files);
+}
+
+fn bench_corpus(c: &mut Criterion, name: &str, corpus: &Corpus) {
+    print_memory(name, corpus);
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(corpus.bytes() as u64));
+    group.bench_function("tokenize", |b| b.iter(|| corpus.tokenize()));
+    group.bench_function("parse", |b| b.iter(|| corpus.parse()));
+    group.finish();
+}
+
+fn generated(c: &mut Criterion) {
+    bench_corpus(c, "generated", &Corpus::generated(GENERATED_UNITS));
+}
+
+fn incremental(c: &mut Criterion) {
+    let code = generated_code(GENERATED_UNITS);
+    let package = code
+        .find(&format!("package pkg{} is", GENERATED_UNITS / 2))
+        .unwrap();
+    let offset = package + code[package..].find("done);").unwrap();
+    let edited = format!("{}paused, {}", &code[..offset], &code[offset..]);
+    let sources: Vec<Source> = [&code, &edited]
+        .iter()
+        .map(|contents| Source::from_file_contents("generated.vhd", contents).unwrap())
+        .collect();
+
+    let mut project = Project::new();
+    let mut group = c.benchmark_group("incremental");
+    group.throughput(Throughput::Bytes(code.len() as u64));
+    // Each iteration edits the file which was parsed by the previous iteration
+    let mut sources_cycle = sources.iter().cycle();
+    group.bench_function("reparse", |b| {
+        b.iter(|| {
+            let source = sources_cycle.next().unwrap().clone();
+            project.update_source("lib", source).unwrap()
+        })
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            let source = sources_cycle.next().unwrap().clone();
+            project.remove_file("generated.vhd");
+            project.update_source("lib", source).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn project(c: &mut Criterion) {
+    let file_name = match env::var("RUST_HDL_BENCH_PROJECT") {
+        Ok(file_name) => file_name,
+        Err(_) => return,
+    };
+    let config = Config::read_file_path(Path::new(&file_name))
+        .unwrap_or_else(|err| panic!("Could not read {}: {}", file_name, err));
+    let mut project = Project::new();
+    for (file_name, err) in project.add_config(&config) {
+        eprintln!("Could not add {}: {}", file_name, err);
+    }
+    bench_corpus(c, "project", &Corpus::from_project(&project));
+}
+
+criterion_group!(benches, generated, incremental, project);
+criterion_main!(benches);
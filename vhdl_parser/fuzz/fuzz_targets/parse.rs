@@ -0,0 +1,19 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Parses arbitrary bytes as latin-1 code, where a panic or a stack overflow of the
+//! tokenizer or the parser is a crash of the fuzzer
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate vhdl_parser;
+
+use vhdl_parser::VHDLParser;
+
+fuzz_target!(|data: &[u8]| {
+    VHDLParser::new().parse_str_resilient(data);
+});
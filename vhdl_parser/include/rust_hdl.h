@@ -0,0 +1,84 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this file,
+ * You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ * Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+ */
+
+/* C interface of the VHDL parser and analyzer, link with the vhdl_parser static or
+ * dynamic library built by cargo build --release
+ *
+ * Strings returned are owned by the project and valid until it is analyzed again or
+ * freed. Functions returning int return 0 on success and -1 on failure where
+ * rust_hdl_last_error gives the reason.
+ */
+
+#ifndef RUST_HDL_H
+#define RUST_HDL_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RustHdlProject RustHdlProject;
+
+enum RustHdlSeverity {
+    RUST_HDL_ERROR = 0,
+    RUST_HDL_WARNING = 1,
+    RUST_HDL_HINT = 2
+};
+
+/* A diagnostic of the analysis, code is NULL when the diagnostic has none */
+typedef struct {
+    int severity;
+    const char *file_name;
+    /* The one based line and column of the start */
+    size_t line;
+    size_t column;
+    const char *message;
+    const char *code;
+} RustHdlDiagnostic;
+
+/* A declaration with its kind such as entity, signal or shared_variable */
+typedef struct {
+    const char *name;
+    const char *kind;
+    const char *file_name;
+    /* The one based line and column of the declared name */
+    size_t line;
+    size_t column;
+} RustHdlSymbol;
+
+/* Creates an empty project which must be freed by rust_hdl_project_free */
+RustHdlProject *rust_hdl_project_new(void);
+void rust_hdl_project_free(RustHdlProject *project);
+
+/* Parses the file into the library */
+int rust_hdl_project_add_file(RustHdlProject *project, const char *library_name, const char *file_name);
+
+/* Adds the libraries of a project file such as vhdl_ls.toml */
+int rust_hdl_project_add_config(RustHdlProject *project, const char *file_name);
+
+/* Analyzes the project and returns the number of diagnostics */
+size_t rust_hdl_project_analyze(RustHdlProject *project);
+
+/* The reason of the last failure or NULL when the last call succeeded */
+const char *rust_hdl_last_error(const RustHdlProject *project);
+
+size_t rust_hdl_diagnostic_count(const RustHdlProject *project);
+int rust_hdl_diagnostic(const RustHdlProject *project, size_t index, RustHdlDiagnostic *diagnostic);
+
+size_t rust_hdl_symbol_count(const RustHdlProject *project);
+int rust_hdl_symbol(const RustHdlProject *project, size_t index, RustHdlSymbol *symbol);
+
+/* The index of the symbol declared or denoted by the name at the one based line and
+ * column of the file, -1 when there is no such name */
+ptrdiff_t rust_hdl_symbol_at(const RustHdlProject *project, const char *file_name, size_t line, size_t column);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,319 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use ast::{ContextItem, LibraryUnit, Name};
+use library::{selected_name_parts, symbol_key, DesignRoot, Library, LibraryUnitRef, UnitKey};
+use message::{error, Message};
+use source::WithPos;
+use std::collections::BTreeSet;
+
+extern crate rayon;
+use self::rayon::prelude::*;
+
+/// Analyze the context clauses of all design units, the units are analyzed concurrently
+pub fn analyze(root: &DesignRoot) -> Vec<Message> {
+    let units: Vec<(&Library, &LibraryUnitRef)> = root
+        .libraries()
+        .flat_map(|library| library.units().map(move |unit| (library, unit)))
+        .collect();
+    units
+        .par_iter()
+        .flat_map_iter(|&(library, unit)| analyze_unit(root, library, unit))
+        .collect()
+}
+
+fn analyze_unit(root: &DesignRoot, library: &Library, unit: &LibraryUnitRef) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut analyzer = ContextAnalyzer::new(root, library);
+
+    // LRM 13.4 The context clause of a primary unit also applies to its secondary units
+    if !unit.id.is_primary() {
+        if let Some(primary) = library.get(&unit.id.primary_unit().key) {
+            analyzer.declare_context_clause(&primary.unit.context_clause);
+        }
+    }
+    analyzer.analyze_context_clause(&unit.unit.context_clause, &mut messages);
+
+    if let LibraryUnit::ContextDeclaration(ref context) = unit.unit.library_unit {
+        analyzer.analyze_context_clause(&context.items, &mut messages);
+    }
+    messages
+}
+
+/// Tracks the library names which are visible within a design unit
+struct ContextAnalyzer<'r, 'a: 'r> {
+    root: &'r DesignRoot<'a>,
+    library: &'r Library<'a>,
+    visible_libraries: BTreeSet<String>,
+}
+
+impl<'r, 'a: 'r> ContextAnalyzer<'r, 'a> {
+    fn new(root: &'r DesignRoot<'a>, library: &'r Library<'a>) -> ContextAnalyzer<'r, 'a> {
+        // LRM 13.2 Every design unit is assumed to contain the library clause "library std, work;"
+        let mut visible_libraries = BTreeSet::new();
+        visible_libraries.insert("std".to_owned());
+        visible_libraries.insert("work".to_owned());
+
+        ContextAnalyzer {
+            root,
+            library,
+            visible_libraries,
+        }
+    }
+
+    /// The library a visible library name refers to
+    fn library_name(&self, name: &str) -> String {
+        if name == "work" {
+            self.library.name().to_owned()
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// Make the library names of an already analyzed context clause visible
+    fn declare_context_clause(&mut self, context_clause: &[ContextItem]) {
+        self.analyze_context_clause(context_clause, &mut Vec::new());
+    }
+
+    fn analyze_context_clause(
+        &mut self,
+        context_clause: &[ContextItem],
+        messages: &mut Vec<Message>,
+    ) {
+        for item in context_clause {
+            match item {
+                ContextItem::Library(ref library_clause) => {
+                    for name in library_clause.name_list.iter() {
+                        self.visible_libraries.insert(symbol_key(&name.item));
+                    }
+                }
+                ContextItem::Use(ref use_clause) => {
+                    for name in use_clause.name_list.iter() {
+                        self.analyze_name(name, false, messages);
+                    }
+                }
+                ContextItem::Context(ref context_reference) => {
+                    for name in context_reference.name_list.iter() {
+                        if let Some(unit) = self.analyze_name(name, true, messages) {
+                            // LRM 13.4 A context reference makes the library names of the context declaration visible
+                            if let LibraryUnit::ContextDeclaration(ref context) =
+                                unit.unit.library_unit
+                            {
+                                self.declare_context_clause(&context.items);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check that a name of the form lib.unit refers to a visible library and an existing unit
+    /// Names within libraries outside of the project cannot be checked and are accepted
+    fn analyze_name(
+        &self,
+        name: &WithPos<Name>,
+        is_context_reference: bool,
+        messages: &mut Vec<Message>,
+    ) -> Option<&'r LibraryUnitRef<'a>> {
+        let parts = selected_name_parts(&name.item)?;
+        if parts.len() < 2 {
+            return None;
+        }
+
+        let prefix = symbol_key(parts[0]);
+        if !self.visible_libraries.contains(&prefix) {
+            if self.root.library(&prefix).is_some() {
+                messages.push(
+                    error(
+                        name,
+                        &format!(
+                            "Library '{}' is not visible, missing library clause",
+                            prefix
+                        ),
+                    )
+                    .with_code("missing-library-clause"),
+                );
+            }
+            return None;
+        }
+
+        let library = self.root.library(&self.library_name(&prefix))?;
+        let unit_name = symbol_key(parts[1]);
+        match library.get(&UnitKey::Primary(unit_name.clone())) {
+            Some(unit) => {
+                let is_context =
+                    matches!(unit.unit.library_unit, LibraryUnit::ContextDeclaration(..));
+                if is_context_reference && !is_context {
+                    messages.push(
+                        error(
+                            name,
+                            &format!("'{}' does not denote a context declaration", unit.id),
+                        )
+                        .with_code("not-a-context"),
+                    );
+                    None
+                } else {
+                    Some(unit)
+                }
+            }
+            None => {
+                messages.push(
+                    error(
+                        name,
+                        &format!(
+                            "No primary unit '{}' within library '{}'",
+                            unit_name,
+                            library.name()
+                        ),
+                    )
+                    .with_code("missing-unit"),
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn analyze_messages(project: &TempProject) -> Vec<String> {
+        let files = project.project.files();
+        let root = DesignRoot::new(&files);
+        analyze(&root).into_iter().map(|msg| msg.message).collect()
+    }
+
+    #[test]
+    fn work_and_declared_libraries_are_visible() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib1",
+            "pkg1.vhd",
+            "
+package pkg is
+end package;",
+        );
+        project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+library lib1;
+use lib1.pkg.all;
+use work.pkg.all;
+entity ent is
+end entity;
+
+package pkg is
+end package;",
+        );
+        assert_eq!(analyze_messages(&project), Vec::<String>::new());
+    }
+
+    #[test]
+    fn library_must_be_declared_by_library_clause() {
+        let mut project = TempProject::new();
+        project.add_file("lib1", "pkg.vhd", "package pkg is end package;");
+        project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+use lib1.pkg.all;
+entity ent is
+end entity;",
+        );
+        assert_eq!(
+            analyze_messages(&project),
+            vec!["Library 'lib1' is not visible, missing library clause"]
+        );
+    }
+
+    #[test]
+    fn library_clause_of_primary_unit_is_visible_in_secondary_units() {
+        let mut project = TempProject::new();
+        project.add_file("lib1", "pkg.vhd", "package pkg is end package;");
+        project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+library lib1;
+entity ent is
+end entity;
+
+use lib1.pkg.all;
+architecture rtl of ent is
+begin
+end architecture;",
+        );
+        assert_eq!(analyze_messages(&project), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_unit_within_library() {
+        let mut project = TempProject::new();
+        project.add_file("lib1", "pkg.vhd", "package pkg is end package;");
+        project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+library lib1, ieee;
+use lib1.missing.all;
+use work.pkg.all;
+use ieee.std_logic_1164.all;
+entity ent is
+end entity;",
+        );
+        assert_eq!(
+            analyze_messages(&project),
+            vec![
+                "No primary unit 'missing' within library 'lib1'",
+                "No primary unit 'pkg' within library 'lib2'",
+            ]
+        );
+    }
+
+    #[test]
+    fn context_reference_makes_library_visible() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib1",
+            "ctx.vhd",
+            "
+library lib2;
+context ctx is
+  library lib2;
+  use lib2.pkg.all;
+end context;",
+        );
+        project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+package pkg is end package;
+
+library lib1;
+context lib1.ctx;
+use lib2.pkg.all;
+entity ent is
+end entity;
+
+library lib1;
+context lib1.missing;
+context work.pkg;
+entity ent2 is
+end entity;",
+        );
+        assert_eq!(
+            analyze_messages(&project),
+            vec![
+                "No primary unit 'missing' within library 'lib1'",
+                "'lib2.pkg' does not denote a context declaration",
+            ]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! JSON-RPC 2.0 queries of a project for build systems and tools which are not editors
+//!
+//! Each request and response is one line of JSON such as:
+//!
+//! ```text
+//! --> {"jsonrpc": "2.0", "id": 1, "method": "ports", "params": {"entity": "lib.fifo"}}
+//! <-- {"jsonrpc": "2.0", "id": 1, "result": {"generics": [...], "ports": [...]}}
+//! ```
+//!
+//! The methods are:
+//! - entities: the entities with their library, file and line
+//! - ports: the generics and ports of the entity such as lib.ent, or ent when unique
+//! - compile_order: the files with their library and standard in compile order
+//! - diagnostics: the messages of parsing, analysis and name resolution, only those of
+//!   the file when given
+//! - update_file: parses the file again into its library, or the library given
+//! - shutdown: ends the server
+
+use ast::{InterfaceDeclaration, LibraryUnit, Mode};
+use compile_script::compile_steps;
+use documentation::declaration_text;
+use instance_tree::InstanceHierarchy;
+use message::{Message, Severity};
+use name_resolution::{entity_interface, interface_entity, NamedEntityKind};
+use project::Project;
+use source::SrcPos;
+
+extern crate serde_json;
+use self::serde_json::{json, Value};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A generic or port of an entity
+#[derive(PartialEq, Debug, Serialize)]
+pub struct InterfaceElement {
+    pub name: String,
+    /// The mode of a port such as in or inout
+    pub mode: Option<&'static str>,
+    /// The subtype indication as written such as std_logic_vector(7 downto 0)
+    pub subtype: String,
+    /// The default expression as written
+    pub default: Option<String>,
+}
+
+fn mode_name(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::In => "in",
+        Mode::Out => "out",
+        Mode::InOut => "inout",
+        Mode::Buffer => "buffer",
+        Mode::Linkage => "linkage",
+    }
+}
+
+/// The subtype and default of the declaration text such as a, b : in bit := '0'
+fn subtype_and_default(text: &str) -> (String, Option<String>) {
+    let colon = text
+        .char_indices()
+        .find(|&(i, chr)| chr == ':' && !text[i + 1..].starts_with('='))
+        .map_or(0, |(i, _)| i + 1);
+    let mut rest = text[colon..].trim_start();
+    let lower = rest.to_lowercase();
+    for mode in ["inout", "in", "out", "buffer", "linkage"].iter() {
+        if lower.starts_with(mode)
+            && lower[mode.len()..].starts_with(|chr: char| chr.is_whitespace())
+        {
+            rest = rest[mode.len()..].trim_start();
+            break;
+        }
+    }
+    let subtype_end = rest.find(":=").unwrap_or(rest.len());
+    let default = rest[subtype_end..]
+        .strip_prefix(":=")
+        .map(|default| default.trim().to_owned());
+    (rest[..subtype_end].trim().to_owned(), default)
+}
+
+/// The generics or ports of an interface list as written
+pub fn interface_elements(
+    interface: &[InterfaceDeclaration],
+    kind: NamedEntityKind,
+) -> Vec<InterfaceElement> {
+    let mut elements = Vec::new();
+    for declaration in interface.iter() {
+        let object = match declaration {
+            InterfaceDeclaration::Object(ref object) => object,
+            _ => continue,
+        };
+        let entity = interface_entity(declaration, kind);
+        let (subtype, default) = match declaration_text(&entity) {
+            Some(text) => subtype_and_default(&text.text),
+            None => (String::new(), None),
+        };
+        elements.push(InterfaceElement {
+            name: entity.designator,
+            mode: if kind == NamedEntityKind::Port {
+                Some(mode_name(&object.mode))
+            } else {
+                None
+            },
+            subtype,
+            default,
+        });
+    }
+    elements
+}
+
+fn location(pos: &SrcPos) -> Value {
+    let (line, column) = pos.line_and_column();
+    json!({
+        "file": pos.source.file_name().unwrap_or(""),
+        "line": line,
+        "column": column,
+    })
+}
+
+fn diagnostic(message: &Message) -> Value {
+    let mut value = location(&message.pos);
+    value["severity"] = json!(match message.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Hint => "hint",
+    });
+    value["message"] = json!(message.message);
+    value["code"] = json!(message.code);
+    value
+}
+
+/// An error of a request with its JSON-RPC error code
+struct RequestError {
+    code: i64,
+    message: String,
+}
+
+impl RequestError {
+    fn invalid_params(message: &str) -> RequestError {
+        RequestError {
+            code: INVALID_PARAMS,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// The string parameter of the request with the name
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, RequestError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(string)) => Ok(Some(string)),
+        Some(_) => Err(RequestError::invalid_params(&format!(
+            "{} must be a string",
+            name
+        ))),
+    }
+}
+
+/// Answers the requests of one project which is kept in memory between them
+pub struct AnalysisServer {
+    project: Project,
+    shutdown: bool,
+}
+
+impl AnalysisServer {
+    pub fn new(project: Project) -> AnalysisServer {
+        AnalysisServer {
+            project,
+            shutdown: false,
+        }
+    }
+
+    /// Whether a shutdown request has been answered
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// The response to a line of JSON, None for notifications which have no response
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    &format!("Invalid JSON: {}", err),
+                ));
+            }
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST,
+                    "The request has no method",
+                ));
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.call(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+            Err(err) => error_response(id, err.code, &err.message),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, RequestError> {
+        match method {
+            "entities" => Ok(self.entities()),
+            "ports" => {
+                let name = string_param(params, "entity")?
+                    .ok_or_else(|| RequestError::invalid_params("entity is required"))?;
+                self.ports(name)
+            }
+            "compile_order" => Ok(self.compile_order()),
+            "diagnostics" => Ok(self.diagnostics(string_param(params, "file")?)),
+            "update_file" => {
+                let file_name = string_param(params, "file")?
+                    .ok_or_else(|| RequestError::invalid_params("file is required"))?;
+                let library_name = string_param(params, "library")?;
+                self.update_file(library_name, file_name)
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RequestError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method '{}'", method),
+            }),
+        }
+    }
+
+    fn entities(&self) -> Value {
+        let mut entities = Vec::new();
+        for file in self.project.files() {
+            for design_unit in file.design_file.design_units.iter() {
+                if let LibraryUnit::EntityDeclaration { ref ident, .. } = design_unit.library_unit {
+                    let mut entity = location(&ident.pos);
+                    entity["library"] = json!(file.library_name);
+                    entity["name"] = json!(ident.item.name_utf8());
+                    entities.push(entity);
+                }
+            }
+        }
+        Value::Array(entities)
+    }
+
+    fn ports(&self, name: &str) -> Result<Value, RequestError> {
+        let files = self.project.files();
+        let hierarchy = InstanceHierarchy::new(&files);
+        let library_unit = hierarchy
+            .top_unit(name)
+            .and_then(|unit_id| hierarchy.entity(&unit_id))
+            .ok_or_else(|| RequestError::invalid_params(&format!("No entity '{}'", name)))?;
+        let (generics, ports) = entity_interface(library_unit);
+        Ok(json!({
+            "generics": interface_elements(generics, NamedEntityKind::Generic),
+            "ports": interface_elements(ports, NamedEntityKind::Port),
+        }))
+    }
+
+    fn compile_order(&self) -> Value {
+        Value::Array(
+            compile_steps(&self.project)
+                .iter()
+                .map(|step| {
+                    json!({
+                        "library": step.library_name,
+                        "file": step.file_name,
+                        "standard": step.standard.version_string(),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    fn diagnostics(&self, file_name: Option<&str>) -> Value {
+        let mut messages = self.project.messages();
+        messages.extend(self.project.analyze());
+        messages.extend(self.project.resolve().messages().iter().cloned());
+        messages
+            .retain(|message| file_name.is_none() || message.pos.source.file_name() == file_name);
+        messages.sort_by_key(|message| {
+            (
+                message.pos.source.file_name().map(str::to_owned),
+                message.pos.start,
+            )
+        });
+        Value::Array(messages.iter().map(diagnostic).collect())
+    }
+
+    fn update_file(
+        &mut self,
+        library_name: Option<&str>,
+        file_name: &str,
+    ) -> Result<Value, RequestError> {
+        let library_name = library_name
+            .map(str::to_owned)
+            .or_else(|| {
+                self.project
+                    .get_file(file_name)
+                    .map(|file| file.library_name.clone())
+            })
+            .ok_or_else(|| {
+                RequestError::invalid_params(&format!(
+                    "{} is not within the project and no library is given",
+                    file_name
+                ))
+            })?;
+        self.project
+            .update_file(&library_name, file_name)
+            .map(|()| Value::Null)
+            .map_err(|err| {
+                RequestError::invalid_params(&format!("Could not read {}: {}", file_name, err))
+            })
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn request(server: &mut AnalysisServer, request: Value) -> Value {
+        serde_json::from_str(&server.handle(&request.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn project_queries() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "fifo.vhd",
+            "entity fifo is
+  generic (width : natural := 8);
+  port (
+    clk : in bit;
+    data : inout bit_vector(width - 1 downto 0) := (others => '0'));
+end entity;
+
+entity broken
+end entity;
+",
+        );
+        let mut server = AnalysisServer::new(project.project);
+
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "entities"}),
+        );
+        assert_eq!(
+            response,
+            json!({"jsonrpc": "2.0", "id": 1, "result": [
+                {"library": "lib", "name": "fifo", "file": file_name, "line": 1, "column": 8}
+            ]})
+        );
+
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "ports", "params": {"entity": "fifo"}}),
+        );
+        assert_eq!(
+            response["result"],
+            json!({
+                "generics": [
+                    {"name": "width", "mode": null, "subtype": "natural", "default": "8"}
+                ],
+                "ports": [
+                    {"name": "clk", "mode": "in", "subtype": "bit", "default": null},
+                    {
+                        "name": "data",
+                        "mode": "inout",
+                        "subtype": "bit_vector(width - 1 downto 0)",
+                        "default": "(others => '0')"
+                    }
+                ]
+            })
+        );
+
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "compile_order"}),
+        );
+        assert_eq!(
+            response["result"],
+            json!([{"library": "lib", "file": file_name, "standard": "2008"}])
+        );
+
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 4, "method": "diagnostics"}),
+        );
+        assert_eq!(
+            response["result"],
+            json!([{
+                "file": file_name,
+                "line": 8,
+                "column": 1,
+                "severity": "error",
+                "message": "Failed to parse entity declaration",
+                "code": null
+            }])
+        );
+    }
+
+    #[test]
+    fn errors_and_notifications() {
+        let mut server = AnalysisServer::new(Project::new());
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "ports", "params": {"entity": "missing"}}),
+        );
+        assert_eq!(
+            response["error"],
+            json!({"code": INVALID_PARAMS, "message": "No entity 'missing'"})
+        );
+        let response = request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "unknown"}),
+        );
+        assert_eq!(response["error"]["code"], json!(METHOD_NOT_FOUND));
+        let response: Value = serde_json::from_str(&server.handle("{").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], json!(PARSE_ERROR));
+
+        assert_eq!(
+            server.handle(&json!({"jsonrpc": "2.0", "method": "entities"}).to_string()),
+            None
+        );
+        assert!(!server.is_shutdown());
+        request(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+        );
+        assert!(server.is_shutdown());
+    }
+}
@@ -4,12 +4,20 @@
 //
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
+//! The abstract syntax tree of a design file
+//!
+//! The declarations of an identifier list share their subtype indication and expressions
+//! through an Arc when parsed, design files loaded from the parse cache or moved by an
+//! incremental reparse have a copy for each declaration as described in the cache module
+
 use latin_1::Latin1String;
-use source::WithPos;
+use smallvec::SmallVec;
+use source::{SrcPos, WithPos};
+use std::sync::Arc;
 use symbol_table::Symbol;
 
 /// LRM 15.8 Bit string literals
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum BaseSpecifier {
     B,
     O,
@@ -23,7 +31,7 @@ pub enum BaseSpecifier {
     D,
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Unary {
     And,
     Or,
@@ -38,7 +46,7 @@ pub enum Unary {
     QueQue, // ?? conditional operator
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Binary {
     And,
     Or,
@@ -80,7 +88,7 @@ pub enum Binary {
 }
 
 /// LRM 8.6 Attribute names
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AttributeName {
     pub name: WithPos<Name>,
     pub signature: Option<Signature>,
@@ -89,7 +97,7 @@ pub struct AttributeName {
 }
 
 /// LRM 8. Names
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Name {
     Simple(Symbol),
     CharacterLiteral(u8),
@@ -97,7 +105,7 @@ pub enum Name {
     All,
     Selected(Box<WithPos<Name>>, Box<WithPos<Name>>),
     Indexed(Box<WithPos<Name>>, Vec<WithPos<Expression>>),
-    Slice(Box<WithPos<Name>>, DiscreteRange),
+    Slice(Box<WithPos<Name>>, Box<DiscreteRange>),
     Attribute(Box<AttributeName>),
     /// LRM 9.3.4 Function calls
     FunctionCall(Box<FunctionCall>),
@@ -105,50 +113,53 @@ pub enum Name {
 }
 
 /// LRM 9.3.4 Function calls
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub name: WithPos<Name>,
-    pub parameters: Vec<AssociationElement>,
+    pub parameters: SmallVec<[AssociationElement; 1]>,
 }
 
 /// LRM 9.3.3 Aggregates
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Choice {
     Expression(WithPos<Expression>),
     DiscreteRange(DiscreteRange),
     Others,
 }
 
+/// Most aggregate and case alternatives have a single choice which is stored inline
+pub type Choices = SmallVec<[Choice; 1]>;
+
 /// LRM 9.3.3 Aggregates
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ElementAssociation {
     Positional(WithPos<Expression>),
-    Named(Vec<Choice>, WithPos<Expression>),
+    Named(Choices, WithPos<Expression>),
 }
 
 /// LRM 6.5.7 Association Lists
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ActualPart {
     Expression(Expression),
     Open,
 }
 
 /// LRM 6.5.7 Association Lists
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AssociationElement {
     pub formal: Option<WithPos<Name>>,
     pub actual: WithPos<ActualPart>,
 }
 
 /// LRM 15.5 Abstract literals
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum AbstractLiteral {
     Integer(i64),
     Real(f64),
 }
 
 /// LRM 15.8 Bit string literals
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct BitString {
     pub length: Option<u32>,
     pub base: BaseSpecifier,
@@ -156,7 +167,7 @@ pub struct BitString {
 }
 
 /// LRM 9.3.2 Literals
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     String(Latin1String),
     BitString(BitString),
@@ -167,21 +178,21 @@ pub enum Literal {
 }
 
 /// LRM 9.3.7 Allocators
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Allocator {
     Qualified(QualifiedExpression),
     Subtype(SubtypeIndication),
 }
 
 /// LRM 9.3.5 Qualified expressions
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct QualifiedExpression {
     pub name: Box<WithPos<Name>>,
     pub expr: Box<WithPos<Expression>>,
 }
 
 /// LRM 9. Expressions
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Expression {
     Binary(Binary, Box<WithPos<Expression>>, Box<WithPos<Expression>>),
     Unary(Unary, Box<WithPos<Expression>>),
@@ -199,12 +210,12 @@ pub enum Expression {
     Literal(Literal),
 
     /// LRM 9.3.7 Allocators
-    New(WithPos<Allocator>),
+    New(Box<WithPos<Allocator>>),
 }
 
 pub type Ident = WithPos<Symbol>;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Ascending,
     Descending,
@@ -216,33 +227,33 @@ pub enum Direction {
 ///     range_attribute_name
 ///   | simple_expression direction simple_expression
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum DiscreteRange {
     Discrete(SelectedName, Option<Range>),
     Range(Range),
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct RangeConstraint {
     pub direction: Direction,
     pub left_expr: Box<WithPos<Expression>>,
     pub right_expr: Box<WithPos<Expression>>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Range {
     Range(RangeConstraint),
     Attribute(Box<AttributeName>),
 }
 
 /// LRM: record_element_constraint
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ElementConstraint {
     pub ident: Ident,
     pub constraint: Box<SubtypeConstraint>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum SubtypeConstraint {
     Range(Range),
     Array(Vec<DiscreteRange>, Option<Box<SubtypeConstraint>>),
@@ -250,17 +261,18 @@ pub enum SubtypeConstraint {
 }
 
 /// LRM 8. Names
-pub type SelectedName = Vec<Ident>;
+/// Most selected names are a single type mark such as std_logic and are stored inline
+pub type SelectedName = SmallVec<[Ident; 1]>;
 
 /// LRM 6.3 Subtype declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct RecordElementResolution {
     pub ident: Ident,
     pub resolution: Box<ResolutionIndication>,
 }
 
 /// LRM 6.3 Subtype declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ResolutionIndication {
     FunctionName(SelectedName),
     ArrayElement(SelectedName),
@@ -269,7 +281,7 @@ pub enum ResolutionIndication {
 }
 
 /// LRM 6.3 Subtype declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct SubtypeIndication {
     pub resolution: ResolutionIndication,
     pub type_mark: SelectedName,
@@ -277,7 +289,7 @@ pub struct SubtypeIndication {
 }
 
 /// LRM 5.3 Array Types
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ArrayIndex {
     /// Unbounded
     /// {identifier} range <>
@@ -288,20 +300,20 @@ pub enum ArrayIndex {
 }
 
 /// LRM 5.3.3 Record types
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ElementDeclaration {
     pub ident: Ident,
     pub subtype: SubtypeIndication,
 }
 
 /// LRM 5.6.2 Protected type declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ProtectedTypeDeclarativeItem {
     Subprogram(SubprogramDeclaration),
 }
 
 /// LRM 6.6 Alias declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AliasDeclaration {
     pub designator: WithPos<Designator>,
     pub subtype_indication: Option<SubtypeIndication>,
@@ -310,21 +322,21 @@ pub struct AliasDeclaration {
 }
 
 /// LRM 6.7 Attribute declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AttributeDeclaration {
     pub ident: Ident,
     pub type_mark: SelectedName,
 }
 
 /// LRM 7.2 Attribute specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct EntityTag {
     pub designator: WithPos<Designator>,
     pub signature: Option<Signature>,
 }
 
 /// LRM 7.2 Attribute specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum EntityName {
     Name(EntityTag),
     All,
@@ -333,7 +345,7 @@ pub enum EntityName {
 
 /// LRM 7.2 Attribute specification
 // @TODO there are more classes
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum EntityClass {
     Entity,
     Architecture,
@@ -346,35 +358,36 @@ pub enum EntityClass {
 }
 
 /// LRM 7.2 Attribute specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AttributeSpecification {
     pub ident: Ident,
     pub entity_name: EntityName,
     pub entity_class: EntityClass,
-    pub expr: WithPos<Expression>,
+    /// Shared by the specifications of all entity names of the list
+    pub expr: Arc<WithPos<Expression>>,
 }
 
 /// LRM 7.2 Attribute specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Attribute {
     Specification(AttributeSpecification),
     Declaration(AttributeDeclaration),
 }
 
 /// LRM 5.6.2 Protected type declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ProtectedTypeDeclaration {
     pub items: Vec<ProtectedTypeDeclarativeItem>,
 }
 
 /// LRM 5.6.3 Protected type bodies
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ProtectedTypeBody {
     pub decl: Vec<Declaration>,
 }
 
 /// LRM 5.4.2 Physical type declaration
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct PhysicalTypeDeclaration {
     pub range: Range,
     pub primary_unit: Ident,
@@ -382,18 +395,18 @@ pub struct PhysicalTypeDeclaration {
 }
 
 /// LRM 5.2.2 Enumeration types
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum EnumerationLiteral {
     Identifier(Symbol),
     Character(u8),
 }
 
 /// LRM 5 Types
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum TypeDefinition {
     /// LRM 5.2 Scalar Types
     /// LRM 5.2.2 Enumeration types
-    Enumeration(Vec<EnumerationLiteral>),
+    Enumeration(Vec<WithPos<EnumerationLiteral>>),
     /// LRM 5.2.3 Integer types
     Integer(Range),
     /// LRM 5.2.4 Physical types
@@ -418,14 +431,14 @@ pub enum TypeDefinition {
 }
 
 /// LRM 6.2 Type declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct TypeDeclaration {
     pub ident: Ident,
     pub def: TypeDefinition,
 }
 
 /// LRM 6.4.2 Object Declarations
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ObjectClass {
     Signal,
     // @TODO signal_kind
@@ -434,23 +447,25 @@ pub enum ObjectClass {
     SharedVariable,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+/// The subtype and expression are shared by the declarations of an identifier list
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectDeclaration {
     pub class: ObjectClass,
     pub ident: Ident,
-    pub subtype_indication: SubtypeIndication,
-    pub expression: Option<WithPos<Expression>>,
+    pub subtype_indication: Arc<SubtypeIndication>,
+    pub expression: Option<Arc<WithPos<Expression>>>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+/// The subtype and expressions are shared by the declarations of an identifier list
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct FileDeclaration {
     pub ident: Ident,
-    pub subtype_indication: SubtypeIndication,
-    pub open_info: Option<WithPos<Expression>>,
-    pub file_name: Option<WithPos<Expression>>,
+    pub subtype_indication: Arc<SubtypeIndication>,
+    pub open_info: Option<Arc<WithPos<Expression>>>,
+    pub file_name: Option<Arc<WithPos<Expression>>>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Designator {
     Identifier(Symbol),
     OperatorSymbol(Latin1String),
@@ -458,14 +473,14 @@ pub enum Designator {
 }
 
 /// LRM 4.2 Subprogram declaration
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ProcedureSpecification {
     pub designator: WithPos<Designator>,
     pub parameter_list: Vec<InterfaceDeclaration>,
 }
 
 /// LRM 4.2 Subprogram declaration
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionSpecification {
     pub pure: bool,
     pub designator: WithPos<Designator>,
@@ -474,7 +489,7 @@ pub struct FunctionSpecification {
 }
 
 /// LRM 4.3 Subprogram bodies
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct SubprogramBody {
     pub specification: SubprogramDeclaration,
     pub declarations: Vec<Declaration>,
@@ -482,41 +497,43 @@ pub struct SubprogramBody {
 }
 
 /// LRM 4.5.3 Signatures
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Signature {
     Function(Vec<SelectedName>, SelectedName),
     Procedure(Vec<SelectedName>),
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum SubprogramDeclaration {
     Procedure(ProcedureSpecification),
     Function(FunctionSpecification),
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct InterfaceFileDeclaration {
     pub ident: Ident,
-    pub subtype_indication: SubtypeIndication,
+    /// Shared by the declarations of an identifier list
+    pub subtype_indication: Arc<SubtypeIndication>,
 }
 
 /// LRM 6.5.2 Interface object declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct InterfaceObjectDeclaration {
     pub class: ObjectClass,
     pub ident: Ident,
     pub mode: Mode,
-    pub subtype_indication: SubtypeIndication,
-    pub expression: Option<WithPos<Expression>>,
+    /// The subtype and expression are shared by the declarations of an identifier list
+    pub subtype_indication: Arc<SubtypeIndication>,
+    pub expression: Option<Arc<WithPos<Expression>>>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum SubprogramDefault {
     Name(SelectedName),
     Box,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum InterfaceDeclaration {
     Object(InterfaceObjectDeclaration),
     File(InterfaceFileDeclaration),
@@ -525,12 +542,12 @@ pub enum InterfaceDeclaration {
     Subprogram(SubprogramDeclaration, Option<SubprogramDefault>),
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct GenericClause {
     pub generic_list: Vec<InterfaceDeclaration>,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Mode {
     In,
     Out,
@@ -539,20 +556,20 @@ pub enum Mode {
     Linkage,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct PortClause {
     pub port_list: Vec<InterfaceDeclaration>,
 }
 
 /// LRM 6.8 Component declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentDeclaration {
     pub ident: Ident,
     pub generic_list: Vec<InterfaceDeclaration>,
     pub port_list: Vec<InterfaceDeclaration>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Declaration {
     Object(ObjectDeclaration),
     File(FileDeclaration),
@@ -567,7 +584,7 @@ pub enum Declaration {
 }
 
 /// LRM 10.2 Wait statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct WaitStatement {
     pub sensitivity_clause: Vec<WithPos<Name>>,
     pub condition_clause: Option<WithPos<Expression>>,
@@ -575,7 +592,7 @@ pub struct WaitStatement {
 }
 
 /// LRM 10.3 Assertion statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct AssertStatement {
     pub condition: WithPos<Expression>,
     pub report: Option<WithPos<Expression>>,
@@ -583,42 +600,42 @@ pub struct AssertStatement {
 }
 
 /// LRM 10.4 Report statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ReportStatement {
     pub report: WithPos<Expression>,
     pub severity: Option<WithPos<Expression>>,
 }
 
 /// LRM 10.5 Signal assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Target {
     Name(Name),
     Aggregate(Vec<ElementAssociation>),
 }
 
 /// LRM 10.5 Signal assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct WaveformElement {
     pub value: WithPos<Expression>,
     pub after: Option<WithPos<Expression>>,
 }
 
 /// LRM 10.5 Signal assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Waveform {
     Elements(Vec<WaveformElement>),
     Unaffected,
 }
 
 /// LRM 10.5 Signal assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum DelayMechanism {
     Transport,
     Inertial { reject: Option<WithPos<Expression>> },
 }
 
 /// LRM 10.5 Signal assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct SignalAssignment {
     pub target: WithPos<Target>,
     pub delay_mechanism: Option<DelayMechanism>,
@@ -626,7 +643,7 @@ pub struct SignalAssignment {
 }
 
 /// LRM 10.6 Variable assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct VariableAssignment {
     pub target: WithPos<Target>,
     pub rhs: AssignmentRightHand<WithPos<Expression>>,
@@ -634,20 +651,20 @@ pub struct VariableAssignment {
 
 /// LRM 10.5 Signal assignment statement
 /// LRM 10.6 Variable assignment statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum AssignmentRightHand<T> {
     Simple(T),
     Conditional(Conditionals<T>),
     Selected(Selection<T>),
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Conditional<T> {
     pub condition: WithPos<Expression>,
     pub item: T,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Conditionals<T> {
     pub conditionals: Vec<Conditional<T>>,
     pub else_item: Option<T>,
@@ -659,13 +676,13 @@ pub type ConditionalExpressions = Conditionals<WithPos<Expression>>;
 /// LRM 10.8 If statement
 pub type IfStatement = Conditionals<Vec<LabeledSequentialStatement>>;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Alternative<T> {
-    pub choices: Vec<Choice>,
+    pub choices: Choices,
     pub item: T,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Selection<T> {
     pub expression: WithPos<Expression>,
     pub alternatives: Vec<Alternative<T>>,
@@ -675,41 +692,41 @@ pub struct Selection<T> {
 pub type CaseStatement = Selection<Vec<LabeledSequentialStatement>>;
 
 /// LRM 10.10 Loop statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum IterationScheme {
     While(WithPos<Expression>),
     For(Ident, DiscreteRange),
 }
 
 /// LRM 10.10 Loop statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct LoopStatement {
     pub iteration_scheme: Option<IterationScheme>,
     pub statements: Vec<LabeledSequentialStatement>,
 }
 
 /// LRM 10.11 Next statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct NextStatement {
     pub loop_label: Option<Ident>,
     pub condition: Option<WithPos<Expression>>,
 }
 
 /// LRM 10.12 Exit statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ExitStatement {
     pub loop_label: Option<Ident>,
     pub condition: Option<WithPos<Expression>>,
 }
 
 /// LRM 10.13 Return statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ReturnStatement {
     pub expression: Option<WithPos<Expression>>,
 }
 
 /// LRM 10. Sequential statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum SequentialStatement {
     Wait(WaitStatement),
     Assert(AssertStatement),
@@ -727,14 +744,14 @@ pub enum SequentialStatement {
 }
 
 /// LRM 10. Sequential statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct LabeledSequentialStatement {
     pub label: Option<Ident>,
     pub statement: SequentialStatement,
 }
 
 /// LRM 11.2 Block statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct BlockStatement {
     pub guard_condition: Option<WithPos<Expression>>,
     pub decl: Vec<Declaration>,
@@ -742,7 +759,7 @@ pub struct BlockStatement {
 }
 
 /// LRM 11.3 Process statement
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessStatement {
     pub postponed: bool,
     pub sensitivity_list: Vec<WithPos<Name>>,
@@ -751,21 +768,21 @@ pub struct ProcessStatement {
 }
 
 /// LRM 11.4 Concurrent procedure call statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ConcurrentProcedureCall {
     pub postponed: bool,
     pub call: FunctionCall,
 }
 
 /// LRM 11.5 Concurrent assertion statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ConcurrentAssertStatement {
     pub postponed: bool,
     pub statement: AssertStatement,
 }
 
 /// 11.6 Concurrent signal assignment statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ConcurrentSignalAssignment {
     pub postponed: bool,
     pub guarded: bool,
@@ -775,7 +792,7 @@ pub struct ConcurrentSignalAssignment {
 }
 
 /// 11.7 Component instantiation statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum InstantiatedUnit {
     Component(SelectedName),
     Entity(SelectedName, Option<Ident>),
@@ -783,7 +800,7 @@ pub enum InstantiatedUnit {
 }
 
 /// 11.7 Component instantiation statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct InstantiationStatement {
     pub unit: InstantiatedUnit,
     pub generic_map: Vec<AssociationElement>,
@@ -791,7 +808,7 @@ pub struct InstantiationStatement {
 }
 
 /// 11.8 Generate statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateBody {
     pub alternative_label: Option<Ident>,
     pub decl: Option<Vec<Declaration>>,
@@ -799,7 +816,7 @@ pub struct GenerateBody {
 }
 
 /// 11.8 Generate statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ForGenerateStatement {
     pub index_name: Ident,
     pub discrete_range: DiscreteRange,
@@ -810,7 +827,7 @@ pub struct ForGenerateStatement {
 pub type IfGenerateStatement = Conditionals<GenerateBody>;
 
 /// LRM 11. Concurrent statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ConcurrentStatement {
     ProcedureCall(ConcurrentProcedureCall),
     Block(BlockStatement),
@@ -823,32 +840,32 @@ pub enum ConcurrentStatement {
 }
 
 /// LRM 11. Concurrent statements
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct LabeledConcurrentStatement {
     pub label: Option<Ident>,
     pub statement: ConcurrentStatement,
 }
 
 /// LRM 13. Design units and their analysis
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct LibraryClause {
     pub name_list: Vec<Ident>,
 }
 
 /// LRM 12.4. Use clauses
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct UseClause {
     pub name_list: Vec<WithPos<Name>>,
 }
 
 /// LRM 13.4 Context clauses
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ContextReference {
     pub name_list: Vec<WithPos<Name>>,
 }
 
 /// LRM 13.4 Context clauses
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ContextItem {
     Use(UseClause),
     Library(LibraryClause),
@@ -856,14 +873,14 @@ pub enum ContextItem {
 }
 
 /// LRM 13.4 Context clauses
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ContextDeclaration {
     pub ident: Ident,
     pub items: Vec<ContextItem>,
 }
 
 /// LRM 4.9 Package instatiation declaration
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct PackageInstantiation {
     pub ident: Ident,
     pub package_name: SelectedName,
@@ -871,7 +888,7 @@ pub struct PackageInstantiation {
 }
 
 /// LRM 7.3 Configuration specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum InstantiationList {
     Labels(Vec<Ident>),
     Others,
@@ -879,7 +896,7 @@ pub enum InstantiationList {
 }
 
 /// LRM 7.3.2 Binding indication
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum EntityAspect {
     Entity(SelectedName, Option<Ident>),
     Configuration(SelectedName),
@@ -887,7 +904,7 @@ pub enum EntityAspect {
 }
 
 /// LRM 7.3.2 Binding indication
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct BindingIndication {
     pub entity_aspect: Option<EntityAspect>,
     pub generic_map: Option<Vec<AssociationElement>>,
@@ -895,21 +912,21 @@ pub struct BindingIndication {
 }
 
 /// LRM 7.3 Configuration specification
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentSpecification {
     pub instantiation_list: InstantiationList,
     pub component_name: SelectedName,
 }
 
 /// LRM 3.4 Configuration declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ConfigurationDeclarativeItem {
     Use(UseClause),
     // @TODO attribute
     // @TODO group
 }
 /// LRM 3.4 Configuration declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentConfiguration {
     pub spec: ComponentSpecification,
     pub bind_ind: Option<BindingIndication>,
@@ -917,14 +934,14 @@ pub struct ComponentConfiguration {
 }
 
 /// LRM 3.4 Configuration declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ConfigurationItem {
     Block(BlockConfiguration),
     Component(ComponentConfiguration),
 }
 
 /// LRM 3.4 Configuration declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct BlockConfiguration {
     pub block_spec: WithPos<Name>,
     pub use_clauses: Vec<UseClause>,
@@ -932,7 +949,7 @@ pub struct BlockConfiguration {
 }
 
 /// LRM 3.4 Configuration declarations
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigurationDeclaration {
     pub ident: Ident,
     pub entity_name: SelectedName,
@@ -941,7 +958,7 @@ pub struct ConfigurationDeclaration {
 }
 
 /// LRM 13.1 Design units
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum LibraryUnit {
     /// LRM 3.2 Entity declaration
     EntityDeclaration {
@@ -953,8 +970,9 @@ pub enum LibraryUnit {
     /// LRM 3.3 Architecture bodies
     ArchitectureBody {
         ident: Ident,
-        entity_name: Symbol,
+        entity_name: Ident,
         decl: Vec<Declaration>,
+        statements: Vec<LabeledConcurrentStatement>,
     },
 
     /// LRM 3.4 Configuration declarations
@@ -967,7 +985,10 @@ pub enum LibraryUnit {
     },
 
     /// LRM 4.8 Package bodies
-    PackageBody { ident: Ident },
+    PackageBody {
+        ident: Ident,
+        decl: Vec<Declaration>,
+    },
 
     /// LRM 4.9 Package instatiation declaration
     PackageInstance(PackageInstantiation),
@@ -977,13 +998,28 @@ pub enum LibraryUnit {
 }
 
 /// LRM 13.1 Design units
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct DesignUnit {
     pub context_clause: Vec<ContextItem>,
     pub library_unit: LibraryUnit,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+/// The contents of an architecture or package body which were skipped when parsing
+/// only the interface of the design units
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredBody {
+    /// The index of the design unit within the design file
+    pub design_unit: usize,
+    /// From the first token after the is keyword until the end of the design unit
+    pub pos: SrcPos,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct DesignFile {
     pub design_units: Vec<DesignUnit>,
+    /// Parsed on demand by VHDLParser::parse_deferred_bodies
+    pub deferred_bodies: Vec<DeferredBody>,
+    /// The position after the last token of each design unit, where the parsing of the
+    /// next design unit starts, such that an edit only needs the changed units parsed again
+    pub design_unit_ends: Vec<usize>,
 }
@@ -11,6 +11,7 @@ use ast::{
 use expression::parse_expression;
 use message::ParseResult;
 use names::parse_selected_name;
+use std::sync::Arc;
 use subprogram::parse_signature;
 use tokenizer::Kind::*;
 use tokenstream::TokenStream;
@@ -96,7 +97,7 @@ pub fn parse_attribute(stream: &mut TokenStream) -> ParseResult<Vec<Attribute>>
             stream.expect_kind(Colon)?;
             let entity_class = parse_entity_class(stream)?;
             stream.expect_kind(Is)?;
-            let expr = parse_expression(stream)?;
+            let expr = Arc::new(parse_expression(stream)?);
             stream.expect_kind(SemiColon)?;
 
             let attributes = entity_names
@@ -104,7 +105,7 @@ pub fn parse_attribute(stream: &mut TokenStream) -> ParseResult<Vec<Attribute>>
                 .map(|entity_name| {
                     Attribute::Specification(AttributeSpecification {
                         ident: ident.clone(),
-                        entity_name: entity_name.clone(),
+                        entity_name,
                         entity_class: entity_class,
                         expr: expr.clone(),
                     })
@@ -148,7 +149,7 @@ mod tests {
                     signature: None
                 }),
                 entity_class: EntityClass::Signal,
-                expr: util.expr("0+1")
+                expr: Arc::new(util.expr("0+1"))
             })]
         )
     }
@@ -169,7 +170,7 @@ mod tests {
                         signature: None
                     }),
                     entity_class: EntityClass::Signal,
-                    expr: util.expr("0+1")
+                    expr: Arc::new(util.expr("0+1"))
                 }),
                 Attribute::Specification(AttributeSpecification {
                     ident: util.ident("attr_name"),
@@ -178,10 +179,16 @@ mod tests {
                         signature: None
                     }),
                     entity_class: EntityClass::Signal,
-                    expr: util.expr("0+1")
+                    expr: Arc::new(util.expr("0+1"))
                 })
             ]
-        )
+        );
+        match (&result[0], &result[1]) {
+            (Attribute::Specification(ref first), Attribute::Specification(ref second)) => {
+                assert!(Arc::ptr_eq(&first.expr, &second.expr));
+            }
+            _ => panic!("Expected attribute specifications"),
+        }
     }
 
     #[test]
@@ -196,7 +203,7 @@ mod tests {
                 ident: util.ident("attr_name"),
                 entity_name: EntityName::All,
                 entity_class: EntityClass::Signal,
-                expr: util.expr("0+1")
+                expr: Arc::new(util.expr("0+1"))
             })]
         )
     }
@@ -213,7 +220,7 @@ mod tests {
                 ident: util.ident("attr_name"),
                 entity_name: EntityName::Others,
                 entity_class: EntityClass::Signal,
-                expr: util.expr("0+1")
+                expr: Arc::new(util.expr("0+1"))
             })]
         )
     }
@@ -233,7 +240,7 @@ mod tests {
                     signature: Some(util.signature("[return natural]"))
                 }),
                 entity_class: EntityClass::Signal,
-                expr: util.expr("0+1")
+                expr: Arc::new(util.expr("0+1"))
             })]
         )
     }
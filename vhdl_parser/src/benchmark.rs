@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Measures the throughput of tokenizing and parsing in MB/s on the files of a project
+//! or on generated code, used by the benchmarks and the bench command
+
+use ast::DesignFile;
+use latin_1::Latin1String;
+use project::Project;
+use source::Source;
+use standard::VHDLStandard;
+use std::sync::Arc;
+use std::time::Instant;
+use symbol_table::SymbolTable;
+use tokenizer::Tokenizer;
+use {ParseOptions, VHDLParser};
+
+/// The parsing throughput on a single core which the parser should reach on the
+/// generated corpus in a release build
+pub const TARGET_MB_PER_SECOND: f64 = 25.0;
+
+/// The number of packages, entities and architectures of the generated corpus
+pub const GENERATED_UNITS: usize = 200;
+
+/// The sources to measure with their contents in memory so that reading files is not measured
+pub struct Corpus {
+    sources: Vec<(Source, VHDLStandard)>,
+    bytes: usize,
+}
+
+impl Corpus {
+    /// The files of the project with the standard of their library
+    pub fn from_project(project: &Project) -> Corpus {
+        let mut corpus = Corpus {
+            sources: Vec::new(),
+            bytes: 0,
+        };
+        for file in project.files() {
+            if let Ok(contents) = file.source.contents() {
+                let source = Source::from_file_latin1(file.file_name(), contents);
+                corpus.add(source, project.standard(file));
+            }
+        }
+        corpus
+    }
+
+    /// A single file of generated code with the given number of packages, entities and architectures
+    pub fn generated(units: usize) -> Corpus {
+        let mut corpus = Corpus {
+            sources: Vec::new(),
+            bytes: 0,
+        };
+        let code = Latin1String::from_vec(generated_code(units).into_bytes());
+        corpus.add(
+            Source::from_file_latin1("generated.vhd", Arc::new(code)),
+            VHDLStandard::default(),
+        );
+        corpus
+    }
+
+    fn add(&mut self, source: Source, standard: VHDLStandard) {
+        if let Ok(contents) = source.contents() {
+            self.bytes += contents.len();
+            self.sources.push((source, standard));
+        }
+    }
+
+    pub fn files(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Tokenizes every file and returns the number of tokens
+    pub fn tokenize(&self) -> usize {
+        let symtab = Arc::new(SymbolTable::new());
+        let mut tokens = 0;
+        for (source, standard) in self.sources.iter() {
+            let code = match source.contents() {
+                Ok(code) => code,
+                Err(_) => continue,
+            };
+            let mut tokenizer =
+                Tokenizer::with_standard(symtab.clone(), source.clone(), code, *standard);
+            loop {
+                match tokenizer.pop() {
+                    Ok(Some(_)) => tokens += 1,
+                    Ok(None) => break,
+                    // Continue after the illegal token as the parser does not
+                    Err(_) => {}
+                }
+            }
+        }
+        tokens
+    }
+
+    /// Parses every file and returns the number of design units
+    pub fn parse(&self) -> usize {
+        self.design_files()
+            .iter()
+            .map(|design_file| design_file.design_units.len())
+            .sum()
+    }
+
+    /// The design files of the files which could be parsed
+    pub fn design_files(&self) -> Vec<DesignFile> {
+        let parser = VHDLParser::new();
+        let mut design_files = Vec::new();
+        for (source, standard) in self.sources.iter() {
+            let options = ParseOptions {
+                standard: *standard,
+                ..ParseOptions::default()
+            };
+            let mut messages = Vec::new();
+            if let Ok(design_file) =
+                parser.parse_design_source_with_options(source, &options, &mut messages)
+            {
+                design_files.push(design_file);
+            }
+        }
+        design_files
+    }
+}
+
+/// The time taken by a number of iterations over a corpus
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Throughput {
+    pub bytes: usize,
+    pub iterations: usize,
+    pub seconds: f64,
+}
+
+impl Throughput {
+    fn measure(corpus: &Corpus, iterations: usize, step: impl Fn(&Corpus) -> usize) -> Throughput {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            step(corpus);
+        }
+        Throughput {
+            bytes: corpus.bytes,
+            iterations,
+            seconds: start.elapsed().as_secs_f64(),
+        }
+    }
+
+    pub fn mb_per_second(&self) -> f64 {
+        if self.seconds > 0.0 {
+            (self.bytes * self.iterations) as f64 / 1e6 / self.seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The throughput of tokenizing and parsing the corpus
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Benchmark {
+    pub files: usize,
+    pub tokenize: Throughput,
+    pub parse: Throughput,
+}
+
+/// Tokenizes and parses the corpus the given number of times after a warm up
+pub fn benchmark(corpus: &Corpus, iterations: usize) -> Benchmark {
+    corpus.parse();
+    Benchmark {
+        files: corpus.files(),
+        tokenize: Throughput::measure(corpus, iterations, Corpus::tokenize),
+        parse: Throughput::measure(corpus, iterations, Corpus::parse),
+    }
+}
+
+/// Code with the constructs of typical designs, a package with types and subprograms
+/// and an entity with an architecture of processes, instances and generate statements
+pub fn generated_code(units: usize) -> String {
+    let mut code =
+        String::from("library ieee;\nuse ieee.std_logic_1164.all;\nuse ieee.numeric_std.all;\n\n");
+    for i in 0..units {
+        code.push_str(&format!(
+            "\
+package pkg{i} is
+  constant width : natural := {width};
+  type state_t is (idle, running, done);
+  type regs_t is array (0 to 7) of std_logic_vector(width - 1 downto 0);
+  function parity(value : std_logic_vector) return std_logic;
+end package;
+
+package body pkg{i} is
+  function parity(value : std_logic_vector) return std_logic is
+    variable result : std_logic := '0';
+  begin
+    for j in value'range loop
+      result := result xor value(j);
+    end loop;
+    return result;
+  end function;
+end package body;
+
+library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+use work.pkg{i}.all;
+
+-- A counter with a register file
+entity ent{i} is
+  generic (
+    depth : positive := 16;
+    name : string := \"ent{i}\");
+  port (
+    clk : in std_logic;
+    rst : in std_logic;
+    din : in std_logic_vector(width - 1 downto 0);
+    dout : out std_logic_vector(width - 1 downto 0);
+    valid : out std_logic);
+end entity;
+
+architecture rtl of ent{i} is
+  signal state : state_t := idle;
+  signal count : unsigned(width - 1 downto 0) := (others => '0');
+  signal regs : regs_t := (others => (others => '0'));
+begin
+  main : process (clk)
+  begin
+    if rising_edge(clk) then
+      if rst = '1' then
+        state <= idle;
+        count <= (others => '0');
+      else
+        case state is
+          when idle =>
+            if din /= x\"00\" then
+              state <= running;
+            end if;
+          when running =>
+            count <= count + 1;
+            regs(to_integer(count(2 downto 0))) <= din;
+            if count = depth - 1 then
+              state <= done;
+            end if;
+          when done =>
+            state <= idle;
+        end case;
+      end if;
+    end if;
+  end process;
+
+  dout <= regs(0) when state = done else std_logic_vector(count);
+  valid <= parity(din) and not rst;
+
+  gen : for k in 0 to 1 generate
+    inst : entity work.ent{i}
+      generic map (depth => depth / 2, name => name & \"_sub\")
+      port map (clk => clk, rst => rst, din => din, dout => open, valid => open);
+  end generate;
+end architecture;
+
+",
+            i = i,
+            width = 8 + i % 8
+        ));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn generated_code_parses_without_errors() {
+        let code = generated_code(2);
+        let mut messages = Vec::new();
+        let design_file = VHDLParser::new()
+            .parse_design_source(&Source::from_str(&code).unwrap(), &mut messages)
+            .unwrap_or_else(|_| panic!("Could not parse generated code"));
+        assert_eq!(messages, Vec::new());
+        assert_eq!(design_file.design_units.len(), 8);
+    }
+
+    #[test]
+    fn measures_project_files() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "ent.vhd", "entity ent is\nend entity;\n");
+        project.add_file("lib", "pkg.vhd", "package pkg is\nend package;\n");
+
+        let corpus = Corpus::from_project(&project.project);
+        assert_eq!(corpus.files(), 2);
+        assert_eq!(corpus.bytes(), 54);
+        assert_eq!(corpus.tokenize(), 12);
+        assert_eq!(corpus.parse(), 2);
+
+        let result = benchmark(&corpus, 3);
+        assert_eq!(result.files, 2);
+        assert_eq!(result.parse.bytes, 54);
+        assert_eq!(result.parse.iterations, 3);
+        assert!(result.parse.mb_per_second() > 0.0);
+    }
+}
@@ -0,0 +1,4166 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Command line interface for scripts and continuous integration
+//!
+//! rust_hdl check [--watch] [--external] <project.toml|files...>
+//! rust_hdl fmt [--check|--stdout] [--preset NAME] [--indent-size N] [--tabs] [--operator-spacing] [--comma-spacing] [--one-item-per-line] [--align-colons] [--align-arrows] [--align-assignments] [--keyword-case CASE] [--standard-case CASE] [--declared-spelling] [--align-comments] [--comment-width N] [--max-width N] <project.toml|files...>
+//! rust_hdl lint [--watch] [--enable CODE]... [--disable CODE]... [--min-severity S] [--fail-on S] [--lint ID=SETTING]... [--waivers] [--keyword-case CASE] [--standard-case CASE] [--declared-spelling] <project.toml|files...>
+//! rust_hdl graph [--format dot] [--files|--instances] <project.toml|files...>
+//! rust_hdl fsm [--format dot] <project.toml|files...>
+//! rust_hdl stats [--json] <project.toml|files...>
+//! rust_hdl memory [--json] <project.toml|files...>
+//! rust_hdl bench [--iterations N] [--generated UNITS] [--json] [<project.toml|files...>]
+//! rust_hdl doc [--format html|markdown] [--output DIR] <project.toml|files...>
+//! rust_hdl tree --top LIB.ENTITY [--depth N] [--filter PATTERN] <project.toml|files...>
+//! rust_hdl convert [--indent-size N] [--tabs] NAME <project.toml|files...>
+//! rust_hdl script --simulator ghdl|modelsim|riviera [--output FILE] <project.toml|files...>
+//! rust_hdl files [--format f|vivado|synplify] [--output FILE] <project.toml|files...>
+//! rust_hdl dump-ast [--format json] <project.toml|files...>
+//! rust_hdl syntax [--standard YEAR] [--stdin-filename NAME] <file|->
+//! rust_hdl duplicates <project.toml|files...>
+//! rust_hdl coverage <project.toml|files...>
+//! rust_hdl rename [--kind KIND] [--in FILE] [--patch] OLD NEW <project.toml|files...>
+//! rust_hdl dead-code [--top LIB.ENTITY] <project.toml|files...>
+//! rust_hdl interfaces <project.toml|files...>
+//! rust_hdl blackboxes <project.toml|files...>
+//! rust_hdl header [--language c|rust] [--output FILE] PACKAGE <project.toml|files...>
+//! rust_hdl serve <project.toml|files...>
+//! rust_hdl yosys-json [--top LIB.ENTITY] [--output FILE] <project.toml|files...>
+//! rust_hdl netlist --top LIB.ENTITY [--format json|text] [--output FILE] <project.toml|files...>
+//! rust_hdl sql [--output FILE] <project.toml|files...>
+//! rust_hdl lsif [--root DIR] [--output FILE] <project.toml|files...>
+//! rust_hdl top-interface --top LIB.ENTITY [--format json|yaml] [--generic NAME=VALUE]... [--output FILE] <project.toml|files...>
+//! rust_hdl run [--stop-time TIME] [--vcd FILE] TOP <project.toml|files...>
+//!
+//! Check parses and analyzes the project and prints its messages, the exit code is
+//! 0 when there are no errors, 1 when there are errors and 2 for invalid usage
+//!
+//! With --external check also runs the external compiler of the project configuration
+//! on each file and reports those of its diagnostics which the analyzer does not
+//!
+//! With --watch check and lint keep the project in memory and report again each time
+//! a file of the project changes until interrupted
+//!
+//! Fmt formats the files in place, prints them with --stdout or only prints the changes
+//! as a unified diff with --check where the exit code is then 1 if there are any,
+//! besides the indentation it optionally spaces operators and commas, puts each
+//! generic and port on its own line, aligns colons, arrows, assignments and trailing
+//! comments, reflows paragraphs of comments wider than the comment width and wraps
+//! lines of code wider than the maximum width
+//!
+//! The style of fmt is that of the nearest .vhdlfmt.toml file of each file or of the
+//! preset given, changed by the options given
+//!
+//! Fmt and lint normalize keywords and the identifiers of the std and ieee libraries
+//! to lower or upper case and other identifiers to the spelling of their declaration,
+//! lint reports them as keyword-case and identifier-case findings
+//!
+//! Lint prints the messages with a code, the rules, which are enabled and at least of
+//! the minimum severity, the exit code is 1 when any of them is at least of the fail on
+//! severity which defaults to error
+//!
+//! The lint section of project files configures the rules, the case options enable the
+//! keyword-case and identifier-case rules
+//!
+//! Graph prints the dependencies between design units, files or the instance hierarchy
+//! as a Graphviz digraph, circular dependencies are printed to stderr and give the exit
+//! code 1
+//!
+//! Stats prints the design units, processes and lines of code of each library together
+//! with the time spent parsing and analyzing as a table or as JSON
+//!
+//! Bench measures the throughput of tokenizing and parsing the files in MB/s, with
+//! --generated or without files on generated code with the number of units given
+//! instead, 200 by default
+//!
+//! Doc writes cross-linked HTML or markdown pages of the entities and packages to the
+//! output directory
+//!
+//! Tree prints the instances below the top level entity with their entity, architecture
+//! and generics where a filter only keeps the instances containing the pattern
+//!
+//! Convert prints the component declaration of the entity with the name, or the entity
+//! declaration of the component with the name when there is no such entity
+//!
+//! Script prints, or writes to the output file, a script which analyzes all files in
+//! compile order into their libraries with the language standard of each file,
+//! circular dependencies are printed to stderr and give the exit code 1
+//!
+//! Files prints, or writes to the output file, the files in compile order as a plain
+//! list with one file per line or as Tcl commands which read them into their libraries
+//! for Vivado or Synplify, circular dependencies are printed to stderr and give the exit
+//! code 1
+//!
+//! Dump-ast prints the parse tree of each file as JSON for custom analyses, syntax
+//! errors are printed to stderr and give the exit code 1
+//!
+//! Syntax only parses a single file, or stdin when the file is -, and prints each
+//! syntax message on one line as file:line:column: severity: message for editors
+//! which run external checkers
+//!
+//! Duplicates lists the design units declared in more than one file or library with
+//! the location of each declaration, the exit code is 1 when there are any
+//!
+//! Coverage prints how many AST nodes of each kind were parsed and the syntax errors,
+//! which is where constructs that are not supported end up
+//!
+//! Rename renames the declaration with the old name, and all names which denote it,
+//! in place or prints the edits as a unified diff with --patch, the kind and file
+//! select the declaration when several have the same name
+//!
+//! Dead-code lists the entities which are not instantiated below the top, or anywhere
+//! without a top, the packages no other design unit uses and the subprograms which are
+//! never called together with their locations
+//!
+//! Interfaces lists the instances of each entity and flags the generic and port maps
+//! which no longer match the entity, such as ports without default which are not
+//! associated or formals which are not generics or ports, the exit code is 1 when any
+//! instance is inconsistent
+//!
+//! Blackboxes lists the instances which are not bound to an entity or configuration
+//! within the project, either as a blackbox matching the blackboxes of the project file
+//! or as unbound together with a similarly named entity, the exit code is 1 when any
+//! instance is unbound
+//!
+//! Header prints, or writes to the output file, a C header or Rust module with the
+//! constants, enumeration types and record types of the package
+//!
+//! Serve answers JSON-RPC requests for the entities, ports, compile order and
+//! diagnostics of the project, one per line on stdin, with one response per line on
+//! stdout for build systems and tools which are not editors
+//!
+//! Yosys-json prints, or writes to the output file, the entities below the top, or all
+//! of them, as the modules of a Yosys JSON netlist with their ports and a cell for each
+//! instance but no logic
+//!
+//! Netlist prints, or writes to the output file, the instances below the top with
+//! their hierarchical paths and the nets connecting their ports as JSON or as
+//! s-expressions in the manner of EDIF
+//!
+//! Sql prints, or writes to the output file, an SQL script which creates a SQLite
+//! database of the declarations, references and instances of the project, such as by
+//! rust_hdl sql project.toml | sqlite3 symbols.db
+//!
+//! Lsif prints, or writes to the output file, an LSIF index of the project with one
+//! element per line for code navigation in web code browsers, the project root is the
+//! current directory unless given
+//!
+//! Top-interface prints, or writes to the output file, the generics and ports of the top
+//! with the direction, type and width of each port for co-simulation harness generators,
+//! the widths are evaluated with the default generics unless values are given
+//!
+//! Run elaborates the top, such as tb_foo or lib.tb_foo, and simulates it with the
+//! experimental interpreter of a subset of VHDL until there are no more events, it is
+//! stopped or the stop time such as 10 us is reached, the exit code is 1 when an
+//! assertion or report of severity error or failure fails the simulation, with --vcd
+//! the values of the signals are written to a VCD file for waveform viewers
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+extern crate vhdl_parser;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
+use vhdl_parser::analysis_server::AnalysisServer;
+use vhdl_parser::ast::DesignFile;
+use vhdl_parser::benchmark::{benchmark, Benchmark, Corpus, GENERATED_UNITS, TARGET_MB_PER_SECOND};
+use vhdl_parser::blackbox::blackboxes;
+use vhdl_parser::compile_script::{
+    compile_script, compile_steps, file_list, FileListFormat, Simulator,
+};
+use vhdl_parser::config::Config;
+use vhdl_parser::conversion::convert;
+use vhdl_parser::coverage::coverage;
+use vhdl_parser::dead_code::dead_code;
+use vhdl_parser::dependency_graph::{DependencyGraph, GraphKind};
+use vhdl_parser::docgen::{pages, DocFormat, Documentation};
+use vhdl_parser::duplicate_units::duplicate_units;
+use vhdl_parser::external_tool::{merge_messages, run_external_tool};
+use vhdl_parser::format_config::{preset, FormatConfig, PRESETS};
+use vhdl_parser::formatter::{format_contents, spellings, Case, FormatStyle, Indentation};
+use vhdl_parser::header::{header, HeaderLanguage};
+use vhdl_parser::instance_tree::InstanceHierarchy;
+use vhdl_parser::interface_consistency::interface_consistency;
+use vhdl_parser::lint::{LintConfig, LintContext, Registry, RuleConfig};
+use vhdl_parser::lsif::lsif_index;
+use vhdl_parser::memory::MemoryReport;
+use vhdl_parser::message::{Message, Severity};
+use vhdl_parser::name_resolution::{NamedEntity, NamedEntityKind};
+use vhdl_parser::netlist::{netlist, netlist_text};
+use vhdl_parser::project::Project;
+use vhdl_parser::rename::{find_declarations, rename_positions, renamed_contents};
+use vhdl_parser::simulation::{Finish, Simulation};
+use vhdl_parser::simulation_value::{parse_time, time_image, Time};
+use vhdl_parser::source::{Source, SrcPos};
+use vhdl_parser::standard::VHDLStandard;
+use vhdl_parser::state_machine::state_machines;
+use vhdl_parser::statistics::{library_statistics, ProjectStatistics};
+use vhdl_parser::symbol_database::symbol_database_sql;
+use vhdl_parser::top_interface::{top_interface, top_interface_yaml};
+use vhdl_parser::waiver::Waiver;
+use vhdl_parser::watcher::FileWatcher;
+use vhdl_parser::yosys_json::yosys_design;
+use vhdl_parser::{ParseOptions, ParserError, VHDLParser};
+
+const USAGE: &str = "Usage: rust_hdl check [--watch] [--external] <project.toml|files...>
+       rust_hdl fmt [--check|--stdout] [--preset NAME] [--indent-size N] [--tabs] [--operator-spacing] [--comma-spacing] [--one-item-per-line] [--align-colons] [--align-arrows] [--align-assignments] [--keyword-case CASE] [--standard-case CASE] [--declared-spelling] [--align-comments] [--comment-width N] [--max-width N] <project.toml|files...>
+       rust_hdl lint [--watch] [--enable CODE]... [--disable CODE]... [--min-severity hint|warning|error] [--fail-on hint|warning|error] [--lint ID=off|hint|warning|error]... [--waivers] [--keyword-case CASE] [--standard-case CASE] [--declared-spelling] <project.toml|files...>
+       rust_hdl graph [--format dot] [--files|--instances] <project.toml|files...>
+       rust_hdl fsm [--format dot] <project.toml|files...>
+       rust_hdl stats [--json] <project.toml|files...>
+       rust_hdl memory [--json] <project.toml|files...>
+       rust_hdl bench [--iterations N] [--generated UNITS] [--json] [<project.toml|files...>]
+       rust_hdl doc [--format html|markdown] [--output DIR] <project.toml|files...>
+       rust_hdl tree --top LIB.ENTITY [--depth N] [--filter PATTERN] <project.toml|files...>
+       rust_hdl convert [--indent-size N] [--tabs] NAME <project.toml|files...>
+       rust_hdl script --simulator ghdl|modelsim|riviera [--output FILE] <project.toml|files...>
+       rust_hdl files [--format f|vivado|synplify] [--output FILE] <project.toml|files...>
+       rust_hdl dump-ast [--format json] <project.toml|files...>
+       rust_hdl syntax [--standard YEAR] [--stdin-filename NAME] <file|->
+       rust_hdl duplicates <project.toml|files...>
+       rust_hdl coverage <project.toml|files...>
+       rust_hdl rename [--kind KIND] [--in FILE] [--patch] OLD NEW <project.toml|files...>
+       rust_hdl dead-code [--top LIB.ENTITY] <project.toml|files...>
+       rust_hdl interfaces <project.toml|files...>
+       rust_hdl blackboxes <project.toml|files...>
+       rust_hdl header [--language c|rust] [--output FILE] PACKAGE <project.toml|files...>
+       rust_hdl serve <project.toml|files...>
+       rust_hdl yosys-json [--top LIB.ENTITY] [--output FILE] <project.toml|files...>
+       rust_hdl netlist --top LIB.ENTITY [--format json|text] [--output FILE] <project.toml|files...>
+       rust_hdl sql [--output FILE] <project.toml|files...>
+       rust_hdl lsif [--root DIR] [--output FILE] <project.toml|files...>
+       rust_hdl top-interface --top LIB.ENTITY [--format json|yaml] [--generic NAME=VALUE]... [--output FILE] <project.toml|files...>
+       rust_hdl run [--stop-time TIME] [--vcd FILE] TOP <project.toml|files...>";
+
+/// The library of files given on the command line
+const DEFAULT_LIBRARY: &str = "work";
+
+/// The interval between scans of the files of the project and the time without changes
+/// before the project is checked again in watch mode
+const WATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The directory of the generated documentation
+const DEFAULT_DOC_DIRECTORY: &str = "doc";
+
+/// Arguments ending with .toml are project files, other files are added to the work library
+fn load_project(args: &[String]) -> Result<Project, String> {
+    let mut project = Project::new();
+    for arg in args {
+        if arg.ends_with(".toml") {
+            let config = Config::read_file_path(Path::new(arg)).map_err(|err| err.to_string())?;
+            if let Some((file_name, err)) = project.add_config(&config).into_iter().next() {
+                return Err(format!("Could not add {}: {}", file_name, err));
+            }
+        } else {
+            project
+                .update_file(DEFAULT_LIBRARY, arg)
+                .map_err(|err| format!("Could not read {}: {}", arg, err))?;
+        }
+    }
+    Ok(project)
+}
+
+/// The files of all libraries of project files and the other arguments
+fn file_names(args: &[String]) -> Result<Vec<String>, String> {
+    let mut file_names = Vec::new();
+    for arg in args {
+        if arg.ends_with(".toml") {
+            let config = Config::read_file_path(Path::new(arg)).map_err(|err| err.to_string())?;
+            for library in config.iter_libraries() {
+                file_names.extend(library.file_names()?);
+            }
+        } else {
+            file_names.push(arg.clone());
+        }
+    }
+    Ok(file_names)
+}
+
+/// Syntax, semantic and name resolution messages ordered by file and position
+fn check(project: &Project) -> Vec<Message> {
+    let mut messages = project.messages();
+    messages.extend(project.analyze());
+    messages.extend(project.resolve().messages().iter().cloned());
+    sort_messages(&mut messages);
+    messages
+}
+
+fn sort_messages(messages: &mut [Message]) {
+    messages.sort_by_key(|message| {
+        (
+            message.pos.source.file_name().map(str::to_owned),
+            message.pos.start,
+        )
+    });
+}
+
+/// The messages of check and those of the external tool which are not on the same lines
+fn check_external(project: &Project) -> Result<Vec<Message>, String> {
+    let tool = project
+        .config()
+        .external_tool()
+        .ok_or_else(|| "The project configuration has no external tool".to_owned())?;
+    let external = run_external_tool(project, tool)
+        .map_err(|err| format!("Could not run the external tool: {}", err))?;
+    let mut messages = check(project);
+    merge_messages(&mut messages, external);
+    sort_messages(&mut messages);
+    Ok(messages)
+}
+
+/// Prints the circular dependencies to stderr and returns the exit code
+fn report_cycles(graph: &DependencyGraph) -> i32 {
+    let cycles = graph.cycle_messages();
+    for message in cycles.iter() {
+        eprintln!("{}", message.pretty_string());
+    }
+    if cycles.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Prints the messages with a summary and returns the exit code
+fn report(messages: &[Message]) -> i32 {
+    for message in messages {
+        println!("{}", message.pretty_string());
+    }
+    let errors = messages
+        .iter()
+        .filter(|message| message.severity == Severity::Error)
+        .count();
+    println!(
+        "Found {} errors and {} warnings",
+        errors,
+        messages.len() - errors
+    );
+    if errors > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// A watcher of the files of the project which does not report the files as created
+fn project_watcher(project: &Project) -> io::Result<FileWatcher> {
+    let mut watcher = FileWatcher::new(WATCH_INTERVAL);
+    for file in project.files() {
+        watcher.add_file(&file.library_name, Path::new(file.file_name()));
+    }
+    watcher.skip_existing()?;
+    Ok(watcher)
+}
+
+/// Reports the project and then again each time its files change, only returns on errors
+fn watch<F>(mut project: Project, mut report: F) -> Result<i32, String>
+where
+    F: FnMut(&Project) -> i32,
+{
+    report(&project);
+    let mut watcher = project_watcher(&project).map_err(|err| err.to_string())?;
+    watcher
+        .watch(WATCH_INTERVAL, |changes| {
+            let start = Instant::now();
+            for (file_name, err) in project.apply_changes(changes) {
+                eprintln!("Could not read {}: {}", file_name, err);
+            }
+            report(&project);
+            println!(
+                "Checked after {} changed files in {} ms",
+                changes.len(),
+                start.elapsed().as_millis()
+            );
+            true
+        })
+        .map_err(|err| err.to_string())?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+enum FmtMode {
+    InPlace,
+    Stdout,
+    Check,
+}
+
+#[derive(PartialEq, Debug)]
+struct FmtOptions {
+    mode: FmtMode,
+    /// The preset replacing the style of the configuration files
+    preset: Option<String>,
+    indent_size: Option<usize>,
+    tabs: bool,
+    operator_spacing: bool,
+    comma_spacing: bool,
+    one_item_per_line: bool,
+    align_colons: bool,
+    align_arrows: bool,
+    align_assignments: bool,
+    keyword_case: Option<Case>,
+    standard_case: Option<Case>,
+    declared_spelling: bool,
+    align_comments: bool,
+    comment_width: Option<usize>,
+    max_width: Option<usize>,
+    files: Vec<String>,
+}
+
+fn parse_fmt_options(args: &[String]) -> Result<FmtOptions, String> {
+    let mut options = FmtOptions {
+        mode: FmtMode::InPlace,
+        preset: None,
+        indent_size: None,
+        tabs: false,
+        operator_spacing: false,
+        comma_spacing: false,
+        one_item_per_line: false,
+        align_colons: false,
+        align_arrows: false,
+        align_assignments: false,
+        keyword_case: None,
+        standard_case: None,
+        declared_spelling: false,
+        align_comments: false,
+        comment_width: None,
+        max_width: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.mode = FmtMode::Check,
+            "--stdout" => options.mode = FmtMode::Stdout,
+            "--tabs" => options.tabs = true,
+            "--operator-spacing" => options.operator_spacing = true,
+            "--comma-spacing" => options.comma_spacing = true,
+            "--one-item-per-line" => options.one_item_per_line = true,
+            "--align-colons" => options.align_colons = true,
+            "--align-arrows" => options.align_arrows = true,
+            "--align-assignments" => options.align_assignments = true,
+            "--keyword-case" => options.keyword_case = Some(parse_case(arg, args.next())?),
+            "--standard-case" => options.standard_case = Some(parse_case(arg, args.next())?),
+            "--declared-spelling" => options.declared_spelling = true,
+            "--align-comments" => options.align_comments = true,
+            "--comment-width" => {
+                options.comment_width = Some(
+                    args.next()
+                        .and_then(|width| width.parse().ok())
+                        .filter(|width| *width > 0)
+                        .ok_or_else(|| "--comment-width must be a positive number".to_owned())?,
+                );
+            }
+            "--max-width" => {
+                options.max_width = Some(
+                    args.next()
+                        .and_then(|width| width.parse().ok())
+                        .filter(|width| *width > 0)
+                        .ok_or_else(|| "--max-width must be a positive number".to_owned())?,
+                );
+            }
+            "--preset" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| "--preset must be followed by a preset".to_owned())?;
+                if preset(name).is_none() {
+                    return Err(format!(
+                        "Unknown preset '{}', expected one of {}",
+                        name,
+                        PRESETS.join(", ")
+                    ));
+                }
+                options.preset = Some(name.clone());
+            }
+            "--indent-size" => {
+                options.indent_size = Some(
+                    args.next()
+                        .and_then(|size| size.parse().ok())
+                        .filter(|size| *size > 0)
+                        .ok_or_else(|| "--indent-size must be a positive number".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(options)
+}
+
+fn parse_case(option: &str, case: Option<&String>) -> Result<Case, String> {
+    case.ok_or_else(|| format!("{} must be followed by lower or upper", option))?
+        .parse()
+}
+
+impl FmtOptions {
+    /// The style of the preset, or of the nearest configuration file of the file, changed
+    /// by the options given
+    fn style(&self, file_name: &str) -> Result<FormatStyle, String> {
+        let default = || {
+            FormatStyle::new(Indentation {
+                size: 2,
+                tabs: false,
+            })
+        };
+        let mut style = match self.preset {
+            Some(ref name) => preset(name).unwrap_or_else(default),
+            None => match FormatConfig::find(Path::new(file_name))? {
+                Some(config) => config.style(Path::new(file_name)),
+                None => default(),
+            },
+        };
+        if let Some(size) = self.indent_size {
+            style.indentation.size = size;
+        }
+        style.indentation.tabs |= self.tabs;
+        style.operator_spacing |= self.operator_spacing;
+        style.comma_spacing |= self.comma_spacing;
+        style.one_item_per_line |= self.one_item_per_line;
+        style.align_colons |= self.align_colons;
+        style.align_arrows |= self.align_arrows;
+        style.align_assignments |= self.align_assignments;
+        style.keyword_case = self.keyword_case.or(style.keyword_case);
+        style.standard_case = self.standard_case.or(style.standard_case);
+        style.declared_spelling |= self.declared_spelling;
+        style.align_comments |= self.align_comments;
+        style.comment_width = self.comment_width.or(style.comment_width);
+        style.max_width = self.max_width.or(style.max_width);
+        Ok(style)
+    }
+}
+
+/// Formats each file and returns the exit code
+fn fmt(options: &FmtOptions) -> Result<i32, String> {
+    let mut styles = Vec::new();
+    for file_name in file_names(&options.files)? {
+        let style = options.style(&file_name)?;
+        styles.push((file_name, style));
+    }
+    // The spelling of identifiers depends on the declarations they refer to
+    let needs_resolution = styles
+        .iter()
+        .any(|(_, style)| style.standard_case.is_some() || style.declared_spelling);
+    let resolved = if needs_resolution {
+        let project = load_project(&options.files)?;
+        let resolution = project.resolve();
+        Some((project, resolution))
+    } else {
+        None
+    };
+    let mut unformatted = 0;
+    for (file_name, style) in styles {
+        let source = Source::from_file(&file_name);
+        let contents = source
+            .contents()
+            .map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+        let spellings = match resolved {
+            Some((ref project, ref resolution)) => {
+                spellings(project, resolution, &file_name, &style)
+            }
+            _ => Vec::new(),
+        };
+        let formatted = format_contents(&source, &style, &spellings)
+            .map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+        match options.mode {
+            FmtMode::InPlace => {
+                if formatted != *contents {
+                    fs::write(&file_name, &formatted.bytes)
+                        .map_err(|err| format!("Could not write {}: {}", file_name, err))?;
+                }
+            }
+            FmtMode::Stdout => {
+                let stdout = io::stdout();
+                stdout
+                    .lock()
+                    .write_all(&formatted.bytes)
+                    .map_err(|err| err.to_string())?;
+            }
+            FmtMode::Check => {
+                if formatted != *contents {
+                    print!(
+                        "{}",
+                        unified_diff(&file_name, &contents.to_string(), &formatted.to_string())
+                    );
+                    unformatted += 1;
+                }
+            }
+        }
+    }
+    Ok(if unformatted > 0 { 1 } else { 0 })
+}
+
+#[derive(PartialEq, Debug)]
+struct LintOptions {
+    /// Only these rules are enabled unless empty
+    enable: Vec<String>,
+    disable: Vec<String>,
+    min_severity: Severity,
+    fail_on: Severity,
+    /// Overrides whether a rule is enabled and its severity after the project files
+    rules: Vec<(String, RuleConfig)>,
+    /// Whether the waivers in the code are reported
+    waivers: bool,
+    watch: bool,
+    keyword_case: Option<Case>,
+    standard_case: Option<Case>,
+    declared_spelling: bool,
+    files: Vec<String>,
+}
+
+fn parse_severity(severity: Option<&String>) -> Result<Severity, String> {
+    match severity.map(String::as_str) {
+        Some("hint") => Ok(Severity::Hint),
+        Some("warning") => Ok(Severity::Warning),
+        Some("error") => Ok(Severity::Error),
+        _ => Err("Severity must be hint, warning or error".to_owned()),
+    }
+}
+
+fn parse_lint_options(args: &[String]) -> Result<LintOptions, String> {
+    let mut options = LintOptions {
+        enable: Vec::new(),
+        disable: Vec::new(),
+        min_severity: Severity::Hint,
+        fail_on: Severity::Error,
+        rules: Vec::new(),
+        waivers: false,
+        watch: false,
+        keyword_case: None,
+        standard_case: None,
+        declared_spelling: false,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--enable" | "--disable" => {
+                let code = args
+                    .next()
+                    .ok_or_else(|| format!("{} must be followed by a code", arg))?;
+                if arg == "--enable" {
+                    options.enable.push(code.clone());
+                } else {
+                    options.disable.push(code.clone());
+                }
+            }
+            "--min-severity" => options.min_severity = parse_severity(args.next())?,
+            "--fail-on" => options.fail_on = parse_severity(args.next())?,
+            "--lint" => {
+                let rule = args
+                    .next()
+                    .ok_or_else(|| "--lint must be followed by ID=SETTING".to_owned())?;
+                let mut parts = rule.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(id), Some(setting)) if !id.is_empty() => {
+                        let setting = toml::Value::String(setting.to_owned());
+                        let config = RuleConfig::from_value(&setting, id)?;
+                        options.rules.push((id.to_owned(), config));
+                    }
+                    _ => return Err(format!("--lint {} must be ID=SETTING", rule)),
+                }
+            }
+            "--waivers" => options.waivers = true,
+            "--watch" => options.watch = true,
+            "--keyword-case" => options.keyword_case = Some(parse_case(arg, args.next())?),
+            "--standard-case" => options.standard_case = Some(parse_case(arg, args.next())?),
+            "--declared-spelling" => options.declared_spelling = true,
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(options)
+}
+
+/// Error is above warning which is above hint
+fn at_least(severity: Severity, threshold: Severity) -> bool {
+    let rank = |severity| match severity {
+        Severity::Hint => 0,
+        Severity::Warning => 1,
+        Severity::Error => 2,
+    };
+    rank(severity) >= rank(threshold)
+}
+
+/// The lint section of the project files where the case options enable the keyword-case
+/// and identifier-case rules and the --lint options override the severity of a rule
+/// while keeping its options
+fn lint_config(options: &LintOptions) -> Result<LintConfig, String> {
+    let mut config = LintConfig::default();
+    for arg in options.files.iter().filter(|arg| arg.ends_with(".toml")) {
+        let project = Config::read_file_path(Path::new(arg)).map_err(|err| err.to_string())?;
+        config.merge(project.lint().clone());
+    }
+    let case_config = |options: Vec<(&str, toml::Value)>| {
+        let mut config = RuleConfig {
+            enabled: Some(true),
+            ..RuleConfig::default()
+        };
+        for (key, value) in options {
+            config.options.insert(key.to_owned(), value);
+        }
+        config
+    };
+    if let Some(case) = options.keyword_case {
+        config.set(
+            "keyword-case",
+            case_config(vec![("case", toml::Value::String(case.to_string()))]),
+        );
+    }
+    if options.standard_case.is_some() || options.declared_spelling {
+        let mut identifier_options = vec![(
+            "declared_spelling",
+            toml::Value::Boolean(options.declared_spelling),
+        )];
+        if let Some(case) = options.standard_case {
+            identifier_options.push(("standard_case", toml::Value::String(case.to_string())));
+        }
+        config.set("identifier-case", case_config(identifier_options));
+    }
+    for (id, rule) in options.rules.iter() {
+        let mut rule_config = config.get(id).cloned().unwrap_or_default();
+        rule_config.enabled = rule.enabled;
+        if rule.severity.is_some() {
+            rule_config.severity = rule.severity;
+        }
+        config.set(id, rule_config);
+    }
+    Ok(config)
+}
+
+/// The findings of the lint rules enabled by the configuration which are not waived
+/// together with the waivers of the project
+fn lint_messages(
+    config: &LintConfig,
+    project: &Project,
+) -> Result<(Vec<Message>, Vec<Waiver>), String> {
+    let mut messages = project.messages();
+    messages.extend(project.analyze());
+    let resolution = project.resolve();
+    messages.extend(resolution.messages().iter().cloned());
+    let context = LintContext {
+        project,
+        messages: &messages,
+        resolution: &resolution,
+    };
+    Registry::default().check_waived(&context, config)
+}
+
+/// The messages of the enabled rules which are at least of the minimum severity
+fn lint(options: &LintOptions, messages: Vec<Message>) -> Vec<Message> {
+    messages
+        .into_iter()
+        .filter(|message| match message.code {
+            Some(ref code) => {
+                (options.enable.is_empty() || options.enable.contains(code))
+                    && !options.disable.contains(code)
+                    && at_least(message.severity, options.min_severity)
+            }
+            None => false,
+        })
+        .collect()
+}
+
+/// Prints the waivers with their reason and the number of findings they waive
+fn report_waivers(waivers: &[Waiver]) {
+    for waiver in waivers {
+        println!(
+            "{}: [{}] {} ({} waived)",
+            location(&waiver.pos),
+            waiver.rule,
+            waiver.reason,
+            waiver.waived
+        );
+    }
+    println!("Found {} lint waivers", waivers.len());
+}
+
+/// Prints the findings with their code and returns the exit code
+fn report_lint(options: &LintOptions, findings: &[Message]) -> i32 {
+    for finding in findings {
+        println!(
+            "[{}] {}",
+            finding.code.as_deref().unwrap_or(""),
+            finding.pretty_string()
+        );
+    }
+    println!("Found {} lint findings", findings.len());
+    if findings
+        .iter()
+        .any(|finding| at_least(finding.severity, options.fail_on))
+    {
+        1
+    } else {
+        0
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct GraphOptions {
+    kind: GraphKind,
+    files: Vec<String>,
+}
+
+fn parse_graph_options(args: &[String]) -> Result<GraphOptions, String> {
+    let mut options = GraphOptions {
+        kind: GraphKind::Units,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("dot") => {}
+                _ => {
+                    return Err("The only supported format is dot".to_owned());
+                }
+            },
+            "--files" => options.kind = GraphKind::Files,
+            "--instances" => options.kind = GraphKind::Instances,
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(options)
+}
+
+#[derive(PartialEq, Debug)]
+struct FsmOptions {
+    /// Print the transition graphs in the dot format instead of a summary
+    dot: bool,
+    files: Vec<String>,
+}
+
+fn parse_fsm_options(args: &[String]) -> Result<FsmOptions, String> {
+    let mut options = FsmOptions {
+        dot: false,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("dot") => options.dot = true,
+                _ => {
+                    return Err("The only supported format is dot".to_owned());
+                }
+            },
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(options)
+}
+
+/// Each state machine with its location followed by its transitions and the states which
+/// are unreachable or have no exit
+fn fsm_report(project: &Project) -> Vec<String> {
+    let mut lines = Vec::new();
+    for fsm in state_machines(&project.files()) {
+        lines.push(format!(
+            "{} {}: {} states, initial {} ({})",
+            fsm.architecture,
+            fsm.signal,
+            fsm.states.len(),
+            fsm.initial.join(", "),
+            location(&fsm.pos)
+        ));
+        let mut edges: Vec<(&str, &str)> = Vec::new();
+        for transition in fsm.transitions.iter() {
+            let edge = (transition.from.as_str(), transition.to.as_str());
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+        for (from, to) in edges {
+            lines.push(format!("  {} -> {}", from, to));
+        }
+        let unreachable = fsm.unreachable_states();
+        if !unreachable.is_empty() {
+            lines.push(format!("  unreachable: {}", unreachable.join(", ")));
+        }
+        let without_exit = fsm.states_without_exit();
+        if !without_exit.is_empty() {
+            lines.push(format!("  no exit: {}", without_exit.join(", ")));
+        }
+    }
+    lines
+}
+
+/// Loads and checks the project while measuring the time of each step
+fn stats(files: &[String]) -> Result<ProjectStatistics, String> {
+    let start = Instant::now();
+    let project = load_project(files)?;
+    let parse_seconds = start.elapsed().as_secs_f64();
+    let start = Instant::now();
+    let _ = check(&project);
+    let analysis_seconds = start.elapsed().as_secs_f64();
+    Ok(ProjectStatistics {
+        libraries: library_statistics(&project.files()),
+        parse_seconds,
+        analysis_seconds,
+    })
+}
+
+#[derive(PartialEq, Debug)]
+struct BenchOptions {
+    iterations: usize,
+    generated: Option<usize>,
+    json: bool,
+    files: Vec<String>,
+}
+
+fn parse_bench_options(args: &[String]) -> Result<BenchOptions, String> {
+    let mut options = BenchOptions {
+        iterations: 10,
+        generated: None,
+        json: false,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => {
+                options.iterations = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|iterations| *iterations > 0)
+                    .ok_or_else(|| {
+                        "--iterations must be followed by a positive number".to_owned()
+                    })?;
+            }
+            "--generated" => {
+                options.generated = Some(
+                    args.next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| "--generated must be followed by a number".to_owned())?,
+                );
+            }
+            "--json" => options.json = true,
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if !options.files.is_empty() && options.generated.is_some() {
+        return Err(USAGE.to_owned());
+    }
+    if options.files.is_empty() && options.generated.is_none() {
+        options.generated = Some(GENERATED_UNITS);
+    }
+    Ok(options)
+}
+
+/// Measures the files or generated code
+fn bench(options: &BenchOptions) -> Result<Benchmark, String> {
+    let corpus = match options.generated {
+        Some(units) => Corpus::generated(units),
+        None => Corpus::from_project(&load_project(&options.files)?),
+    };
+    Ok(benchmark(&corpus, options.iterations))
+}
+
+fn bench_table(bench: &Benchmark) -> String {
+    format!(
+        "{} files of {:.3} MB, {} iterations\n\
+         tokenize {:>8.1} MB/s\n\
+         parse    {:>8.1} MB/s (target {:.0} MB/s)\n",
+        bench.files,
+        bench.parse.bytes as f64 / 1e6,
+        bench.parse.iterations,
+        bench.tokenize.mb_per_second(),
+        bench.parse.mb_per_second(),
+        TARGET_MB_PER_SECOND
+    )
+}
+
+fn stats_table(stats: &ProjectStatistics) -> String {
+    let mut table = format!(
+        "{:<20} {:>8} {:>8} {:>13} {:>8} {:>9} {:>13}\n",
+        "library", "files", "entities", "architectures", "packages", "processes", "lines of code"
+    );
+    for library in stats.libraries.iter() {
+        table.push_str(&format!(
+            "{:<20} {:>8} {:>8} {:>13} {:>8} {:>9} {:>13}\n",
+            library.library_name,
+            library.files,
+            library.entities,
+            library.architectures,
+            library.packages,
+            library.processes,
+            library.lines_of_code
+        ));
+    }
+    table.push_str(&format!(
+        "Parsed in {:.3}s and analyzed in {:.3}s\n",
+        stats.parse_seconds, stats.analysis_seconds
+    ));
+    table
+}
+
+/// The estimated memory of each library in MB followed by the symbol table and the cache
+fn memory_table(report: &MemoryReport) -> String {
+    let mb = |bytes: usize| bytes as f64 / 1e6;
+    let mut table = format!(
+        "{:<20} {:>8} {:>14} {:>10} {:>10} {:>10} {:>10}\n",
+        "library", "files", "interface only", "source", "ast", "messages", "total"
+    );
+    for library in report.libraries.iter() {
+        table.push_str(&format!(
+            "{:<20} {:>8} {:>14} {:>10.3} {:>10.3} {:>10.3} {:>10.3}\n",
+            library.library_name,
+            library.files.len(),
+            if library.interface_only { "yes" } else { "no" },
+            mb(library.source_bytes),
+            mb(library.design_file_bytes),
+            mb(library.message_bytes),
+            mb(library.total_bytes())
+        ));
+    }
+    table.push_str(&format!(
+        "Symbol table of {} names in {:.3} MB, {:.3} MB in total\n",
+        report.symbols,
+        mb(report.symbol_table_bytes),
+        mb(report.total_bytes())
+    ));
+    if let Some(ref cache) = report.cache {
+        table.push_str(&format!(
+            "Cache of {} entries in {:.3} MB on disk\n",
+            cache.entries,
+            cache.disk_bytes as f64 / 1e6
+        ));
+    }
+    table
+}
+
+#[derive(PartialEq, Debug)]
+struct DocOptions {
+    format: DocFormat,
+    output: String,
+    files: Vec<String>,
+}
+
+fn parse_doc_options(args: &[String]) -> Result<DocOptions, String> {
+    let mut options = DocOptions {
+        format: DocFormat::Html,
+        output: DEFAULT_DOC_DIRECTORY.to_owned(),
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                options.format = match args.next().map(String::as_str) {
+                    Some("html") => DocFormat::Html,
+                    Some("markdown") => DocFormat::Markdown,
+                    _ => {
+                        return Err("The format must be html or markdown".to_owned());
+                    }
+                };
+            }
+            "--output" => {
+                options.output = args
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| "--output must be followed by a directory".to_owned())?;
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(options)
+}
+
+/// Writes the pages to the output directory and returns their number
+fn write_pages(output: &Path, pages: &[(String, String)]) -> Result<usize, String> {
+    for (path, contents) in pages {
+        let path = output.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Could not create {}: {}", parent.display(), err))?;
+        }
+        fs::write(&path, contents)
+            .map_err(|err| format!("Could not write {}: {}", path.display(), err))?;
+    }
+    Ok(pages.len())
+}
+
+fn doc(options: &DocOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let pages = pages(&Documentation::new(&project), options.format);
+    let count = write_pages(Path::new(&options.output), &pages)?;
+    println!("Wrote {} pages to {}", count, options.output);
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct TreeOptions {
+    top: String,
+    depth: Option<usize>,
+    filter: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_tree_options(args: &[String]) -> Result<TreeOptions, String> {
+    let mut top = None;
+    let mut options = TreeOptions {
+        top: String::new(),
+        depth: None,
+        filter: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                top = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--top must be followed by an entity".to_owned())?,
+                );
+            }
+            "--depth" => {
+                options.depth = Some(
+                    args.next()
+                        .and_then(|depth| depth.parse().ok())
+                        .ok_or_else(|| "--depth must be a number".to_owned())?,
+                );
+            }
+            "--filter" => {
+                options.filter = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--filter must be followed by a pattern".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    match top {
+        Some(top) if !options.files.is_empty() => {
+            options.top = top;
+            Ok(options)
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// The instance tree of the top level entity as text
+fn tree(options: &TreeOptions) -> Result<String, String> {
+    let project = load_project(&options.files)?;
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    let top = hierarchy
+        .top_unit(&options.top)
+        .ok_or_else(|| format!("Found no single entity {}", options.top))?;
+    let tree = hierarchy
+        .tree(&top, options.depth)
+        .ok_or_else(|| format!("Found no entity {}", top))?;
+    let tree = match options.filter {
+        Some(ref pattern) => tree.filter(pattern),
+        None => Some(tree),
+    };
+    Ok(tree.map(|tree| tree.pretty_string()).unwrap_or_default())
+}
+
+#[derive(PartialEq, Debug)]
+struct ConvertOptions {
+    indentation: Indentation,
+    name: String,
+    files: Vec<String>,
+}
+
+fn parse_convert_options(args: &[String]) -> Result<ConvertOptions, String> {
+    let mut indentation = Indentation {
+        size: 2,
+        tabs: false,
+    };
+    let mut names = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tabs" => indentation.tabs = true,
+            "--indent-size" => {
+                indentation.size = args
+                    .next()
+                    .and_then(|size| size.parse().ok())
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| "--indent-size must be a positive number".to_owned())?;
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => names.push(arg.clone()),
+        }
+    }
+    if names.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let name = names.remove(0);
+    Ok(ConvertOptions {
+        indentation,
+        name,
+        files: names,
+    })
+}
+
+fn convert_unit(options: &ConvertOptions) -> Result<String, String> {
+    let project = load_project(&options.files)?;
+    convert(&project.files(), &options.name, &options.indentation)
+        .ok_or_else(|| format!("Found no entity or component {}", options.name))
+}
+
+#[derive(PartialEq, Debug)]
+struct HeaderOptions {
+    language: HeaderLanguage,
+    output: Option<String>,
+    package: String,
+    files: Vec<String>,
+}
+
+fn parse_header_options(args: &[String]) -> Result<HeaderOptions, String> {
+    let mut language = HeaderLanguage::C;
+    let mut output = None;
+    let mut names = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--language" => {
+                language = args
+                    .next()
+                    .ok_or_else(|| "--language must be followed by a language".to_owned())?
+                    .parse()?;
+            }
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => names.push(arg.clone()),
+        }
+    }
+    if names.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let package = names.remove(0);
+    Ok(HeaderOptions {
+        language,
+        output,
+        package,
+        files: names,
+    })
+}
+
+/// Prints the definitions of the package or writes them to the output file
+fn header_of_package(options: &HeaderOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let text = header(&project.files(), &options.package, options.language)
+        .ok_or_else(|| format!("Found no package {}", options.package))?;
+    print_or_write(options.output.as_deref(), &text)?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct ScriptOptions {
+    simulator: Simulator,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_script_options(args: &[String]) -> Result<ScriptOptions, String> {
+    let mut simulator = None;
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--simulator" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| "--simulator must be followed by a simulator".to_owned())?;
+                simulator = Some(name.parse()?);
+            }
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    match simulator {
+        Some(simulator) if !files.is_empty() => Ok(ScriptOptions {
+            simulator,
+            output,
+            files,
+        }),
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// Prints the contents or writes them to the output file
+fn print_or_write(output: Option<&str>, contents: &str) -> Result<(), String> {
+    match output {
+        Some(output) => fs::write(output, contents)
+            .map_err(|err| format!("Could not write {}: {}", output, err)),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+/// Prints the compile script or writes it to the output file and reports circular
+/// dependencies
+fn script(options: &ScriptOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let script = compile_script(&compile_steps(&project), options.simulator);
+    print_or_write(options.output.as_deref(), &script)?;
+    Ok(report_cycles(&project.dependency_graph()))
+}
+
+#[derive(PartialEq, Debug)]
+struct FilesOptions {
+    format: FileListFormat,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_files_options(args: &[String]) -> Result<FilesOptions, String> {
+    let mut options = FilesOptions {
+        format: FileListFormat::Plain,
+        output: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                options.format = args
+                    .next()
+                    .ok_or_else(|| "--format must be followed by a format".to_owned())?
+                    .parse()?;
+            }
+            "--output" => {
+                options.output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        Err(USAGE.to_owned())
+    } else {
+        Ok(options)
+    }
+}
+
+/// Prints the file list or writes it to the output file and reports circular dependencies
+fn files(options: &FilesOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let file_list = file_list(&compile_steps(&project), options.format);
+    print_or_write(options.output.as_deref(), &file_list)?;
+    Ok(report_cycles(&project.dependency_graph()))
+}
+
+/// The parse tree of a file
+#[derive(Serialize)]
+struct FileAst<'a> {
+    file_name: &'a str,
+    library_name: &'a str,
+    design_file: &'a DesignFile,
+}
+
+fn parse_dump_ast_options(args: &[String]) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("json") => {}
+                _ => {
+                    return Err("The only supported format is json".to_owned());
+                }
+            },
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    if files.is_empty() {
+        return Err(USAGE.to_owned());
+    }
+    Ok(files)
+}
+
+/// A JSON array of the parse tree of each file and the syntax errors
+fn dump_ast(files: &[String]) -> Result<(String, Vec<Message>), String> {
+    let project = load_project(files)?;
+    let files = project.files();
+    let asts: Vec<FileAst> = files
+        .iter()
+        .map(|file| FileAst {
+            file_name: file.file_name(),
+            library_name: &file.library_name,
+            design_file: &file.design_file,
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&asts).map_err(|err| err.to_string())?;
+    let errors = project
+        .messages()
+        .into_iter()
+        .filter(|message| message.severity == Severity::Error)
+        .collect();
+    Ok((json, errors))
+}
+
+#[derive(PartialEq, Debug)]
+struct SyntaxOptions {
+    standard: VHDLStandard,
+    /// The file name of stdin within messages
+    stdin_filename: Option<String>,
+    file: String,
+}
+
+fn parse_syntax_options(args: &[String]) -> Result<SyntaxOptions, String> {
+    let mut standard = VHDLStandard::default();
+    let mut stdin_filename = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--standard" => {
+                standard = args
+                    .next()
+                    .ok_or_else(|| "--standard must be followed by a year".to_owned())?
+                    .parse()?;
+            }
+            "--stdin-filename" => {
+                stdin_filename = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--stdin-filename must be followed by a name".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    if files.len() != 1 {
+        return Err(USAGE.to_owned());
+    }
+    Ok(SyntaxOptions {
+        standard,
+        stdin_filename,
+        file: files.remove(0),
+    })
+}
+
+/// The one based line and column of a character offset
+fn line_and_column(bytes: &[u8], offset: usize) -> (usize, usize) {
+    let before = &bytes[..offset.min(bytes.len())];
+    let line = before.iter().filter(|byte| **byte == b'\n').count() + 1;
+    let column = match before.iter().rposition(|byte| *byte == b'\n') {
+        Some(newline) => offset - newline,
+        None => offset + 1,
+    };
+    (line, column)
+}
+
+/// The file, line and column of a position as file:line:column
+fn location(pos: &SrcPos) -> String {
+    let (line, column) = match pos.source.contents() {
+        Ok(contents) => line_and_column(&contents.bytes, pos.start),
+        Err(_) => (0, 0),
+    };
+    format!(
+        "{}:{}:{}",
+        pos.source.file_name().unwrap_or("<unknown file>"),
+        line,
+        column
+    )
+}
+
+/// The syntax messages of the source as file:line:column: severity: message
+fn syntax_lines(source: &Source, standard: VHDLStandard) -> Result<Vec<String>, String> {
+    let contents = source.contents().map_err(|err| err.to_string())?;
+    let options = ParseOptions {
+        standard,
+        ..ParseOptions::default()
+    };
+    let mut messages = Vec::new();
+    match VHDLParser::new().parse_design_source_with_options(source, &options, &mut messages) {
+        Ok(_) => {}
+        Err(ParserError::Message(message)) => messages.push(message),
+        Err(ParserError::IOError(err)) => return Err(err.to_string()),
+    }
+    let file_name = source.file_name().unwrap_or("-");
+    Ok(messages
+        .iter()
+        .map(|message| {
+            let (line, column) = line_and_column(&contents.bytes, message.pos.start);
+            let severity = match message.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Hint => "hint",
+            };
+            format!(
+                "{}:{}:{}: {}: {}",
+                file_name, line, column, severity, message.message
+            )
+        })
+        .collect())
+}
+
+/// Prints the syntax messages of the file or stdin and returns the exit code
+fn syntax(options: &SyntaxOptions) -> Result<i32, String> {
+    let source = if options.file == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(|err| format!("Could not read stdin: {}", err))?;
+        let file_name = options.stdin_filename.as_deref().unwrap_or("-");
+        Source::from_file_contents(file_name, &contents)?
+    } else {
+        Source::from_file(&options.file)
+    };
+    let lines = syntax_lines(&source, options.standard)?;
+    for line in lines.iter() {
+        println!("{}", line);
+    }
+    Ok(if lines.iter().any(|line| line.contains(": error: ")) {
+        1
+    } else {
+        0
+    })
+}
+
+/// Each duplicated unit followed by the library and location of its declarations
+fn duplicates_report(project: &Project) -> Vec<String> {
+    let mut lines = Vec::new();
+    for duplicate in duplicate_units(&project.files()) {
+        lines.push(format!(
+            "{} is declared {} times",
+            duplicate.key,
+            duplicate.declarations.len()
+        ));
+        for (library_name, pos) in duplicate.declarations.iter() {
+            let (line, column) = match pos.source.contents() {
+                Ok(contents) => line_and_column(&contents.bytes, pos.start),
+                Err(_) => (0, 0),
+            };
+            lines.push(format!(
+                "  {}: {}:{}:{}",
+                library_name,
+                pos.source.file_name().unwrap_or("<unknown file>"),
+                line,
+                column
+            ));
+        }
+    }
+    lines
+}
+
+/// The count of each node kind followed by the syntax errors with their location
+fn coverage_report(project: &Project) -> Vec<String> {
+    let coverage = coverage(&project.files());
+    let width = coverage.kinds.keys().map(String::len).max().unwrap_or(0);
+    let mut lines: Vec<String> = coverage
+        .kinds
+        .iter()
+        .map(|(kind, count)| format!("{:<width$} {:>8}", kind, count, width = width))
+        .collect();
+    lines.push(format!(
+        "Found {} syntax errors",
+        coverage.syntax_errors.len()
+    ));
+    for error in coverage.syntax_errors.iter() {
+        let (line, column) = match error.pos.source.contents() {
+            Ok(contents) => line_and_column(&contents.bytes, error.pos.start),
+            Err(_) => (0, 0),
+        };
+        lines.push(format!(
+            "{}:{}:{}: {}",
+            error.pos.source.file_name().unwrap_or("<unknown file>"),
+            line,
+            column,
+            error.message
+        ));
+    }
+    lines
+}
+
+#[derive(PartialEq, Debug)]
+struct DeadCodeOptions {
+    top: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_dead_code_options(args: &[String]) -> Result<DeadCodeOptions, String> {
+    let mut options = DeadCodeOptions {
+        top: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                options.top = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--top must be followed by an entity".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        Err(USAGE.to_owned())
+    } else {
+        Ok(options)
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct YosysJsonOptions {
+    top: Option<String>,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_yosys_json_options(args: &[String]) -> Result<YosysJsonOptions, String> {
+    let mut options = YosysJsonOptions {
+        top: None,
+        output: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                options.top = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--top must be followed by an entity".to_owned())?,
+                );
+            }
+            "--output" => {
+                options.output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        Err(USAGE.to_owned())
+    } else {
+        Ok(options)
+    }
+}
+
+/// Prints the Yosys JSON netlist of the project or writes it to the output file
+fn yosys_json(options: &YosysJsonOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let top = match options.top {
+        Some(ref top) => Some(
+            InstanceHierarchy::new(&project.files())
+                .top_unit(top)
+                .ok_or_else(|| format!("Found no single entity {}", top))?,
+        ),
+        None => None,
+    };
+    let design = yosys_design(&project, top.as_ref());
+    let json = serde_json::to_string_pretty(&design).map_err(|err| err.to_string())?;
+    print_or_write(options.output.as_deref(), &format!("{}\n", json))?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct NetlistOptions {
+    top: String,
+    text: bool,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_netlist_options(args: &[String]) -> Result<NetlistOptions, String> {
+    let mut top = None;
+    let mut text = false;
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                top = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--top must be followed by an entity".to_owned())?,
+                );
+            }
+            "--format" => match args.next().map(String::as_str) {
+                Some("json") => text = false,
+                Some("text") => text = true,
+                Some(format) => return Err(format!("Unknown format {}", format)),
+                None => return Err("--format must be followed by json or text".to_owned()),
+            },
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    match top {
+        Some(top) if !files.is_empty() => Ok(NetlistOptions {
+            top,
+            text,
+            output,
+            files,
+        }),
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// Prints the structural netlist below the top or writes it to the output file
+fn write_netlist(options: &NetlistOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let top = InstanceHierarchy::new(&project.files())
+        .top_unit(&options.top)
+        .ok_or_else(|| format!("Found no single entity {}", options.top))?;
+    let netlist =
+        netlist(&project, &top).ok_or_else(|| format!("Found no single entity {}", options.top))?;
+    let contents = if options.text {
+        netlist_text(&netlist)
+    } else {
+        serde_json::to_string_pretty(&netlist).map_err(|err| err.to_string())?
+    };
+    print_or_write(options.output.as_deref(), &format!("{}\n", contents))?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct RunOptions {
+    top: String,
+    stop_time: Option<Time>,
+    vcd: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, String> {
+    let mut stop_time = None;
+    let mut vcd = None;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stop-time" => {
+                let time = args
+                    .next()
+                    .ok_or_else(|| "--stop-time must be followed by a time".to_owned())?;
+                stop_time =
+                    Some(parse_time(time).ok_or_else(|| format!("Invalid stop time {}", time))?);
+            }
+            "--vcd" => {
+                vcd = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--vcd must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.len() < 2 {
+        return Err(USAGE.to_owned());
+    }
+    let files = positional.split_off(1);
+    Ok(RunOptions {
+        top: positional.remove(0),
+        stop_time,
+        vcd,
+        files,
+    })
+}
+
+/// Simulates the top and prints its reports, the exit code is 1 when the simulation
+/// failed or reported errors
+fn run_simulation(options: &RunOptions, output: &mut dyn Write) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let files = project.files();
+    let top = InstanceHierarchy::new(&files)
+        .top_unit(&options.top)
+        .ok_or_else(|| format!("Found no single entity {}", options.top))?;
+    let mut simulation = Simulation::new(&files);
+    if let Err(message) = simulation.elaborate(&top) {
+        eprintln!("{}", message.pretty_string());
+        return Ok(1);
+    }
+    if let Some(ref file_name) = options.vcd {
+        let file = fs::File::create(file_name)
+            .map_err(|err| format!("Could not write {}: {}", file_name, err))?;
+        simulation.dump_vcd(Box::new(io::BufWriter::new(file)));
+    }
+    let result = simulation.run(options.stop_time, output);
+    if let Some(ref file_name) = options.vcd {
+        simulation
+            .finish_vcd()
+            .map_err(|err| format!("Could not write {}: {}", file_name, err))?;
+    }
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(message) => {
+            eprintln!("{}", message.pretty_string());
+            return Ok(1);
+        }
+    };
+    let reason = match summary.finish {
+        Finish::Quiet => "no more events",
+        Finish::StopTime => "stop time",
+        Finish::Stop => "stopped",
+        Finish::Failure => "failure",
+    };
+    eprintln!(
+        "Simulation ended at {} ({}) with {} error(s)",
+        time_image(summary.time),
+        reason,
+        summary.errors
+    );
+    if summary.finish == Finish::Failure || summary.errors > 0 {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct SqlOptions {
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_sql_options(args: &[String]) -> Result<SqlOptions, String> {
+    let mut options = SqlOptions {
+        output: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => {
+                options.output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        Err(USAGE.to_owned())
+    } else {
+        Ok(options)
+    }
+}
+
+/// Prints the SQL script of the symbol database or writes it to the output file
+fn sql(options: &SqlOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    print_or_write(options.output.as_deref(), &symbol_database_sql(&project))?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct LsifOptions {
+    root: Option<String>,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_lsif_options(args: &[String]) -> Result<LsifOptions, String> {
+    let mut options = LsifOptions {
+        root: None,
+        output: None,
+        files: Vec::new(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => {
+                options.root = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--root must be followed by a directory".to_owned())?,
+                );
+            }
+            "--output" => {
+                options.output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.files.is_empty() {
+        Err(USAGE.to_owned())
+    } else {
+        Ok(options)
+    }
+}
+
+/// Prints the LSIF index of the project or writes it to the output file
+fn lsif(options: &LsifOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let root = match options.root {
+        Some(ref root) => Path::new(root).to_owned(),
+        None => env::current_dir().map_err(|err| err.to_string())?,
+    };
+    let root = root
+        .canonicalize()
+        .map_err(|err| format!("Could not find {}: {}", root.to_string_lossy(), err))?;
+    let mut lines = String::new();
+    for element in lsif_index(&project, &root.to_string_lossy()) {
+        lines.push_str(&element.to_string());
+        lines.push('\n');
+    }
+    print_or_write(options.output.as_deref(), &lines)?;
+    Ok(0)
+}
+
+#[derive(PartialEq, Debug)]
+struct TopInterfaceOptions {
+    top: String,
+    yaml: bool,
+    generics: Vec<(String, String)>,
+    output: Option<String>,
+    files: Vec<String>,
+}
+
+fn parse_top_interface_options(args: &[String]) -> Result<TopInterfaceOptions, String> {
+    let mut top = None;
+    let mut yaml = false;
+    let mut generics = Vec::new();
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                top = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--top must be followed by an entity".to_owned())?,
+                );
+            }
+            "--format" => match args.next().map(String::as_str) {
+                Some("json") => yaml = false,
+                Some("yaml") => yaml = true,
+                Some(format) => return Err(format!("Unknown format {}", format)),
+                None => return Err("--format must be followed by json or yaml".to_owned()),
+            },
+            "--generic" => {
+                let generic = args
+                    .next()
+                    .ok_or_else(|| "--generic must be followed by NAME=VALUE".to_owned())?;
+                let (name, value) = generic
+                    .split_once('=')
+                    .ok_or_else(|| format!("Generic {} must be NAME=VALUE", generic))?;
+                generics.push((name.to_owned(), value.to_owned()));
+            }
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--output must be followed by a file".to_owned())?,
+                );
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    match top {
+        Some(top) if !files.is_empty() => Ok(TopInterfaceOptions {
+            top,
+            yaml,
+            generics,
+            output,
+            files,
+        }),
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// Prints the interface of the top or writes it to the output file
+fn write_top_interface(options: &TopInterfaceOptions) -> Result<i32, String> {
+    let project = load_project(&options.files)?;
+    let top = InstanceHierarchy::new(&project.files())
+        .top_unit(&options.top)
+        .ok_or_else(|| format!("Found no single entity {}", options.top))?;
+    let interface = top_interface(&project, &top, &options.generics)?;
+    let contents = if options.yaml {
+        top_interface_yaml(&interface)
+    } else {
+        let json = serde_json::to_string_pretty(&interface).map_err(|err| err.to_string())?;
+        format!("{}\n", json)
+    };
+    print_or_write(options.output.as_deref(), &contents)?;
+    Ok(0)
+}
+
+/// A heading for each kind of unused declaration followed by their designators and locations
+fn dead_code_report(project: &Project, top: Option<&str>) -> Result<Vec<String>, String> {
+    let top = match top {
+        Some(top) => Some(
+            InstanceHierarchy::new(&project.files())
+                .top_unit(top)
+                .ok_or_else(|| format!("Found no single entity {}", top))?,
+        ),
+        None => None,
+    };
+    let resolution = project.resolve();
+    let unused = dead_code(project, &resolution, top.as_ref());
+    let entities_heading = match top {
+        Some(ref top) => format!("Entities not instantiated below {}", top),
+        None => "Entities never instantiated".to_owned(),
+    };
+    let mut lines = Vec::new();
+    let mut add_section = |heading: String, entities: &[&NamedEntity]| {
+        lines.push(format!("{}: {}", heading, entities.len()));
+        for entity in entities.iter() {
+            lines.push(format!(
+                "  {}: {}",
+                entity.designator,
+                location(&entity.pos)
+            ));
+        }
+    };
+    add_section(entities_heading, &unused.entities);
+    add_section("Packages never used".to_owned(), &unused.packages);
+    add_section("Subprograms never called".to_owned(), &unused.subprograms);
+    Ok(lines)
+}
+
+/// Each instantiated entity followed by its instances and their inconsistent associations,
+/// together with whether all instances are consistent
+fn interfaces_report(project: &Project) -> (Vec<String>, bool) {
+    let entities = interface_consistency(&project.files());
+    let mut lines = Vec::new();
+    for entity in entities.iter() {
+        let inconsistent = entity
+            .instances
+            .iter()
+            .filter(|instance| !instance.inconsistencies.is_empty())
+            .count();
+        lines.push(format!(
+            "{}: {} instances, {} inconsistent",
+            entity.entity,
+            entity.instances.len(),
+            inconsistent
+        ));
+        for instance in entity.instances.iter() {
+            lines.push(format!(
+                "  {} {}: {}",
+                instance.architecture,
+                instance.label,
+                location(&instance.pos)
+            ));
+            for inconsistency in instance.inconsistencies.iter() {
+                let pos = inconsistency.pos().unwrap_or(&instance.pos);
+                lines.push(format!("    {}: {}", location(pos), inconsistency));
+            }
+        }
+    }
+    (lines, entities.iter().all(|entity| entity.is_consistent()))
+}
+
+/// Each instance which is not bound within the project and whether all of them are
+/// configured blackboxes
+fn blackboxes_report(project: &Project) -> (Vec<String>, bool) {
+    let blackboxes = blackboxes(project);
+    let mut lines = Vec::new();
+    for blackbox in blackboxes.iter() {
+        let kind = if blackbox.configured {
+            "blackbox".to_owned()
+        } else {
+            match blackbox.similar {
+                Some(ref entity) => format!("unbound, did you mean {}?", entity),
+                None => "unbound".to_owned(),
+            }
+        };
+        lines.push(format!(
+            "{} {}: {} {} ({})",
+            blackbox.architecture,
+            blackbox.label,
+            blackbox.unit_name,
+            kind,
+            location(&blackbox.pos)
+        ));
+    }
+    let configured = blackboxes
+        .iter()
+        .filter(|blackbox| blackbox.configured)
+        .count();
+    lines.push(format!(
+        "Found {} blackboxes and {} unbound instances",
+        configured,
+        blackboxes.len() - configured
+    ));
+    (lines, configured == blackboxes.len())
+}
+
+/// Answers the requests of each line of the input until shutdown or the end of input
+fn serve(
+    server: &mut AnalysisServer,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle(&line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+        if server.is_shutdown() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The lines of context around the changes of a unified diff
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(PartialEq, Debug)]
+struct RenameOptions {
+    kind: Option<NamedEntityKind>,
+    /// Only declarations within this file are renamed
+    file_name: Option<String>,
+    patch: bool,
+    old_name: String,
+    new_name: String,
+    files: Vec<String>,
+}
+
+fn parse_rename_options(args: &[String]) -> Result<RenameOptions, String> {
+    let mut kind = None;
+    let mut file_name = None;
+    let mut patch = false;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--kind" => {
+                kind = Some(
+                    args.next()
+                        .ok_or_else(|| "--kind must be followed by a kind".to_owned())?
+                        .parse()?,
+                );
+            }
+            "--in" => {
+                file_name = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| "--in must be followed by a file".to_owned())?,
+                );
+            }
+            "--patch" => patch = true,
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown option {}", arg));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    if positional.len() < 3 {
+        return Err(USAGE.to_owned());
+    }
+    let files = positional.split_off(2);
+    let new_name = positional.pop().unwrap_or_default();
+    let old_name = positional.pop().unwrap_or_default();
+    Ok(RenameOptions {
+        kind,
+        file_name,
+        patch,
+        old_name,
+        new_name,
+        files,
+    })
+}
+
+/// A line of a diff
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum DiffLine {
+    /// The index of an unchanged line in the old and new lines
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// The shortest edit script from the old to the new lines by the algorithm of Myers
+fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let max = old.len() + new.len();
+    let offset = max as isize;
+    // The furthest old line reached on each diagonal k = x - y, for each number of edits
+    let mut furthest = vec![0usize; 2 * max + 2];
+    let mut trace = Vec::new();
+    'edits: for edits in 0..=max as isize {
+        trace.push(furthest.clone());
+        for k in (-edits..=edits).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -edits || (k != edits && furthest[index - 1] < furthest[index + 1])
+            {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            while x < old.len() && y < new.len() && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index] = x;
+            if x >= old.len() && y >= new.len() {
+                break 'edits;
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (old.len(), new.len());
+    for (edits, furthest) in trace.iter().enumerate().rev() {
+        let edits = edits as isize;
+        let k = x as isize - y as isize;
+        let index = (k + offset) as usize;
+        // The edits start from the first lines
+        let (previous_x, previous_y) = if edits == 0 {
+            (0, 0)
+        } else {
+            let previous_k =
+                if k == -edits || (k != edits && furthest[index - 1] < furthest[index + 1]) {
+                    k + 1
+                } else {
+                    k - 1
+                };
+            let previous_x = furthest[(previous_k + offset) as usize];
+            (previous_x, (previous_x as isize - previous_k) as usize)
+        };
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            lines.push(DiffLine::Same(x, y));
+        }
+        if edits > 0 {
+            if x == previous_x {
+                lines.push(DiffLine::Added(y - 1));
+            } else {
+                lines.push(DiffLine::Removed(x - 1));
+            }
+            x = previous_x;
+            y = previous_y;
+        }
+    }
+    lines.reverse();
+    lines
+}
+
+/// A unified diff of the old and new contents
+fn unified_diff(file_name: &str, old: &str, new: &str) -> String {
+    let lines = |text: &str| -> Vec<String> {
+        let mut lines: Vec<String> = text.split('\n').map(|line| format!("{}\n", line)).collect();
+        if text.is_empty() || text.ends_with('\n') {
+            lines.pop();
+        } else if let Some(last) = lines.last_mut() {
+            last.pop();
+            last.push_str("\n\\ No newline at end of file\n");
+        }
+        lines
+    };
+    let old_lines = lines(old);
+    let new_lines = lines(new);
+    let diff_lines = diff_lines(&old_lines, &new_lines);
+    let changed: Vec<usize> = (0..diff_lines.len())
+        .filter(|i| !matches!(diff_lines[*i], DiffLine::Same(..)))
+        .collect();
+
+    let mut diff = String::new();
+    if changed.is_empty() {
+        return diff;
+    }
+    diff.push_str(&format!("--- {0}\n+++ {0}\n", file_name));
+    let mut first = 0;
+    while first < changed.len() {
+        // Changes closer than twice the context share a hunk
+        let mut last = first;
+        while last + 1 < changed.len() && changed[last + 1] <= changed[last] + 2 * DIFF_CONTEXT {
+            last += 1;
+        }
+        let start = changed[first].saturating_sub(DIFF_CONTEXT);
+        let end = (changed[last] + DIFF_CONTEXT + 1).min(diff_lines.len());
+        let hunk = &diff_lines[start..end];
+        // The lines before the hunk and within it, a hunk without lines starts after the line
+        let count = |lines: &[DiffLine], old: bool| {
+            lines
+                .iter()
+                .filter(|line| match line {
+                    DiffLine::Same(..) => true,
+                    DiffLine::Removed(..) => old,
+                    DiffLine::Added(..) => !old,
+                })
+                .count()
+        };
+        let range = |old: bool| {
+            let before = count(&diff_lines[..start], old);
+            let length = count(hunk, old);
+            (if length > 0 { before + 1 } else { before }, length)
+        };
+        let (old_start, old_length) = range(true);
+        let (new_start, new_length) = range(false);
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_length, new_start, new_length
+        ));
+        let mut i = 0;
+        while i < hunk.len() {
+            if let DiffLine::Same(x, _) = hunk[i] {
+                diff.push_str(&format!(" {}", old_lines[x]));
+                i += 1;
+                continue;
+            }
+            // The removed lines of a change come before the added lines
+            let mut run_end = i;
+            while run_end < hunk.len() && !matches!(hunk[run_end], DiffLine::Same(..)) {
+                run_end += 1;
+            }
+            for line in hunk[i..run_end].iter() {
+                if let DiffLine::Removed(x) = line {
+                    diff.push_str(&format!("-{}", old_lines[*x]));
+                }
+            }
+            for line in hunk[i..run_end].iter() {
+                if let DiffLine::Added(y) = line {
+                    diff.push_str(&format!("+{}", new_lines[*y]));
+                }
+            }
+            i = run_end;
+        }
+        first = last + 1;
+    }
+    diff
+}
+
+/// Renames the declaration in place and returns a summary, or returns the patch
+fn rename(options: &RenameOptions) -> Result<String, String> {
+    let project = load_project(&options.files)?;
+    let resolution = project.resolve();
+    let declarations = find_declarations(
+        &resolution,
+        &options.old_name,
+        options.kind,
+        options.file_name.as_deref(),
+    );
+    let entity = match declarations.as_slice() {
+        [] => {
+            return Err(format!("Found no declaration of {}", options.old_name));
+        }
+        [entity] => entity,
+        _ => {
+            let mut message = format!(
+                "{} is declared {} times, select one with --kind or --in",
+                options.old_name,
+                declarations.len()
+            );
+            for entity in declarations.iter() {
+                let (line, column) = match entity.pos.source.contents() {
+                    Ok(contents) => line_and_column(&contents.bytes, entity.pos.start),
+                    Err(_) => (0, 0),
+                };
+                message.push_str(&format!(
+                    "\n  {:?} at {}:{}:{}",
+                    entity.kind,
+                    entity.pos.source.file_name().unwrap_or("<unknown file>"),
+                    line,
+                    column
+                ));
+            }
+            return Err(message);
+        }
+    };
+
+    let positions = rename_positions(&resolution, entity, &options.new_name)?;
+    let contents =
+        renamed_contents(&positions, &options.new_name).map_err(|err| err.to_string())?;
+    if options.patch {
+        return Ok(contents
+            .iter()
+            .map(|(file_name, old, new)| {
+                unified_diff(file_name, &old.to_string(), &new.to_string())
+            })
+            .collect());
+    }
+    for (file_name, _, new) in contents.iter() {
+        fs::write(file_name, &new.bytes)
+            .map_err(|err| format!("Could not write {}: {}", file_name, err))?;
+    }
+    Ok(format!(
+        "Renamed {} occurrences in {} files\n",
+        positions.len(),
+        contents.len()
+    ))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, args)) if command == "check" => {
+            let watch_mode = args.iter().any(|arg| arg == "--watch");
+            let external = args.iter().any(|arg| arg == "--external");
+            let files: Vec<String> = args
+                .iter()
+                .filter(|arg| *arg != "--watch" && *arg != "--external")
+                .cloned()
+                .collect();
+            if files.is_empty() {
+                Err(USAGE.to_owned())
+            } else if external && watch_mode {
+                load_project(&files).and_then(|project| {
+                    watch(project, |project| match check_external(project) {
+                        Ok(messages) => report(&messages),
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            2
+                        }
+                    })
+                })
+            } else if external {
+                load_project(&files)
+                    .and_then(|project| check_external(&project))
+                    .map(|messages| report(&messages))
+            } else if watch_mode {
+                load_project(&files)
+                    .and_then(|project| watch(project, |project| report(&check(project))))
+            } else {
+                load_project(&files).map(|project| report(&check(&project)))
+            }
+        }
+        Some((command, args)) if command == "fmt" => {
+            parse_fmt_options(args).and_then(|options| fmt(&options))
+        }
+        Some((command, args)) if command == "lint" => {
+            parse_lint_options(args).and_then(|options| {
+                let config = lint_config(&options)?;
+                let project = load_project(&options.files)?;
+                if options.watch {
+                    watch(project, |project| match lint_messages(&config, project) {
+                        Ok((findings, waivers)) => {
+                            if options.waivers {
+                                report_waivers(&waivers);
+                            }
+                            report_lint(&options, &lint(&options, findings))
+                        }
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            2
+                        }
+                    })
+                } else {
+                    let (findings, waivers) = lint_messages(&config, &project)?;
+                    if options.waivers {
+                        report_waivers(&waivers);
+                    }
+                    Ok(report_lint(&options, &lint(&options, findings)))
+                }
+            })
+        }
+        Some((command, args)) if command == "graph" => {
+            parse_graph_options(args).and_then(|options| {
+                let project = load_project(&options.files)?;
+                let graph = project.dependency_graph();
+                print!("{}", graph.to_dot(options.kind));
+                Ok(report_cycles(&graph))
+            })
+        }
+        Some((command, args)) if command == "fsm" => parse_fsm_options(args).and_then(|options| {
+            let project = load_project(&options.files)?;
+            if options.dot {
+                for fsm in state_machines(&project.files()) {
+                    print!("{}", fsm.to_dot());
+                }
+            } else {
+                for line in fsm_report(&project) {
+                    println!("{}", line);
+                }
+            }
+            Ok(0)
+        }),
+        Some((command, args)) if command == "stats" => {
+            let json = args.iter().any(|arg| arg == "--json");
+            let files: Vec<String> = args
+                .iter()
+                .filter(|arg| *arg != "--json")
+                .cloned()
+                .collect();
+            if files.is_empty() {
+                Err(USAGE.to_owned())
+            } else {
+                stats(&files).and_then(|stats| {
+                    if json {
+                        let json =
+                            serde_json::to_string_pretty(&stats).map_err(|err| err.to_string())?;
+                        println!("{}", json);
+                    } else {
+                        print!("{}", stats_table(&stats));
+                    }
+                    Ok(0)
+                })
+            }
+        }
+        Some((command, args)) if command == "memory" => {
+            let json = args.iter().any(|arg| arg == "--json");
+            let files: Vec<String> = args
+                .iter()
+                .filter(|arg| *arg != "--json")
+                .cloned()
+                .collect();
+            if files.is_empty() {
+                Err(USAGE.to_owned())
+            } else {
+                load_project(&files).and_then(|project| {
+                    let report = project.memory_report();
+                    if json {
+                        let json =
+                            serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?;
+                        println!("{}", json);
+                    } else {
+                        print!("{}", memory_table(&report));
+                    }
+                    Ok(0)
+                })
+            }
+        }
+        Some((command, args)) if command == "bench" => {
+            parse_bench_options(args).and_then(|options| {
+                let result = bench(&options)?;
+                if options.json {
+                    let json =
+                        serde_json::to_string_pretty(&result).map_err(|err| err.to_string())?;
+                    println!("{}", json);
+                } else {
+                    print!("{}", bench_table(&result));
+                }
+                Ok(0)
+            })
+        }
+        Some((command, args)) if command == "doc" => {
+            parse_doc_options(args).and_then(|options| doc(&options))
+        }
+        Some((command, args)) if command == "tree" => parse_tree_options(args)
+            .and_then(|options| tree(&options))
+            .map(|tree| {
+                println!("{}", tree);
+                0
+            }),
+        Some((command, args)) if command == "convert" => parse_convert_options(args)
+            .and_then(|options| convert_unit(&options))
+            .map(|text| {
+                println!("{}", text);
+                0
+            }),
+        Some((command, args)) if command == "script" => {
+            parse_script_options(args).and_then(|options| script(&options))
+        }
+        Some((command, args)) if command == "header" => {
+            parse_header_options(args).and_then(|options| header_of_package(&options))
+        }
+        Some((command, args)) if command == "files" => {
+            parse_files_options(args).and_then(|options| files(&options))
+        }
+        Some((command, args)) if command == "syntax" => {
+            parse_syntax_options(args).and_then(|options| syntax(&options))
+        }
+        Some((command, files)) if command == "duplicates" && !files.is_empty() => {
+            load_project(files).map(|project| {
+                let lines = duplicates_report(&project);
+                for line in lines.iter() {
+                    println!("{}", line);
+                }
+                if lines.is_empty() {
+                    0
+                } else {
+                    1
+                }
+            })
+        }
+        Some((command, files)) if command == "coverage" && !files.is_empty() => load_project(files)
+            .map(|project| {
+                for line in coverage_report(&project) {
+                    println!("{}", line);
+                }
+                0
+            }),
+        Some((command, args)) if command == "rename" => parse_rename_options(args)
+            .and_then(|options| rename(&options))
+            .map(|output| {
+                print!("{}", output);
+                0
+            }),
+        Some((command, args)) if command == "dead-code" => parse_dead_code_options(args)
+            .and_then(|options| {
+                let project = load_project(&options.files)?;
+                dead_code_report(&project, options.top.as_deref())
+            })
+            .map(|lines| {
+                for line in lines {
+                    println!("{}", line);
+                }
+                0
+            }),
+        Some((command, files)) if command == "interfaces" && !files.is_empty() => {
+            load_project(files).map(|project| {
+                let (lines, consistent) = interfaces_report(&project);
+                for line in lines.iter() {
+                    println!("{}", line);
+                }
+                if consistent {
+                    0
+                } else {
+                    1
+                }
+            })
+        }
+        Some((command, files)) if command == "blackboxes" && !files.is_empty() => {
+            load_project(files).map(|project| {
+                let (lines, configured) = blackboxes_report(&project);
+                for line in lines.iter() {
+                    println!("{}", line);
+                }
+                if configured {
+                    0
+                } else {
+                    1
+                }
+            })
+        }
+        Some((command, files)) if command == "serve" && !files.is_empty() => load_project(files)
+            .and_then(|project| {
+                let stdin = io::stdin();
+                let stdout = io::stdout();
+                serve(
+                    &mut AnalysisServer::new(project),
+                    stdin.lock(),
+                    &mut stdout.lock(),
+                )
+                .map(|()| 0)
+                .map_err(|err| format!("Failed to serve: {}", err))
+            }),
+        Some((command, args)) if command == "yosys-json" => {
+            parse_yosys_json_options(args).and_then(|options| yosys_json(&options))
+        }
+        Some((command, args)) if command == "top-interface" => {
+            parse_top_interface_options(args).and_then(|options| write_top_interface(&options))
+        }
+        Some((command, args)) if command == "lsif" => {
+            parse_lsif_options(args).and_then(|options| lsif(&options))
+        }
+        Some((command, args)) if command == "sql" => {
+            parse_sql_options(args).and_then(|options| sql(&options))
+        }
+        Some((command, args)) if command == "netlist" => {
+            parse_netlist_options(args).and_then(|options| write_netlist(&options))
+        }
+        Some((command, args)) if command == "run" => parse_run_options(args).and_then(|options| {
+            let stdout = io::stdout();
+            run_simulation(&options, &mut stdout.lock())
+        }),
+        Some((command, args)) if command == "dump-ast" => parse_dump_ast_options(args)
+            .and_then(|files| dump_ast(&files))
+            .map(|(json, errors)| {
+                println!("{}", json);
+                for error in errors.iter() {
+                    eprintln!("{}", error.pretty_string());
+                }
+                if errors.is_empty() {
+                    0
+                } else {
+                    1
+                }
+            }),
+        _ => Err(USAGE.to_owned()),
+    };
+    let code = match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            2
+        }
+    };
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use std::fs;
+
+    #[test]
+    fn exit_code_of_project_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.vhd");
+        let bad = dir.path().join("bad.vhd");
+        fs::write(&good, "entity good is\nend entity;").unwrap();
+        fs::write(&bad, "entity bad is").unwrap();
+        let config_file = dir.path().join("project.toml");
+        fs::write(&config_file, "[libraries]\nlib.files = ['good.vhd']").unwrap();
+
+        let arg = |path: &Path| path.to_string_lossy().into_owned();
+        let project = load_project(&[arg(&config_file)]).unwrap();
+        assert_eq!(report(&check(&project)), 0);
+
+        let project = load_project(&[arg(&config_file), arg(&bad)]).unwrap();
+        let messages = check(&project);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].pos.source.file_name(), Some(arg(&bad).as_str()));
+        assert_eq!(report(&messages), 1);
+
+        assert!(load_project(&[arg(&dir.path().join("missing.vhd"))]).is_err());
+    }
+
+    #[test]
+    fn check_reports_circular_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("pkgs.vhd");
+        fs::write(
+            &file_name,
+            "use work.q2.all;\npackage q1 is\nend package;\n\
+             use work.q1.all;\npackage q2 is\nend package;\n",
+        )
+        .unwrap();
+
+        let project = load_project(&[file_name.to_string_lossy().into_owned()]).unwrap();
+        let messages = check(&project);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].message,
+            "Found circular dependency work.q2 -> work.q1 -> work.q2"
+        );
+        assert_eq!(report(&messages), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn check_with_external_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(&file_name, "entity top is\nend entity;\n").unwrap();
+        let config_file = dir.path().join("project.toml");
+        fs::write(
+            &config_file,
+            "[libraries]\nlib.files = ['top.vhd']\n\n\
+             [external]\ncommand = \"echo '{file}:2:1: missing architecture'\"\n",
+        )
+        .unwrap();
+
+        let arg = |path: &Path| path.to_string_lossy().into_owned();
+        let project = load_project(&[arg(&config_file)]).unwrap();
+        let messages = check_external(&project).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message, "missing architecture");
+        assert_eq!(messages[0].code, Some("external".to_owned()));
+        assert_eq!(report(&messages), 1);
+
+        let project = load_project(&[arg(&file_name)]).unwrap();
+        assert!(check_external(&project).is_err());
+    }
+
+    #[test]
+    fn fmt_options() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+        assert_eq!(
+            parse_fmt_options(&args(&["--check", "--indent-size", "4", "a.vhd"])),
+            Ok(FmtOptions {
+                mode: FmtMode::Check,
+                preset: None,
+                indent_size: Some(4),
+                tabs: false,
+                operator_spacing: false,
+                comma_spacing: false,
+                one_item_per_line: false,
+                align_colons: false,
+                align_arrows: false,
+                align_assignments: false,
+                keyword_case: None,
+                standard_case: None,
+                declared_spelling: false,
+                align_comments: false,
+                comment_width: None,
+                max_width: None,
+                files: args(&["a.vhd"]),
+            })
+        );
+        let options = parse_fmt_options(&args(&[
+            "--operator-spacing",
+            "--comma-spacing",
+            "--one-item-per-line",
+            "--align-colons",
+            "--align-arrows",
+            "--align-assignments",
+            "--keyword-case",
+            "upper",
+            "--declared-spelling",
+            "--comment-width",
+            "80",
+            "--max-width",
+            "100",
+            "a.vhd",
+        ]))
+        .unwrap();
+        assert!(options.operator_spacing && options.comma_spacing && options.one_item_per_line);
+        assert!(options.align_colons && options.align_arrows && options.align_assignments);
+        assert_eq!(options.keyword_case, Some(Case::Upper));
+        assert!(options.declared_spelling);
+        assert_eq!(options.comment_width, Some(80));
+        assert_eq!(options.max_width, Some(100));
+        assert!(parse_fmt_options(&args(&["--comment-width", "0", "a.vhd"])).is_err());
+        assert!(parse_fmt_options(&args(&["--preset", "fancy", "a.vhd"])).is_err());
+        assert!(parse_fmt_options(&args(&["--keyword-case", "title", "a.vhd"])).is_err());
+        assert!(parse_fmt_options(&args(&["--indent-size", "0", "a.vhd"])).is_err());
+        assert!(parse_fmt_options(&args(&["--fix", "a.vhd"])).is_err());
+        assert!(parse_fmt_options(&args(&["--tabs"])).is_err());
+    }
+
+    #[test]
+    fn fmt_check_and_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(
+            &file_name,
+            "entity ent is\nport (clk : in bit);\nend entity;",
+        )
+        .unwrap();
+        let config_file = dir.path().join("project.toml");
+        fs::write(&config_file, "[libraries]\nlib.files = ['*.vhd']").unwrap();
+
+        let options = |mode| FmtOptions {
+            mode,
+            preset: None,
+            indent_size: Some(2),
+            tabs: false,
+            operator_spacing: false,
+            comma_spacing: false,
+            one_item_per_line: false,
+            align_colons: false,
+            align_arrows: false,
+            align_assignments: false,
+            keyword_case: None,
+            standard_case: None,
+            declared_spelling: false,
+            align_comments: false,
+            comment_width: None,
+            max_width: None,
+            files: vec![config_file.to_string_lossy().into_owned()],
+        };
+        assert_eq!(fmt(&options(FmtMode::Check)), Ok(1));
+        assert_eq!(fmt(&options(FmtMode::InPlace)), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&file_name).unwrap(),
+            "entity ent is\n  port (clk : in bit);\nend entity;"
+        );
+        assert_eq!(fmt(&options(FmtMode::Check)), Ok(0));
+
+        fs::write(
+            &file_name,
+            "entity ent is\n  port (clk:in bit;rst:in bit);\nend entity;",
+        )
+        .unwrap();
+        let laid_out = FmtOptions {
+            operator_spacing: true,
+            comma_spacing: true,
+            one_item_per_line: true,
+            ..options(FmtMode::InPlace)
+        };
+        assert_eq!(fmt(&laid_out), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&file_name).unwrap(),
+            "entity ent is\n  port (\n    clk : in bit;\n    rst : in bit\n  );\nend entity;"
+        );
+
+        fs::write(
+            &file_name,
+            "entity ent is\n  port (\n    clk : in bit;\n    reset : in bit);\nend entity;",
+        )
+        .unwrap();
+        let aligned = FmtOptions {
+            align_colons: true,
+            ..options(FmtMode::InPlace)
+        };
+        assert_eq!(fmt(&aligned), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&file_name).unwrap(),
+            "entity ent is\n  port (\n    clk   : in bit;\n    reset : in bit);\nend entity;"
+        );
+    }
+
+    #[test]
+    fn fmt_style_of_configuration_file_and_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("legacy")).unwrap();
+        let code = "entity ent is\nport (clk:in bit);\nend entity;";
+        let file_names = [
+            dir.path().join("ent.vhd"),
+            dir.path().join("legacy").join("ent.vhd"),
+        ];
+        for file_name in file_names.iter() {
+            fs::write(file_name, code).unwrap();
+        }
+        fs::write(
+            dir.path().join(".vhdlfmt.toml"),
+            "preset = \"spaces-4-lowercase\"\n\n\
+             [[directories]]\npath = \"legacy\"\npreset = \"classic-uppercase\"",
+        )
+        .unwrap();
+
+        let mut options =
+            parse_fmt_options(&[file_names[0].to_string_lossy().into_owned()]).unwrap();
+        options
+            .files
+            .push(file_names[1].to_string_lossy().into_owned());
+        assert_eq!(fmt(&options), Ok(0));
+        let read = |i: usize| fs::read_to_string(&file_names[i]).unwrap();
+        assert_eq!(
+            read(0),
+            "entity ent is\n    port (clk : in bit);\nend entity;"
+        );
+        assert_eq!(
+            read(1),
+            "ENTITY ent IS\n    PORT (clk : IN bit);\nEND ENTITY;"
+        );
+
+        // The options given change the style of the configuration file
+        options.indent_size = Some(2);
+        assert_eq!(fmt(&options), Ok(0));
+        assert_eq!(
+            read(0),
+            "entity ent is\n  port (clk : in bit);\nend entity;"
+        );
+
+        // A preset replaces the style of the configuration files
+        options.indent_size = None;
+        options.preset = Some("default".to_owned());
+        options.keyword_case = Some(Case::Lower);
+        assert_eq!(fmt(&options), Ok(0));
+        assert_eq!(
+            read(1),
+            "entity ent is\n  port (clk : in bit);\nend entity;"
+        );
+    }
+
+    #[test]
+    fn lint_rule_selection_and_severity() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("pkg.vhd");
+        fs::write(
+            &file_name,
+            "package pkg is\nend package;\npackage pkg is\nend package;\n\
+             package pkg2 is\ncomponent comp is\nend component bad;\nend package;",
+        )
+        .unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.clone()))
+                .collect()
+        };
+        let codes = |args: &[String]| -> Vec<String> {
+            let options = parse_lint_options(args).unwrap();
+            let project = load_project(&options.files).unwrap();
+            let config = lint_config(&options).unwrap();
+            lint(&options, lint_messages(&config, &project).unwrap().0)
+                .into_iter()
+                .filter_map(|finding| finding.code)
+                .collect()
+        };
+
+        assert_eq!(
+            codes(&args(&[])),
+            vec!["duplicate-unit", "end-identifier-mismatch"]
+        );
+        assert_eq!(
+            codes(&args(&["--disable", "duplicate-unit"])),
+            vec!["end-identifier-mismatch"]
+        );
+        assert_eq!(
+            codes(&args(&["--enable", "duplicate-unit"])),
+            vec!["duplicate-unit"]
+        );
+
+        assert_eq!(
+            codes(&args(&[
+                "--keyword-case",
+                "lower",
+                "--enable",
+                "keyword-case"
+            ])),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            codes(&args(&[
+                "--keyword-case",
+                "upper",
+                "--enable",
+                "keyword-case"
+            ]))
+            .len(),
+            16
+        );
+
+        let project_file = dir.path().join("project.toml");
+        fs::write(
+            &project_file,
+            "[libraries]\nlib.files = ['pkg.vhd']\n\n\
+             [lint]\nend-identifier-mismatch = 'off'\nduplicate-unit = 'warning'",
+        )
+        .unwrap();
+        let options = parse_lint_options(&[project_file.to_string_lossy().into_owned()]).unwrap();
+        let project = load_project(&options.files).unwrap();
+        let (findings, _) = lint_messages(&lint_config(&options).unwrap(), &project).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, Some("duplicate-unit".to_owned()));
+        assert_eq!(findings[0].severity, Severity::Warning);
+
+        fs::write(&project_file, "[lint]\nunknown = 'off'").unwrap();
+        let config = lint_config(&options).unwrap();
+        assert_eq!(
+            lint_messages(&config, &project).map(|(findings, _)| findings),
+            Err("Unknown lint rule 'unknown'".to_owned())
+        );
+
+        fs::write(
+            &project_file,
+            "[libraries]\nlib.files = ['pkg.vhd']\n\n\
+             [lint]\nend-identifier-mismatch = 'off'\nkeyword-case = { case = 'upper' }",
+        )
+        .unwrap();
+        let mut project_args = args(&[
+            "--lint",
+            "duplicate-unit=hint",
+            "--lint",
+            "end-identifier-mismatch=error",
+            "--lint",
+            "keyword-case=off",
+        ]);
+        project_args.pop();
+        project_args.push(project_file.to_string_lossy().into_owned());
+        let options = parse_lint_options(&project_args).unwrap();
+        let config = lint_config(&options).unwrap();
+        assert_eq!(config.get("keyword-case").unwrap().enabled, Some(false));
+        assert_eq!(config.get("keyword-case").unwrap().options.len(), 1);
+        let findings: Vec<(String, Severity)> = lint_messages(&config, &project)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|finding| (finding.code.unwrap(), finding.severity))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                ("duplicate-unit".to_owned(), Severity::Hint),
+                ("end-identifier-mismatch".to_owned(), Severity::Error),
+            ]
+        );
+        assert!(parse_lint_options(&args(&["--lint", "duplicate-unit"])).is_err());
+        assert!(parse_lint_options(&args(&["--lint", "duplicate-unit=fatal"])).is_err());
+
+        let waived_file = dir.path().join("waived.vhd");
+        fs::write(
+            &waived_file,
+            "package waived is\ncomponent comp is\n\
+             -- lint: waive end-identifier-mismatch \"Generated code\"\n\
+             end component other;\nend package;\n",
+        )
+        .unwrap();
+        let options = parse_lint_options(&[
+            waived_file.to_string_lossy().into_owned(),
+            "--waivers".to_owned(),
+        ])
+        .unwrap();
+        assert!(options.waivers);
+        let project = load_project(&options.files).unwrap();
+        let (findings, waivers) = lint_messages(&lint_config(&options).unwrap(), &project).unwrap();
+        assert_eq!(findings, vec![]);
+        assert_eq!(waivers.len(), 1);
+        assert_eq!(waivers[0].rule, "end-identifier-mismatch");
+        assert_eq!(waivers[0].reason, "Generated code");
+        assert_eq!(waivers[0].waived, 1);
+
+        let options = parse_lint_options(&args(&["--fail-on", "warning"])).unwrap();
+        assert_eq!(options.fail_on, Severity::Warning);
+        assert!(parse_lint_options(&args(&["--min-severity", "fatal"])).is_err());
+        assert!(parse_lint_options(&args(&["--enable"])[..1]).is_err());
+    }
+
+    #[test]
+    fn watched_project_is_checked_again_after_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;").unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+        let options = parse_lint_options(&[file_name.clone(), "--watch".to_owned()]).unwrap();
+        assert!(options.watch);
+
+        let mut project = load_project(&options.files).unwrap();
+        let mut watcher = project_watcher(&project).unwrap();
+        assert_eq!(watcher.poll().unwrap(), vec![]);
+
+        fs::write(
+            &file_name,
+            "entity ent is\nend entity;\nentity ent is\nend entity;",
+        )
+        .unwrap();
+        let mut changes = Vec::new();
+        while changes.is_empty() {
+            changes = watcher.poll().unwrap();
+        }
+        assert!(project.apply_changes(&changes).is_empty());
+        assert_eq!(lint(&options, check(&project)).len(), 1);
+    }
+
+    #[test]
+    fn graph_options() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+        assert_eq!(
+            parse_graph_options(&args(&["--format", "dot", "--instances", "a.vhd"])),
+            Ok(GraphOptions {
+                kind: GraphKind::Instances,
+                files: args(&["a.vhd"]),
+            })
+        );
+        assert_eq!(
+            parse_graph_options(&args(&["a.vhd"])).map(|options| options.kind),
+            Ok(GraphKind::Units)
+        );
+        assert!(parse_graph_options(&args(&["--format", "svg", "a.vhd"])).is_err());
+        assert!(parse_graph_options(&args(&["--files"])).is_err());
+    }
+
+    #[test]
+    fn bench_of_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;\n").unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+
+        let options =
+            parse_bench_options(&["--iterations".to_owned(), "2".to_owned(), file_name]).unwrap();
+        assert_eq!(options.iterations, 2);
+        let result = bench(&options).unwrap();
+        assert_eq!(result.files, 1);
+        assert_eq!(result.parse.bytes, 26);
+        assert!(bench_table(&result).starts_with("1 files of 0.000 MB, 2 iterations\n"));
+
+        let options = parse_bench_options(&["--generated".to_owned(), "1".to_owned()]).unwrap();
+        assert_eq!(bench(&options).unwrap().files, 1);
+        assert_eq!(
+            parse_bench_options(&["--iterations".to_owned(), "5".to_owned()])
+                .unwrap()
+                .generated,
+            Some(GENERATED_UNITS)
+        );
+        assert!(parse_bench_options(&[
+            "--generated".to_owned(),
+            "1".to_owned(),
+            "a.vhd".to_owned()
+        ])
+        .is_err());
+        assert!(parse_bench_options(&["--iterations".to_owned(), "0".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn stats_of_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;").unwrap();
+        let stats = stats(&[file_name.to_string_lossy().into_owned()]).unwrap();
+        assert_eq!(stats.libraries.len(), 1);
+        assert_eq!(stats.libraries[0].library_name, DEFAULT_LIBRARY);
+        assert_eq!(stats.libraries[0].entities, 1);
+
+        let table = stats_table(&stats);
+        assert!(table.starts_with("library"));
+        assert!(table.lines().nth(1).unwrap().starts_with("work "));
+
+        let json: serde_json::Value = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["libraries"][0]["lines_of_code"], 2);
+    }
+
+    #[test]
+    fn memory_of_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;").unwrap();
+        let project = load_project(&[file_name.to_string_lossy().into_owned()]).unwrap();
+        let report = project.memory_report();
+
+        let table = memory_table(&report);
+        assert!(table.starts_with("library"));
+        assert!(table.lines().nth(1).unwrap().starts_with("work "));
+        assert!(table
+            .lines()
+            .nth(2)
+            .unwrap()
+            .starts_with("Symbol table of "));
+        assert_eq!(table.lines().count(), 3);
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["libraries"][0]["files"][0]["source_bytes"], 0);
+        assert_eq!(json["cache"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn doc_writes_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "-- An entity\nentity ent is\nend entity;").unwrap();
+        let output = dir.path().join("html");
+        let args = vec![
+            "--output".to_owned(),
+            output.to_string_lossy().into_owned(),
+            file_name.to_string_lossy().into_owned(),
+        ];
+        let options = parse_doc_options(&args).unwrap();
+        assert_eq!(doc(&options), Ok(0));
+        assert!(output.join("index.html").exists());
+        assert!(fs::read_to_string(output.join("work").join("ent.html"))
+            .unwrap()
+            .contains("An entity"));
+        assert!(parse_doc_options(&args[..1]).is_err());
+
+        let mut args = args;
+        args.insert(0, "--format".to_owned());
+        args.insert(1, "markdown".to_owned());
+        let options = parse_doc_options(&args).unwrap();
+        assert_eq!(options.format, DocFormat::Markdown);
+        assert_eq!(doc(&options), Ok(0));
+        assert!(output.join("SUMMARY.md").exists());
+        assert!(output.join("work").join("ent.md").exists());
+        args[1] = "pdf".to_owned();
+        assert!(parse_doc_options(&args).is_err());
+    }
+
+    #[test]
+    fn tree_of_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\nend entity;\n\
+             architecture rtl of top is\nbegin\ninst: entity work.leaf;\nend architecture;\n\
+             entity leaf is\nend entity;",
+        )
+        .unwrap();
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_tree_options(&args(&["--top", "work.top"])).unwrap();
+        assert_eq!(
+            tree(&options),
+            Ok("top : work.top(rtl)\n  inst : work.leaf".to_owned())
+        );
+        let options = parse_tree_options(&args(&["--top", "top", "--depth", "0"])).unwrap();
+        assert_eq!(tree(&options), Ok("top : work.top(rtl)".to_owned()));
+        let options = parse_tree_options(&args(&["--top", "top", "--filter", "x"])).unwrap();
+        assert_eq!(tree(&options), Ok(String::new()));
+        let options = parse_tree_options(&args(&["--top", "missing"])).unwrap();
+        assert!(tree(&options).is_err());
+        assert!(parse_tree_options(&args(&[])).is_err());
+    }
+
+    #[test]
+    fn convert_entity() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(
+            &file_name,
+            "entity ent is\nport (clk : in bit);\nend entity;",
+        )
+        .unwrap();
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_convert_options(&args(&["--indent-size", "4", "ent"])).unwrap();
+        assert_eq!(options.name, "ent");
+        assert_eq!(
+            convert_unit(&options),
+            Ok(
+                "component ent is\n    port (\n        clk : in bit\n    );\nend component;"
+                    .to_owned()
+            )
+        );
+        let options = parse_convert_options(&args(&["missing"])).unwrap();
+        assert!(convert_unit(&options).is_err());
+        assert!(parse_convert_options(&args(&[])).is_err());
+    }
+
+    #[test]
+    fn header_of_package_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("pkg.vhd");
+        fs::write(
+            &file_name,
+            "package pkg is\n  constant width : natural := 8;\nend package;",
+        )
+        .unwrap();
+        let output = dir.path().join("pkg.rs");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_header_options(&args(&[
+            "--language",
+            "rust",
+            "--output",
+            &output.to_string_lossy(),
+            "pkg",
+        ]))
+        .unwrap();
+        assert_eq!(options.language, HeaderLanguage::Rust);
+        assert_eq!(header_of_package(&options), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "// Generated from VHDL package pkg, do not edit\n\npub const WIDTH: i32 = 8;\n"
+        );
+        let options = parse_header_options(&args(&["missing"])).unwrap();
+        assert_eq!(options.language, HeaderLanguage::C);
+        assert!(header_of_package(&options).is_err());
+        assert!(parse_header_options(&args(&[])).is_err());
+        assert!(parse_header_options(&args(&["--language", "ada", "pkg"])).is_err());
+    }
+
+    #[test]
+    fn ghdl_script_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;").unwrap();
+        let output = dir.path().join("compile.sh");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_script_options(&args(&[
+            "--simulator",
+            "ghdl",
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert_eq!(options.simulator, Simulator::Ghdl);
+        assert_eq!(script(&options), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!(
+                "#!/bin/sh\nset -e\nghdl -a --std=08 --work=work '{}'\n",
+                file_name.to_string_lossy()
+            )
+        );
+        assert!(parse_script_options(&args(&[])).is_err());
+        assert!(parse_script_options(&args(&["--simulator", "xsim"])).is_err());
+    }
+
+    #[test]
+    fn script_reports_circular_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("pkgs.vhd");
+        fs::write(
+            &file_name,
+            "use work.q2.all;\npackage q1 is\nend package;\n\
+             use work.q1.all;\npackage q2 is\nend package;\n",
+        )
+        .unwrap();
+        let output = dir.path().join("compile.sh");
+
+        let options = parse_script_options(&[
+            "--simulator".to_owned(),
+            "ghdl".to_owned(),
+            "--output".to_owned(),
+            output.to_string_lossy().into_owned(),
+            file_name.to_string_lossy().into_owned(),
+        ])
+        .unwrap();
+        assert_eq!(script(&options), Ok(1));
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn file_list_in_compile_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let ent = dir.path().join("ent.vhd");
+        let pkg = dir.path().join("pkg.vhd");
+        fs::write(&ent, "use work.pkg.all;\nentity ent is\nend entity;").unwrap();
+        fs::write(&pkg, "package pkg is\nend package;").unwrap();
+        let output = dir.path().join("files.tcl");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(ent.to_string_lossy().into_owned()))
+                .chain(Some(pkg.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_files_options(&args(&[
+            "--format",
+            "vivado",
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert_eq!(options.format, FileListFormat::Vivado);
+        assert_eq!(files(&options), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!(
+                "read_vhdl -library work -vhdl2008 {{{}}}\n\
+                 read_vhdl -library work -vhdl2008 {{{}}}\n",
+                pkg.to_string_lossy(),
+                ent.to_string_lossy()
+            )
+        );
+        assert_eq!(
+            parse_files_options(&args(&[])).map(|options| options.format),
+            Ok(FileListFormat::Plain)
+        );
+        assert!(parse_files_options(&args(&["--format", "quartus"])).is_err());
+        assert!(parse_files_options(&[]).is_err());
+    }
+
+    #[test]
+    fn file_list_reports_circular_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("pkgs.vhd");
+        fs::write(
+            &file_name,
+            "use work.q2.all;\npackage q1 is\nend package;\n\
+             use work.q1.all;\npackage q2 is\nend package;\n",
+        )
+        .unwrap();
+        let output = dir.path().join("files.f");
+
+        let options = parse_files_options(&[
+            file_name.to_string_lossy().into_owned(),
+            "--output".to_owned(),
+            output.to_string_lossy().into_owned(),
+        ])
+        .unwrap();
+        assert_eq!(files(&options), Ok(1));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!("{}\n", file_name.to_string_lossy())
+        );
+    }
+
+    #[test]
+    fn dump_ast_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;\nentity").unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+
+        let files =
+            parse_dump_ast_options(&["--format".to_owned(), "json".to_owned(), file_name.clone()])
+                .unwrap();
+        let (json, errors) = dump_ast(&files).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["file_name"], file_name.as_str());
+        assert_eq!(value[0]["library_name"], DEFAULT_LIBRARY);
+        let unit = &value[0]["design_file"]["design_units"][0]["library_unit"];
+        assert_eq!(unit["EntityDeclaration"]["ident"]["item"], "ent");
+        assert_eq!(errors.len(), 1);
+        assert!(parse_dump_ast_options(&["--format".to_owned(), "xml".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn syntax_messages_with_line_and_column() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+        assert_eq!(
+            parse_syntax_options(&args(&[
+                "--standard",
+                "93",
+                "--stdin-filename",
+                "a.vhd",
+                "-"
+            ])),
+            Ok(SyntaxOptions {
+                standard: VHDLStandard::VHDL1993,
+                stdin_filename: Some("a.vhd".to_owned()),
+                file: "-".to_owned(),
+            })
+        );
+        assert!(parse_syntax_options(&args(&["a.vhd", "b.vhd"])).is_err());
+        assert!(parse_syntax_options(&args(&["--standard", "87", "a.vhd"])).is_err());
+
+        let source =
+            Source::from_file_contents("a.vhd", "entity ent is\nend entity;\n  entity").unwrap();
+        let lines = syntax_lines(&source, VHDLStandard::default()).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0],
+            "a.vhd:3:3: error: Failed to parse entity declaration"
+        );
+        let source = Source::from_file_contents("a.vhd", "entity ent is\nend entity;").unwrap();
+        assert_eq!(syntax_lines(&source, VHDLStandard::default()), Ok(vec![]));
+    }
+
+    #[test]
+    fn duplicates_with_locations() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.vhd");
+        let second = dir.path().join("b.vhd");
+        fs::write(&first, "entity ent is\nend entity;").unwrap();
+        fs::write(&second, "\n  entity ent is\nend entity;").unwrap();
+        let files: Vec<String> = [&first, &second]
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        let project = load_project(&files).unwrap();
+        assert_eq!(
+            duplicates_report(&project),
+            vec![
+                "ent is declared 2 times".to_owned(),
+                format!("  work: {}:1:8", files[0]),
+                format!("  work: {}:2:10", files[1]),
+            ]
+        );
+        let project = load_project(&files[..1]).unwrap();
+        assert_eq!(duplicates_report(&project), Vec::<String>::new());
+    }
+
+    #[test]
+    fn state_machine_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("fsm.vhd");
+        fs::write(
+            &file_name,
+            "entity ent is\nend entity;\n\
+             architecture rtl of ent is\n\
+             type state_t is (idle, busy, lost);\n\
+             signal state : state_t := idle;\n\
+             begin\n\
+             process (clk)\nbegin\nif rising_edge(clk) then\n\
+             case state is\n\
+             when idle => state <= busy;\n\
+             when others => state <= idle;\n\
+             end case;\nend if;\nend process;\nend architecture;",
+        )
+        .unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+        let options =
+            parse_fsm_options(&["--format".to_owned(), "dot".to_owned(), file_name.clone()])
+                .unwrap();
+        assert!(options.dot);
+        assert!(
+            parse_fsm_options(&["--format".to_owned(), "svg".to_owned(), file_name.clone()])
+                .is_err()
+        );
+
+        let project = load_project(&options.files).unwrap();
+        assert_eq!(
+            fsm_report(&project),
+            vec![
+                format!(
+                    "work.ent(rtl) state: 3 states, initial idle ({}:10:6)",
+                    file_name
+                ),
+                "  idle -> busy".to_owned(),
+                "  busy -> idle".to_owned(),
+                "  lost -> idle".to_owned(),
+                "  unreachable: lost".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn coverage_of_node_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("ent.vhd");
+        fs::write(&file_name, "entity ent is\nend entity;\nentity").unwrap();
+        let files = vec![file_name.to_string_lossy().into_owned()];
+
+        let project = load_project(&files).unwrap();
+        assert_eq!(
+            coverage_report(&project),
+            vec![
+                "LibraryUnit::EntityDeclaration        1".to_owned(),
+                "Found 1 syntax errors".to_owned(),
+                format!("{}:3:1: Failed to parse entity declaration", files[0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn dead_code_with_locations() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\nend entity;\n\narchitecture rtl of top is\n\
+             procedure unused is\n  begin\n  end procedure;\nbegin\n\
+             inst : entity work.child;\nend architecture;\n\n\
+             entity child is\nend entity;\n\npackage pkg is\nend package;\n",
+        )
+        .unwrap();
+        let files = vec![file_name.to_string_lossy().into_owned()];
+
+        let project = load_project(&files).unwrap();
+        assert_eq!(
+            dead_code_report(&project, None),
+            Ok(vec![
+                "Entities never instantiated: 1".to_owned(),
+                format!("  top: {}:1:8", files[0]),
+                "Packages never used: 1".to_owned(),
+                format!("  pkg: {}:15:9", files[0]),
+                "Subprograms never called: 1".to_owned(),
+                format!("  unused: {}:5:11", files[0]),
+            ])
+        );
+        assert_eq!(
+            dead_code_report(&project, Some("work.top")).map(|lines| lines[0].clone()),
+            Ok("Entities not instantiated below work.top: 0".to_owned())
+        );
+        assert!(dead_code_report(&project, Some("missing")).is_err());
+        assert_eq!(
+            parse_dead_code_options(&["--top".to_owned()]),
+            Err("--top must be followed by an entity".to_owned())
+        );
+    }
+
+    #[test]
+    fn interfaces_with_inconsistent_associations() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\nend entity;\n\narchitecture rtl of top is\n\
+             signal clk : bit;\nbegin\n\
+             u1 : entity work.sub port map (clk => clk, old => clk);\n\
+             u2 : entity work.sub port map (clk => clk, rst => clk);\n\
+             end architecture;\n\n\
+             entity sub is\n  port (clk, rst : in bit);\nend entity;\n",
+        )
+        .unwrap();
+        let files = vec![file_name.to_string_lossy().into_owned()];
+
+        let project = load_project(&files).unwrap();
+        assert_eq!(
+            interfaces_report(&project),
+            (
+                vec![
+                    "work.sub: 2 instances, 1 inconsistent".to_owned(),
+                    format!("  work.top(rtl) u1: {}:7:1", files[0]),
+                    format!("    {}:7:44: 'old' is not a port of the entity", files[0]),
+                    format!("    {}:7:1: port 'rst' is not associated", files[0]),
+                    format!("  work.top(rtl) u2: {}:8:1", files[0]),
+                ],
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn blackboxes_of_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\nend entity;\n\narchitecture rtl of top is\nbegin\n\
+             buf : component bufg;\n\
+             u1 : entity work.sub_cores;\n\
+             end architecture;\n\n\
+             entity sub_core is\nend entity;\n",
+        )
+        .unwrap();
+        let project_file = dir.path().join("project.toml");
+        fs::write(
+            &project_file,
+            format!(
+                "blackboxes = ['bufg']\n[libraries]\nwork.files = ['{}']\n",
+                file_name.to_string_lossy()
+            ),
+        )
+        .unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+
+        let project = load_project(&[project_file.to_string_lossy().into_owned()]).unwrap();
+        assert_eq!(
+            blackboxes_report(&project),
+            (
+                vec![
+                    format!("work.top(rtl) buf: bufg blackbox ({}:6:1)", file_name),
+                    format!(
+                        "work.top(rtl) u1: work.sub_cores unbound, did you mean work.sub_core? ({}:7:1)",
+                        file_name
+                    ),
+                    "Found 1 blackboxes and 1 unbound instances".to_owned(),
+                ],
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn serve_until_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(&file_name, "entity top is\nend entity;\n").unwrap();
+        let project = load_project(&[file_name.to_string_lossy().into_owned()]).unwrap();
+
+        let input = "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"entities\"}\n\n\
+                     {\"jsonrpc\": \"2.0\", \"method\": \"diagnostics\"}\n\
+                     {\"jsonrpc\": \"2.0\", \"id\": 2, \"method\": \"shutdown\"}\n\
+                     {\"jsonrpc\": \"2.0\", \"id\": 3, \"method\": \"entities\"}\n";
+        let mut output = Vec::new();
+        serve(
+            &mut AnalysisServer::new(project),
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let responses: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"][0]["name"], "top");
+        assert_eq!(responses[0]["result"][0]["library"], "work");
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["result"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn rename_in_place_or_as_patch() {
+        let dir = tempfile::tempdir().unwrap();
+        let ent = dir.path().join("ent.vhd");
+        let pkg = dir.path().join("pkg.vhd");
+        fs::write(
+            &ent,
+            "entity ent is\nend entity;\n\narchitecture rtl of ent is\n  signal foo : bit;\n\
+             begin\n  foo <= '1';\nend architecture;\n",
+        )
+        .unwrap();
+        fs::write(
+            &pkg,
+            "package pkg is\n  constant foo : bit := '0';\nend package;",
+        )
+        .unwrap();
+        let ent_name = ent.to_string_lossy().into_owned();
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(ent_name.clone()))
+                .chain(Some(pkg.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_rename_options(&args(&["foo", "bar"])).unwrap();
+        assert!(rename(&options)
+            .unwrap_err()
+            .starts_with("foo is declared 2 times"));
+
+        let options =
+            parse_rename_options(&args(&["--kind", "signal", "--patch", "foo", "bar"])).unwrap();
+        assert_eq!(options.kind, Some(NamedEntityKind::Signal));
+        assert_eq!(
+            rename(&options),
+            Ok(format!(
+                "--- {0}\n+++ {0}\n@@ -2,7 +2,7 @@\n end entity;\n \n\
+                 \x20architecture rtl of ent is\n-  signal foo : bit;\n+  signal bar : bit;\n\
+                 \x20begin\n-  foo <= '1';\n+  bar <= '1';\n end architecture;\n",
+                ent_name
+            ))
+        );
+        assert!(fs::read_to_string(&ent).unwrap().contains("foo"));
+
+        let options = parse_rename_options(&args(&["--in", &ent_name, "foo", "bar"])).unwrap();
+        assert_eq!(
+            rename(&options),
+            Ok("Renamed 2 occurrences in 1 files\n".to_owned())
+        );
+        assert!(fs::read_to_string(&ent).unwrap().contains("signal bar"));
+        assert!(parse_rename_options(&args(&["--kind", "wire", "a", "b"])).is_err());
+        assert!(parse_rename_options(&args(&[])[..1]).is_err());
+    }
+
+    #[test]
+    fn unified_diff_of_separate_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj";
+        let new = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ";
+        assert_eq!(
+            unified_diff("x.vhd", old, new),
+            "--- x.vhd\n+++ x.vhd\n@@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n d\n\
+             @@ -7,4 +7,4 @@\n g\n h\n i\n\
+             -j\n\\ No newline at end of file\n+J\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified_diff("x.vhd", old, old), "");
+    }
+
+    #[test]
+    fn unified_diff_of_added_and_removed_lines() {
+        let old = "a\nb\nc\nd\n";
+        let new = "a\nB\nb1\nb2\nd\n";
+        assert_eq!(
+            unified_diff("x.vhd", old, new),
+            "--- x.vhd\n+++ x.vhd\n@@ -1,4 +1,5 @@\n a\n-b\n-c\n+B\n+b1\n+b2\n d\n"
+        );
+        assert_eq!(
+            unified_diff("x.vhd", old, "a\nd\n"),
+            "--- x.vhd\n+++ x.vhd\n@@ -1,4 +1,2 @@\n a\n-b\n-c\n d\n"
+        );
+        assert_eq!(
+            unified_diff("x.vhd", "", "a\n"),
+            "--- x.vhd\n+++ x.vhd\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn yosys_json_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\n  port (clk : in bit);\nend entity;\n\n\
+             architecture rtl of top is\nbegin\n  \
+             inst : entity work.child port map (clk => clk);\nend architecture;\n\n\
+             entity child is\n  port (clk : in bit);\nend entity;\n",
+        )
+        .unwrap();
+        let output = dir.path().join("top.json");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_yosys_json_options(&args(&[
+            "--top",
+            "top",
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert_eq!(options.top, Some("top".to_owned()));
+        assert_eq!(yosys_json(&options), Ok(0));
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(
+            json["modules"]["top"]["cells"]["inst"]["connections"]["clk"],
+            serde_json::json!([2])
+        );
+        assert!(json["modules"]["child"].is_object());
+
+        let options = parse_yosys_json_options(&args(&["--top", "missing"])).unwrap();
+        assert!(yosys_json(&options).is_err());
+        assert!(parse_yosys_json_options(&[]).is_err());
+    }
+
+    #[test]
+    fn netlist_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\n  port (clk : in bit);\nend entity;\n\n\
+             architecture rtl of top is\nbegin\n  \
+             inst : entity work.child port map (clk => clk);\nend architecture;\n\n\
+             entity child is\n  port (clk : in bit);\nend entity;\n",
+        )
+        .unwrap();
+        let output = dir.path().join("top.net");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_netlist_options(&args(&[
+            "--top",
+            "top",
+            "--format",
+            "text",
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert!(options.text);
+        assert_eq!(write_netlist(&options), Ok(0));
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "(netlist top\n  \
+             (instance top (unit work.top(rtl)) (port clk in top.clk))\n  \
+             (instance top.inst (unit work.child) (port clk in top.clk))\n  \
+             (net top.clk top.clk top.inst.clk))\n"
+        );
+
+        let options = parse_netlist_options(&args(&["--top", "missing"])).unwrap();
+        assert!(write_netlist(&options).is_err());
+        assert!(parse_netlist_options(&args(&[])).is_err());
+        assert!(parse_netlist_options(&args(&["--top", "top", "--format", "edif"])).is_err());
+    }
+
+    #[test]
+    fn run_testbench() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("tb.vhd");
+        fs::write(
+            &file_name,
+            "entity tb is\nend entity;\n\n\
+             architecture sim of tb is\n  signal clk : bit;\nbegin\n  \
+             clk <= not clk after 5 ns;\n\n  \
+             process\n  begin\n    wait for 12 ns;\n    \
+             assert clk = '1' report \"clk is \" & bit'image(clk);\n    \
+             assert clk = '0' report \"clk is \" & bit'image(clk);\n    \
+             wait;\n  end process;\nend architecture;\n",
+        )
+        .unwrap();
+        let file_name = file_name.to_string_lossy().into_owned();
+        let vcd = dir.path().join("tb.vcd");
+
+        let options = parse_run_options(&[
+            "--stop-time".to_owned(),
+            "20 ns".to_owned(),
+            "--vcd".to_owned(),
+            vcd.to_string_lossy().into_owned(),
+            "tb".to_owned(),
+            file_name.clone(),
+        ])
+        .unwrap();
+        assert_eq!(options.stop_time, Some(20_000_000));
+        let mut output = Vec::new();
+        assert_eq!(run_simulation(&options, &mut output), Ok(1));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}:12:12:@12ns:(assertion error): clk is '0'\n", file_name)
+        );
+        assert!(fs::read_to_string(&vcd)
+            .unwrap()
+            .ends_with("#5000000\n1!\n#10000000\n0!\n#15000000\n1!\n#20000000\n0!\n"));
+
+        let options = parse_run_options(&["missing".to_owned(), file_name.clone()]).unwrap();
+        assert!(run_simulation(&options, &mut Vec::new()).is_err());
+        assert!(parse_run_options(&["tb".to_owned()]).is_err());
+        assert!(parse_run_options(&["--stop-time".to_owned(), "soon".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn sql_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(&file_name, "entity top is\nend entity;\n").unwrap();
+        let output = dir.path().join("symbols.sql");
+
+        let options = parse_sql_options(&[
+            "--output".to_owned(),
+            output.to_string_lossy().into_owned(),
+            file_name.to_string_lossy().into_owned(),
+        ])
+        .unwrap();
+        assert_eq!(sql(&options), Ok(0));
+        let contents = fs::read_to_string(&output).unwrap();
+        assert!(contents.contains("CREATE TABLE declarations ("));
+        assert!(contents.contains("'top', 'entity', 1, 1, 8);"));
+
+        assert!(parse_sql_options(&[]).is_err());
+        assert!(parse_sql_options(&["--format".to_owned(), "top.vhd".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn lsif_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(&file_name, "entity top is\nend entity;\n").unwrap();
+        let output = dir.path().join("dump.lsif");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_lsif_options(&args(&[
+            "--root",
+            &dir.path().to_string_lossy(),
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert_eq!(lsif(&options), Ok(0));
+        let contents = fs::read_to_string(&output).unwrap();
+        let elements: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(elements[0]["label"], "metaData");
+        assert!(elements
+            .iter()
+            .any(|element| element["label"] == "document"));
+
+        let options = parse_lsif_options(&args(&["--root", "/missing/directory"])).unwrap();
+        assert!(lsif(&options).is_err());
+        assert!(parse_lsif_options(&[]).is_err());
+    }
+
+    #[test]
+    fn top_interface_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("top.vhd");
+        fs::write(
+            &file_name,
+            "entity top is\n  generic (width : natural := 4);\n  \
+             port (data : out bit_vector(width - 1 downto 0));\nend entity;\n",
+        )
+        .unwrap();
+        let output = dir.path().join("top.json");
+        let args = |args: &[&str]| -> Vec<String> {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .chain(Some(file_name.to_string_lossy().into_owned()))
+                .collect()
+        };
+
+        let options = parse_top_interface_options(&args(&[
+            "--top",
+            "top",
+            "--generic",
+            "width=16",
+            "--output",
+            &output.to_string_lossy(),
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.generics,
+            vec![("width".to_owned(), "16".to_owned())]
+        );
+        assert_eq!(write_top_interface(&options), Ok(0));
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(json["ports"][0]["width"], 16);
+        assert_eq!(json["ports"][0]["direction"], "out");
+        assert_eq!(json["generics"][0]["default"], "4");
+
+        let options =
+            parse_top_interface_options(&args(&["--top", "top", "--format", "yaml"])).unwrap();
+        assert!(options.yaml);
+        assert!(
+            parse_top_interface_options(&args(&["--top", "top", "--generic", "width"])).is_err()
+        );
+        assert!(parse_top_interface_options(&args(&["--format", "yaml"])).is_err());
+        let options = parse_top_interface_options(&args(&["--top", "missing"])).unwrap();
+        assert!(write_top_interface(&options).is_err());
+    }
+}
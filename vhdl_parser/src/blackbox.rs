@@ -0,0 +1,216 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Instances which are not bound to an entity, configuration or Verilog module within the project
+//!
+//! Such blackboxes are intended for vendor primitives and IP which are configured with
+//! the blackboxes patterns of the project file, the others are likely typos and are
+//! reported by the unbound-instance rule together with an entity of a similar name
+
+use instance_tree::InstanceHierarchy;
+use library::UnitId;
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use project::Project;
+use source::SrcPos;
+
+/// An instance of a unit which is not within the project
+#[derive(PartialEq, Debug, Clone)]
+pub struct Blackbox {
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    pub pos: SrcPos,
+    /// The instantiated unit as written such as unisim.bufg
+    pub unit_name: String,
+    /// Whether the unit matches the blackboxes of the project file
+    pub configured: bool,
+    /// An entity of the project with a similar name when not configured
+    pub similar: Option<UnitId>,
+}
+
+/// The number of single character insertions, deletions and substitutions between the strings
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+    for (i, left_char) in left.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != *right_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[right.len()]
+}
+
+/// The entity whose name is closest to the unit name, at most a third of it different
+fn similar_entity(unit_name: &str, entities: &[&UnitId]) -> Option<UnitId> {
+    let name = unit_name
+        .rsplit('.')
+        .next()
+        .unwrap_or(unit_name)
+        .to_lowercase();
+    let max_distance = (name.len() / 3).max(1);
+    entities
+        .iter()
+        .map(|entity| (edit_distance(&name, entity.primary_name()), *entity))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, entity)| entity.clone())
+}
+
+/// The instances of the project which are not bound within it in the order of the architectures
+pub fn blackboxes(project: &Project) -> Vec<Blackbox> {
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    let entities = hierarchy.entity_ids();
+    hierarchy
+        .unbound_instantiations()
+        .into_iter()
+        .map(|instance| {
+            let configured = project.config().is_blackbox(&instance.unit_name);
+            Blackbox {
+                similar: if configured {
+                    None
+                } else {
+                    similar_entity(&instance.unit_name, &entities)
+                },
+                architecture: instance.architecture,
+                label: instance.label,
+                pos: instance.pos,
+                unit_name: instance.unit_name,
+                configured,
+            }
+        })
+        .collect()
+}
+
+pub struct UnboundInstance;
+
+impl Rule for UnboundInstance {
+    fn id(&self) -> &str {
+        "unbound-instance"
+    }
+
+    fn description(&self) -> &str {
+        "An instance is not bound to an entity or configuration and is not a configured blackbox"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        Ok(blackboxes(context.project)
+            .into_iter()
+            .filter(|blackbox| !blackbox.configured)
+            .map(|blackbox| {
+                let mut message = format!(
+                    "Instance '{}' of '{}' is not bound to an entity or configuration of the project",
+                    blackbox.label, blackbox.unit_name
+                );
+                if let Some(ref entity) = blackbox.similar {
+                    message.push_str(&format!(", did you mean '{}'?", entity));
+                }
+                warning(&blackbox.pos, &message)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use lint::{LintConfig, Registry};
+    use std::path::Path;
+    use test_util::TempProject;
+
+    #[test]
+    fn unbound_instances() {
+        let mut project = TempProject::new();
+        let code = "
+entity top is
+end entity;
+
+architecture rtl of top is
+begin
+  buf: component bufg;
+  fifo: entity work.fifo_synch;
+  ram: component xpm_memory_spram;
+  pll: component clk_wizard;
+end architecture;
+
+entity fifo_sync is
+end entity;";
+        project.add_file("lib", "top.vhd", code);
+        let project = &mut project.project;
+        let config = Config::parse("blackboxes = ['unisim.*', 'bufg', 'xpm_*']", Path::new(""));
+        project.add_config(&config.unwrap());
+
+        let summary: Vec<(String, bool, Option<String>)> = blackboxes(project)
+            .into_iter()
+            .map(|blackbox| {
+                (
+                    blackbox.label,
+                    blackbox.configured,
+                    blackbox.similar.map(|entity| entity.to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("buf".to_owned(), true, None),
+                ("fifo".to_owned(), false, Some("lib.fifo_sync".to_owned())),
+                ("ram".to_owned(), true, None),
+                ("pll".to_owned(), false, None),
+            ]
+        );
+
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<(String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("unbound-instance".to_owned()))
+            .map(|finding| (finding.message, finding.pos.start))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    "Instance 'fifo' of 'work.fifo_synch' is not bound to an entity or \
+                     configuration of the project, did you mean 'lib.fifo_sync'?"
+                        .to_owned(),
+                    code.find("fifo:").unwrap()
+                ),
+                (
+                    "Instance 'pll' of 'clk_wizard' is not bound to an entity or \
+                     configuration of the project"
+                        .to_owned(),
+                    code.find("pll:").unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn distance_between_names() {
+        assert_eq!(edit_distance("fifo", "fifo"), 0);
+        assert_eq!(edit_distance("fifo_synch", "fifo_sync"), 1);
+        assert_eq!(edit_distance("ram", "rom"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
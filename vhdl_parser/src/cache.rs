@@ -0,0 +1,427 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Persistent on-disk cache of parsed design files keyed by a hash of the file contents
+//!
+//! Serde writes an Arc once for every reference, so the subtype and expression which
+//! the declarations of an identifier list share after parsing are separate copies in a
+//! loaded or relocated design file
+
+use ast::DesignFile;
+use bincode;
+use latin_1::Latin1String;
+use message::Message;
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use source::{Source, SrcPos};
+use std::cell::RefCell;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use symbol_table::{Symbol, SymbolTable};
+use {ParseOptions, VHDLParser};
+
+extern crate fnv;
+use self::fnv::FnvHasher;
+
+/// Hash of file contents which is stable between program invocations
+pub fn content_hash(contents: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(contents);
+    hasher.finish()
+}
+
+/// Must be incremented when the serialized format of the AST changes
+const FORMAT_VERSION: u32 = 6;
+
+/// The symbol table and source of the value currently being deserialized
+struct Context {
+    symtab: Arc<SymbolTable>,
+    source: Source,
+    /// Positions at or after the first are moved by the second
+    shift: (usize, isize),
+    /// The symbols of a relocated value which are serialized by their index
+    symbols: Option<Vec<Symbol>>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+    /// The symbols of the value currently being serialized for relocation
+    static SYMBOLS: RefCell<Option<Vec<Symbol>>> = const { RefCell::new(None) };
+}
+
+/// Positions are serialized without their source since all
+/// positions within a cache entry belong to the same source
+impl Serialize for SrcPos {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.start, self.length).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SrcPos {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SrcPos, D::Error> {
+        let (start, length) = <(usize, usize)>::deserialize(deserializer)?;
+        CONTEXT.with(|context| match *context.borrow() {
+            Some(ref context) => {
+                let (from, delta) = context.shift;
+                let start = if start >= from {
+                    (start as isize + delta) as usize
+                } else {
+                    start
+                };
+                Ok(SrcPos {
+                    source: context.source.clone(),
+                    start,
+                    length,
+                })
+            }
+            None => Err(D::Error::custom("SrcPos deserialized without a source")),
+        })
+    }
+}
+
+/// Symbols are serialized by name since the id is only unique within a symbol table
+/// The newtype does not change the encoding but lets a serializer tell names of
+/// symbols, which belong to the symbol table, from other strings
+/// A relocated value keeps its symbols which are serialized by index instead of being
+/// looked up by name again
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let index = SYMBOLS.with(|symbols| {
+            symbols.borrow_mut().as_mut().map(|symbols| {
+                symbols.push(self.clone());
+                symbols.len() - 1
+            })
+        });
+        match index {
+            Some(index) => serializer.serialize_newtype_struct("Symbol", &index),
+            None => serializer.serialize_newtype_struct("Symbol", self.name()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Symbol, D::Error> {
+        let by_index = CONTEXT.with(|context| match *context.borrow() {
+            Some(ref context) => Ok(context.symbols.is_some()),
+            None => Err(D::Error::custom(
+                "Symbol deserialized without a symbol table",
+            )),
+        })?;
+        if by_index {
+            let index = usize::deserialize(deserializer)?;
+            CONTEXT.with(|context| {
+                context
+                    .borrow()
+                    .as_ref()
+                    .and_then(|context| context.symbols.as_ref())
+                    .and_then(|symbols| symbols.get(index).cloned())
+                    .ok_or_else(|| D::Error::custom("Symbol index out of range"))
+            })
+        } else {
+            let name = Latin1String::deserialize(deserializer)?;
+            CONTEXT.with(|context| match *context.borrow() {
+                Some(ref context) => Ok(context.symtab.insert(&name)),
+                None => Err(D::Error::custom(
+                    "Symbol deserialized without a symbol table",
+                )),
+            })
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    design_file: &'a DesignFile,
+    messages: &'a [Message],
+}
+
+#[derive(Deserialize)]
+struct CacheEntry {
+    design_file: DesignFile,
+    messages: Vec<Message>,
+}
+
+fn deserialize_with<T: DeserializeOwned>(bytes: &[u8], context: Context) -> Option<T> {
+    CONTEXT.with(|cell| {
+        *cell.borrow_mut() = Some(context);
+    });
+    let value = bincode::deserialize(bytes);
+    CONTEXT.with(|cell| {
+        *cell.borrow_mut() = None;
+    });
+    value.ok()
+}
+
+/// A copy of the value where all positions belong to the source and the positions at or
+/// after the first of the shift are moved by the second, such as the parse result of the
+/// unchanged design units of a file after an edit
+/// The copy may be of another type with the same serialized form such as a vector of a slice
+pub fn relocate<T: Serialize + ?Sized, R: DeserializeOwned>(
+    value: &T,
+    symtab: &Arc<SymbolTable>,
+    source: &Source,
+    shift: (usize, isize),
+) -> Option<R> {
+    SYMBOLS.with(|symbols| {
+        *symbols.borrow_mut() = Some(Vec::new());
+    });
+    let bytes = bincode::serialize(value);
+    let symbols = SYMBOLS.with(|symbols| symbols.borrow_mut().take());
+    let context = Context {
+        symtab: symtab.clone(),
+        source: source.clone(),
+        shift,
+        symbols,
+    };
+    let bytes = bytes.ok()?;
+    deserialize_with(&bytes, context)
+}
+
+/// A directory of parse results, an unchanged file is loaded from the cache instead of being parsed again
+pub struct ParseCache {
+    directory: PathBuf,
+}
+
+impl ParseCache {
+    /// Use the directory as cache, it is created if it does not exist
+    pub fn new(directory: &Path) -> io::Result<ParseCache> {
+        fs::create_dir_all(directory)?;
+        Ok(ParseCache {
+            directory: directory.to_owned(),
+        })
+    }
+
+    /// The key of the parse result of the contents, different parse options
+    /// such as the language standard give a different result
+    pub fn key(contents: &[u8], options: &ParseOptions) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write_u32(FORMAT_VERSION);
+        options.hash(&mut hasher);
+        hasher.write(contents);
+        hasher.finish()
+    }
+
+    fn entry_path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", key))
+    }
+
+    /// Load the parse result of the source, symbols are added to the symbol table of the parser
+    /// Returns None if there is no entry or if the entry could not be read
+    /// The Arcs of the design file are not shared, see the module documentation
+    pub fn load(
+        &self,
+        parser: &VHDLParser,
+        source: &Source,
+        key: u64,
+    ) -> Option<(DesignFile, Vec<Message>)> {
+        let bytes = fs::read(self.entry_path(key)).ok()?;
+        let context = Context {
+            symtab: parser.symtab.clone(),
+            source: source.clone(),
+            shift: (usize::MAX, 0),
+            symbols: None,
+        };
+        let entry: CacheEntry = deserialize_with(&bytes, context)?;
+        Some((entry.design_file, entry.messages))
+    }
+
+    /// Store the parse result, the entry is written to a temporary file first
+    /// such that a concurrent load never sees a partially written entry
+    pub fn store(
+        &self,
+        key: u64,
+        design_file: &DesignFile,
+        messages: &[Message],
+    ) -> io::Result<()> {
+        let entry = CacheEntryRef {
+            design_file,
+            messages,
+        };
+        let bytes = bincode::serialize(&entry)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(&tmp_path, &path)
+    }
+
+    /// The number of entries and their total size in bytes on disk
+    pub fn disk_usage(&self) -> io::Result<(usize, u64)> {
+        let mut entries = 0;
+        let mut bytes = 0;
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if entry.path().extension().and_then(|ext| ext.to_str()) == Some("bin") {
+                entries += 1;
+                bytes += entry.metadata()?.len();
+            }
+        }
+        Ok((entries, bytes))
+    }
+
+    /// Remove all entries of the cache
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("bin") {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use ast::{DesignUnit, LibraryUnit};
+    use standard::VHDLStandard;
+
+    fn parse(parser: &VHDLParser, source: &Source) -> (DesignFile, Vec<Message>) {
+        let mut messages = Vec::new();
+        let design_file = match parser.parse_design_source(source, &mut messages) {
+            Ok(design_file) => design_file,
+            Err(_) => panic!("Parse failed"),
+        };
+        (design_file, messages)
+    }
+
+    #[test]
+    fn store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ParseCache::new(&dir.path().join("cache")).unwrap();
+        let code = "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity ent is
+  port (clk : in std_logic);
+end entity;
+
+architecture rtl of ent is
+  signal foo : natural := 16#ff#;
+begin
+  foo <= 1 + 2 when clk = '1' else 0;
+  warn : foo <= 0
+end architecture;
+";
+        let source = Source::from_str(code).unwrap();
+        let parser = VHDLParser::new();
+        let (design_file, messages) = parse(&parser, &source);
+        assert_eq!(messages.len(), 1);
+
+        let key = ParseCache::key(code.as_bytes(), &ParseOptions::default());
+        assert_eq!(cache.load(&parser, &source, key), None);
+        cache.store(key, &design_file, &messages).unwrap();
+
+        // A different parser has a separate symbol table
+        let other_parser = VHDLParser::new();
+        let (other_design_file, other_messages) = parse(&other_parser, &source);
+        assert_eq!(
+            cache.load(&other_parser, &source, key),
+            Some((other_design_file, other_messages))
+        );
+    }
+
+    #[test]
+    fn key_depends_on_contents_and_options() {
+        let code = b"entity ent is end entity;";
+        let options = ParseOptions::default();
+        let key = ParseCache::key(code, &options);
+        assert_eq!(key, ParseCache::key(code, &options));
+        assert_ne!(
+            key,
+            ParseCache::key(b"entity ent2 is end entity;", &options)
+        );
+
+        let interface_only = ParseOptions {
+            interface_only: true,
+            ..ParseOptions::default()
+        };
+        assert_ne!(key, ParseCache::key(code, &interface_only));
+
+        let mut defines = ParseOptions::default();
+        defines
+            .defines
+            .insert("debug".to_owned(), "true".to_owned());
+        assert_ne!(key, ParseCache::key(code, &defines));
+
+        let standard = ParseOptions {
+            standard: VHDLStandard::VHDL1993,
+            ..ParseOptions::default()
+        };
+        assert_ne!(key, ParseCache::key(code, &standard));
+    }
+
+    #[test]
+    fn corrupt_entry_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ParseCache::new(dir.path()).unwrap();
+        let key = ParseCache::key(b"", &ParseOptions::default());
+        fs::write(cache.entry_path(key), b"corrupt").unwrap();
+
+        let source = Source::from_str("").unwrap();
+        assert_eq!(cache.load(&VHDLParser::new(), &source, key), None);
+
+        cache.clear().unwrap();
+        assert!(!cache.entry_path(key).exists());
+    }
+
+    #[test]
+    fn disk_usage_of_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ParseCache::new(dir.path()).unwrap();
+        assert_eq!(cache.disk_usage().unwrap(), (0, 0));
+
+        let key = ParseCache::key(b"", &ParseOptions::default());
+        fs::write(cache.entry_path(key), b"entry").unwrap();
+        fs::write(dir.path().join("other.txt"), b"not an entry").unwrap();
+        assert_eq!(cache.disk_usage().unwrap(), (1, 5));
+    }
+
+    #[test]
+    fn relocate_positions_to_source() {
+        let code = "entity ent is end entity; entity ent2 is end entity;";
+        let source = Source::from_str(code).unwrap();
+        let parser = VHDLParser::new();
+        let (design_file, _) = parse(&parser, &source);
+
+        let edited = Source::from_str(&format!("  {}", code)).unwrap();
+        let (edited_file, _) = parse(&parser, &edited);
+        let units: Vec<DesignUnit> = relocate(
+            &design_file.design_units[1..],
+            &parser.symtab,
+            &edited,
+            (design_file.design_unit_ends[0], 2),
+        )
+        .unwrap();
+        assert_eq!(units, edited_file.design_units[1..].to_vec());
+
+        // Positions before the shift are only moved to the source
+        let units: Vec<DesignUnit> = relocate(
+            &design_file.design_units[..1],
+            &parser.symtab,
+            &edited,
+            (design_file.design_unit_ends[0], 2),
+        )
+        .unwrap();
+        let ident_pos = |unit: &DesignUnit| match unit.library_unit {
+            LibraryUnit::EntityDeclaration { ref ident, .. } => ident.pos.clone(),
+            _ => panic!("Expected entity"),
+        };
+        assert_eq!(ident_pos(&units[0]).source, edited);
+        assert_eq!(
+            ident_pos(&units[0]).start,
+            ident_pos(&design_file.design_units[0]).start
+        );
+    }
+}
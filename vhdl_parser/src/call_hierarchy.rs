@@ -0,0 +1,323 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Calls of subprograms and instantiations of entities and components
+//! together with the subprogram body or design unit containing them
+
+use name_resolution::{NameResolution, NamedEntity, NamedEntityKind};
+use source::SrcPos;
+use source_tokens::SourceTokens;
+use std::collections::HashMap;
+use tokenizer::Kind::*;
+
+/// A call of a subprogram or an instantiation of an entity or component
+#[derive(Clone, Debug)]
+pub struct Call {
+    /// The innermost subprogram body or design unit containing the call
+    pub caller: NamedEntity,
+    pub callee: NamedEntity,
+    /// The name of the callee at the call
+    pub pos: SrcPos,
+}
+
+fn is_callee(kind: NamedEntityKind) -> bool {
+    use self::NamedEntityKind as K;
+    matches!(kind, K::Function | K::Procedure | K::Entity | K::Component)
+}
+
+fn is_caller(kind: NamedEntityKind) -> bool {
+    use self::NamedEntityKind as K;
+    matches!(
+        kind,
+        K::Function | K::Procedure | K::Entity | K::Architecture | K::Package
+    )
+}
+
+/// Named entities which may be called or may contain calls
+pub fn is_callable(kind: NamedEntityKind) -> bool {
+    is_callee(kind) || is_caller(kind)
+}
+
+/// The first token of the construct when the designator at the index declares or completes it
+fn construct_start(tokens: &SourceTokens, i: usize) -> Option<usize> {
+    let previous = tokens.kind(i.checked_sub(1)?)?;
+    let before = i.checked_sub(2).and_then(|j| tokens.kind(j));
+    // Instantiations, binding indications and entity classes of attribute specifications
+    let referenced = before == Some(Colon) || before == Some(Use);
+    match previous {
+        Function | Procedure => Some(i - 1),
+        Entity | Architecture | Package | Component if !referenced => Some(i - 1),
+        Body if before == Some(Package) => Some(i - 2),
+        _ => None,
+    }
+}
+
+/// Names of a design unit after of and repeated designators after end are not calls
+fn is_call(tokens: &SourceTokens, i: usize) -> bool {
+    construct_start(tokens, i).is_none()
+        && !matches!(
+            i.checked_sub(1).and_then(|j| tokens.kind(j)),
+            Some(Of) | Some(End)
+        )
+}
+
+/// The calls of one file given the references within it
+fn calls_of_file(resolution: &NameResolution, references: &[(&SrcPos, &NamedEntity)]) -> Vec<Call> {
+    let source = &references[0].0.source;
+    let file_name = match source.file_name() {
+        Some(file_name) => file_name,
+        None => {
+            return Vec::new();
+        }
+    };
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+
+    // The extent of each construct which may contain calls
+    let mut callers: Vec<(&NamedEntity, usize, usize)> = Vec::new();
+    for (i, token) in tokens.tokens.iter().enumerate() {
+        let start = match construct_start(&tokens, i) {
+            Some(start) => start,
+            None => continue,
+        };
+        let caller = resolution
+            .entities_at(file_name, token.pos.start)
+            .into_iter()
+            .find(|entity| is_caller(entity.kind));
+        if let Some(caller) = caller {
+            let last = &tokens.tokens[tokens.end_of(start)].pos;
+            callers.push((
+                caller,
+                tokens.tokens[start].pos.start,
+                last.start + last.length,
+            ));
+        }
+    }
+
+    let mut calls = Vec::new();
+    for (pos, callee) in references.iter() {
+        match tokens.index_of(pos) {
+            Some(i) if is_call(&tokens, i) => {}
+            _ => continue,
+        }
+        let caller = callers
+            .iter()
+            .filter(|(_, start, end)| *start <= pos.start && pos.start < *end)
+            .min_by_key(|(_, start, end)| end - start);
+        if let Some((caller, _, _)) = caller {
+            calls.push(Call {
+                caller: (*caller).clone(),
+                callee: (*callee).clone(),
+                pos: (*pos).clone(),
+            });
+        }
+    }
+    calls
+}
+
+/// All calls of the project
+pub fn calls(resolution: &NameResolution) -> Vec<Call> {
+    let mut references_by_file: HashMap<&str, Vec<(&SrcPos, &NamedEntity)>> = HashMap::new();
+    for reference in resolution.references() {
+        let file_name = match reference.pos.source.file_name() {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+        for entity in reference.entities.iter() {
+            if is_callee(entity.kind) {
+                references_by_file
+                    .entry(file_name)
+                    .or_default()
+                    .push((&reference.pos, entity));
+            }
+        }
+    }
+
+    let mut file_names: Vec<&str> = references_by_file.keys().cloned().collect();
+    file_names.sort();
+    file_names
+        .into_iter()
+        .flat_map(|file_name| calls_of_file(resolution, &references_by_file[file_name]))
+        .collect()
+}
+
+/// Adds the position to the group of the named entity in order of first occurrence
+fn group(groups: &mut Vec<(NamedEntity, Vec<SrcPos>)>, entity: NamedEntity, pos: SrcPos) {
+    match groups.iter_mut().find(|(other, _)| *other == entity) {
+        Some((_, positions)) => positions.push(pos),
+        None => groups.push((entity, vec![pos])),
+    }
+}
+
+/// The callers of the named entity with the positions of their calls of it
+pub fn incoming_calls(
+    resolution: &NameResolution,
+    callee: &NamedEntity,
+) -> Vec<(NamedEntity, Vec<SrcPos>)> {
+    let mut groups = Vec::new();
+    for call in calls(resolution) {
+        if call.callee == *callee {
+            group(&mut groups, call.caller, call.pos);
+        }
+    }
+    groups
+}
+
+/// The named entities called by the caller with the positions of the calls
+pub fn outgoing_calls(
+    resolution: &NameResolution,
+    caller: &NamedEntity,
+) -> Vec<(NamedEntity, Vec<SrcPos>)> {
+    let mut groups = Vec::new();
+    for call in calls(resolution) {
+        if call.caller == *caller {
+            group(&mut groups, call.callee, call.pos);
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use library::DesignRoot;
+    use test_util::TempProject;
+
+    fn resolve(project: &TempProject) -> NameResolution {
+        let files = project.project.files();
+        NameResolution::new(&DesignRoot::new(&files))
+    }
+
+    fn declaration<'a>(
+        resolution: &'a NameResolution,
+        designator: &str,
+        kind: NamedEntityKind,
+    ) -> &'a NamedEntity {
+        resolution
+            .declarations()
+            .iter()
+            .find(|entity| entity.designator == designator && entity.kind == kind)
+            .unwrap()
+    }
+
+    /// The designators of the groups together with the number of calls
+    fn summary(groups: Vec<(NamedEntity, Vec<SrcPos>)>) -> Vec<(String, usize)> {
+        groups
+            .into_iter()
+            .map(|(entity, positions)| (entity.designator, positions.len()))
+            .collect()
+    }
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  function inc(value : natural) return natural;
+  function twice(value : natural) return natural;
+end package;
+
+package body pkg is
+  function inc(value : natural) return natural is
+  begin
+    return value + 1;
+  end function inc;
+
+  function twice(value : natural) return natural is
+  begin
+    return inc(inc(value));
+  end function;
+end package body;",
+        );
+        project.add_file(
+            "lib",
+            "child.vhd",
+            "
+use work.pkg.all;
+
+entity child is
+end entity child;
+
+architecture rtl of child is
+  constant width : natural := twice(1);
+begin
+end architecture;",
+        );
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+use work.pkg.all;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+  component comp is
+  end component;
+begin
+  first : entity work.child;
+  second : entity work.child;
+  third : component comp;
+
+  main : process
+    variable count : natural;
+  begin
+    count := inc(count);
+    wait;
+  end process;
+end architecture;",
+        );
+        project
+    }
+
+    #[test]
+    fn incoming_calls_of_subprograms_and_entities() {
+        let project = project();
+        let resolution = resolve(&project);
+        let inc = declaration(&resolution, "inc", NamedEntityKind::Function);
+        let incoming = incoming_calls(&resolution, inc);
+        assert_eq!(
+            summary(incoming.clone()),
+            vec![("twice".to_owned(), 2), ("rtl".to_owned(), 1)]
+        );
+        assert_eq!(incoming[1].0.kind, NamedEntityKind::Architecture);
+
+        let child = declaration(&resolution, "child", NamedEntityKind::Entity);
+        assert_eq!(
+            summary(incoming_calls(&resolution, child)),
+            vec![("rtl".to_owned(), 2)]
+        );
+    }
+
+    #[test]
+    fn outgoing_calls_of_architecture() {
+        let project = project();
+        let resolution = resolve(&project);
+        let rtl = resolution
+            .declarations()
+            .iter()
+            .find(|entity| {
+                entity.designator == "rtl"
+                    && entity.pos.source.file_name().unwrap().ends_with("top.vhd")
+            })
+            .unwrap();
+        assert_eq!(
+            summary(outgoing_calls(&resolution, rtl)),
+            vec![
+                ("child".to_owned(), 2),
+                ("comp".to_owned(), 1),
+                ("inc".to_owned(), 1)
+            ]
+        );
+    }
+}
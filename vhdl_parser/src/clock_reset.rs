@@ -0,0 +1,361 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Lint rules on the clock and reset style of processes using their structure
+//!
+//! - both-clock-edges: a process is clocked on both edges of a clock
+//! - clock-in-logic: a clock is read by combinational logic other than a clock generator
+//! - mixed-reset: an architecture has both synchronous and asynchronous resets
+//! - incomplete-reset: a registered signal is not assigned by the reset of its process
+//!
+//! The mixed-reset and incomplete-reset rules are only enabled when configured
+
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use process_structure::{architecture_structures, Edge, ResetKind};
+
+pub struct BothClockEdges;
+
+impl Rule for BothClockEdges {
+    fn id(&self) -> &str {
+        "both-clock-edges"
+    }
+
+    fn description(&self) -> &str {
+        "A process is clocked on both edges of a clock"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            for process in structure.processes.iter() {
+                for (i, edge) in process.clock_edges.iter().enumerate() {
+                    let other_edge = process.clock_edges[..i].iter().any(|other| {
+                        other.clock.eq_ignore_ascii_case(&edge.clock) && other.edge != edge.edge
+                    });
+                    if edge.edge == Edge::Both || other_edge {
+                        findings.push(warning(
+                            &edge.pos,
+                            &format!(
+                                "{} is clocked on both edges of '{}'",
+                                process.name(),
+                                edge.clock
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct ClockInLogic;
+
+impl Rule for ClockInLogic {
+    fn id(&self) -> &str {
+        "clock-in-logic"
+    }
+
+    fn description(&self) -> &str {
+        "A clock is read by combinational logic"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            let clocks = structure.clocks();
+            let is_clock = |name: &str| clocks.contains(&name.to_lowercase());
+            // Clock generators such as clk <= not clk after 5 ns are not logic
+            let reads = structure
+                .concurrent_assignments
+                .iter()
+                .filter(|assignment| {
+                    !assignment.delayed
+                        && !assignment
+                            .target
+                            .as_ref()
+                            .is_some_and(|name| is_clock(name))
+                })
+                .flat_map(|assignment| assignment.reads.iter())
+                .chain(
+                    structure
+                        .processes
+                        .iter()
+                        .filter(|process| !process.timed)
+                        .flat_map(|process| process.combinational_reads.iter()),
+                );
+            for (name, pos) in reads {
+                if is_clock(name) {
+                    findings.push(warning(
+                        pos,
+                        &format!("Clock '{}' is used in combinational logic", name),
+                    ));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct MixedReset;
+
+impl Rule for MixedReset {
+    fn id(&self) -> &str {
+        "mixed-reset"
+    }
+
+    fn description(&self) -> &str {
+        "An architecture has both synchronous and asynchronous resets"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            let mut resets = structure
+                .processes
+                .iter()
+                .flat_map(|process| process.resets.iter());
+            let first = match resets.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            for reset in resets.filter(|reset| reset.kind != first.kind) {
+                let (kind, other) = match reset.kind {
+                    ResetKind::Synchronous => ("Synchronous", "asynchronous"),
+                    ResetKind::Asynchronous => ("Asynchronous", "synchronous"),
+                };
+                findings.push(warning(
+                    &reset.pos,
+                    &format!(
+                        "{} reset '{}' is mixed with the {} reset '{}'",
+                        kind, reset.signal, other, first.signal
+                    ),
+                ));
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct IncompleteReset;
+
+impl Rule for IncompleteReset {
+    fn id(&self) -> &str {
+        "incomplete-reset"
+    }
+
+    fn description(&self) -> &str {
+        "A registered signal is not assigned by the reset of its process"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            for process in structure.processes.iter() {
+                for reset in process.resets.iter() {
+                    for (name, pos) in process.registered.iter() {
+                        if !reset.assigned.contains(&name.to_lowercase()) {
+                            findings.push(warning(
+                                pos,
+                                &format!(
+                                    "'{}' is registered but not reset by '{}'",
+                                    name, reset.signal
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+    extern crate toml;
+    use self::toml::Value;
+
+    #[test]
+    fn clock_and_reset_style() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  async : process (clk, rst)
+  begin
+    if rst = '1' then
+      count <= 0;
+    elsif rising_edge(clk) then
+      count <= count + 1;
+      data <= input;
+    end if;
+  end process;
+
+  sync : process (clk)
+  begin
+    if rising_edge(clk) then
+      if sync_rst = '1' then
+        flag <= '0';
+      else
+        flag <= not flag;
+      end if;
+    end if;
+  end process;
+
+  ddr : process (clk)
+  begin
+    if rising_edge(clk) then
+      q <= d;
+    end if;
+    if falling_edge(clk) then
+      q <= e;
+    end if;
+  end process;
+
+  gated <= clk and enable;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings = |contents: &str| -> Vec<(String, String, usize)> {
+            let value: Value = contents.parse().unwrap();
+            Registry::default()
+                .check(&context, &LintConfig::from_value(&value).unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|finding| (finding.code.unwrap(), finding.message, finding.pos.start))
+                .collect()
+        };
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(
+            findings(""),
+            vec![
+                (
+                    string("both-clock-edges"),
+                    string("Process 'ddr' is clocked on both edges of 'clk'"),
+                    code.find("falling_edge(clk)").unwrap()
+                ),
+                (
+                    string("clock-in-logic"),
+                    string("Clock 'clk' is used in combinational logic"),
+                    code.find("clk and enable").unwrap()
+                ),
+            ]
+        );
+        assert_eq!(
+            findings(
+                "both-clock-edges = 'off'\nclock-in-logic = 'off'\n\
+                 mixed-reset = 'warning'\nincomplete-reset = 'warning'"
+            ),
+            vec![
+                (
+                    string("incomplete-reset"),
+                    string("'data' is registered but not reset by 'rst'"),
+                    code.find("data <=").unwrap()
+                ),
+                (
+                    string("mixed-reset"),
+                    string(
+                        "Synchronous reset 'sync_rst' is mixed with the asynchronous reset 'rst'"
+                    ),
+                    code.find("sync_rst = '1'").unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn clock_generators_are_not_logic() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "tb.vhd",
+            "
+entity tb is
+end entity;
+
+architecture sim of tb is
+begin
+  clk <= not clk after 5 ns;
+  fast_clk <= not fast_clk;
+
+  generator : process
+  begin
+    slow_clk <= '0';
+    wait for 10 ns;
+    slow_clk <= not slow_clk;
+    wait for 10 ns;
+  end process;
+
+  reg : process (clk, fast_clk, slow_clk)
+  begin
+    if rising_edge(clk) then
+      q <= d;
+    end if;
+    if rising_edge(fast_clk) then
+      r <= d;
+    end if;
+    if rising_edge(slow_clk) then
+      s <= d;
+    end if;
+  end process;
+end architecture;",
+        );
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings = ClockInLogic
+            .check(&context, &RuleConfig::default())
+            .unwrap();
+        assert_eq!(findings, vec![]);
+    }
+}
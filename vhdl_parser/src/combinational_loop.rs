@@ -0,0 +1,262 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The combinational-loop lint rule which reports cycles of signals through
+//! combinational logic without a register
+//!
+//! A concurrent signal assignment makes its target depend on the names it reads and a
+//! process which is not clocked makes every signal it assigns depend on every name it
+//! reads. A loop within a single process is reported by combinational-feedback instead
+//! and an assignment delayed by after, such as a clock generator, does not loop
+
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use process_structure::{architecture_structures, assigned_signals, ArchitectureStructure};
+use source::SrcPos;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The signal assigned from a read signal by a statement
+struct Edge {
+    to: String,
+    /// The position of the read
+    pos: SrcPos,
+    /// The index of the statement which is a process or a concurrent assignment
+    statement: (bool, usize),
+}
+
+/// The edges from each signal by its lower case name
+type Graph = HashMap<String, Vec<Edge>>;
+
+/// The dependencies of the signals and the lower case name and spelling of the signals
+/// in the order they are first read
+fn dependency_graph(structure: &ArchitectureStructure) -> (Graph, Vec<(String, String)>) {
+    let mut graph: Graph = HashMap::new();
+    let mut names: Vec<(String, String)> = Vec::new();
+    let mut add = |from: &str, to: &str, pos: &SrcPos, statement| {
+        let key = from.to_lowercase();
+        if !names.iter().any(|(other, _)| *other == key) {
+            names.push((key.clone(), from.to_owned()));
+        }
+        graph.entry(key).or_default().push(Edge {
+            to: to.to_lowercase(),
+            pos: pos.clone(),
+            statement,
+        });
+    };
+
+    for (i, assignment) in structure.concurrent_assignments.iter().enumerate() {
+        if assignment.delayed {
+            continue;
+        }
+        if let Some(ref target) = assignment.target {
+            for (name, pos) in assignment.reads.iter() {
+                add(name, target, pos, (false, i));
+            }
+        }
+    }
+    for (i, process) in structure.processes.iter().enumerate() {
+        if process.is_clocked() {
+            continue;
+        }
+        let mut assigned = Vec::new();
+        assigned_signals(&process.process.statements, &mut assigned);
+        for (name, pos) in process.combinational_reads.iter() {
+            for target in assigned.iter() {
+                add(name, target, pos, (true, i));
+            }
+        }
+    }
+    (graph, names)
+}
+
+/// The edges of the shortest path from the start back to itself
+fn shortest_loop<'a>(graph: &'a Graph, start: &str) -> Option<Vec<&'a Edge>> {
+    let mut previous: HashMap<&str, (&str, &Edge)> = HashMap::new();
+    let mut pending = VecDeque::new();
+    pending.push_back(start);
+    while let Some(node) = pending.pop_front() {
+        for edge in graph.get(node).into_iter().flatten() {
+            if edge.to == start {
+                let mut path = vec![edge];
+                let mut current = node;
+                while current != start {
+                    let (from, edge) = previous[current];
+                    path.push(edge);
+                    current = from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if !previous.contains_key(edge.to.as_str()) {
+                previous.insert(&edge.to, (node, edge));
+                pending.push_back(&edge.to);
+            }
+        }
+    }
+    None
+}
+
+pub struct CombinationalLoop;
+
+impl Rule for CombinationalLoop {
+    fn id(&self) -> &str {
+        "combinational-loop"
+    }
+
+    fn description(&self) -> &str {
+        "Signals depend on each other through combinational logic without a register"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            let (graph, names) = dependency_graph(&structure);
+            let spelling = |key: &str| {
+                names
+                    .iter()
+                    .find(|(other, _)| other == key)
+                    .map_or(key.to_owned(), |(_, name)| name.clone())
+            };
+            let mut reported: HashSet<String> = HashSet::new();
+            for (key, _) in names.iter() {
+                if reported.contains(key) {
+                    continue;
+                }
+                let path = match shortest_loop(&graph, key) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                let statement = path[0].statement;
+                if statement.0 && path.iter().all(|edge| edge.statement == statement) {
+                    continue;
+                }
+                let mut from = key.clone();
+                let mut signals = vec![format!("'{}'", spelling(key))];
+                let mut related = Vec::new();
+                for edge in path.iter() {
+                    reported.insert(edge.to.clone());
+                    signals.push(format!("'{}'", spelling(&edge.to)));
+                    related.push((
+                        edge.pos.clone(),
+                        format!(
+                            "'{}' depends on '{}' here",
+                            spelling(&edge.to),
+                            spelling(&from)
+                        ),
+                    ));
+                    from = edge.to.clone();
+                }
+                let mut finding = warning(
+                    &path[0].pos,
+                    &format!(
+                        "Combinational loop {} without a register",
+                        signals.join(" -> ")
+                    ),
+                );
+                for (pos, message) in related {
+                    finding = finding.related(&pos, &message);
+                }
+                findings.push(finding);
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn loops_through_combinational_logic() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  a <= b and enable;
+  b <= c when sel = '1' else d;
+
+  comb : process (a)
+  begin
+    c <= not a;
+  end process;
+
+  reg : process (clk)
+  begin
+    if rising_edge(clk) then
+      q <= r;
+    end if;
+  end process;
+  r <= q + 1;
+
+  toggle <= not toggle;
+  clk <= not clk after 5 ns;
+
+  self : process (y)
+  begin
+    y <= not y;
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<Message> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("combinational-loop".to_owned()))
+            .collect();
+
+        let summary: Vec<(&str, usize)> = findings
+            .iter()
+            .map(|finding| (finding.message.as_str(), finding.pos.start))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "Combinational loop 'b' -> 'a' -> 'c' -> 'b' without a register",
+                    code.find("b and enable").unwrap()
+                ),
+                (
+                    "Combinational loop 'toggle' -> 'toggle' without a register",
+                    code.find("toggle;").unwrap()
+                ),
+            ]
+        );
+        let related: Vec<(usize, &str)> = findings[0]
+            .related
+            .iter()
+            .map(|(pos, message)| (pos.start, message.as_str()))
+            .collect();
+        assert_eq!(
+            related,
+            vec![
+                (
+                    code.find("b and enable").unwrap(),
+                    "'a' depends on 'b' here"
+                ),
+                (code.find("a;").unwrap(), "'c' depends on 'a' here"),
+                (code.find("c when").unwrap(), "'b' depends on 'c' here"),
+            ]
+        );
+    }
+}
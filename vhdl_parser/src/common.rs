@@ -35,10 +35,18 @@ pub fn warning_on_end_identifier_mismatch(
 ) -> Option<Message> {
     if let Some(end_ident) = end_ident {
         if ident.item != end_ident.item {
-            return Some(warning(
-                &end_ident.pos,
-                &format!("End identifier mismatch, expected {}", ident.item.name()),
-            ));
+            let name = ident.item.name_utf8();
+            return Some(
+                warning(
+                    &end_ident.pos,
+                    &format!("End identifier mismatch, expected {}", ident.item.name()),
+                )
+                .with_code("end-identifier-mismatch")
+                .with_fix(
+                    &format!("Replace with {}", name),
+                    vec![(end_ident.pos.clone(), name)],
+                ),
+            );
         }
     }
     None
@@ -0,0 +1,361 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Scripts which analyze all files of a project in compile order with a simulator and
+//! file lists in compile order for synthesis and other EDA tools
+
+use project::Project;
+use standard::VHDLStandard;
+use std::str::FromStr;
+
+/// The simulator a compile script is generated for
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Simulator {
+    /// A shell script calling ghdl -a
+    Ghdl,
+    /// A ModelSim or Questa do file calling vcom
+    Modelsim,
+    /// A Riviera-PRO do file calling vcom
+    Riviera,
+}
+
+impl FromStr for Simulator {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Simulator, String> {
+        match string {
+            "ghdl" => Ok(Simulator::Ghdl),
+            "modelsim" | "questa" => Ok(Simulator::Modelsim),
+            "riviera" => Ok(Simulator::Riviera),
+            _ => Err(format!(
+                "Unknown simulator '{}', expected one of ghdl, modelsim, questa or riviera",
+                string
+            )),
+        }
+    }
+}
+
+/// The format of a list of files in compile order
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FileListFormat {
+    /// One file name per line as read by the -f option of many tools
+    Plain,
+    /// A Tcl script calling read_vhdl -library for Vivado
+    Vivado,
+    /// A Tcl script calling add_file -vhdl -lib for Synplify
+    Synplify,
+}
+
+impl FromStr for FileListFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<FileListFormat, String> {
+        match string {
+            "f" | "plain" => Ok(FileListFormat::Plain),
+            "vivado" => Ok(FileListFormat::Vivado),
+            "synplify" => Ok(FileListFormat::Synplify),
+            _ => Err(format!(
+                "Unknown file list format '{}', expected one of f, vivado or synplify",
+                string
+            )),
+        }
+    }
+}
+
+/// A file to analyze into a library with a language standard
+#[derive(PartialEq, Debug, Clone)]
+pub struct CompileStep {
+    pub library_name: String,
+    pub file_name: String,
+    pub standard: VHDLStandard,
+}
+
+/// The files of the project in compile order, files within circular dependencies are
+/// placed last and files without design units are left out
+pub fn compile_steps(project: &Project) -> Vec<CompileStep> {
+    project
+        .dependency_graph()
+        .file_compile_order()
+        .order
+        .into_iter()
+        .filter_map(|file_name| {
+            let file = project.get_file(&file_name)?;
+            Some(CompileStep {
+                library_name: file.library_name.clone(),
+                standard: project.standard(file),
+                file_name,
+            })
+        })
+        .collect()
+}
+
+/// Quotes a word for a POSIX shell
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Quotes a word for Tcl, braces within the word are escaped
+fn tcl_quote(word: &str) -> String {
+    format!("{{{}}}", word.replace('{', "\\{").replace('}', "\\}"))
+}
+
+fn ghdl_standard(standard: VHDLStandard) -> &'static str {
+    match standard {
+        VHDLStandard::VHDL1993 => "93",
+        VHDLStandard::VHDL2002 => "02",
+        VHDLStandard::VHDL2008 => "08",
+        VHDLStandard::VHDL2019 => "19",
+    }
+}
+
+fn vcom_standard(standard: VHDLStandard) -> &'static str {
+    match standard {
+        VHDLStandard::VHDL1993 => "-93",
+        VHDLStandard::VHDL2002 => "-2002",
+        VHDLStandard::VHDL2008 => "-2008",
+        VHDLStandard::VHDL2019 => "-2019",
+    }
+}
+
+/// The libraries of the steps in the order they are first compiled into
+fn libraries(steps: &[CompileStep]) -> Vec<&str> {
+    let mut libraries: Vec<&str> = Vec::new();
+    for step in steps {
+        if !libraries.contains(&step.library_name.as_str()) {
+            libraries.push(&step.library_name);
+        }
+    }
+    libraries
+}
+
+fn ghdl_script(steps: &[CompileStep]) -> String {
+    let mut script = String::from("#!/bin/sh\nset -e\n");
+    for step in steps {
+        script.push_str(&format!(
+            "ghdl -a --std={} --work={} {}\n",
+            ghdl_standard(step.standard),
+            step.library_name,
+            shell_quote(&step.file_name)
+        ));
+    }
+    script
+}
+
+fn vcom_script(steps: &[CompileStep], simulator: Simulator) -> String {
+    let mut script = String::from("onerror {quit -code 1}\n");
+    for library_name in libraries(steps) {
+        script.push_str(&format!("vlib {}\n", library_name));
+        // Riviera maps libraries created by vlib, ModelSim needs an explicit mapping
+        if simulator == Simulator::Modelsim {
+            script.push_str(&format!("vmap {0} {0}\n", library_name));
+        }
+    }
+    for step in steps {
+        script.push_str(&format!(
+            "vcom {} -work {} {}\n",
+            vcom_standard(step.standard),
+            step.library_name,
+            tcl_quote(&step.file_name)
+        ));
+    }
+    script
+}
+
+/// A script which analyzes the files of the steps in order with the simulator
+pub fn compile_script(steps: &[CompileStep], simulator: Simulator) -> String {
+    match simulator {
+        Simulator::Ghdl => ghdl_script(steps),
+        Simulator::Modelsim | Simulator::Riviera => vcom_script(steps, simulator),
+    }
+}
+
+fn vivado_standard(standard: VHDLStandard) -> &'static str {
+    match standard {
+        VHDLStandard::VHDL1993 | VHDLStandard::VHDL2002 => "",
+        VHDLStandard::VHDL2008 => " -vhdl2008",
+        VHDLStandard::VHDL2019 => " -vhdl2019",
+    }
+}
+
+fn vivado_file_list(steps: &[CompileStep]) -> String {
+    let mut file_list = String::new();
+    for step in steps {
+        file_list.push_str(&format!(
+            "read_vhdl -library {}{} {}\n",
+            step.library_name,
+            vivado_standard(step.standard),
+            tcl_quote(&step.file_name)
+        ));
+    }
+    file_list
+}
+
+fn synplify_file_list(steps: &[CompileStep]) -> String {
+    let mut file_list = String::new();
+    // The language standard is a project option of Synplify rather than one of each file
+    if steps.iter().any(|step| {
+        matches!(
+            step.standard,
+            VHDLStandard::VHDL2008 | VHDLStandard::VHDL2019
+        )
+    }) {
+        file_list.push_str("set_option -vhdl2008 1\n");
+    }
+    for step in steps {
+        file_list.push_str(&format!(
+            "add_file -vhdl -lib {} {}\n",
+            step.library_name,
+            tcl_quote(&step.file_name)
+        ));
+    }
+    file_list
+}
+
+/// A list of the files of the steps in order in the format
+pub fn file_list(steps: &[CompileStep], format: FileListFormat) -> String {
+    match format {
+        FileListFormat::Plain => steps
+            .iter()
+            .map(|step| format!("{}\n", step.file_name))
+            .collect(),
+        FileListFormat::Vivado => vivado_file_list(steps),
+        FileListFormat::Synplify => synplify_file_list(steps),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use config::Config;
+    use std::fs;
+
+    #[test]
+    fn scripts_in_compile_order_with_standard_of_each_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            path.to_str().unwrap().to_owned()
+        };
+        let ent = write(
+            "ent.vhd",
+            "library base; use base.pkg.all; entity ent is end entity;",
+        );
+        let pkg = write("pkg.vhd", "package pkg is end package;");
+        let legacy = write("legacy.vhd", "package legacy is end package;");
+        let config = Config::parse(
+            "
+[libraries]
+base.files = ['pkg.vhd', 'legacy.vhd']
+lib.files = ['ent.vhd']
+
+[[files]]
+pattern = 'legacy.vhd'
+standard = '1993'
+",
+            dir.path(),
+        )
+        .unwrap();
+        let mut project = Project::new();
+        assert!(project.add_config(&config).is_empty());
+
+        let steps = compile_steps(&project);
+        let step = |library_name: &str, file_name: &str, standard| CompileStep {
+            library_name: library_name.to_owned(),
+            file_name: file_name.to_owned(),
+            standard,
+        };
+        assert_eq!(
+            steps,
+            vec![
+                step("base", &legacy, VHDLStandard::VHDL1993),
+                step("base", &pkg, VHDLStandard::VHDL2008),
+                step("lib", &ent, VHDLStandard::VHDL2008),
+            ]
+        );
+
+        assert_eq!(
+            compile_script(&steps, Simulator::Ghdl),
+            format!(
+                "#!/bin/sh
+set -e
+ghdl -a --std=93 --work=base '{}'
+ghdl -a --std=08 --work=base '{}'
+ghdl -a --std=08 --work=lib '{}'
+",
+                legacy, pkg, ent
+            )
+        );
+        assert_eq!(
+            compile_script(&steps, Simulator::Modelsim),
+            format!(
+                "onerror {{quit -code 1}}
+vlib base
+vmap base base
+vlib lib
+vmap lib lib
+vcom -93 -work base {{{}}}
+vcom -2008 -work base {{{}}}
+vcom -2008 -work lib {{{}}}
+",
+                legacy, pkg, ent
+            )
+        );
+        assert!(compile_script(&steps, Simulator::Riviera)
+            .starts_with("onerror {quit -code 1}\nvlib base\nvlib lib\nvcom -93 -work base"));
+    }
+
+    #[test]
+    fn file_lists_in_compile_order() {
+        let step = |library_name: &str, file_name: &str, standard| CompileStep {
+            library_name: library_name.to_owned(),
+            file_name: file_name.to_owned(),
+            standard,
+        };
+        let steps = vec![
+            step("base", "legacy.vhd", VHDLStandard::VHDL1993),
+            step("lib", "src/ent.vhd", VHDLStandard::VHDL2008),
+        ];
+        assert_eq!(
+            file_list(&steps, FileListFormat::Plain),
+            "legacy.vhd\nsrc/ent.vhd\n"
+        );
+        assert_eq!(
+            file_list(&steps, FileListFormat::Vivado),
+            "read_vhdl -library base {legacy.vhd}
+read_vhdl -library lib -vhdl2008 {src/ent.vhd}
+"
+        );
+        assert_eq!(
+            file_list(&steps, FileListFormat::Synplify),
+            "set_option -vhdl2008 1
+add_file -vhdl -lib base {legacy.vhd}
+add_file -vhdl -lib lib {src/ent.vhd}
+"
+        );
+        assert_eq!(
+            file_list(&steps[..1], FileListFormat::Synplify),
+            "add_file -vhdl -lib base {legacy.vhd}\n"
+        );
+        assert_eq!("f".parse(), Ok(FileListFormat::Plain));
+        assert!("quartus".parse::<FileListFormat>().is_err());
+    }
+
+    #[test]
+    fn quoting_of_file_names() {
+        assert_eq!(shell_quote("it's.vhd"), "'it'\\''s.vhd'");
+        assert_eq!(tcl_quote("a {b}.vhd"), "{a \\{b\\}.vhd}");
+    }
+
+    #[test]
+    fn simulator_from_str() {
+        assert_eq!("questa".parse(), Ok(Simulator::Modelsim));
+        assert_eq!("riviera".parse(), Ok(Simulator::Riviera));
+        assert!("xsim".parse::<Simulator>().is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Tracking of which design units are out of date since they were last compiled
+//!
+//! The state is stored as a toml file such that it can also be used by external build systems:
+//!
+//! ```toml
+//! [units."lib.ent(rtl)"]
+//! hash = "b1e1a2d4c09e4f5a"
+//! dependencies = ["lib.ent", "lib.pkg"]
+//! ```
+
+use dependency_graph::DependencyGraph;
+use library::UnitId;
+use project::Project;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+extern crate toml;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct UnitState {
+    /// Content hash of the file containing the unit
+    hash: String,
+    dependencies: Vec<String>,
+}
+
+/// The content hashes and dependencies of design units when they were last compiled
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CompileState {
+    units: BTreeMap<String, UnitState>,
+}
+
+fn unit_state(project: &Project, graph: &DependencyGraph, unit_id: &UnitId) -> Option<UnitState> {
+    let node = graph.get(unit_id)?;
+    let file = project.get_file(&node.file_name)?;
+    Some(UnitState {
+        hash: format!("{:016x}", file.content_hash),
+        dependencies: node
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.to_string())
+            .collect(),
+    })
+}
+
+impl CompileState {
+    pub fn new() -> CompileState {
+        CompileState::default()
+    }
+
+    pub fn read_file_path(file_name: &Path) -> io::Result<CompileState> {
+        let contents = fs::read_to_string(file_name)?;
+        toml::from_str(&contents).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", file_name.to_string_lossy(), err),
+            )
+        })
+    }
+
+    pub fn write_file_path(&self, file_name: &Path) -> io::Result<()> {
+        let contents = toml::to_string(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        fs::write(file_name, contents)
+    }
+
+    /// The units which must be compiled again in compile order
+    /// A unit is out of date if its file or dependencies changed since it was
+    /// compiled, or if any unit it directly or indirectly depends on is out of date
+    pub fn outdated_units(&self, project: &Project, graph: &DependencyGraph) -> Vec<UnitId> {
+        let changed: BTreeSet<UnitId> = graph
+            .units()
+            .map(|(unit_id, _)| unit_id)
+            .filter(|unit_id| {
+                let current = unit_state(project, graph, unit_id);
+                self.units.get(&unit_id.to_string()) != current.as_ref()
+            })
+            .cloned()
+            .collect();
+
+        let outdated = graph.transitive_dependents(&changed);
+        graph
+            .compile_order()
+            .order
+            .into_iter()
+            .filter(|unit_id| outdated.contains(unit_id))
+            .collect()
+    }
+
+    /// Record that the units have been compiled
+    pub fn mark_compiled(
+        &mut self,
+        project: &Project,
+        graph: &DependencyGraph,
+        unit_ids: &[UnitId],
+    ) {
+        for unit_id in unit_ids {
+            if let Some(state) = unit_state(project, graph, unit_id) {
+                self.units.insert(unit_id.to_string(), state);
+            }
+        }
+    }
+
+    /// Record that all units of the project have been compiled, units no longer within the project are forgotten
+    pub fn mark_all_compiled(&mut self, project: &Project, graph: &DependencyGraph) {
+        self.units.clear();
+        let unit_ids: Vec<UnitId> = graph.units().map(|(unit_id, _)| unit_id.clone()).collect();
+        self.mark_compiled(project, graph, &unit_ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use test_util::TempProject;
+
+    const PKG: &str = "
+package pkg is
+end package;";
+
+    const ENT: &str = "
+use work.pkg.all;
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+end architecture;";
+
+    fn outdated(project: &TempProject, state: &CompileState) -> Vec<String> {
+        let graph = project.project.dependency_graph();
+        state
+            .outdated_units(&project.project, &graph)
+            .iter()
+            .map(|unit_id| unit_id.to_string())
+            .collect()
+    }
+
+    fn compile_all(project: &TempProject, state: &mut CompileState) {
+        let graph = project.project.dependency_graph();
+        state.mark_all_compiled(&project.project, &graph);
+    }
+
+    #[test]
+    fn all_units_are_outdated_initially() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "pkg.vhd", PKG);
+        project.add_file("lib", "ent.vhd", ENT);
+
+        let mut state = CompileState::new();
+        assert_eq!(
+            outdated(&project, &state),
+            vec!["lib.pkg", "lib.ent", "lib.ent(rtl)"]
+        );
+
+        compile_all(&project, &mut state);
+        assert_eq!(outdated(&project, &state), Vec::<String>::new());
+    }
+
+    #[test]
+    fn dependents_of_changed_file_are_outdated() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "pkg.vhd", PKG);
+        project.add_file("lib", "ent.vhd", ENT);
+        project.add_file("lib", "other.vhd", "entity other is end entity;");
+
+        let mut state = CompileState::new();
+        compile_all(&project, &mut state);
+
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  constant c : natural := 0;
+end package;",
+        );
+        assert_eq!(
+            outdated(&project, &state),
+            vec!["lib.pkg", "lib.ent", "lib.ent(rtl)"]
+        );
+    }
+
+    #[test]
+    fn changed_dependencies_are_outdated() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "ent.vhd", ENT);
+
+        let mut state = CompileState::new();
+        compile_all(&project, &mut state);
+
+        // The use clause now refers to a unit within the project
+        project.add_file("lib", "pkg.vhd", PKG);
+        assert_eq!(
+            outdated(&project, &state),
+            vec!["lib.pkg", "lib.ent", "lib.ent(rtl)"]
+        );
+    }
+
+    #[test]
+    fn partially_compiled() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "pkg.vhd", PKG);
+        project.add_file("lib", "ent.vhd", ENT);
+
+        let mut state = CompileState::new();
+        let graph = project.project.dependency_graph();
+        state.mark_compiled(&project.project, &graph, &[UnitId::primary("lib", "pkg")]);
+        assert_eq!(outdated(&project, &state), vec!["lib.ent", "lib.ent(rtl)"]);
+    }
+
+    #[test]
+    fn write_and_read_file() {
+        let mut project = TempProject::new();
+        project.add_file("lib", "pkg.vhd", PKG);
+        project.add_file("lib", "ent.vhd", ENT);
+
+        let mut state = CompileState::new();
+        compile_all(&project, &mut state);
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("compile_state.toml");
+        state.write_file_path(&file_name).unwrap();
+        assert_eq!(CompileState::read_file_path(&file_name).unwrap(), state);
+
+        fs::write(&file_name, "units = 1").unwrap();
+        assert!(CompileState::read_file_path(&file_name).is_err());
+    }
+}
@@ -24,6 +24,7 @@ use sequential_statement::{
     parse_assert_statement_known_keyword, parse_labeled_sequential_statements, parse_selection,
     parse_signal_assignment_right_hand, parse_target,
 };
+use smallvec::SmallVec;
 use source::WithPos;
 use tokenizer::{Kind::*, Token};
 use tokenstream::TokenStream;
@@ -120,7 +121,7 @@ fn to_procedure_call(
             postponed,
             call: FunctionCall {
                 name: WithPos::new(name, target.pos),
-                parameters: vec![],
+                parameters: SmallVec::new(),
             },
         }),
         Target::Aggregate(..) => {
@@ -476,29 +477,32 @@ pub fn parse_labeled_concurrent_statement_initial_token(
     token: Token,
     messages: &mut MessageHandler,
 ) -> ParseResult<LabeledConcurrentStatement> {
-    if token.kind == Identifier {
-        let name = parse_name_initial_token(stream, token)?;
-        let token = stream.expect()?;
-        if token.kind == Colon {
-            let label = Some(to_simple_name(name)?);
+    stream.nested(|stream| {
+        stream.enter(&token)?;
+        if token.kind == Identifier {
+            let name = parse_name_initial_token(stream, token)?;
             let token = stream.expect()?;
-            let statement = parse_concurrent_statement(stream, token, messages)?;
-            Ok(LabeledConcurrentStatement { label, statement })
+            if token.kind == Colon {
+                let label = Some(to_simple_name(name)?);
+                let token = stream.expect()?;
+                let statement = parse_concurrent_statement(stream, token, messages)?;
+                Ok(LabeledConcurrentStatement { label, statement })
+            } else {
+                let target = name.map_into(Target::Name);
+                let statement = parse_assignment_or_procedure_call(stream, token, target)?;
+                Ok(LabeledConcurrentStatement {
+                    label: None,
+                    statement,
+                })
+            }
         } else {
-            let target = name.map_into(Target::Name);
-            let statement = parse_assignment_or_procedure_call(stream, token, target)?;
+            let statement = parse_concurrent_statement(stream, token, messages)?;
             Ok(LabeledConcurrentStatement {
                 label: None,
                 statement,
             })
         }
-    } else {
-        let statement = parse_concurrent_statement(stream, token, messages)?;
-        Ok(LabeledConcurrentStatement {
-            label: None,
-            statement,
-        })
-    }
+    })
 }
 
 #[cfg(test)]
@@ -0,0 +1,648 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! VHDL-2019 conditional analysis tool directives such as:
+//!
+//! ```vhdl
+//! `if TOOL_NAME = "rust_hdl" and not (DEBUG = "false") then
+//!   ...
+//! `elsif VHDL_VERSION >= "2008" then
+//!   ...
+//! `else
+//!   `warning "Unsupported tool"
+//! `end if
+//! ```
+//!
+//! Directives are evaluated before tokenization by replacing directive lines and lines
+//! which are not analyzed with whitespace, such that source positions are unchanged
+
+use latin_1::Latin1String;
+use message::{error, warning, MessageHandler};
+use source::Source;
+use standard::VHDLStandard;
+use std::collections::BTreeMap;
+use tokenstream::MAX_NESTING_DEPTH;
+
+/// Conditional analysis identifiers and their values, identifiers are case insensitive
+pub type Defines = BTreeMap<String, String>;
+
+/// The predefined conditional analysis identifiers
+pub fn predefined(standard: VHDLStandard) -> Defines {
+    let mut defines = Defines::new();
+    defines.insert(
+        "vhdl_version".to_owned(),
+        standard.version_string().to_owned(),
+    );
+    defines.insert("tool_type".to_owned(), "ANALYZER".to_owned());
+    defines.insert("tool_vendor".to_owned(), "rust_hdl".to_owned());
+    defines.insert("tool_name".to_owned(), "rust_hdl".to_owned());
+    defines.insert("tool_edition".to_owned(), String::new());
+    defines.insert(
+        "tool_version".to_owned(),
+        env!("CARGO_PKG_VERSION").to_owned(),
+    );
+    defines
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum DirectiveToken {
+    Identifier(String),
+    StringLiteral(String),
+    LeftPar,
+    RightPar,
+    Relation(&'static str),
+}
+
+/// Split the text of a directive into tokens, returns the offset of each token within the text
+fn tokenize(text: &[u8]) -> Result<Vec<(usize, DirectiveToken)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < text.len() {
+        let start = idx;
+        let byte = text[idx];
+        let token = match byte {
+            b' ' | b'\t' | b'\r' => {
+                idx += 1;
+                continue;
+            }
+            b'-' if text.get(idx + 1) == Some(&b'-') => {
+                // Trailing comment
+                break;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' => {
+                while idx < text.len() && (text[idx].is_ascii_alphanumeric() || text[idx] == b'_') {
+                    idx += 1;
+                }
+                let name = String::from_utf8_lossy(&text[start..idx]).to_lowercase();
+                DirectiveToken::Identifier(name)
+            }
+            b'"' => {
+                let mut value = Vec::new();
+                idx += 1;
+                loop {
+                    match text.get(idx) {
+                        Some(b'"') if text.get(idx + 1) == Some(&b'"') => {
+                            value.push(b'"');
+                            idx += 2;
+                        }
+                        Some(b'"') => {
+                            idx += 1;
+                            break;
+                        }
+                        Some(chr) => {
+                            value.push(*chr);
+                            idx += 1;
+                        }
+                        None => {
+                            return Err((start, "Unterminated string literal".to_owned()));
+                        }
+                    }
+                }
+                DirectiveToken::StringLiteral(Latin1String::from_vec(value).to_string())
+            }
+            b'(' => {
+                idx += 1;
+                DirectiveToken::LeftPar
+            }
+            b')' => {
+                idx += 1;
+                DirectiveToken::RightPar
+            }
+            b'=' => {
+                idx += 1;
+                DirectiveToken::Relation("=")
+            }
+            b'/' | b'<' | b'>' => {
+                idx += 1;
+                if text.get(idx) == Some(&b'=') {
+                    idx += 1;
+                    DirectiveToken::Relation(match byte {
+                        b'/' => "/=",
+                        b'<' => "<=",
+                        _ => ">=",
+                    })
+                } else if byte == b'/' {
+                    return Err((start, "Expected '/='".to_owned()));
+                } else if byte == b'<' {
+                    DirectiveToken::Relation("<")
+                } else {
+                    DirectiveToken::Relation(">")
+                }
+            }
+            _ => {
+                return Err((start, format!("Unexpected character '{}'", byte as char)));
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Evaluates a conditional analysis expression
+struct ExpressionParser<'a> {
+    tokens: &'a [(usize, DirectiveToken)],
+    idx: usize,
+    end: usize,
+    defines: &'a Defines,
+    /// The levels of parentheses and negations
+    depth: usize,
+}
+
+type EvalResult<T> = Result<T, (usize, String)>;
+
+impl<'a> ExpressionParser<'a> {
+    fn peek(&self) -> Option<&'a DirectiveToken> {
+        self.tokens.get(self.idx).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.idx)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.end)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.peek() == Some(&DirectiveToken::Identifier(keyword.to_owned()))
+    }
+
+    fn expression(&mut self) -> EvalResult<bool> {
+        let mut value = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some(DirectiveToken::Identifier(ref name)) => match name.as_str() {
+                    "and" | "or" | "xor" | "xnor" | "nand" | "nor" => name.clone(),
+                    _ => {
+                        return Ok(value);
+                    }
+                },
+                _ => {
+                    return Ok(value);
+                }
+            };
+            self.idx += 1;
+            let rhs = self.unary()?;
+            value = match operator.as_str() {
+                "and" => value && rhs,
+                "or" => value || rhs,
+                "xor" => value != rhs,
+                "xnor" => value == rhs,
+                "nand" => !(value && rhs),
+                _ => !(value || rhs),
+            };
+        }
+    }
+
+    fn unary(&mut self) -> EvalResult<bool> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err((
+                self.offset(),
+                format!("Nesting is deeper than {} levels", MAX_NESTING_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        let value = if self.is_keyword("not") {
+            self.idx += 1;
+            self.unary().map(|value| !value)
+        } else {
+            self.primary()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn primary(&mut self) -> EvalResult<bool> {
+        let offset = self.offset();
+        match self.peek() {
+            Some(DirectiveToken::LeftPar) => {
+                self.idx += 1;
+                let value = self.expression()?;
+                if self.peek() != Some(&DirectiveToken::RightPar) {
+                    return Err((self.offset(), "Expected ')'".to_owned()));
+                }
+                self.idx += 1;
+                Ok(value)
+            }
+            Some(DirectiveToken::Identifier(ref name)) => {
+                self.idx += 1;
+                let operator = match self.peek() {
+                    Some(DirectiveToken::Relation(operator)) => *operator,
+                    _ => {
+                        return Err((self.offset(), "Expected relational operator".to_owned()));
+                    }
+                };
+                self.idx += 1;
+                let rhs = match self.peek() {
+                    Some(DirectiveToken::StringLiteral(ref value)) => value,
+                    _ => {
+                        return Err((self.offset(), "Expected string literal".to_owned()));
+                    }
+                };
+                self.idx += 1;
+
+                let lhs = match self.defines.get(name) {
+                    Some(value) => value,
+                    None => {
+                        return Err((
+                            offset,
+                            format!("Undefined conditional analysis identifier '{}'", name),
+                        ));
+                    }
+                };
+
+                Ok(match operator {
+                    "=" => lhs == rhs,
+                    "/=" => lhs != rhs,
+                    "<" => lhs < rhs,
+                    "<=" => lhs <= rhs,
+                    ">" => lhs > rhs,
+                    _ => lhs >= rhs,
+                })
+            }
+            _ => Err((
+                offset,
+                "Expected conditional analysis expression".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Evaluate the condition of an `if or `elsif directive which must end with then
+fn evaluate_condition(text: &[u8], defines: &Defines) -> EvalResult<bool> {
+    let tokens = tokenize(text)?;
+    let mut parser = ExpressionParser {
+        tokens: &tokens,
+        idx: 0,
+        end: text.len(),
+        defines,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if !parser.is_keyword("then") {
+        return Err((parser.offset(), "Expected 'then'".to_owned()));
+    }
+    parser.idx += 1;
+    if parser.idx < tokens.len() {
+        return Err((parser.offset(), "Unexpected text after 'then'".to_owned()));
+    }
+    Ok(value)
+}
+
+/// The string of a `warning or `error directive
+fn directive_message(text: &[u8]) -> EvalResult<String> {
+    match tokenize(text)?.as_slice() {
+        [(_, DirectiveToken::StringLiteral(ref value))] => Ok(value.clone()),
+        _ => Err((0, "Expected string literal".to_owned())),
+    }
+}
+
+/// An `if directive and its alternatives
+struct Conditional {
+    /// The start of the `if directive
+    start: usize,
+    parent_active: bool,
+    any_taken: bool,
+    active: bool,
+    in_else: bool,
+}
+
+fn blank(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        if *byte != b'\n' && *byte != b'\r' {
+            *byte = b' ';
+        }
+    }
+}
+
+/// Apply the conditional analysis directives of the code
+/// Returns None if the code does not contain any directives
+pub fn preprocess(
+    source: &Source,
+    code: &Latin1String,
+    defines: &Defines,
+    messages: &mut dyn MessageHandler,
+) -> Option<Latin1String> {
+    if !code.bytes.contains(&b'`') {
+        return None;
+    }
+
+    let mut bytes = code.bytes.clone();
+    let mut stack: Vec<Conditional> = Vec::new();
+    let mut line_start = 0;
+
+    while line_start < bytes.len() {
+        let line_end = bytes[line_start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map(|idx| line_start + idx)
+            .unwrap_or_else(|| bytes.len());
+        let active = stack.last().map(|cond| cond.active).unwrap_or(true);
+
+        let directive_start = bytes[line_start..line_end]
+            .iter()
+            .position(|byte| *byte != b' ' && *byte != b'\t')
+            .map(|idx| line_start + idx)
+            .filter(|idx| bytes[*idx] == b'`');
+
+        let directive_start = match directive_start {
+            Some(directive_start) => directive_start,
+            None => {
+                if !active {
+                    blank(&mut bytes[line_start..line_end]);
+                }
+                line_start = line_end + 1;
+                continue;
+            }
+        };
+
+        let name_start = directive_start + 1;
+        let name_end = bytes[name_start..line_end]
+            .iter()
+            .position(|byte| !byte.is_ascii_alphabetic())
+            .map(|idx| name_start + idx)
+            .unwrap_or(line_end);
+        let name = String::from_utf8_lossy(&bytes[name_start..name_end]).to_lowercase();
+        let rest = &bytes[name_end..line_end];
+        let directive_pos = source.pos(directive_start, name_end - directive_start);
+        let rest_error =
+            |(offset, msg): (usize, String)| error(source.pos(name_end + offset, 1), &msg);
+
+        match name.as_str() {
+            "if" => {
+                let condition = if active {
+                    match evaluate_condition(rest, defines) {
+                        Ok(value) => value,
+                        Err(err) => {
+                            messages.push(rest_error(err));
+                            false
+                        }
+                    }
+                } else {
+                    false
+                };
+                stack.push(Conditional {
+                    start: directive_start,
+                    parent_active: active,
+                    any_taken: condition,
+                    active: active && condition,
+                    in_else: false,
+                });
+            }
+            "elsif" | "else" => match stack.last_mut() {
+                Some(ref mut cond) if !cond.in_else => {
+                    let condition = if name == "else" {
+                        cond.in_else = true;
+                        true
+                    } else if cond.parent_active && !cond.any_taken {
+                        match evaluate_condition(rest, defines) {
+                            Ok(value) => value,
+                            Err(err) => {
+                                messages.push(rest_error(err));
+                                false
+                            }
+                        }
+                    } else {
+                        false
+                    };
+                    cond.active = cond.parent_active && !cond.any_taken && condition;
+                    cond.any_taken = cond.any_taken || condition;
+                }
+                Some(_) => {
+                    messages.push(error(
+                        &directive_pos,
+                        &format!("`{} directive after `else", name),
+                    ));
+                }
+                None => {
+                    messages.push(error(
+                        &directive_pos,
+                        &format!("`{} directive without `if", name),
+                    ));
+                }
+            },
+            "end" => {
+                if stack.pop().is_none() {
+                    messages.push(error(&directive_pos, "`end directive without `if"));
+                }
+            }
+            "warning" | "error" => {
+                if active {
+                    match directive_message(rest) {
+                        Ok(msg) => {
+                            if name == "warning" {
+                                messages.push(warning(&directive_pos, &msg));
+                            } else {
+                                messages.push(error(&directive_pos, &msg));
+                            }
+                        }
+                        Err(err) => messages.push(rest_error(err)),
+                    }
+                }
+            }
+            _ => {
+                messages.push(error(
+                    &directive_pos,
+                    &format!("Unknown conditional analysis directive `{}", name),
+                ));
+            }
+        }
+
+        blank(&mut bytes[line_start..line_end]);
+        line_start = line_end + 1;
+    }
+
+    for cond in stack {
+        messages.push(error(
+            source.pos(cond.start, 3),
+            "Missing `end for `if directive",
+        ));
+    }
+
+    Some(Latin1String::from_vec(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::{Message, Severity};
+
+    fn preprocess_code(code: &str, defines: &[(&str, &str)]) -> (String, Vec<Message>) {
+        let source = Source::from_str(code).unwrap();
+        let mut all_defines = predefined(VHDLStandard::VHDL2019);
+        for (name, value) in defines {
+            all_defines.insert(name.to_lowercase(), value.to_string());
+        }
+        let mut messages = Vec::new();
+        let result = preprocess(
+            &source,
+            &source.contents().unwrap(),
+            &all_defines,
+            &mut messages,
+        )
+        .map(|code| code.to_string())
+        .unwrap_or_else(|| code.to_owned());
+        (result, messages)
+    }
+
+    /// Lines which are analyzed with all other lines blanked
+    fn analyzed_lines(code: &str) -> Vec<&str> {
+        code.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn code_without_directives_is_unchanged() {
+        let source = Source::from_str("entity ent is end entity;").unwrap();
+        let mut messages = Vec::new();
+        assert_eq!(
+            preprocess(
+                &source,
+                &source.contents().unwrap(),
+                &Defines::new(),
+                &mut messages
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn if_elsif_else() {
+        let code = "
+`if DEBUG = \"true\" then
+debug
+`elsif mode = \"fast\" then
+fast
+`else
+other
+`end if
+after";
+        let (result, messages) = preprocess_code(code, &[("DEBUG", "true"), ("mode", "")]);
+        assert_eq!(messages, vec![]);
+        assert_eq!(analyzed_lines(&result), vec!["debug", "after"]);
+        assert_eq!(result.len(), code.len());
+
+        let (result, _) = preprocess_code(code, &[("DEBUG", "false"), ("mode", "fast")]);
+        assert_eq!(analyzed_lines(&result), vec!["fast", "after"]);
+
+        let (result, _) = preprocess_code(code, &[("DEBUG", "false"), ("mode", "slow")]);
+        assert_eq!(analyzed_lines(&result), vec!["other", "after"]);
+    }
+
+    #[test]
+    fn nested_conditionals() {
+        let code = "
+`if VHDL_VERSION >= \"2008\" then
+  `if TOOL_NAME /= \"rust_hdl\" then
+  a
+  `else
+  b
+  `end
+`else
+  `if TOOL_NAME = \"rust_hdl\" then
+  c
+  `end if
+`end if";
+        let (result, messages) = preprocess_code(code, &[]);
+        assert_eq!(messages, vec![]);
+        assert_eq!(analyzed_lines(&result), vec!["b"]);
+    }
+
+    #[test]
+    fn logical_operators() {
+        let code = "
+`if not (A = \"1\" and B = \"1\") or A = \"1\" xor (B < \"2\") then
+yes
+`end if";
+        let (result, messages) = preprocess_code(code, &[("A", "1"), ("B", "1")]);
+        assert_eq!(messages, vec![]);
+        // (not true) or true xor true, evaluated left to right
+        assert_eq!(analyzed_lines(&result), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn warning_and_error_directives() {
+        let code = "
+`if A = \"1\" then
+`warning \"Active warning\"
+`else
+`error \"Inactive error\"
+`end if
+`error \"Active \"\"error\"\"\"";
+        let (_, messages) = preprocess_code(code, &[("A", "1")]);
+        let messages: Vec<(Severity, &str)> = messages
+            .iter()
+            .map(|msg| (msg.severity, msg.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (Severity::Warning, "Active warning"),
+                (Severity::Error, "Active \"error\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_identifier() {
+        let code = "
+`if MISSING = \"1\" then
+a
+`end if";
+        let source = Source::from_str(code).unwrap();
+        let (result, messages) = preprocess_code(code, &[]);
+        assert_eq!(
+            messages,
+            vec![error(
+                source.first_substr_pos("M"),
+                "Undefined conditional analysis identifier 'missing'"
+            )]
+        );
+        assert_eq!(analyzed_lines(&result), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn malformed_directives() {
+        let (_, messages) = preprocess_code("`if A = \"1\"\n`end if", &[("A", "1")]);
+        assert_eq!(messages[0].message, "Expected 'then'");
+
+        let (_, messages) = preprocess_code("`end if", &[]);
+        assert_eq!(messages[0].message, "`end directive without `if");
+
+        let (_, messages) = preprocess_code("`if A = \"1\" then", &[("A", "1")]);
+        assert_eq!(messages[0].message, "Missing `end for `if directive");
+
+        let (_, messages) = preprocess_code("`define A", &[]);
+        assert_eq!(
+            messages[0].message,
+            "Unknown conditional analysis directive `define"
+        );
+
+        let (_, messages) =
+            preprocess_code("`if A = \"1\" then\n`else\n`else\n`end", &[("A", "1")]);
+        assert_eq!(messages[0].message, "`else directive after `else");
+    }
+
+    #[test]
+    fn deeply_nested_condition() {
+        let code = format!(
+            "`if {}A = \"1\"{} then\n`end if",
+            "(".repeat(MAX_NESTING_DEPTH),
+            ")".repeat(MAX_NESTING_DEPTH)
+        );
+        let (_, messages) = preprocess_code(&code, &[("A", "1")]);
+        assert_eq!(
+            messages[0].message,
+            format!("Nesting is deeper than {} levels", MAX_NESTING_DEPTH)
+        );
+
+        let code = format!("`if {}A = \"1\" then\n`end if", "not ".repeat(10));
+        let (_, messages) = preprocess_code(&code, &[("A", "1")]);
+        assert_eq!(messages, vec![]);
+    }
+}
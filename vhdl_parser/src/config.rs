@@ -0,0 +1,927 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Project configuration read from a toml file such as:
+//!
+//! ```toml
+//! blackboxes = ["unisim.*", "xpm_*"]
+//!
+//! [libraries]
+//! lib.files = ["src/**/*.vhd", "ip/fifo.vhd"]
+//! lib.exclude = ["src/**/*_tb.vhd"]
+//! vendor.files = ["vendor/*.vhd"]
+//! vendor.interface_only = true
+//! legacy.files = ["legacy/*.vhd"]
+//! legacy.standard = "1993"
+//! legacy.defines = { SIMULATION = "true" }
+//!
+//! [[files]]
+//! pattern = "legacy/new_*.vhd"
+//! standard = "2008"
+//! defines = { DEBUG = "false" }
+//!
+//! [testbench]
+//! command = "python run.py {library}.{entity}.*"
+//!
+//! [external]
+//! command = "ghdl -a --std={standard} --work={library} {file}"
+//! format = "ghdl"
+//!
+//! [lint]
+//! incomplete-sensitivity-list = "off"
+//! keyword-case = { case = "lower" }
+//! ```
+//!
+//! Relative patterns are expanded relative to the directory of the project file
+//! Only the interface of design units within an interface only library is analyzed,
+//! which is intended for third party code such as vendor simulation models
+//!
+//! The language standard and conditional analysis defines are set per library and
+//! can be overridden for individual files, later file entries take precedence
+//!
+//! Blackboxes are the units such as vendor primitives which are intentionally instantiated
+//! without being part of the project, the patterns match the instantiated unit name such
+//! as unisim.bufg or only its last part such as bufg ignoring case
+//!
+//! The testbench command runs a testbench from the directory of the project file
+//! where {library}, {entity} and {file} are replaced by those of the testbench
+//!
+//! The external command is a compiler which is run on each file in compile order where
+//! {library}, {file} and {standard} are replaced by those of the file, its diagnostics are
+//! parsed in the format of ghdl or vcom as described in the external_tool module
+//!
+//! The lint section configures the lint rules by their id as described in the lint module
+//! A lints.toml file next to the project file configures the lint rules the same way
+//! without a section, the lint section of the project file takes precedence over it
+
+use external_tool::{ExternalTool, OutputFormat};
+use lint::LintConfig;
+use standard::VHDLStandard;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+extern crate glob;
+extern crate toml;
+use self::glob::{MatchOptions, Pattern};
+use self::toml::Value;
+use ParseOptions;
+
+/// The source files of a library
+#[derive(Clone, PartialEq, Debug)]
+pub struct LibraryConfig {
+    name: String,
+    patterns: Vec<String>,
+    exclude: Vec<Pattern>,
+    interface_only: bool,
+    standard: Option<VHDLStandard>,
+    defines: BTreeMap<String, String>,
+}
+
+impl LibraryConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bodies are not analyzed and errors are reported as warnings
+    pub fn is_interface_only(&self) -> bool {
+        self.interface_only
+    }
+
+    /// Expand the file patterns, files matching an exclusion pattern are removed
+    /// The result is sorted and free of duplicates to give a deterministic order
+    pub fn file_names(&self) -> Result<Vec<String>, String> {
+        let mut file_names = BTreeSet::new();
+        for pattern in self.patterns.iter() {
+            let paths = glob::glob(pattern)
+                .map_err(|err| format!("Invalid pattern '{}': {}", pattern, err))?;
+            for path in paths {
+                let path = path.map_err(|err| err.to_string())?;
+                if !path.is_file() || self.is_excluded(&path) {
+                    continue;
+                }
+                file_names.insert(path.to_string_lossy().into_owned());
+            }
+        }
+        Ok(file_names.into_iter().collect())
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(path))
+    }
+}
+
+/// Parse options of the files matching a pattern
+#[derive(Clone, PartialEq, Debug)]
+struct FileConfig {
+    pattern: Pattern,
+    standard: Option<VHDLStandard>,
+    defines: BTreeMap<String, String>,
+}
+
+/// The libraries of a project
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Config {
+    libraries: Vec<LibraryConfig>,
+    files: Vec<FileConfig>,
+    testbench_command: Option<String>,
+    external_tool: Option<ExternalTool>,
+    lint: LintConfig,
+    blackboxes: Vec<Pattern>,
+}
+
+/// Make a relative pattern relative to the directory of the project file
+fn absolute_pattern(parent: &Path, pattern: &str) -> String {
+    if Path::new(pattern).is_absolute() {
+        pattern.to_owned()
+    } else {
+        parent.join(pattern).to_string_lossy().into_owned()
+    }
+}
+
+fn string_array(value: &Value, what: &str) -> Result<Vec<String>, String> {
+    let array = value
+        .as_array()
+        .ok_or_else(|| format!("{} must be an array", what))?;
+    let mut strings = Vec::with_capacity(array.len());
+    for item in array {
+        let string = item
+            .as_str()
+            .ok_or_else(|| format!("{} must only contain strings", what))?;
+        strings.push(string.to_owned());
+    }
+    Ok(strings)
+}
+
+fn parse_pattern(parent: &Path, pattern: &str) -> Result<Pattern, String> {
+    let pattern = absolute_pattern(parent, pattern);
+    Pattern::new(&pattern).map_err(|err| format!("Invalid pattern '{}': {}", pattern, err))
+}
+
+fn parse_standard(value: Option<&Value>, what: &str) -> Result<Option<VHDLStandard>, String> {
+    match value {
+        Some(value) => {
+            let string = value
+                .as_str()
+                .ok_or_else(|| format!("{} standard must be a string", what))?;
+            string
+                .parse()
+                .map(Some)
+                .map_err(|err| format!("{} {}", what, err))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Conditional analysis identifiers are case insensitive and stored in lower case
+fn parse_defines(value: Option<&Value>, what: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut defines = BTreeMap::new();
+    let table = match value {
+        Some(value) => value
+            .as_table()
+            .ok_or_else(|| format!("{} defines must be a table", what))?,
+        None => {
+            return Ok(defines);
+        }
+    };
+    for (name, value) in table.iter() {
+        let value = value
+            .as_str()
+            .ok_or_else(|| format!("{} define {} must be a string", what, name))?;
+        defines.insert(name.to_lowercase(), value.to_owned());
+    }
+    Ok(defines)
+}
+
+fn parse_files(value: &Value, parent: &Path) -> Result<Vec<FileConfig>, String> {
+    let array = value
+        .as_array()
+        .ok_or_else(|| "files must be an array of tables".to_owned())?;
+    let mut files = Vec::with_capacity(array.len());
+    for file in array {
+        let file = file
+            .as_table()
+            .ok_or_else(|| "files must be an array of tables".to_owned())?;
+
+        for key in file.keys() {
+            if key != "pattern" && key != "standard" && key != "defines" {
+                return Err(format!("Unknown key '{}' of files", key));
+            }
+        }
+
+        let pattern = file
+            .get("pattern")
+            .and_then(|pattern| pattern.as_str())
+            .ok_or_else(|| "files entry must have a pattern string".to_owned())?;
+        let what = format!("files entry {}", pattern);
+
+        files.push(FileConfig {
+            pattern: parse_pattern(parent, pattern)?,
+            standard: parse_standard(file.get("standard"), &what)?,
+            defines: parse_defines(file.get("defines"), &what)?,
+        });
+    }
+    Ok(files)
+}
+
+fn parse_testbench(value: &Value) -> Result<Option<String>, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| "testbench must be a table".to_owned())?;
+    for key in table.keys() {
+        if key != "command" {
+            return Err(format!("Unknown key '{}' of testbench", key));
+        }
+    }
+    match table.get("command") {
+        Some(command) => command
+            .as_str()
+            .map(|command| Some(command.to_owned()))
+            .ok_or_else(|| "testbench command must be a string".to_owned()),
+        None => Ok(None),
+    }
+}
+
+fn parse_external(value: &Value) -> Result<ExternalTool, String> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| "external must be a table".to_owned())?;
+    for key in table.keys() {
+        if key != "command" && key != "format" {
+            return Err(format!("Unknown key '{}' of external", key));
+        }
+    }
+    let command = table
+        .get("command")
+        .ok_or_else(|| "external is missing command".to_owned())?
+        .as_str()
+        .ok_or_else(|| "external command must be a string".to_owned())?;
+    let format = match table.get("format") {
+        Some(format) => format
+            .as_str()
+            .ok_or_else(|| "external format must be a string".to_owned())?
+            .parse()?,
+        None => OutputFormat::Ghdl,
+    };
+    Ok(ExternalTool {
+        command: command.to_owned(),
+        format,
+    })
+}
+
+impl Config {
+    /// Parse the configuration where relative patterns are relative to the parent directory
+    pub fn parse(string: &str, parent: &Path) -> Result<Config, String> {
+        let value: Value = string.parse().map_err(|err| format!("{}", err))?;
+        Config::from_value(&value, parent)
+    }
+
+    /// The configuration of an already parsed value such as settings of an editor
+    pub fn from_value(value: &Value, parent: &Path) -> Result<Config, String> {
+        let mut libraries = Vec::new();
+
+        let files = match value.get("files") {
+            Some(files) => parse_files(files, parent)?,
+            None => Vec::new(),
+        };
+
+        let testbench_command = match value.get("testbench") {
+            Some(testbench) => parse_testbench(testbench)?,
+            None => None,
+        };
+
+        let external_tool = match value.get("external") {
+            Some(external) => Some(parse_external(external)?),
+            None => None,
+        };
+
+        let lint = match value.get("lint") {
+            Some(lint) => LintConfig::from_value(lint)?,
+            None => LintConfig::default(),
+        };
+
+        let blackboxes = match value.get("blackboxes") {
+            Some(blackboxes) => {
+                let mut patterns = Vec::new();
+                for pattern in string_array(blackboxes, "blackboxes")? {
+                    patterns.push(Pattern::new(&pattern).map_err(|err| {
+                        format!("Invalid blackbox pattern '{}': {}", pattern, err)
+                    })?);
+                }
+                patterns
+            }
+            None => Vec::new(),
+        };
+
+        let libs = match value.get("libraries") {
+            Some(libs) => libs
+                .as_table()
+                .ok_or_else(|| "libraries must be a table".to_owned())?,
+            None => {
+                return Ok(Config {
+                    libraries,
+                    files,
+                    testbench_command,
+                    external_tool,
+                    lint,
+                    blackboxes,
+                });
+            }
+        };
+
+        for (name, lib) in libs.iter() {
+            let lib = lib
+                .as_table()
+                .ok_or_else(|| format!("library {} must be a table", name))?;
+
+            for key in lib.keys() {
+                if !["files", "exclude", "interface_only", "standard", "defines"]
+                    .contains(&key.as_str())
+                {
+                    return Err(format!("Unknown key '{}' of library {}", key, name));
+                }
+            }
+
+            let patterns = match lib.get("files") {
+                Some(files) => string_array(files, &format!("library {} files", name))?,
+                None => {
+                    return Err(format!("library {} is missing files", name));
+                }
+            };
+            let patterns = patterns
+                .iter()
+                .map(|pattern| absolute_pattern(parent, pattern))
+                .collect();
+
+            let exclude = match lib.get("exclude") {
+                Some(exclude) => string_array(exclude, &format!("library {} exclude", name))?,
+                None => Vec::new(),
+            };
+            let mut exclude_patterns = Vec::with_capacity(exclude.len());
+            for pattern in exclude {
+                exclude_patterns.push(parse_pattern(parent, &pattern)?);
+            }
+
+            let interface_only = match lib.get("interface_only") {
+                Some(value) => value
+                    .as_bool()
+                    .ok_or_else(|| format!("library {} interface_only must be a boolean", name))?,
+                None => false,
+            };
+
+            let what = format!("library {}", name);
+            libraries.push(LibraryConfig {
+                name: name.to_owned(),
+                patterns,
+                exclude: exclude_patterns,
+                interface_only,
+                standard: parse_standard(lib.get("standard"), &what)?,
+                defines: parse_defines(lib.get("defines"), &what)?,
+            });
+        }
+
+        Ok(Config {
+            libraries,
+            files,
+            testbench_command,
+            external_tool,
+            lint,
+            blackboxes,
+        })
+    }
+
+    /// Read the project file, patterns are relative to the directory of the file
+    pub fn read_file_path(file_name: &Path) -> io::Result<Config> {
+        let mut file = File::open(file_name)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let parent = file_name.parent().map(Path::to_owned).unwrap_or_default();
+
+        let invalid = |file_name: &Path, msg: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", file_name.to_string_lossy(), msg),
+            )
+        };
+        let mut config =
+            Config::parse(&contents, &parent).map_err(|msg| invalid(file_name, msg))?;
+
+        let lints_file = parent.join("lints.toml");
+        if lints_file.is_file() {
+            let mut contents = String::new();
+            File::open(&lints_file)?.read_to_string(&mut contents)?;
+            let mut lint = contents
+                .parse::<Value>()
+                .map_err(|err| err.to_string())
+                .and_then(|value| LintConfig::from_value(&value))
+                .map_err(|msg| invalid(&lints_file, msg))?;
+            lint.merge(config.lint);
+            config.lint = lint;
+        }
+        Ok(config)
+    }
+
+    /// Libraries of the other configuration replace those of the same name
+    /// and its file entries and lint rule configurations take precedence over the existing ones
+    /// while its blackboxes are added
+    pub fn merge(&mut self, other: Config) {
+        for library in other.libraries {
+            match self
+                .libraries
+                .iter_mut()
+                .find(|existing| existing.name == library.name)
+            {
+                Some(existing) => *existing = library,
+                None => self.libraries.push(library),
+            }
+        }
+        self.files.extend(other.files);
+        if other.testbench_command.is_some() {
+            self.testbench_command = other.testbench_command;
+        }
+        if other.external_tool.is_some() {
+            self.external_tool = other.external_tool;
+        }
+        self.lint.merge(other.lint);
+        self.blackboxes.extend(other.blackboxes);
+    }
+
+    pub fn get_library(&self, name: &str) -> Option<&LibraryConfig> {
+        self.libraries.iter().find(|library| library.name == name)
+    }
+
+    /// The command template which runs a testbench
+    pub fn testbench_command(&self) -> Option<&str> {
+        self.testbench_command.as_deref()
+    }
+
+    /// The external compiler whose diagnostics complement those of the analyzer
+    pub fn external_tool(&self) -> Option<&ExternalTool> {
+        self.external_tool.as_ref()
+    }
+
+    /// The configuration of the lint rules
+    pub fn lint(&self) -> &LintConfig {
+        &self.lint
+    }
+
+    /// Whether the instantiated unit, such as unisim.bufg, is an intended blackbox
+    pub fn is_blackbox(&self, unit_name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        let last = unit_name.rsplit('.').next().unwrap_or(unit_name);
+        self.blackboxes.iter().any(|pattern| {
+            pattern.matches_with(unit_name, options) || pattern.matches_with(last, options)
+        })
+    }
+
+    /// Libraries sorted by name
+    pub fn iter_libraries(&self) -> impl Iterator<Item = &LibraryConfig> {
+        self.libraries.iter()
+    }
+
+    /// The language standard and defines of a file within the library
+    /// Settings of the library are overridden by matching file entries
+    pub fn parse_options(&self, library_name: &str, file_name: &str) -> ParseOptions {
+        let mut options = ParseOptions::default();
+
+        let library_name = library_name.to_lowercase();
+        if let Some(library) = self
+            .libraries
+            .iter()
+            .find(|library| library.name.to_lowercase() == library_name)
+        {
+            options.interface_only = library.interface_only;
+            if let Some(standard) = library.standard {
+                options.standard = standard;
+            }
+            options.defines = library.defines.clone();
+        }
+
+        let path = Path::new(file_name);
+        for file in self.files.iter() {
+            if !file.pattern.matches_path(path) {
+                continue;
+            }
+            if let Some(standard) = file.standard {
+                options.standard = standard;
+            }
+            for (name, value) in file.defines.iter() {
+                options.defines.insert(name.clone(), value.clone());
+            }
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::Severity;
+    extern crate tempfile;
+    use std::fs;
+
+    fn touch(parent: &Path, file_name: &str) -> String {
+        let path = parent.join(file_name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn expands_globs_relative_to_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path();
+        let file1 = touch(parent, "src/a.vhd");
+        let file2 = touch(parent, "src/sub/b.vhd");
+        touch(parent, "src/c.txt");
+        let file3 = touch(parent, "ip/fifo.vhd");
+
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = ['src/**/*.vhd', 'ip/fifo.vhd']
+",
+            parent,
+        )
+        .unwrap();
+
+        let library = config.get_library("lib").unwrap();
+        assert_eq!(library.name(), "lib");
+        let mut expected = vec![file1, file2, file3];
+        expected.sort();
+        assert_eq!(library.file_names().unwrap(), expected);
+    }
+
+    #[test]
+    fn exclusion_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path();
+        let file1 = touch(parent, "src/a.vhd");
+        touch(parent, "src/a_tb.vhd");
+        touch(parent, "src/tb/b.vhd");
+
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = ['src/**/*.vhd']
+lib.exclude = ['src/*_tb.vhd', 'src/tb/*']
+",
+            parent,
+        )
+        .unwrap();
+
+        let library = config.get_library("lib").unwrap();
+        assert_eq!(library.file_names().unwrap(), vec![file1]);
+    }
+
+    #[test]
+    fn overlapping_patterns_are_not_duplicated() {
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path();
+        let file1 = touch(parent, "a.vhd");
+
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = ['*.vhd', 'a.vhd']
+",
+            parent,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.get_library("lib").unwrap().file_names().unwrap(),
+            vec![file1]
+        );
+    }
+
+    #[test]
+    fn interface_only_libraries() {
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = []
+vendor.files = []
+vendor.interface_only = true
+",
+            Path::new(""),
+        )
+        .unwrap();
+        assert!(!config.get_library("lib").unwrap().is_interface_only());
+        assert!(config.get_library("vendor").unwrap().is_interface_only());
+        assert_eq!(
+            Config::parse(
+                "[libraries]\nlib.files = []\nlib.interface_only = 1",
+                Path::new("")
+            ),
+            Err("library lib interface_only must be a boolean".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_options_of_library_and_files() {
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = []
+legacy.files = []
+legacy.standard = '93'
+legacy.defines = { Simulation = 'true', DEBUG = 'true' }
+
+[[files]]
+pattern = 'legacy/new_*.vhd'
+standard = '2008'
+defines = { debug = 'false' }
+
+[[files]]
+pattern = 'legacy/new_2019.vhd'
+standard = '2019'
+",
+            Path::new("/project"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.parse_options("lib", "/project/src/a.vhd"),
+            ParseOptions::default()
+        );
+
+        let options = config.parse_options("legacy", "/project/legacy/a.vhd");
+        assert_eq!(options.standard, VHDLStandard::VHDL1993);
+        assert_eq!(
+            options.defines,
+            vec![
+                ("debug".to_owned(), "true".to_owned()),
+                ("simulation".to_owned(), "true".to_owned())
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        let options = config.parse_options("Legacy", "/project/legacy/new_a.vhd");
+        assert_eq!(options.standard, VHDLStandard::VHDL2008);
+        assert_eq!(options.defines.get("debug"), Some(&"false".to_owned()));
+        assert_eq!(options.defines.get("simulation"), Some(&"true".to_owned()));
+
+        let options = config.parse_options("legacy", "/project/legacy/new_2019.vhd");
+        assert_eq!(options.standard, VHDLStandard::VHDL2019);
+    }
+
+    #[test]
+    fn invalid_parse_options() {
+        let parent = Path::new("");
+        assert_eq!(
+            Config::parse("[libraries]\nlib.files = []\nlib.standard = '87'", parent),
+            Err(
+                "library lib Unknown VHDL standard '87', expected one of 1993, 2002, 2008 or 2019"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            Config::parse("[libraries]\nlib.files = []\nlib.defines = 1", parent),
+            Err("library lib defines must be a table".to_owned())
+        );
+        assert_eq!(
+            Config::parse(
+                "[libraries]\nlib.files = []\nlib.defines = { debug = 1 }",
+                parent
+            ),
+            Err("library lib define debug must be a string".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[[files]]\nstandard = '2008'", parent),
+            Err("files entry must have a pattern string".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[[files]]\npattern = '*.vhd'\nfoo = 1", parent),
+            Err("Unknown key 'foo' of files".to_owned())
+        );
+    }
+
+    #[test]
+    fn libraries_are_sorted() {
+        let config = Config::parse(
+            "
+[libraries]
+lib2.files = []
+lib1.files = []
+",
+            Path::new(""),
+        )
+        .unwrap();
+        let names: Vec<&str> = config.iter_libraries().map(|lib| lib.name()).collect();
+        assert_eq!(names, vec!["lib1", "lib2"]);
+    }
+
+    #[test]
+    fn invalid_configurations() {
+        let parent = Path::new("");
+        assert_eq!(
+            Config::parse("libraries = 1", parent),
+            Err("libraries must be a table".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[libraries]\nlib.exclude = []", parent),
+            Err("library lib is missing files".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[libraries]\nlib.files = [1]", parent),
+            Err("library lib files must only contain strings".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[libraries]\nlib.foo = []", parent),
+            Err("Unknown key 'foo' of library lib".to_owned())
+        );
+        assert!(Config::parse("[libraries]\nlib.files = []\nlib.exclude = ['[']", parent).is_err());
+    }
+
+    #[test]
+    fn testbench_command() {
+        let parent = Path::new("");
+        let config = Config::parse(
+            "[testbench]\ncommand = 'python run.py {library}.{entity}.*'",
+            parent,
+        )
+        .unwrap();
+        assert_eq!(
+            config.testbench_command(),
+            Some("python run.py {library}.{entity}.*")
+        );
+        assert_eq!(Config::parse("", parent).unwrap().testbench_command(), None);
+        assert_eq!(
+            Config::parse("[testbench]\ncommand = 1", parent),
+            Err("testbench command must be a string".to_owned())
+        );
+        assert_eq!(
+            Config::parse("[testbench]\nfoo = 'bar'", parent),
+            Err("Unknown key 'foo' of testbench".to_owned())
+        );
+    }
+
+    #[test]
+    fn external_tool() {
+        let parent = Path::new("");
+        let config = Config::parse(
+            "[external]\ncommand = 'vcom -work {library} {file}'\nformat = 'modelsim'",
+            parent,
+        )
+        .unwrap();
+        assert_eq!(
+            config.external_tool(),
+            Some(&ExternalTool {
+                command: "vcom -work {library} {file}".to_owned(),
+                format: OutputFormat::Vcom,
+            })
+        );
+        assert_eq!(
+            Config::parse("[external]\ncommand = 'ghdl -a {file}'", parent)
+                .unwrap()
+                .external_tool()
+                .map(|tool| tool.format),
+            Some(OutputFormat::Ghdl)
+        );
+        assert_eq!(Config::parse("", parent).unwrap().external_tool(), None);
+        assert_eq!(
+            Config::parse("[external]\nformat = 'ghdl'", parent),
+            Err("external is missing command".to_owned())
+        );
+        assert!(Config::parse("[external]\ncommand = 'c'\nformat = 'vivado'", parent).is_err());
+        assert_eq!(
+            Config::parse("[external]\ncommand = 'c'\nfoo = 1", parent),
+            Err("Unknown key 'foo' of external".to_owned())
+        );
+    }
+
+    #[test]
+    fn blackboxes() {
+        let parent = Path::new("");
+        let config = Config::parse("blackboxes = ['unisim.*', 'xpm_*']", parent).unwrap();
+        assert!(config.is_blackbox("unisim.bufg"));
+        assert!(config.is_blackbox("UNISIM.vcomponents.BUFG"));
+        assert!(config.is_blackbox("xpm_memory_spram"));
+        assert!(config.is_blackbox("work.xpm_cdc_single"));
+        assert!(!config.is_blackbox("fifo"));
+        assert!(!Config::parse("", parent).unwrap().is_blackbox("bufg"));
+        assert_eq!(
+            Config::parse("blackboxes = 'bufg'", parent),
+            Err("blackboxes must be an array".to_owned())
+        );
+    }
+
+    #[test]
+    fn lint_rules() {
+        let parent = Path::new("");
+        let mut config = Config::parse(
+            "[lint]\nundeclared = 'off'\nkeyword-case = { case = 'upper' }",
+            parent,
+        )
+        .unwrap();
+        config.merge(Config::parse("[lint]\nundeclared = 'warning'", parent).unwrap());
+        assert_eq!(
+            config.lint().get("undeclared").unwrap().severity,
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            config.lint().get("keyword-case").unwrap().enabled,
+            Some(true)
+        );
+        assert_eq!(config.lint().get("not-visible"), None);
+        assert_eq!(
+            Config::parse("lint = 1", parent),
+            Err("lint must be a table".to_owned())
+        );
+    }
+
+    #[test]
+    fn merge_replaces_libraries_of_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path();
+        touch(parent, "a.vhd");
+        let file2 = touch(parent, "b.vhd");
+        let file3 = touch(parent, "c.vhd");
+
+        let mut config = Config::parse(
+            "
+[libraries]
+lib.files = ['a.vhd']
+other.files = ['c.vhd']
+
+[testbench]
+command = 'run'
+",
+            parent,
+        )
+        .unwrap();
+        config.merge(
+            Config::parse(
+                "
+[libraries]
+lib.files = ['b.vhd']
+",
+                parent,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(
+            config.get_library("lib").unwrap().file_names().unwrap(),
+            vec![file2]
+        );
+        assert_eq!(
+            config.get_library("other").unwrap().file_names().unwrap(),
+            vec![file3]
+        );
+        assert_eq!(config.testbench_command(), Some("run"));
+    }
+
+    #[test]
+    fn read_file_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = touch(dir.path(), "a.vhd");
+        let config_file = dir.path().join("vhdl_ls.toml");
+        fs::write(&config_file, "[libraries]\nlib.files = ['*.vhd']").unwrap();
+
+        let config = Config::read_file_path(&config_file).unwrap();
+        assert_eq!(
+            config.get_library("lib").unwrap().file_names().unwrap(),
+            vec![file1]
+        );
+
+        fs::write(&config_file, "[libraries]\nlib = 1").unwrap();
+        assert!(Config::read_file_path(&config_file).is_err());
+    }
+
+    #[test]
+    fn read_lints_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("vhdl_ls.toml");
+        fs::write(&config_file, "[lint]\nundeclared = 'error'").unwrap();
+        fs::write(
+            dir.path().join("lints.toml"),
+            "undeclared = 'off'\nwrite-only = 'hint'",
+        )
+        .unwrap();
+
+        let config = Config::read_file_path(&config_file).unwrap();
+        let undeclared = config.lint().get("undeclared").unwrap();
+        assert_eq!(undeclared.enabled, Some(true));
+        assert_eq!(undeclared.severity, Some(Severity::Error));
+        assert_eq!(
+            config.lint().get("write-only").unwrap().severity,
+            Some(Severity::Hint)
+        );
+
+        fs::write(dir.path().join("lints.toml"), "write-only = 'fatal'").unwrap();
+        assert!(Config::read_file_path(&config_file).is_err());
+    }
+}
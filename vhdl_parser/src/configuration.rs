@@ -92,10 +92,22 @@ fn parse_component_configuration_known_instance(
     })
 }
 
+/// Each block configuration within another one is one more level of nesting
 fn parse_block_configuration_known_name(
     stream: &mut TokenStream,
     name: WithPos<Name>,
-    messages: &mut MessageHandler,
+    messages: &mut dyn MessageHandler,
+) -> ParseResult<BlockConfiguration> {
+    stream.nested(|stream| {
+        stream.enter(&name)?;
+        parse_block_configuration_items(stream, name, messages)
+    })
+}
+
+fn parse_block_configuration_items(
+    stream: &mut TokenStream,
+    name: WithPos<Name>,
+    messages: &mut dyn MessageHandler,
 ) -> ParseResult<BlockConfiguration> {
     let block_spec = name;
     // @TODO use clauses
@@ -0,0 +1,672 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Lint rules on conditions and choices whose outcome is known from static values
+//!
+//! - constant-condition: a condition is always true or false, or a comparison of an
+//!   integer object with a static value is always true or false for the range of the object
+//! - unmatched-choice: a choice of a case statement or selected assignment is outside the
+//!   range of the selected object or is a null range
+//!
+//! Conditions of if statements which make a branch unreachable are reported by
+//! unreachable-code instead. The range of an object is given by its subtype, such as
+//! integer range 0 to 255, natural or a locally declared integer type, for the values of
+//! the generics of every instance
+
+use ast::{
+    AssignmentRightHand, Binary, Choice, ConcurrentStatement, Conditionals, Declaration, Direction,
+    DiscreteRange, Expression, Ident, InterfaceDeclaration, IterationScheme,
+    LabeledConcurrentStatement, LabeledSequentialStatement, LibraryUnit, Range, RangeConstraint,
+    Selection, SequentialStatement, SubprogramDeclaration, SubtypeConstraint, SubtypeIndication,
+    TypeDefinition, Unary,
+};
+use library::symbol_key;
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use process_structure::simple_name;
+use source::WithPos;
+use static_value::{
+    declare_constants, hide_interface, static_architectures, static_condition, static_value,
+    Constants, Value,
+};
+use std::collections::BTreeMap;
+
+/// The smallest and largest value by lower case name
+type Ranges = BTreeMap<String, (i64, i64)>;
+
+/// The static values and ranges visible within a region for one instance
+#[derive(Clone)]
+struct Scope {
+    constants: Constants,
+    types: Ranges,
+    ranges: Ranges,
+}
+
+impl Scope {
+    fn new(constants: Constants) -> Scope {
+        let mut types = Ranges::new();
+        types.insert("natural".to_owned(), (0, i64::from(i32::MAX)));
+        types.insert("positive".to_owned(), (1, i64::from(i32::MAX)));
+        Scope {
+            constants,
+            types,
+            ranges: Ranges::new(),
+        }
+    }
+
+    /// The smallest and largest value of a range with static bounds which is not null
+    fn range_bounds(&self, range: &RangeConstraint) -> Option<(i64, i64)> {
+        match (
+            static_value(&range.left_expr.item, &self.constants)?,
+            static_value(&range.right_expr.item, &self.constants)?,
+        ) {
+            (Value::Integer(left), Value::Integer(right)) => {
+                let (low, high) = (left.min(right), left.max(right));
+                let ascending = range.direction == Direction::Ascending;
+                if (ascending && left > right) || (!ascending && left < right) {
+                    None
+                } else {
+                    Some((low, high))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn subtype_range(&self, subtype: &SubtypeIndication) -> Option<(i64, i64)> {
+        match subtype.constraint {
+            Some(SubtypeConstraint::Range(Range::Range(ref range))) => self.range_bounds(range),
+            Some(..) => None,
+            None => {
+                let type_mark = subtype.type_mark.last()?;
+                self.types.get(&symbol_key(&type_mark.item)).cloned()
+            }
+        }
+    }
+
+    fn set_range(&mut self, key: String, range: Option<(i64, i64)>) {
+        match range {
+            Some(range) => self.ranges.insert(key, range),
+            None => self.ranges.remove(&key),
+        };
+    }
+
+    fn declare(&mut self, decl: &[Declaration]) {
+        declare_constants(decl, &mut self.constants);
+        for declaration in decl.iter() {
+            match declaration {
+                Declaration::Object(ref object) => {
+                    let range = self.subtype_range(&object.subtype_indication);
+                    self.set_range(symbol_key(&object.ident.item), range);
+                }
+                Declaration::Type(ref type_decl) => {
+                    let range = match type_decl.def {
+                        TypeDefinition::Integer(Range::Range(ref range)) => {
+                            self.range_bounds(range)
+                        }
+                        TypeDefinition::Subtype(ref subtype) => self.subtype_range(subtype),
+                        _ => None,
+                    };
+                    let key = symbol_key(&type_decl.ident.item);
+                    match range {
+                        Some(range) => self.types.insert(key, range),
+                        None => self.types.remove(&key),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_interface(&mut self, interface: &[InterfaceDeclaration]) {
+        hide_interface(interface, &mut self.constants);
+        for declaration in interface.iter() {
+            if let InterfaceDeclaration::Object(ref object) = declaration {
+                let range = self.subtype_range(&object.subtype_indication);
+                self.set_range(symbol_key(&object.ident.item), range);
+            }
+        }
+    }
+
+    /// Declares the index of a loop or generate with the range of its discrete range
+    fn declare_index(&mut self, index: &Ident, range: &DiscreteRange) {
+        let key = symbol_key(&index.item);
+        self.constants.remove(&key);
+        let range = match range {
+            DiscreteRange::Range(Range::Range(ref range)) => self.range_bounds(range),
+            _ => None,
+        };
+        self.set_range(key, range);
+    }
+
+    /// The range of an expression which is the simple name of an object
+    fn object_range(&self, expr: &Expression) -> Option<(String, (i64, i64))> {
+        let name = simple_name(expr)?;
+        let range = self.ranges.get(&name.to_lowercase())?;
+        Some((name, *range))
+    }
+}
+
+fn declare(scopes: &[Scope], decl: &[Declaration]) -> Vec<Scope> {
+    let mut scopes = scopes.to_vec();
+    for scope in scopes.iter_mut() {
+        scope.declare(decl);
+    }
+    scopes
+}
+
+fn declare_index(scopes: &[Scope], index: &Ident, range: &DiscreteRange) -> Vec<Scope> {
+    let mut scopes = scopes.to_vec();
+    for scope in scopes.iter_mut() {
+        scope.declare_index(index, range);
+    }
+    scopes
+}
+
+/// The value of the comparison of an object with the range to the value, when known
+fn compare(op: Binary, (low, high): (i64, i64), value: i64) -> Option<bool> {
+    match op {
+        Binary::LT if high < value => Some(true),
+        Binary::LT if low >= value => Some(false),
+        Binary::LTE if high <= value => Some(true),
+        Binary::LTE if low > value => Some(false),
+        Binary::GT if low > value => Some(true),
+        Binary::GT if high <= value => Some(false),
+        Binary::GTE if low >= value => Some(true),
+        Binary::GTE if high < value => Some(false),
+        Binary::EQ | Binary::NE => {
+            let equal = if value < low || value > high {
+                Some(false)
+            } else if low == value && high == value {
+                Some(true)
+            } else {
+                None
+            };
+            equal.map(|equal| (op == Binary::EQ) == equal)
+        }
+        _ => None,
+    }
+}
+
+/// The operator with the operands swapped such that 5 < x is x > 5
+fn swapped(op: Binary) -> Binary {
+    match op {
+        Binary::LT => Binary::GT,
+        Binary::LTE => Binary::GTE,
+        Binary::GT => Binary::LT,
+        Binary::GTE => Binary::LTE,
+        op => op,
+    }
+}
+
+/// The value of a comparison of an object with a static value which is the same for the
+/// range of the object in every scope, and the name of the object
+fn range_comparison(expr: &Expression, scopes: &[Scope]) -> Option<(bool, String)> {
+    let (op, left, right) = match expr {
+        Expression::Binary(op, ref left, ref right) => (*op, &left.item, &right.item),
+        _ => return None,
+    };
+    let mut result = None;
+    for scope in scopes.iter() {
+        let (name, range, op, value) = match (scope.object_range(left), scope.object_range(right)) {
+            (Some((name, range)), None) => (name, range, op, right),
+            (None, Some((name, range))) => (name, range, swapped(op), left),
+            _ => return None,
+        };
+        let value = match static_value(value, &scope.constants)? {
+            Value::Integer(value) => value,
+            _ => return None,
+        };
+        let outcome = compare(op, range, value)?;
+        if result.as_ref().is_some_and(|(other, _)| *other != outcome) {
+            return None;
+        }
+        result = Some((outcome, name));
+    }
+    result
+}
+
+#[derive(Default)]
+struct Walker {
+    conditions: Vec<Message>,
+    choices: Vec<Message>,
+}
+
+impl Walker {
+    /// Reports a condition which is always true or false, or otherwise the comparisons
+    /// within it which are
+    fn condition(
+        &mut self,
+        condition: &WithPos<Expression>,
+        scopes: &[Scope],
+        report_static: bool,
+    ) {
+        if let Some(value) = static_condition(&condition.item, &constants(scopes)) {
+            if report_static {
+                self.conditions.push(warning(
+                    condition,
+                    &format!("The condition is always {}", value),
+                ));
+            }
+            return;
+        }
+        self.comparisons(condition, scopes);
+    }
+
+    fn comparisons(&mut self, expr: &WithPos<Expression>, scopes: &[Scope]) {
+        match expr.item {
+            Expression::Binary(op, ref left, ref right) => match op {
+                Binary::And
+                | Binary::Or
+                | Binary::Nand
+                | Binary::Nor
+                | Binary::Xor
+                | Binary::Xnor => {
+                    self.comparisons(left, scopes);
+                    self.comparisons(right, scopes);
+                }
+                _ => {
+                    if let Some((value, name)) = range_comparison(&expr.item, scopes) {
+                        self.conditions.push(warning(
+                            expr,
+                            &format!(
+                                "The comparison is always {} for the range of '{}'",
+                                value, name
+                            ),
+                        ));
+                    }
+                }
+            },
+            Expression::Unary(Unary::Not, ref operand) => self.comparisons(operand, scopes),
+            _ => {}
+        }
+    }
+
+    fn if_conditions<T, F>(&mut self, conditionals: &Conditionals<T>, scopes: &[Scope], mut walk: F)
+    where
+        F: FnMut(&mut Walker, &T),
+    {
+        let count = conditionals.conditionals.len();
+        for (i, conditional) in conditionals.conditionals.iter().enumerate() {
+            // Only the last condition when there is no else is not reported by unreachable-code
+            let is_last = i + 1 == count && conditionals.else_item.is_none();
+            let value = static_condition(&conditional.condition.item, &constants(scopes));
+            if value == Some(true) && is_last {
+                self.condition(&conditional.condition, scopes, true);
+            } else if value.is_none() {
+                self.comparisons(&conditional.condition, scopes);
+            }
+            walk(self, &conditional.item);
+        }
+        if let Some(ref item) = conditionals.else_item {
+            walk(self, item);
+        }
+    }
+
+    fn choices<T>(&mut self, selection: &Selection<T>, scopes: &[Scope]) {
+        let ranges: Vec<(String, (i64, i64))> = match scopes
+            .iter()
+            .map(|scope| scope.object_range(&selection.expression.item))
+            .collect()
+        {
+            Some(ranges) => ranges,
+            None => return,
+        };
+        let name = match ranges.first() {
+            Some((name, _)) => name.clone(),
+            None => return,
+        };
+        for alternative in selection.alternatives.iter() {
+            for choice in alternative.choices.iter() {
+                match choice {
+                    Choice::Expression(ref expr) => {
+                        let outside =
+                            scopes.iter().zip(ranges.iter()).all(|(scope, (_, range))| {
+                                match static_value(&expr.item, &scope.constants) {
+                                    Some(Value::Integer(value)) => {
+                                        value < range.0 || value > range.1
+                                    }
+                                    _ => false,
+                                }
+                            });
+                        if outside {
+                            self.choices.push(warning(
+                                expr,
+                                &format!("The choice is never matched for the range of '{}'", name),
+                            ));
+                        }
+                    }
+                    Choice::DiscreteRange(DiscreteRange::Range(Range::Range(ref range))) => {
+                        let pos = range.left_expr.pos.combine(&range.right_expr.pos);
+                        let bounds: Vec<Option<(i64, i64)>> = scopes
+                            .iter()
+                            .map(|scope| scope.range_bounds(range))
+                            .collect();
+                        let is_null = scopes.iter().zip(bounds.iter()).all(|(scope, bounds)| {
+                            bounds.is_none()
+                                && static_value(&range.left_expr.item, &scope.constants).is_some()
+                                && static_value(&range.right_expr.item, &scope.constants).is_some()
+                        });
+                        let outside =
+                            bounds
+                                .iter()
+                                .zip(ranges.iter())
+                                .all(|(bounds, (_, range))| {
+                                    bounds
+                                        .is_some_and(|(low, high)| high < range.0 || low > range.1)
+                                });
+                        if is_null {
+                            self.choices.push(warning(
+                                &pos,
+                                "The choice range is null so it is never matched",
+                            ));
+                        } else if outside {
+                            self.choices.push(warning(
+                                &pos,
+                                &format!("The choice is never matched for the range of '{}'", name),
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn rhs<T>(&mut self, rhs: &AssignmentRightHand<T>, scopes: &[Scope]) {
+        match rhs {
+            AssignmentRightHand::Simple(..) => {}
+            AssignmentRightHand::Conditional(ref conditionals) => {
+                for conditional in conditionals.conditionals.iter() {
+                    self.condition(&conditional.condition, scopes, true);
+                }
+            }
+            AssignmentRightHand::Selected(ref selection) => self.choices(selection, scopes),
+        }
+    }
+
+    fn sequential(&mut self, statements: &[LabeledSequentialStatement], scopes: &[Scope]) {
+        for statement in statements.iter() {
+            match statement.statement {
+                SequentialStatement::If(ref ifs) => {
+                    self.if_conditions(ifs, scopes, |walker, statements| {
+                        walker.sequential(statements, scopes)
+                    });
+                }
+                SequentialStatement::Case(ref case) => {
+                    self.choices(case, scopes);
+                    for alternative in case.alternatives.iter() {
+                        self.sequential(&alternative.item, scopes);
+                    }
+                }
+                SequentialStatement::Loop(ref lp) => match lp.iteration_scheme {
+                    Some(IterationScheme::For(ref index, ref range)) => {
+                        self.sequential(&lp.statements, &declare_index(scopes, index, range));
+                    }
+                    Some(IterationScheme::While(ref condition)) => {
+                        // A while loop on true is intended to loop until exit
+                        if static_condition(&condition.item, &constants(scopes)) != Some(true) {
+                            self.condition(condition, scopes, true);
+                        }
+                        self.sequential(&lp.statements, scopes);
+                    }
+                    None => self.sequential(&lp.statements, scopes),
+                },
+                SequentialStatement::Exit(ref exit) => {
+                    if let Some(ref condition) = exit.condition {
+                        self.condition(condition, scopes, true);
+                    }
+                }
+                SequentialStatement::Next(ref next) => {
+                    if let Some(ref condition) = next.condition {
+                        self.condition(condition, scopes, true);
+                    }
+                }
+                SequentialStatement::SignalAssignment(ref assignment) => {
+                    self.rhs(&assignment.rhs, scopes);
+                }
+                SequentialStatement::VariableAssignment(ref assignment) => {
+                    self.rhs(&assignment.rhs, scopes);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Walks the bodies of the subprograms of the declarations
+    fn declarations(&mut self, decl: &[Declaration], scopes: &[Scope]) {
+        for declaration in decl.iter() {
+            if let Declaration::SubprogramBody(ref body) = declaration {
+                let parameters = match body.specification {
+                    SubprogramDeclaration::Procedure(ref procedure) => &procedure.parameter_list,
+                    SubprogramDeclaration::Function(ref function) => &function.parameter_list,
+                };
+                let mut scopes = scopes.to_vec();
+                for scope in scopes.iter_mut() {
+                    scope.declare_interface(parameters);
+                    scope.declare(&body.declarations);
+                }
+                self.declarations(&body.declarations, &scopes);
+                self.sequential(&body.statements, &scopes);
+            }
+        }
+    }
+
+    fn concurrent(&mut self, statements: &[LabeledConcurrentStatement], scopes: &[Scope]) {
+        for statement in statements.iter() {
+            match statement.statement {
+                ConcurrentStatement::Process(ref process) => {
+                    let scopes = declare(scopes, &process.decl);
+                    self.declarations(&process.decl, &scopes);
+                    self.sequential(&process.statements, &scopes);
+                }
+                ConcurrentStatement::Block(ref block) => {
+                    let scopes = declare(scopes, &block.decl);
+                    self.declarations(&block.decl, &scopes);
+                    self.concurrent(&block.statements, &scopes);
+                }
+                ConcurrentStatement::Assignment(ref assignment) => {
+                    self.rhs(&assignment.rhs, scopes);
+                }
+                ConcurrentStatement::IfGenerate(ref gen) => {
+                    self.if_conditions(gen, scopes, |walker, body| {
+                        let decl = body.decl.as_deref().unwrap_or(&[]);
+                        let scopes = declare(scopes, decl);
+                        walker.declarations(decl, &scopes);
+                        walker.concurrent(&body.statements, &scopes);
+                    });
+                }
+                ConcurrentStatement::ForGenerate(ref gen) => {
+                    let scopes = declare_index(scopes, &gen.index_name, &gen.discrete_range);
+                    let decl = gen.body.decl.as_deref().unwrap_or(&[]);
+                    let scopes = declare(&scopes, decl);
+                    self.declarations(decl, &scopes);
+                    self.concurrent(&gen.body.statements, &scopes);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn constants(scopes: &[Scope]) -> Vec<Constants> {
+    scopes.iter().map(|scope| scope.constants.clone()).collect()
+}
+
+/// The findings of the conditions and the choices of the project
+fn walk(context: &LintContext) -> Walker {
+    let files = context.project.files();
+    let mut walker = Walker::default();
+    for architecture in static_architectures(&files) {
+        let scopes: Vec<Scope> = architecture
+            .instances
+            .iter()
+            .map(|constants| {
+                let mut scope = Scope::new(constants.clone());
+                scope.declare_interface(architecture.ports);
+                scope.declare(architecture.decl);
+                scope
+            })
+            .collect();
+        walker.declarations(architecture.decl, &scopes);
+        walker.concurrent(architecture.statements, &scopes);
+    }
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            if let LibraryUnit::PackageBody { ref decl, .. } = design_unit.library_unit {
+                let mut scope = Scope::new(Constants::new());
+                scope.declare(decl);
+                walker.declarations(decl, &[scope]);
+            }
+        }
+    }
+    walker
+}
+
+pub struct ConstantCondition;
+
+impl Rule for ConstantCondition {
+    fn id(&self) -> &str {
+        "constant-condition"
+    }
+
+    fn description(&self) -> &str {
+        "A condition or comparison is always true or always false"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        Ok(walk(context).conditions)
+    }
+}
+
+pub struct UnmatchedChoice;
+
+impl Rule for UnmatchedChoice {
+    fn id(&self) -> &str {
+        "unmatched-choice"
+    }
+
+    fn description(&self) -> &str {
+        "A choice is never matched by the selected value"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        Ok(walk(context).choices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn constant_conditions_and_unmatched_choices() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+  generic (debug : boolean := false);
+  port (count : in integer range 0 to 255; sel : in natural);
+end entity;
+
+architecture rtl of ent is
+  type nibble is range 0 to 15;
+  signal low : nibble;
+  signal q, r : bit;
+begin
+  q <= '1' when debug else '0';
+  r <= '1' when count < 256 and sel > 2 else '0';
+
+  process (count, low, sel)
+  begin
+    if sel >= 0 then
+      report \"always\";
+    end if;
+    case count is
+      when 0 to 255 => null;
+      when 300 => null;
+      when 8 downto 9 => null;
+      when others => null;
+    end case;
+    for i in 0 to 7 loop
+      exit when i = 9;
+    end loop;
+    with low select q <=
+      '1' when 16,
+      '0' when others;
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let ids = ["constant-condition", "unmatched-choice"];
+        let findings: Vec<(String, String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| ids.contains(&finding.code.as_ref().unwrap().as_str()))
+            .map(|finding| (finding.code.unwrap(), finding.message, finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    string("constant-condition"),
+                    string("The condition is always false"),
+                    code.find("debug else").unwrap()
+                ),
+                (
+                    string("constant-condition"),
+                    string("The comparison is always true for the range of 'count'"),
+                    code.find("count < 256").unwrap()
+                ),
+                (
+                    string("constant-condition"),
+                    string("The comparison is always true for the range of 'sel'"),
+                    code.find("sel >= 0").unwrap()
+                ),
+                (
+                    string("unmatched-choice"),
+                    string("The choice is never matched for the range of 'count'"),
+                    code.find("300").unwrap()
+                ),
+                (
+                    string("unmatched-choice"),
+                    string("The choice range is null so it is never matched"),
+                    code.find("8 downto 9").unwrap()
+                ),
+                (
+                    string("constant-condition"),
+                    string("The comparison is always false for the range of 'i'"),
+                    code.find("i = 9").unwrap()
+                ),
+                (
+                    string("unmatched-choice"),
+                    string("The choice is never matched for the range of 'low'"),
+                    code.find("16,").unwrap()
+                ),
+            ]
+        );
+    }
+}
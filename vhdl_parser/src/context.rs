@@ -250,6 +250,11 @@ end context ident2;
             vec![warning(
                 &util.first_substr_pos("ident2"),
                 "End identifier mismatch, expected ident"
+            )
+            .with_code("end-identifier-mismatch")
+            .with_fix(
+                "Replace with ident",
+                vec![(util.first_substr_pos("ident2"), "ident".to_owned())]
             )]
         );
         assert_eq!(
@@ -0,0 +1,218 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Conversion between entity declarations and matching component declarations
+
+use ast::{ComponentDeclaration, Declaration, InterfaceDeclaration, LibraryUnit};
+use documentation::declaration_text;
+use formatter::Indentation;
+use name_resolution::{interface_entity, NamedEntityKind};
+use project::SourceFile;
+
+/// The interface list where identifiers declared together share their declaration
+fn interface_list(
+    keyword: &str,
+    decls: &[InterfaceDeclaration],
+    kind: NamedEntityKind,
+    indentation: &Indentation,
+) -> String {
+    let mut texts: Vec<String> = Vec::new();
+    for decl in decls {
+        let entity = interface_entity(decl, kind);
+        let text = declaration_text(&entity)
+            .map(|text| text.text)
+            .unwrap_or(entity.designator);
+        if texts.last() != Some(&text) {
+            texts.push(text);
+        }
+    }
+    if texts.is_empty() {
+        return String::new();
+    }
+
+    let element_indent = indentation.levels(2);
+    let elements: Vec<String> = texts
+        .iter()
+        .map(|text| {
+            let lines: Vec<String> = text
+                .lines()
+                .map(|line| format!("{}{}", element_indent, line))
+                .collect();
+            lines.join("\n")
+        })
+        .collect();
+    format!(
+        "{indent}{keyword} (\n{elements}\n{indent});\n",
+        indent = indentation.levels(1),
+        keyword = keyword,
+        elements = elements.join(";\n")
+    )
+}
+
+fn interface_declaration(
+    header: &str,
+    generics: &[InterfaceDeclaration],
+    ports: &[InterfaceDeclaration],
+    end: &str,
+    indentation: &Indentation,
+) -> String {
+    let mut text = format!("{}\n", header);
+    text.push_str(&interface_list(
+        "generic",
+        generics,
+        NamedEntityKind::Generic,
+        indentation,
+    ));
+    text.push_str(&interface_list(
+        "port",
+        ports,
+        NamedEntityKind::Port,
+        indentation,
+    ));
+    text.push_str(end);
+    text
+}
+
+/// The component declaration with the generics and ports of an entity declaration
+pub fn entity_to_component(
+    library_unit: &LibraryUnit,
+    indentation: &Indentation,
+) -> Option<String> {
+    match library_unit {
+        LibraryUnit::EntityDeclaration {
+            ref ident,
+            ref generic_clause,
+            ref port_clause,
+        } => Some(interface_declaration(
+            &format!("component {} is", ident.item.name_utf8()),
+            generic_clause
+                .as_ref()
+                .map(|clause| clause.generic_list.as_slice())
+                .unwrap_or(&[]),
+            port_clause
+                .as_ref()
+                .map(|clause| clause.port_list.as_slice())
+                .unwrap_or(&[]),
+            "end component;",
+            indentation,
+        )),
+        _ => None,
+    }
+}
+
+/// The entity declaration with the generics and ports of a component declaration
+pub fn component_to_entity(component: &ComponentDeclaration, indentation: &Indentation) -> String {
+    interface_declaration(
+        &format!("entity {} is", component.ident.item.name_utf8()),
+        &component.generic_list,
+        &component.port_list,
+        "end entity;",
+        indentation,
+    )
+}
+
+fn find_component<'a>(decls: &'a [Declaration], name: &str) -> Option<&'a ComponentDeclaration> {
+    decls.iter().find_map(|decl| match decl {
+        Declaration::Component(ref component)
+            if component.ident.item.name_utf8().eq_ignore_ascii_case(name) =>
+        {
+            Some(component)
+        }
+        _ => None,
+    })
+}
+
+/// The component of the entity with the name, or otherwise the entity of the component
+/// with the name declared in a package or architecture
+pub fn convert(files: &[&SourceFile], name: &str, indentation: &Indentation) -> Option<String> {
+    let library_units = || {
+        files
+            .iter()
+            .flat_map(|file| file.design_file.design_units.iter())
+            .map(|design_unit| &design_unit.library_unit)
+    };
+    for library_unit in library_units() {
+        if let LibraryUnit::EntityDeclaration { ref ident, .. } = library_unit {
+            if ident.item.name_utf8().eq_ignore_ascii_case(name) {
+                return entity_to_component(library_unit, indentation);
+            }
+        }
+    }
+    library_units()
+        .find_map(|library_unit| match library_unit {
+            LibraryUnit::PackageDeclaration { ref decl, .. }
+            | LibraryUnit::ArchitectureBody { ref decl, .. } => find_component(decl, name),
+            _ => None,
+        })
+        .map(|component| component_to_entity(component, indentation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn spaces(size: usize) -> Indentation {
+        Indentation { size, tabs: false }
+    }
+
+    #[test]
+    fn entity_to_component_and_back() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+    generic (width : natural := 8);
+    port (clk : in bit; -- The clock
+          data, valid : out bit_vector(width - 1 downto 0));
+end entity;
+
+entity empty is
+end entity;
+
+package pkg is
+  component comp
+    port (a : in bit);
+  end component;
+end package;",
+        );
+        let files = project.project.files();
+
+        assert_eq!(
+            convert(&files, "ENT", &spaces(2)),
+            Some(
+                "component ent is
+  generic (
+    width : natural := 8
+  );
+  port (
+    clk : in bit;
+    data, valid : out bit_vector(width - 1 downto 0)
+  );
+end component;"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            convert(&files, "empty", &spaces(2)),
+            Some("component empty is\nend component;".to_owned())
+        );
+        assert_eq!(
+            convert(
+                &files,
+                "comp",
+                &Indentation {
+                    size: 4,
+                    tabs: true
+                }
+            ),
+            Some("entity comp is\n\tport (\n\t\ta : in bit\n\t);\nend entity;".to_owned())
+        );
+        assert_eq!(convert(&files, "missing", &spaces(2)), None);
+    }
+}
@@ -0,0 +1,352 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The kinds of AST nodes parsed from a corpus and the syntax errors it produced
+//!
+//! Every variant of every AST enum such as ConcurrentStatement::Process is counted by
+//! walking the design files with a serializer, constructs which are not supported
+//! result in syntax errors instead
+
+use message::{Message, Severity};
+use project::SourceFile;
+use serde::ser::{self, Serialize};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+/// The node kinds of a corpus and the syntax errors of its files
+#[derive(PartialEq, Debug, Default)]
+pub struct Coverage {
+    /// The number of nodes of each kind such as Declaration::Signal
+    pub kinds: BTreeMap<String, usize>,
+    pub syntax_errors: Vec<Message>,
+}
+
+impl Coverage {
+    pub fn count(&self, kind: &str) -> usize {
+        self.kinds.get(kind).cloned().unwrap_or(0)
+    }
+}
+
+/// Never produced since counting cannot fail
+#[derive(Debug)]
+struct CountError(String);
+
+impl fmt::Display for CountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl error::Error for CountError {}
+
+impl ser::Error for CountError {
+    fn custom<T: fmt::Display>(msg: T) -> CountError {
+        CountError(msg.to_string())
+    }
+}
+
+/// Counts the enum variants of the serialized value
+#[derive(Default)]
+struct KindCounter {
+    kinds: BTreeMap<String, usize>,
+}
+
+impl KindCounter {
+    fn add(&mut self, name: &str, variant: &str) {
+        *self
+            .kinds
+            .entry(format!("{}::{}", name, variant))
+            .or_insert(0) += 1;
+    }
+}
+
+type CountResult = Result<(), CountError>;
+
+impl ser::Serializer for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, _: bool) -> CountResult {
+        Ok(())
+    }
+    fn serialize_i8(self, _: i8) -> CountResult {
+        Ok(())
+    }
+    fn serialize_i16(self, _: i16) -> CountResult {
+        Ok(())
+    }
+    fn serialize_i32(self, _: i32) -> CountResult {
+        Ok(())
+    }
+    fn serialize_i64(self, _: i64) -> CountResult {
+        Ok(())
+    }
+    fn serialize_u8(self, _: u8) -> CountResult {
+        Ok(())
+    }
+    fn serialize_u16(self, _: u16) -> CountResult {
+        Ok(())
+    }
+    fn serialize_u32(self, _: u32) -> CountResult {
+        Ok(())
+    }
+    fn serialize_u64(self, _: u64) -> CountResult {
+        Ok(())
+    }
+    fn serialize_f32(self, _: f32) -> CountResult {
+        Ok(())
+    }
+    fn serialize_f64(self, _: f64) -> CountResult {
+        Ok(())
+    }
+    fn serialize_char(self, _: char) -> CountResult {
+        Ok(())
+    }
+    fn serialize_str(self, _: &str) -> CountResult {
+        Ok(())
+    }
+    fn serialize_bytes(self, _: &[u8]) -> CountResult {
+        Ok(())
+    }
+    fn serialize_none(self) -> CountResult {
+        Ok(())
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> CountResult {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> CountResult {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> CountResult {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> CountResult {
+        self.add(name, variant);
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> CountResult {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> CountResult {
+        self.add(name, variant);
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, CountError> {
+        Ok(self)
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self, CountError> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, CountError> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self, CountError> {
+        self.add(name, variant);
+        Ok(self)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, CountError> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, CountError> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self, CountError> {
+        self.add(name, variant);
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> CountResult {
+        key.serialize(&mut **self)
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut KindCounter {
+    type Ok = ();
+    type Error = CountError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> CountResult {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> CountResult {
+        Ok(())
+    }
+}
+
+/// The node kinds of the design files and their syntax errors
+pub fn coverage(files: &[&SourceFile]) -> Coverage {
+    let mut counter = KindCounter::default();
+    let mut syntax_errors = Vec::new();
+    for file in files {
+        // Counting never fails
+        let _ = file.design_file.serialize(&mut counter);
+        syntax_errors.extend(
+            file.messages
+                .iter()
+                .filter(|message| message.severity == Severity::Error)
+                .cloned(),
+        );
+    }
+    Coverage {
+        kinds: counter.kinds,
+        syntax_errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn kinds_of_nodes_and_syntax_errors() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal a, b : bit;
+begin
+  a <= b;
+  process
+  begin
+    wait;
+  end process;
+end architecture;",
+        );
+        project.add_file("lib", "bad.vhd", "entity bad is");
+
+        let files = project.project.files();
+        let coverage = coverage(&files);
+        assert_eq!(coverage.count("LibraryUnit::EntityDeclaration"), 1);
+        assert_eq!(coverage.count("LibraryUnit::ArchitectureBody"), 1);
+        // Identifiers declared together are separate declarations
+        assert_eq!(coverage.count("Declaration::Object"), 2);
+        assert_eq!(coverage.count("ConcurrentStatement::Assignment"), 1);
+        assert_eq!(coverage.count("ConcurrentStatement::Process"), 1);
+        assert_eq!(coverage.count("SequentialStatement::Wait"), 1);
+        assert_eq!(coverage.count("ConcurrentStatement::Block"), 0);
+        assert_eq!(coverage.syntax_errors.len(), 1);
+        assert_eq!(
+            coverage.syntax_errors[0].pos.source.file_name(),
+            Some(files[0].file_name())
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Entities, packages and subprograms which are never used by the rest of a design
+
+use call_hierarchy::calls;
+use dependency_graph::DependencyGraph;
+use library::{UnitId, UnitKey};
+use name_resolution::{NameResolution, NamedEntity, NamedEntityKind};
+use project::Project;
+use std::collections::BTreeSet;
+
+/// The declarations which are never used
+#[derive(PartialEq, Debug)]
+pub struct DeadCode<'a> {
+    /// Entities which are not instantiated below the top, or anywhere without a top
+    pub entities: Vec<&'a NamedEntity>,
+    /// Packages which no design unit other than their body depends on
+    pub packages: Vec<&'a NamedEntity>,
+    /// Functions and procedures which are never called
+    pub subprograms: Vec<&'a NamedEntity>,
+}
+
+/// The unit identifier of a primary unit declaration
+fn unit_id(project: &Project, entity: &NamedEntity) -> Option<UnitId> {
+    let file = project.get_file(entity.pos.source.file_name()?)?;
+    Some(UnitId::primary(&file.library_name, &entity.designator))
+}
+
+/// The entities instantiated directly or indirectly below the top, instances of
+/// configurations include the entities the configuration depends on
+fn instantiated(graph: &DependencyGraph, top: Option<&UnitId>) -> BTreeSet<UnitId> {
+    let hierarchy = graph.instance_hierarchy();
+    let mut pending: Vec<UnitId> = match top {
+        Some(top) => vec![top.clone()],
+        None => hierarchy.values().flatten().cloned().collect(),
+    };
+    let mut reached = BTreeSet::new();
+    while let Some(unit_id) = pending.pop() {
+        if !reached.insert(unit_id.clone()) {
+            continue;
+        }
+        if let Some(instances) = hierarchy.get(&unit_id) {
+            pending.extend(instances.iter().cloned());
+        }
+        if let Some(node) = graph.get(&unit_id) {
+            pending.extend(
+                node.dependencies
+                    .iter()
+                    .filter(|dep| dep.is_primary())
+                    .cloned(),
+            );
+        }
+    }
+    reached
+}
+
+/// True if a design unit other than the body of the package depends on it
+fn is_used_package(graph: &DependencyGraph, unit_id: &UnitId) -> bool {
+    graph
+        .dependents(unit_id)
+        .iter()
+        .any(|dependent| match dependent.key {
+            UnitKey::PackageBody(ref name) => {
+                dependent.library_name != unit_id.library_name || name != unit_id.primary_name()
+            }
+            _ => true,
+        })
+}
+
+/// The declarations of the kinds which are unused, sorted by location
+fn unused<'a>(
+    resolution: &'a NameResolution,
+    kinds: &[NamedEntityKind],
+    is_unused: impl Fn(&NamedEntity) -> bool,
+) -> Vec<&'a NamedEntity> {
+    let mut entities: Vec<&NamedEntity> = resolution
+        .declarations()
+        .iter()
+        .filter(|entity| kinds.contains(&entity.kind) && is_unused(entity))
+        .collect();
+    entities.sort_by_key(|entity| (entity.pos.source.file_name(), entity.pos.start));
+    entities
+}
+
+/// The entities, packages and subprograms of the project which are never used
+/// Entities are checked against the instance hierarchy below the top when given
+pub fn dead_code<'a>(
+    project: &Project,
+    resolution: &'a NameResolution,
+    top: Option<&UnitId>,
+) -> DeadCode<'a> {
+    let graph = project.dependency_graph();
+    let instantiated = instantiated(&graph, top);
+    let called: Vec<NamedEntity> = calls(resolution)
+        .into_iter()
+        .map(|call| call.callee)
+        .collect();
+    DeadCode {
+        entities: unused(resolution, &[NamedEntityKind::Entity], |entity| {
+            unit_id(project, entity).is_some_and(|id| !instantiated.contains(&id))
+        }),
+        packages: unused(resolution, &[NamedEntityKind::Package], |entity| {
+            unit_id(project, entity).is_some_and(|id| !is_used_package(&graph, &id))
+        }),
+        subprograms: unused(
+            resolution,
+            &[NamedEntityKind::Function, NamedEntityKind::Procedure],
+            |entity| !called.contains(entity),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn designators(entities: &[&NamedEntity]) -> Vec<String> {
+        entities
+            .iter()
+            .map(|entity| entity.designator.clone())
+            .collect()
+    }
+
+    #[test]
+    fn unused_entities_packages_and_subprograms() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  function used(value : natural) return natural;
+  procedure unused;
+end package;
+
+package body pkg is
+  function used(value : natural) return natural is
+  begin
+    return value;
+  end function;
+
+  procedure unused is
+  begin
+  end procedure;
+end package body;
+
+package legacy is
+end package;
+
+package body legacy is
+end package body;",
+        );
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+use work.pkg.all;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+  function local return natural is
+  begin
+    return used(0);
+  end function;
+begin
+  inst : entity work.child;
+end architecture;
+
+entity child is
+end entity;
+
+architecture rtl of child is
+begin
+end architecture;
+
+entity orphan is
+end entity;
+
+architecture rtl of orphan is
+begin
+  inst : entity work.child;
+end architecture;",
+        );
+
+        let resolution = project.project.resolve();
+        let unused = dead_code(&project.project, &resolution, None);
+        assert_eq!(designators(&unused.entities), vec!["top", "orphan"]);
+        assert_eq!(designators(&unused.packages), vec!["legacy"]);
+        assert_eq!(designators(&unused.subprograms), vec!["unused", "local"]);
+
+        let top = UnitId::primary("lib", "top");
+        let unused = dead_code(&project.project, &resolution, Some(&top));
+        assert_eq!(designators(&unused.entities), vec!["orphan"]);
+    }
+}
@@ -13,7 +13,7 @@ use message::{MessageHandler, ParseResult};
 use names::{parse_association_list, parse_selected_name};
 use object_declaration::{parse_file_declaration, parse_object_declaration};
 use subprogram::parse_subprogram;
-use tokenizer::{Kind::*, Token};
+use tokenizer::{Kind, Kind::*, Token};
 use tokenstream::TokenStream;
 use type_declaration::parse_type_declaration;
 
@@ -42,39 +42,61 @@ pub fn parse_package_instantiation(stream: &mut TokenStream) -> ParseResult<Pack
 }
 
 pub fn is_declarative_part(stream: &mut TokenStream, begin_is_end: bool) -> ParseResult<bool> {
-    Ok(check_declarative_part(&stream.peek_expect()?, begin_is_end).is_ok())
+    Ok(is_declarative_kind(
+        stream.peek_expect()?.kind,
+        begin_is_end,
+    ))
 }
 
-fn check_declarative_part(token: &Token, begin_is_end: bool) -> ParseResult<()> {
-    match token.kind {
+fn end_kind(begin_is_end: bool) -> Kind {
+    if begin_is_end {
+        Begin
+    } else {
+        End
+    }
+}
+
+/// Without creating an error as every generate body is checked for a declarative part
+fn is_declarative_kind(kind: Kind, begin_is_end: bool) -> bool {
+    match kind {
         Use | Type | Subtype | Shared | Constant | Signal | Variable | File | Component
-        | Attribute | Alias | Impure | Function | Procedure | Package => Ok(()),
-        kind => {
-            let end_kind = {
-                if begin_is_end {
-                    Begin
-                } else {
-                    End
-                }
-            };
-            let decl_kinds = [
-                Use, Type, Subtype, Shared, Constant, Signal, Variable, File, Component, Attribute,
-                Alias, Impure, Function, Procedure, Package, end_kind,
-            ];
-
-            if kind == end_kind {
-                Ok(())
-            } else {
-                Err(token.kinds_error(&decl_kinds))
-            }
-        }
+        | Attribute | Alias | Impure | Function | Procedure | Package => true,
+        kind => kind == end_kind(begin_is_end),
     }
 }
 
+fn check_declarative_part(token: &Token, begin_is_end: bool) -> ParseResult<()> {
+    if is_declarative_kind(token.kind, begin_is_end) {
+        Ok(())
+    } else {
+        let end_kind = end_kind(begin_is_end);
+        let decl_kinds = [
+            Use, Type, Subtype, Shared, Constant, Signal, Variable, File, Component, Attribute,
+            Alias, Impure, Function, Procedure, Package, end_kind,
+        ];
+        Err(token.kinds_error(&decl_kinds))
+    }
+}
+
+/// Each declarative part such as of a subprogram body within another one is one more
+/// level of nesting
 pub fn parse_declarative_part(
     stream: &mut TokenStream,
     messages: &mut MessageHandler,
     begin_is_end: bool,
+) -> ParseResult<Vec<Declaration>> {
+    stream.nested(|stream| {
+        if let Some(token) = stream.peek()? {
+            stream.enter(&token)?;
+        }
+        parse_declarations(stream, messages, begin_is_end)
+    })
+}
+
+fn parse_declarations(
+    stream: &mut TokenStream,
+    messages: &mut dyn MessageHandler,
+    begin_is_end: bool,
 ) -> ParseResult<Vec<Declaration>> {
     let mut declarations: Vec<Declaration> = Vec::new();
 
@@ -0,0 +1,946 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use ast::{
+    BlockConfiguration, ConcurrentStatement, ConfigurationDeclarativeItem, ConfigurationItem,
+    ContextItem, Declaration, EntityAspect, InstantiatedUnit, LabeledConcurrentStatement,
+    LibraryUnit, SelectedName,
+};
+use library::{selected_name_parts, symbol_key, DesignRoot, UnitId};
+use message::{error, Message};
+use project::SourceFile;
+use source::SrcPos;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A design unit within the dependency graph
+pub struct UnitNode {
+    pub pos: SrcPos,
+    pub file_name: String,
+    pub dependencies: BTreeSet<UnitId>,
+    /// The entities and configurations instantiated by an architecture
+    pub instances: BTreeSet<UnitId>,
+}
+
+/// The relation shown by a graph of the project
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum GraphKind {
+    /// Design units and their dependencies
+    Units,
+    /// Files and the files containing their dependencies
+    Files,
+    /// Entities and the entities or configurations instantiated by their architectures
+    Instances,
+}
+
+/// The dependencies between design units of a project
+/// LRM 13.5 A design unit depends on the units it references via context clauses,
+/// its primary unit as well as the entities and configurations it instantiates
+pub struct DependencyGraph {
+    units: BTreeMap<UnitId, UnitNode>,
+}
+
+/// The result of ordering design units such that every unit comes after its dependencies
+pub struct CompileOrder<T> {
+    /// All units in a legal order, units within cycles are placed last
+    pub order: Vec<T>,
+    /// Circular dependencies, each cycle lists the units in dependency order
+    pub cycles: Vec<Vec<T>>,
+}
+
+/// Kahn's algorithm where ties are broken by key order to get a deterministic result
+fn topological_sort<T: Ord + Clone>(dependencies: &BTreeMap<T, BTreeSet<T>>) -> CompileOrder<T> {
+    let mut num_unresolved: BTreeMap<&T, usize> = BTreeMap::new();
+    let mut dependents: BTreeMap<&T, Vec<&T>> = BTreeMap::new();
+    let mut ready = BTreeSet::new();
+
+    for (node, deps) in dependencies.iter() {
+        let deps: Vec<&T> = deps
+            .iter()
+            .filter(|dep| *dep != node && dependencies.contains_key(dep))
+            .collect();
+        if deps.is_empty() {
+            ready.insert(node);
+        }
+        num_unresolved.insert(node, deps.len());
+        for dep in deps {
+            dependents.entry(dep).or_default().push(node);
+        }
+    }
+
+    let mut order = Vec::with_capacity(dependencies.len());
+    while let Some(node) = ready.iter().next().cloned() {
+        ready.remove(node);
+        order.push(node.clone());
+        num_unresolved.remove(node);
+
+        if let Some(nodes) = dependents.get(node) {
+            for dependent in nodes {
+                if let Some(count) = num_unresolved.get_mut(dependent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(dependent);
+                    }
+                }
+            }
+        }
+    }
+
+    // Every remaining node has at least one remaining dependency, thus following
+    // dependencies must eventually revisit a node on the path
+    let mut cycles = Vec::new();
+    let mut in_cycle = BTreeSet::new();
+    for node in num_unresolved.keys() {
+        if in_cycle.contains(node) {
+            continue;
+        }
+        let mut path: Vec<&T> = vec![node];
+        loop {
+            let last = path[path.len() - 1];
+            let next = dependencies[last]
+                .iter()
+                .find(|dep| *dep != last && num_unresolved.contains_key(dep))
+                .unwrap();
+
+            if let Some(idx) = path.iter().position(|visited| *visited == next) {
+                let cycle: Vec<&T> = path[idx..].to_vec();
+                if !cycle.iter().any(|node| in_cycle.contains(node)) {
+                    in_cycle.extend(cycle.iter().cloned());
+                    let mut cycle: Vec<T> = cycle.into_iter().cloned().collect();
+                    cycle.reverse();
+                    cycles.push(cycle);
+                }
+                break;
+            } else if in_cycle.contains(next) {
+                break;
+            }
+            path.push(next);
+        }
+    }
+
+    order.extend(num_unresolved.keys().map(|node| (*node).clone()));
+    CompileOrder { order, cycles }
+}
+
+/// A quoted Graphviz identifier
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Collects the candidate dependencies of a design unit
+struct DependencyCollector<'a> {
+    library_name: &'a str,
+    dependencies: BTreeSet<UnitId>,
+    instances: BTreeSet<UnitId>,
+}
+
+impl<'a> DependencyCollector<'a> {
+    fn library_key(&self, name: &str) -> String {
+        if name == "work" {
+            self.library_name.to_owned()
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// A name such as lib.unit or lib.unit.item referencing a primary unit
+    fn add_name_parts(&mut self, parts: &[String]) {
+        if parts.len() >= 2 {
+            let library_name = self.library_key(&parts[0]);
+            self.dependencies
+                .insert(UnitId::primary(&library_name, &parts[1]));
+        }
+    }
+
+    /// A unit name which is implicitly within the work library unless prefixed with a library
+    fn add_unit_name(
+        &mut self,
+        name: &SelectedName,
+        architecture: Option<String>,
+    ) -> Option<UnitId> {
+        let parts: Vec<String> = name.iter().map(|ident| symbol_key(&ident.item)).collect();
+        let (library_name, unit_name) = match parts.len() {
+            1 => (self.library_name.to_owned(), parts[0].clone()),
+            2 => (self.library_key(&parts[0]), parts[1].clone()),
+            _ => return None,
+        };
+        let unit_id = UnitId::primary(&library_name, &unit_name);
+        self.dependencies.insert(unit_id.clone());
+        if let Some(architecture) = architecture {
+            self.dependencies.insert(UnitId::architecture(
+                &library_name,
+                &unit_name,
+                &architecture,
+            ));
+        }
+        Some(unit_id)
+    }
+
+    fn add_context_clause(&mut self, context_clause: &[ContextItem]) {
+        for item in context_clause {
+            match item {
+                ContextItem::Use(ref use_clause) => {
+                    for name in use_clause.name_list.iter() {
+                        if let Some(parts) = selected_name_parts(&name.item) {
+                            let parts: Vec<String> = parts.into_iter().map(symbol_key).collect();
+                            self.add_name_parts(&parts);
+                        }
+                    }
+                }
+                ContextItem::Context(ref context_ref) => {
+                    for name in context_ref.name_list.iter() {
+                        if let Some(parts) = selected_name_parts(&name.item) {
+                            let parts: Vec<String> = parts.into_iter().map(symbol_key).collect();
+                            self.add_name_parts(&parts);
+                        }
+                    }
+                }
+                ContextItem::Library(..) => {}
+            }
+        }
+    }
+
+    fn add_declarations(&mut self, decls: &[Declaration]) {
+        for decl in decls {
+            match decl {
+                Declaration::Use(ref use_clause) => {
+                    self.add_context_clause(&[ContextItem::Use(use_clause.clone())]);
+                }
+                Declaration::Package(ref inst) => {
+                    self.add_unit_name(&inst.package_name, None);
+                }
+                Declaration::SubprogramBody(ref body) => {
+                    self.add_declarations(&body.declarations);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_statements(&mut self, statements: &[LabeledConcurrentStatement]) {
+        for statement in statements {
+            match statement.statement {
+                ConcurrentStatement::Instance(ref instance) => {
+                    let unit_id = match instance.unit {
+                        InstantiatedUnit::Entity(ref name, ref architecture) => {
+                            let architecture =
+                                architecture.as_ref().map(|ident| symbol_key(&ident.item));
+                            self.add_unit_name(name, architecture)
+                        }
+                        InstantiatedUnit::Configuration(ref name) => self.add_unit_name(name, None),
+                        // Default binding to an entity with the same name in the work library
+                        InstantiatedUnit::Component(ref name) => name.last().map(|ident| {
+                            let unit_id =
+                                UnitId::primary(self.library_name, &symbol_key(&ident.item));
+                            self.dependencies.insert(unit_id.clone());
+                            unit_id
+                        }),
+                    };
+                    self.instances.extend(unit_id);
+                }
+                ConcurrentStatement::Block(ref block) => {
+                    self.add_declarations(&block.decl);
+                    self.add_statements(&block.statements);
+                }
+                ConcurrentStatement::Process(ref process) => {
+                    self.add_declarations(&process.decl);
+                }
+                ConcurrentStatement::ForGenerate(ref gen) => {
+                    if let Some(ref decl) = gen.body.decl {
+                        self.add_declarations(decl);
+                    }
+                    self.add_statements(&gen.body.statements);
+                }
+                ConcurrentStatement::IfGenerate(ref gen) => {
+                    for conditional in gen.conditionals.iter() {
+                        if let Some(ref decl) = conditional.item.decl {
+                            self.add_declarations(decl);
+                        }
+                        self.add_statements(&conditional.item.statements);
+                    }
+                    if let Some(ref body) = gen.else_item {
+                        if let Some(ref decl) = body.decl {
+                            self.add_declarations(decl);
+                        }
+                        self.add_statements(&body.statements);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_block_configuration(&mut self, block_config: &BlockConfiguration) {
+        for use_clause in block_config.use_clauses.iter() {
+            self.add_context_clause(&[ContextItem::Use(use_clause.clone())]);
+        }
+        for item in block_config.items.iter() {
+            match item {
+                ConfigurationItem::Block(ref block_config) => {
+                    self.add_block_configuration(block_config);
+                }
+                ConfigurationItem::Component(ref component_config) => {
+                    if let Some(ref bind_ind) = component_config.bind_ind {
+                        match bind_ind.entity_aspect {
+                            Some(EntityAspect::Entity(ref name, ref architecture)) => {
+                                let architecture =
+                                    architecture.as_ref().map(|ident| symbol_key(&ident.item));
+                                self.add_unit_name(name, architecture);
+                            }
+                            Some(EntityAspect::Configuration(ref name)) => {
+                                self.add_unit_name(name, None);
+                            }
+                            Some(EntityAspect::Open) | None => {}
+                        }
+                    }
+                    if let Some(ref block_config) = component_config.block_config {
+                        self.add_block_configuration(block_config);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_library_unit(&mut self, library_unit: &LibraryUnit) {
+        match library_unit {
+            LibraryUnit::EntityDeclaration { .. } => {}
+            LibraryUnit::ArchitectureBody {
+                ref entity_name,
+                ref decl,
+                ref statements,
+                ..
+            } => {
+                self.dependencies.insert(UnitId::primary(
+                    self.library_name,
+                    &symbol_key(&entity_name.item),
+                ));
+                self.add_declarations(decl);
+                self.add_statements(statements);
+            }
+            LibraryUnit::PackageDeclaration { ref decl, .. } => {
+                self.add_declarations(decl);
+            }
+            LibraryUnit::PackageBody {
+                ref ident,
+                ref decl,
+            } => {
+                self.dependencies
+                    .insert(UnitId::primary(self.library_name, &symbol_key(&ident.item)));
+                self.add_declarations(decl);
+            }
+            LibraryUnit::Configuration(ref config) => {
+                self.add_unit_name(&config.entity_name, None);
+                for item in config.decl.iter() {
+                    match item {
+                        ConfigurationDeclarativeItem::Use(ref use_clause) => {
+                            self.add_context_clause(&[ContextItem::Use(use_clause.clone())]);
+                        }
+                    }
+                }
+                if let Some(ref block_config) = config.block_config {
+                    self.add_block_configuration(block_config);
+                }
+            }
+            LibraryUnit::PackageInstance(ref inst) => {
+                self.add_unit_name(&inst.package_name, None);
+            }
+            LibraryUnit::ContextDeclaration(ref context) => {
+                self.add_context_clause(&context.items);
+            }
+        }
+    }
+}
+
+impl DependencyGraph {
+    pub fn new(files: &[&SourceFile]) -> DependencyGraph {
+        DependencyGraph::from_root(&DesignRoot::new(files))
+    }
+
+    /// The dependencies between the design units of the libraries, where duplicate
+    /// design units have already been ignored
+    pub fn from_root(root: &DesignRoot) -> DependencyGraph {
+        let mut units = BTreeMap::new();
+
+        for library in root.libraries() {
+            for unit in library.units() {
+                let mut collector = DependencyCollector {
+                    library_name: &unit.id.library_name,
+                    dependencies: BTreeSet::new(),
+                    instances: BTreeSet::new(),
+                };
+                collector.add_context_clause(&unit.unit.context_clause);
+                collector.add_library_unit(&unit.unit.library_unit);
+                let DependencyCollector {
+                    dependencies,
+                    instances,
+                    ..
+                } = collector;
+
+                units.insert(
+                    unit.id.clone(),
+                    UnitNode {
+                        pos: unit.pos().clone(),
+                        file_name: unit.file_name.to_owned(),
+                        dependencies,
+                        instances,
+                    },
+                );
+            }
+        }
+
+        // Only keep dependencies on units within the project
+        let unit_ids: BTreeSet<UnitId> = units.keys().cloned().collect();
+        for (unit_id, node) in units.iter_mut() {
+            node.dependencies
+                .retain(|dep| dep != unit_id && unit_ids.contains(dep));
+            node.instances.retain(|inst| unit_ids.contains(inst));
+        }
+
+        DependencyGraph { units }
+    }
+
+    pub fn units(&self) -> impl Iterator<Item = (&UnitId, &UnitNode)> {
+        self.units.iter()
+    }
+
+    pub fn get(&self, unit_id: &UnitId) -> Option<&UnitNode> {
+        self.units.get(unit_id)
+    }
+
+    /// The units which directly depend on the unit
+    pub fn dependents(&self, unit_id: &UnitId) -> BTreeSet<UnitId> {
+        self.units
+            .iter()
+            .filter(|(_, node)| node.dependencies.contains(unit_id))
+            .map(|(dependent, _)| dependent.clone())
+            .collect()
+    }
+
+    /// The units together with all units which directly or indirectly depend on them
+    pub fn transitive_dependents(&self, unit_ids: &BTreeSet<UnitId>) -> BTreeSet<UnitId> {
+        let mut dependents: BTreeSet<UnitId> = BTreeSet::new();
+        let mut pending: Vec<UnitId> = unit_ids.iter().cloned().collect();
+
+        while let Some(unit_id) = pending.pop() {
+            if dependents.insert(unit_id.clone()) {
+                pending.extend(self.dependents(&unit_id));
+            }
+        }
+        dependents
+    }
+
+    /// The units together with all units which they directly or indirectly depend on
+    pub fn transitive_dependencies(&self, unit_ids: &BTreeSet<UnitId>) -> BTreeSet<UnitId> {
+        let mut dependencies: BTreeSet<UnitId> = BTreeSet::new();
+        let mut pending: Vec<UnitId> = unit_ids.iter().cloned().collect();
+
+        while let Some(unit_id) = pending.pop() {
+            if dependencies.insert(unit_id.clone()) {
+                if let Some(node) = self.units.get(&unit_id) {
+                    pending.extend(node.dependencies.iter().cloned());
+                }
+            }
+        }
+        dependencies
+    }
+
+    fn unit_dependencies(&self) -> BTreeMap<UnitId, BTreeSet<UnitId>> {
+        self.units
+            .iter()
+            .map(|(unit_id, node)| (unit_id.clone(), node.dependencies.clone()))
+            .collect()
+    }
+
+    /// All design units of all libraries in compile order
+    pub fn compile_order(&self) -> CompileOrder<UnitId> {
+        topological_sort(&self.unit_dependencies())
+    }
+
+    /// Groups of design units where each unit only depends on units of earlier groups,
+    /// the units of a group are independent of each other
+    /// Dependencies which close a cycle are ignored
+    pub fn levels(&self) -> Vec<Vec<UnitId>> {
+        let mut level_of: BTreeMap<&UnitId, usize> = BTreeMap::new();
+        let mut levels: Vec<Vec<UnitId>> = Vec::new();
+        for unit_id in self.compile_order().order {
+            let (unit_id, node) = self.units.get_key_value(&unit_id).unwrap();
+            let level = node
+                .dependencies
+                .iter()
+                .filter_map(|dep| level_of.get(dep))
+                .map(|level| level + 1)
+                .max()
+                .unwrap_or(0);
+            level_of.insert(unit_id, level);
+            if level == levels.len() {
+                levels.push(Vec::new());
+            }
+            levels[level].push(unit_id.clone());
+        }
+        levels
+    }
+
+    /// The design units of a single library in compile order
+    pub fn library_compile_order(&self, library_name: &str) -> Vec<UnitId> {
+        let library_name = library_name.to_lowercase();
+        self.compile_order()
+            .order
+            .into_iter()
+            .filter(|unit_id| unit_id.library_name == library_name)
+            .collect()
+    }
+
+    /// A file depends on the files containing the units it depends on
+    fn file_dependencies(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut dependencies: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for node in self.units.values() {
+            let file_deps = dependencies.entry(node.file_name.clone()).or_default();
+            for dep in node.dependencies.iter() {
+                let dep_file = &self.units[dep].file_name;
+                if *dep_file != node.file_name {
+                    file_deps.insert(dep_file.clone());
+                }
+            }
+        }
+        dependencies
+    }
+
+    /// The instantiating entity, or the architecture without its entity, and the instantiated units
+    pub fn instance_hierarchy(&self) -> BTreeMap<UnitId, BTreeSet<UnitId>> {
+        let mut hierarchy: BTreeMap<UnitId, BTreeSet<UnitId>> = BTreeMap::new();
+        for (unit_id, node) in self.units.iter() {
+            if node.instances.is_empty() {
+                continue;
+            }
+            let primary_unit = unit_id.primary_unit();
+            let parent = if self.units.contains_key(&primary_unit) {
+                primary_unit
+            } else {
+                unit_id.clone()
+            };
+            hierarchy
+                .entry(parent)
+                .or_default()
+                .extend(node.instances.iter().cloned());
+        }
+        hierarchy
+    }
+
+    /// The files in compile order, a file depends on the files containing the units it depends on
+    pub fn file_compile_order(&self) -> CompileOrder<String> {
+        topological_sort(&self.file_dependencies())
+    }
+
+    /// A Graphviz digraph with an edge from each node to its dependencies or instances
+    pub fn to_dot(&self, kind: GraphKind) -> String {
+        let edges: BTreeMap<String, BTreeSet<String>> = match kind {
+            GraphKind::Units => self
+                .unit_dependencies()
+                .into_iter()
+                .map(|(unit_id, deps)| {
+                    let deps = deps.iter().map(|dep| dep.to_string()).collect();
+                    (unit_id.to_string(), deps)
+                })
+                .collect(),
+            GraphKind::Files => self.file_dependencies(),
+            GraphKind::Instances => self
+                .instance_hierarchy()
+                .into_iter()
+                .map(|(unit_id, insts)| {
+                    let insts = insts.iter().map(|inst| inst.to_string()).collect();
+                    (unit_id.to_string(), insts)
+                })
+                .collect(),
+        };
+
+        let mut dot = String::from("digraph {\n");
+        for (node, deps) in edges.iter() {
+            dot.push_str(&format!("    {};\n", dot_id(node)));
+            for dep in deps.iter() {
+                dot.push_str(&format!("    {} -> {};\n", dot_id(node), dot_id(dep)));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Error messages for all circular dependencies between design units
+    pub fn cycle_messages(&self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        for cycle in self.compile_order().cycles {
+            let mut path: Vec<String> = cycle.iter().map(|unit_id| unit_id.to_string()).collect();
+            path.push(cycle[0].to_string());
+            let node = &self.units[&cycle[0]];
+            messages.push(error(
+                &node.pos,
+                &format!("Found circular dependency {}", path.join(" -> ")),
+            ));
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn graph(project: &TempProject) -> DependencyGraph {
+        project.project.dependency_graph()
+    }
+
+    fn ids(names: &[&str]) -> Vec<UnitId> {
+        names
+            .iter()
+            .map(|name| {
+                let parts: Vec<&str> = name.split('.').collect();
+                UnitId::primary(parts[0], parts[1])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn use_clause_dependency() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+use work.pkg.all;
+entity ent is
+end entity;",
+        );
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+end package;",
+        );
+
+        let graph = graph(&project);
+        assert_eq!(
+            graph
+                .get(&UnitId::primary("lib", "ent"))
+                .unwrap()
+                .dependencies,
+            ids(&["lib.pkg"]).into_iter().collect()
+        );
+        assert_eq!(graph.compile_order().order, ids(&["lib.pkg", "lib.ent"]));
+    }
+
+    #[test]
+    fn dependencies_on_other_libraries() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib1",
+            "ent.vhd",
+            "
+library lib2;
+use lib2.a_pkg.all;
+context lib2.ctx;
+entity ent is
+end entity;",
+        );
+        project.add_file(
+            "lib2",
+            "pkg.vhd",
+            "
+package a_pkg is
+end package;
+
+context ctx is
+end context;
+
+-- Not visible from lib1 via work
+package pkg is
+end package;",
+        );
+
+        let graph = graph(&project);
+        assert_eq!(
+            graph
+                .get(&UnitId::primary("lib1", "ent"))
+                .unwrap()
+                .dependencies,
+            ids(&["lib2.a_pkg", "lib2.ctx"]).into_iter().collect()
+        );
+        assert_eq!(graph.library_compile_order("lib1"), ids(&["lib1.ent"]));
+    }
+
+    #[test]
+    fn secondary_units_depend_on_primary_units() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "file.vhd",
+            "
+architecture rtl of ent is
+begin
+end architecture;
+
+package body pkg is
+end package body;
+
+entity ent is
+end entity;
+
+package pkg is
+end package;",
+        );
+
+        let graph = graph(&project);
+        let order = graph.compile_order().order;
+        let position = |unit_id: &UnitId| order.iter().position(|id| id == unit_id).unwrap();
+        assert!(
+            position(&UnitId::primary("lib", "ent"))
+                < position(&UnitId::architecture("lib", "ent", "rtl"))
+        );
+        assert!(
+            position(&UnitId::primary("lib", "pkg"))
+                < position(&UnitId::package_body("lib", "pkg"))
+        );
+    }
+
+    #[test]
+    fn levels_of_independent_units() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "file.vhd",
+            "
+use work.pkg.all;
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+end architecture;
+
+package pkg is
+end package;
+
+package other is
+end package;
+
+package body pkg is
+end package body;",
+        );
+
+        assert_eq!(
+            graph(&project).levels(),
+            vec![
+                ids(&["lib.other", "lib.pkg"]),
+                vec![
+                    UnitId::primary("lib", "ent"),
+                    UnitId::package_body("lib", "pkg")
+                ],
+                vec![UnitId::architecture("lib", "ent", "rtl")],
+            ]
+        );
+    }
+
+    #[test]
+    fn instantiation_dependencies() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+architecture rtl of top is
+begin
+  inst0: entity work.ent0(arch);
+  gen: for i in 0 to 1 generate
+    inst1: component ent1;
+  end generate;
+  inst2: configuration work.cfg;
+end architecture;",
+        );
+        project.add_file(
+            "lib",
+            "units.vhd",
+            "
+entity top is end entity;
+entity ent0 is end entity;
+architecture arch of ent0 is begin end architecture;
+entity ent1 is end entity;
+entity ent2 is end entity;
+configuration cfg of ent2 is
+  for rtl
+  end for;
+end configuration;",
+        );
+
+        let graph = graph(&project);
+        let mut expected: BTreeSet<UnitId> = ids(&["lib.top", "lib.ent0", "lib.ent1", "lib.cfg"])
+            .into_iter()
+            .collect();
+        expected.insert(UnitId::architecture("lib", "ent0", "arch"));
+        assert_eq!(
+            graph
+                .get(&UnitId::architecture("lib", "top", "rtl"))
+                .unwrap()
+                .dependencies,
+            expected
+        );
+        assert_eq!(
+            graph
+                .get(&UnitId::primary("lib", "cfg"))
+                .unwrap()
+                .dependencies,
+            ids(&["lib.ent2"]).into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn dot_graphs() {
+        let mut project = TempProject::new();
+        let top_file = project.add_file(
+            "lib",
+            "top.vhd",
+            "
+use work.pkg.all;
+entity top is end entity;
+architecture rtl of top is
+begin
+  inst: entity work.ent;
+end architecture;",
+        );
+        let units_file = project.add_file(
+            "lib",
+            "units.vhd",
+            "
+package pkg is end package;
+entity ent is end entity;",
+        );
+
+        let graph = graph(&project);
+        assert_eq!(
+            graph.to_dot(GraphKind::Units),
+            "digraph {
+    \"lib.ent\";
+    \"lib.pkg\";
+    \"lib.top\";
+    \"lib.top\" -> \"lib.pkg\";
+    \"lib.top(rtl)\";
+    \"lib.top(rtl)\" -> \"lib.ent\";
+    \"lib.top(rtl)\" -> \"lib.top\";
+}
+"
+        );
+        assert_eq!(
+            graph.to_dot(GraphKind::Files),
+            format!(
+                "digraph {{\n    \"{0}\";\n    \"{0}\" -> \"{1}\";\n    \"{1}\";\n}}\n",
+                top_file, units_file
+            )
+        );
+        assert_eq!(
+            graph.to_dot(GraphKind::Instances),
+            "digraph {\n    \"lib.top\";\n    \"lib.top\" -> \"lib.ent\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn circular_dependencies() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+use work.pkg_b.all;
+package pkg_a is
+end package;
+
+use work.pkg_a.all;
+package pkg_b is
+end package;
+
+use work.pkg_a.all;
+package pkg_c is
+end package;",
+        );
+
+        let graph = graph(&project);
+        let compile_order = graph.compile_order();
+        assert_eq!(compile_order.cycles, vec![ids(&["lib.pkg_b", "lib.pkg_a"])]);
+        assert_eq!(compile_order.order.len(), 3);
+
+        let messages = graph.cycle_messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].message,
+            "Found circular dependency lib.pkg_b -> lib.pkg_a -> lib.pkg_b"
+        );
+        assert_eq!(messages[0].pos.source.file_name(), Some(file_name.as_str()));
+    }
+
+    #[test]
+    fn file_compile_order() {
+        let mut project = TempProject::new();
+        let ent_file = project.add_file(
+            "lib",
+            "a_ent.vhd",
+            "
+use work.pkg.all;
+entity ent is
+end entity;",
+        );
+        let pkg_file = project.add_file(
+            "lib",
+            "b_pkg.vhd",
+            "
+package pkg is
+end package;",
+        );
+
+        let graph = graph(&project);
+        let compile_order = graph.file_compile_order();
+        assert_eq!(compile_order.order, vec![pkg_file, ent_file]);
+        assert!(compile_order.cycles.is_empty());
+    }
+
+    #[test]
+    fn transitive_dependents() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "units.vhd",
+            "
+package pkg_a is
+end package;
+
+use work.pkg_a.all;
+package pkg_b is
+end package;
+
+use work.pkg_b.all;
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+end architecture;
+
+package pkg_c is
+end package;",
+        );
+
+        let graph = graph(&project);
+        let changed = ids(&["lib.pkg_b"]).into_iter().collect();
+        let mut expected: BTreeSet<UnitId> = ids(&["lib.pkg_b", "lib.ent"]).into_iter().collect();
+        expected.insert(UnitId::architecture("lib", "ent", "rtl"));
+        assert_eq!(graph.transitive_dependents(&changed), expected);
+
+        let architecture = vec![UnitId::architecture("lib", "ent", "rtl")]
+            .into_iter()
+            .collect();
+        let mut expected: BTreeSet<UnitId> = ids(&["lib.pkg_a", "lib.pkg_b", "lib.ent"])
+            .into_iter()
+            .collect();
+        expected.insert(UnitId::architecture("lib", "ent", "rtl"));
+        assert_eq!(graph.transitive_dependencies(&architecture), expected);
+    }
+}
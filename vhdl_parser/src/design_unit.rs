@@ -4,17 +4,21 @@
 //
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
-use symbol_table::Symbol;
 use tokenizer::Kind::*;
 use tokenstream::TokenStream;
 
-use ast::{DesignFile, DesignUnit, GenericClause, Ident, LibraryUnit, PortClause};
+use ast::{
+    ContextItem, Declaration, DeferredBody, DesignFile, DesignUnit, GenericClause, Ident,
+    LabeledConcurrentStatement, LibraryUnit, PortClause,
+};
 use concurrent_statement::parse_labeled_concurrent_statements;
 use configuration::parse_configuration_declaration;
 use context::{parse_context, parse_library_clause, parse_use_clause, DeclarationOrReference};
 use declarative_part::{parse_declarative_part, parse_package_instantiation};
 use interface_declaration::{parse_generic_interface_list, parse_port_interface_list};
 use message::{error, push_result, MessageHandler, ParseResult};
+use source::SrcPos;
+use std::mem::take;
 
 /// Parse a generic clause
 fn parse_generic_clause(
@@ -130,49 +134,124 @@ fn parse_entity_decl(
 }
 
 /// Parse architecture symbol declaration and entity name
-fn parse_architecture_header(stream: &mut TokenStream) -> ParseResult<(Ident, Symbol)> {
+fn parse_architecture_header(stream: &mut TokenStream) -> ParseResult<(Ident, Ident)> {
     let ident = stream.expect_ident()?;
     stream.expect_kind(Of)?;
-    let Ident {
-        item: entity_name, ..
-    } = stream.expect_ident()?;
+    let entity_name = stream.expect_ident()?;
     stream.expect_kind(Is)?;
     Ok((ident, entity_name))
 }
 
+/// Skip the remainder of a secondary unit until the start of the next design unit
+/// Use clauses directly preceding the next design unit are not skipped since they are its context clause
+/// A package declaration is only considered to start a new design unit after an end statement
+/// since package instantiations may also occur within a declarative part
+/// Returns the position of the skipped tokens
+fn skip_secondary_unit(stream: &mut TokenStream) -> ParseResult<Option<SrcPos>> {
+    let mut at_statement_start = false;
+    let mut statement_kind = None;
+    let mut previous_statement_kind = None;
+    let mut use_clause_start = None;
+    let mut first_pos: Option<SrcPos> = None;
+    let mut end = 0;
+    let mut end_before_use_clause = 0;
+
+    while let Some(token) = stream.peek()? {
+        if at_statement_start {
+            let is_design_unit = match token.kind {
+                Library | Context | Entity | Architecture | Configuration => true,
+                Package => {
+                    stream.is_peek_kinds(&[Package, Body])?
+                        || (previous_statement_kind == Some(End)
+                            && stream.is_peek_kinds(&[Package, Identifier, Is])?)
+                }
+                _ => false,
+            };
+
+            if is_design_unit {
+                if let Some(state) = use_clause_start {
+                    stream.set_state(state);
+                    end = end_before_use_clause;
+                }
+                break;
+            } else if token.kind == Use {
+                if use_clause_start.is_none() {
+                    use_clause_start = Some(stream.state());
+                    end_before_use_clause = end;
+                }
+            } else {
+                use_clause_start = None;
+            }
+            statement_kind = Some(token.kind);
+        }
+
+        stream.move_after(&token);
+        if first_pos.is_none() {
+            first_pos = Some(token.pos.clone());
+        }
+        end = token.pos.start + token.pos.length;
+        at_statement_start = token.kind == SemiColon;
+        if at_statement_start {
+            previous_statement_kind = statement_kind;
+        }
+    }
+    Ok(first_pos.map(|pos| pos.source.pos(pos.start, end - pos.start)))
+}
+
+/// The declarations and statements of an architecture after the is keyword
+fn parse_architecture_contents(
+    stream: &mut TokenStream,
+    messages: &mut dyn MessageHandler,
+) -> ParseResult<(Vec<Declaration>, Vec<LabeledConcurrentStatement>)> {
+    let decl = parse_declarative_part(stream, messages, true)?;
+
+    let statements = match parse_labeled_concurrent_statements(stream, messages) {
+        Ok(statements) => statements,
+        Err(msg) => {
+            messages.push(msg);
+            Vec::new()
+        }
+    };
+    stream.pop_if_kind(Architecture)?;
+    // @TODO check end identifier
+    stream.pop_if_kind(Identifier)?;
+    stream.expect_kind(SemiColon)?;
+
+    Ok((decl, statements))
+}
+
 /// LRM 3.3.1
+/// Returns the position of the contents as well when they are skipped
 fn parse_architecture_body(
     stream: &mut TokenStream,
     messages: &mut MessageHandler,
-) -> ParseResult<LibraryUnit> {
+    skip_bodies: bool,
+) -> ParseResult<(LibraryUnit, Option<SrcPos>)> {
     let architecture_token = stream.expect_kind(Architecture)?;
-    let design_unit = match parse_architecture_header(stream) {
-        Ok((ident, entity_name)) => Ok(LibraryUnit::ArchitectureBody {
-            ident,
-            entity_name,
-            decl: parse_declarative_part(stream, messages, true)?,
-        }),
+    let (ident, entity_name) = match parse_architecture_header(stream) {
+        Ok(header) => header,
         Err(err) => {
             messages.push(err);
             return Err(error(&architecture_token, "Failed to parse architecture"));
         }
     };
 
-    let statements = parse_labeled_concurrent_statements(stream, messages);
-    push_result(messages, statements);
-    stream.pop_if_kind(Architecture)?;
-    // @TODO check end identifier
-    stream.pop_if_kind(Identifier)?;
-    stream.expect_kind(SemiColon)?;
-
-    if design_unit.is_err() {
-        messages.push(error(
-            &architecture_token,
-            "Failed to parse architecture declaration",
-        ));
-    }
+    let (decl, statements, skipped) = if skip_bodies {
+        (Vec::new(), Vec::new(), skip_secondary_unit(stream)?)
+    } else {
+        let (decl, statements) = parse_architecture_contents(stream, messages)?;
+        (decl, statements, None)
+    };
 
-    return design_unit;
+    Ok((
+        LibraryUnit::ArchitectureBody {
+            ident,
+            entity_name,
+            decl,
+            statements,
+        },
+        skipped,
+    ))
 }
 
 /// LRM 4.7 Package declarations
@@ -202,11 +281,34 @@ fn parse_package_declaration(
     return Ok(LibraryUnit::PackageDeclaration { ident, decl });
 }
 
+/// The declarations of a package body after the is keyword
+fn parse_package_body_contents(
+    stream: &mut TokenStream,
+    messages: &mut dyn MessageHandler,
+) -> ParseResult<Vec<Declaration>> {
+    let decl = match parse_declarative_part(stream, messages, false) {
+        Ok(decl) => decl,
+        Err(msg) => {
+            messages.push(msg);
+            Vec::new()
+        }
+    };
+    stream.pop_if_kind(Package)?;
+    stream.pop_if_kind(Body)?;
+    // @TODO check end identifier
+    stream.pop_if_kind(Identifier)?;
+    stream.expect_kind(SemiColon)?;
+
+    Ok(decl)
+}
+
 /// LRM 4.8 Package bodies
+/// Returns the position of the contents as well when they are skipped
 fn parse_package_body(
     stream: &mut TokenStream,
     messages: &mut MessageHandler,
-) -> ParseResult<LibraryUnit> {
+    skip_bodies: bool,
+) -> ParseResult<(LibraryUnit, Option<SrcPos>)> {
     stream.expect_kind(Package)?;
     stream.expect_kind(Body)?;
     let ident = match stream.expect_ident() {
@@ -217,58 +319,128 @@ fn parse_package_body(
     };
 
     push_result(messages, stream.expect_kind(Is));
-    let decl = parse_declarative_part(stream, messages, false);
-    stream.pop_if_kind(Package)?;
-    stream.pop_if_kind(Body)?;
-    // @TODO check end identifier
-    stream.pop_if_kind(Identifier)?;
-    stream.expect_kind(SemiColon)?;
-    push_result(messages, decl);
+    if skip_bodies {
+        let skipped = skip_secondary_unit(stream)?;
+        return Ok((
+            LibraryUnit::PackageBody {
+                ident,
+                decl: Vec::new(),
+            },
+            skipped,
+        ));
+    }
 
-    return Ok(LibraryUnit::PackageBody { ident });
+    let decl = parse_package_body_contents(stream, messages)?;
+    return Ok((LibraryUnit::PackageBody { ident, decl }, None));
 }
 
+/// Parse the skipped contents of an architecture or package body, the stream
+/// starts at the position of the deferred body
+pub fn parse_deferred_body(
+    stream: &mut TokenStream,
+    messages: &mut dyn MessageHandler,
+    library_unit: &mut LibraryUnit,
+) -> ParseResult<()> {
+    match *library_unit {
+        LibraryUnit::ArchitectureBody {
+            ref mut decl,
+            ref mut statements,
+            ..
+        } => {
+            let (contents_decl, contents_statements) =
+                parse_architecture_contents(stream, messages)?;
+            *decl = contents_decl;
+            *statements = contents_statements;
+        }
+        LibraryUnit::PackageBody { ref mut decl, .. } => {
+            *decl = parse_package_body_contents(stream, messages)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parse all design units of a file
+/// When skipping bodies only the interface of architectures and package bodies is kept
+/// and the position of their contents is recorded as deferred bodies
 pub fn parse_design_file(
     stream: &mut TokenStream,
     messages: &mut MessageHandler,
+    skip_bodies: bool,
 ) -> ParseResult<DesignFile> {
-    let mut design_units = vec![];
+    parse_design_units(stream, messages, skip_bodies, usize::MAX)
+}
 
-    while let Some(token) = stream.peek()? {
+/// Parse the design units from the current position until the end of the file or
+/// until a design unit ends at or after the end position
+pub fn parse_design_units(
+    stream: &mut TokenStream,
+    messages: &mut dyn MessageHandler,
+    skip_bodies: bool,
+    end: usize,
+) -> ParseResult<DesignFile> {
+    let mut context_clause = vec![];
+    let mut design_units = vec![];
+    let mut deferred_bodies = vec![];
+    let mut design_unit_ends = vec![];
+
+    loop {
+        if design_unit_ends.len() < design_units.len() {
+            design_unit_ends.push(stream.state().start());
+            if stream.state().start() >= end {
+                break;
+            }
+        }
+        let token = match stream.peek()? {
+            Some(token) => token,
+            None => break,
+        };
         try_token_kind!(
             token,
             Library => {
-                let decl = parse_library_clause(stream);
-                push_result(messages, decl);
+                match parse_library_clause(stream) {
+                    Ok(library) => context_clause.push(ContextItem::Library(library)),
+                    Err(msg) => messages.push(msg),
+                }
             },
             Use => {
-                let decl = parse_use_clause(stream);
-                push_result(messages, decl);
+                match parse_use_clause(stream) {
+                    Ok(use_clause) => context_clause.push(ContextItem::Use(use_clause)),
+                    Err(msg) => messages.push(msg),
+                }
             },
             Context => match parse_context(stream, messages) {
                 Ok(DeclarationOrReference::Declaration(context_decl)) => {
                     design_units.push(DesignUnit {
-                        context_clause: vec![],
+                        context_clause: take(&mut context_clause),
                         library_unit: LibraryUnit::ContextDeclaration(context_decl),
                     });
                 }
-                Ok(_) => {}
+                Ok(DeclarationOrReference::Reference(context_ref)) => {
+                    context_clause.push(ContextItem::Context(context_ref));
+                }
                 Err(msg) => messages.push(msg),
             },
             Entity => match parse_entity_decl(stream, messages) {
                 Ok(library_unit) => {
                     design_units.push(DesignUnit {
-                        context_clause: vec![],
+                        context_clause: take(&mut context_clause),
                         library_unit,
                     });
                 }
                 Err(msg) => messages.push(msg),
             },
 
-            Architecture => match parse_architecture_body(stream, messages) {
-                Ok(library_unit) => {
+            Architecture => match parse_architecture_body(stream, messages, skip_bodies) {
+                Ok((library_unit, skipped)) => {
+                    if let Some(pos) = skipped {
+                        deferred_bodies.push(DeferredBody {
+                            design_unit: design_units.len(),
+                            pos,
+                        });
+                    }
                     design_units.push(DesignUnit {
-                        context_clause: vec![],
+                        context_clause: take(&mut context_clause),
                         library_unit,
                     });
                 }
@@ -278,7 +450,7 @@ pub fn parse_design_file(
             Configuration => match parse_configuration_declaration(stream, messages) {
                 Ok(configuration) => {
                     design_units.push(DesignUnit {
-                        context_clause: vec![],
+                        context_clause: take(&mut context_clause),
                         library_unit: LibraryUnit::Configuration(configuration),
                     });
                 }
@@ -286,10 +458,16 @@ pub fn parse_design_file(
             },
             Package => {
                 if stream.is_peek_kinds(&[Package, Body])? {
-                    match parse_package_body(stream, messages) {
-                        Ok(library_unit) => {
+                    match parse_package_body(stream, messages, skip_bodies) {
+                        Ok((library_unit, skipped)) => {
+                            if let Some(pos) = skipped {
+                                deferred_bodies.push(DeferredBody {
+                                    design_unit: design_units.len(),
+                                    pos,
+                                });
+                            }
                             design_units.push(DesignUnit {
-                                context_clause: vec![],
+                                context_clause: take(&mut context_clause),
                                 library_unit,
                             });
                         }
@@ -298,7 +476,7 @@ pub fn parse_design_file(
                 } else if stream.is_peek_kinds(&[Package, Identifier, Is, New])? {
                     match parse_package_instantiation(stream) {
                         Ok(inst) => design_units.push(DesignUnit {
-                            context_clause: vec![],
+                            context_clause: take(&mut context_clause),
                             library_unit: LibraryUnit::PackageInstance(inst),
                         }),
                         Err(msg) => messages.push(msg),
@@ -307,7 +485,7 @@ pub fn parse_design_file(
                     match parse_package_declaration(stream, messages) {
                         Ok(library_unit) => {
                             design_units.push(DesignUnit {
-                                context_clause: vec![],
+                                context_clause: take(&mut context_clause),
                                 library_unit,
                             });
                         }
@@ -318,24 +496,46 @@ pub fn parse_design_file(
         );
     }
 
-    Ok(DesignFile { design_units })
+    Ok(DesignFile {
+        design_units,
+        deferred_bodies,
+        design_unit_ends,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use ast::{InterfaceDeclaration, InterfaceObjectDeclaration, Mode, ObjectClass};
+    use ast::{
+        ContextReference, InterfaceDeclaration, InterfaceObjectDeclaration, LibraryClause, Mode,
+        ObjectClass,
+    };
     use message::Message;
-    use test_util::{check_no_messages, with_stream, TestUtil};
+    use source::WithPos;
+    use std::sync::Arc;
+    use test_util::{check_no_messages, with_partial_stream, with_stream, TestUtil};
+    use tokenizer::TokenState;
 
     fn parse_str(code: &str) -> (TestUtil, DesignFile, Vec<Message>) {
         let mut messages = vec![];
-        let (util, design_file) =
-            with_stream(|stream| parse_design_file(stream, &mut messages), code);
+        let (util, design_file) = with_stream(
+            |stream| parse_design_file(stream, &mut messages, false),
+            code,
+        );
         (util, design_file, messages)
     }
 
+    fn parse_interface_ok(code: &str) -> (TestUtil, DesignFile) {
+        let mut messages = vec![];
+        let (util, design_file) = with_stream(
+            |stream| parse_design_file(stream, &mut messages, true),
+            code,
+        );
+        check_no_messages(&messages);
+        (util, design_file)
+    }
+
     fn parse_ok(code: &str) -> (TestUtil, DesignFile) {
         let (util, designfile, messages) = parse_str(code);
         check_no_messages(&messages);
@@ -427,7 +627,7 @@ end entity;
             mode: Mode::In,
             class: ObjectClass::Constant,
             ident: util.ident("runner_cfg"),
-            subtype_indication: util.subtype_indication("string"),
+            subtype_indication: Arc::new(util.subtype_indication("string")),
             expression: None,
         }));
 
@@ -464,7 +664,7 @@ end entity;
             mode: Mode::In,
             class: ObjectClass::Constant,
             ident: util.ident("runner_cfg"),
-            subtype_indication: util.subtype_indication("string"),
+            subtype_indication: Arc::new(util.subtype_indication("string")),
             expression: None,
         }));
 
@@ -472,8 +672,8 @@ end entity;
             mode: Mode::In,
             class: ObjectClass::Constant,
             ident: util.ident("foo"),
-            subtype_indication: util.subtype_indication("boolean"),
-            expression: Some(util.expr("false")),
+            subtype_indication: Arc::new(util.subtype_indication("boolean")),
+            expression: Some(Arc::new(util.expr("false"))),
         }));
 
         assert_eq!(
@@ -595,7 +795,7 @@ end entity;
             mode: Mode::In,
             class: ObjectClass::Signal,
             ident: util.ident("clk"),
-            subtype_indication: util.subtype_indication("std_logic"),
+            subtype_indication: Arc::new(util.subtype_indication("std_logic")),
             expression: None,
         }));
 
@@ -603,8 +803,8 @@ end entity;
             mode: Mode::Out,
             class: ObjectClass::Signal,
             ident: util.ident("bar"),
-            subtype_indication: util.subtype_indication("bit"),
-            expression: Some(util.expr("'1'")),
+            subtype_indication: Arc::new(util.subtype_indication("bit")),
+            expression: Some(Arc::new(util.expr("'1'"))),
         }));
 
         assert_eq!(
@@ -638,7 +838,7 @@ end entity;
             mode: Mode::In,
             class: ObjectClass::Signal,
             ident: util.ident("clk"),
-            subtype_indication: util.subtype_indication("std_logic"),
+            subtype_indication: Arc::new(util.subtype_indication("std_logic")),
             expression: None,
         }));
 
@@ -731,11 +931,12 @@ entity myent is
     }
 
     // An simple entity with only a name
-    fn simple_architecture(ident: Ident, entity_name: Symbol) -> LibraryUnit {
+    fn simple_architecture(ident: Ident, entity_name: Ident) -> LibraryUnit {
         LibraryUnit::ArchitectureBody {
             ident,
             entity_name,
             decl: Vec::new(),
+            statements: Vec::new(),
         }
     }
 
@@ -752,7 +953,7 @@ end architecture;
             library_units(design_file),
             [simple_architecture(
                 util.ident("arch_name"),
-                util.symbol("myent")
+                util.ident("myent")
             )]
         );
     }
@@ -770,7 +971,7 @@ end architecture arch_name;
             library_units(design_file),
             [simple_architecture(
                 util.ident("arch_name"),
-                util.symbol("myent")
+                util.ident("myent")
             )]
         );
     }
@@ -788,7 +989,7 @@ end;
             library_units(design_file),
             [simple_architecture(
                 util.ident("arch_name"),
-                util.symbol("myent")
+                util.ident("myent")
             )]
         );
     }
@@ -813,4 +1014,245 @@ end package;
             [simple_package(util.ident("pkg_name"),)]
         );
     }
+
+    #[test]
+    fn parse_architecture_body_with_statements() {
+        let (util, design_file) = parse_ok(
+            "
+architecture arch_name of myent is
+begin
+  foo(clk);
+end architecture;
+",
+        );
+        assert_eq!(
+            library_units(design_file),
+            [LibraryUnit::ArchitectureBody {
+                ident: util.ident("arch_name"),
+                entity_name: util.ident("myent"),
+                decl: Vec::new(),
+                statements: vec![util.concurrent_statement("foo(clk);")],
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_package_body_with_declarations() {
+        let (util, design_file) = parse_ok(
+            "
+package body pkg_name is
+  constant foo : natural := 0;
+end package body;
+",
+        );
+        assert_eq!(
+            library_units(design_file),
+            [LibraryUnit::PackageBody {
+                ident: util.ident("pkg_name"),
+                decl: util.declarative_part("constant foo : natural := 0;"),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_design_unit_context_clause() {
+        let (util, design_file) = parse_ok(
+            "
+library lib;
+use lib.pkg.all;
+context lib.ctx;
+entity myent is
+end entity;
+
+entity myent2 is
+end entity;
+",
+        );
+        assert_eq!(
+            design_file.design_units,
+            [
+                DesignUnit {
+                    context_clause: vec![
+                        ContextItem::Library(LibraryClause {
+                            name_list: vec![WithPos::new(
+                                util.symbol("lib"),
+                                util.substr_pos("lib", 2),
+                            )],
+                        }),
+                        ContextItem::Use(util.use_clause("use lib.pkg.all;")),
+                        ContextItem::Context(ContextReference {
+                            name_list: vec![util.name("lib.ctx")],
+                        }),
+                    ],
+                    library_unit: simple_entity(util.ident("myent")),
+                },
+                DesignUnit {
+                    context_clause: vec![],
+                    library_unit: simple_entity(util.ident("myent2")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_design_file_skipping_bodies() {
+        let (util, design_file) = parse_interface_ok(
+            "
+architecture arch_name of myent is
+  package inst is new work.gpkg;
+begin
+  main : process
+  begin
+    wait;
+  end process;
+end architecture;
+
+use work.pkg.all;
+use work.pkg2.all;
+entity myent is
+end entity;
+
+package body pkg_name is
+  function fun return natural is
+  begin
+    return 0;
+  end;
+end package body;
+
+package pkg_name is
+end package;
+",
+        );
+        assert_eq!(
+            design_file.design_units,
+            [
+                DesignUnit {
+                    context_clause: vec![],
+                    library_unit: LibraryUnit::ArchitectureBody {
+                        ident: util.ident("arch_name"),
+                        entity_name: util.ident("myent"),
+                        decl: Vec::new(),
+                        statements: Vec::new(),
+                    },
+                },
+                DesignUnit {
+                    context_clause: vec![
+                        ContextItem::Use(util.use_clause("use work.pkg.all;")),
+                        ContextItem::Use(util.use_clause("use work.pkg2.all;")),
+                    ],
+                    library_unit: simple_entity(util.substr_ident("myent", 2)),
+                },
+                DesignUnit {
+                    context_clause: vec![],
+                    library_unit: LibraryUnit::PackageBody {
+                        ident: util.ident("pkg_name"),
+                        decl: Vec::new(),
+                    },
+                },
+                DesignUnit {
+                    context_clause: vec![],
+                    library_unit: LibraryUnit::PackageDeclaration {
+                        ident: util.substr_ident("pkg_name", 2),
+                        decl: Vec::new(),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            design_file.deferred_bodies,
+            [
+                DeferredBody {
+                    design_unit: 0,
+                    pos: util
+                        .first_substr_pos("package inst")
+                        .combine(&util.first_substr_pos("end architecture;")),
+                },
+                DeferredBody {
+                    design_unit: 2,
+                    pos: util
+                        .first_substr_pos("function fun")
+                        .combine(&util.first_substr_pos("end package body;")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_deferred_bodies_as_when_not_skipped() {
+        let code = "
+architecture arch_name of myent is
+  signal foo : natural;
+begin
+  foo <= 0;
+end architecture;
+
+package body pkg_name is
+  constant c : natural := 0;
+end package body;
+";
+        let (_, (design_file, deferred_file)) = with_partial_stream(
+            |stream| {
+                let mut messages = vec![];
+                let design_file = parse_design_file(stream, &mut messages, false).unwrap();
+
+                stream.set_state(TokenState::new());
+                let mut deferred_file = parse_design_file(stream, &mut messages, true).unwrap();
+                for body in take(&mut deferred_file.deferred_bodies) {
+                    stream.set_state(TokenState::at(body.pos.start));
+                    let design_unit = &mut deferred_file.design_units[body.design_unit];
+                    parse_deferred_body(stream, &mut messages, &mut design_unit.library_unit)
+                        .unwrap();
+                }
+                check_no_messages(&messages);
+                (design_file, deferred_file)
+            },
+            code,
+        );
+        assert_eq!(deferred_file, design_file);
+    }
+
+    #[test]
+    fn parse_design_units_until_end() {
+        let code = "
+entity ent is
+end entity;
+
+library ieee;
+package pkg is
+end package;
+
+entity ent2 is
+end entity;
+";
+        let (util, (design_file, units)) = with_partial_stream(
+            |stream| {
+                let mut messages = vec![];
+                let design_file = parse_design_file(stream, &mut messages, false).unwrap();
+
+                let (start, end) = (
+                    design_file.design_unit_ends[0],
+                    design_file.design_unit_ends[1],
+                );
+                stream.set_state(TokenState::at(start));
+                let units = parse_design_units(stream, &mut messages, false, end).unwrap();
+                check_no_messages(&messages);
+                (design_file, units)
+            },
+            code,
+        );
+        let end_of = |substr: &str| {
+            let pos = util.first_substr_pos(substr);
+            pos.start + pos.length
+        };
+        assert_eq!(
+            design_file.design_unit_ends,
+            vec![
+                end_of("end entity;"),
+                end_of("end package;"),
+                code.len() - 1
+            ]
+        );
+        assert_eq!(units.design_units, design_file.design_units[1..2].to_vec());
+        assert_eq!(units.design_unit_ends, vec![end_of("end package;")]);
+    }
 }
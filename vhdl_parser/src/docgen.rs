@@ -0,0 +1,742 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Documentation of the entities and packages of a project rendered as cross-linked
+//! HTML or markdown pages
+
+use ast::{Ident, LibraryUnit};
+use documentation::declaration_text;
+use library::UnitId;
+use name_resolution::{
+    declared_entities, entity_interface, interface_entity, NamedEntity, NamedEntityKind,
+};
+use project::Project;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum UnitDocKind {
+    Entity,
+    Package,
+}
+
+impl UnitDocKind {
+    fn title(self) -> &'static str {
+        match self {
+            UnitDocKind::Entity => "Entity",
+            UnitDocKind::Package => "Package",
+        }
+    }
+}
+
+/// The format of the pages of the documentation
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DocFormat {
+    Html,
+    /// One file per library and design unit with a SUMMARY.md for mdBook
+    Markdown,
+}
+
+/// A generic, port or declaration of a package
+#[derive(PartialEq, Debug, Clone)]
+pub struct ItemDoc {
+    pub name: String,
+    /// The declaration as written in the source
+    pub text: String,
+    pub comment: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnitDoc {
+    pub id: UnitId,
+    /// The designator as written in the declaration
+    pub name: String,
+    pub kind: UnitDocKind,
+    pub comment: Option<String>,
+    pub generics: Vec<ItemDoc>,
+    pub ports: Vec<ItemDoc>,
+    pub declarations: Vec<ItemDoc>,
+    pub architectures: Vec<String>,
+    /// The entities and configurations instantiated by the architectures of an entity
+    pub instances: Vec<UnitId>,
+}
+
+/// The documented design units of all libraries sorted by library and name
+pub struct Documentation {
+    pub units: Vec<UnitDoc>,
+}
+
+fn item_doc(entity: &NamedEntity) -> ItemDoc {
+    let text = declaration_text(entity);
+    ItemDoc {
+        name: entity.designator.clone(),
+        text: text
+            .as_ref()
+            .map(|text| text.text.clone())
+            .unwrap_or_else(|| entity.designator.clone()),
+        comment: text.and_then(|text| text.comment),
+    }
+}
+
+fn unit_entity(ident: &Ident, kind: NamedEntityKind) -> NamedEntity {
+    NamedEntity {
+        designator: ident.item.name_utf8(),
+        kind,
+        pos: ident.pos.clone(),
+    }
+}
+
+impl Documentation {
+    pub fn new(project: &Project) -> Documentation {
+        let instances = project.dependency_graph().instance_hierarchy();
+        let mut units: BTreeMap<UnitId, UnitDoc> = BTreeMap::new();
+        let mut architectures: BTreeMap<UnitId, Vec<String>> = BTreeMap::new();
+
+        for file in project.files() {
+            for design_unit in file.design_file.design_units.iter() {
+                let id = UnitId::new(&file.library_name, design_unit);
+                let library_unit = &design_unit.library_unit;
+                let (ident, kind) = match library_unit {
+                    LibraryUnit::EntityDeclaration { ref ident, .. } => {
+                        (ident, UnitDocKind::Entity)
+                    }
+                    LibraryUnit::PackageDeclaration { ref ident, .. } => {
+                        (ident, UnitDocKind::Package)
+                    }
+                    LibraryUnit::ArchitectureBody { ref ident, .. } => {
+                        architectures
+                            .entry(id.primary_unit())
+                            .or_default()
+                            .push(ident.item.name_utf8());
+                        continue;
+                    }
+                    _ => {
+                        continue;
+                    }
+                };
+                if units.contains_key(&id) {
+                    continue;
+                }
+
+                let entity_kind = match kind {
+                    UnitDocKind::Entity => NamedEntityKind::Entity,
+                    UnitDocKind::Package => NamedEntityKind::Package,
+                };
+                let (generics, ports) = entity_interface(library_unit);
+                let declarations = match library_unit {
+                    LibraryUnit::PackageDeclaration { ref decl, .. } => decl
+                        .iter()
+                        .filter_map(|decl| declared_entities(decl).into_iter().next())
+                        .map(|entity| item_doc(&entity))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                units.insert(
+                    id.clone(),
+                    UnitDoc {
+                        name: ident.item.name_utf8(),
+                        kind,
+                        comment: declaration_text(&unit_entity(ident, entity_kind))
+                            .and_then(|text| text.comment),
+                        generics: generics
+                            .iter()
+                            .map(|decl| item_doc(&interface_entity(decl, NamedEntityKind::Generic)))
+                            .collect(),
+                        ports: ports
+                            .iter()
+                            .map(|decl| item_doc(&interface_entity(decl, NamedEntityKind::Port)))
+                            .collect(),
+                        declarations,
+                        architectures: Vec::new(),
+                        instances: instances
+                            .get(&id)
+                            .map(|instances| instances.iter().cloned().collect())
+                            .unwrap_or_default(),
+                        id,
+                    },
+                );
+            }
+        }
+
+        for (id, names) in architectures {
+            if let Some(unit) = units.get_mut(&id) {
+                unit.architectures = names;
+            }
+        }
+        Documentation {
+            units: units.into_values().collect(),
+        }
+    }
+
+    pub fn get(&self, id: &UnitId) -> Option<&UnitDoc> {
+        self.units.iter().find(|unit| unit.id == *id)
+    }
+
+    /// The libraries and their documented units
+    pub fn libraries(&self) -> BTreeMap<&str, Vec<&UnitDoc>> {
+        let mut libraries: BTreeMap<&str, Vec<&UnitDoc>> = BTreeMap::new();
+        for unit in self.units.iter() {
+            libraries
+                .entry(unit.id.library_name.as_str())
+                .or_default()
+                .push(unit);
+        }
+        libraries
+    }
+
+    /// The entities which instantiate the unit
+    pub fn instantiated_by(&self, id: &UnitId) -> Vec<&UnitDoc> {
+        self.units
+            .iter()
+            .filter(|unit| unit.instances.contains(id))
+            .collect()
+    }
+
+    /// Entities with instances which are not instantiated themselves
+    pub fn top_units(&self) -> Vec<&UnitDoc> {
+        let instantiated: BTreeSet<&UnitId> = self
+            .units
+            .iter()
+            .flat_map(|unit| unit.instances.iter())
+            .collect();
+        self.units
+            .iter()
+            .filter(|unit| !unit.instances.is_empty() && !instantiated.contains(&unit.id))
+            .collect()
+    }
+}
+
+/// The path of the page of a unit relative to the output directory
+pub fn unit_path(id: &UnitId, extension: &str) -> String {
+    format!("{}/{}.{}", id.library_name, id.primary_name(), extension)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A link to the page of the unit when documented
+fn html_unit_link(doc: &Documentation, id: &UnitId, root: &str) -> String {
+    match doc.get(id) {
+        Some(unit) => format!(
+            "<a href=\"{}{}\">{}.{}</a>",
+            root,
+            unit_path(id, "html"),
+            escape_html(&id.library_name),
+            escape_html(&unit.name)
+        ),
+        None => escape_html(&id.to_string()),
+    }
+}
+
+fn html_page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+pre {{ background: #f4f4f4; padding: 0.5em; margin: 0; }}
+td {{ vertical-align: top; padding: 0.25em 1em 0.25em 0; }}
+</style>
+</head>
+<body>
+<p><a href=\"{root}index.html\">Index</a></p>
+{body}</body>
+</html>
+",
+        title = escape_html(title),
+        root = root,
+        body = body
+    )
+}
+
+fn html_comment(comment: &Option<String>) -> String {
+    match comment {
+        Some(comment) => format!("<p>{}</p>\n", escape_html(comment).replace('\n', "<br>\n")),
+        None => String::new(),
+    }
+}
+
+fn html_items(heading: &str, items: &[ItemDoc]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut html = format!("<h2>{}</h2>\n<table>\n", heading);
+    for item in items {
+        html.push_str(&format!(
+            "<tr id=\"{}\"><td><pre>{}</pre></td><td>{}</td></tr>\n",
+            escape_html(&item.name.to_lowercase()),
+            escape_html(&item.text),
+            item.comment
+                .as_ref()
+                .map(|comment| escape_html(comment).replace('\n', "<br>"))
+                .unwrap_or_default()
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn html_list(heading: &str, items: &[String]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut html = format!("<h2>{}</h2>\n<ul>\n", heading);
+    for item in items {
+        html.push_str(&format!("<li>{}</li>\n", item));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+fn html_unit_page(doc: &Documentation, unit: &UnitDoc) -> String {
+    let root = "../";
+    let mut body = format!(
+        "<h1>{} {}.{}</h1>\n",
+        unit.kind.title(),
+        escape_html(&unit.id.library_name),
+        escape_html(&unit.name)
+    );
+    body.push_str(&html_comment(&unit.comment));
+    body.push_str(&html_items("Generics", &unit.generics));
+    body.push_str(&html_items("Ports", &unit.ports));
+    body.push_str(&html_items("Declarations", &unit.declarations));
+    let architectures: Vec<String> = unit
+        .architectures
+        .iter()
+        .map(|name| escape_html(name))
+        .collect();
+    body.push_str(&html_list("Architectures", &architectures));
+    let instances: Vec<String> = unit
+        .instances
+        .iter()
+        .map(|id| html_unit_link(doc, id, root))
+        .collect();
+    body.push_str(&html_list("Instantiates", &instances));
+    let instantiated_by: Vec<String> = doc
+        .instantiated_by(&unit.id)
+        .into_iter()
+        .map(|parent| html_unit_link(doc, &parent.id, root))
+        .collect();
+    body.push_str(&html_list("Instantiated by", &instantiated_by));
+    html_page(
+        &format!("{}.{}", unit.id.library_name, unit.name),
+        root,
+        &body,
+    )
+}
+
+/// Nested lists of the instances below the unit where cycles are cut
+fn html_hierarchy(doc: &Documentation, id: &UnitId, path: &mut Vec<UnitId>) -> String {
+    let mut html = format!("<li>{}", html_unit_link(doc, id, ""));
+    if !path.contains(id) {
+        if let Some(unit) = doc.get(id) {
+            if !unit.instances.is_empty() {
+                path.push(id.clone());
+                html.push_str("\n<ul>\n");
+                for instance in unit.instances.iter() {
+                    html.push_str(&html_hierarchy(doc, instance, path));
+                }
+                html.push_str("</ul>\n");
+                path.pop();
+            }
+        }
+    }
+    html.push_str("</li>\n");
+    html
+}
+
+fn html_index(doc: &Documentation) -> String {
+    let mut body = String::from("<h1>Documentation</h1>\n");
+    for (library_name, units) in doc.libraries() {
+        let links: Vec<String> = units
+            .iter()
+            .map(|unit| html_unit_link(doc, &unit.id, ""))
+            .collect();
+        body.push_str(&html_list(&escape_html(library_name), &links));
+    }
+    let top_units = doc.top_units();
+    if !top_units.is_empty() {
+        body.push_str("<h2>Hierarchy</h2>\n<ul>\n");
+        for unit in top_units {
+            body.push_str(&html_hierarchy(doc, &unit.id, &mut Vec::new()));
+        }
+        body.push_str("</ul>\n");
+    }
+    html_page("Documentation", "", &body)
+}
+
+/// The index and the page of each unit by path relative to the output directory
+pub fn html_pages(doc: &Documentation) -> Vec<(String, String)> {
+    let mut pages = vec![("index.html".to_owned(), html_index(doc))];
+    for unit in doc.units.iter() {
+        pages.push((unit_path(&unit.id, "html"), html_unit_page(doc, unit)));
+    }
+    pages
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for chr in text.chars() {
+        if "\\`*_[]<>#".contains(chr) {
+            escaped.push('\\');
+        }
+        escaped.push(chr);
+    }
+    escaped
+}
+
+/// A link to the page of the unit when documented
+fn markdown_unit_link(doc: &Documentation, id: &UnitId, root: &str) -> String {
+    match doc.get(id) {
+        Some(unit) => format!(
+            "[{}.{}]({}{})",
+            escape_markdown(&id.library_name),
+            escape_markdown(&unit.name),
+            root,
+            unit_path(id, "md")
+        ),
+        None => escape_markdown(&id.to_string()),
+    }
+}
+
+fn markdown_list(heading: &str, items: &[String]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut markdown = format!("## {}\n\n", heading);
+    for item in items {
+        markdown.push_str(&format!("- {}\n", item));
+    }
+    markdown.push('\n');
+    markdown
+}
+
+/// Each item has an anchor of its lowercase name followed by its declaration and comment
+fn markdown_items(heading: &str, items: &[ItemDoc]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut markdown = format!("## {}\n\n", heading);
+    for item in items {
+        markdown.push_str(&format!(
+            "<a id=\"{}\"></a>\n\n```vhdl\n{}\n```\n\n",
+            escape_html(&item.name.to_lowercase()),
+            item.text
+        ));
+        if let Some(ref comment) = item.comment {
+            markdown.push_str(comment);
+            markdown.push_str("\n\n");
+        }
+    }
+    markdown
+}
+
+fn markdown_unit_page(doc: &Documentation, unit: &UnitDoc) -> String {
+    let root = "../";
+    let mut markdown = format!(
+        "# {} {}.{}\n\n",
+        unit.kind.title(),
+        escape_markdown(&unit.id.library_name),
+        escape_markdown(&unit.name)
+    );
+    if let Some(ref comment) = unit.comment {
+        markdown.push_str(comment);
+        markdown.push_str("\n\n");
+    }
+    markdown.push_str(&markdown_items("Generics", &unit.generics));
+    markdown.push_str(&markdown_items("Ports", &unit.ports));
+    markdown.push_str(&markdown_items("Declarations", &unit.declarations));
+    let architectures: Vec<String> = unit
+        .architectures
+        .iter()
+        .map(|name| escape_markdown(name))
+        .collect();
+    markdown.push_str(&markdown_list("Architectures", &architectures));
+    let instances: Vec<String> = unit
+        .instances
+        .iter()
+        .map(|id| markdown_unit_link(doc, id, root))
+        .collect();
+    markdown.push_str(&markdown_list("Instantiates", &instances));
+    let instantiated_by: Vec<String> = doc
+        .instantiated_by(&unit.id)
+        .into_iter()
+        .map(|parent| markdown_unit_link(doc, &parent.id, root))
+        .collect();
+    markdown.push_str(&markdown_list("Instantiated by", &instantiated_by));
+    markdown
+}
+
+fn markdown_library_page(doc: &Documentation, library_name: &str, units: &[&UnitDoc]) -> String {
+    let links: Vec<String> = units
+        .iter()
+        .map(|unit| {
+            format!(
+                "{} {}",
+                unit.kind.title(),
+                markdown_unit_link(doc, &unit.id, "")
+            )
+        })
+        .collect();
+    let mut markdown = format!("# Library {}\n\n", escape_markdown(library_name));
+    markdown.push_str(&markdown_list("Design units", &links));
+    markdown
+}
+
+/// Nested lists of the instances below the unit where cycles are cut
+fn markdown_hierarchy(
+    doc: &Documentation,
+    id: &UnitId,
+    path: &mut Vec<UnitId>,
+    markdown: &mut String,
+) {
+    markdown.push_str(&format!(
+        "{}- {}\n",
+        "  ".repeat(path.len()),
+        markdown_unit_link(doc, id, "")
+    ));
+    if path.contains(id) {
+        return;
+    }
+    if let Some(unit) = doc.get(id) {
+        path.push(id.clone());
+        for instance in unit.instances.iter() {
+            markdown_hierarchy(doc, instance, path, markdown);
+        }
+        path.pop();
+    }
+}
+
+fn markdown_index(doc: &Documentation) -> String {
+    let mut markdown = String::from("# Documentation\n\n");
+    let libraries: Vec<String> = doc
+        .libraries()
+        .keys()
+        .map(|library_name| format!("[{}]({}.md)", escape_markdown(library_name), library_name))
+        .collect();
+    markdown.push_str(&markdown_list("Libraries", &libraries));
+    let top_units = doc.top_units();
+    if !top_units.is_empty() {
+        markdown.push_str("## Hierarchy\n\n");
+        for unit in top_units {
+            markdown_hierarchy(doc, &unit.id, &mut Vec::new(), &mut markdown);
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// The chapters of an mdBook
+fn markdown_summary(doc: &Documentation) -> String {
+    let mut markdown = String::from("# Summary\n\n[Documentation](index.md)\n\n");
+    for (library_name, units) in doc.libraries() {
+        markdown.push_str(&format!(
+            "- [{}]({}.md)\n",
+            escape_markdown(library_name),
+            library_name
+        ));
+        for unit in units {
+            markdown.push_str(&format!(
+                "  - [{}]({})\n",
+                escape_markdown(&unit.name),
+                unit_path(&unit.id, "md")
+            ));
+        }
+    }
+    markdown
+}
+
+/// The index, summary, the page of each library and the page of each unit
+pub fn markdown_pages(doc: &Documentation) -> Vec<(String, String)> {
+    let mut pages = vec![
+        ("index.md".to_owned(), markdown_index(doc)),
+        ("SUMMARY.md".to_owned(), markdown_summary(doc)),
+    ];
+    for (library_name, units) in doc.libraries() {
+        pages.push((
+            format!("{}.md", library_name),
+            markdown_library_page(doc, library_name, &units),
+        ));
+    }
+    for unit in doc.units.iter() {
+        pages.push((unit_path(&unit.id, "md"), markdown_unit_page(doc, unit)));
+    }
+    pages
+}
+
+/// The pages of the format by path relative to the output directory
+pub fn pages(doc: &Documentation, format: DocFormat) -> Vec<(String, String)> {
+    match format {
+        DocFormat::Html => html_pages(doc),
+        DocFormat::Markdown => markdown_pages(doc),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+-- The top level
+entity top is
+  generic (width : natural := 8);
+  port (
+    clk : in bit; -- The clock
+    data : out bit_vector(width - 1 downto 0));
+end entity;
+
+architecture rtl of top is
+begin
+  inst: entity work.leaf;
+end architecture;
+
+entity leaf is
+end entity;",
+        );
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  -- A <small> constant
+  constant c : natural := 0;
+end package;",
+        );
+        project
+    }
+
+    #[test]
+    fn documentation_of_units() {
+        let project = project();
+        let doc = Documentation::new(&project.project);
+        let ids: Vec<String> = doc.units.iter().map(|unit| unit.id.to_string()).collect();
+        assert_eq!(ids, vec!["lib.leaf", "lib.pkg", "lib.top"]);
+
+        let top = doc.get(&UnitId::primary("lib", "top")).unwrap();
+        assert_eq!(top.kind, UnitDocKind::Entity);
+        assert_eq!(top.comment, Some("The top level".to_owned()));
+        assert_eq!(
+            top.generics,
+            vec![ItemDoc {
+                name: "width".to_owned(),
+                text: "width : natural := 8".to_owned(),
+                comment: None,
+            }]
+        );
+        assert_eq!(top.ports.len(), 2);
+        assert_eq!(top.ports[0].comment, Some("The clock".to_owned()));
+        assert_eq!(top.architectures, vec!["rtl"]);
+        assert_eq!(top.instances, vec![UnitId::primary("lib", "leaf")]);
+
+        let pkg = doc.get(&UnitId::primary("lib", "pkg")).unwrap();
+        assert_eq!(pkg.kind, UnitDocKind::Package);
+        assert_eq!(pkg.declarations.len(), 1);
+        assert_eq!(
+            pkg.declarations[0].comment,
+            Some("A <small> constant".to_owned())
+        );
+
+        let leaf_id = UnitId::primary("lib", "leaf");
+        assert_eq!(doc.instantiated_by(&leaf_id), vec![top]);
+        assert_eq!(doc.top_units(), vec![top]);
+    }
+
+    #[test]
+    fn cross_linked_html_pages() {
+        let project = project();
+        let doc = Documentation::new(&project.project);
+        let pages: BTreeMap<String, String> = html_pages(&doc).into_iter().collect();
+        let paths: Vec<&str> = pages.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "index.html",
+                "lib/leaf.html",
+                "lib/pkg.html",
+                "lib/top.html"
+            ]
+        );
+
+        let index = &pages["index.html"];
+        assert!(index.contains("<h2>Hierarchy</h2>"));
+        assert!(index.contains(
+            "<li><a href=\"lib/top.html\">lib.top</a>\n\
+             <ul>\n\
+             <li><a href=\"lib/leaf.html\">lib.leaf</a></li>"
+        ));
+
+        let top = &pages["lib/top.html"];
+        assert!(top.contains("<h1>Entity lib.top</h1>"));
+        assert!(
+            top.contains("<tr id=\"clk\"><td><pre>clk : in bit</pre></td><td>The clock</td></tr>")
+        );
+        assert!(top.contains(
+            "<h2>Instantiates</h2>\n<ul>\n<li><a href=\"../lib/leaf.html\">lib.leaf</a></li>"
+        ));
+
+        let leaf = &pages["lib/leaf.html"];
+        assert!(leaf.contains(
+            "<h2>Instantiated by</h2>\n<ul>\n<li><a href=\"../lib/top.html\">lib.top</a></li>"
+        ));
+
+        assert!(pages["lib/pkg.html"].contains("A &lt;small&gt; constant"));
+    }
+
+    #[test]
+    fn cross_linked_markdown_pages() {
+        let project = project();
+        let doc = Documentation::new(&project.project);
+        let pages: BTreeMap<String, String> =
+            pages(&doc, DocFormat::Markdown).into_iter().collect();
+        let paths: Vec<&str> = pages.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "SUMMARY.md",
+                "index.md",
+                "lib.md",
+                "lib/leaf.md",
+                "lib/pkg.md",
+                "lib/top.md"
+            ]
+        );
+
+        assert_eq!(
+            pages["SUMMARY.md"],
+            "# Summary
+
+[Documentation](index.md)
+
+- [lib](lib.md)
+  - [leaf](lib/leaf.md)
+  - [pkg](lib/pkg.md)
+  - [top](lib/top.md)
+"
+        );
+        assert!(pages["index.md"]
+            .contains("## Hierarchy\n\n- [lib.top](lib/top.md)\n  - [lib.leaf](lib/leaf.md)\n"));
+        assert!(pages["lib.md"].contains("- Entity [lib.top](lib/top.md)\n"));
+
+        let top = &pages["lib/top.md"];
+        assert!(top.starts_with("# Entity lib.top\n\nThe top level\n\n"));
+        assert!(top.contains("<a id=\"clk\"></a>\n\n```vhdl\nclk : in bit\n```\n\nThe clock\n"));
+        assert!(top.contains("## Instantiates\n\n- [lib.leaf](../lib/leaf.md)\n"));
+        let leaf = &pages["lib/leaf.md"];
+        assert!(leaf.contains("## Instantiated by\n\n- [lib.top](../lib/top.md)\n"));
+    }
+}
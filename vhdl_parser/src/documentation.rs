@@ -0,0 +1,247 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The source text and comments of declarations
+
+use latin_1::Latin1String;
+use name_resolution::{NamedEntity, NamedEntityKind};
+use source_tokens::SourceTokens;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct DeclarationText {
+    /// The declaration as written in the source without common indentation
+    pub text: String,
+    /// The comment lines directly before the declaration and a trailing comment on its last line
+    pub comment: Option<String>,
+}
+
+/// The start of the line containing the offset
+fn line_start(bytes: &[u8], offset: usize) -> usize {
+    bytes[..offset]
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+fn line_end(bytes: &[u8], offset: usize) -> usize {
+    bytes[offset..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map(|i| offset + i)
+        .unwrap_or_else(|| bytes.len())
+}
+
+fn to_string(bytes: &[u8]) -> String {
+    Latin1String::new(bytes).to_string()
+}
+
+/// Remove the indentation of the first line from all lines
+fn dedent(text: &str, indent: usize) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            let whitespace = line.len() - line.trim_start().len();
+            &line[whitespace.min(indent)..]
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn comment_text(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix("--").map(|comment| comment.trim())
+}
+
+/// The comment lines directly above the line containing the start
+fn leading_comment(bytes: &[u8], start: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line_begin = line_start(bytes, start);
+    while line_begin > 0 {
+        let previous_begin = line_start(bytes, line_begin - 1);
+        let line = to_string(&bytes[previous_begin..line_begin - 1]);
+        match comment_text(&line) {
+            Some(comment) => lines.push(comment.to_owned()),
+            None => break,
+        }
+        line_begin = previous_begin;
+    }
+    lines.reverse();
+    lines
+}
+
+/// The source text and comments of the declaration of a named entity
+pub fn declaration_text(entity: &NamedEntity) -> Option<DeclarationText> {
+    let source_tokens = SourceTokens::new(&entity.pos.source)?;
+    let i = source_tokens.index_of(&entity.pos)?;
+    let (first, last) = source_tokens.declaration_extent(i, entity.kind);
+    let tokens = &source_tokens.tokens;
+    let bytes = &source_tokens.code.bytes;
+    let start = tokens[first].pos.start;
+    let end = tokens[last].pos.start + tokens[last].pos.length;
+
+    let mut text = dedent(
+        &to_string(&bytes[start..end]),
+        start - line_start(bytes, start),
+    );
+    match entity.kind {
+        NamedEntityKind::Entity => text.push_str("\nend entity;"),
+        NamedEntityKind::Component => text.push_str("\nend component;"),
+        _ => {}
+    }
+
+    // Only declarations which start their line are preceded by their own comment
+    let line_begin = line_start(bytes, start);
+    let mut comment = if bytes[line_begin..start].iter().all(u8::is_ascii_whitespace) {
+        leading_comment(bytes, start)
+    } else {
+        Vec::new()
+    };
+    // The delimiter after an interface element comes before its trailing comment
+    let trailing = to_string(&bytes[end..line_end(bytes, end)]);
+    let trailing =
+        trailing.trim_start_matches(|chr: char| chr.is_whitespace() || chr == ';' || chr == ')');
+    if let Some(trailing) = comment_text(trailing) {
+        comment.push(trailing.to_owned());
+    }
+
+    Some(DeclarationText {
+        text,
+        comment: if comment.is_empty() {
+            None
+        } else {
+            Some(comment.join("\n"))
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use library::DesignRoot;
+    use name_resolution::NameResolution;
+    use test_util::TempProject;
+
+    /// The declaration text of each declaration of the code by designator
+    fn declaration_texts(code: &str) -> Vec<(String, DeclarationText)> {
+        let mut project = TempProject::new();
+        project.add_file("lib", "file.vhd", code);
+        let files = project.project.files();
+        let resolution = NameResolution::new(&DesignRoot::new(&files));
+        resolution
+            .declarations()
+            .iter()
+            .map(|entity| (entity.designator.clone(), declaration_text(entity).unwrap()))
+            .collect()
+    }
+
+    fn text(texts: &[(String, DeclarationText)], designator: &str) -> DeclarationText {
+        texts
+            .iter()
+            .find(|(name, _)| name == designator)
+            .map(|(_, text)| text.clone())
+            .unwrap()
+    }
+
+    fn uncommented(text: &str) -> DeclarationText {
+        DeclarationText {
+            text: text.to_owned(),
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn entity_with_ports_and_comments() {
+        let texts = declaration_texts(
+            "
+-- The device
+-- under test
+entity dut is
+  generic (width : natural := 8);
+  port (
+    clk : in bit; -- The clock
+    data, valid : out bit_vector(width - 1 downto 0));
+begin
+  assert true;
+end entity;",
+        );
+
+        assert_eq!(
+            text(&texts, "dut"),
+            DeclarationText {
+                text: "entity dut is
+  generic (width : natural := 8);
+  port (
+    clk : in bit; -- The clock
+    data, valid : out bit_vector(width - 1 downto 0));
+end entity;"
+                    .to_owned(),
+                comment: Some("The device\nunder test".to_owned()),
+            }
+        );
+        assert_eq!(
+            text(&texts, "clk"),
+            DeclarationText {
+                text: "clk : in bit".to_owned(),
+                comment: Some("The clock".to_owned()),
+            }
+        );
+        assert_eq!(
+            text(&texts, "valid"),
+            uncommented("data, valid : out bit_vector(width - 1 downto 0)")
+        );
+        assert_eq!(text(&texts, "width"), uncommented("width : natural := 8"));
+    }
+
+    #[test]
+    fn declarations_of_architecture() {
+        let texts = declaration_texts(
+            "
+architecture rtl of ent is
+  -- A record
+  type rec_t is record
+    field : natural;
+  end record;
+  signal foo : rec_t; -- Foo
+  impure function fun(arg : natural) return natural is
+  begin
+  end function;
+begin
+  main : process
+  begin
+    for i in 0 to 1 loop
+    end loop;
+  end process;
+end architecture;",
+        );
+
+        assert_eq!(
+            text(&texts, "rtl"),
+            uncommented("architecture rtl of ent is")
+        );
+        assert_eq!(
+            text(&texts, "rec_t"),
+            DeclarationText {
+                text: "type rec_t is record\n  field : natural;\nend record;".to_owned(),
+                comment: Some("A record".to_owned()),
+            }
+        );
+        assert_eq!(
+            text(&texts, "foo"),
+            DeclarationText {
+                text: "signal foo : rec_t;".to_owned(),
+                comment: Some("Foo".to_owned()),
+            }
+        );
+        assert_eq!(
+            text(&texts, "fun"),
+            uncommented("impure function fun(arg : natural) return natural")
+        );
+        assert_eq!(text(&texts, "arg"), uncommented("arg : natural"));
+        assert_eq!(text(&texts, "main"), uncommented("main : process"));
+        assert_eq!(text(&texts, "i"), uncommented("for i in 0 to 1"));
+    }
+}
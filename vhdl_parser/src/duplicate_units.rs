@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Design units which are declared in more than one file or library
+
+use library::{unit_ident, UnitId, UnitKey};
+use project::SourceFile;
+use source::SrcPos;
+use std::collections::BTreeMap;
+
+/// A design unit declared more than once within the project
+#[derive(PartialEq, Debug, Clone)]
+pub struct DuplicateUnit {
+    /// The unit within its library such as ent, ent(rtl) or pkg body
+    pub key: UnitKey,
+    /// The library and position of each declaration in file order
+    pub declarations: Vec<(String, SrcPos)>,
+}
+
+/// The design units with the same name declared more than once regardless of the library,
+/// a copy in another library is as likely to be stale as one within the same library
+pub fn duplicate_units(files: &[&SourceFile]) -> Vec<DuplicateUnit> {
+    let mut declarations: BTreeMap<UnitKey, Vec<(String, SrcPos)>> = BTreeMap::new();
+    for file in files {
+        for design_unit in file.design_file.design_units.iter() {
+            let unit_id = UnitId::new(&file.library_name, design_unit);
+            let pos = unit_ident(&design_unit.library_unit).pos.clone();
+            declarations
+                .entry(unit_id.key)
+                .or_default()
+                .push((unit_id.library_name, pos));
+        }
+    }
+    declarations
+        .into_iter()
+        .filter(|(_, declarations)| declarations.len() > 1)
+        .map(|(key, declarations)| DuplicateUnit { key, declarations })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn units_declared_in_several_files_or_libraries() {
+        let mut project = TempProject::new();
+        let first = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+end architecture;
+
+package pkg is
+end package;",
+        );
+        let copy = project.add_file(
+            "lib",
+            "copy.vhd",
+            "
+architecture rtl of ent is
+begin
+end architecture;",
+        );
+        let other = project.add_file(
+            "other",
+            "old.vhd",
+            "
+entity ENT is
+end entity;",
+        );
+
+        let files = project.project.files();
+        let duplicates = duplicate_units(&files);
+        let summary: Vec<(String, Vec<(String, String)>)> = duplicates
+            .iter()
+            .map(|duplicate| {
+                let declarations = duplicate
+                    .declarations
+                    .iter()
+                    .map(|(library_name, pos)| {
+                        let file_name = pos.source.file_name().unwrap().to_owned();
+                        (library_name.clone(), file_name)
+                    })
+                    .collect();
+                (duplicate.key.to_string(), declarations)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "ent".to_owned(),
+                    vec![
+                        ("lib".to_owned(), first.clone()),
+                        ("other".to_owned(), other.clone())
+                    ]
+                ),
+                (
+                    "ent(rtl)".to_owned(),
+                    vec![("lib".to_owned(), copy.clone()), ("lib".to_owned(), first)]
+                ),
+            ]
+        );
+    }
+}
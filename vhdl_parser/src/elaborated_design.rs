@@ -0,0 +1,383 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Queries of the elaborated hierarchy below a top level entity for chip level scripts
+//! such as the extraction of a pinout or the collection of register maps
+//!
+//! Instances are looked up by their hierarchical paths such as top.cpu.gen.alu, ignoring
+//! case. The generics of each instance have the static values of their actuals, evaluated
+//! with the generics and constants of the enclosing instance, or of their defaults. The
+//! ports are connected to the nets of the netlist
+
+use ast::{AssociationElement, Declaration, LibraryUnit};
+use instance_tree::{InstanceHierarchy, InstanceNode};
+use library::UnitId;
+use name_resolution::entity_interface;
+use netlist::{netlist, Net, NetlistInstance, NetlistPort};
+use project::Project;
+use static_value::{declare_constants, generic_values, generic_values_in, Constants, Value};
+use std::collections::BTreeMap;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ElaboratedGeneric {
+    pub name: String,
+    /// The actual or default as written
+    pub expression: String,
+    /// The value when it is static
+    pub value: Option<Value>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ElaboratedInstance {
+    /// The hierarchical name such as top.cpu.gen.alu
+    pub path: String,
+    /// The entity and architecture such as lib.core(fast), the Verilog module or the
+    /// instantiated unit as written
+    pub unit: String,
+    /// The entity bound to the instance, None when it is not within the project
+    pub entity: Option<UnitId>,
+    pub architecture: Option<String>,
+    pub generics: Vec<ElaboratedGeneric>,
+    pub ports: Vec<NetlistPort>,
+    /// The index of the enclosing instance, None for the top
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+impl ElaboratedInstance {
+    /// The generic of the name ignoring case
+    pub fn generic(&self, name: &str) -> Option<&ElaboratedGeneric> {
+        self.generics
+            .iter()
+            .find(|generic| generic.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The port of the name ignoring case
+    pub fn port(&self, name: &str) -> Option<&NetlistPort> {
+        self.ports
+            .iter()
+            .find(|port| port.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// The instances below a top level entity with the nets connecting their ports
+pub struct ElaboratedDesign {
+    /// The instances in depth first order starting with the top
+    instances: Vec<ElaboratedInstance>,
+    /// The index of each instance by its lower case path
+    paths: BTreeMap<String, usize>,
+    nets: Vec<Net>,
+}
+
+impl ElaboratedDesign {
+    /// The design below the top level entity, None when it is not within the project
+    pub fn new(project: &Project, top: &UnitId) -> Option<ElaboratedDesign> {
+        let netlist = netlist(project, top)?;
+        let files = project.files();
+        let verilog_files = project.verilog_files();
+        let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+        let tree = hierarchy.tree(top, None)?;
+
+        let mut declarations = BTreeMap::new();
+        for file in files.iter() {
+            for design_unit in file.design_file.design_units.iter() {
+                if let LibraryUnit::ArchitectureBody { ref decl, .. } = design_unit.library_unit {
+                    declarations.insert(
+                        UnitId::new(&file.library_name, design_unit),
+                        decl.as_slice(),
+                    );
+                }
+            }
+        }
+        let mut generic_maps = BTreeMap::new();
+        for instantiation in hierarchy.instantiations() {
+            generic_maps.insert(
+                (instantiation.architecture, instantiation.label),
+                instantiation.instance.generic_map.as_slice(),
+            );
+        }
+
+        let mut resolver = Resolver {
+            hierarchy: &hierarchy,
+            declarations,
+            generic_maps,
+            constants: Vec::new(),
+        };
+        resolver.resolve(&tree, None, None);
+
+        // The netlist has the instances of the tree in the same depth first order
+        let mut design = ElaboratedDesign {
+            instances: Vec::new(),
+            paths: BTreeMap::new(),
+            nets: netlist.nets,
+        };
+        design.add(
+            &tree,
+            None,
+            &mut netlist.instances.into_iter(),
+            &mut resolver.constants.into_iter(),
+        );
+        Some(design)
+    }
+
+    fn add(
+        &mut self,
+        node: &InstanceNode,
+        parent: Option<usize>,
+        netlist_instances: &mut dyn Iterator<Item = NetlistInstance>,
+        constants: &mut dyn Iterator<Item = Constants>,
+    ) {
+        let (instance, constants_of_node) = match (netlist_instances.next(), constants.next()) {
+            (Some(instance), Some(constants)) => (instance, constants),
+            _ => return,
+        };
+        let index = self.instances.len();
+        self.paths.insert(instance.path.to_lowercase(), index);
+        if let Some(parent) = parent {
+            self.instances[parent].children.push(index);
+        }
+        self.instances.push(ElaboratedInstance {
+            path: instance.path,
+            unit: instance.unit,
+            entity: node.entity.clone(),
+            architecture: node.architecture.clone(),
+            generics: node
+                .generics
+                .iter()
+                .map(|(name, expression)| ElaboratedGeneric {
+                    name: name.clone(),
+                    expression: expression.clone(),
+                    value: constants_of_node.get(&name.to_lowercase()).cloned(),
+                })
+                .collect(),
+            ports: instance.ports,
+            parent,
+            children: Vec::new(),
+        });
+        for child in node.children.iter() {
+            self.add(child, Some(index), netlist_instances, constants);
+        }
+    }
+
+    /// The top level instance
+    pub fn top(&self) -> &ElaboratedInstance {
+        &self.instances[0]
+    }
+
+    /// The instances in depth first order starting with the top
+    pub fn instances(&self) -> &[ElaboratedInstance] {
+        &self.instances
+    }
+
+    /// The instance of the hierarchical path ignoring case
+    pub fn instance(&self, path: &str) -> Option<&ElaboratedInstance> {
+        self.paths
+            .get(&path.to_lowercase())
+            .map(|index| &self.instances[*index])
+    }
+
+    /// The instance enclosing the instance, None for the top
+    pub fn parent(&self, instance: &ElaboratedInstance) -> Option<&ElaboratedInstance> {
+        instance.parent.map(|index| &self.instances[index])
+    }
+
+    /// The instances directly within the architecture of the instance
+    pub fn children<'s>(&'s self, instance: &ElaboratedInstance) -> Vec<&'s ElaboratedInstance> {
+        instance
+            .children
+            .iter()
+            .map(|index| &self.instances[*index])
+            .collect()
+    }
+
+    /// The nets in the order of their declaration
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+
+    /// The net of the hierarchical name ignoring case such as top.clk
+    pub fn net(&self, name: &str) -> Option<&Net> {
+        self.nets
+            .iter()
+            .find(|net| net.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Evaluates the generics of the instances of the tree in depth first order
+struct Resolver<'h, 'a: 'h> {
+    hierarchy: &'h InstanceHierarchy<'a>,
+    declarations: BTreeMap<UnitId, &'a [Declaration]>,
+    /// The generic map of each instantiation by its architecture and label
+    generic_maps: BTreeMap<(UnitId, String), &'a [AssociationElement]>,
+    /// The generics of each instance
+    constants: Vec<Constants>,
+}
+
+impl<'h, 'a: 'h> Resolver<'h, 'a> {
+    /// Evaluates the generics of the node where the actuals are evaluated with the
+    /// generics and constants of the architecture enclosing the instance
+    fn resolve(
+        &mut self,
+        node: &InstanceNode,
+        enclosing: Option<&Constants>,
+        generic_map: Option<&'a [AssociationElement]>,
+    ) {
+        let generic_list = node
+            .entity
+            .as_ref()
+            .and_then(|entity| self.hierarchy.entity(entity))
+            .map(|library_unit| entity_interface(library_unit).0)
+            .unwrap_or(&[]);
+        let generics = match (enclosing, generic_map) {
+            (Some(enclosing), Some(generic_map)) => {
+                generic_values_in(generic_list, generic_map, enclosing)
+            }
+            _ => generic_values(generic_list, &[]),
+        };
+        self.constants.push(generics.clone());
+
+        let architecture_id = match (&node.entity, &node.architecture) {
+            (Some(entity), Some(architecture)) => {
+                UnitId::architecture(&entity.library_name, entity.primary_name(), architecture)
+            }
+            _ => UnitId::primary("", ""),
+        };
+        let mut constants = generics;
+        if let Some(decl) = self.declarations.get(&architecture_id) {
+            declare_constants(decl, &mut constants);
+        }
+        for child in node.children.iter() {
+            let generic_map = self
+                .generic_maps
+                .get(&(architecture_id.clone(), child.label.clone()))
+                .cloned();
+            self.resolve(child, Some(&constants), generic_map);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn query_instances_generics_and_ports() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+entity top is
+  port (clk : in bit; pins : out bit_vector(3 downto 0));
+end entity;
+
+architecture rtl of top is
+  constant base : natural := 16#100#;
+  signal irq : bit;
+begin
+  uart0: entity work.uart generic map (address => base, name => \"uart0\") port map (clk, irq);
+  gen: for i in 0 to 1 generate
+    timer: entity work.timer generic map (address => base + 16 * i) port map (clk => clk);
+  end generate;
+end architecture;
+
+entity uart is
+  generic (address : natural; name : string; fifo_depth : natural := 16);
+  port (clk : in bit; irq : out bit);
+end entity;
+
+architecture rtl of uart is
+  constant fifo_address : natural := address + 8;
+begin
+  fifo: entity work.fifo generic map (address => fifo_address, depth => fifo_depth * 2)
+    port map (clk => clk);
+end architecture;
+
+entity fifo is
+  generic (address : natural; depth : natural);
+  port (clk : in bit);
+end entity;
+
+architecture rtl of fifo is
+begin
+end architecture;
+
+entity timer is
+  generic (address : natural);
+  port (clk : in bit);
+end entity;
+
+architecture rtl of timer is
+begin
+end architecture;",
+        );
+        let design =
+            ElaboratedDesign::new(&project.project, &UnitId::primary("lib", "top")).unwrap();
+
+        let paths: Vec<&str> = design
+            .instances()
+            .iter()
+            .map(|instance| instance.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["top", "top.uart0", "top.uart0.fifo", "top.gen.timer"]
+        );
+        assert_eq!(design.top().path, "top");
+
+        let uart = design.instance("TOP.Uart0").unwrap();
+        assert_eq!(uart.unit, "lib.uart(rtl)");
+        assert_eq!(uart.entity, Some(UnitId::primary("lib", "uart")));
+        assert_eq!(
+            uart.generic("address").unwrap().value,
+            Some(Value::Integer(256))
+        );
+        assert_eq!(
+            uart.generic("name").unwrap().value,
+            Some(Value::String(b"uart0".to_vec()))
+        );
+        assert_eq!(
+            uart.generic("fifo_depth").unwrap().value,
+            Some(Value::Integer(16))
+        );
+        assert_eq!(uart.port("irq").unwrap().net, "top.irq");
+        assert_eq!(uart.port("clk").unwrap().direction, Some("in"));
+        assert_eq!(design.parent(uart).unwrap().path, "top");
+
+        let fifo = design.instance("top.uart0.fifo").unwrap();
+        assert_eq!(design.children(uart), vec![fifo]);
+        assert_eq!(
+            fifo.generic("address").unwrap().value,
+            Some(Value::Integer(264))
+        );
+        assert_eq!(
+            fifo.generic("depth").unwrap().value,
+            Some(Value::Integer(32))
+        );
+        assert_eq!(fifo.port("clk").unwrap().net, "top.clk");
+
+        // The generate parameter is not static
+        let timer = design.instance("top.gen.timer").unwrap();
+        let address = timer.generic("address").unwrap();
+        assert_eq!(address.expression, "base + 16 * i");
+        assert_eq!(address.value, None);
+
+        assert_eq!(
+            design.net("top.clk").unwrap().ports,
+            vec![
+                "top.clk",
+                "top.uart0.clk",
+                "top.uart0.fifo.clk",
+                "top.gen.timer.clk"
+            ]
+        );
+        assert!(design.instance("top.missing").is_none());
+        assert!(
+            ElaboratedDesign::new(&project.project, &UnitId::primary("lib", "missing")).is_none()
+        );
+    }
+}
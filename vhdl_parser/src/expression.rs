@@ -5,9 +5,9 @@
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
 use ast::{
-    Allocator, Binary, Choice, Direction, DiscreteRange, ElementAssociation, Expression, Literal,
-    Name, QualifiedExpression, Range, RangeConstraint, ResolutionIndication, SubtypeConstraint,
-    SubtypeIndication, Unary,
+    Allocator, Binary, Choice, Choices, Direction, DiscreteRange, ElementAssociation, Expression,
+    Literal, Name, QualifiedExpression, Range, RangeConstraint, ResolutionIndication,
+    SubtypeConstraint, SubtypeIndication, Unary,
 };
 use message::{error, ParseResult};
 use names::{parse_name, parse_name_initial_token, to_selected_name};
@@ -57,8 +57,8 @@ fn kind_to_binary_op(kind: Kind) -> Option<(Binary, usize)> {
         Plus => Some((Binary::Plus, 5)),
         Minus => Some((Binary::Minus, 5)),
         Concat => Some((Binary::Concat, 5)),
-        Times => Some((Binary::Times, 5)),
 
+        Times => Some((Binary::Times, 7)),
         Div => Some((Binary::Div, 7)),
         Mod => Some((Binary::Mod, 7)),
         Rem => Some((Binary::Rem, 7)),
@@ -94,49 +94,47 @@ fn kind_to_prefix_unary_op(kind: Kind) -> Option<(Unary, usize)> {
 
 pub fn parse_aggregate_initial_choices(
     stream: &mut TokenStream,
-    choices: Vec<Choice>,
+    choices: Choices,
 ) -> ParseResult<WithPos<Vec<ElementAssociation>>> {
-    let token = stream.expect()?;
-
     let mut result = Vec::new();
-    match_token_kind!(
-        token,
-        RightPar => {
-            if let &[Choice::Expression(ref choice)] = choices.as_slice() {
-                result.push(ElementAssociation::Positional(choice.clone()));
-                Ok(WithPos::new(result, token))
-            } else {
-                return Err(error(&token, "Expected => after others"));
-            }
-        },
-        Comma => {
-            if let &[Choice::Expression(ref choice)] = choices.as_slice() {
-                result.push(ElementAssociation::Positional(choice.clone()));
-                let initial_choices = parse_choices(stream)?;
-                let mut rhs = parse_aggregate_initial_choices(stream, initial_choices)?;
-                result.append(&mut rhs.item);
-                Ok(WithPos::new(result, rhs))
-            } else {
-                return Err(error(&token, "Expected => after others"));
-            }
-        },
-        RightArrow => {
-            let rhs = parse_expression(stream)?;
-            result.push(ElementAssociation::Named(choices, rhs));
-
-            let token = stream.expect()?;
-            match_token_kind!(
-                token,
-                RightPar => Ok(WithPos::new(result, token)),
-                Comma => {
-                    let initial_choices = parse_choices(stream)?;
-                    let mut rhs = parse_aggregate_initial_choices(stream, initial_choices)?;
-                    result.append(&mut rhs.item);
-                    Ok(WithPos::new(result, rhs))
+    let mut choices = choices;
+    loop {
+        let token = stream.expect()?;
+        try_token_kind!(
+            token,
+            RightPar => {
+                if let &[Choice::Expression(ref choice)] = choices.as_slice() {
+                    result.push(ElementAssociation::Positional(choice.clone()));
+                    return Ok(WithPos::new(result, token));
+                } else {
+                    return Err(error(&token, "Expected => after others"));
                 }
-            )
-        }
-    )
+            },
+            Comma => {
+                if let &[Choice::Expression(ref choice)] = choices.as_slice() {
+                    result.push(ElementAssociation::Positional(choice.clone()));
+                    choices = parse_choices(stream)?;
+                } else {
+                    return Err(error(&token, "Expected => after others"));
+                }
+            },
+            RightArrow => {
+                let rhs = parse_expression(stream)?;
+                result.push(ElementAssociation::Named(choices, rhs));
+
+                let token = stream.expect()?;
+                try_token_kind!(
+                    token,
+                    RightPar => {
+                        return Ok(WithPos::new(result, token));
+                    },
+                    Comma => {
+                        choices = parse_choices(stream)?;
+                    }
+                )
+            }
+        )
+    }
 }
 
 #[cfg(test)]
@@ -186,8 +184,8 @@ fn parse_choice(stream: &mut TokenStream) -> ParseResult<Choice> {
     }
 }
 
-pub fn parse_choices(stream: &mut TokenStream) -> ParseResult<Vec<Choice>> {
-    let mut choices = Vec::new();
+pub fn parse_choices(stream: &mut TokenStream) -> ParseResult<Choices> {
+    let mut choices = Choices::new();
     loop {
         choices.push(parse_choice(stream)?);
 
@@ -208,7 +206,10 @@ fn name_to_subtype_indication(name: &WithPos<Name>) -> ParseResult<SubtypeIndica
         Name::Slice(ref prefix, ref discrete_range) => Ok(SubtypeIndication {
             resolution: ResolutionIndication::Unresolved,
             type_mark: to_selected_name(prefix)?,
-            constraint: Some(SubtypeConstraint::Array(vec![discrete_range.clone()], None)),
+            constraint: Some(SubtypeConstraint::Array(
+                vec![discrete_range.as_ref().clone()],
+                None,
+            )),
         }),
         _ => Err(error(
             &name,
@@ -285,7 +286,7 @@ fn parse_primary_initial_token(
 
             let new_pos = token.pos.combine(&alloc.pos);
             Ok(WithPos {
-                item: Expression::New(alloc),
+                item: Expression::New(Box::new(alloc)),
                 pos: new_pos,
             })
         }
@@ -321,7 +322,7 @@ fn parse_primary_initial_token(
                         Comma | RightArrow => {
                             Ok(parse_aggregate_initial_choices(
                                 stream,
-                                vec![Choice::Expression(expr.clone())],
+                                smallvec![Choice::Expression(expr.clone())],
                             )?.map_into(Expression::Aggregate)
                                .combine_pos_with(&token))
                         },
@@ -371,31 +372,34 @@ fn parse_expr_initial_token(
     token: Token,
     min_precedence: usize,
 ) -> ParseResult<WithPos<Expression>> {
-    let mut lhs = parse_primary_initial_token(stream, token)?;
-    while let Some(token) = stream.peek()? {
-        if token.kind == RightPar {
-            return Ok(lhs);
-        };
+    stream.nested(|stream| {
+        stream.enter(&token)?;
+        let mut lhs = parse_primary_initial_token(stream, token)?;
+        while let Some(token) = stream.peek()? {
+            if token.kind == RightPar {
+                return Ok(lhs);
+            };
 
-        if let Some((binary_op, op_precedence)) = kind_to_binary_op(token.kind) {
-            // Binary operation
-            if op_precedence > min_precedence {
-                stream.move_after(&token);
-                let rhs = parse_expr(stream, op_precedence)?;
-                let pos = lhs.pos.combine(&rhs.pos);
-                lhs = WithPos {
-                    item: Expression::Binary(binary_op, Box::new(lhs), Box::new(rhs)),
-                    pos: pos,
-                };
+            if let Some((binary_op, op_precedence)) = kind_to_binary_op(token.kind) {
+                // Binary operation
+                if op_precedence > min_precedence {
+                    stream.move_after(&token);
+                    let rhs = parse_expr(stream, op_precedence)?;
+                    let pos = lhs.pos.combine(&rhs.pos);
+                    lhs = WithPos {
+                        item: Expression::Binary(binary_op, Box::new(lhs), Box::new(rhs)),
+                        pos: pos,
+                    };
+                } else {
+                    return Ok(lhs);
+                }
             } else {
                 return Ok(lhs);
-            }
-        } else {
-            return Ok(lhs);
-        };
-    }
+            };
+        }
 
-    Ok(lhs)
+        Ok(lhs)
+    })
 }
 
 /// Parse expressions using a [Pratt parser](https://en.wikipedia.org/wiki/Pratt_parser)
@@ -612,7 +616,7 @@ mod tests {
         };
 
         let new_expr = WithPos {
-            item: Expression::New(alloc),
+            item: Expression::New(Box::new(alloc)),
             pos: util.entire_pos(),
         };
 
@@ -629,7 +633,7 @@ mod tests {
         };
 
         let new_expr = WithPos {
-            item: Expression::New(alloc),
+            item: Expression::New(Box::new(alloc)),
             pos: util.entire_pos(),
         };
 
@@ -646,7 +650,7 @@ mod tests {
         };
 
         let new_expr = WithPos {
-            item: Expression::New(alloc),
+            item: Expression::New(Box::new(alloc)),
             pos: util.entire_pos(),
         };
 
@@ -791,7 +795,7 @@ mod tests {
         };
 
         let assoc_list = vec![ElementAssociation::Named(
-            vec![Choice::Expression(one_expr)],
+            smallvec![Choice::Expression(one_expr)],
             two_expr,
         )];
         let expr = WithPos {
@@ -821,7 +825,7 @@ mod tests {
         };
 
         let assoc_list = vec![ElementAssociation::Named(
-            vec![Choice::Expression(one_expr), Choice::Expression(two_expr)],
+            smallvec![Choice::Expression(one_expr), Choice::Expression(two_expr)],
             three_expr,
         )];
         let expr = WithPos {
@@ -840,7 +844,10 @@ mod tests {
             pos: util.first_substr_pos("1"),
         };
 
-        let assoc_list = vec![ElementAssociation::Named(vec![Choice::Others], one_expr)];
+        let assoc_list = vec![ElementAssociation::Named(
+            smallvec![Choice::Others],
+            one_expr,
+        )];
         let expr = WithPos {
             item: Expression::Aggregate(assoc_list),
             pos: util.entire_pos(),
@@ -882,7 +889,7 @@ mod tests {
             }));
 
             let assoc_list = vec![ElementAssociation::Named(
-                vec![Choice::DiscreteRange(range)],
+                smallvec![Choice::DiscreteRange(range)],
                 two_expr,
             )];
             let expr = WithPos {
@@ -908,8 +915,8 @@ mod tests {
         };
 
         let assoc_list = vec![
-            ElementAssociation::Named(vec![Choice::Others], one_expr),
-            ElementAssociation::Named(vec![Choice::Others], two_expr),
+            ElementAssociation::Named(smallvec![Choice::Others], one_expr),
+            ElementAssociation::Named(smallvec![Choice::Others], two_expr),
         ];
         let expr = WithPos {
             item: Expression::Aggregate(assoc_list),
@@ -936,7 +943,7 @@ mod tests {
         };
 
         let assoc_list = vec![
-            ElementAssociation::Named(vec![Choice::Expression(one_expr)], two_expr),
+            ElementAssociation::Named(smallvec![Choice::Expression(one_expr)], two_expr),
             ElementAssociation::Positional(three_expr),
         ];
         let expr = WithPos {
@@ -1018,6 +1025,10 @@ mod tests {
                 format!("({} {:?} {})", fmt(lhs), op, fmt(rhs))
             }
             Expression::Unary(ref op, ref rhs) => format!("({:?} {})", op, fmt(rhs)),
+            Expression::Name(ref name) => match **name {
+                Name::Simple(ref sym) => sym.name().to_string(),
+                _ => panic!("Cannot format {:?}", name),
+            },
             Expression::Literal(ref lit) => match lit {
                 Literal::Null => "null".to_string(),
                 // @TODO quote and escape
@@ -1083,6 +1094,12 @@ mod tests {
 
         assert_expression_is("1+2+3", "((Integer(1) Plus Integer(2)) Plus Integer(3))");
 
+        assert_expression_is("1+2*3", "(Integer(1) Plus (Integer(2) Times Integer(3)))");
+
+        assert_expression_is("a + b * c", "(a Plus (b Times c))");
+
+        assert_expression_is("a * b ** c", "(a Times (b Pow c))");
+
         assert_expression_is("1-2-3", "((Integer(1) Minus Integer(2)) Minus Integer(3))");
 
         assert_expression_is("not 1 + 2", "((Not Integer(1)) Plus Integer(2))");
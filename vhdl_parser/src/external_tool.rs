@@ -0,0 +1,476 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Diagnostics of an external compiler such as ghdl -a which complement those of the
+//! analyzer for what it does not yet check
+//!
+//! The command of the tool is run by the shell for each file of the project in compile
+//! order and the diagnostics in its output are parsed into messages with the code
+//! external. A message on the same line and with the same severity as one of the
+//! analyzer is left out as it most likely reports the same problem
+
+use compile_script::compile_steps;
+use message::{Message, Severity};
+use project::Project;
+use source::Source;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+/// The code of the messages of the external tool
+pub const EXTERNAL_CODE: &str = "external";
+
+/// How the external tool reports diagnostics
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OutputFormat {
+    /// file:line:column: message where an optional error:, warning: or note: gives the
+    /// severity, as written by ghdl and gcc style tools
+    Ghdl,
+    /// ** Error: file(line): message as written by vcom of ModelSim and Questa
+    Vcom,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<OutputFormat, String> {
+        match string {
+            "ghdl" | "gcc" => Ok(OutputFormat::Ghdl),
+            "vcom" | "modelsim" | "questa" => Ok(OutputFormat::Vcom),
+            _ => Err(format!(
+                "Unknown output format '{}', expected one of ghdl, gcc, vcom, modelsim or questa",
+                string
+            )),
+        }
+    }
+}
+
+/// An external compiler which analyzes each file of the project in compile order
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExternalTool {
+    /// The shell command where {library}, {file} and {standard} are replaced by those of
+    /// each file and the standard is a two digit year such as 08
+    pub command: String,
+    pub format: OutputFormat,
+}
+
+/// A diagnostic as reported by the external tool
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExternalDiagnostic {
+    /// The file name as written by the tool
+    pub file_name: String,
+    /// The one based line
+    pub line: usize,
+    /// The one based column when the tool reports one
+    pub column: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn parse_severity(word: &str) -> Option<Severity> {
+    match word.to_lowercase().as_str() {
+        "error" | "fatal" => Some(Severity::Error),
+        "warning" => Some(Severity::Warning),
+        "note" | "info" => Some(Severity::Hint),
+        _ => None,
+    }
+}
+
+/// The number and the text following it when the text starts with digits and a colon
+fn number_and_colon(text: &str) -> Option<(usize, &str)> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || !text[digits..].starts_with(':') {
+        return None;
+    }
+    Some((text[..digits].parse().ok()?, &text[digits + 1..]))
+}
+
+/// Parses file:line:column: severity: message where the column and severity are optional,
+/// the file name may contain colons such as those of Windows drive letters
+fn parse_ghdl_line(line: &str) -> Option<ExternalDiagnostic> {
+    for (i, _) in line.match_indices(':') {
+        let (lineno, rest) = match number_and_colon(&line[i + 1..]) {
+            Some(found) => found,
+            None => continue,
+        };
+        let (column, rest) = match number_and_colon(rest) {
+            Some((column, rest)) => (Some(column), rest),
+            None => (None, rest),
+        };
+        let rest = rest.trim_start();
+        let (severity, message) = match rest.find(':') {
+            Some(colon) => match parse_severity(&rest[..colon]) {
+                Some(severity) => (severity, rest[colon + 1..].trim()),
+                None => (Severity::Error, rest.trim()),
+            },
+            None => (Severity::Error, rest.trim()),
+        };
+        return Some(ExternalDiagnostic {
+            file_name: line[..i].to_owned(),
+            line: lineno,
+            column,
+            severity,
+            message: message.to_owned(),
+        });
+    }
+    None
+}
+
+/// Parses ** Severity: file(line): message where the severity may be followed by a
+/// qualifier such as (suppressible) and the file name by an error count such as [4]
+fn parse_vcom_line(line: &str) -> Option<ExternalDiagnostic> {
+    let rest = line.trim_start().strip_prefix("** ")?;
+    let colon = rest.find(':')?;
+    let severity = parse_severity(rest[..colon].split_whitespace().next()?)?;
+    let mut rest = rest[colon + 1..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest[rest.find(']')? + 1..].trim_start();
+    }
+    let open = rest.find("):")?;
+    let paren = rest[..open].rfind('(')?;
+    let lineno = rest[paren + 1..open].parse().ok()?;
+    Some(ExternalDiagnostic {
+        file_name: rest[..paren].to_owned(),
+        line: lineno,
+        column: None,
+        severity,
+        message: rest[open + 2..].trim().to_owned(),
+    })
+}
+
+/// The diagnostics of the output, lines which are not diagnostics are ignored
+pub fn parse_output(output: &str, format: OutputFormat) -> Vec<ExternalDiagnostic> {
+    output
+        .lines()
+        .filter_map(|line| match format {
+            OutputFormat::Ghdl => parse_ghdl_line(line),
+            OutputFormat::Vcom => parse_vcom_line(line),
+        })
+        .collect()
+}
+
+/// The source of the project file the tool refers to by the same or a relative name
+fn project_source<'a>(project: &'a Project, file_name: &str) -> Option<&'a Source> {
+    if let Some(file) = project.get_file(file_name) {
+        return Some(&file.source);
+    }
+    let path = Path::new(file_name);
+    project
+        .files()
+        .into_iter()
+        .find(|file| Path::new(file.file_name()).ends_with(path))
+        .map(|file| &file.source)
+}
+
+/// The message at the word starting at the column or at the whole line without a column,
+/// None when the file is not within the project or the line is not within the file
+pub fn diagnostic_message(project: &Project, diagnostic: &ExternalDiagnostic) -> Option<Message> {
+    let source = project_source(project, &diagnostic.file_name)?;
+    let contents = source.contents().ok()?;
+    let bytes = &contents.bytes;
+    let mut start = 0;
+    for _ in 1..diagnostic.line {
+        start += bytes[start..].iter().position(|byte| *byte == b'\n')? + 1;
+    }
+    let end = bytes[start..]
+        .iter()
+        .position(|byte| *byte == b'\n' || *byte == b'\r')
+        .map_or(bytes.len(), |length| start + length);
+    let (start, length) = match diagnostic.column {
+        Some(column) => {
+            let start = (start + column.max(1) - 1).min(end);
+            let word = bytes[start..end]
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+                .count();
+            (start, word.max(1).min(end - start))
+        }
+        None => {
+            let indent = bytes[start..end]
+                .iter()
+                .take_while(|byte| byte.is_ascii_whitespace())
+                .count();
+            (start + indent, end - start - indent)
+        }
+    };
+    Some(
+        Message {
+            pos: source.pos(start, length),
+            message: diagnostic.message.clone(),
+            severity: diagnostic.severity,
+            code: None,
+            related: Vec::new(),
+            fixes: Vec::new(),
+        }
+        .with_code(EXTERNAL_CODE),
+    )
+}
+
+/// The command of the tool for a file with the placeholders replaced
+fn tool_command(template: &str, library_name: &str, file_name: &str, standard: &str) -> String {
+    template
+        .replace("{library}", library_name)
+        .replace("{file}", file_name)
+        .replace("{standard}", standard)
+}
+
+/// Runs the command by the shell and returns its output on stdout and stderr
+fn shell_output(command: &str) -> io::Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let output = shell.arg(command).stdin(process::Stdio::null()).output()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(text)
+}
+
+/// Runs the tool on each file of the project in compile order and returns the messages
+/// of its diagnostics within the project
+pub fn run_external_tool(project: &Project, tool: &ExternalTool) -> io::Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    for step in compile_steps(project) {
+        let command = tool_command(
+            &tool.command,
+            &step.library_name,
+            &step.file_name,
+            &step.standard.version_string()[2..],
+        );
+        let output = shell_output(&command)?;
+        messages.extend(
+            parse_output(&output, tool.format)
+                .iter()
+                .filter_map(|diagnostic| diagnostic_message(project, diagnostic)),
+        );
+    }
+    Ok(messages)
+}
+
+/// Adds the messages of the external tool which are not on the same line and of the same
+/// severity as a message which is already there
+pub fn merge_messages(messages: &mut Vec<Message>, external: Vec<Message>) {
+    let line = |message: &Message| {
+        (
+            message.pos.source.file_name().map(str::to_owned),
+            message.pos.line_and_column().0,
+            message.severity,
+        )
+    };
+    let mut lines: Vec<_> = messages.iter().map(line).collect();
+    for message in external {
+        let key = line(&message);
+        if !lines.contains(&key) {
+            lines.push(key);
+            messages.push(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::error;
+    use test_util::TempProject;
+
+    fn diagnostic(
+        file_name: &str,
+        line: usize,
+        column: Option<usize>,
+        severity: Severity,
+        message: &str,
+    ) -> ExternalDiagnostic {
+        ExternalDiagnostic {
+            file_name: file_name.to_owned(),
+            line,
+            column,
+            severity,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn ghdl_output() {
+        let output = "\
+src/top.vhd:12:5: no declaration for \"foo\"
+C:\\src\\top.vhd:3:10:warning: declaration of \"x\" hides signal \"x\"
+top.vhd:7: error: missing semicolon
+ghdl:error: compilation error
+";
+        assert_eq!(
+            parse_output(output, OutputFormat::Ghdl),
+            vec![
+                diagnostic(
+                    "src/top.vhd",
+                    12,
+                    Some(5),
+                    Severity::Error,
+                    "no declaration for \"foo\""
+                ),
+                diagnostic(
+                    "C:\\src\\top.vhd",
+                    3,
+                    Some(10),
+                    Severity::Warning,
+                    "declaration of \"x\" hides signal \"x\""
+                ),
+                diagnostic("top.vhd", 7, None, Severity::Error, "missing semicolon"),
+            ]
+        );
+    }
+
+    #[test]
+    fn vcom_output() {
+        let output = "\
+-- Compiling entity top
+** Error: src/top.vhd(12): (vcom-1136) Unknown identifier \"foo\".
+** Error (suppressible): [2] src/top.vhd(3): (vcom-1272) Length of expected is 4.
+** Warning: src/top.vhd(5): (vcom-1246) Range 7 downto 8 is null.
+** Error: src/top.vhd(14): VHDL Compiler exiting
+";
+        assert_eq!(
+            parse_output(output, OutputFormat::Vcom),
+            vec![
+                diagnostic(
+                    "src/top.vhd",
+                    12,
+                    None,
+                    Severity::Error,
+                    "(vcom-1136) Unknown identifier \"foo\"."
+                ),
+                diagnostic(
+                    "src/top.vhd",
+                    3,
+                    None,
+                    Severity::Error,
+                    "(vcom-1272) Length of expected is 4."
+                ),
+                diagnostic(
+                    "src/top.vhd",
+                    5,
+                    None,
+                    Severity::Warning,
+                    "(vcom-1246) Range 7 downto 8 is null."
+                ),
+                diagnostic(
+                    "src/top.vhd",
+                    14,
+                    None,
+                    Severity::Error,
+                    "VHDL Compiler exiting"
+                ),
+            ]
+        );
+        assert_eq!("questa".parse(), Ok(OutputFormat::Vcom));
+        assert!("vivado".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn messages_of_diagnostics() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "top.vhd",
+            "entity top is\nend entity;\n\narchitecture rtl of top is\n  signal foo_bar : bit;\nbegin\nend;\n",
+        );
+        let source = &project.project.get_file(&file_name).unwrap().source;
+
+        let message = diagnostic_message(
+            &project.project,
+            &diagnostic(&file_name, 5, Some(10), Severity::Error, "unused"),
+        )
+        .unwrap();
+        assert_eq!(message.pos, source.first_substr_pos("foo_bar"));
+        assert_eq!(message.code, Some("external".to_owned()));
+
+        let message = diagnostic_message(
+            &project.project,
+            &diagnostic("top.vhd", 5, None, Severity::Warning, "unused"),
+        )
+        .unwrap();
+        assert_eq!(
+            message.pos,
+            source.first_substr_pos("signal foo_bar : bit;")
+        );
+
+        assert_eq!(
+            diagnostic_message(
+                &project.project,
+                &diagnostic(&file_name, 20, None, Severity::Error, "beyond")
+            ),
+            None
+        );
+        assert_eq!(
+            diagnostic_message(
+                &project.project,
+                &diagnostic("other.vhd", 1, None, Severity::Error, "missing")
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn merge_leaves_out_messages_on_the_same_line() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file("lib", "top.vhd", "entity top is\nend entity;\n");
+        let source = project.project.get_file(&file_name).unwrap().source.clone();
+
+        let mut messages = vec![error(source.first_substr_pos("top"), "native")];
+        merge_messages(
+            &mut messages,
+            vec![
+                error(source.first_substr_pos("entity"), "same line").with_code(EXTERNAL_CODE),
+                error(source.first_substr_pos("end"), "other line").with_code(EXTERNAL_CODE),
+                error(source.first_substr_pos("end entity;"), "again").with_code(EXTERNAL_CODE),
+            ],
+        );
+        let texts: Vec<&str> = messages
+            .iter()
+            .map(|message| message.message.as_str())
+            .collect();
+        assert_eq!(texts, vec!["native", "other line"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_tool_in_compile_order() {
+        let mut project = TempProject::new();
+        let pkg = project.add_file("lib", "pkg.vhd", "package pkg is\nend package;\n");
+        let top = project.add_file(
+            "lib",
+            "top.vhd",
+            "use work.pkg.all;\n\nentity top is\nend entity;\n",
+        );
+        let tool = ExternalTool {
+            command: "echo '{file}:1:1:note: {library} {standard}'".to_owned(),
+            format: OutputFormat::Ghdl,
+        };
+        let messages = run_external_tool(&project.project, &tool).unwrap();
+        let found: Vec<(Option<&str>, &str, Severity)> = messages
+            .iter()
+            .map(|message| {
+                (
+                    message.pos.source.file_name(),
+                    message.message.as_str(),
+                    message.severity,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some(pkg.as_str()), "lib 08", Severity::Hint),
+                (Some(top.as_str()), "lib 08", Severity::Hint),
+            ]
+        );
+    }
+}
@@ -0,0 +1,468 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! C interface to embed the parser and analyzer in C and C++ tools, declared by
+//! include/rust_hdl.h
+//!
+//! A project is created, files are added and analyzed, after which the diagnostics and
+//! declared symbols are read by index. Strings returned are owned by the project and
+//! valid until it is analyzed again or freed. Functions which fail return -1 and the
+//! reason is given by rust_hdl_last_error
+
+use config::Config;
+use message::{Message, Severity};
+use name_resolution::NameResolution;
+use project::Project;
+use source::SrcPos;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::ptr;
+
+/// A diagnostic of the analysis, the code is null when the diagnostic has none
+#[repr(C)]
+pub struct RustHdlDiagnostic {
+    /// 0 for errors, 1 for warnings and 2 for hints
+    pub severity: c_int,
+    pub file_name: *const c_char,
+    /// The one based line and column of the start
+    pub line: usize,
+    pub column: usize,
+    pub message: *const c_char,
+    pub code: *const c_char,
+}
+
+/// A declaration such as a signal with its kind such as shared_variable
+#[repr(C)]
+pub struct RustHdlSymbol {
+    pub name: *const c_char,
+    pub kind: *const c_char,
+    pub file_name: *const c_char,
+    /// The one based line and column of the declared name
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The strings of a position as kept by the project
+struct Location {
+    file_name: CString,
+    line: usize,
+    column: usize,
+}
+
+struct Diagnostic {
+    severity: c_int,
+    location: Location,
+    message: CString,
+    code: Option<CString>,
+}
+
+struct Symbol {
+    name: CString,
+    kind: CString,
+    location: Location,
+}
+
+/// The project and the results of its last analysis
+pub struct RustHdlProject {
+    project: Project,
+    resolution: Option<NameResolution>,
+    diagnostics: Vec<Diagnostic>,
+    symbols: Vec<Symbol>,
+    last_error: Option<CString>,
+}
+
+/// A C string of the string without the nul characters it cannot contain
+fn c_string(string: &str) -> CString {
+    CString::new(string.replace('\0', "")).unwrap_or_default()
+}
+
+fn location(pos: &SrcPos) -> Location {
+    let (line, column) = pos.line_and_column();
+    Location {
+        file_name: c_string(pos.source.file_name().unwrap_or("")),
+        line,
+        column,
+    }
+}
+
+fn diagnostic(message: &Message) -> Diagnostic {
+    Diagnostic {
+        severity: match message.severity {
+            Severity::Error => 0,
+            Severity::Warning => 1,
+            Severity::Hint => 2,
+        },
+        location: location(&message.pos),
+        message: c_string(&message.message),
+        code: message.code.as_deref().map(c_string),
+    }
+}
+
+/// The string of a C string which must be valid UTF-8
+unsafe fn string_arg<'a>(string: *const c_char, what: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!("The {} is null", what));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!("The {} is not valid UTF-8", what))
+}
+
+impl RustHdlProject {
+    /// Keeps the error to be returned by rust_hdl_last_error
+    fn result(&mut self, result: Result<(), String>) -> c_int {
+        match result {
+            Ok(()) => {
+                self.last_error = None;
+                0
+            }
+            Err(err) => {
+                self.last_error = Some(c_string(&err));
+                -1
+            }
+        }
+    }
+
+    /// Parses, analyzes and resolves the names of the project
+    fn analyze(&mut self) {
+        let mut messages = self.project.messages();
+        messages.extend(self.project.analyze());
+        let resolution = self.project.resolve();
+        messages.extend(resolution.messages().iter().cloned());
+        messages.sort_by_key(|message| {
+            (
+                message.pos.source.file_name().map(str::to_owned),
+                message.pos.start,
+            )
+        });
+        self.diagnostics = messages.iter().map(diagnostic).collect();
+        self.symbols = resolution
+            .declarations()
+            .iter()
+            .map(|entity| Symbol {
+                name: c_string(&entity.designator),
+                kind: c_string(&entity.kind.to_string()),
+                location: location(&entity.pos),
+            })
+            .collect();
+        self.resolution = Some(resolution);
+    }
+
+    /// The index of the symbol declared by, or denoted by the name at, a line and column
+    fn symbol_at(&self, file_name: &str, line: usize, column: usize) -> Option<usize> {
+        let resolution = self.resolution.as_ref()?;
+        let file = self.project.get_file(file_name)?;
+        let contents = file.source.contents().ok()?;
+        let line_start = if line <= 1 {
+            0
+        } else {
+            contents
+                .bytes
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .nth(line - 2)?
+                .0
+                + 1
+        };
+        let offset = line_start + column.max(1) - 1;
+        let entity = resolution
+            .entities_at(file_name, offset)
+            .into_iter()
+            .next()?;
+        resolution
+            .declarations()
+            .iter()
+            .position(|declaration| declaration == entity)
+    }
+}
+
+/// Creates an empty project which must be freed by rust_hdl_project_free
+#[no_mangle]
+pub extern "C" fn rust_hdl_project_new() -> *mut RustHdlProject {
+    Box::into_raw(Box::new(RustHdlProject {
+        project: Project::new(),
+        resolution: None,
+        diagnostics: Vec::new(),
+        symbols: Vec::new(),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+/// The project must be null or created by rust_hdl_project_new and not freed before
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_project_free(project: *mut RustHdlProject) {
+    if !project.is_null() {
+        drop(Box::from_raw(project));
+    }
+}
+
+/// Parses the file into the library, returns 0 on success and -1 on failure
+///
+/// # Safety
+/// The project must be valid and the names nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_project_add_file(
+    project: *mut RustHdlProject,
+    library_name: *const c_char,
+    file_name: *const c_char,
+) -> c_int {
+    let project = match project.as_mut() {
+        Some(project) => project,
+        None => return -1,
+    };
+    let result = string_arg(library_name, "library name").and_then(|library_name| {
+        let file_name = string_arg(file_name, "file name")?;
+        project
+            .project
+            .update_file(library_name, file_name)
+            .map_err(|err| format!("Could not read {}: {}", file_name, err))
+    });
+    project.result(result)
+}
+
+/// Adds the libraries of a project file such as vhdl_ls.toml, returns 0 on success and
+/// -1 on failure
+///
+/// # Safety
+/// The project must be valid and the file name a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_project_add_config(
+    project: *mut RustHdlProject,
+    file_name: *const c_char,
+) -> c_int {
+    let project = match project.as_mut() {
+        Some(project) => project,
+        None => return -1,
+    };
+    let result = string_arg(file_name, "file name").and_then(|file_name| {
+        let config = Config::read_file_path(Path::new(file_name)).map_err(|err| err.to_string())?;
+        match project.project.add_config(&config).into_iter().next() {
+            Some((file_name, err)) => Err(format!("Could not add {}: {}", file_name, err)),
+            None => Ok(()),
+        }
+    });
+    project.result(result)
+}
+
+/// Analyzes the project and returns the number of diagnostics
+///
+/// # Safety
+/// The project must be valid
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_project_analyze(project: *mut RustHdlProject) -> usize {
+    match project.as_mut() {
+        Some(project) => {
+            project.analyze();
+            project.diagnostics.len()
+        }
+        None => 0,
+    }
+}
+
+/// The reason of the last failure or null when the last call succeeded
+///
+/// # Safety
+/// The project must be valid
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_last_error(project: *const RustHdlProject) -> *const c_char {
+    project
+        .as_ref()
+        .and_then(|project| project.last_error.as_ref())
+        .map_or(ptr::null(), |err| err.as_ptr())
+}
+
+/// # Safety
+/// The project must be valid
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_diagnostic_count(project: *const RustHdlProject) -> usize {
+    project
+        .as_ref()
+        .map_or(0, |project| project.diagnostics.len())
+}
+
+/// Fills the diagnostic with the one at the index, returns -1 when there is none
+///
+/// # Safety
+/// The project must be valid and the diagnostic point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_diagnostic(
+    project: *const RustHdlProject,
+    index: usize,
+    diagnostic: *mut RustHdlDiagnostic,
+) -> c_int {
+    let found = project
+        .as_ref()
+        .and_then(|project| project.diagnostics.get(index));
+    match (found, diagnostic.as_mut()) {
+        (Some(found), Some(diagnostic)) => {
+            *diagnostic = RustHdlDiagnostic {
+                severity: found.severity,
+                file_name: found.location.file_name.as_ptr(),
+                line: found.location.line,
+                column: found.location.column,
+                message: found.message.as_ptr(),
+                code: found
+                    .code
+                    .as_ref()
+                    .map_or(ptr::null(), |code| code.as_ptr()),
+            };
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// # Safety
+/// The project must be valid
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_symbol_count(project: *const RustHdlProject) -> usize {
+    project.as_ref().map_or(0, |project| project.symbols.len())
+}
+
+/// Fills the symbol with the one at the index, returns -1 when there is none
+///
+/// # Safety
+/// The project must be valid and the symbol point to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_symbol(
+    project: *const RustHdlProject,
+    index: usize,
+    symbol: *mut RustHdlSymbol,
+) -> c_int {
+    let found = project
+        .as_ref()
+        .and_then(|project| project.symbols.get(index));
+    match (found, symbol.as_mut()) {
+        (Some(found), Some(symbol)) => {
+            *symbol = RustHdlSymbol {
+                name: found.name.as_ptr(),
+                kind: found.kind.as_ptr(),
+                file_name: found.location.file_name.as_ptr(),
+                line: found.location.line,
+                column: found.location.column,
+            };
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// The index of the symbol declared or denoted by the name at the one based line and
+/// column of the file, -1 when there is no such name
+///
+/// # Safety
+/// The project must be valid and the file name a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_symbol_at(
+    project: *const RustHdlProject,
+    file_name: *const c_char,
+    line: usize,
+    column: usize,
+) -> isize {
+    let project = match project.as_ref() {
+        Some(project) => project,
+        None => return -1,
+    };
+    match string_arg(file_name, "file name") {
+        Ok(file_name) => project
+            .symbol_at(file_name, line, column)
+            .map_or(-1, |index| index as isize),
+        Err(_) => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    /// The string of a C string returned by the interface
+    fn string(string: *const c_char) -> String {
+        unsafe { CStr::from_ptr(string) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn diagnostics_and_symbols() {
+        let mut files = TempProject::new();
+        let file_name = files.add_file(
+            "lib",
+            "ent.vhd",
+            "entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal foo : bit;
+begin
+  foo <= foo;
+end architecture;
+
+entity broken
+end entity;
+",
+        );
+        let file_name = c_string(&file_name);
+        let library_name = c_string("lib");
+
+        unsafe {
+            let project = rust_hdl_project_new();
+            assert_eq!(
+                rust_hdl_project_add_file(project, library_name.as_ptr(), file_name.as_ptr()),
+                0
+            );
+            assert!(rust_hdl_last_error(project).is_null());
+            let missing = c_string("missing.vhd");
+            assert_eq!(
+                rust_hdl_project_add_file(project, library_name.as_ptr(), missing.as_ptr()),
+                -1
+            );
+            assert!(string(rust_hdl_last_error(project)).starts_with("Could not read missing.vhd"));
+
+            assert_eq!(rust_hdl_project_analyze(project), 1);
+            assert_eq!(rust_hdl_diagnostic_count(project), 1);
+            let mut diagnostic = RustHdlDiagnostic {
+                severity: -1,
+                file_name: ptr::null(),
+                line: 0,
+                column: 0,
+                message: ptr::null(),
+                code: ptr::null(),
+            };
+            assert_eq!(rust_hdl_diagnostic(project, 0, &mut diagnostic), 0);
+            assert_eq!(diagnostic.severity, 0);
+            assert_eq!(string(diagnostic.file_name), string(file_name.as_ptr()));
+            assert_eq!((diagnostic.line, diagnostic.column), (10, 1));
+            assert_eq!(
+                string(diagnostic.message),
+                "Failed to parse entity declaration"
+            );
+            assert!(diagnostic.code.is_null());
+            assert_eq!(rust_hdl_diagnostic(project, 1, &mut diagnostic), -1);
+
+            let index = rust_hdl_symbol_at(project, file_name.as_ptr(), 7, 3);
+            assert!(index >= 0);
+            assert!((index as usize) < rust_hdl_symbol_count(project));
+            let mut symbol = RustHdlSymbol {
+                name: ptr::null(),
+                kind: ptr::null(),
+                file_name: ptr::null(),
+                line: 0,
+                column: 0,
+            };
+            assert_eq!(rust_hdl_symbol(project, index as usize, &mut symbol), 0);
+            assert_eq!(string(symbol.name), "foo");
+            assert_eq!(string(symbol.kind), "signal");
+            assert_eq!((symbol.line, symbol.column), (5, 10));
+            assert_eq!(rust_hdl_symbol_at(project, file_name.as_ptr(), 3, 1), -1);
+
+            rust_hdl_project_free(project);
+        }
+    }
+}
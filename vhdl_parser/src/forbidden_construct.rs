@@ -0,0 +1,378 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The forbidden-construct lint rule which reports the constructs forbidden by the
+//! synthesis policy of a project within the design units which are synthesized, such as:
+//!
+//! ```toml
+//! [lint]
+//! forbidden-construct = { constructs = ["shared-variable", "wait-for", "after", "textio"], exclude = ["**/sim/*.vhd"] }
+//! ```
+//!
+//! Testbenches, named tb_* or *_tb like VUnit testbenches, together with their context
+//! clauses and architectures are not synthesized, neither are the files matching an
+//! exclude pattern
+
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use source_tokens::SourceTokens;
+use std::fmt;
+use std::str::FromStr;
+use tokenizer::Kind::*;
+use vunit::is_testbench_name;
+
+extern crate glob;
+use self::glob::Pattern;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Construct {
+    SharedVariable,
+    /// A wait statement with a timeout clause
+    WaitFor,
+    /// A delay of a waveform element
+    After,
+    StdLogicArith,
+    BufferPort,
+    /// The std.textio and ieee.std_logic_textio packages
+    Textio,
+}
+
+pub const CONSTRUCTS: &[Construct] = &[
+    Construct::SharedVariable,
+    Construct::WaitFor,
+    Construct::After,
+    Construct::StdLogicArith,
+    Construct::BufferPort,
+    Construct::Textio,
+];
+
+impl fmt::Display for Construct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Construct::SharedVariable => write!(f, "shared-variable"),
+            Construct::WaitFor => write!(f, "wait-for"),
+            Construct::After => write!(f, "after"),
+            Construct::StdLogicArith => write!(f, "std-logic-arith"),
+            Construct::BufferPort => write!(f, "buffer-port"),
+            Construct::Textio => write!(f, "textio"),
+        }
+    }
+}
+
+impl FromStr for Construct {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Construct, String> {
+        CONSTRUCTS
+            .iter()
+            .find(|construct| construct.to_string() == string)
+            .cloned()
+            .ok_or_else(|| format!("Unknown construct '{}'", string))
+    }
+}
+
+impl Construct {
+    fn describe(self) -> &'static str {
+        match self {
+            Construct::SharedVariable => "Shared variable",
+            Construct::WaitFor => "Wait statement with a timeout",
+            Construct::After => "Delay with after",
+            Construct::StdLogicArith => "Package std_logic_arith",
+            Construct::BufferPort => "Port of mode buffer",
+            Construct::Textio => "Package textio",
+        }
+    }
+}
+
+/// The first and last token of each design unit, including its context clause, and
+/// whether it is a testbench
+fn design_units(tokens: &SourceTokens) -> Vec<(usize, usize, bool)> {
+    let mut units = Vec::new();
+    let mut first = None;
+    let mut i = 0;
+    while i < tokens.tokens.len() {
+        let kind = tokens.tokens[i].kind;
+        let is_unit = match kind {
+            Entity | Architecture | Package | Configuration => true,
+            Context => tokens.kind(i + 2) == Some(Is),
+            _ => false,
+        };
+        if is_unit {
+            let start = first.take().unwrap_or(i);
+            let last = tokens.end_of(i);
+            let name = match kind {
+                Entity => Some(i + 1),
+                Architecture => Some(i + 3),
+                _ => None,
+            };
+            let is_testbench = name
+                .filter(|name| tokens.kind(*name) == Some(Identifier))
+                .is_some_and(|name| is_testbench_name(&tokens.text(name)));
+            units.push((start, last, is_testbench));
+            i = last + 1;
+        } else if let Library | Use | Context = kind {
+            first.get_or_insert(i);
+            i = tokens.end_of(i) + 1;
+        } else {
+            i += 1;
+        }
+    }
+    units
+}
+
+/// The constructs within the tokens from the first to the last token
+fn constructs(tokens: &SourceTokens, first: usize, last: usize) -> Vec<(Construct, usize, usize)> {
+    let mut found = Vec::new();
+    let is_identifier = |i: usize, names: &[&str]| {
+        tokens.kind(i) == Some(Identifier)
+            && names
+                .iter()
+                .any(|name| tokens.text(i).eq_ignore_ascii_case(name))
+    };
+    for i in first..=last {
+        match tokens.tokens[i].kind {
+            Shared if tokens.kind(i + 1) == Some(Variable) => {
+                found.push((Construct::SharedVariable, i, i + 1));
+            }
+            Wait => {
+                let end = tokens.next_of(i, SemiColon).unwrap_or(last).min(last);
+                if (i..end).any(|j| tokens.tokens[j].kind == For) {
+                    found.push((Construct::WaitFor, i, end.max(i + 1) - 1));
+                }
+            }
+            After => found.push((Construct::After, i, i)),
+            Buffer => found.push((Construct::BufferPort, i, i)),
+            Use => {
+                let end = tokens.next_of(i, SemiColon).unwrap_or(last).min(last);
+                for j in i + 1..end {
+                    if is_identifier(j, &["std_logic_arith"]) {
+                        found.push((Construct::StdLogicArith, j, j));
+                    } else if is_identifier(j, &["textio", "std_logic_textio"]) {
+                        found.push((Construct::Textio, j, j));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+pub struct ForbiddenConstruct;
+
+impl Rule for ForbiddenConstruct {
+    fn id(&self) -> &str {
+        "forbidden-construct"
+    }
+
+    fn description(&self) -> &str {
+        "A construct forbidden by the synthesis policy is used by a synthesized design unit"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        let strings = |key: &str| -> Result<Vec<&str>, String> {
+            match config.options.get(key) {
+                Some(value) => value
+                    .as_array()
+                    .and_then(|array| array.iter().map(|item| item.as_str()).collect())
+                    .ok_or_else(|| {
+                        format!(
+                            "lint rule {} {} must be an array of strings",
+                            self.id(),
+                            key
+                        )
+                    }),
+                None => Ok(Vec::new()),
+            }
+        };
+        config.unknown_options(self.id(), &["constructs", "exclude"])?;
+        let mut forbidden = Vec::new();
+        for construct in strings("constructs")? {
+            forbidden.push(
+                construct
+                    .parse::<Construct>()
+                    .map_err(|err| format!("lint rule {}: {}", self.id(), err))?,
+            );
+        }
+        if forbidden.is_empty() {
+            return Err(format!(
+                "lint rule {} needs at least one of the constructs",
+                self.id()
+            ));
+        }
+        let mut exclude = Vec::new();
+        for pattern in strings("exclude")? {
+            exclude.push(
+                Pattern::new(pattern)
+                    .map_err(|err| format!("Invalid pattern '{}': {}", pattern, err))?,
+            );
+        }
+
+        let mut findings = Vec::new();
+        for file in context.project.files() {
+            if exclude
+                .iter()
+                .any(|pattern| pattern.matches(file.file_name()))
+            {
+                continue;
+            }
+            let tokens = match SourceTokens::new(&file.source) {
+                Some(tokens) => tokens,
+                None => continue,
+            };
+            for (first, last, is_testbench) in design_units(&tokens) {
+                if is_testbench {
+                    continue;
+                }
+                for (construct, start, end) in constructs(&tokens, first, last) {
+                    if forbidden.contains(&construct) {
+                        findings.push(warning(
+                            tokens.range(start, end),
+                            &format!(
+                                "{} is forbidden by the synthesis policy",
+                                construct.describe()
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+    extern crate toml;
+    use self::toml::Value;
+
+    #[test]
+    fn forbidden_constructs_of_synthesized_units() {
+        let mut project = TempProject::new();
+        let code = "
+library ieee;
+use ieee.std_logic_1164.all;
+use ieee.std_logic_arith.all;
+
+entity ent is
+  port (q : buffer bit);
+end entity;
+
+architecture rtl of ent is
+  shared variable count : natural;
+begin
+  q <= '1' after 1 ns;
+
+  process
+  begin
+    wait for 10 ns;
+    wait until q = '1';
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        project.add_file(
+            "lib",
+            "tb_ent.vhd",
+            "
+use std.textio.all;
+
+entity tb_ent is
+end entity;
+
+architecture tb of tb_ent is
+  signal clk : bit;
+begin
+  clk <= not clk after 5 ns;
+end architecture;",
+        );
+        let excluded = project.add_file(
+            "lib",
+            "sim_model.vhd",
+            "package sim_model is\n  shared variable seed : natural;\nend package;",
+        );
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let check = |contents: &str| {
+            let value: Value = contents.parse().unwrap();
+            Registry::default().check(&context, &LintConfig::from_value(&value).unwrap())
+        };
+
+        let findings = check(&format!(
+            "forbidden-construct = {{ constructs = ['shared-variable', 'wait-for', 'after', \
+             'std-logic-arith', 'buffer-port', 'textio'], exclude = ['{}'] }}",
+            excluded
+        ))
+        .unwrap();
+        let described: Vec<(String, usize)> = findings
+            .iter()
+            .map(|finding| (finding.message.clone(), finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+        assert_eq!(
+            described,
+            vec![
+                (
+                    string("Package std_logic_arith is forbidden by the synthesis policy"),
+                    code.find("std_logic_arith").unwrap()
+                ),
+                (
+                    string("Port of mode buffer is forbidden by the synthesis policy"),
+                    code.find("buffer").unwrap()
+                ),
+                (
+                    string("Shared variable is forbidden by the synthesis policy"),
+                    code.find("shared").unwrap()
+                ),
+                (
+                    string("Delay with after is forbidden by the synthesis policy"),
+                    code.find("after").unwrap()
+                ),
+                (
+                    string("Wait statement with a timeout is forbidden by the synthesis policy"),
+                    code.find("wait for").unwrap()
+                ),
+            ]
+        );
+        assert_eq!(findings[4].pos.length, "wait for 10 ns".len());
+
+        assert_eq!(
+            check("forbidden-construct = { constructs = ['after'] }")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            check("forbidden-construct = { constructs = ['shared-variable'] }")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            check("forbidden-construct = { constructs = ['goto'] }"),
+            Err("lint rule forbidden-construct: Unknown construct 'goto'".to_owned())
+        );
+        assert_eq!(
+            check("forbidden-construct = 'error'"),
+            Err("lint rule forbidden-construct needs at least one of the constructs".to_owned())
+        );
+    }
+}
@@ -0,0 +1,370 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Formatting style read from a .vhdlfmt.toml file such as:
+//!
+//! ```toml
+//! preset = "spaces-2-lowercase"
+//! max_width = 100
+//!
+//! [[directories]]
+//! path = "legacy"
+//! preset = "classic-uppercase"
+//! align_comments = false
+//! ```
+//!
+//! The nearest file in the directory of a source file or above it gives its style
+//!
+//! A style starts from its preset, or the default style which only indents by two spaces,
+//! and is changed by the options given with it, an entry of directories applies to the
+//! files below its path relative to the file and later entries take precedence
+
+use formatter::{Case, FormatStyle, Indentation};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+extern crate toml;
+use self::toml::value::Table;
+use self::toml::Value;
+
+pub const FILE_NAME: &str = ".vhdlfmt.toml";
+
+/// The names of the presets
+pub const PRESETS: &[&str] = &[
+    "default",
+    "spaces-2-lowercase",
+    "spaces-4-lowercase",
+    "tabs-lowercase",
+    "classic-uppercase",
+];
+
+fn spaces(size: usize) -> Indentation {
+    Indentation { size, tabs: false }
+}
+
+/// Spaced operators and commas with lower case keywords
+fn lowercase(indentation: Indentation) -> FormatStyle {
+    let mut style = FormatStyle::new(indentation);
+    style.operator_spacing = true;
+    style.comma_spacing = true;
+    style.keyword_case = Some(Case::Lower);
+    style
+}
+
+/// The style of the preset
+pub fn preset(name: &str) -> Option<FormatStyle> {
+    let style = match name {
+        "default" => FormatStyle::new(spaces(2)),
+        "spaces-2-lowercase" => lowercase(spaces(2)),
+        "spaces-4-lowercase" => lowercase(spaces(4)),
+        "tabs-lowercase" => lowercase(Indentation {
+            size: 4,
+            tabs: true,
+        }),
+        // Upper case keywords with generics, ports and comments in columns
+        "classic-uppercase" => {
+            let mut style = lowercase(spaces(4));
+            style.keyword_case = Some(Case::Upper);
+            style.one_item_per_line = true;
+            style.align_colons = true;
+            style.align_arrows = true;
+            style.align_assignments = true;
+            style.align_comments = true;
+            style
+        }
+        _ => {
+            return None;
+        }
+    };
+    Some(style)
+}
+
+fn parse_bool(value: &Value, key: &str, what: &str) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("{} {} must be a boolean", what, key))
+}
+
+fn parse_width(value: &Value, key: &str, what: &str) -> Result<usize, String> {
+    value
+        .as_integer()
+        .filter(|width| *width > 0)
+        .map(|width| width as usize)
+        .ok_or_else(|| format!("{} {} must be a positive integer", what, key))
+}
+
+fn parse_case(value: &Value, key: &str, what: &str) -> Result<Case, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{} {} must be a string", what, key))?
+        .parse()
+        .map_err(|err| format!("{} {}: {}", what, key, err))
+}
+
+/// Changes the style by the preset and then by the other options of the table
+fn apply_options(table: &Table, style: &mut FormatStyle, what: &str) -> Result<(), String> {
+    if let Some(value) = table.get("preset") {
+        let name = value
+            .as_str()
+            .ok_or_else(|| format!("{} preset must be a string", what))?;
+        *style = preset(name).ok_or_else(|| {
+            format!(
+                "{} has unknown preset '{}', expected one of {}",
+                what,
+                name,
+                PRESETS.join(", ")
+            )
+        })?;
+    }
+    for (key, value) in table.iter() {
+        match key.as_str() {
+            "preset" | "path" => {}
+            "indent_size" => style.indentation.size = parse_width(value, key, what)?,
+            "tabs" => style.indentation.tabs = parse_bool(value, key, what)?,
+            "operator_spacing" => style.operator_spacing = parse_bool(value, key, what)?,
+            "comma_spacing" => style.comma_spacing = parse_bool(value, key, what)?,
+            "one_item_per_line" => style.one_item_per_line = parse_bool(value, key, what)?,
+            "align_colons" => style.align_colons = parse_bool(value, key, what)?,
+            "align_arrows" => style.align_arrows = parse_bool(value, key, what)?,
+            "align_assignments" => style.align_assignments = parse_bool(value, key, what)?,
+            "keyword_case" => style.keyword_case = Some(parse_case(value, key, what)?),
+            "standard_case" => style.standard_case = Some(parse_case(value, key, what)?),
+            "declared_spelling" => style.declared_spelling = parse_bool(value, key, what)?,
+            "align_comments" => style.align_comments = parse_bool(value, key, what)?,
+            "comment_width" => style.comment_width = Some(parse_width(value, key, what)?),
+            "max_width" => style.max_width = Some(parse_width(value, key, what)?),
+            _ => {
+                return Err(format!("Unknown key '{}' of {}", key, what));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The options of the files below a directory
+#[derive(Clone, PartialEq, Debug)]
+struct DirectoryConfig {
+    path: PathBuf,
+    options: Table,
+}
+
+/// The style of the files below the directory of a configuration file
+#[derive(Clone, PartialEq, Debug)]
+pub struct FormatConfig {
+    options: Table,
+    directories: Vec<DirectoryConfig>,
+}
+
+impl FormatConfig {
+    /// Parse the contents of the file within the parent directory
+    pub fn parse(contents: &str, parent: &Path) -> Result<FormatConfig, String> {
+        let value: Value = contents.parse().map_err(|err| format!("{}", err))?;
+        let mut options = value
+            .as_table()
+            .cloned()
+            .ok_or_else(|| "Expected a table".to_owned())?;
+        let directories = match options.remove("directories") {
+            Some(directories) => parse_directories(&directories, parent)?,
+            None => Vec::new(),
+        };
+        if options.contains_key("path") {
+            return Err("Unknown key 'path' of the style".to_owned());
+        }
+        apply_options(&options, &mut FormatStyle::new(spaces(2)), "the style")?;
+        Ok(FormatConfig {
+            options,
+            directories,
+        })
+    }
+
+    pub fn read_file_path(file_name: &Path) -> Result<FormatConfig, String> {
+        let contents = fs::read_to_string(file_name)
+            .map_err(|err| format!("Could not read {}: {}", file_name.to_string_lossy(), err))?;
+        let parent = file_name.parent().unwrap_or_else(|| Path::new(""));
+        FormatConfig::parse(&contents, parent)
+            .map_err(|err| format!("{}: {}", file_name.to_string_lossy(), err))
+    }
+
+    /// The configuration of the nearest file in the directory of the source file or above it
+    pub fn find(file_name: &Path) -> Result<Option<FormatConfig>, String> {
+        let file_name = absolute(file_name);
+        for directory in file_name.ancestors().skip(1) {
+            let config_file = directory.join(FILE_NAME);
+            if config_file.is_file() {
+                return FormatConfig::read_file_path(&config_file).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The style of the source file
+    pub fn style(&self, file_name: &Path) -> FormatStyle {
+        let file_name = absolute(file_name);
+        let mut style = FormatStyle::new(spaces(2));
+        // The options were checked when parsed
+        let _ = apply_options(&self.options, &mut style, "the style");
+        for directory in self.directories.iter() {
+            if file_name.starts_with(&directory.path) {
+                let _ = apply_options(&directory.options, &mut style, "directories entry");
+            }
+        }
+        style
+    }
+}
+
+fn absolute(file_name: &Path) -> PathBuf {
+    if file_name.is_absolute() {
+        file_name.to_owned()
+    } else {
+        env::current_dir()
+            .map(|dir| dir.join(file_name))
+            .unwrap_or_else(|_| file_name.to_owned())
+    }
+}
+
+fn parse_directories(value: &Value, parent: &Path) -> Result<Vec<DirectoryConfig>, String> {
+    let array = value
+        .as_array()
+        .ok_or_else(|| "directories must be an array of tables".to_owned())?;
+    let mut directories = Vec::with_capacity(array.len());
+    for directory in array {
+        let options = directory
+            .as_table()
+            .ok_or_else(|| "directories must be an array of tables".to_owned())?;
+        let path = options
+            .get("path")
+            .and_then(|path| path.as_str())
+            .ok_or_else(|| "directories entry must have a path string".to_owned())?;
+        let what = format!("directories entry {}", path);
+        if options.contains_key("directories") {
+            return Err(format!("Unknown key 'directories' of {}", what));
+        }
+        apply_options(options, &mut FormatStyle::new(spaces(2)), &what)?;
+        directories.push(DirectoryConfig {
+            path: absolute(&parent.join(path)),
+            options: options.clone(),
+        });
+    }
+    Ok(directories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for name in PRESETS {
+            assert!(preset(name).is_some());
+        }
+        assert_eq!(preset("default"), Some(FormatStyle::new(spaces(2))));
+        let classic = preset("classic-uppercase").unwrap();
+        assert_eq!(classic.indentation, spaces(4));
+        assert_eq!(classic.keyword_case, Some(Case::Upper));
+        assert!(classic.align_colons && classic.one_item_per_line);
+        assert_eq!(preset("fancy"), None);
+    }
+
+    #[test]
+    fn style_of_preset_options_and_directories() {
+        let root = Path::new("/project");
+        let config = FormatConfig::parse(
+            "
+preset = \"spaces-2-lowercase\"
+max_width = 100
+keyword_case = \"upper\"
+
+[[directories]]
+path = \"legacy\"
+preset = \"classic-uppercase\"
+align_comments = false
+
+[[directories]]
+path = \"legacy/generated\"
+indent_size = 3
+",
+            root,
+        )
+        .unwrap();
+
+        let style = config.style(Path::new("/project/src/ent.vhd"));
+        let mut expected = preset("spaces-2-lowercase").unwrap();
+        expected.max_width = Some(100);
+        expected.keyword_case = Some(Case::Upper);
+        assert_eq!(style, expected);
+
+        let style = config.style(Path::new("/project/legacy/ent.vhd"));
+        let mut expected = preset("classic-uppercase").unwrap();
+        expected.align_comments = false;
+        assert_eq!(style, expected);
+
+        let style = config.style(Path::new("/project/legacy/generated/ent.vhd"));
+        expected.indentation.size = 3;
+        assert_eq!(style, expected);
+
+        // A directory with a name starting with the path is not below it
+        let style = config.style(Path::new("/project/legacy_old/ent.vhd"));
+        assert_eq!(style.keyword_case, Some(Case::Upper));
+        assert_eq!(style.max_width, Some(100));
+    }
+
+    #[test]
+    fn invalid_options() {
+        let parse = |contents: &str| FormatConfig::parse(contents, Path::new("/project"));
+        assert_eq!(
+            parse("preset = \"fancy\""),
+            Err(
+                "the style has unknown preset 'fancy', expected one of default, \
+                 spaces-2-lowercase, spaces-4-lowercase, tabs-lowercase, classic-uppercase"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            parse("indent = 2"),
+            Err("Unknown key 'indent' of the style".to_owned())
+        );
+        assert_eq!(
+            parse("max_width = 0"),
+            Err("the style max_width must be a positive integer".to_owned())
+        );
+        assert_eq!(
+            parse("[[directories]]\npath = \"legacy\"\ntabs = 1"),
+            Err("directories entry legacy tabs must be a boolean".to_owned())
+        );
+        assert_eq!(
+            parse("[[directories]]\ntabs = true"),
+            Err("directories entry must have a path string".to_owned())
+        );
+        assert!(parse("keyword_case = \"title\"").is_err());
+    }
+
+    #[test]
+    fn finds_nearest_file_above() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        let file_name = nested.join("ent.vhd");
+        assert_eq!(FormatConfig::find(&file_name), Ok(None));
+
+        fs::write(dir.path().join(FILE_NAME), "preset = \"tabs-lowercase\"").unwrap();
+        let style = FormatConfig::find(&file_name)
+            .unwrap()
+            .unwrap()
+            .style(&file_name);
+        assert!(style.indentation.tabs);
+
+        fs::write(dir.path().join("a").join(FILE_NAME), "indent_size = 3").unwrap();
+        let style = FormatConfig::find(&file_name)
+            .unwrap()
+            .unwrap()
+            .style(&file_name);
+        assert_eq!(style, FormatStyle::new(spaces(3)));
+
+        fs::write(dir.path().join("a").join(FILE_NAME), "indent_size = ").unwrap();
+        assert!(FormatConfig::find(&file_name).is_err());
+    }
+}
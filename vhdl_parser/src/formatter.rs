@@ -0,0 +1,2331 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Indentation of declarations and statements by the nesting of the constructs enclosing them
+//! Continuation lines keep their alignment relative to the line they continue
+//! and trailing whitespace is removed, nothing else within a line is changed unless the
+//! style also spaces operators and commas, puts each generic and port on its own line
+//! or aligns colons, arrows and assignments of consecutive lines in columns
+//! Comments stay before or after the tokens they are written next to, trailing comments
+//! of consecutive lines may be aligned and comment paragraphs reflowed to a width
+//! Lines of code wider than the maximum width are wrapped after commas and before logical
+//! operators
+//! Keywords and identifiers keep their case unless the style gives the case of keywords
+//! and of the standard libraries or the spelling of the declaration of each identifier
+//! A selection is formatted within the whole source but only the changes within it are kept
+//! While typing, a new line is indented and an end is completed with its construct
+
+use latin_1::Latin1String;
+use message::{warning, Message};
+use name_resolution::NameResolution;
+use project::Project;
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokenizer::Kind::{self, *};
+
+/// The indentation of one level
+#[derive(PartialEq, Debug, Clone)]
+pub struct Indentation {
+    /// The width of a level and of a tab
+    pub size: usize,
+    /// Indent with tabs instead of spaces
+    pub tabs: bool,
+}
+
+impl Indentation {
+    /// The width of leading whitespace where tabs advance to the next multiple of the size
+    fn width_of(&self, whitespace: &[u8]) -> usize {
+        whitespace.iter().fold(0, |width, byte| {
+            if *byte == b'\t' {
+                (width / self.size + 1) * self.size
+            } else {
+                width + 1
+            }
+        })
+    }
+
+    /// The whitespace of the nesting depth
+    pub fn levels(&self, depth: usize) -> String {
+        self.text_of(depth * self.size)
+    }
+
+    fn text_of(&self, width: usize) -> String {
+        if self.tabs {
+            let mut text = "\t".repeat(width / self.size);
+            text.push_str(&" ".repeat(width % self.size));
+            text
+        } else {
+            " ".repeat(width)
+        }
+    }
+}
+
+/// The libraries whose declarations are spelled in the standard case
+pub const STANDARD_LIBRARIES: &[&str] = &["std", "ieee"];
+
+/// The case of keywords or of the identifiers of the standard libraries
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    Lower,
+    Upper,
+}
+
+impl Case {
+    fn apply(self, text: &str) -> String {
+        match self {
+            Case::Lower => text.to_ascii_lowercase(),
+            Case::Upper => text.to_ascii_uppercase(),
+        }
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Case::Lower => write!(f, "lower"),
+            Case::Upper => write!(f, "upper"),
+        }
+    }
+}
+
+impl FromStr for Case {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Case, String> {
+        match string {
+            "lower" => Ok(Case::Lower),
+            "upper" => Ok(Case::Upper),
+            _ => Err(format!(
+                "Unknown case '{}', expected lower or upper",
+                string
+            )),
+        }
+    }
+}
+
+/// The indentation together with the layout within lines, the layout is only changed
+/// between tokens on the same line and where a generic or port is put on its own line
+#[derive(PartialEq, Debug, Clone)]
+pub struct FormatStyle {
+    pub indentation: Indentation,
+    /// One space around binary operators, assignments, arrows and colons and none after a sign
+    pub operator_spacing: bool,
+    /// One space after a comma and none before a comma or semi colon
+    pub comma_spacing: bool,
+    /// Each generic or port of a clause with several of them on its own line and the
+    /// closing parenthesis on the line after them, unless the clause fits on its line
+    /// within the maximum width
+    pub one_item_per_line: bool,
+    /// The colons of consecutive declarations, generics and ports in one column
+    pub align_colons: bool,
+    /// The arrows of consecutive associations, aggregate elements and case alternatives
+    /// in one column
+    pub align_arrows: bool,
+    /// The <= and := of consecutive assignments and default values in one column
+    pub align_assignments: bool,
+    pub keyword_case: Option<Case>,
+    /// The case of identifiers declared in the standard libraries std and ieee
+    pub standard_case: Option<Case>,
+    /// Other identifiers are spelled as in their declaration
+    pub declared_spelling: bool,
+    /// The comments after the code of consecutive lines in one column
+    pub align_comments: bool,
+    /// The width which paragraphs of comments on lines of their own with a line wider than
+    /// it are reflowed to
+    pub comment_width: Option<usize>,
+    /// The width which lines of code wider than it are wrapped to after commas and before
+    /// logical operators, continuing one level deeper than the line
+    pub max_width: Option<usize>,
+}
+
+impl FormatStyle {
+    /// The style which only changes the indentation
+    pub fn new(indentation: Indentation) -> FormatStyle {
+        FormatStyle {
+            indentation,
+            operator_spacing: false,
+            comma_spacing: false,
+            one_item_per_line: false,
+            align_colons: false,
+            align_arrows: false,
+            align_assignments: false,
+            keyword_case: None,
+            standard_case: None,
+            declared_spelling: false,
+            align_comments: false,
+            comment_width: None,
+            max_width: None,
+        }
+    }
+
+    fn changes_layout(&self) -> bool {
+        self.keyword_case.is_some()
+            || self.align_comments
+            || self.comment_width.is_some()
+            || self.max_width.is_some()
+            || self.operator_spacing
+            || self.comma_spacing
+            || self.one_item_per_line
+            || !self.aligned().is_empty()
+    }
+
+    /// The kinds of tokens aligned together, one group at a time
+    fn aligned(&self) -> Vec<&'static [Kind]> {
+        let mut aligned: Vec<&'static [Kind]> = Vec::new();
+        if self.align_colons {
+            aligned.push(&[Colon]);
+        }
+        if self.align_arrows {
+            aligned.push(&[RightArrow]);
+        }
+        if self.align_assignments {
+            aligned.push(&[LTE, ColonEq]);
+        }
+        aligned
+    }
+}
+
+/// The start and end of each line excluding the line break
+fn lines_of(bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte == b'\n' {
+            let end = if i > start && bytes[i - 1] == b'\r' {
+                i - 1
+            } else {
+                i
+            };
+            lines.push((start, end));
+            start = i + 1;
+        }
+    }
+    lines.push((start, bytes.len()));
+    lines
+}
+
+/// The line of the offset
+fn line_of(lines: &[(usize, usize)], offset: usize) -> usize {
+    lines.partition_point(|(_, end)| *end < offset)
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t'
+}
+
+fn is_within(lines: Option<(usize, usize)>, lineno: usize) -> bool {
+    match lines {
+        Some((first, last)) => first <= lineno && lineno <= last,
+        None => true,
+    }
+}
+
+/// The bytes after applying the edits which are ordered by position
+fn apply(bytes: &[u8], edits: &[(SrcPos, String)]) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    for (pos, text) in edits.iter().rev() {
+        bytes.splice(pos.start..pos.start + pos.length, text.bytes());
+    }
+    bytes
+}
+
+fn rewrite(bytes: &[u8], edits: &[(SrcPos, String)]) -> Arc<Latin1String> {
+    Arc::new(Latin1String::from_vec(apply(bytes, edits)))
+}
+
+/// The number of lines the edits add, negative when they remove lines
+fn line_delta(bytes: &[u8], edits: &[(SrcPos, String)]) -> isize {
+    edits
+        .iter()
+        .map(|(pos, text)| {
+            let removed = bytes[pos.start..pos.start + pos.length]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count();
+            text.matches('\n').count() as isize - removed as isize
+        })
+        .sum()
+}
+
+/// The first and last line after the lines have changed by the delta
+fn shift_lines(lines: Option<(usize, usize)>, delta: isize) -> Option<(usize, usize)> {
+    lines.map(|(first, last)| {
+        let last = if delta < 0 {
+            last.saturating_sub(delta.unsigned_abs())
+        } else {
+            last.saturating_add(delta as usize)
+        };
+        (first, last.max(first))
+    })
+}
+
+fn is_operator(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Plus | Minus
+            | EQ
+            | NE
+            | LT
+            | LTE
+            | GT
+            | GTE
+            | QueEQ
+            | QueNE
+            | QueLT
+            | QueLTE
+            | QueGT
+            | QueGTE
+            | Times
+            | Pow
+            | Div
+            | Concat
+            | And
+            | Or
+            | Nand
+            | Nor
+            | Xor
+            | Xnor
+            | SLL
+            | SRL
+            | SLA
+            | SRA
+            | ROL
+            | ROR
+            | Mod
+            | Rem
+    )
+}
+
+/// The tokens which end an operand so that an operator after them is a binary operator
+fn ends_operand(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Identifier
+            | AbstractLiteral
+            | StringLiteral
+            | BitString
+            | Character
+            | RightPar
+            | RightSquare
+            | All
+            | Others
+            | Open
+            | Null
+    )
+}
+
+/// An operator, assignment, arrow, colon or bar between two operands
+fn is_binary(tokens: &SourceTokens, i: usize) -> bool {
+    match tokens.kind(i) {
+        Some(ColonEq) | Some(RightArrow) | Some(Colon) | Some(Bar) => true,
+        Some(kind) => is_operator(kind) && i > 0 && ends_operand(tokens.tokens[i - 1].kind),
+        None => false,
+    }
+}
+
+/// The whitespace of the style between the token at the index and the one before it
+fn spacing(tokens: &SourceTokens, style: &FormatStyle, i: usize) -> Option<&'static str> {
+    let (before, kind) = (tokens.tokens[i - 1].kind, tokens.tokens[i].kind);
+    // The brackets of an external name such as << signal .tb.clk : bit >>
+    let closes_name = kind == GT && tokens.kind(i + 1) == Some(GT);
+    if (before == LT && kind == LT) || (before == GT && kind == GT) || closes_name {
+        return None;
+    }
+    if style.comma_spacing {
+        if kind == Comma || kind == SemiColon {
+            return Some("");
+        } else if before == Comma {
+            return Some(" ");
+        }
+    }
+    if style.operator_spacing {
+        if is_binary(tokens, i - 1) || is_binary(tokens, i) {
+            return Some(" ");
+        } else if before == Plus || before == Minus {
+            return Some("");
+        }
+    }
+    None
+}
+
+/// The leading whitespace of each generic and port of the clauses with several of them
+/// and of the closing parenthesis of the clause by the index of their first token, a clause
+/// on one line which fits within the maximum width when indented is kept on its line
+fn item_breaks(
+    tokens: &SourceTokens,
+    lines: &[(usize, usize)],
+    indentation: &Indentation,
+    max_width: Option<usize>,
+) -> HashMap<usize, String> {
+    let bytes = &tokens.code.bytes;
+    let levels = tokens.indentation_levels();
+    let mut breaks = HashMap::new();
+    for i in 0..tokens.tokens.len() {
+        let is_clause = tokens.kind(i) == Some(Port) || tokens.kind(i) == Some(Generic);
+        if !is_clause || tokens.kind(i + 1) != Some(LeftPar) {
+            continue;
+        }
+        let mut items = vec![i + 2];
+        let mut close = None;
+        let mut depth = 0;
+        for j in i + 1..tokens.tokens.len() {
+            match tokens.tokens[j].kind {
+                LeftPar => depth += 1,
+                RightPar => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(j);
+                        break;
+                    }
+                }
+                SemiColon if depth == 1 => items.push(j + 1),
+                _ => {}
+            }
+        }
+        let close = match close {
+            Some(close) if items.len() > 1 => close,
+            _ => continue,
+        };
+        let (start, end) = lines[line_of(lines, tokens.tokens[i].pos.start)];
+        let text_start = (start..end)
+            .find(|i| !is_whitespace(bytes[*i]))
+            .unwrap_or(end);
+        let width = indentation.width_of(&bytes[start..text_start]);
+        if let Some(max_width) = max_width {
+            let first = (0..=i)
+                .rev()
+                .find(|j| tokens.tokens[*j].pos.start <= text_start)
+                .unwrap_or(i);
+            let indented = match levels[first] {
+                Some(level) if tokens.tokens[first].pos.start == text_start => {
+                    level * indentation.size
+                }
+                _ => width,
+            };
+            let on_line = tokens.tokens[close].pos.start < end;
+            if on_line && indented + end - text_start <= max_width {
+                continue;
+            }
+        }
+        for item in items {
+            breaks.insert(item, indentation.text_of(width + indentation.size));
+        }
+        breaks.insert(close, indentation.text_of(width));
+    }
+    breaks
+}
+
+/// The replacements of the whitespace between tokens which lay out the lines between the
+/// first and last line as given by the style
+fn layout(
+    source: &Source,
+    tokens: &SourceTokens,
+    style: &FormatStyle,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let bytes = &tokens.code.bytes;
+    let line_ranges = lines_of(bytes);
+    let breaks = if style.one_item_per_line {
+        item_breaks(tokens, &line_ranges, &style.indentation, style.max_width)
+    } else {
+        HashMap::new()
+    };
+
+    let mut edits = Vec::new();
+    for i in 1..tokens.tokens.len() {
+        let previous = &tokens.tokens[i - 1].pos;
+        let gap_start = previous.start + previous.length;
+        let gap_end = tokens.tokens[i].pos.start;
+        if !is_within(lines, line_of(&line_ranges, gap_end)) {
+            continue;
+        }
+        let same_line = bytes[gap_start..gap_end]
+            .iter()
+            .all(|byte| is_whitespace(*byte));
+        let (start, text) = match breaks.get(&i) {
+            Some(whitespace) if same_line => (gap_start, format!("\n{}", whitespace)),
+            Some(whitespace) => {
+                // Only the leading whitespace of a line starting with the token is replaced
+                let start = (gap_start..gap_end)
+                    .rev()
+                    .find(|i| !is_whitespace(bytes[*i]))
+                    .map(|i| i + 1)
+                    .unwrap_or(gap_start);
+                if bytes[start - 1] != b'\n' {
+                    continue;
+                }
+                // Comments on lines of their own before the token are indented with it
+                for line_start in (gap_start..start).filter(|i| bytes[*i - 1] == b'\n') {
+                    let text_start = (line_start..start)
+                        .find(|i| !is_whitespace(bytes[*i]))
+                        .unwrap_or(start);
+                    let is_comment = bytes[text_start..start].starts_with(b"--");
+                    if is_comment && bytes[line_start..text_start] != *whitespace.as_bytes() {
+                        let length = text_start - line_start;
+                        edits.push((source.pos(line_start, length), whitespace.clone()));
+                    }
+                }
+                (start, whitespace.clone())
+            }
+            None if same_line => match spacing(tokens, style, i) {
+                Some(whitespace) => (gap_start, whitespace.to_owned()),
+                None => continue,
+            },
+            None => continue,
+        };
+        if bytes[start..gap_end] != *text.as_bytes() {
+            edits.push((source.pos(start, gap_end - start), text));
+        }
+    }
+    edits
+}
+
+/// The first token on a line of a kind which is aligned outside of parenthesis opened on
+/// the line
+struct Alignable {
+    /// The kind of the first token of the line which must be the same within a column
+    first: Kind,
+    line_start: usize,
+    text_start: usize,
+    /// The end of the token before the aligned token
+    gap_start: usize,
+    gap_end: usize,
+}
+
+/// The replacements of the whitespace before the tokens of the kinds on consecutive lines
+/// within the first and last line which align them in one column, the lines must have the
+/// same indentation and start with the same kind of token
+fn align(source: &Source, kinds: &[Kind], lines: Option<(usize, usize)>) -> Vec<(SrcPos, String)> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let bytes = &tokens.code.bytes;
+    let line_ranges = lines_of(bytes);
+
+    let mut alignables: Vec<Option<Alignable>> = (0..line_ranges.len()).map(|_| None).collect();
+    let mut first = 0;
+    while first < tokens.tokens.len() {
+        let lineno = line_of(&line_ranges, tokens.tokens[first].pos.start);
+        let (line_start, line_end) = line_ranges[lineno];
+        let mut depth: isize = 0;
+        let mut i = first + 1;
+        while i < tokens.tokens.len() && tokens.tokens[i].pos.start <= line_end {
+            let kind = tokens.tokens[i].kind;
+            if kind == LeftPar {
+                depth += 1;
+            } else if kind == RightPar {
+                depth -= 1;
+            } else if depth == 0 && kinds.contains(&kind) && alignables[lineno].is_none() {
+                let previous = &tokens.tokens[i - 1].pos;
+                let gap_start = previous.start + previous.length;
+                let gap_end = tokens.tokens[i].pos.start;
+                let is_whitespace = bytes[gap_start..gap_end]
+                    .iter()
+                    .all(|byte| is_whitespace(*byte));
+                if is_whitespace && is_within(lines, lineno) {
+                    alignables[lineno] = Some(Alignable {
+                        first: tokens.tokens[first].kind,
+                        line_start,
+                        text_start: tokens.tokens[first].pos.start,
+                        gap_start,
+                        gap_end,
+                    });
+                }
+            }
+            i += 1;
+        }
+        first = i;
+    }
+
+    let mut edits = Vec::new();
+    let mut lineno = 0;
+    while lineno < alignables.len() {
+        let head = match alignables[lineno] {
+            Some(ref head) => head,
+            None => {
+                lineno += 1;
+                continue;
+            }
+        };
+        let indentation = &bytes[head.line_start..head.text_start];
+        let column: Vec<&Alignable> = alignables[lineno..]
+            .iter()
+            .map_while(|alignable| alignable.as_ref())
+            .take_while(|alignable| {
+                alignable.first == head.first
+                    && bytes[alignable.line_start..alignable.text_start] == *indentation
+            })
+            .collect();
+        lineno += column.len();
+        if column.len() < 2 {
+            continue;
+        }
+        let width = column
+            .iter()
+            .map(|alignable| alignable.gap_start - alignable.line_start + 1)
+            .max()
+            .unwrap_or(0);
+        for alignable in column {
+            let text = " ".repeat(width - (alignable.gap_start - alignable.line_start));
+            if bytes[alignable.gap_start..alignable.gap_end] != *text.as_bytes() {
+                let length = alignable.gap_end - alignable.gap_start;
+                edits.push((source.pos(alignable.gap_start, length), text));
+            }
+        }
+    }
+    edits
+}
+
+/// The spelling of the style of each identifier of the file which refers to a declaration,
+/// the standard case for declarations of the standard libraries and otherwise the spelling
+/// of the declaration when the style spells identifiers as declared
+pub fn spellings(
+    project: &Project,
+    resolution: &NameResolution,
+    file_name: &str,
+    style: &FormatStyle,
+) -> Vec<(SrcPos, String)> {
+    resolution
+        .references()
+        .iter()
+        .filter(|reference| reference.pos.source.file_name() == Some(file_name))
+        .filter_map(|reference| {
+            let entity = reference.entities.first()?;
+            let is_standard = entity
+                .pos
+                .source
+                .file_name()
+                .and_then(|file_name| project.get_file(file_name))
+                .is_some_and(|file| STANDARD_LIBRARIES.contains(&file.library_name.as_str()));
+            let spelling = if is_standard {
+                style.standard_case?.apply(&entity.designator)
+            } else if style.declared_spelling {
+                entity.designator.clone()
+            } else {
+                return None;
+            };
+            Some((reference.pos.clone(), spelling))
+        })
+        .collect()
+}
+
+fn is_keyword(tokens: &SourceTokens, i: usize) -> bool {
+    let pos = &tokens.tokens[i].pos;
+    tokens.tokens[i].kind != Identifier
+        && tokens.code.bytes[pos.start..pos.start + pos.length]
+            .iter()
+            .all(u8::is_ascii_alphabetic)
+}
+
+/// The replacements of the keywords which are not in the case of the style
+fn keyword_edits(
+    source: &Source,
+    tokens: &SourceTokens,
+    case: Case,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let bytes = &tokens.code.bytes;
+    let line_ranges = lines_of(bytes);
+    (0..tokens.tokens.len())
+        .filter(|i| is_keyword(tokens, *i))
+        .filter_map(|i| {
+            let pos = &tokens.tokens[i].pos;
+            if !is_within(lines, line_of(&line_ranges, pos.start)) {
+                return None;
+            }
+            let text = Latin1String::from_vec(bytes[pos.start..pos.start + pos.length].to_vec())
+                .to_string();
+            let cased = case.apply(&text);
+            if cased == text {
+                None
+            } else {
+                Some((source.pos(pos.start, pos.length), cased))
+            }
+        })
+        .collect()
+}
+
+/// The replacements of the identifiers which differ from their spelling only by case
+fn spelling_edits(
+    source: &Source,
+    bytes: &[u8],
+    spellings: &[(SrcPos, String)],
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let line_ranges = lines_of(bytes);
+    spellings
+        .iter()
+        .filter(|(pos, spelling)| {
+            let text = match bytes.get(pos.start..pos.start + pos.length) {
+                Some(text) => text,
+                None => {
+                    return false;
+                }
+            };
+            is_within(lines, line_of(&line_ranges, pos.start))
+                && text.eq_ignore_ascii_case(spelling.as_bytes())
+                && text != spelling.as_bytes()
+        })
+        .map(|(pos, spelling)| (source.pos(pos.start, pos.length), spelling.clone()))
+        .collect()
+}
+
+/// The keywords which are not in the case of the style and the identifiers which are not
+/// spelled as given as lint findings with a fix
+pub fn case_findings(
+    source: &Source,
+    style: &FormatStyle,
+    spellings: &[(SrcPos, String)],
+) -> Vec<Message> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let finding = |(pos, text): (SrcPos, String), code: &str| {
+        let written =
+            Latin1String::from_vec(tokens.code.bytes[pos.start..pos.start + pos.length].to_vec())
+                .to_string();
+        warning(&pos, &format!("'{}' is not written as '{}'", written, text))
+            .with_code(code)
+            .with_fix(&format!("Replace with '{}'", text), vec![(pos, text)])
+    };
+    let mut findings: Vec<Message> = match style.keyword_case {
+        Some(case) => keyword_edits(source, &tokens, case, None)
+            .into_iter()
+            .map(|edit| finding(edit, "keyword-case"))
+            .collect(),
+        None => Vec::new(),
+    };
+    findings.extend(
+        spelling_edits(source, &tokens.code.bytes, spellings, None)
+            .into_iter()
+            .map(|edit| finding(edit, "identifier-case")),
+    );
+    findings.sort_by_key(|finding| finding.pos.start);
+    findings
+}
+
+/// A comment after the last token of a line
+struct TrailingComment {
+    line_start: usize,
+    /// The end of the last token
+    code_end: usize,
+    start: usize,
+}
+
+/// The replacements of the whitespace before the trailing comments of consecutive lines
+/// within the first and last line which align them in one column
+fn trailing_comment_edits(
+    source: &Source,
+    tokens: &SourceTokens,
+    line_ranges: &[(usize, usize)],
+    indentation: &Indentation,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let bytes = &tokens.code.bytes;
+    let mut comments: Vec<Option<TrailingComment>> = line_ranges.iter().map(|_| None).collect();
+    for (i, token) in tokens.tokens.iter().enumerate() {
+        let code_end = token.pos.start + token.pos.length;
+        let lineno = line_of(line_ranges, code_end);
+        let (line_start, line_end) = line_ranges[lineno];
+        let next_start = tokens
+            .tokens
+            .get(i + 1)
+            .map(|next| next.pos.start)
+            .unwrap_or(bytes.len());
+        if next_start <= line_end || !is_within(lines, lineno) {
+            continue;
+        }
+        let rest = &bytes[code_end..line_end];
+        let start = rest
+            .iter()
+            .position(|byte| !is_whitespace(*byte))
+            .filter(|offset| rest[*offset..].starts_with(b"--"));
+        if let Some(offset) = start {
+            comments[lineno] = Some(TrailingComment {
+                line_start,
+                code_end,
+                start: code_end + offset,
+            });
+        }
+    }
+
+    let mut edits = Vec::new();
+    let mut lineno = 0;
+    while lineno < comments.len() {
+        let column: Vec<&TrailingComment> = comments[lineno..]
+            .iter()
+            .map_while(|comment| comment.as_ref())
+            .collect();
+        lineno += column.len().max(1);
+        if let [comment] = column.as_slice() {
+            if comment.code_end == comment.start {
+                edits.push((source.pos(comment.start, 0), " ".to_owned()));
+            }
+            continue;
+        }
+        let code_width = |comment: &TrailingComment| {
+            indentation.width_of(&bytes[comment.line_start..comment.code_end])
+        };
+        let width = column
+            .iter()
+            .map(|comment| code_width(comment))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for comment in column {
+            let text = " ".repeat(width - code_width(comment));
+            if bytes[comment.code_end..comment.start] != *text.as_bytes() {
+                let length = comment.start - comment.code_end;
+                edits.push((source.pos(comment.code_end, length), text));
+            }
+        }
+    }
+    edits
+}
+
+/// A comment on a line of its own with text which may be reflowed
+struct CommentLine<'a> {
+    lineno: usize,
+    /// The leading whitespace
+    indentation: &'a [u8],
+    /// The dashes, any ! or | of documentation comments and the space after them
+    prefix: &'a [u8],
+    text: &'a [u8],
+    /// The text is a list item which starts a new paragraph
+    is_item: bool,
+}
+
+fn comment_line<'a>(
+    bytes: &'a [u8],
+    lineno: usize,
+    (start, end): (usize, usize),
+) -> Option<CommentLine<'a>> {
+    let text_start = (start..end).find(|i| !is_whitespace(bytes[*i]))?;
+    if !bytes[text_start..end].starts_with(b"--") {
+        return None;
+    }
+    let mut prefix_end = text_start + 2;
+    while prefix_end < end && (bytes[prefix_end] == b'!' || bytes[prefix_end] == b'|') {
+        prefix_end += 1;
+    }
+    // Separators, empty comments and indented text such as code examples are kept
+    if bytes.get(prefix_end) != Some(&b' ') || prefix_end + 1 >= end {
+        return None;
+    }
+    let text_end = (prefix_end + 1..end)
+        .rev()
+        .find(|i| !is_whitespace(bytes[*i]))
+        .map(|i| i + 1)?;
+    let text = &bytes[prefix_end + 1..text_end];
+    if is_whitespace(text[0]) {
+        return None;
+    }
+    Some(CommentLine {
+        lineno,
+        indentation: &bytes[start..text_start],
+        prefix: &bytes[text_start..prefix_end + 1],
+        text,
+        is_item: text.starts_with(b"- ") || text.starts_with(b"* "),
+    })
+}
+
+/// The replacements of the paragraphs of comments within the first and last line which
+/// have a line wider than the width by the words of the paragraph filled up to the width
+fn reflow_edits(
+    source: &Source,
+    bytes: &[u8],
+    line_ranges: &[(usize, usize)],
+    indentation: &Indentation,
+    width: usize,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let comment_lines: Vec<Option<CommentLine>> = line_ranges
+        .iter()
+        .enumerate()
+        .map(|(lineno, range)| {
+            comment_line(bytes, lineno, *range).filter(|_| is_within(lines, lineno))
+        })
+        .collect();
+
+    let mut edits = Vec::new();
+    let mut lineno = 0;
+    while lineno < comment_lines.len() {
+        let head = match comment_lines[lineno] {
+            Some(ref head) => head,
+            None => {
+                lineno += 1;
+                continue;
+            }
+        };
+        let paragraph: Vec<&CommentLine> = comment_lines[lineno..]
+            .iter()
+            .map_while(|line| line.as_ref())
+            .enumerate()
+            .take_while(|(i, line)| {
+                *i == 0
+                    || (!line.is_item
+                        && line.indentation == head.indentation
+                        && line.prefix == head.prefix)
+            })
+            .map(|(_, line)| line)
+            .collect();
+        lineno += paragraph.len();
+
+        let line_width = |line: &CommentLine| {
+            indentation.width_of(line.indentation) + line.prefix.len() + line.text.len()
+        };
+        if paragraph.iter().all(|line| line_width(line) <= width) {
+            continue;
+        }
+        let start_width = indentation.width_of(head.indentation) + head.prefix.len();
+        let mut reflowed: Vec<Vec<u8>> = Vec::new();
+        let mut current: Vec<u8> = Vec::new();
+        let mut current_width = 0;
+        let words = paragraph
+            .iter()
+            .flat_map(|line| line.text.split(|byte| is_whitespace(*byte)))
+            .filter(|word| !word.is_empty());
+        for word in words {
+            if !current.is_empty() && current_width + 1 + word.len() > width {
+                reflowed.push(current);
+                current = Vec::new();
+            }
+            if current.is_empty() {
+                current.extend_from_slice(head.indentation);
+                current.extend_from_slice(head.prefix);
+                current_width = start_width;
+            } else {
+                current.push(b' ');
+                current_width += 1;
+            }
+            current.extend_from_slice(word);
+            current_width += word.len();
+        }
+        reflowed.push(current);
+
+        let start = line_ranges[head.lineno].0;
+        let end = line_ranges[paragraph[paragraph.len() - 1].lineno].1;
+        let text = Latin1String::from_vec(reflowed.join(&b'\n')).to_string();
+        if bytes[start..end] != *text.as_bytes() {
+            edits.push((source.pos(start, end - start), text));
+        }
+    }
+    edits
+}
+
+/// The replacements which align the trailing comments and reflow the comment paragraphs
+/// within the first and last line as given by the style
+fn comment_edits(
+    source: &Source,
+    style: &FormatStyle,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let bytes = &tokens.code.bytes;
+    let line_ranges = lines_of(bytes);
+    let mut edits = Vec::new();
+    if style.align_comments {
+        edits.extend(trailing_comment_edits(
+            source,
+            &tokens,
+            &line_ranges,
+            &style.indentation,
+            lines,
+        ));
+    }
+    if let Some(width) = style.comment_width {
+        edits.extend(reflow_edits(
+            source,
+            bytes,
+            &line_ranges,
+            &style.indentation,
+            width,
+            lines,
+        ));
+    }
+    edits.sort_by_key(|(pos, _)| pos.start);
+    edits
+}
+
+fn is_logical(kind: Kind) -> bool {
+    matches!(kind, And | Or | Nand | Nor | Xor | Xnor)
+}
+
+/// The line breaks by the index of the token after them which wrap the lines of code between
+/// the first and last line which are wider than the maximum width, each line is broken where the code before the break fits
+/// preferring the least nested break and then the last one, after the semicolon of an
+/// interface list, after a comma, before a logical operator or, when no other break fits,
+/// after an opening parenthesis. The continuation lines are indented one level deeper
+/// for each parenthesis enclosing the break
+fn wrap(
+    source: &Source,
+    indentation: &Indentation,
+    max_width: usize,
+    lines: Option<(usize, usize)>,
+) -> Vec<(usize, String)> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let levels = tokens.indentation_levels();
+    let bytes = &tokens.code.bytes;
+
+    let mut breaks = Vec::new();
+    let mut next_token = 0;
+    for (lineno, (start, end)) in lines_of(bytes).into_iter().enumerate() {
+        while next_token < tokens.tokens.len() && tokens.tokens[next_token].pos.start < start {
+            next_token += 1;
+        }
+        let text_start = (start..end)
+            .find(|i| !is_whitespace(bytes[*i]))
+            .unwrap_or(end);
+        let base = indentation.width_of(&bytes[start..text_start]);
+        if !is_within(lines, lineno) || base + end - text_start <= max_width {
+            continue;
+        }
+        let last_token = (next_token..tokens.tokens.len())
+            .take_while(|i| tokens.tokens[*i].pos.start < end)
+            .last()
+            .unwrap_or(next_token);
+
+        // The breaks before the tokens of the line by their rank, the token index and the
+        // indentation of the continuation line
+        let mut candidates = Vec::new();
+        let mut depth = 0;
+        for (n, pair) in tokens.tokens[next_token..=last_token]
+            .windows(2)
+            .enumerate()
+        {
+            let (i, before, kind) = (next_token + 1 + n, pair[0].kind, pair[1].kind);
+            if before == LeftPar {
+                depth += 1;
+            }
+            if kind == RightPar {
+                depth -= 1;
+            }
+            if levels[i].is_none() {
+                let continuation = base + indentation.size * cmp::max(depth, 1) as usize;
+                if before == SemiColon {
+                    candidates.push((3 * depth, i, continuation));
+                } else if before == Comma || is_logical(kind) {
+                    candidates.push((3 * depth + 1, i, continuation));
+                } else if before == LeftPar {
+                    candidates.push((3 * depth + 2, i, continuation));
+                }
+            }
+        }
+
+        let (mut column, mut offset) = (base, text_start);
+        while column + end - offset > max_width {
+            let column_of = |i: usize| column + tokens.tokens[i].pos.start - offset;
+            let shortens = candidates.iter().filter(|(_, i, continuation)| {
+                tokens.tokens[*i].pos.start > offset && column_of(*i) > *continuation
+            });
+            let fits = shortens.clone().filter(|(_, i, _)| {
+                let previous = &tokens.tokens[*i - 1].pos;
+                column_of(*i) - (tokens.tokens[*i].pos.start - previous.start - previous.length)
+                    <= max_width
+            });
+            let best = fits
+                .max_by_key(|(rank, i, _)| (-(*rank as isize), *i))
+                .or_else(|| shortens.min_by_key(|(_, i, _)| *i));
+            let (i, continuation) = match best {
+                Some((_, i, continuation)) => (*i, *continuation),
+                None => break,
+            };
+            breaks.push((i, format!("\n{}", indentation.text_of(continuation))));
+            offset = tokens.tokens[i].pos.start;
+            column = continuation;
+        }
+    }
+    breaks
+}
+
+/// The replacements which format the lines between the first and last line with the style
+/// and the spellings of identifiers, all lines are formatted when no lines are given
+///
+/// When only the indentation is changed the replacements are of leading and trailing
+/// whitespace, otherwise the lines are replaced by the formatted lines
+pub fn format(
+    source: &Source,
+    style: &FormatStyle,
+    spellings: &[(SrcPos, String)],
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    if !style.changes_layout() && spellings.is_empty() {
+        return indent(source, &style.indentation, lines);
+    }
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let bytes = &tokens.code.bytes;
+    let mut edits = layout(source, &tokens, style, lines);
+    if let Some(case) = style.keyword_case {
+        edits.extend(keyword_edits(source, &tokens, case, lines));
+    }
+    edits.extend(spelling_edits(source, bytes, spellings, lines));
+    edits.sort_by_key(|(pos, _)| pos.start);
+    let mut delta = line_delta(bytes, &edits);
+    let mut formatted = rewrite(bytes, &edits);
+    formatted = rewrite(
+        &formatted.bytes,
+        &indent(
+            &Source::from_latin1(formatted.clone()),
+            &style.indentation,
+            shift_lines(lines, delta),
+        ),
+    );
+    // Aligning may widen lines which are then wrapped and aligned again
+    let mut unaligned = formatted;
+    loop {
+        formatted = unaligned.clone();
+        for kinds in style.aligned() {
+            formatted = rewrite(
+                &formatted.bytes,
+                &align(
+                    &Source::from_latin1(formatted.clone()),
+                    kinds,
+                    shift_lines(lines, delta),
+                ),
+            );
+        }
+        let breaks = match style.max_width {
+            Some(max_width) => wrap(
+                &Source::from_latin1(formatted.clone()),
+                &style.indentation,
+                max_width,
+                shift_lines(lines, delta),
+            ),
+            None => Vec::new(),
+        };
+        // The aligned and unaligned code only differ in whitespace between tokens
+        let source = Source::from_latin1(unaligned.clone());
+        let tokens = match SourceTokens::new(&source) {
+            Some(tokens) if !breaks.is_empty() => tokens,
+            _ => break,
+        };
+        let edits: Vec<(SrcPos, String)> = breaks
+            .into_iter()
+            .map(|(i, text)| {
+                let previous = &tokens.tokens[i - 1].pos;
+                let start = previous.start + previous.length;
+                (source.pos(start, tokens.tokens[i].pos.start - start), text)
+            })
+            .collect();
+        delta += line_delta(&unaligned.bytes, &edits);
+        unaligned = rewrite(&unaligned.bytes, &edits);
+    }
+    let edits = comment_edits(
+        &Source::from_latin1(formatted.clone()),
+        style,
+        shift_lines(lines, delta),
+    );
+    delta += line_delta(&formatted.bytes, &edits);
+    formatted = rewrite(&formatted.bytes, &edits);
+
+    let (first, last) = lines.unwrap_or((0, usize::MAX));
+    let old_lines = lines_of(bytes);
+    let new_lines = lines_of(&formatted.bytes);
+    if first >= old_lines.len() {
+        return Vec::new();
+    }
+    let start = old_lines[first].0;
+    let end = old_lines[last.min(old_lines.len() - 1)].1;
+    let new_start = new_lines[first].0;
+    let new_last = match shift_lines(Some((first, last)), delta) {
+        Some((_, new_last)) => new_last,
+        None => last,
+    };
+    let new_end = new_lines[new_last.min(new_lines.len() - 1)].1;
+    if bytes[start..end] == formatted.bytes[new_start..new_end] {
+        return Vec::new();
+    }
+    let text = Latin1String::from_vec(formatted.bytes[new_start..new_end].to_vec()).to_string();
+    vec![(source.pos(start, end - start), text)]
+}
+
+/// The replacement of the differing part of the old bytes by the new bytes which remains
+/// after removing the common prefix and suffix
+fn trimmed(source: &Source, start: usize, old: &[u8], new: &[u8]) -> Option<(SrcPos, String)> {
+    if old == new {
+        return None;
+    }
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let text = Latin1String::from_vec(new[prefix..new.len() - suffix].to_vec()).to_string();
+    Some((
+        source.pos(start + prefix, old.len() - prefix - suffix),
+        text,
+    ))
+}
+
+/// The replacements of the whitespace and characters which differ between the old and new
+/// line when they only differ in whitespace and case, otherwise of the differing part
+fn line_edits(source: &Source, start: usize, old: &[u8], new: &[u8]) -> Vec<(SrcPos, String)> {
+    let words = |line: &[u8]| -> Vec<u8> {
+        line.iter()
+            .filter(|byte| !is_whitespace(**byte))
+            .map(u8::to_ascii_lowercase)
+            .collect()
+    };
+    if words(old) != words(new) {
+        return trimmed(source, start, old, new).into_iter().collect();
+    }
+    let mut edits: Vec<(SrcPos, String)> = Vec::new();
+    let mut push = |i: usize, old_end: usize, new_part: &[u8]| {
+        if &old[i..old_end] == new_part {
+            return;
+        }
+        let text = Latin1String::from_vec(new_part.to_vec()).to_string();
+        match edits.last_mut() {
+            Some((pos, last)) if pos.start + pos.length == start + i => {
+                *pos = source.pos(pos.start, pos.length + old_end - i);
+                last.push_str(&text);
+            }
+            _ => edits.push((source.pos(start + i, old_end - i), text)),
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let old_end = i + old[i..].iter().take_while(|b| is_whitespace(**b)).count();
+        let new_end = j + new[j..].iter().take_while(|b| is_whitespace(**b)).count();
+        push(i, old_end, &new[j..new_end]);
+        i = old_end;
+        j = new_end;
+        if i == old.len() {
+            return edits;
+        }
+        push(i, i + 1, &new[j..=j]);
+        i += 1;
+        j += 1;
+    }
+}
+
+/// The replacements split into a replacement of the changed part of each line when the
+/// number of lines is unchanged, otherwise into the changed part of all lines
+fn split_edits(
+    source: &Source,
+    bytes: &[u8],
+    edits: Vec<(SrcPos, String)>,
+) -> Vec<(SrcPos, String)> {
+    let mut split = Vec::new();
+    for (pos, text) in edits {
+        let old = &bytes[pos.start..pos.start + pos.length];
+        let new = match Latin1String::from_utf8(&text) {
+            Ok(new) => new.bytes,
+            Err(..) => {
+                split.push((pos, text));
+                continue;
+            }
+        };
+        let old_lines: Vec<&[u8]> = old.split(|byte| *byte == b'\n').collect();
+        let new_lines: Vec<&[u8]> = new.split(|byte| *byte == b'\n').collect();
+        if old_lines.len() != new_lines.len() {
+            split.extend(trimmed(source, pos.start, old, &new));
+            continue;
+        }
+        let mut start = pos.start;
+        for (old_line, new_line) in old_lines.into_iter().zip(new_lines) {
+            split.extend(line_edits(source, start, old_line, new_line));
+            start += old_line.len() + 1;
+        }
+    }
+    split
+}
+
+/// The replacements which format the selection from the start to the end offset, the lines
+/// of the selection are formatted and the replacements within the selection are kept where
+/// the selection includes the whitespace before it and after it on its first and last line
+pub fn format_selection(
+    source: &Source,
+    style: &FormatStyle,
+    spellings: &[(SrcPos, String)],
+    start: usize,
+    end: usize,
+) -> Vec<(SrcPos, String)> {
+    let code = match source.contents() {
+        Ok(code) => code,
+        Err(..) => {
+            return Vec::new();
+        }
+    };
+    let bytes = &code.bytes;
+    let end = end.min(bytes.len());
+    let start = start.min(end);
+    let line_ranges = lines_of(bytes);
+    let first = line_of(&line_ranges, start);
+    let mut last = line_of(&line_ranges, end);
+    // A selection of whole lines ends at the start of the line after them
+    if last > first && line_ranges[last].0 == end {
+        last -= 1;
+    }
+    let (line_start, _) = line_ranges[first];
+    let start = if bytes[line_start..start]
+        .iter()
+        .all(|byte| is_whitespace(*byte))
+    {
+        line_start
+    } else {
+        start
+    };
+    let (_, line_end) = line_ranges[line_of(&line_ranges, end)];
+    let end = if bytes[end..line_end].iter().all(|byte| is_whitespace(*byte)) {
+        line_end
+    } else {
+        end
+    };
+    split_edits(
+        source,
+        bytes,
+        format(source, style, spellings, Some((first, last))),
+    )
+    .into_iter()
+    .filter(|(pos, _)| start <= pos.start && pos.start + pos.length <= end)
+    .collect()
+}
+
+/// The replacements of leading and trailing whitespace which indent the lines between
+/// the first and last line, all lines are indented when no lines are given
+fn indent(
+    source: &Source,
+    indentation: &Indentation,
+    lines: Option<(usize, usize)>,
+) -> Vec<(SrcPos, String)> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let levels = tokens.indentation_levels();
+    let bytes = &tokens.code.bytes;
+
+    let mut edits = Vec::new();
+    let mut next_token = 0;
+    // The change of indentation of the line being continued
+    let mut delta: isize = 0;
+    for (lineno, (start, end)) in lines_of(bytes).into_iter().enumerate() {
+        while next_token < tokens.tokens.len() && tokens.tokens[next_token].pos.start < start {
+            next_token += 1;
+        }
+        let text_start = (start..end)
+            .find(|i| !is_whitespace(bytes[*i]))
+            .unwrap_or(end);
+        let text_end = (text_start..end)
+            .rev()
+            .find(|i| !is_whitespace(bytes[*i]))
+            .map(|i| i + 1)
+            .unwrap_or(text_start);
+
+        let old_width = indentation.width_of(&bytes[start..text_start]);
+        let starts_token =
+            next_token < tokens.tokens.len() && tokens.tokens[next_token].pos.start == text_start;
+        let is_comment = bytes[text_start..end].starts_with(b"--");
+
+        let width = if starts_token {
+            match levels[next_token] {
+                Some(level) => {
+                    let width = level * indentation.size;
+                    delta = width as isize - old_width as isize;
+                    Some(width)
+                }
+                None => Some((old_width as isize + delta).max(0) as usize),
+            }
+        } else if is_comment {
+            // A comment belongs to what follows it, within the construct before an end
+            match levels.get(next_token).cloned().unwrap_or(Some(0)) {
+                Some(level) if tokens.kind(next_token) == Some(End) => {
+                    Some((level + 1) * indentation.size)
+                }
+                Some(level) => Some(level * indentation.size),
+                None => Some((old_width as isize + delta).max(0) as usize),
+            }
+        } else {
+            None
+        };
+
+        if !is_within(lines, lineno) {
+            continue;
+        }
+
+        if text_start == end {
+            if start < end {
+                edits.push((source.pos(start, end - start), String::new()));
+            }
+            continue;
+        }
+        if let Some(width) = width {
+            let text = indentation.text_of(width);
+            if text.as_bytes() != &bytes[start..text_start] {
+                edits.push((source.pos(start, text_start - start), text));
+            }
+        }
+        if text_end < end {
+            edits.push((source.pos(text_end, end - text_end), String::new()));
+        }
+    }
+    edits
+}
+
+/// The contents of the source with all lines formatted
+pub fn format_contents(
+    source: &Source,
+    style: &FormatStyle,
+    spellings: &[(SrcPos, String)],
+) -> io::Result<Latin1String> {
+    let bytes = apply(
+        &source.contents()?.bytes,
+        &format(source, style, spellings, None),
+    );
+    Ok(Latin1String::from_vec(bytes))
+}
+
+/// The indentation of the line where a blank line is indented as a statement starting on it
+pub fn indent_line(
+    source: &Source,
+    indentation: &Indentation,
+    line: usize,
+) -> Vec<(SrcPos, String)> {
+    let code = match source.contents() {
+        Ok(code) => code,
+        Err(..) => {
+            return Vec::new();
+        }
+    };
+    let (start, end) = match lines_of(&code.bytes).get(line) {
+        Some(line) => *line,
+        None => {
+            return Vec::new();
+        }
+    };
+    let text_start = (start..end)
+        .find(|i| !is_whitespace(code.bytes[*i]))
+        .unwrap_or(end);
+    let indentation_only = |(pos, _): &(SrcPos, String)| pos.start == start;
+    if text_start < end {
+        return indent(source, indentation, Some((line, line)))
+            .into_iter()
+            .filter(indentation_only)
+            .collect();
+    }
+
+    // The leading whitespace is at the same position in the source with a placeholder
+    let mut bytes = code.bytes.clone();
+    bytes.insert(end, b'x');
+    let placeholder = match Source::from_str(&Latin1String::from_vec(bytes).to_string()) {
+        Ok(placeholder) => placeholder,
+        Err(..) => {
+            return Vec::new();
+        }
+    };
+    indent(&placeholder, indentation, Some((line, line)))
+        .into_iter()
+        .filter(indentation_only)
+        .map(|(pos, text)| (source.pos(pos.start, pos.length), text))
+        .collect()
+}
+
+/// The keywords which follow end for the construct opened by the keyword at the index
+fn end_keywords(tokens: &SourceTokens, opener: usize) -> Option<&'static str> {
+    let keywords = match tokens.kind(opener)? {
+        Entity => "entity",
+        Architecture => "architecture",
+        Configuration => "configuration",
+        Package if tokens.kind(opener + 1) == Some(Body) => "package body",
+        Package => "package",
+        Context => "context",
+        Component => "component",
+        Function => "function",
+        Procedure => "procedure",
+        Units => "units",
+        Record => "record",
+        Protected if tokens.kind(opener + 1) == Some(Body) => "protected body",
+        Protected => "protected",
+        Process => "process",
+        Block => "block",
+        Loop => "loop",
+        Case => "case",
+        For => "for",
+        If if tokens.kind(tokens.find_forward(opener, &[Then, Generate])?) == Some(Generate) => {
+            "generate"
+        }
+        If => "if",
+        Generate => "generate",
+        _ => {
+            return None;
+        }
+    };
+    Some(keywords)
+}
+
+/// The designator of a design unit or subprogram or the label of a statement which is
+/// repeated after end
+fn end_label(tokens: &SourceTokens, opener: usize) -> Option<usize> {
+    let kind = tokens.kind(opener)?;
+    let label = match kind {
+        Entity | Architecture | Configuration | Context | Component | Function | Procedure => {
+            opener + 1
+        }
+        Package if tokens.kind(opener + 1) == Some(Body) => opener + 2,
+        Package => opener + 1,
+        // The designator of a type is before is
+        Protected | Units | Record => opener.checked_sub(2)?,
+        _ => {
+            let boundaries: &[Kind] =
+                &[SemiColon, Begin, Then, Else, Is, Loop, Generate, RightArrow];
+            let start = (0..opener)
+                .rev()
+                .find(|i| boundaries.contains(&tokens.tokens[*i].kind))
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            if tokens.kind(start + 1) != Some(Colon) {
+                return None;
+            }
+            start
+        }
+    };
+    match tokens.kind(label)? {
+        Identifier | StringLiteral => Some(label),
+        _ => None,
+    }
+}
+
+/// Completes an end directly followed by the semi colon at the offset
+/// with the keywords and the label of the construct it ends
+pub fn complete_end(source: &Source, offset: usize) -> Option<(SrcPos, String)> {
+    let tokens = SourceTokens::new(source)?;
+    let semi = tokens.tokens.iter().position(|token| {
+        token.kind == SemiColon && token.pos.start < offset && offset <= token.pos.start + 1
+    })?;
+    let end = semi.checked_sub(1)?;
+    let opener = tokens.opener_of(end)?;
+
+    let mut text = format!(" {}", end_keywords(&tokens, opener)?);
+    if let Some(label) = end_label(&tokens, opener) {
+        text.push(' ');
+        text.push_str(&tokens.text(label));
+    }
+    let end_pos = &tokens.tokens[end].pos;
+    Some((source.pos(end_pos.start + end_pos.length, 0), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn spaces(size: usize) -> Indentation {
+        Indentation { size, tabs: false }
+    }
+
+    fn style(size: usize) -> FormatStyle {
+        FormatStyle::new(spaces(size))
+    }
+
+    fn apply(code: &str, style: &FormatStyle, lines: Option<(usize, usize)>) -> String {
+        let source = Source::from_str(code).unwrap();
+        let mut code = code.to_owned();
+        for (pos, text) in format(&source, style, &[], lines).iter().rev() {
+            code.replace_range(pos.start..pos.start + pos.length, text);
+        }
+        code
+    }
+
+    /// The code after applying the edits, formatting the whole code is checked to be
+    /// idempotent for every code formatted by the tests
+    fn formatted(code: &str, style: &FormatStyle, lines: Option<(usize, usize)>) -> String {
+        let code = apply(code, style, lines);
+        if lines.is_none() {
+            assert_eq!(
+                apply(&code, style, None),
+                code,
+                "formatting is not idempotent"
+            );
+        }
+        code
+    }
+
+    #[test]
+    fn indents_design_units_and_statements() {
+        let code = "
+library ieee;
+    use ieee.std_logic_1164.all;
+
+entity ent is
+generic (width : natural);
+port (
+  clk : in std_logic;
+  q : out std_logic);
+end entity;
+
+architecture rtl of ent is
+type state_t is (idle, running);
+type rec_t is record
+field : natural;
+end record;
+signal state : state_t;
+begin
+main : process (clk)
+variable count : natural;
+begin
+if rising_edge(clk) then
+case state is
+when idle =>
+-- Start counting
+state <= running;
+when others =>
+for i in 0 to 3 loop
+count := count + i;
+end loop;
+end case;
+elsif state = idle then
+q <= '1' when count > 0 else
+     '0';
+else
+null;
+end if;
+end process;
+
+gen : if width > 1 generate
+inst : entity work.child
+  port map (
+    clk => clk);
+else generate
+end generate;
+end architecture;
+";
+        assert_eq!(
+            formatted(code, &style(2), None),
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity ent is
+  generic (width : natural);
+  port (
+    clk : in std_logic;
+    q : out std_logic);
+end entity;
+
+architecture rtl of ent is
+  type state_t is (idle, running);
+  type rec_t is record
+    field : natural;
+  end record;
+  signal state : state_t;
+begin
+  main : process (clk)
+    variable count : natural;
+  begin
+    if rising_edge(clk) then
+      case state is
+        when idle =>
+          -- Start counting
+          state <= running;
+        when others =>
+          for i in 0 to 3 loop
+            count := count + i;
+          end loop;
+      end case;
+    elsif state = idle then
+      q <= '1' when count > 0 else
+           '0';
+    else
+      null;
+    end if;
+  end process;
+
+  gen : if width > 1 generate
+    inst : entity work.child
+      port map (
+        clk => clk);
+  else generate
+  end generate;
+end architecture;
+"
+        );
+    }
+
+    #[test]
+    fn subprograms_and_package_bodies() {
+        let code = "package body pkg is
+function calc(value : natural) return natural is
+variable result : natural;
+begin
+return result;
+end function;
+-- Trailing comment
+end package body;";
+        assert_eq!(
+            formatted(code, &style(4), None),
+            "package body pkg is
+    function calc(value : natural) return natural is
+        variable result : natural;
+    begin
+        return result;
+    end function;
+    -- Trailing comment
+end package body;"
+        );
+    }
+
+    /// The code after completing the end before the cursor marker
+    fn completed(code: &str) -> String {
+        let offset = code.find('|').unwrap();
+        let code = code.replacen('|', "", 1);
+        let source = Source::from_str(&code).unwrap();
+        let mut code = code.to_owned();
+        if let Some((pos, text)) = complete_end(&source, offset) {
+            code.replace_range(pos.start..pos.start, &text);
+        }
+        code
+    }
+
+    #[test]
+    fn completes_end_of_construct() {
+        assert_eq!(
+            completed("entity ent is\nend;|"),
+            "entity ent is\nend entity ent;"
+        );
+        assert_eq!(
+            completed("package body pkg is\nend;|"),
+            "package body pkg is\nend package body pkg;"
+        );
+        assert_eq!(
+            completed(
+                "architecture rtl of ent is\nbegin\n  main : process\n  begin\n    if a then\n    end;|"
+            ),
+            "architecture rtl of ent is\nbegin\n  main : process\n  begin\n    if a then\n    end if;"
+        );
+        assert_eq!(
+            completed(
+                "architecture rtl of ent is\nbegin\n  main : process\n  begin\n    wait;\n  end;|\nend architecture;"
+            ),
+            "architecture rtl of ent is\nbegin\n  main : process\n  begin\n    wait;\n  end process main;\nend architecture;"
+        );
+        assert_eq!(
+            completed("architecture rtl of ent is\nbegin\n  gen : if a generate\n  end;|"),
+            "architecture rtl of ent is\nbegin\n  gen : if a generate\n  end generate gen;"
+        );
+        assert_eq!(
+            completed("package pkg is\n  type rec_t is record\n    a : bit;\n  end;|"),
+            "package pkg is\n  type rec_t is record\n    a : bit;\n  end record rec_t;"
+        );
+        assert_eq!(
+            completed("function f return bit is\nbegin\n  for i in 0 to 1 loop\n  end;|"),
+            "function f return bit is\nbegin\n  for i in 0 to 1 loop\n  end loop;"
+        );
+        // Ends which are already complete are left as they are
+        assert_eq!(
+            completed("entity ent is\nend entity;|"),
+            "entity ent is\nend entity;"
+        );
+    }
+
+    #[test]
+    fn formats_contents_of_source() {
+        let code = "entity ent is\nport (clk : in bit);  \nend entity;\n";
+        let source = Source::from_str(code).unwrap();
+        assert_eq!(
+            format_contents(&source, &style(2), &[])
+                .unwrap()
+                .to_string(),
+            "entity ent is\n  port (clk : in bit);\nend entity;\n"
+        );
+    }
+
+    #[test]
+    fn indents_new_line() {
+        let code = "architecture rtl of ent is\nbegin\n  main : process\n  begin\n\n  end process;\n    \nend architecture;";
+        let source = Source::from_str(code).unwrap();
+        let indent = |line| indent_line(&source, &spaces(2), line);
+
+        let blank = indent(4);
+        assert_eq!(blank.len(), 1);
+        assert_eq!(blank[0].0.start, code.find("\n\n").unwrap() + 1);
+        assert_eq!(blank[0].0.length, 0);
+        assert_eq!(blank[0].1, "    ");
+
+        // The whitespace of a blank line is replaced
+        let whitespace = indent(6);
+        assert_eq!(whitespace[0].0.length, 4);
+        assert_eq!(whitespace[0].1, "  ");
+
+        // A line with text is indented by its first token
+        assert_eq!(indent(5), vec![]);
+    }
+
+    #[test]
+    fn tabs_and_trailing_whitespace() {
+        let code = "entity ent is  \n   \n        port (clk : in bit);\nend entity;";
+        assert_eq!(
+            formatted(
+                code,
+                &FormatStyle::new(Indentation {
+                    size: 4,
+                    tabs: true
+                }),
+                None
+            ),
+            "entity ent is\n\n\tport (clk : in bit);\nend entity;"
+        );
+    }
+
+    #[test]
+    fn only_formats_lines_within_range() {
+        let code = "entity ent is\nport (a : in bit;\nb : in bit);\nend entity;";
+        assert_eq!(
+            formatted(code, &style(2), Some((2, 3))),
+            "entity ent is\nport (a : in bit;\n  b : in bit);\nend entity;"
+        );
+    }
+
+    fn full_style(size: usize) -> FormatStyle {
+        FormatStyle {
+            indentation: spaces(size),
+            operator_spacing: true,
+            comma_spacing: true,
+            one_item_per_line: true,
+            align_colons: false,
+            align_arrows: false,
+            align_assignments: false,
+            keyword_case: None,
+            standard_case: None,
+            declared_spelling: false,
+            align_comments: false,
+            comment_width: None,
+            max_width: None,
+        }
+    }
+
+    #[test]
+    fn spaces_operators_and_commas() {
+        let code = "architecture rtl of ent is
+  signal a,b:bit_vector(width-1 downto 0):=(others=>'0') ;
+  alias c is <<signal .tb.c:bit>>;
+begin
+  a<=b&c when b/=c or(a= -1) else b ;
+  d <= f(x , - 1)*2;
+end architecture;";
+        assert_eq!(
+            formatted(code, &full_style(2), None),
+            "architecture rtl of ent is
+  signal a, b : bit_vector(width - 1 downto 0) := (others => '0');
+  alias c is <<signal .tb.c : bit>>;
+begin
+  a <= b & c when b /= c or (a = -1) else b;
+  d <= f(x, -1) * 2;
+end architecture;"
+        );
+
+        // Only the enabled spacing is changed
+        let mut commas = style(2);
+        commas.comma_spacing = true;
+        assert_eq!(
+            formatted("foo(a ,b)<=x+1 ;", &commas, None),
+            "foo(a, b)<=x+1;"
+        );
+    }
+
+    #[test]
+    fn puts_each_item_on_its_own_line() {
+        let code = "entity ent is
+  generic (width : natural);
+  port (clk, rst : in bit; data : in bit_vector(width - 1 downto 0);
+        q : out bit);
+end entity;
+
+architecture rtl of ent is
+begin
+  inst : entity work.child port map (a => b, c => d);
+end architecture;";
+        assert_eq!(
+            formatted(code, &full_style(2), None),
+            "entity ent is
+  generic (width : natural);
+  port (
+    clk, rst : in bit;
+    data : in bit_vector(width - 1 downto 0);
+    q : out bit
+  );
+end entity;
+
+architecture rtl of ent is
+begin
+  inst : entity work.child port map (a => b, c => d);
+end architecture;"
+        );
+    }
+
+    #[test]
+    fn lays_out_lines_within_range() {
+        let code = "entity ent is\nport (a:in bit; b:in bit);\nend entity;\nconstant c:bit;";
+        let source = Source::from_str(code).unwrap();
+        let edits = format(&source, &full_style(2), &[], Some((1, 1)));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].0.start, code.find("port").unwrap());
+        assert_eq!(
+            formatted(code, &full_style(2), Some((1, 1))),
+            "entity ent is\n  port (\n    a : in bit;\n    b : in bit\n  );\nend entity;\nconstant c:bit;"
+        );
+        assert_eq!(formatted(code, &full_style(2), Some((9, 9))), code);
+    }
+
+    #[test]
+    fn aligns_colons_arrows_and_assignments() {
+        let code = "architecture rtl of ent is
+  signal clk : bit;
+  signal counter : natural := 0;
+  signal q: bit := '1';
+
+  constant width : natural := 8;
+begin
+  inst : entity work.child
+    port map (
+      clk => clk,
+      counter => counter,
+      q=> q);
+
+  a <= b;
+  counter <= counter + 1;
+  v := f(x <= y);
+end architecture;";
+        let mut aligned = style(2);
+        aligned.align_colons = true;
+        aligned.align_arrows = true;
+        aligned.align_assignments = true;
+        assert_eq!(
+            formatted(code, &aligned, None),
+            "architecture rtl of ent is
+  signal clk     : bit;
+  signal counter : natural := 0;
+  signal q       : bit     := '1';
+
+  constant width : natural := 8;
+begin
+  inst : entity work.child
+    port map (
+      clk     => clk,
+      counter => counter,
+      q       => q);
+
+  a       <= b;
+  counter <= counter + 1;
+  v       := f(x <= y);
+end architecture;"
+        );
+
+        // Only the lines within the range are aligned
+        assert_eq!(
+            formatted(code, &aligned, Some((1, 2))),
+            code.replacen("clk :", "clk     :", 1)
+        );
+    }
+
+    #[test]
+    fn normalizes_keywords_and_identifiers() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "ieee",
+            "std_logic_1164.vhd",
+            "package std_logic_1164 is
+  type STD_ULOGIC is ('0', '1');
+  function RISING_EDGE(s : STD_ULOGIC) return STD_ULOGIC;
+end package;",
+        );
+        let code = "library ieee;
+use ieee.std_logic_1164.all;
+
+ENTITY ent IS
+  PORT (Clk : IN std_ulogic);
+END ENTITY;
+
+architecture rtl of ent is
+  signal Count : STD_ULOGIC;
+begin
+  main : process (CLK)
+  begin
+    if Rising_Edge(clk) then
+      count <= COUNT AND Clk;
+    end if;
+  end process;
+end architecture;";
+        let file_name = project.add_file("lib", "ent.vhd", code);
+        let resolution = project.project.resolve();
+        let mut style = style(2);
+        style.keyword_case = Some(Case::Lower);
+        style.standard_case = Some(Case::Lower);
+        style.declared_spelling = true;
+        let spellings = spellings(&project.project, &resolution, &file_name, &style);
+
+        let source = project.project.get_file(&file_name).unwrap().source.clone();
+        let formatted = format_contents(&source, &style, &spellings).unwrap();
+        assert_eq!(
+            formatted.to_string(),
+            "library ieee;
+use ieee.std_logic_1164.all;
+
+entity ent is
+  port (Clk : in std_ulogic);
+end entity;
+
+architecture rtl of ent is
+  signal Count : std_ulogic;
+begin
+  main : process (Clk)
+  begin
+    if rising_edge(Clk) then
+      Count <= Count and Clk;
+    end if;
+  end process;
+end architecture;"
+        );
+
+        let findings = case_findings(&source, &style, &spellings);
+        let codes: Vec<&str> = findings
+            .iter()
+            .filter_map(|finding| finding.code.as_deref())
+            .collect();
+        assert_eq!(codes.len(), 13);
+        assert_eq!(findings[0].message, "'ENTITY' is not written as 'entity'");
+        assert!(codes.contains(&"identifier-case"));
+        assert_eq!(findings[0].fixes[0].edits[0].1, "entity");
+    }
+
+    #[test]
+    fn aligns_trailing_comments_and_reflows_paragraphs() {
+        let code = "package pkg is
+  -- The width of the data bus which is used by all of the interfaces of the design
+  -- and the depth
+  constant width : natural := 8; -- Bits
+  constant depth : natural := 16;  -- Words
+  -------------------------------------------------------------------------------------
+  --!   indented documentation text is kept
+  --! Short documentation
+  --
+  -- - a list item which is long enough to be reflowed onto the line after it
+end package;";
+        let mut style = style(2);
+        style.align_comments = true;
+        style.comment_width = Some(40);
+        assert_eq!(
+            formatted(code, &style, None),
+            "package pkg is
+  -- The width of the data bus which is
+  -- used by all of the interfaces of
+  -- the design and the depth
+  constant width : natural := 8;  -- Bits
+  constant depth : natural := 16; -- Words
+  -------------------------------------------------------------------------------------
+  --!   indented documentation text is kept
+  --! Short documentation
+  --
+  -- - a list item which is long enough
+  -- to be reflowed onto the line after
+  -- it
+end package;"
+        );
+
+        // Lines after a reflowed paragraph are still formatted within the range
+        let edits = format(&Source::from_str(code).unwrap(), &style, &[], Some((1, 4)));
+        assert_eq!(edits.len(), 1);
+        assert!(edits[0]
+            .1
+            .ends_with("constant depth : natural := 16; -- Words"));
+    }
+
+    /// The words of the comments before each token together with the token
+    fn comments_by_token(code: &str) -> Vec<(Vec<String>, String)> {
+        let source = Source::from_str(code).unwrap();
+        let tokens = SourceTokens::new(&source).unwrap();
+        let mut end = 0;
+        let mut comments = Vec::new();
+        for (i, token) in tokens.tokens.iter().enumerate() {
+            let gap = &code[end..token.pos.start];
+            let words: Vec<String> = gap
+                .lines()
+                .filter_map(|line| line.find("--").map(|start| &line[start + 2..]))
+                .flat_map(|comment| comment.split_whitespace())
+                .map(str::to_owned)
+                .collect();
+            comments.push((words, tokens.text(i)));
+            end = token.pos.start + token.pos.length;
+        }
+        comments
+    }
+
+    #[test]
+    fn comments_stay_with_their_tokens() {
+        let code = "-- Header of the file
+library ieee; -- The library
+use ieee.std_logic_1164.all;
+
+-- The entity
+entity ent is
+generic (width:natural:=8;-- Bits
+depth : natural); -- Words
+port (clk:in std_logic; -- The clock of all registers which are in the design of this entity
+-- Before the data
+data : out std_logic_vector(width-1 downto 0) -- The data
+);
+end entity;
+
+architecture rtl of ent is
+begin
+main : process (clk) -- Clocked
+begin
+-- Rising edge
+if rising_edge(clk) then
+data <= (others=>'0'); -- Reset
+end if;
+end process;
+end architecture;
+";
+        let style = FormatStyle {
+            indentation: spaces(2),
+            operator_spacing: true,
+            comma_spacing: true,
+            one_item_per_line: true,
+            align_colons: true,
+            align_arrows: true,
+            align_assignments: true,
+            keyword_case: Some(Case::Lower),
+            standard_case: None,
+            declared_spelling: false,
+            align_comments: true,
+            comment_width: Some(60),
+            max_width: None,
+        };
+        let once = formatted(code, &style, None);
+        assert_eq!(comments_by_token(&once), comments_by_token(code));
+        assert_eq!(formatted(&once, &style, None), once);
+        assert!(once.contains("  generic (\n    width : natural := 8; -- Bits\n"));
+        assert!(once.contains("\n    -- Before the data\n    data : out"));
+    }
+
+    /// The code after formatting the selection between the markers
+    fn formatted_selection(code: &str, style: &FormatStyle) -> String {
+        let start = code.find('[').unwrap();
+        let code = code.replacen('[', "", 1);
+        let end = code.find(']').unwrap();
+        let code = code.replacen(']', "", 1);
+        let source = Source::from_str(&code).unwrap();
+        let mut code = code.clone();
+        for (pos, text) in format_selection(&source, style, &[], start, end)
+            .iter()
+            .rev()
+        {
+            code.replace_range(pos.start..pos.start + pos.length, text);
+        }
+        code
+    }
+
+    #[test]
+    fn formats_selection_only() {
+        let code = "entity ent is
+port (a:in bit;
+b:in bit);
+end entity;";
+        // Whole lines are re-indented relative to the enclosing construct
+        assert_eq!(
+            formatted_selection(
+                code.replacen("port", "[port", 1)
+                    .replacen("b:", "]b:", 1)
+                    .as_str(),
+                &style(2)
+            ),
+            "entity ent is\n  port (a:in bit;\nb:in bit);\nend entity;"
+        );
+
+        let mut spacing = style(2);
+        spacing.operator_spacing = true;
+        // Only the spacing within the selection is changed
+        assert_eq!(
+            formatted_selection(
+                "entity ent is\n  port ([a:in] bit; b:in bit);\nend entity;",
+                &spacing
+            ),
+            "entity ent is\n  port (a : in bit; b:in bit);\nend entity;"
+        );
+        // The indentation belongs to a selection starting after it
+        assert_eq!(
+            formatted_selection(
+                "entity ent is\n    [port (a:in bit);]\nend entity;",
+                &spacing
+            ),
+            "entity ent is\n  port (a : in bit);\nend entity;"
+        );
+        // Lines after the selection are kept even when it is formatted as a whole
+        let mut breaks = style(2);
+        breaks.one_item_per_line = true;
+        assert_eq!(
+            formatted_selection(
+                "entity ent is\n  [port (a : in bit; b : in bit);\n]  generic (c : bit; d : bit);\nend entity;",
+                &breaks
+            ),
+            "entity ent is\n  port (\n    a : in bit;\n    b : in bit\n  );\n  generic (c : bit; d : bit);\nend entity;"
+        );
+    }
+
+    #[test]
+    fn formatting_is_idempotent_for_every_style() {
+        let code = "library ieee;
+use ieee.std_logic_1164.all;
+-- A counter which counts up to the width and then wraps around to zero again,
+-- the count is reset synchronously
+entity counter is
+generic(width:natural:=8;depth : natural);
+port(clk,rst:in std_logic; -- Clock and reset
+count:out natural range 0 to 2**width-1 -- The count
+);
+end entity;
+architecture rtl of counter is
+signal value:natural:=0; -- The value
+constant init : bit_vector(0 to 3) := (0=>'1',others=>'0');
+begin
+main:process(clk)
+begin
+if rising_edge(clk) then
+if rst='1' then value<=0;
+else
+case value is
+when 2**width-1=>value<=0;
+when others=>value <= value+1;
+end case;
+end if;
+end if;
+end process;
+count<=value when rst/='1' else
+0;
+inst:entity work.child generic map(width=>width)
+port map(clk=>clk,q=>open);
+end architecture;";
+        let mut aligned = full_style(3);
+        aligned.align_colons = true;
+        aligned.align_arrows = true;
+        aligned.align_assignments = true;
+        aligned.keyword_case = Some(Case::Upper);
+        aligned.align_comments = true;
+        aligned.comment_width = Some(30);
+        aligned.max_width = Some(40);
+        let tabs = FormatStyle::new(Indentation {
+            size: 4,
+            tabs: true,
+        });
+        for style in [style(2), tabs, full_style(2), aligned].iter() {
+            let once = formatted(code, style, None);
+            assert_ne!(once, code);
+            // Formatting all lines of the formatted code again changes nothing
+            let lines = Some((0, once.lines().count() - 1));
+            assert_eq!(formatted(&once, style, lines), once);
+        }
+    }
+
+    #[test]
+    fn wraps_lines_wider_than_maximum_width() {
+        let code = "entity ent is
+  generic (width : natural; depth : natural);
+  port (clk : in bit; rst : in bit; data : in bit_vector(7 downto 0); valid : out bit);
+end entity;
+
+architecture rtl of ent is
+  constant init : bit_vector(0 to 7) := (0 => '1', 1 => '0', 2 => '1', others => '0');
+begin
+  inst : entity work.child port map (clk => clk, rst => rst, data => data, valid => valid);
+  ready <= valid and not busy and (state = idle or state = waiting) and enable;
+end architecture;";
+        let mut style = style(2);
+        style.one_item_per_line = true;
+        style.max_width = Some(60);
+        // The generics fit on their line while the ports are put on lines of their own
+        assert_eq!(
+            formatted(code, &style, None),
+            "entity ent is
+  generic (width : natural; depth : natural);
+  port (
+    clk : in bit;
+    rst : in bit;
+    data : in bit_vector(7 downto 0);
+    valid : out bit
+  );
+end entity;
+
+architecture rtl of ent is
+  constant init : bit_vector(0 to 7) := (0 => '1', 1 => '0',
+    2 => '1', others => '0');
+begin
+  inst : entity work.child port map (clk => clk, rst => rst,
+    data => data, valid => valid);
+  ready <= valid and not busy
+    and (state = idle or state = waiting) and enable;
+end architecture;"
+        );
+        // Only the lines within the range are wrapped
+        let wrapped = formatted(code, &style, Some((9, 9)));
+        assert!(wrapped.contains("  ready <= valid and not busy\n    and (state"));
+        assert!(
+            wrapped.contains("port map (clk => clk, rst => rst, data => data, valid => valid);")
+        );
+    }
+
+    #[test]
+    fn wraps_interface_lists_at_semicolons() {
+        let code = "entity ent is
+  port (clk : in bit; rst : in bit; d : in bit_vector(7 downto 0); q : out bit_vector(7 downto 0));
+end entity;
+
+architecture rtl of ent is
+begin
+  result <= resize(unsigned(some_long_signal_name_a) + unsigned(other_signal), 8);
+end architecture;";
+        let mut style = style(2);
+        style.max_width = Some(60);
+        // Breaks within nested parentheses are indented one level deeper
+        assert_eq!(
+            formatted(code, &style, None),
+            "entity ent is
+  port (clk : in bit; rst : in bit;
+    d : in bit_vector(7 downto 0);
+    q : out bit_vector(7 downto 0));
+end entity;
+
+architecture rtl of ent is
+begin
+  result <= resize(
+    unsigned(some_long_signal_name_a) + unsigned(
+      other_signal), 8);
+end architecture;"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Lint rules on the states of the finite state machines of the state_machine module
+//!
+//! - fsm-unreachable-state: a state is not reached from an initial state
+//! - fsm-no-exit: a reachable state has no transition to another state
+//! - fsm-missing-default: the case statement has no others alternative which recovers
+//!   from illegal states
+
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use state_machine::state_machines;
+
+pub struct UnreachableState;
+
+impl Rule for UnreachableState {
+    fn id(&self) -> &str {
+        "fsm-unreachable-state"
+    }
+
+    fn description(&self) -> &str {
+        "A state of a state machine is not reached from an initial state"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for fsm in state_machines(&context.project.files()) {
+            for state in fsm.unreachable_states() {
+                if let Some(pos) = fsm.state_pos(state) {
+                    findings.push(warning(
+                        pos,
+                        &format!(
+                            "State '{}' of state machine '{}' is unreachable",
+                            state, fsm.signal
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct NoExit;
+
+impl Rule for NoExit {
+    fn id(&self) -> &str {
+        "fsm-no-exit"
+    }
+
+    fn description(&self) -> &str {
+        "A state of a state machine has no transition to another state"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for fsm in state_machines(&context.project.files()) {
+            for state in fsm.states_without_exit() {
+                if let Some(pos) = fsm.state_pos(state) {
+                    findings.push(warning(
+                        pos,
+                        &format!(
+                            "State '{}' of state machine '{}' has no exit",
+                            state, fsm.signal
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct MissingDefault;
+
+impl Rule for MissingDefault {
+    fn id(&self) -> &str {
+        "fsm-missing-default"
+    }
+
+    fn description(&self) -> &str {
+        "The case statement of a state machine has no others alternative"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        Ok(state_machines(&context.project.files())
+            .into_iter()
+            .filter(|fsm| !fsm.has_others)
+            .map(|fsm| {
+                warning(
+                    &fsm.pos,
+                    &format!(
+                        "State machine '{}' has no others alternative to recover from illegal states",
+                        fsm.signal
+                    ),
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn state_machine_states() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  type state_t is (idle, busy, done, lost);
+  signal state : state_t;
+begin
+  fsm : process (clk)
+  begin
+    if rising_edge(clk) then
+      case state is
+        when idle => state <= busy;
+        when busy => state <= done;
+        when done => state <= done;
+        when lost => state <= idle;
+      end case;
+    end if;
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<(String, String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code.as_ref().unwrap().starts_with("fsm-"))
+            .map(|finding| (finding.code.unwrap(), finding.message, finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    string("fsm-missing-default"),
+                    string(
+                        "State machine 'state' has no others alternative to recover from \
+                         illegal states"
+                    ),
+                    code.find("state is").unwrap()
+                ),
+                (
+                    string("fsm-no-exit"),
+                    string("State 'done' of state machine 'state' has no exit"),
+                    code.find("done =>").unwrap()
+                ),
+                (
+                    string("fsm-unreachable-state"),
+                    string("State 'lost' of state machine 'state' is unreachable"),
+                    code.find("lost =>").unwrap()
+                ),
+            ]
+        );
+    }
+}
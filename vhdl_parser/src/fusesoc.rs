@@ -0,0 +1,423 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! FuseSoC CAPI2 core files such as:
+//!
+//! ```yaml
+//! CAPI=2:
+//! name: ::fifo:1.0
+//!
+//! filesets:
+//!   rtl:
+//!     files:
+//!       - rtl/fifo_pkg.vhd
+//!       - rtl/fifo.vhd: {file_type: vhdlSource-2008}
+//!     file_type: vhdlSource
+//!     logical_name: fifo_lib
+//!     depend:
+//!       - ">=::ram:1.0"
+//! ```
+//!
+//! A logical name of a file takes precedence over the logical name of its file set,
+//! files without a logical name belong to the work library
+//! Cores are imported together with all cores they directly or indirectly depend on
+
+use import::{is_vhdl_file, ImportedProject};
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use yaml;
+
+extern crate glob;
+
+/// The library of files without a logical name
+pub const DEFAULT_LIBRARY: &str = "work";
+
+/// A file of a file set
+#[derive(PartialEq, Debug, Clone)]
+pub struct CoreFile {
+    pub file_name: String,
+    pub file_type: Option<String>,
+    pub logical_name: Option<String>,
+}
+
+impl CoreFile {
+    /// Files without a file type are identified by their extension
+    pub fn is_vhdl(&self) -> bool {
+        match self.file_type {
+            Some(ref file_type) => file_type.starts_with("vhdlSource"),
+            None => is_vhdl_file(&self.file_name),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct FileSet {
+    pub name: String,
+    pub files: Vec<CoreFile>,
+    pub depend: Vec<String>,
+}
+
+/// A parsed .core file
+#[derive(PartialEq, Debug, Clone)]
+pub struct Core {
+    /// The VLNV name such as vendor:library:name:version
+    pub name: String,
+    pub file_name: PathBuf,
+    pub filesets: Vec<FileSet>,
+}
+
+/// The vendor, library and name of a VLNV name or dependency, the version and
+/// any version comparison operator are removed such that any version matches
+pub fn core_identity(vlnv: &str) -> String {
+    // Conditional dependencies such as "tool_vivado? (::core)" are always included
+    let vlnv = match (vlnv.find('('), vlnv.rfind(')')) {
+        (Some(start), Some(end)) if start < end => &vlnv[start + 1..end],
+        _ => vlnv,
+    };
+    let vlnv = vlnv.trim().trim_start_matches(|chr| "<>=^~!".contains(chr));
+    let parts: Vec<&str> = vlnv.split(':').collect();
+    if parts.len() > 3 {
+        parts[..3].join(":")
+    } else {
+        parts.join(":")
+    }
+}
+
+fn optional_string(value: Option<&yaml::Value>, what: &str) -> Result<Option<String>, String> {
+    match value {
+        None | Some(yaml::Value::Null) => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(|string| Some(string.to_owned()))
+            .ok_or_else(|| format!("{} must be a string", what)),
+    }
+}
+
+fn parse_file(
+    value: &yaml::Value,
+    fileset: &yaml::Value,
+    core_dir: &Path,
+) -> Result<CoreFile, String> {
+    let (file_name, attributes) = match value {
+        yaml::Value::String(file_name) => (file_name.as_str(), None),
+        yaml::Value::Mapping(entries) if entries.len() == 1 => {
+            (entries[0].0.as_str(), Some(&entries[0].1))
+        }
+        _ => {
+            return Err("file must be a string or a mapping with a single file name".to_owned());
+        }
+    };
+    let what = format!("file {}", file_name);
+    let attribute = |name| attributes.and_then(|attributes| attributes.get(name));
+
+    let file_type = match optional_string(attribute("file_type"), &what)? {
+        Some(file_type) => Some(file_type),
+        None => optional_string(fileset.get("file_type"), "fileset file_type")?,
+    };
+    let logical_name = match optional_string(attribute("logical_name"), &what)? {
+        Some(logical_name) => Some(logical_name),
+        None => optional_string(fileset.get("logical_name"), "fileset logical_name")?,
+    };
+
+    Ok(CoreFile {
+        file_name: core_dir.join(file_name).to_string_lossy().into_owned(),
+        file_type,
+        logical_name,
+    })
+}
+
+fn parse_fileset(name: &str, value: &yaml::Value, core_dir: &Path) -> Result<FileSet, String> {
+    let mut files = Vec::new();
+    if let Some(items) = value.get("files") {
+        let items = items
+            .as_sequence()
+            .ok_or_else(|| format!("fileset {} files must be a list", name))?;
+        for item in items {
+            files.push(parse_file(item, value, core_dir)?);
+        }
+    }
+
+    let mut depend = Vec::new();
+    if let Some(items) = value.get("depend") {
+        let items = items
+            .as_sequence()
+            .ok_or_else(|| format!("fileset {} depend must be a list", name))?;
+        for item in items {
+            let item = item
+                .as_str()
+                .ok_or_else(|| format!("fileset {} depend must only contain strings", name))?;
+            depend.push(item.to_owned());
+        }
+    }
+
+    Ok(FileSet {
+        name: name.to_owned(),
+        files,
+        depend,
+    })
+}
+
+impl Core {
+    /// Parse the contents of the core file at the path, file names are relative to its directory
+    pub fn parse(contents: &str, file_name: &Path) -> Result<Core, String> {
+        let value = yaml::parse(contents)?;
+        if value.get("CAPI=2").is_none() {
+            return Err("Only CAPI=2 core files are supported".to_owned());
+        }
+        let name = value
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| "core file must have a name".to_owned())?;
+        let core_dir = file_name.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut filesets = Vec::new();
+        if let Some(entries) = value.get("filesets") {
+            let entries = entries
+                .as_mapping()
+                .ok_or_else(|| "filesets must be a mapping".to_owned())?;
+            for (fileset_name, fileset) in entries {
+                filesets.push(parse_fileset(fileset_name, fileset, core_dir)?);
+            }
+        }
+
+        Ok(Core {
+            name: name.to_owned(),
+            file_name: file_name.to_owned(),
+            filesets,
+        })
+    }
+
+    pub fn read_file_path(file_name: &Path) -> io::Result<Core> {
+        let contents = fs::read_to_string(file_name)?;
+        Core::parse(&contents, file_name).map_err(|msg| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", file_name.to_string_lossy(), msg),
+            )
+        })
+    }
+
+    /// The dependencies of all file sets
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.filesets
+            .iter()
+            .flat_map(|fileset| fileset.depend.iter().map(|depend| depend.as_str()))
+    }
+
+    /// Add the VHDL files of all file sets to their libraries
+    pub fn add_files(&self, project: &mut ImportedProject) {
+        for fileset in self.filesets.iter() {
+            for file in fileset.files.iter().filter(|file| file.is_vhdl()) {
+                let library_name = file.logical_name.as_deref().unwrap_or(DEFAULT_LIBRARY);
+                project.add_file(library_name, &file.file_name);
+            }
+        }
+    }
+}
+
+/// Find and read all core files within the directories and their sub directories
+/// Returns the cores and the file names of the core files which could not be read
+pub fn discover_cores(directories: &[&Path]) -> (Vec<Core>, Vec<(String, io::Error)>) {
+    let mut cores = Vec::new();
+    let mut errors = Vec::new();
+    for directory in directories {
+        let pattern = directory.join("**").join("*.core");
+        let paths = match glob::glob(&pattern.to_string_lossy()) {
+            Ok(paths) => paths,
+            Err(err) => {
+                errors.push((
+                    directory.to_string_lossy().into_owned(),
+                    io::Error::new(io::ErrorKind::InvalidInput, err.to_string()),
+                ));
+                continue;
+            }
+        };
+        for path in paths.filter_map(|path| path.ok()) {
+            match Core::read_file_path(&path) {
+                Ok(core) => cores.push(core),
+                Err(err) => errors.push((path.to_string_lossy().into_owned(), err)),
+            }
+        }
+    }
+    (cores, errors)
+}
+
+/// Import the files of the core and all cores it depends on
+/// Returns the dependencies which were not found among the cores
+pub fn import_core(root: &Core, cores: &[Core]) -> (ImportedProject, Vec<String>) {
+    let mut project = ImportedProject::new();
+    let mut missing = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut queue = VecDeque::new();
+
+    visited.insert(core_identity(&root.name));
+    queue.push_back(root);
+
+    while let Some(core) = queue.pop_front() {
+        core.add_files(&mut project);
+        for depend in core.dependencies() {
+            let identity = core_identity(depend);
+            if !visited.insert(identity.clone()) {
+                continue;
+            }
+            match cores
+                .iter()
+                .find(|core| core_identity(&core.name) == identity)
+            {
+                Some(dependency) => queue.push_back(dependency),
+                None => missing.push(depend.to_owned()),
+            }
+        }
+    }
+    (project, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+
+    fn files(project: &ImportedProject, library_name: &str) -> Vec<String> {
+        project
+            .get_library(library_name)
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    const FIFO: &str = "
+CAPI=2:
+name: ::fifo:1.0
+description: A fifo
+
+filesets:
+  rtl:
+    files:
+      - rtl/fifo_pkg.vhd
+      - rtl/fifo.vhd: {file_type: vhdlSource-2008}
+      - rtl/other.vhd: {logical_name: other_lib}
+      - rtl/fifo.v: {file_type: verilogSource}
+    file_type: vhdlSource
+    logical_name: fifo_lib
+    depend:
+      - \">=::ram:1.0\"
+  tb:
+    files:
+      - tb/tb_fifo.vhd
+      - tb/data.txt
+    depend: [\"tool_ghdl? (::vunit_util)\"]
+
+targets:
+  default:
+    filesets: [rtl]
+";
+
+    #[test]
+    fn parse_core() {
+        let core = Core::parse(FIFO, Path::new("/cores/fifo/fifo.core")).unwrap();
+        assert_eq!(core.name, "::fifo:1.0");
+        assert_eq!(core.filesets.len(), 2);
+        assert_eq!(
+            core.filesets[0].files[1],
+            CoreFile {
+                file_name: "/cores/fifo/rtl/fifo.vhd".to_owned(),
+                file_type: Some("vhdlSource-2008".to_owned()),
+                logical_name: Some("fifo_lib".to_owned()),
+            }
+        );
+        assert_eq!(
+            core.dependencies().collect::<Vec<_>>(),
+            vec![">=::ram:1.0", "tool_ghdl? (::vunit_util)"]
+        );
+
+        let mut project = ImportedProject::new();
+        core.add_files(&mut project);
+        assert_eq!(
+            files(&project, "fifo_lib"),
+            vec!["/cores/fifo/rtl/fifo.vhd", "/cores/fifo/rtl/fifo_pkg.vhd"]
+        );
+        assert_eq!(
+            files(&project, "other_lib"),
+            vec!["/cores/fifo/rtl/other.vhd"]
+        );
+        assert_eq!(files(&project, "work"), vec!["/cores/fifo/tb/tb_fifo.vhd"]);
+    }
+
+    #[test]
+    fn identity_ignores_version_and_operators() {
+        assert_eq!(core_identity("::fifo:1.0"), "::fifo");
+        assert_eq!(core_identity(">=::fifo:1.0"), "::fifo");
+        assert_eq!(core_identity("::fifo"), "::fifo");
+        assert_eq!(core_identity("vendor:lib:fifo:1.0-r1"), "vendor:lib:fifo");
+        assert_eq!(
+            core_identity("tool_ghdl? (^vendor:lib:fifo:1.0)"),
+            "vendor:lib:fifo"
+        );
+    }
+
+    #[test]
+    fn import_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("fifo")).unwrap();
+        fs::create_dir_all(dir.path().join("ram")).unwrap();
+        fs::write(dir.path().join("fifo").join("fifo.core"), FIFO).unwrap();
+        fs::write(
+            dir.path().join("ram").join("ram.core"),
+            "
+CAPI=2:
+name: ::ram:1.1
+filesets:
+  rtl:
+    files: [ram.vhd]
+    logical_name: ram_lib
+    depend: ['::fifo:1.0']
+",
+        )
+        .unwrap();
+        fs::write(dir.path().join("invalid.core"), "name: ::invalid").unwrap();
+
+        let (cores, errors) = discover_cores(&[dir.path()]);
+        assert_eq!(cores.len(), 2);
+        assert_eq!(errors.len(), 1);
+
+        let root = cores.iter().find(|core| core.name == "::fifo:1.0").unwrap();
+        let (project, missing) = import_core(root, &cores);
+        assert_eq!(missing, vec!["tool_ghdl? (::vunit_util)"]);
+        assert_eq!(
+            files(&project, "ram_lib"),
+            vec![dir.path().join("ram").join("ram.vhd").to_string_lossy()]
+        );
+        assert_eq!(project.libraries().count(), 4);
+    }
+
+    #[test]
+    fn invalid_core_files() {
+        let path = Path::new("x.core");
+        assert_eq!(
+            Core::parse("name: ::x", path),
+            Err("Only CAPI=2 core files are supported".to_owned())
+        );
+        assert_eq!(
+            Core::parse("CAPI=2:\nfilesets: {}", path),
+            Err("core file must have a name".to_owned())
+        );
+        assert_eq!(
+            Core::parse(
+                "CAPI=2:\nname: x\nfilesets:\n  rtl:\n    files: a.vhd",
+                path
+            ),
+            Err("fileset rtl files must be a list".to_owned())
+        );
+        assert_eq!(
+            Core::parse(
+                "CAPI=2:\nname: x\nfilesets:\n  rtl:\n    files: [{a.vhd: x, b.vhd: y}]",
+                path
+            ),
+            Err("file must be a string or a mapping with a single file name".to_owned())
+        );
+    }
+}
@@ -0,0 +1,847 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! C headers and Rust modules with the constants, enumeration types and record types of
+//! a package for firmware and co-simulation which must match the hardware
+//!
+//! Only constants with a static value of an integer, boolean, bit, bit vector or
+//! enumeration type of the package are generated, other declarations are left out with
+//! a comment
+
+use ast::{
+    AbstractLiteral, BaseSpecifier, BitString, Choice, Declaration, DiscreteRange,
+    ElementAssociation, EnumerationLiteral, Expression, LibraryUnit, Literal, Name, ObjectClass,
+    Range, SubtypeConstraint, SubtypeIndication, TypeDefinition, Unary,
+};
+use library::symbol_key;
+use project::SourceFile;
+use static_value::integer_binary;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// The language of the generated definitions
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum HeaderLanguage {
+    /// A C header which is also valid C++
+    C,
+    /// A Rust module with types of the same layout as the C header
+    Rust,
+}
+
+impl FromStr for HeaderLanguage {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<HeaderLanguage, String> {
+        match string {
+            "c" | "c++" | "cpp" => Ok(HeaderLanguage::C),
+            "rust" => Ok(HeaderLanguage::Rust),
+            _ => Err(format!(
+                "Unknown language '{}', expected one of c, c++ or rust",
+                string
+            )),
+        }
+    }
+}
+
+/// The representation of a VHDL type
+#[derive(PartialEq, Debug, Clone)]
+enum HeaderType {
+    Boolean,
+    /// An integer with its lowest and highest value
+    Integer(i64, i64),
+    /// A bit, or a bit vector with its width unless unconstrained, and whether it is signed
+    Bits(Option<u32>, bool),
+    /// An enumeration or record type of the package
+    Named(String),
+}
+
+/// The value of a constant known without elaboration
+#[derive(PartialEq, Debug, Clone)]
+enum Value {
+    Integer(i64),
+    Boolean(bool),
+    /// A bit vector of at most 64 bits with its width
+    Bits(u64, u32),
+    /// A literal of an enumeration type of the package with the name of the type
+    Enumeration(String, String),
+}
+
+enum Definition {
+    Constant(String, Value),
+    Enumeration(String, Vec<String>),
+    Record(String, Vec<(String, HeaderType)>),
+    /// A declaration which is left out with the reason
+    Unsupported(String),
+}
+
+/// The definitions of a package in declaration order
+struct Package {
+    name: String,
+    /// The integer types, subtypes, enumeration types and record types by key
+    types: HashMap<String, HeaderType>,
+    /// The literals of each enumeration type by key
+    enumerations: HashMap<String, Vec<String>>,
+    constants: HashMap<String, Value>,
+    definitions: Vec<Definition>,
+}
+
+fn standard_type(key: &str) -> Option<HeaderType> {
+    let max = i64::from(i32::MAX);
+    match key {
+        "boolean" => Some(HeaderType::Boolean),
+        "integer" => Some(HeaderType::Integer(i64::from(i32::MIN), max)),
+        "natural" => Some(HeaderType::Integer(0, max)),
+        "positive" => Some(HeaderType::Integer(1, max)),
+        "bit" | "std_logic" | "std_ulogic" => Some(HeaderType::Bits(Some(1), false)),
+        "bit_vector" | "std_logic_vector" | "std_ulogic_vector" | "unsigned" => {
+            Some(HeaderType::Bits(None, false))
+        }
+        "signed" => Some(HeaderType::Bits(None, true)),
+        _ => None,
+    }
+}
+
+/// The value and width of a bit string literal of at most 64 bits
+fn bit_string_value(bit_string: &BitString) -> Option<(u64, u32)> {
+    let digits: Vec<u8> = bit_string
+        .value
+        .bytes
+        .iter()
+        .cloned()
+        .filter(|byte| *byte != b'_')
+        .collect();
+    let (radix, digit_bits, signed) = match bit_string.base {
+        BaseSpecifier::B | BaseSpecifier::UB => (2, 1, false),
+        BaseSpecifier::O | BaseSpecifier::UO => (8, 3, false),
+        BaseSpecifier::X | BaseSpecifier::UX => (16, 4, false),
+        BaseSpecifier::SB => (2, 1, true),
+        BaseSpecifier::SO => (8, 3, true),
+        BaseSpecifier::SX => (16, 4, true),
+        BaseSpecifier::D => (10, 0, false),
+    };
+    let mut value: u64 = 0;
+    for digit in digits.iter() {
+        let digit = u64::from((*digit as char).to_digit(radix)?);
+        value = value.checked_mul(u64::from(radix))?.checked_add(digit)?;
+    }
+    let natural_width = if radix == 10 {
+        64 - value.leading_zeros()
+    } else {
+        digit_bits * digits.len() as u32
+    };
+    let width = bit_string.length.unwrap_or(natural_width);
+    if width == 0 || width > 64 || natural_width > 64 {
+        return None;
+    }
+    let mask = u64::MAX >> (64 - width);
+    if width < natural_width && value & !mask != 0 {
+        return None;
+    }
+    let negative = signed && natural_width > 0 && value >> (natural_width - 1) & 1 == 1;
+    if width > natural_width && negative {
+        value |= mask & !(u64::MAX >> (64 - natural_width));
+    }
+    Some((value & mask, width))
+}
+
+impl Package {
+    fn new(name: &str) -> Package {
+        Package {
+            name: name.to_owned(),
+            types: HashMap::new(),
+            enumerations: HashMap::new(),
+            constants: HashMap::new(),
+            definitions: Vec::new(),
+        }
+    }
+
+    fn integer(&self, expression: &Expression) -> Option<i64> {
+        match expression {
+            Expression::Literal(Literal::AbstractLiteral(AbstractLiteral::Integer(value))) => {
+                Some(*value)
+            }
+            Expression::Name(ref name) => match **name {
+                Name::Simple(ref symbol) => match self.constants.get(&symbol_key(symbol)) {
+                    Some(Value::Integer(value)) => Some(*value),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Expression::Unary(Unary::Minus, ref operand) => {
+                self.integer(&operand.item)?.checked_neg()
+            }
+            Expression::Unary(Unary::Plus, ref operand) => self.integer(&operand.item),
+            Expression::Binary(op, ref left, ref right) => {
+                integer_binary(*op, self.integer(&left.item)?, self.integer(&right.item)?)
+            }
+            _ => None,
+        }
+    }
+
+    fn bits(&self, expression: &Expression, width: Option<u32>) -> Option<(u64, u32)> {
+        let (value, value_width) = match expression {
+            Expression::Literal(Literal::Character(byte)) => match byte {
+                b'0' => (0, 1),
+                b'1' => (1, 1),
+                _ => return None,
+            },
+            Expression::Literal(Literal::BitString(ref bit_string)) => {
+                bit_string_value(bit_string)?
+            }
+            Expression::Literal(Literal::String(ref string)) => {
+                let bytes = &string.bytes;
+                if bytes.is_empty() || bytes.len() > 64 {
+                    return None;
+                }
+                let mut value = 0;
+                for byte in bytes.iter() {
+                    value = value << 1
+                        | match byte {
+                            b'0' => 0,
+                            b'1' => 1,
+                            _ => return None,
+                        };
+                }
+                (value, bytes.len() as u32)
+            }
+            Expression::Name(ref name) => match **name {
+                Name::Simple(ref symbol) => match self.constants.get(&symbol_key(symbol)) {
+                    Some(Value::Bits(value, width)) => (*value, *width),
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            // (others => '0') and (others => '1')
+            Expression::Aggregate(ref associations) => {
+                let width = width.filter(|width| *width <= 64)?;
+                match associations.as_slice() {
+                    [ElementAssociation::Named(ref choices, ref element)]
+                        if choices.as_slice() == [Choice::Others] =>
+                    {
+                        match self.bits(&element.item, Some(1))? {
+                            (0, _) => (0, width),
+                            _ => (u64::MAX >> (64 - width), width),
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        if width.is_none_or(|width| width == value_width) {
+            Some((value, value_width))
+        } else {
+            None
+        }
+    }
+
+    fn value(&self, header_type: &HeaderType, expression: &Expression) -> Option<Value> {
+        match header_type {
+            HeaderType::Boolean => match expression {
+                Expression::Name(ref name) => match **name {
+                    Name::Simple(ref symbol) => match symbol_key(symbol).as_str() {
+                        "true" => Some(Value::Boolean(true)),
+                        "false" => Some(Value::Boolean(false)),
+                        key => self.constants.get(key).cloned(),
+                    },
+                    _ => None,
+                },
+                _ => None,
+            },
+            HeaderType::Integer(..) => self.integer(expression).map(Value::Integer),
+            HeaderType::Bits(width, _) => self
+                .bits(expression, *width)
+                .map(|(value, width)| Value::Bits(value, width)),
+            HeaderType::Named(ref type_name) => {
+                let literals = self.enumerations.get(&type_name.to_lowercase())?;
+                match expression {
+                    Expression::Name(ref name) => match **name {
+                        Name::Simple(ref symbol) => literals
+                            .iter()
+                            .find(|literal| literal.to_lowercase() == symbol_key(symbol))
+                            .map(|literal| Value::Enumeration(type_name.clone(), literal.clone())),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The lowest and highest value of a range
+    fn range(&self, range: &Range) -> Option<(i64, i64)> {
+        match range {
+            Range::Range(ref constraint) => {
+                let left = self.integer(&constraint.left_expr.item)?;
+                let right = self.integer(&constraint.right_expr.item)?;
+                Some((left.min(right), left.max(right)))
+            }
+            Range::Attribute(..) => None,
+        }
+    }
+
+    fn header_type(&self, subtype: &SubtypeIndication) -> Option<HeaderType> {
+        let key = symbol_key(&subtype.type_mark.last()?.item);
+        let header_type = self
+            .types
+            .get(&key)
+            .cloned()
+            .or_else(|| standard_type(&key))?;
+        match (header_type, &subtype.constraint) {
+            (header_type, None) => Some(header_type),
+            (HeaderType::Integer(..), Some(SubtypeConstraint::Range(ref range))) => {
+                let (low, high) = self.range(range)?;
+                Some(HeaderType::Integer(low, high))
+            }
+            (HeaderType::Bits(None, signed), Some(SubtypeConstraint::Array(ref ranges, None))) => {
+                match ranges.as_slice() {
+                    [DiscreteRange::Range(ref range)] => {
+                        let (low, high) = self.range(range)?;
+                        let width = u32::try_from(high - low + 1).ok()?;
+                        Some(HeaderType::Bits(Some(width), signed))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn add_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Type(ref type_declaration) => {
+                let name = type_declaration.ident.item.name_utf8();
+                let key = symbol_key(&type_declaration.ident.item);
+                match type_declaration.def {
+                    TypeDefinition::Enumeration(ref literals) => {
+                        let literals: Option<Vec<String>> = literals
+                            .iter()
+                            .map(|literal| match literal.item {
+                                EnumerationLiteral::Identifier(ref symbol) => {
+                                    Some(symbol.name_utf8())
+                                }
+                                EnumerationLiteral::Character(..) => None,
+                            })
+                            .collect();
+                        match literals {
+                            Some(literals) => {
+                                self.types
+                                    .insert(key.clone(), HeaderType::Named(name.clone()));
+                                self.enumerations.insert(key, literals.clone());
+                                self.definitions
+                                    .push(Definition::Enumeration(name, literals));
+                            }
+                            None => self.definitions.push(Definition::Unsupported(format!(
+                                "Enumeration type {} is left out since it has character literals",
+                                name
+                            ))),
+                        }
+                    }
+                    TypeDefinition::Record(ref elements) => {
+                        let mut fields = Vec::new();
+                        for element in elements.iter() {
+                            let element_name = element.ident.item.name_utf8();
+                            match self.header_type(&element.subtype) {
+                                Some(HeaderType::Bits(None, _)) | None => {
+                                    self.definitions.push(Definition::Unsupported(format!(
+                                        "Record type {} is left out since element {} has an \
+                                         unsupported type",
+                                        name, element_name
+                                    )));
+                                    return;
+                                }
+                                Some(header_type) => fields.push((element_name, header_type)),
+                            }
+                        }
+                        self.types.insert(key, HeaderType::Named(name.clone()));
+                        self.definitions.push(Definition::Record(name, fields));
+                    }
+                    TypeDefinition::Integer(ref range) => {
+                        if let Some((low, high)) = self.range(range) {
+                            self.types.insert(key, HeaderType::Integer(low, high));
+                        }
+                    }
+                    TypeDefinition::Subtype(ref subtype) => {
+                        if let Some(header_type) = self.header_type(subtype) {
+                            self.types.insert(key, header_type);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Declaration::Object(ref object) if object.class == ObjectClass::Constant => {
+                let name = object.ident.item.name_utf8();
+                let value = match (
+                    self.header_type(&object.subtype_indication),
+                    &object.expression,
+                ) {
+                    (Some(header_type), Some(ref expression)) => {
+                        self.value(&header_type, &expression.item)
+                    }
+                    _ => None,
+                };
+                match value {
+                    Some(value) => {
+                        self.constants
+                            .insert(symbol_key(&object.ident.item), value.clone());
+                        self.definitions.push(Definition::Constant(name, value));
+                    }
+                    None => self.definitions.push(Definition::Unsupported(format!(
+                        "Constant {} is left out since its type or value is not supported",
+                        name
+                    ))),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The name without a trailing _t which is common for VHDL types
+fn type_base_name(name: &str) -> &str {
+    let lower = name.to_lowercase();
+    if lower.len() > 2 && lower.ends_with("_t") {
+        &name[..name.len() - 2]
+    } else {
+        name
+    }
+}
+
+/// The number of bits and whether it is signed of the smallest integer type of the range
+fn integer_bits(low: i64, high: i64) -> (u32, bool) {
+    let signed = low < 0;
+    let (low, high) = (i128::from(low), i128::from(high));
+    let bits = [8, 16, 32, 64]
+        .iter()
+        .cloned()
+        .find(|bits| {
+            if signed {
+                low >= -(1 << (bits - 1)) && high < 1 << (bits - 1)
+            } else {
+                high < 1 << bits
+            }
+        })
+        .unwrap_or(64);
+    (bits, signed)
+}
+
+/// The number of bits of the smallest integer type of the width
+fn width_bits(width: u32) -> Option<u32> {
+    [8, 16, 32, 64].iter().cloned().find(|bits| width <= *bits)
+}
+
+fn c_type_name(package: &str, name: &str) -> String {
+    format!("{}_{}_t", package, type_base_name(name)).to_lowercase()
+}
+
+fn c_field(package: &str, name: &str, header_type: &HeaderType) -> String {
+    let (c_type, suffix) = match header_type {
+        HeaderType::Boolean => ("bool".to_owned(), String::new()),
+        HeaderType::Integer(low, high) => {
+            let (bits, signed) = integer_bits(*low, *high);
+            (
+                format!("{}int{}_t", if signed { "" } else { "u" }, bits),
+                String::new(),
+            )
+        }
+        HeaderType::Bits(width, signed) => {
+            let width = width.unwrap_or(64);
+            match width_bits(width) {
+                Some(bits) => (
+                    format!("{}int{}_t", if *signed { "" } else { "u" }, bits),
+                    String::new(),
+                ),
+                None => ("uint8_t".to_owned(), format!("[{}]", width.div_ceil(8))),
+            }
+        }
+        HeaderType::Named(ref type_name) => (c_type_name(package, type_name), String::new()),
+    };
+    format!("{} {}{};", c_type, name.to_lowercase(), suffix)
+}
+
+fn c_value(package: &str, value: &Value) -> String {
+    match value {
+        Value::Integer(value) if i32::try_from(*value).is_err() => format!("({}LL)", value),
+        Value::Integer(value) if *value < 0 => format!("({})", value),
+        Value::Integer(value) => value.to_string(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Bits(value, width) => format!(
+            "0x{:0digits$x}{}",
+            value,
+            if *width > 32 { "ull" } else { "u" },
+            digits = width.div_ceil(4) as usize
+        ),
+        Value::Enumeration(type_name, literal) => {
+            format!("{}_{}_{}", package, type_base_name(type_name), literal).to_uppercase()
+        }
+    }
+}
+
+fn c_header(package: &Package) -> String {
+    let guard = format!("{}_H", package.name.to_uppercase());
+    let mut lines = vec![
+        format!(
+            "/* Generated from VHDL package {}, do not edit */",
+            package.name
+        ),
+        format!("#ifndef {}", guard),
+        format!("#define {}", guard),
+        String::new(),
+        "#include <stdbool.h>".to_owned(),
+        "#include <stdint.h>".to_owned(),
+    ];
+    for definition in package.definitions.iter() {
+        match definition {
+            Definition::Constant(name, value) => {
+                if lines
+                    .last()
+                    .is_some_and(|line| !line.starts_with("#define"))
+                {
+                    lines.push(String::new());
+                }
+                lines.push(format!(
+                    "#define {}_{} {}",
+                    package.name.to_uppercase(),
+                    name.to_uppercase(),
+                    c_value(&package.name, value)
+                ));
+            }
+            Definition::Enumeration(name, literals) => {
+                lines.push(String::new());
+                lines.push("typedef enum {".to_owned());
+                for (i, literal) in literals.iter().enumerate() {
+                    lines.push(format!(
+                        "    {}_{}_{} = {}{}",
+                        package.name.to_uppercase(),
+                        type_base_name(name).to_uppercase(),
+                        literal.to_uppercase(),
+                        i,
+                        if i + 1 < literals.len() { "," } else { "" }
+                    ));
+                }
+                lines.push(format!("}} {};", c_type_name(&package.name, name)));
+            }
+            Definition::Record(name, fields) => {
+                lines.push(String::new());
+                lines.push("typedef struct {".to_owned());
+                for (field_name, header_type) in fields.iter() {
+                    lines.push(format!(
+                        "    {}",
+                        c_field(&package.name, field_name, header_type)
+                    ));
+                }
+                lines.push(format!("}} {};", c_type_name(&package.name, name)));
+            }
+            Definition::Unsupported(reason) => {
+                lines.push(String::new());
+                lines.push(format!("/* {} */", reason));
+            }
+        }
+    }
+    lines.push(String::new());
+    lines.push(format!("#endif /* {} */", guard));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// The name in upper camel case such as BusyWait for busy_wait
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let part = part.to_lowercase();
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// The type name in upper camel case such as StateMachine for state_machine_t
+fn rust_type_name(name: &str) -> String {
+    camel_case(type_base_name(name))
+}
+
+fn rust_type(header_type: &HeaderType) -> String {
+    match header_type {
+        HeaderType::Boolean => "bool".to_owned(),
+        HeaderType::Integer(low, high) => {
+            let (bits, signed) = integer_bits(*low, *high);
+            format!("{}{}", if signed { "i" } else { "u" }, bits)
+        }
+        HeaderType::Bits(width, signed) => {
+            let width = width.unwrap_or(64);
+            match width_bits(width) {
+                Some(bits) => format!("{}{}", if *signed { "i" } else { "u" }, bits),
+                None => format!("[u8; {}]", width.div_ceil(8)),
+            }
+        }
+        HeaderType::Named(ref type_name) => rust_type_name(type_name),
+    }
+}
+
+fn rust_constant(name: &str, value: &Value) -> String {
+    let (rust_type, value) = match value {
+        Value::Integer(value) if i32::try_from(*value).is_ok() => {
+            ("i32".to_owned(), value.to_string())
+        }
+        Value::Integer(value) => ("i64".to_owned(), value.to_string()),
+        Value::Boolean(value) => ("bool".to_owned(), value.to_string()),
+        Value::Bits(value, width) => (
+            rust_type(&HeaderType::Bits(Some(*width), false)),
+            format!("0x{:0digits$x}", value, digits = width.div_ceil(4) as usize),
+        ),
+        Value::Enumeration(type_name, literal) => (
+            rust_type_name(type_name),
+            format!("{}::{}", rust_type_name(type_name), camel_case(literal)),
+        ),
+    };
+    format!(
+        "pub const {}: {} = {};",
+        name.to_uppercase(),
+        rust_type,
+        value
+    )
+}
+
+fn rust_module(package: &Package) -> String {
+    let mut lines = vec![format!(
+        "// Generated from VHDL package {}, do not edit",
+        package.name
+    )];
+    for definition in package.definitions.iter() {
+        match definition {
+            Definition::Constant(name, value) => {
+                if lines
+                    .last()
+                    .is_some_and(|line| !line.starts_with("pub const"))
+                {
+                    lines.push(String::new());
+                }
+                lines.push(rust_constant(name, value));
+            }
+            Definition::Enumeration(name, literals) => {
+                lines.push(String::new());
+                lines.push("#[repr(C)]".to_owned());
+                lines.push("#[derive(Clone, Copy, Debug, PartialEq, Eq)]".to_owned());
+                lines.push(format!("pub enum {} {{", rust_type_name(name)));
+                for (i, literal) in literals.iter().enumerate() {
+                    lines.push(format!("    {} = {},", camel_case(literal), i));
+                }
+                lines.push("}".to_owned());
+            }
+            Definition::Record(name, fields) => {
+                lines.push(String::new());
+                lines.push("#[repr(C)]".to_owned());
+                lines.push("#[derive(Clone, Copy, Debug)]".to_owned());
+                lines.push(format!("pub struct {} {{", rust_type_name(name)));
+                for (field_name, header_type) in fields.iter() {
+                    lines.push(format!(
+                        "    pub {}: {},",
+                        field_name.to_lowercase(),
+                        rust_type(header_type)
+                    ));
+                }
+                lines.push("}".to_owned());
+            }
+            Definition::Unsupported(reason) => {
+                lines.push(String::new());
+                lines.push(format!("// {}", reason));
+            }
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// The definitions of the package with the name in the language
+pub fn header(files: &[&SourceFile], name: &str, language: HeaderLanguage) -> Option<String> {
+    let (ident, decl) = files
+        .iter()
+        .flat_map(|file| file.design_file.design_units.iter())
+        .find_map(|design_unit| match design_unit.library_unit {
+            LibraryUnit::PackageDeclaration {
+                ref ident,
+                ref decl,
+            } if ident.item.name_utf8().eq_ignore_ascii_case(name) => Some((ident, decl)),
+            _ => None,
+        })?;
+    let mut package = Package::new(&ident.item.name_utf8());
+    for declaration in decl.iter() {
+        package.add_declaration(declaration);
+    }
+    Some(match language {
+        HeaderLanguage::C => c_header(&package),
+        HeaderLanguage::Rust => rust_module(&package),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_1::Latin1String;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "regs.vhd",
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+package regs is
+  constant data_width : natural := 12;
+  constant depth : integer := 2 ** 4 - 1;
+  constant offset : integer := -3;
+  constant debug : boolean := false;
+  constant magic : std_logic_vector(15 downto 0) := x\"cafe\";
+  constant reset_value : std_logic_vector(data_width - 1 downto 0) := (others => '1');
+  constant period : time := 10 ns;
+
+  type state_t is (idle, busy_wait);
+  constant initial_state : state_t := busy_wait;
+  subtype byte_t is std_logic_vector(7 downto 0);
+
+  type ctrl_t is record
+    enable : std_logic;
+    mode : integer range 0 to 3;
+    state : state_t;
+    data : byte_t;
+    wide : unsigned(71 downto 0);
+  end record;
+
+  type bad_t is record
+    name : string(1 to 4);
+  end record;
+end package;",
+        );
+        project
+    }
+
+    #[test]
+    fn c_header_of_package() {
+        let project = project();
+        let files = project.project.files();
+        assert_eq!(
+            header(&files, "REGS", HeaderLanguage::C).unwrap(),
+            "/* Generated from VHDL package regs, do not edit */
+#ifndef REGS_H
+#define REGS_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define REGS_DATA_WIDTH 12
+#define REGS_DEPTH 15
+#define REGS_OFFSET (-3)
+#define REGS_DEBUG false
+#define REGS_MAGIC 0xcafeu
+#define REGS_RESET_VALUE 0xfffu
+
+/* Constant period is left out since its type or value is not supported */
+
+typedef enum {
+    REGS_STATE_IDLE = 0,
+    REGS_STATE_BUSY_WAIT = 1
+} regs_state_t;
+
+#define REGS_INITIAL_STATE REGS_STATE_BUSY_WAIT
+
+typedef struct {
+    uint8_t enable;
+    uint8_t mode;
+    regs_state_t state;
+    uint8_t data;
+    uint8_t wide[9];
+} regs_ctrl_t;
+
+/* Record type bad_t is left out since element name has an unsupported type */
+
+#endif /* REGS_H */
+"
+        );
+        assert_eq!(header(&files, "missing", HeaderLanguage::C), None);
+    }
+
+    #[test]
+    fn rust_module_of_package() {
+        let project = project();
+        let files = project.project.files();
+        assert_eq!(
+            header(&files, "regs", HeaderLanguage::Rust).unwrap(),
+            "// Generated from VHDL package regs, do not edit
+
+pub const DATA_WIDTH: i32 = 12;
+pub const DEPTH: i32 = 15;
+pub const OFFSET: i32 = -3;
+pub const DEBUG: bool = false;
+pub const MAGIC: u16 = 0xcafe;
+pub const RESET_VALUE: u16 = 0xfff;
+
+// Constant period is left out since its type or value is not supported
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Idle = 0,
+    BusyWait = 1,
+}
+
+pub const INITIAL_STATE: State = State::BusyWait;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Ctrl {
+    pub enable: u8,
+    pub mode: u8,
+    pub state: State,
+    pub data: u8,
+    pub wide: [u8; 9],
+}
+
+// Record type bad_t is left out since element name has an unsupported type
+"
+        );
+    }
+
+    #[test]
+    fn bit_string_values() {
+        let bit_string = |length, base, value: &str| BitString {
+            length,
+            base,
+            value: Latin1String::from_utf8_unchecked(value),
+        };
+        assert_eq!(
+            bit_string_value(&bit_string(None, BaseSpecifier::B, "1010_0101")),
+            Some((0xa5, 8))
+        );
+        assert_eq!(
+            bit_string_value(&bit_string(Some(6), BaseSpecifier::SX, "F")),
+            Some((0x3f, 6))
+        );
+        assert_eq!(
+            bit_string_value(&bit_string(Some(3), BaseSpecifier::X, "7")),
+            Some((7, 3))
+        );
+        assert_eq!(
+            bit_string_value(&bit_string(Some(3), BaseSpecifier::X, "F")),
+            None
+        );
+        assert_eq!(
+            bit_string_value(&bit_string(Some(8), BaseSpecifier::D, "200")),
+            Some((200, 8))
+        );
+        assert_eq!(
+            bit_string_value(&bit_string(None, BaseSpecifier::X, "Z")),
+            None
+        );
+    }
+}
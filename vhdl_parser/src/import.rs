@@ -0,0 +1,144 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Library assignments imported from the project files of other tools
+
+use config::Config;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+extern crate glob;
+extern crate toml;
+use self::glob::Pattern;
+use self::toml::value::{Table, Value};
+
+/// VHDL source files and the libraries they belong to
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ImportedProject {
+    libraries: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Only files with a VHDL extension are imported since other tools also list
+/// Verilog, constraint and IP files
+pub fn is_vhdl_file(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            let ext = ext.to_lowercase();
+            ext == "vhd" || ext == "vhdl"
+        })
+        .unwrap_or(false)
+}
+
+impl ImportedProject {
+    pub fn new() -> ImportedProject {
+        ImportedProject::default()
+    }
+
+    /// Library names are case insensitive and stored in lower case
+    pub fn add_file(&mut self, library_name: &str, file_name: &str) {
+        self.libraries
+            .entry(library_name.to_lowercase())
+            .or_default()
+            .insert(file_name.to_owned());
+    }
+
+    /// The files of the library sorted by name
+    pub fn get_library(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.libraries.get(&name.to_lowercase())
+    }
+
+    /// The files of each library sorted by name
+    pub fn libraries(&self) -> impl Iterator<Item = (&str, &BTreeSet<String>)> {
+        self.libraries
+            .iter()
+            .map(|(name, files)| (name.as_str(), files))
+    }
+
+    /// Generate the contents of a project configuration file placed within the directory
+    /// File names within the directory are made relative to it
+    pub fn to_toml(&self, parent: &Path) -> String {
+        let mut libraries = Table::new();
+        for (name, file_names) in self.libraries.iter() {
+            let files = file_names
+                .iter()
+                .map(|file_name| {
+                    let path = Path::new(file_name);
+                    let path = path.strip_prefix(parent).unwrap_or(path);
+                    // File names are interpreted as glob patterns by the configuration
+                    Value::String(Pattern::escape(&path.to_string_lossy()))
+                })
+                .collect();
+
+            let mut library = Table::new();
+            library.insert("files".to_owned(), Value::Array(files));
+            libraries.insert(name.clone(), Value::Table(library));
+        }
+
+        let mut root = Table::new();
+        root.insert("libraries".to_owned(), Value::Table(libraries));
+        // Serializing a table of strings cannot fail
+        toml::to_string(&Value::Table(root)).unwrap()
+    }
+
+    /// The configuration equivalent to the generated project configuration file
+    pub fn to_config(&self, parent: &Path) -> Config {
+        // The generated file is always valid
+        Config::parse(&self.to_toml(parent), parent).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use std::fs::File;
+
+    #[test]
+    fn vhdl_files() {
+        assert!(is_vhdl_file("a.vhd"));
+        assert!(is_vhdl_file("dir/a.VHDL"));
+        assert!(!is_vhdl_file("a.v"));
+        assert!(!is_vhdl_file("a.xdc"));
+        assert!(!is_vhdl_file("vhd"));
+    }
+
+    #[test]
+    fn to_toml_is_relative_to_parent() {
+        let mut project = ImportedProject::new();
+        project.add_file("Lib", "/project/src/a.vhd");
+        project.add_file("lib", "/other/b[1].vhd");
+        project.add_file("lib", "/project/src/a.vhd");
+
+        let toml = project.to_toml(Path::new("/project"));
+        let value: Value = toml.parse().unwrap();
+        assert_eq!(
+            value["libraries"]["lib"]["files"],
+            Value::Array(vec![
+                Value::String("/other/b[[]1[]].vhd".to_owned()),
+                Value::String("src/a.vhd".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a[0].vhd");
+        File::create(&path).unwrap();
+        let file_name = path.to_string_lossy().into_owned();
+
+        let mut project = ImportedProject::new();
+        project.add_file("lib", &file_name);
+
+        let config = project.to_config(dir.path());
+        assert_eq!(
+            config.get_library("lib").unwrap().file_names().unwrap(),
+            vec![file_name]
+        );
+    }
+}
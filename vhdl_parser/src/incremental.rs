@@ -0,0 +1,277 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Parsing only the design units of a file which were changed by an edit
+//!
+//! The changed bytes are the difference between the common prefix and suffix of the
+//! previous and the new contents. The design units containing them are parsed from the
+//! end of the preceding design unit, the other design units and their messages are moved
+//! to the new source where those after the change are shifted by the change in length
+
+use ast::DesignFile;
+use cache::relocate;
+use design_unit::parse_design_units;
+use message::Message;
+use project::SourceFile;
+use source::Source;
+use tokenizer::TokenState;
+use {ParseOptions, VHDLParser};
+
+/// The start and end of the bytes of the previous contents which were replaced
+fn changed_range(previous: &[u8], contents: &[u8]) -> (usize, usize) {
+    let prefix = previous
+        .iter()
+        .zip(contents.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(contents[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix, previous.len() - suffix)
+}
+
+/// Conditional analysis may exclude any part of the file
+fn has_directives(contents: &[u8]) -> bool {
+    contents.contains(&b'`')
+}
+
+/// The same parse result as parsing the source entirely, where only the design units
+/// which changed since the file was parsed are parsed again
+/// Returns None when the whole source must be parsed, such as when the previous contents
+/// were read from disk and are not known, when the options changed, when there are
+/// conditional analysis directives or deferred bodies, or when the changed design units
+/// no longer end where the unchanged design units start
+pub fn reparse(
+    parser: &VHDLParser,
+    file: &SourceFile,
+    source: &Source,
+    options: &ParseOptions,
+) -> Option<(DesignFile, Vec<Message>)> {
+    let design_file = &file.design_file;
+    let ends = &design_file.design_unit_ends;
+    if options.interface_only
+        || file.parse_options != *options
+        || !design_file.deferred_bodies.is_empty()
+        || ends.len() != design_file.design_units.len()
+        || file.source.memory_bytes() == 0
+    {
+        return None;
+    }
+
+    let previous = file.source.contents().ok()?;
+    let contents = source.contents().ok()?;
+    if has_directives(&previous.bytes) || has_directives(&contents.bytes) {
+        return None;
+    }
+
+    let (changed_start, changed_end) = changed_range(&previous.bytes, &contents.bytes);
+    let delta = contents.len() as isize - previous.len() as isize;
+
+    // The text after the last design unit is changed when no design unit ends after the change
+    let first = ends
+        .iter()
+        .position(|end| *end > changed_start)
+        .unwrap_or(ends.len());
+    let last = ends[first..]
+        .iter()
+        .position(|end| *end >= changed_end)
+        .map(|idx| first + idx);
+
+    let start = if first == 0 { 0 } else { ends[first - 1] };
+    let (end, new_end) = match last {
+        Some(last) => (ends[last], (ends[last] as isize + delta) as usize),
+        None => (previous.len(), contents.len()),
+    };
+
+    let mut messages = Vec::new();
+    // Without directives there are no messages of conditional analysis
+    let mut stream = parser.token_stream(source, options, &mut Vec::new()).ok()?;
+    stream.set_state(TokenState::at(start));
+    let changed = parse_design_units(&mut stream, &mut messages, false, new_end).ok()?;
+    if last.is_some() && changed.design_unit_ends.last() != Some(&new_end) {
+        return None;
+    }
+    if messages
+        .iter()
+        .any(|message| message.pos.start < start || message.pos.start > new_end)
+    {
+        return None;
+    }
+
+    let after = last.map(|last| last + 1).unwrap_or(ends.len());
+    let shift = (end, delta);
+    let before_units: Vec<_> = relocate(
+        &design_file.design_units[..first],
+        &parser.symtab,
+        source,
+        shift,
+    )?;
+    let after_units: Vec<_> = relocate(
+        &design_file.design_units[after..],
+        &parser.symtab,
+        source,
+        shift,
+    )?;
+    // Messages at the end of the file such as an unexpected end belong to the text after
+    // the last design unit
+    let (before_messages, after_messages): (Vec<&Message>, Vec<&Message>) = file
+        .messages
+        .iter()
+        .filter(|message| message.pos.start < start || (last.is_some() && message.pos.start >= end))
+        .partition(|message| message.pos.start < start);
+    let before_messages: Vec<Message> = relocate(&before_messages, &parser.symtab, source, shift)?;
+    let after_messages: Vec<Message> = relocate(&after_messages, &parser.symtab, source, shift)?;
+
+    let mut design_units = before_units;
+    design_units.extend(changed.design_units);
+    design_units.extend(after_units);
+
+    let mut design_unit_ends = ends[..first].to_vec();
+    design_unit_ends.extend(changed.design_unit_ends);
+    design_unit_ends.extend(
+        ends[after..]
+            .iter()
+            .map(|end| (*end as isize + delta) as usize),
+    );
+
+    let mut all_messages = before_messages;
+    all_messages.extend(messages);
+    all_messages.extend(after_messages);
+
+    Some((
+        DesignFile {
+            design_units,
+            deferred_bodies: Vec::new(),
+            design_unit_ends,
+        },
+        all_messages,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity ent is
+  port (clk : in std_logic);
+end entity;
+
+architecture rtl of ent is
+  signal foo : natural;
+begin
+  foo <= 1;
+end architecture;
+
+package pkg is
+  constant c : natural := 0;
+end package;
+";
+
+    fn parse(parser: &VHDLParser, file_name: &str, code: &str) -> SourceFile {
+        let source = Source::from_file_contents(file_name, code).unwrap();
+        let mut messages = Vec::new();
+        let design_file = parser
+            .parse_design_source(&source, &mut messages)
+            .unwrap_or_else(|_| panic!("Parse failed"));
+        SourceFile {
+            library_name: "lib".to_owned(),
+            source,
+            design_file,
+            messages,
+            content_hash: 0,
+            parse_options: ParseOptions::default(),
+        }
+    }
+
+    /// Parses the edited code both again and incrementally
+    fn check_reparse(code: &str, edited: &str) -> Option<(DesignFile, Vec<Message>)> {
+        let parser = VHDLParser::new();
+        let file = parse(&parser, "file.vhd", code);
+        let expected = parse(&parser, "file.vhd", edited);
+        let result = reparse(&parser, &file, &expected.source, &ParseOptions::default());
+        if let Some((ref design_file, ref messages)) = result {
+            assert_eq!(*design_file, expected.design_file);
+            assert_eq!(*messages, expected.messages);
+        }
+        result
+    }
+
+    #[test]
+    fn changed_range_of_contents() {
+        assert_eq!(changed_range(b"abcdef", b"abXdef"), (2, 3));
+        assert_eq!(changed_range(b"abcdef", b"abXXXcdef"), (2, 2));
+        assert_eq!(changed_range(b"abcdef", b"abf"), (2, 5));
+        assert_eq!(changed_range(b"aaaa", b"aa"), (2, 4));
+        assert_eq!(changed_range(b"abc", b"abc"), (3, 3));
+    }
+
+    #[test]
+    fn reparse_changed_design_unit() {
+        // The name of a signal in the architecture
+        let edited = CODE.replace("signal foo", "signal foo_bar");
+        let edited = edited.replace("foo <=", "foo_bar <=");
+        assert!(check_reparse(CODE, &edited).is_some());
+
+        // The entity where the positions of the following design units and messages move
+        assert!(check_reparse(CODE, &CODE.replace("clk : in", "clock : in")).is_some());
+
+        // The value of the constant in the package at the end
+        assert!(check_reparse(CODE, &CODE.replace(":= 0;", ":= 1;")).is_some());
+    }
+
+    #[test]
+    fn reparse_design_unit_added_at_end() {
+        let edited = format!("{}\nentity ent2 is\nend entity;\n", CODE);
+        let (design_file, _) = check_reparse(CODE, &edited).unwrap();
+        assert_eq!(design_file.design_units.len(), 4);
+    }
+
+    #[test]
+    fn reparse_unexpected_end_of_file() {
+        let code = "entity ent is\nend entity;\n\nentity ent2 is\nend entity";
+        let (_, messages) = check_reparse(code, &format!("{};", code)).unwrap();
+        assert_eq!(messages, Vec::new());
+    }
+
+    #[test]
+    fn reparse_context_clause_of_first_design_unit() {
+        assert!(
+            check_reparse(CODE, &CODE.replace("library ieee;", "library ieee, std;")).is_some()
+        );
+    }
+
+    #[test]
+    fn design_unit_which_fails_to_parse_is_parsed_entirely() {
+        let parser = VHDLParser::new();
+        let file = parse(&parser, "file.vhd", CODE);
+        let edited =
+            Source::from_file_contents("file.vhd", &CODE.replace("foo <= 1;", "foo := 1;"))
+                .unwrap();
+        assert!(reparse(&parser, &file, &edited, &ParseOptions::default()).is_none());
+    }
+
+    #[test]
+    fn directives_are_parsed_entirely() {
+        let edited = CODE.replace("foo <= 1;", "`if DEBUG = \"true\" then\nfoo <= 1;\n`end if");
+        assert!(check_reparse(CODE, &edited).is_none());
+    }
+
+    #[test]
+    fn contents_read_from_disk_are_parsed_entirely() {
+        let parser = VHDLParser::new();
+        let mut file = parse(&parser, "file.vhd", CODE);
+        file.source = Source::from_file("file.vhd");
+        let edited = Source::from_file_contents("file.vhd", CODE).unwrap();
+        assert!(reparse(&parser, &file, &edited, &ParseOptions::default()).is_none());
+    }
+}
@@ -0,0 +1,320 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Hints shown within the code for the formals of positional associations
+//! and for the subtype of loop parameters and aliases where it is not written
+
+use ast::AbstractLiteral as Literal;
+use name_resolution::{NameResolution, NamedEntityKind};
+use quick_fix::subtype_of;
+use signature::positional_formals;
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+use tokenizer::Kind::*;
+use tokenizer::Value;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum InlayHintKind {
+    /// The formal of a positional association
+    Parameter,
+    /// The subtype of a loop parameter or an alias
+    Type,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct InlayHint {
+    /// The empty position where the hint is shown
+    pub pos: SrcPos,
+    pub label: String,
+    pub kind: InlayHintKind,
+}
+
+fn is_integer(tokens: &SourceTokens, i: usize) -> bool {
+    matches!(
+        tokens.tokens.get(i).map(|token| &token.value),
+        Some(Value::AbstractLiteral(Literal::Integer(..)))
+    )
+}
+
+/// The index of the last identifier of a simple or selected name starting at the index
+fn name_end(tokens: &SourceTokens, start: usize) -> Option<usize> {
+    if tokens.kind(start) != Some(Identifier) {
+        return None;
+    }
+    let mut end = start;
+    while tokens.kind(end + 1) == Some(Dot) && tokens.kind(end + 2) == Some(Identifier) {
+        end += 2;
+    }
+    Some(end)
+}
+
+/// The named entity denoted by the name at the index when it is one of the kinds
+fn resolved_as(
+    resolution: &NameResolution,
+    tokens: &SourceTokens,
+    i: usize,
+    kinds: &[NamedEntityKind],
+) -> Option<String> {
+    let pos = &tokens.tokens[i].pos;
+    let entities = resolution.entities_at(pos.source.file_name()?, pos.start);
+    match entities.as_slice() {
+        [entity] if kinds.contains(&entity.kind) => Some(entity.designator.clone()),
+        _ => None,
+    }
+}
+
+/// LRM 10.10 The subtype of a loop parameter is that of its discrete range which is
+/// only known here when it is an integer range of literals or a type mark
+fn loop_parameter_subtype(
+    resolution: &NameResolution,
+    tokens: &SourceTokens,
+    i: usize,
+) -> Option<String> {
+    if tokens.kind(i + 1) != Some(In) {
+        return None;
+    }
+    let first = i + 2;
+    let last = (first..tokens.tokens.len())
+        .find(|j| matches!(tokens.kind(*j), Some(Loop) | Some(Generate)))?
+        .checked_sub(1)?;
+
+    let is_direction = matches!(tokens.kind(first + 1), Some(To) | Some(Downto));
+    if last == first + 2 && is_direction && is_integer(tokens, first) && is_integer(tokens, last) {
+        return Some("integer".to_owned());
+    }
+    if name_end(tokens, first) == Some(last) {
+        return resolved_as(
+            resolution,
+            tokens,
+            last,
+            &[NamedEntityKind::Type, NamedEntityKind::Subtype],
+        );
+    }
+    None
+}
+
+/// LRM 6.6.2 The subtype of an object alias without a subtype indication is that of the object
+fn alias_subtype(resolution: &NameResolution, tokens: &SourceTokens, i: usize) -> Option<String> {
+    if tokens.kind(i + 1) != Some(Is) {
+        return None;
+    }
+    let last = name_end(tokens, i + 2)?;
+    if tokens.kind(last + 1) != Some(SemiColon) {
+        return None;
+    }
+    let pos = &tokens.tokens[last].pos;
+    let entities = resolution.entities_at(pos.source.file_name()?, pos.start);
+    match entities.as_slice() {
+        [object] => match object.kind {
+            NamedEntityKind::Signal
+            | NamedEntityKind::Constant
+            | NamedEntityKind::Variable
+            | NamedEntityKind::SharedVariable
+            | NamedEntityKind::Generic
+            | NamedEntityKind::Port
+            | NamedEntityKind::Parameter => subtype_of(object),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The hints of all positional associations, loop parameters and aliases of a source
+pub fn inlay_hints(resolution: &NameResolution, source: &Source) -> Vec<InlayHint> {
+    let mut hints: Vec<InlayHint> = positional_formals(resolution, source)
+        .into_iter()
+        .map(|(pos, formal)| InlayHint {
+            pos: source.pos(pos.start, 0),
+            label: format!("{}:", formal.designator),
+            kind: InlayHintKind::Parameter,
+        })
+        .collect();
+
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return hints;
+        }
+    };
+    let file_name = match source.file_name() {
+        Some(file_name) => file_name,
+        None => {
+            return hints;
+        }
+    };
+    for entity in resolution.declarations().iter() {
+        if entity.pos.source.file_name() != Some(file_name) {
+            continue;
+        }
+        let i = match tokens.index_of(&entity.pos) {
+            Some(i) => i,
+            None => continue,
+        };
+        let subtype = match entity.kind {
+            NamedEntityKind::LoopParameter => loop_parameter_subtype(resolution, &tokens, i),
+            NamedEntityKind::Alias => alias_subtype(resolution, &tokens, i),
+            _ => None,
+        };
+        if let Some(subtype) = subtype {
+            hints.push(InlayHint {
+                pos: source.pos(entity.pos.start + entity.pos.length, 0),
+                label: format!(": {}", subtype),
+                kind: InlayHintKind::Type,
+            });
+        }
+    }
+
+    hints.sort_by_key(|hint| hint.pos.start);
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use library::DesignRoot;
+    use test_util::TempProject;
+
+    /// The hints of the code as the text before each hint together with its label
+    fn hints(declarations: &str, code: &str) -> Vec<(String, String, InlayHintKind)> {
+        let mut project = TempProject::new();
+        project.add_file("lib", "decl.vhd", declarations);
+        let file_name = project.add_file("lib", "code.vhd", code);
+        let files = project.project.files();
+        let resolution = NameResolution::new(&DesignRoot::new(&files));
+        let source = &project.project.get_file(&file_name).unwrap().source;
+        inlay_hints(&resolution, source)
+            .into_iter()
+            .map(|hint| {
+                let line = code[..hint.pos.start].lines().last().unwrap_or("");
+                (line.trim().to_owned(), hint.label, hint.kind)
+            })
+            .collect()
+    }
+
+    const DECLARATIONS: &str = "
+package pkg is
+  function add(left : natural; right : natural := 1) return natural;
+  function add(left : real; right : real) return real;
+  function inc(value : natural) return natural;
+  function scale(value : natural; factor : natural) return natural;
+  function scale(other : real) return real;
+end package;
+
+entity child is
+  generic (width : natural);
+  port (clk : in bit; q : out bit);
+end entity;";
+
+    #[test]
+    fn formals_of_positional_associations() {
+        let code = "
+use work.pkg.all;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+  signal clk, q : bit;
+  constant a : natural := add(1, right => 2);
+  constant b : natural := inc(add(1, 2));
+  constant c : natural := scale(1, 2);
+begin
+  inst : entity work.child generic map (8) port map (clk, q => q);
+end architecture;";
+        assert_eq!(
+            hints(DECLARATIONS, code),
+            vec![
+                (
+                    "constant a : natural := add(".to_owned(),
+                    "left:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "constant b : natural := inc(".to_owned(),
+                    "value:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "constant b : natural := inc(add(".to_owned(),
+                    "left:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "constant b : natural := inc(add(1,".to_owned(),
+                    "right:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "constant c : natural := scale(".to_owned(),
+                    "value:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "constant c : natural := scale(1,".to_owned(),
+                    "factor:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "inst : entity work.child generic map (".to_owned(),
+                    "width:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+                (
+                    "inst : entity work.child generic map (8) port map (".to_owned(),
+                    "clk:".to_owned(),
+                    InlayHintKind::Parameter
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn subtypes_of_loop_parameters_and_aliases() {
+        let code = "
+use work.pkg.all;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+  type state_t is (idle, running);
+  signal data : bit_vector(7 downto 0);
+  alias data_alias is data;
+  alias ranged : bit_vector(7 downto 0) is data;
+begin
+  main : process
+  begin
+    for i in 0 to 7 loop
+    end loop;
+    for state in state_t loop
+    end loop;
+    for j in data'range loop
+    end loop;
+    wait;
+  end process;
+end architecture;";
+        assert_eq!(
+            hints(DECLARATIONS, code),
+            vec![
+                (
+                    "alias data_alias".to_owned(),
+                    ": bit_vector(7 downto 0)".to_owned(),
+                    InlayHintKind::Type
+                ),
+                (
+                    "for i".to_owned(),
+                    ": integer".to_owned(),
+                    InlayHintKind::Type
+                ),
+                (
+                    "for state".to_owned(),
+                    ": state_t".to_owned(),
+                    InlayHintKind::Type
+                ),
+            ]
+        );
+    }
+}
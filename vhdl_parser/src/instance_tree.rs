@@ -0,0 +1,855 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The tree of instances below a top level entity
+
+use ast::{
+    ActualPart, AssociationElement, BlockConfiguration, ConcurrentStatement, ConfigurationItem,
+    EntityAspect, InstantiatedUnit, InstantiationStatement, InterfaceDeclaration,
+    LabeledConcurrentStatement, LibraryUnit, Name, SelectedName,
+};
+use latin_1::Latin1String;
+use library::{symbol_key, UnitId};
+use name_resolution::entity_interface;
+use project::{SourceFile, VerilogSourceFile};
+use source::SrcPos;
+use std::collections::{BTreeMap, BTreeSet};
+use verilog_parser::VerilogModule;
+
+/// An instance together with the instances of its architecture
+#[derive(PartialEq, Debug, Clone)]
+pub struct InstanceNode {
+    /// The label of the instance prefixed by enclosing block and generate labels,
+    /// the entity name for the top level
+    pub label: String,
+    /// The entity bound to the instance, None when it is not within the project
+    pub entity: Option<UnitId>,
+    /// The Verilog module bound to the instance when there is no such entity
+    pub module: Option<UnitId>,
+    /// The instantiated unit as written when it is not within the project
+    pub unit_name: String,
+    pub architecture: Option<String>,
+    /// Each generic of the entity with its actual, or its default when not associated
+    pub generics: Vec<(String, String)>,
+    pub children: Vec<InstanceNode>,
+}
+
+impl InstanceNode {
+    /// The entity and architecture such as lib.ent(rtl)
+    pub fn unit(&self) -> String {
+        match (&self.entity, &self.architecture) {
+            (Some(entity), Some(architecture)) => format!("{}({})", entity, architecture),
+            (Some(entity), None) => entity.to_string(),
+            (None, _) => match self.module {
+                Some(ref module) => module.to_string(),
+                None => self.unit_name.clone(),
+            },
+        }
+    }
+
+    /// Only the instances whose label or unit contains the pattern together with their
+    /// parents, matching is case insensitive
+    pub fn filter(&self, pattern: &str) -> Option<InstanceNode> {
+        let pattern = pattern.to_lowercase();
+        let children: Vec<InstanceNode> = self
+            .children
+            .iter()
+            .filter_map(|child| child.filter(&pattern))
+            .collect();
+        let matches = self.label.to_lowercase().contains(&pattern)
+            || self.unit().to_lowercase().contains(&pattern);
+        if matches || !children.is_empty() {
+            Some(InstanceNode {
+                children,
+                ..self.clone()
+            })
+        } else {
+            None
+        }
+    }
+
+    /// One line per instance indented by its depth
+    pub fn pretty_string(&self) -> String {
+        let mut lines = Vec::new();
+        self.push_lines(0, &mut lines);
+        lines.join("\n")
+    }
+
+    fn push_lines(&self, depth: usize, lines: &mut Vec<String>) {
+        let mut line = format!("{}{} : {}", "  ".repeat(depth), self.label, self.unit());
+        if !self.generics.is_empty() {
+            let generics: Vec<String> = self
+                .generics
+                .iter()
+                .map(|(name, value)| format!("{} => {}", name, value))
+                .collect();
+            line.push_str(&format!(" generic map ({})", generics.join(", ")));
+        }
+        lines.push(line);
+        for child in self.children.iter() {
+            child.push_lines(depth + 1, lines);
+        }
+    }
+}
+
+fn pos_text(pos: &SrcPos) -> String {
+    match pos.source.contents() {
+        Ok(contents) => {
+            let end = (pos.start + pos.length).min(contents.bytes.len());
+            Latin1String::new(&contents.bytes[pos.start.min(end)..end]).to_string()
+        }
+        Err(_) => String::new(),
+    }
+}
+
+struct Architecture<'a> {
+    name: String,
+    statements: &'a [LabeledConcurrentStatement],
+}
+
+/// The entities, architectures and configurations of a project by which instances are elaborated
+pub struct InstanceHierarchy<'a> {
+    entities: BTreeMap<UnitId, (String, &'a LibraryUnit)>,
+    /// The architectures of each entity in the order of the files
+    architectures: BTreeMap<UnitId, Vec<Architecture<'a>>>,
+    /// The entity and architecture of each configuration
+    configurations: BTreeMap<UnitId, (UnitId, Option<String>)>,
+    /// The lower case names of the components bound to an entity or configuration by a
+    /// component configuration
+    configured_components: BTreeSet<String>,
+    /// The Verilog modules by their lower case names, instances bind to them when there
+    /// is no entity of the name
+    verilog_modules: BTreeMap<UnitId, &'a VerilogModule>,
+}
+
+/// The library and unit of a name which is implicitly within the library unless prefixed
+fn unit_name_id(library_name: &str, name: &SelectedName) -> Option<UnitId> {
+    let parts: Vec<String> = name.iter().map(|ident| symbol_key(&ident.item)).collect();
+    match parts.len() {
+        1 => Some(UnitId::primary(library_name, &parts[0])),
+        2 if parts[0] == "work" => Some(UnitId::primary(library_name, &parts[1])),
+        2 => Some(UnitId::primary(&parts[0], &parts[1])),
+        _ => None,
+    }
+}
+
+/// Adds the components which the block configuration binds to an entity or configuration
+fn add_configured_components(block: &BlockConfiguration, components: &mut BTreeSet<String>) {
+    for item in block.items.iter() {
+        match item {
+            ConfigurationItem::Block(ref block) => add_configured_components(block, components),
+            ConfigurationItem::Component(ref component) => {
+                let bound = component.bind_ind.as_ref().is_some_and(|binding| {
+                    matches!(
+                        binding.entity_aspect,
+                        Some(EntityAspect::Entity(..)) | Some(EntityAspect::Configuration(..))
+                    )
+                });
+                if let (true, Some(name)) = (bound, component.spec.component_name.last()) {
+                    components.insert(symbol_key(&name.item));
+                }
+                if let Some(ref block) = component.block_config {
+                    add_configured_components(block, components);
+                }
+            }
+        }
+    }
+}
+
+fn unit_name_text(name: &SelectedName) -> String {
+    let parts: Vec<String> = name.iter().map(|ident| ident.item.name_utf8()).collect();
+    parts.join(".")
+}
+
+impl<'a> InstanceHierarchy<'a> {
+    pub fn new(files: &[&'a SourceFile]) -> InstanceHierarchy<'a> {
+        let mut entities = BTreeMap::new();
+        let mut architectures: BTreeMap<UnitId, Vec<Architecture<'a>>> = BTreeMap::new();
+        let mut configurations = BTreeMap::new();
+        let mut configured_components = BTreeSet::new();
+
+        for file in files {
+            for design_unit in file.design_file.design_units.iter() {
+                let unit_id = UnitId::new(&file.library_name, design_unit);
+                match design_unit.library_unit {
+                    LibraryUnit::EntityDeclaration { ref ident, .. } => {
+                        entities
+                            .entry(unit_id)
+                            .or_insert((ident.item.name_utf8(), &design_unit.library_unit));
+                    }
+                    LibraryUnit::ArchitectureBody {
+                        ref ident,
+                        ref statements,
+                        ..
+                    } => {
+                        architectures
+                            .entry(unit_id.primary_unit())
+                            .or_default()
+                            .push(Architecture {
+                                name: ident.item.name_utf8(),
+                                statements,
+                            });
+                    }
+                    LibraryUnit::Configuration(ref config) => {
+                        if let Some(ref block) = config.block_config {
+                            add_configured_components(block, &mut configured_components);
+                        }
+                        if let Some(entity_id) =
+                            unit_name_id(&file.library_name, &config.entity_name)
+                        {
+                            let architecture = config.block_config.as_ref().and_then(|block| {
+                                match block.block_spec.item {
+                                    Name::Simple(ref symbol) => Some(symbol.name_utf8()),
+                                    _ => None,
+                                }
+                            });
+                            configurations.insert(unit_id, (entity_id, architecture));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        InstanceHierarchy {
+            entities,
+            architectures,
+            configurations,
+            configured_components,
+            verilog_modules: BTreeMap::new(),
+        }
+    }
+
+    /// Bind instances to the modules of the Verilog files, the first module of a name wins
+    pub fn with_verilog_files(mut self, files: &[&'a VerilogSourceFile]) -> InstanceHierarchy<'a> {
+        for file in files {
+            for module in file.verilog_file.modules.iter() {
+                self.verilog_modules
+                    .entry(UnitId::primary(
+                        &file.library_name,
+                        &module.name.item.to_lowercase(),
+                    ))
+                    .or_insert(module);
+            }
+        }
+        self
+    }
+
+    /// The entity of a name such as lib.ent or ent where the work library, or no library,
+    /// matches an entity of any library when it is the only one with the name
+    pub fn top_unit(&self, name: &str) -> Option<UnitId> {
+        let parts: Vec<String> = name.split('.').map(str::to_lowercase).collect();
+        let (library_name, entity_name) = match parts.as_slice() {
+            [entity_name] => (None, entity_name),
+            [library_name, entity_name] => (Some(library_name.as_str()), entity_name),
+            _ => {
+                return None;
+            }
+        };
+        if let Some(library_name) = library_name {
+            let unit_id = UnitId::primary(library_name, entity_name);
+            if self.entities.contains_key(&unit_id) || library_name != "work" {
+                return Some(unit_id).filter(|unit_id| self.entities.contains_key(unit_id));
+            }
+        }
+        let mut candidates = self
+            .entities
+            .keys()
+            .filter(|unit_id| unit_id.primary_name() == entity_name);
+        match (candidates.next(), candidates.next()) {
+            (Some(unit_id), None) => Some(unit_id.clone()),
+            _ => None,
+        }
+    }
+
+    /// The instance tree of the entity where instances below the maximum depth are omitted
+    pub fn tree(&self, top: &UnitId, max_depth: Option<usize>) -> Option<InstanceNode> {
+        let (name, _) = self.entities.get(top)?;
+        Some(self.node(
+            name.clone(),
+            String::new(),
+            Some(top.clone()),
+            None,
+            &[],
+            max_depth,
+            &mut Vec::new(),
+        ))
+    }
+
+    /// LRM 7.3.3 Without an explicit architecture the most recently analyzed one is used
+    fn architecture(&self, entity: &UnitId, name: Option<&str>) -> Option<&Architecture<'a>> {
+        let architectures = self.architectures.get(entity)?;
+        match name {
+            Some(name) => architectures
+                .iter()
+                .find(|arch| arch.name.eq_ignore_ascii_case(name)),
+            None => architectures.last(),
+        }
+    }
+
+    fn generics(
+        &self,
+        entity: &UnitId,
+        generic_map: &[AssociationElement],
+    ) -> Vec<(String, String)> {
+        let generic_list = match self.entities.get(entity) {
+            Some((_, library_unit)) => entity_interface(library_unit).0,
+            None => {
+                return Vec::new();
+            }
+        };
+        let mut generics = Vec::new();
+        for (i, decl) in generic_list.iter().enumerate() {
+            let object = match decl {
+                InterfaceDeclaration::Object(ref object) => object,
+                _ => continue,
+            };
+            let name = object.ident.item.name_utf8();
+            let actual = generic_map
+                .iter()
+                .enumerate()
+                .find(|(j, assoc)| match assoc.formal {
+                    Some(ref formal) => pos_text(&formal.pos).eq_ignore_ascii_case(&name),
+                    None => *j == i,
+                });
+            let value = match actual {
+                Some((_, assoc)) => match assoc.actual.item {
+                    ActualPart::Expression(..) => pos_text(&assoc.actual.pos),
+                    ActualPart::Open => "open".to_owned(),
+                },
+                None => object
+                    .expression
+                    .as_ref()
+                    .map(|expr| pos_text(&expr.pos))
+                    .unwrap_or_else(|| "open".to_owned()),
+            };
+            generics.push((name, value));
+        }
+        generics
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn node(
+        &self,
+        label: String,
+        unit_name: String,
+        entity: Option<UnitId>,
+        architecture: Option<String>,
+        generic_map: &[AssociationElement],
+        max_depth: Option<usize>,
+        path: &mut Vec<UnitId>,
+    ) -> InstanceNode {
+        let module = entity
+            .as_ref()
+            .filter(|entity| !self.entities.contains_key(entity))
+            .and_then(|entity| self.verilog_modules.get_key_value(entity));
+        let entity = entity.filter(|entity| self.entities.contains_key(entity));
+        let mut node = InstanceNode {
+            label,
+            unit_name,
+            architecture: None,
+            generics: Vec::new(),
+            children: Vec::new(),
+            entity: entity.clone(),
+            module: module.map(|(module_id, _)| module_id.clone()),
+        };
+        let entity = match entity {
+            Some(entity) => entity,
+            None => {
+                if let Some((_, module)) = module {
+                    node.generics = module_parameters(module, generic_map);
+                }
+                return node;
+            }
+        };
+        node.generics = self.generics(&entity, generic_map);
+        let architecture = match self.architecture(&entity, architecture.as_deref()) {
+            Some(architecture) => architecture,
+            None => {
+                node.architecture = architecture;
+                return node;
+            }
+        };
+        node.architecture = Some(architecture.name.clone());
+
+        let expand = max_depth.map(|depth| path.len() < depth).unwrap_or(true);
+        if expand && !path.contains(&entity) {
+            path.push(entity.clone());
+            self.add_children(
+                &entity.library_name,
+                architecture.statements,
+                max_depth,
+                path,
+                &mut node.children,
+            );
+            path.pop();
+        }
+        node
+    }
+
+    /// The entity and architecture bound to the instantiated unit together with its name
+    fn binding(
+        &self,
+        library_name: &str,
+        unit: &InstantiatedUnit,
+    ) -> (String, Option<UnitId>, Option<String>) {
+        match unit {
+            InstantiatedUnit::Entity(ref name, ref architecture) => (
+                unit_name_text(name),
+                unit_name_id(library_name, name),
+                architecture.as_ref().map(|ident| ident.item.name_utf8()),
+            ),
+            InstantiatedUnit::Configuration(ref name) => {
+                let config = unit_name_id(library_name, name)
+                    .and_then(|config| self.configurations.get(&config));
+                (
+                    unit_name_text(name),
+                    config.map(|(entity, _)| entity.clone()),
+                    config.and_then(|(_, architecture)| architecture.clone()),
+                )
+            }
+            // Default binding to an entity with the same name in the library
+            InstantiatedUnit::Component(ref name) => (
+                unit_name_text(name),
+                name.last()
+                    .map(|ident| UnitId::primary(library_name, &symbol_key(&ident.item))),
+                None,
+            ),
+        }
+    }
+
+    fn add_children(
+        &self,
+        library_name: &str,
+        statements: &[LabeledConcurrentStatement],
+        max_depth: Option<usize>,
+        path: &mut Vec<UnitId>,
+        children: &mut Vec<InstanceNode>,
+    ) {
+        for_each_instance("", statements, &mut |label, _, instance| {
+            let (unit_name, entity, architecture) = self.binding(library_name, &instance.unit);
+            children.push(self.node(
+                label,
+                unit_name,
+                entity,
+                architecture,
+                &instance.generic_map,
+                max_depth,
+                path,
+            ));
+        });
+    }
+
+    /// The generic and port clauses of the entity
+    pub fn entity(&self, unit_id: &UnitId) -> Option<&'a LibraryUnit> {
+        self.entities
+            .get(unit_id)
+            .map(|(_, library_unit)| *library_unit)
+    }
+
+    /// The instances of entities within the project in every architecture
+    pub fn instantiations(&self) -> Vec<Instantiation<'a>> {
+        self.partition_instantiations().instantiations
+    }
+
+    /// The instances in every architecture which are bound to a Verilog module of the project
+    pub fn verilog_instantiations(&self) -> Vec<VerilogInstantiation<'a>> {
+        self.partition_instantiations().verilog
+    }
+
+    /// The instances in every architecture which are not bound to an entity or Verilog module
+    /// within the project, except component instances bound by a configuration
+    pub fn unbound_instantiations(&self) -> Vec<UnboundInstantiation<'a>> {
+        self.partition_instantiations().unbound
+    }
+
+    fn partition_instantiations(&self) -> Partition<'a> {
+        let mut instantiations = Vec::new();
+        let mut verilog = Vec::new();
+        let mut unbound = Vec::new();
+        for (entity_id, architectures) in self.architectures.iter() {
+            for architecture in architectures.iter() {
+                let architecture_id = UnitId::architecture(
+                    &entity_id.library_name,
+                    entity_id.primary_name(),
+                    &architecture.name,
+                );
+                for_each_instance(
+                    "",
+                    architecture.statements,
+                    &mut |label, statement, instance| {
+                        let pos =
+                            match (&statement.label, instance_unit_name(&instance.unit).last()) {
+                                (Some(label), _) | (None, Some(label)) => label.pos.clone(),
+                                (None, None) => return,
+                            };
+                        let (unit_name, entity, _) =
+                            self.binding(&entity_id.library_name, &instance.unit);
+                        match entity {
+                            Some(ref entity) if self.entities.contains_key(entity) => {
+                                instantiations.push(Instantiation {
+                                    architecture: architecture_id.clone(),
+                                    label,
+                                    pos,
+                                    entity: entity.clone(),
+                                    instance,
+                                });
+                            }
+                            Some(ref module) if self.verilog_modules.contains_key(module) => {
+                                verilog.push(VerilogInstantiation {
+                                    architecture: architecture_id.clone(),
+                                    label,
+                                    pos,
+                                    module: module.clone(),
+                                    instance,
+                                });
+                            }
+                            _ => {
+                                if let InstantiatedUnit::Component(ref name) = instance.unit {
+                                    let configured = name.last().is_some_and(|ident| {
+                                        self.configured_components
+                                            .contains(&symbol_key(&ident.item))
+                                    });
+                                    if configured {
+                                        return;
+                                    }
+                                }
+                                unbound.push(UnboundInstantiation {
+                                    architecture: architecture_id.clone(),
+                                    label,
+                                    pos,
+                                    unit_name,
+                                    instance,
+                                });
+                            }
+                        }
+                    },
+                );
+            }
+        }
+        Partition {
+            instantiations,
+            verilog,
+            unbound,
+        }
+    }
+
+    /// The entities within the project
+    pub fn entity_ids(&self) -> Vec<&UnitId> {
+        self.entities.keys().collect()
+    }
+
+    /// The Verilog module of the unit id of its lower case name
+    pub fn verilog_module(&self, unit_id: &UnitId) -> Option<&'a VerilogModule> {
+        self.verilog_modules.get(unit_id).cloned()
+    }
+}
+
+/// Each parameter of the module which an instance can override with its actual, or its
+/// default when not associated
+fn module_parameters(
+    module: &VerilogModule,
+    generic_map: &[AssociationElement],
+) -> Vec<(String, String)> {
+    let mut generics = Vec::new();
+    let parameters = module
+        .parameters
+        .iter()
+        .filter(|parameter| !parameter.local);
+    for (i, parameter) in parameters.enumerate() {
+        let name = parameter.name.item.clone();
+        let actual = generic_map
+            .iter()
+            .enumerate()
+            .find(|(j, assoc)| match assoc.formal {
+                Some(ref formal) => pos_text(&formal.pos).eq_ignore_ascii_case(&name),
+                None => *j == i,
+            });
+        let value = match actual {
+            Some((_, assoc)) => match assoc.actual.item {
+                ActualPart::Expression(..) => pos_text(&assoc.actual.pos),
+                ActualPart::Open => "open".to_owned(),
+            },
+            None => parameter
+                .default
+                .clone()
+                .unwrap_or_else(|| "open".to_owned()),
+        };
+        generics.push((name, value));
+    }
+    generics
+}
+
+struct Partition<'a> {
+    instantiations: Vec<Instantiation<'a>>,
+    verilog: Vec<VerilogInstantiation<'a>>,
+    unbound: Vec<UnboundInstantiation<'a>>,
+}
+
+/// An instance of an entity within an architecture
+#[derive(PartialEq, Debug, Clone)]
+pub struct Instantiation<'a> {
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    /// The position of the label
+    pub pos: SrcPos,
+    /// The entity bound to the instance
+    pub entity: UnitId,
+    pub instance: &'a InstantiationStatement,
+}
+
+/// An instance of a Verilog module within an architecture
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogInstantiation<'a> {
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    /// The position of the label
+    pub pos: SrcPos,
+    /// The library and lower case name of the module bound to the instance
+    pub module: UnitId,
+    pub instance: &'a InstantiationStatement,
+}
+
+/// An instance of a unit which is not within the project, a blackbox
+#[derive(PartialEq, Debug, Clone)]
+pub struct UnboundInstantiation<'a> {
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    /// The position of the label
+    pub pos: SrcPos,
+    /// The instantiated unit as written such as unisim.bufg
+    pub unit_name: String,
+    pub instance: &'a InstantiationStatement,
+}
+
+fn instance_unit_name(unit: &InstantiatedUnit) -> &SelectedName {
+    match unit {
+        InstantiatedUnit::Component(ref name)
+        | InstantiatedUnit::Entity(ref name, _)
+        | InstantiatedUnit::Configuration(ref name) => name,
+    }
+}
+
+/// Calls the function with the label, prefixed by enclosing block and generate labels,
+/// the statement and the instantiation of each instance within the statements
+fn for_each_instance<'a, F>(prefix: &str, statements: &'a [LabeledConcurrentStatement], f: &mut F)
+where
+    F: FnMut(String, &'a LabeledConcurrentStatement, &'a InstantiationStatement),
+{
+    for statement in statements {
+        let label = statement
+            .label
+            .as_ref()
+            .map(|label| format!("{}{}", prefix, label.item.name_utf8()))
+            .unwrap_or_else(|| prefix.trim_end_matches('.').to_owned());
+        let nested = format!("{}.", label);
+        match statement.statement {
+            ConcurrentStatement::Instance(ref instance) => f(label, statement, instance),
+            ConcurrentStatement::Block(ref block) => {
+                for_each_instance(&nested, &block.statements, f);
+            }
+            ConcurrentStatement::ForGenerate(ref gen) => {
+                for_each_instance(&nested, &gen.body.statements, f);
+            }
+            ConcurrentStatement::IfGenerate(ref gen) => {
+                for conditional in gen.conditionals.iter() {
+                    for_each_instance(&nested, &conditional.item.statements, f);
+                }
+                if let Some(ref body) = gen.else_item {
+                    for_each_instance(&nested, &body.statements, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+entity top is
+end entity;
+
+architecture rtl of top is
+begin
+  cpu: entity work.core(fast) generic map (width => 32);
+  gen: for i in 0 to 1 generate
+    mem: component ram generic map (16);
+  end generate;
+  ext: entity other.ip;
+end architecture;
+
+entity core is
+  generic (width : natural := 8; depth : natural := 4);
+end entity;
+
+architecture slow of core is
+begin
+end architecture;
+
+architecture fast of core is
+begin
+  alu: entity work.ram;
+end architecture;
+
+entity ram is
+  generic (size : natural := 1024);
+end entity;
+
+architecture rtl of ram is
+begin
+end architecture;",
+        );
+        project
+    }
+
+    #[test]
+    fn tree_of_top_level() {
+        let project = project();
+        let files = project.project.files();
+        let hierarchy = InstanceHierarchy::new(&files);
+        let top = hierarchy.top_unit("work.top").unwrap();
+        assert_eq!(top, UnitId::primary("lib", "top"));
+        assert_eq!(hierarchy.top_unit("top"), Some(top.clone()));
+        assert_eq!(hierarchy.top_unit("other.top"), None);
+
+        let tree = hierarchy.tree(&top, None).unwrap();
+        assert_eq!(
+            tree.pretty_string(),
+            "top : lib.top(rtl)
+  cpu : lib.core(fast) generic map (width => 32, depth => 4)
+    alu : lib.ram(rtl) generic map (size => 1024)
+  gen.mem : lib.ram(rtl) generic map (size => 16)
+  ext : other.ip"
+        );
+    }
+
+    #[test]
+    fn depth_and_filter() {
+        let project = project();
+        let files = project.project.files();
+        let hierarchy = InstanceHierarchy::new(&files);
+        let top = UnitId::primary("lib", "top");
+
+        let tree = hierarchy.tree(&top, Some(1)).unwrap();
+        assert_eq!(tree.children.len(), 3);
+        assert!(tree.children[0].children.is_empty());
+
+        let tree = hierarchy.tree(&top, None).unwrap();
+        assert_eq!(
+            tree.filter("RAM").unwrap().pretty_string(),
+            "top : lib.top(rtl)
+  cpu : lib.core(fast) generic map (width => 32, depth => 4)
+    alu : lib.ram(rtl) generic map (size => 1024)
+  gen.mem : lib.ram(rtl) generic map (size => 16)"
+        );
+        assert_eq!(tree.filter("missing"), None);
+    }
+
+    #[test]
+    fn unbound_instantiations() {
+        let mut project = project();
+        project.add_file(
+            "lib",
+            "vendor.vhd",
+            "
+entity wrapper is
+end entity;
+
+architecture rtl of wrapper is
+begin
+  buf: component bufg;
+  pll: component clk_wiz;
+  mux: component mux2;
+end architecture;
+
+configuration wrapper_cfg of wrapper is
+  for rtl
+    for mux : mux2
+      use entity work.core;
+    end for;
+  end for;
+end configuration;",
+        );
+        let files = project.project.files();
+        let hierarchy = InstanceHierarchy::new(&files);
+        let unbound = hierarchy.unbound_instantiations();
+        let summary: Vec<(String, &str, &str)> = unbound
+            .iter()
+            .map(|instance| {
+                (
+                    instance.architecture.to_string(),
+                    instance.label.as_str(),
+                    instance.unit_name.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("lib.top(rtl)".to_owned(), "ext", "other.ip"),
+                ("lib.wrapper(rtl)".to_owned(), "buf", "bufg"),
+                ("lib.wrapper(rtl)".to_owned(), "pll", "clk_wiz"),
+            ]
+        );
+    }
+
+    #[test]
+    fn instances_of_verilog_modules() {
+        let mut project = project();
+        project.add_file(
+            "lib",
+            "ip.v",
+            "
+module ip #(parameter WIDTH = 8, parameter DEPTH = 2) (input clk);
+endmodule",
+        );
+        project.add_file(
+            "other",
+            "ip.sv",
+            "
+module ip #(parameter N = 1) ();
+endmodule",
+        );
+        let files = project.project.files();
+        let verilog_files = project.project.verilog_files();
+        let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+        let tree = hierarchy
+            .tree(&UnitId::primary("lib", "top"), Some(1))
+            .unwrap();
+        assert_eq!(
+            tree.children[2].pretty_string(),
+            "ext : other.ip generic map (N => 1)"
+        );
+        assert_eq!(
+            tree.children[2].module,
+            Some(UnitId::primary("other", "ip"))
+        );
+        assert_eq!(hierarchy.unbound_instantiations(), vec![]);
+
+        let verilog = hierarchy.verilog_instantiations();
+        assert_eq!(verilog.len(), 1);
+        assert_eq!(verilog[0].label, "ext");
+        assert_eq!(verilog[0].module, UnitId::primary("other", "ip"));
+        assert_eq!(
+            hierarchy
+                .verilog_module(&verilog[0].module)
+                .map(|module| module.name.item.as_str()),
+            Some("ip")
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The instances of each entity together with the generic and port associations which
+//! no longer match the entity, such as new ports which are not associated or removed
+//! generics which are still associated
+//!
+//! Component instances are checked against the entity of their default binding
+
+use ast::{
+    ActualPart, AssociationElement, Designator, Expression, InterfaceDeclaration, Mode, Name,
+    SubprogramDeclaration,
+};
+use instance_tree::InstanceHierarchy;
+use library::{symbol_key, UnitId};
+use name_resolution::entity_interface;
+use project::SourceFile;
+use source::SrcPos;
+use std::collections::BTreeMap;
+use std::fmt;
+use symbol_table::Symbol;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum InterfaceKind {
+    Generic,
+    Port,
+}
+
+impl fmt::Display for InterfaceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterfaceKind::Generic => write!(f, "generic"),
+            InterfaceKind::Port => write!(f, "port"),
+        }
+    }
+}
+
+/// An association of an instance which does not match the interface of the entity
+#[derive(PartialEq, Debug, Clone)]
+pub enum Inconsistency {
+    /// A generic, or a port of mode in, without default value which is not associated
+    Unassociated(InterfaceKind, String),
+    /// A formal which is not a generic or port of the entity
+    UnknownFormal(InterfaceKind, String, SrcPos),
+    /// A positional association after the last generic or port
+    ExtraPositional(InterfaceKind, SrcPos),
+}
+
+impl Inconsistency {
+    /// The position of the association, None when the generic or port is not associated
+    pub fn pos(&self) -> Option<&SrcPos> {
+        match self {
+            Inconsistency::Unassociated(..) => None,
+            Inconsistency::UnknownFormal(_, _, ref pos)
+            | Inconsistency::ExtraPositional(_, ref pos) => Some(pos),
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inconsistency::Unassociated(kind, name) => {
+                write!(f, "{} '{}' is not associated", kind, name)
+            }
+            Inconsistency::UnknownFormal(kind, name, _) => {
+                write!(f, "'{}' is not a {} of the entity", name, kind)
+            }
+            Inconsistency::ExtraPositional(kind, _) => {
+                write!(f, "positional {} association after the last {}", kind, kind)
+            }
+        }
+    }
+}
+
+/// An instance of an entity and its inconsistent associations
+#[derive(PartialEq, Debug, Clone)]
+pub struct InstanceConsistency {
+    /// The architecture containing the instance
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    pub pos: SrcPos,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+/// All instances of an entity within the project
+#[derive(PartialEq, Debug, Clone)]
+pub struct EntityInstances {
+    pub entity: UnitId,
+    pub instances: Vec<InstanceConsistency>,
+}
+
+impl EntityInstances {
+    pub fn is_consistent(&self) -> bool {
+        self.instances
+            .iter()
+            .all(|instance| instance.inconsistencies.is_empty())
+    }
+}
+
+/// A generic or port by which it is associated
+struct Interface {
+    key: String,
+    name: String,
+    /// Whether an actual must be associated since there is no default value
+    required: bool,
+}
+
+fn interface(kind: InterfaceKind, declaration: &InterfaceDeclaration) -> Interface {
+    let (symbol, required) = match declaration {
+        InterfaceDeclaration::Object(ref object) => (
+            &object.ident.item,
+            object.expression.is_none()
+                && (kind == InterfaceKind::Generic || object.mode == Mode::In),
+        ),
+        InterfaceDeclaration::File(ref file) => (&file.ident.item, true),
+        InterfaceDeclaration::Type(ref ident) => (&ident.item, true),
+        InterfaceDeclaration::Subprogram(ref declaration, ref default) => {
+            let designator = match declaration {
+                SubprogramDeclaration::Procedure(ref spec) => &spec.designator.item,
+                SubprogramDeclaration::Function(ref spec) => &spec.designator.item,
+            };
+            let name = match designator {
+                Designator::Identifier(ref symbol) => symbol.name_utf8(),
+                Designator::OperatorSymbol(ref operator) => format!("\"{}\"", operator),
+            };
+            return Interface {
+                key: name.to_lowercase(),
+                name,
+                required: default.is_none(),
+            };
+        }
+    };
+    Interface {
+        key: symbol_key(symbol),
+        name: symbol.name_utf8(),
+        required,
+    }
+}
+
+/// The generic or port a formal designator denotes, a formal may also be a subelement
+/// such as data(0) or a conversion such as to_integer(count)
+fn formal_symbol<'n>(name: &'n Name, interfaces: &[Interface]) -> Option<&'n Symbol> {
+    match name {
+        Name::Simple(ref symbol) => Some(symbol),
+        Name::Selected(ref prefix, _)
+        | Name::Indexed(ref prefix, _)
+        | Name::Slice(ref prefix, _) => formal_symbol(&prefix.item, interfaces),
+        Name::FunctionCall(ref call) => {
+            let symbol = formal_symbol(&call.name.item, interfaces);
+            let is_interface = |symbol: &Symbol| {
+                interfaces
+                    .iter()
+                    .any(|interface| interface.key == symbol_key(symbol))
+            };
+            if symbol.is_some_and(is_interface) {
+                return symbol;
+            }
+            match call.parameters.as_slice() {
+                [AssociationElement {
+                    formal: None,
+                    actual,
+                }] => match actual.item {
+                    ActualPart::Expression(Expression::Name(ref name)) => {
+                        formal_symbol(name, interfaces).filter(|symbol| is_interface(symbol))
+                    }
+                    _ => symbol,
+                },
+                _ => symbol,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The associations which do not match the generics or ports
+fn check_associations(
+    kind: InterfaceKind,
+    declarations: &[InterfaceDeclaration],
+    associations: &[AssociationElement],
+    inconsistencies: &mut Vec<Inconsistency>,
+) {
+    let interfaces: Vec<Interface> = declarations
+        .iter()
+        .map(|declaration| interface(kind, declaration))
+        .collect();
+    let mut associated = vec![false; interfaces.len()];
+    for (i, association) in associations.iter().enumerate() {
+        let formal = match association.formal {
+            Some(ref formal) => formal,
+            None => {
+                match associated.get_mut(i) {
+                    Some(associated) => *associated = true,
+                    None => inconsistencies.push(Inconsistency::ExtraPositional(
+                        kind,
+                        association.actual.pos.clone(),
+                    )),
+                }
+                continue;
+            }
+        };
+        let symbol = match formal_symbol(&formal.item, &interfaces) {
+            Some(symbol) => symbol,
+            None => continue,
+        };
+        match interfaces
+            .iter()
+            .position(|interface| interface.key == symbol_key(symbol))
+        {
+            Some(j) => associated[j] = true,
+            None => inconsistencies.push(Inconsistency::UnknownFormal(
+                kind,
+                symbol.name_utf8(),
+                formal.pos.clone(),
+            )),
+        }
+    }
+    for (interface, associated) in interfaces.iter().zip(associated) {
+        if interface.required && !associated {
+            inconsistencies.push(Inconsistency::Unassociated(kind, interface.name.clone()));
+        }
+    }
+}
+
+/// The instances of each entity within the project which is instantiated, sorted by entity
+pub fn interface_consistency(files: &[&SourceFile]) -> Vec<EntityInstances> {
+    let hierarchy = InstanceHierarchy::new(files);
+    let mut instances: BTreeMap<UnitId, Vec<InstanceConsistency>> = BTreeMap::new();
+    for instantiation in hierarchy.instantiations() {
+        let (generics, ports) = match hierarchy.entity(&instantiation.entity) {
+            Some(library_unit) => entity_interface(library_unit),
+            None => continue,
+        };
+        let mut inconsistencies = Vec::new();
+        check_associations(
+            InterfaceKind::Generic,
+            generics,
+            &instantiation.instance.generic_map,
+            &mut inconsistencies,
+        );
+        check_associations(
+            InterfaceKind::Port,
+            ports,
+            &instantiation.instance.port_map,
+            &mut inconsistencies,
+        );
+        instances
+            .entry(instantiation.entity)
+            .or_default()
+            .push(InstanceConsistency {
+                architecture: instantiation.architecture,
+                label: instantiation.label,
+                pos: instantiation.pos,
+                inconsistencies,
+            });
+    }
+    instances
+        .into_iter()
+        .map(|(entity, instances)| EntityInstances { entity, instances })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn inconsistent_associations_of_instances() {
+        let mut project = TempProject::new();
+        let code = "
+entity top is
+end entity;
+
+architecture rtl of top is
+  signal clk, rst, data : bit;
+  signal count : natural;
+begin
+  good : entity work.counter
+    generic map (width => 8)
+    port map (clk => clk, rst => rst, count => count, data(0) => data);
+
+  gen : if true generate
+    stale : entity work.counter
+      generic map (width => 8, depth => 4)
+      port map (clk => clk, count => count);
+  end generate;
+
+  positional : entity work.counter
+    generic map (8, 4)
+    port map (clk, rst, count, open, open);
+
+  converted : entity work.counter
+    generic map (width => 8)
+    port map (clk => clk, rst => rst, to_integer(count) => open);
+end architecture;
+
+entity counter is
+  generic (width : natural; enable : boolean := true);
+  port (clk, rst : in bit; count : out natural; data : out bit_vector(0 to 1));
+end entity;";
+        project.add_file("lib", "top.vhd", code);
+
+        let files = project.project.files();
+        let consistency = interface_consistency(&files);
+        assert_eq!(consistency.len(), 1);
+        let counter = &consistency[0];
+        assert_eq!(counter.entity, UnitId::primary("lib", "counter"));
+        assert!(!counter.is_consistent());
+
+        let instances: Vec<(String, Vec<String>)> = counter
+            .instances
+            .iter()
+            .map(|instance| {
+                (
+                    instance.label.clone(),
+                    instance
+                        .inconsistencies
+                        .iter()
+                        .map(|inconsistency| inconsistency.to_string())
+                        .collect(),
+                )
+            })
+            .collect();
+        let strings = |strings: &[&str]| -> Vec<String> {
+            strings.iter().map(|string| (*string).to_owned()).collect()
+        };
+        assert_eq!(
+            instances,
+            vec![
+                ("good".to_owned(), vec![]),
+                (
+                    "gen.stale".to_owned(),
+                    strings(&[
+                        "'depth' is not a generic of the entity",
+                        "port 'rst' is not associated",
+                    ])
+                ),
+                (
+                    "positional".to_owned(),
+                    strings(&["positional port association after the last port"])
+                ),
+                ("converted".to_owned(), vec![]),
+            ]
+        );
+        assert_eq!(
+            counter.instances[0].architecture,
+            UnitId::architecture("lib", "top", "rtl")
+        );
+        let stale = &counter.instances[1];
+        assert_eq!(
+            stale.inconsistencies[0].pos().map(|pos| pos.start),
+            code.find("depth => 4")
+        );
+        assert_eq!(stale.inconsistencies[1].pos(), None);
+    }
+}
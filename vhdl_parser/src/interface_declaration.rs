@@ -13,6 +13,7 @@ use ast::{
 use message::{error, push_result, Message, MessageHandler, ParseResult};
 use names::{parse_identifier_list, parse_selected_name};
 use object_declaration::{parse_file_declaration_no_semi, parse_optional_assignment};
+use std::sync::Arc;
 use subprogram::parse_subprogram_declaration_no_semi;
 use subtype_indication::parse_subtype_indication;
 use tokenizer::Kind::*;
@@ -114,8 +115,8 @@ fn parse_interface_object_declaration(
         (InterfaceListType::Parameter, None, _) => ObjectClass::Variable,
     };
 
-    let subtype = parse_subtype_indication(stream)?;
-    let expr = parse_optional_assignment(stream)?;
+    let subtype = Arc::new(parse_subtype_indication(stream)?);
+    let expr = parse_optional_assignment(stream)?.map(Arc::new);
 
     // @TODO maybe move this to a semantic check?
     for ident in idents.iter() {
@@ -368,14 +369,14 @@ mod tests {
                     mode: Mode::In,
                     class: ObjectClass::Constant,
                     ident: util.ident("foo"),
-                    subtype_indication: util.subtype_indication("natural"),
+                    subtype_indication: Arc::new(util.subtype_indication("natural")),
                     expression: None
                 }),
                 InterfaceDeclaration::Object(InterfaceObjectDeclaration {
                     mode: Mode::In,
                     class: ObjectClass::Constant,
                     ident: util.ident("bar"),
-                    subtype_indication: util.subtype_indication("natural"),
+                    subtype_indication: Arc::new(util.subtype_indication("natural")),
                     expression: None
                 })
             ]
@@ -391,7 +392,7 @@ mod tests {
                 mode: Mode::In,
                 class: ObjectClass::Constant,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("std_logic"),
+                subtype_indication: Arc::new(util.subtype_indication("std_logic")),
                 expression: None
             })
         );
@@ -404,7 +405,7 @@ mod tests {
             result,
             InterfaceDeclaration::File(InterfaceFileDeclaration {
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("text"),
+                subtype_indication: Arc::new(util.subtype_indication("text")),
             })
         );
     }
@@ -443,7 +444,7 @@ mod tests {
                 mode: Mode::In,
                 class: ObjectClass::Signal,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("std_logic"),
+                subtype_indication: Arc::new(util.subtype_indication("std_logic")),
                 expression: None
             })
         );
@@ -511,7 +512,7 @@ mod tests {
 
                 class: ObjectClass::Constant,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("std_logic"),
+                subtype_indication: Arc::new(util.subtype_indication("std_logic")),
                 expression: None
             })
         );
@@ -527,7 +528,7 @@ mod tests {
 
                 class: ObjectClass::Signal,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("std_logic"),
+                subtype_indication: Arc::new(util.subtype_indication("std_logic")),
                 expression: None
             })
         );
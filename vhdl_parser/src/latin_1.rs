@@ -4,6 +4,8 @@
 //
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::str;
 
@@ -123,9 +125,34 @@ impl fmt::Display for Latin1String {
     }
 }
 
+/// Human readable formats such as JSON get a string, other formats get the bytes
+impl Serialize for Latin1String {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            self.bytes.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Latin1String {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Latin1String, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            Latin1String::from_utf8(&string).map_err(D::Error::custom)
+        } else {
+            Vec::deserialize(deserializer).map(Latin1String::from_vec)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    extern crate serde_json;
+
     use super::*;
+    use bincode;
 
     #[test]
     fn test_latin1_to_utf8() {
@@ -146,4 +173,20 @@ mod tests {
             assert_eq!(latin1.to_lowercase().to_string(), utf8.to_lowercase());
         }
     }
+
+    #[test]
+    fn test_latin1_serialization() {
+        let latin1 = Latin1String::new(&[b'a', 0xe5]);
+        let json = serde_json::to_string(&latin1).unwrap();
+        assert_eq!(json, "\"aå\"");
+        assert_eq!(serde_json::from_str::<Latin1String>(&json).unwrap(), latin1);
+
+        // The same encoding as the bytes themselves
+        let bytes = bincode::serialize(&latin1).unwrap();
+        assert_eq!(bytes, bincode::serialize(&latin1.bytes).unwrap());
+        assert_eq!(
+            bincode::deserialize::<Latin1String>(&bytes).unwrap(),
+            latin1
+        );
+    }
 }
@@ -4,46 +4,131 @@
 //
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
+// Parse results carry the whole message with its related information and fixes
+#![allow(clippy::result_large_err)]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate bincode;
+extern crate serde;
+#[macro_use]
+extern crate smallvec;
+
+pub mod analysis;
+pub mod analysis_server;
 pub mod ast;
+pub mod benchmark;
+pub mod blackbox;
+pub mod cache;
+pub mod call_hierarchy;
+pub mod clock_reset;
+pub mod combinational_loop;
 #[macro_use]
 mod tokenizer;
 mod alias_declaration;
 mod attributes;
 mod common;
+pub mod compile_script;
+pub mod compile_state;
 mod component_declaration;
 mod concurrent_statement;
 mod configuration;
+pub mod conditional_analysis;
+pub mod constant_condition;
+pub mod config;
+pub mod conversion;
+pub mod coverage;
 mod context;
+pub mod dead_code;
 mod declarative_part;
+pub mod dependency_graph;
 mod design_unit;
+pub mod docgen;
+pub mod duplicate_units;
+pub mod documentation;
+pub mod elaborated_design;
+pub mod ffi;
 mod expression;
+pub mod external_tool;
+pub mod forbidden_construct;
+pub mod format_config;
+pub mod formatter;
+pub mod fsm_states;
+pub mod fusesoc;
+pub mod header;
+pub mod import;
+pub mod incremental;
+pub mod inlay_hints;
+pub mod instance_tree;
+pub mod interface_consistency;
 mod interface_declaration;
 mod latin_1;
+pub mod library;
+pub mod lint;
+pub mod lsif;
+pub mod memory;
 pub mod message;
+pub mod mixed_language;
+pub mod name_resolution;
+pub mod naming_convention;
 mod names;
+pub mod netlist;
 mod object_declaration;
+pub mod outline;
+pub mod process_sanity;
+pub mod process_structure;
+pub mod project;
+pub mod quartus;
+mod quick_fix;
 mod range;
+pub mod rename;
 mod sequential_statement;
+pub mod signature;
+pub mod simulation;
+pub mod simulation_value;
+pub mod standard;
+pub mod state_machine;
+pub mod static_value;
+pub mod statistics;
 pub mod source;
+mod source_tokens;
 mod subprogram;
 mod subtype_indication;
+pub mod symbol_database;
 mod symbol_table;
 mod tokenstream;
+pub mod top_interface;
 mod type_declaration;
+pub mod unreachable_code;
+pub mod vcd;
+pub mod verilog_parser;
+pub mod verilog_tokenizer;
+pub mod vivado;
+pub mod vunit;
+pub mod waiver;
+pub mod wasm;
 mod waveform;
+pub mod watcher;
+pub mod write_only;
+mod yaml;
+pub mod yosys_json;
 
 #[cfg(test)]
 mod test_util;
 
 use ast::DesignFile;
-use design_unit::parse_design_file;
-use message::{Message, MessageHandler};
+use design_unit::{parse_deferred_body, parse_design_file};
+use latin_1::Latin1String;
+use message::{error, push_result, Message, MessageHandler};
 use source::Source;
+use standard::VHDLStandard;
+use std::collections::BTreeMap;
 use std::convert::From;
 use std::io;
+use std::mem::take;
 use std::sync::Arc;
 use symbol_table::SymbolTable;
-use tokenizer::Tokenizer;
+use tokenizer::{TokenState, Tokenizer};
 use tokenstream::TokenStream;
 
 pub enum ParserError {
@@ -56,6 +141,17 @@ pub struct VHDLParser {
 
 pub type ParserResult = Result<DesignFile, ParserError>;
 
+/// Options which affect the result of parsing a design file
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub standard: VHDLStandard,
+    /// User defined conditional analysis identifiers, in addition to the predefined ones
+    pub defines: BTreeMap<String, String>,
+    /// Only parse the interface of design units, the contents of architectures
+    /// and package bodies are skipped and recorded as deferred bodies
+    pub interface_only: bool,
+}
+
 impl From<io::Error> for ParserError {
     fn from(err: io::Error) -> ParserError {
         ParserError::IOError(err)
@@ -78,12 +174,104 @@ impl VHDLParser {
     pub fn parse_design_source(
         &self,
         source: &Source,
-        messages: &mut MessageHandler,
+        messages: &mut dyn MessageHandler,
     ) -> ParserResult {
-        let code = source.contents()?;
-        let tokenizer = Tokenizer::new(self.symtab.clone(), source.clone(), code);
-        let mut stream = TokenStream::new(tokenizer);
-        Ok(parse_design_file(&mut stream, messages)?)
+        self.parse_design_source_with_options(source, &ParseOptions::default(), messages)
+    }
+
+    /// Parse only the interface of the design units, the contents of
+    /// architectures and package bodies are skipped
+    pub fn parse_design_source_interface(
+        &self,
+        source: &Source,
+        messages: &mut dyn MessageHandler,
+    ) -> ParserResult {
+        let options = ParseOptions {
+            interface_only: true,
+            ..ParseOptions::default()
+        };
+        self.parse_design_source_with_options(source, &options, messages)
+    }
+
+    /// Parse using the keywords of the language standard after applying conditional analysis directives
+    pub fn parse_design_source_with_options(
+        &self,
+        source: &Source,
+        options: &ParseOptions,
+        messages: &mut dyn MessageHandler,
+    ) -> ParserResult {
+        let mut stream = self.token_stream(source, options, messages)?;
+        Ok(parse_design_file(
+            &mut stream,
+            messages,
+            options.interface_only,
+        )?)
+    }
+
+    /// Parse the contents of the architectures and package bodies which were skipped when
+    /// parsing only the interface of the design file, using the same options and contents
+    pub fn parse_deferred_bodies(
+        &self,
+        source: &Source,
+        options: &ParseOptions,
+        design_file: &mut DesignFile,
+        messages: &mut dyn MessageHandler,
+    ) -> io::Result<()> {
+        // Conditional analysis messages were already reported when parsing the interface
+        let mut stream = self.token_stream(source, options, &mut Vec::new())?;
+        for body in take(&mut design_file.deferred_bodies) {
+            if let Some(design_unit) = design_file.design_units.get_mut(body.design_unit) {
+                stream.set_state(TokenState::at(body.pos.start));
+                let result =
+                    parse_deferred_body(&mut stream, messages, &mut design_unit.library_unit);
+                push_result(messages, result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse bytes of any contents where every failure is a message instead of a panic or
+    /// a stack overflow of the host process, the bytes are decoded as latin-1 and the
+    /// design file is None when the parser could not finish
+    pub fn parse_str_resilient(&self, bytes: &[u8]) -> (Option<DesignFile>, Vec<Message>) {
+        let source = Source::from_latin1(Arc::new(Latin1String::from_vec(bytes.to_vec())));
+        let mut messages = Vec::new();
+        let design_file = match self.parse_design_source(&source, &mut messages) {
+            Ok(design_file) => Some(design_file),
+            Err(ParserError::Message(msg)) => {
+                messages.push(msg);
+                None
+            }
+            Err(ParserError::IOError(err)) => {
+                messages.push(error(source.pos(0, 0), &err.to_string()));
+                None
+            }
+        };
+        (design_file, messages)
+    }
+
+    /// Tokens of the contents after applying conditional analysis directives
+    fn token_stream(
+        &self,
+        source: &Source,
+        options: &ParseOptions,
+        messages: &mut dyn MessageHandler,
+    ) -> io::Result<TokenStream> {
+        let mut code = source.contents()?;
+
+        let mut defines = conditional_analysis::predefined(options.standard);
+        for (name, value) in options.defines.iter() {
+            defines.insert(name.to_lowercase(), value.clone());
+        }
+        if let Some(preprocessed) =
+            conditional_analysis::preprocess(source, &code, &defines, messages)
+        {
+            code = Arc::new(preprocessed);
+        }
+
+        let tokenizer =
+            Tokenizer::with_standard(self.symtab.clone(), source.clone(), code, options.standard);
+        Ok(TokenStream::new(tokenizer))
     }
 
     pub fn parse_design_file(
@@ -95,3 +283,140 @@ impl VHDLParser {
         Ok(self.parse_design_source(&source, messages)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn parse_str_resilient_without_errors() {
+        let (design_file, messages) = VHDLParser::new().parse_str_resilient(
+            b"
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  x <= f(a) + (b * c);
+end architecture;",
+        );
+        assert_eq!(messages, vec![]);
+        assert_eq!(design_file.unwrap().design_units.len(), 2);
+    }
+
+    #[test]
+    fn parse_str_resilient_decodes_latin1() {
+        let (design_file, messages) =
+            VHDLParser::new().parse_str_resilient(b"package pkg is\n-- \xe4\xff\nend package;");
+        assert_eq!(messages, vec![]);
+        assert_eq!(design_file.unwrap().design_units.len(), 1);
+
+        let (design_file, messages) = VHDLParser::new().parse_str_resilient(b"package \xff is");
+        assert_eq!(messages.len(), 1);
+        assert!(design_file.is_none());
+    }
+
+    #[test]
+    fn parse_str_resilient_limits_nesting() {
+        let depth = 10 * tokenstream::MAX_NESTING_DEPTH;
+        let codes = [
+            format!(
+                "package pkg is constant c : t := {}1{}; end package;",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            ),
+            format!(
+                "package pkg is constant c : t := {}1{}; end package;",
+                "f(".repeat(depth),
+                ")".repeat(depth)
+            ),
+            format!(
+                "package pkg is constant c : t := {}1; end package;",
+                "- ".repeat(depth)
+            ),
+            format!(
+                "package pkg is constant c : t := a{}; end package;",
+                ".b".repeat(depth)
+            ),
+            format!(
+                "package body pkg is {}{} end package body;",
+                "procedure p is ".repeat(depth),
+                "begin end procedure;".repeat(depth)
+            ),
+            format!(
+                "architecture rtl of ent is begin {}{} end architecture;",
+                "blk: block begin ".repeat(depth),
+                "end block;".repeat(depth)
+            ),
+            format!(
+                "architecture rtl of ent is begin process begin {}{} end process; end architecture;",
+                "if a then ".repeat(depth),
+                "end if;".repeat(depth)
+            ),
+            format!(
+                "configuration cfg of ent is for rtl {}{} end for; end configuration;",
+                "for blk ".repeat(depth),
+                "end for;".repeat(depth)
+            ),
+        ];
+        // The limit is for the 8 MB stack of a main thread when not optimized, test threads
+        // only have 2 MB
+        let parse = thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                codes
+                    .iter()
+                    .map(|code| VHDLParser::new().parse_str_resilient(code.as_bytes()).1)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        for messages in parse.join().unwrap() {
+            assert_eq!(
+                messages[0].message,
+                format!(
+                    "Nesting is deeper than {} levels",
+                    tokenstream::MAX_NESTING_DEPTH
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn parse_str_resilient_of_nesting_within_limit() {
+        let depth = tokenstream::MAX_NESTING_DEPTH / 4;
+        let code = format!(
+            "package pkg is constant c : t := {}1{}; end package;",
+            "(".repeat(depth),
+            ")".repeat(depth)
+        );
+        let (design_file, messages) = VHDLParser::new().parse_str_resilient(code.as_bytes());
+        assert_eq!(messages, vec![]);
+        assert!(design_file.is_some());
+    }
+
+    #[test]
+    fn parse_str_resilient_of_long_aggregate() {
+        let code = format!(
+            "package pkg is constant c : t := ({}1); end package;",
+            "1, ".repeat(100_000)
+        );
+        let (design_file, messages) = VHDLParser::new().parse_str_resilient(code.as_bytes());
+        assert_eq!(messages, vec![]);
+        assert!(design_file.is_some());
+    }
+
+    #[test]
+    fn parse_str_resilient_of_long_operator_chains() {
+        let operands: Vec<String> = (0..500).map(|i| format!("x{}", i)).collect();
+        let code = format!(
+            "package pkg is constant c : boolean := {}; end package;\n\
+             architecture rtl of ent is begin y <= {}; end architecture;",
+            operands.join(" or "),
+            operands.join(" & ")
+        );
+        let (design_file, messages) = VHDLParser::new().parse_str_resilient(code.as_bytes());
+        assert_eq!(messages, vec![]);
+        assert!(design_file.is_some());
+    }
+}
@@ -0,0 +1,462 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use ast::{DesignUnit, Ident, LibraryUnit, Name};
+use message::{error, Message};
+use project::SourceFile;
+use source::SrcPos;
+use std::collections::BTreeMap;
+use std::fmt;
+use symbol_table::Symbol;
+
+/// Case insensitive key of a symbol
+pub fn symbol_key(symbol: &Symbol) -> String {
+    symbol.name().to_lowercase().to_string()
+}
+
+/// The key of a design unit within its library
+/// LRM 13.1 Primary units share a single namespace within a library
+/// while secondary units belong to their primary unit
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum UnitKey {
+    Primary(String),
+    Architecture(String, String),
+    PackageBody(String),
+}
+
+/// Unique identifier of a design unit within a project
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub struct UnitId {
+    pub library_name: String,
+    pub key: UnitKey,
+}
+
+impl UnitId {
+    pub fn primary(library_name: &str, name: &str) -> UnitId {
+        UnitId {
+            library_name: library_name.to_lowercase(),
+            key: UnitKey::Primary(name.to_lowercase()),
+        }
+    }
+
+    pub fn architecture(library_name: &str, entity_name: &str, name: &str) -> UnitId {
+        UnitId {
+            library_name: library_name.to_lowercase(),
+            key: UnitKey::Architecture(entity_name.to_lowercase(), name.to_lowercase()),
+        }
+    }
+
+    pub fn package_body(library_name: &str, name: &str) -> UnitId {
+        UnitId {
+            library_name: library_name.to_lowercase(),
+            key: UnitKey::PackageBody(name.to_lowercase()),
+        }
+    }
+
+    /// The identifier of the design unit within the library
+    pub fn new(library_name: &str, design_unit: &DesignUnit) -> UnitId {
+        let key = match design_unit.library_unit {
+            LibraryUnit::ArchitectureBody {
+                ref ident,
+                ref entity_name,
+                ..
+            } => UnitKey::Architecture(symbol_key(&entity_name.item), symbol_key(&ident.item)),
+            LibraryUnit::PackageBody { ref ident, .. } => {
+                UnitKey::PackageBody(symbol_key(&ident.item))
+            }
+            ref library_unit => UnitKey::Primary(symbol_key(&unit_ident(library_unit).item)),
+        };
+        UnitId {
+            library_name: library_name.to_lowercase(),
+            key,
+        }
+    }
+
+    /// The name of the primary unit, or the primary unit a secondary unit belongs to
+    pub fn primary_name(&self) -> &str {
+        match self.key {
+            UnitKey::Primary(ref name)
+            | UnitKey::Architecture(ref name, _)
+            | UnitKey::PackageBody(ref name) => name,
+        }
+    }
+
+    /// The primary unit identifier, returns itself for primary units
+    pub fn primary_unit(&self) -> UnitId {
+        UnitId::primary(&self.library_name, self.primary_name())
+    }
+
+    pub fn is_primary(&self) -> bool {
+        matches!(self.key, UnitKey::Primary(..))
+    }
+}
+
+impl fmt::Display for UnitKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnitKey::Primary(ref name) => write!(f, "{}", name),
+            UnitKey::Architecture(ref entity_name, ref name) => {
+                write!(f, "{}({})", entity_name, name)
+            }
+            UnitKey::PackageBody(ref name) => write!(f, "{} body", name),
+        }
+    }
+}
+
+impl fmt::Display for UnitId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.library_name, self.key)
+    }
+}
+
+/// The identifier which declares the library unit
+pub fn unit_ident(library_unit: &LibraryUnit) -> &Ident {
+    match library_unit {
+        LibraryUnit::EntityDeclaration { ref ident, .. }
+        | LibraryUnit::ArchitectureBody { ref ident, .. }
+        | LibraryUnit::PackageDeclaration { ref ident, .. }
+        | LibraryUnit::PackageBody { ref ident, .. } => ident,
+        LibraryUnit::Configuration(ref config) => &config.ident,
+        LibraryUnit::PackageInstance(ref inst) => &inst.ident,
+        LibraryUnit::ContextDeclaration(ref context) => &context.ident,
+    }
+}
+
+/// Flatten a selected name such as lib.pkg.all into its simple name parts
+/// Returns None if any part is not a simple name, a trailing .all is ignored
+pub fn selected_name_parts(name: &Name) -> Option<Vec<&Symbol>> {
+    match name {
+        Name::Simple(ref symbol) => Some(vec![symbol]),
+        Name::Selected(ref prefix, ref suffix) => {
+            let mut parts = selected_name_parts(&prefix.item)?;
+            match suffix.item {
+                Name::Simple(ref symbol) => parts.push(symbol),
+                Name::All => {}
+                _ => return None,
+            }
+            Some(parts)
+        }
+        _ => None,
+    }
+}
+
+/// A design unit declared within a library
+pub struct LibraryUnitRef<'a> {
+    pub id: UnitId,
+    pub file_name: &'a str,
+    pub unit: &'a DesignUnit,
+}
+
+impl<'a> LibraryUnitRef<'a> {
+    pub fn ident(&self) -> &'a Ident {
+        unit_ident(&self.unit.library_unit)
+    }
+
+    pub fn pos(&self) -> &'a SrcPos {
+        &self.ident().pos
+    }
+}
+
+/// LRM 13.2 A design library
+/// Primary units of different libraries never collide since each library is its own namespace
+pub struct Library<'a> {
+    name: String,
+    units: BTreeMap<UnitKey, LibraryUnitRef<'a>>,
+}
+
+impl<'a> Library<'a> {
+    fn new(name: &str) -> Library<'a> {
+        Library {
+            name: name.to_owned(),
+            units: BTreeMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add the design unit, returns an error if a unit with the same key already exists
+    fn add_unit(&mut self, file_name: &'a str, unit: &'a DesignUnit) -> Result<(), Message> {
+        let id = UnitId::new(&self.name, unit);
+        if let Some(existing) = self.units.get(&id.key) {
+            let ident = unit_ident(&unit.library_unit);
+            let (lineno, _) = existing.pos().lineno_and_pretty_string();
+            let description = match id.key {
+                UnitKey::Primary(ref name) => format!(
+                    "A primary unit has already been declared with name '{}' in library '{}'",
+                    name, self.name
+                ),
+                UnitKey::Architecture(ref entity_name, ref name) => format!(
+                    "Duplicate architecture '{}' of entity '{}' in library '{}'",
+                    name, entity_name, self.name
+                ),
+                UnitKey::PackageBody(ref name) => format!(
+                    "Duplicate package body of package '{}' in library '{}'",
+                    name, self.name
+                ),
+            };
+            return Err(error(
+                &ident.pos,
+                &format!(
+                    "{}, previously declared at {}:{}",
+                    description, existing.file_name, lineno
+                ),
+            )
+            .with_code("duplicate-unit")
+            .related(existing.pos(), "Previously declared here"));
+        }
+
+        self.units.insert(
+            id.key.clone(),
+            LibraryUnitRef {
+                id,
+                file_name,
+                unit,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, key: &UnitKey) -> Option<&LibraryUnitRef<'a>> {
+        self.units.get(key)
+    }
+
+    pub fn get_primary(&self, name: &str) -> Option<&LibraryUnitRef<'a>> {
+        self.units.get(&UnitKey::Primary(name.to_lowercase()))
+    }
+
+    /// All units sorted by key
+    pub fn units(&self) -> impl Iterator<Item = &LibraryUnitRef<'a>> {
+        self.units.values()
+    }
+
+    /// Check that each secondary unit belongs to a primary unit of the right kind
+    fn check_secondary_units(&self, messages: &mut Vec<Message>) {
+        for unit in self.units.values() {
+            let (expected, kind) = match unit.id.key {
+                UnitKey::Architecture(..) => (
+                    matches!(
+                        self.get(&unit.id.primary_unit().key)
+                            .map(|primary| &primary.unit.library_unit),
+                        Some(LibraryUnit::EntityDeclaration { .. })
+                    ),
+                    "entity",
+                ),
+                UnitKey::PackageBody(..) => (
+                    matches!(
+                        self.get(&unit.id.primary_unit().key)
+                            .map(|primary| &primary.unit.library_unit),
+                        Some(LibraryUnit::PackageDeclaration { .. })
+                    ),
+                    "package",
+                ),
+                UnitKey::Primary(..) => continue,
+            };
+
+            if !expected {
+                messages.push(
+                    error(
+                        unit.pos(),
+                        &format!(
+                            "No {} '{}' within library '{}'",
+                            kind,
+                            unit.id.primary_name(),
+                            self.name
+                        ),
+                    )
+                    .with_code("missing-primary-unit"),
+                );
+            }
+        }
+    }
+}
+
+/// All libraries of a project
+pub struct DesignRoot<'a> {
+    libraries: BTreeMap<String, Library<'a>>,
+    messages: Vec<Message>,
+}
+
+impl<'a> DesignRoot<'a> {
+    /// Add the design units of all files to their libraries
+    /// When a unit is declared more than once the first file in order is kept
+    pub fn new(files: &[&'a SourceFile]) -> DesignRoot<'a> {
+        let mut libraries = BTreeMap::new();
+        let mut messages = Vec::new();
+
+        for file in files {
+            let library_name = file.library_name.to_lowercase();
+            let library = libraries
+                .entry(library_name.clone())
+                .or_insert_with(|| Library::new(&library_name));
+            for unit in file.design_file.design_units.iter() {
+                if let Err(msg) = library.add_unit(file.file_name(), unit) {
+                    messages.push(msg);
+                }
+            }
+        }
+
+        for library in libraries.values() {
+            library.check_secondary_units(&mut messages);
+        }
+
+        DesignRoot {
+            libraries,
+            messages,
+        }
+    }
+
+    pub fn library(&self, name: &str) -> Option<&Library<'a>> {
+        self.libraries.get(&name.to_lowercase())
+    }
+
+    /// All libraries sorted by name
+    pub fn libraries(&self) -> impl Iterator<Item = &Library<'a>> {
+        self.libraries.values()
+    }
+
+    pub fn get(&self, unit_id: &UnitId) -> Option<&LibraryUnitRef<'a>> {
+        self.library(&unit_id.library_name)
+            .and_then(|library| library.get(&unit_id.key))
+    }
+
+    /// Messages about duplicate or misplaced design units
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use names::parse_name;
+    use test_util::{with_stream, TempProject};
+
+    fn parts(code: &str) -> Option<Vec<String>> {
+        let (_, name) = with_stream(parse_name, code);
+        selected_name_parts(&name.item)
+            .map(|parts| parts.iter().map(|sym| symbol_key(sym)).collect())
+    }
+
+    #[test]
+    fn selected_name_parts_of_use_clause_name() {
+        assert_eq!(
+            parts("Lib.Pkg.all"),
+            Some(vec!["lib".to_owned(), "pkg".to_owned()])
+        );
+        assert_eq!(
+            parts("lib.pkg.foo"),
+            Some(vec!["lib".to_owned(), "pkg".to_owned(), "foo".to_owned()])
+        );
+        assert_eq!(parts("foo(0)"), None);
+    }
+
+    #[test]
+    fn unit_id_display() {
+        assert_eq!(UnitId::primary("Lib", "Ent").to_string(), "lib.ent");
+        assert_eq!(
+            UnitId::architecture("lib", "ent", "RTL").to_string(),
+            "lib.ent(rtl)"
+        );
+        assert_eq!(
+            UnitId::package_body("lib", "pkg").to_string(),
+            "lib.pkg body"
+        );
+    }
+
+    #[test]
+    fn primary_unit_of_secondary_unit() {
+        assert_eq!(
+            UnitId::architecture("lib", "ent", "rtl").primary_unit(),
+            UnitId::primary("lib", "ent")
+        );
+        assert_eq!(
+            UnitId::package_body("lib", "pkg").primary_unit(),
+            UnitId::primary("lib", "pkg")
+        );
+        assert!(!UnitId::package_body("lib", "pkg").is_primary());
+    }
+
+    #[test]
+    fn same_unit_name_in_different_libraries() {
+        let mut project = TempProject::new();
+        project.add_file("lib1", "ent1.vhd", "entity ent is end entity;");
+        project.add_file("lib2", "ent2.vhd", "entity ent is end entity;");
+
+        let files = project.project.files();
+        let root = DesignRoot::new(&files);
+        assert_eq!(root.messages(), &[]);
+        assert!(root.get(&UnitId::primary("lib1", "ent")).is_some());
+        assert!(root.get(&UnitId::primary("lib2", "ent")).is_some());
+        let names: Vec<&str> = root.libraries().map(|library| library.name()).collect();
+        assert_eq!(names, vec!["lib1", "lib2"]);
+    }
+
+    #[test]
+    fn duplicate_units_within_library() {
+        let mut project = TempProject::new();
+        let first_file_name = project.add_file(
+            "lib",
+            "a.vhd",
+            "
+entity ent is end entity;
+architecture rtl of ent is begin end architecture;",
+        );
+        let file_name = project.add_file(
+            "Lib",
+            "b.vhd",
+            "
+package ent is end package;
+architecture rtl of ent is begin end architecture;",
+        );
+
+        let files = project.project.files();
+        let root = DesignRoot::new(&files);
+        let messages = root.messages();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].message.starts_with(
+            "A primary unit has already been declared with name 'ent' in library 'lib'"
+        ));
+        assert_eq!(messages[0].pos.source.file_name(), Some(file_name.as_str()));
+        assert_eq!(messages[0].code, Some("duplicate-unit".to_owned()));
+        assert_eq!(messages[0].related.len(), 1);
+        let (ref previous, ref related_message) = messages[0].related[0];
+        assert_eq!(previous.source.file_name(), Some(first_file_name.as_str()));
+        assert_eq!(related_message, "Previously declared here");
+        assert!(messages[1]
+            .message
+            .starts_with("Duplicate architecture 'rtl' of entity 'ent' in library 'lib'"));
+    }
+
+    #[test]
+    fn secondary_unit_without_primary_unit() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "a.vhd",
+            "
+package pkg is end package;
+architecture rtl of pkg is begin end architecture;
+package body missing is end package body;",
+        );
+
+        let files = project.project.files();
+        let root = DesignRoot::new(&files);
+        let messages: Vec<&str> = root
+            .messages()
+            .iter()
+            .map(|msg| msg.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "No entity 'pkg' within library 'lib'",
+                "No package 'missing' within library 'lib'",
+            ]
+        );
+    }
+}
@@ -0,0 +1,706 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Lint rules which check a project and report findings with the id of the rule as code
+//!
+//! The registry runs the rules which are enabled by their configuration, such as the
+//! lint section of the project file, where a rule is turned off, given a severity or
+//! given a table of enabled, severity and the options of the rule:
+//!
+//! ```toml
+//! [lint]
+//! incomplete-sensitivity-list = "off"
+//! end-identifier-mismatch = "error"
+//! keyword-case = { case = "upper" }
+//! identifier-case = { severity = "error", standard_case = "lower", declared_spelling = true }
+//! ```
+//!
+//! Rules which need options such as keyword-case are only enabled when configured
+//!
+//! Findings are waived by comments in the code as described in the waiver module
+
+use blackbox::UnboundInstance;
+use clock_reset::{BothClockEdges, ClockInLogic, IncompleteReset, MixedReset};
+use combinational_loop::CombinationalLoop;
+use constant_condition::{ConstantCondition, UnmatchedChoice};
+use forbidden_construct::ForbiddenConstruct;
+use formatter::{case_findings, spellings, Case, FormatStyle, Indentation};
+use fsm_states::{MissingDefault, NoExit, UnreachableState};
+use message::{warning, Message, Severity};
+use mixed_language::VerilogBinding;
+use name_resolution::NameResolution;
+use naming_convention::NamingConvention;
+use process_sanity::{CombinationalFeedback, EmptyProcess, ProcessWithoutWait};
+use project::Project;
+use std::collections::BTreeMap;
+use unreachable_code::UnreachableCode;
+use waiver::{apply_waivers, source_waivers, InvalidWaiver, Waiver};
+use write_only::WriteOnly;
+
+extern crate toml;
+use self::toml::value::Table;
+use self::toml::Value;
+
+/// The configuration of a rule
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RuleConfig {
+    /// Overrides whether the rule is enabled by default
+    pub enabled: Option<bool>,
+    pub severity: Option<Severity>,
+    /// The options of the rule itself
+    pub options: Table,
+}
+
+fn parse_severity(value: &str, what: &str) -> Result<Severity, String> {
+    match value {
+        "hint" => Ok(Severity::Hint),
+        "warning" => Ok(Severity::Warning),
+        "error" => Ok(Severity::Error),
+        _ => Err(format!(
+            "{} has unknown severity '{}', expected hint, warning or error",
+            what, value
+        )),
+    }
+}
+
+impl RuleConfig {
+    /// The configuration of "off", a severity or a table of enabled, severity and options,
+    /// a configured rule is enabled unless it is off
+    pub fn from_value(value: &Value, id: &str) -> Result<RuleConfig, String> {
+        let what = format!("lint rule {}", id);
+        let mut config = RuleConfig {
+            enabled: Some(true),
+            ..RuleConfig::default()
+        };
+        match value {
+            Value::String(string) if string == "off" => config.enabled = Some(false),
+            Value::String(string) => config.severity = Some(parse_severity(string, &what)?),
+            Value::Table(table) => {
+                for (key, value) in table.iter() {
+                    match key.as_str() {
+                        "enabled" => {
+                            config.enabled =
+                                Some(value.as_bool().ok_or_else(|| {
+                                    format!("{} enabled must be a boolean", what)
+                                })?);
+                        }
+                        "severity" => {
+                            let severity = value
+                                .as_str()
+                                .ok_or_else(|| format!("{} severity must be a string", what))?;
+                            config.severity = Some(parse_severity(severity, &what)?);
+                        }
+                        _ => {
+                            config.options.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+            _ => {
+                return Err(format!("{} must be off, a severity or a table", what));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Fails on the first option of the rule with the id which is not one of the known
+    pub fn unknown_options(&self, id: &str, known: &[&str]) -> Result<(), String> {
+        match self
+            .options
+            .keys()
+            .find(|key| !known.contains(&key.as_str()))
+        {
+            Some(key) => Err(format!("Unknown option '{}' of lint rule {}", key, id)),
+            None => Ok(()),
+        }
+    }
+
+    fn case_option(&self, id: &str, key: &str) -> Result<Option<Case>, String> {
+        match self.options.get(key) {
+            Some(value) => value
+                .as_str()
+                .ok_or_else(|| format!("lint rule {} {} must be a string", id, key))?
+                .parse()
+                .map(Some)
+                .map_err(|err| format!("lint rule {} {}: {}", id, key, err)),
+            None => Ok(None),
+        }
+    }
+
+    fn bool_option(&self, id: &str, key: &str) -> Result<bool, String> {
+        match self.options.get(key) {
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| format!("lint rule {} {} must be a boolean", id, key)),
+            None => Ok(false),
+        }
+    }
+}
+
+/// The configuration of the rules by their id
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LintConfig {
+    rules: BTreeMap<String, RuleConfig>,
+}
+
+impl LintConfig {
+    /// The configuration of a table of rule configurations by id
+    pub fn from_value(value: &Value) -> Result<LintConfig, String> {
+        let table = value
+            .as_table()
+            .ok_or_else(|| "lint must be a table".to_owned())?;
+        let mut config = LintConfig::default();
+        for (id, value) in table.iter() {
+            config.set(id, RuleConfig::from_value(value, id)?);
+        }
+        Ok(config)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RuleConfig> {
+        self.rules.get(id)
+    }
+
+    pub fn set(&mut self, id: &str, config: RuleConfig) {
+        self.rules.insert(id.to_owned(), config);
+    }
+
+    /// The rule configurations of the other configuration replace those of the same rule
+    pub fn merge(&mut self, other: LintConfig) {
+        self.rules.extend(other.rules);
+    }
+}
+
+/// What the rules check
+pub struct LintContext<'a> {
+    pub project: &'a Project,
+    /// The messages of parsing, analysis and name resolution
+    pub messages: &'a [Message],
+    pub resolution: &'a NameResolution,
+}
+
+pub trait Rule {
+    /// The code of the findings such as keyword-case
+    fn id(&self) -> &str;
+    fn description(&self) -> &str;
+    /// The severity of the findings unless configured
+    fn default_severity(&self) -> Severity;
+    /// Rules which need options are only enabled when configured
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+    /// The findings of the project, their code is given by the registry
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String>;
+}
+
+/// The messages of parsing, analysis and name resolution with the code of the rule
+struct MessageRule {
+    id: &'static str,
+    description: &'static str,
+    severity: Severity,
+}
+
+impl Rule for MessageRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id, &[])?;
+        Ok(context
+            .messages
+            .iter()
+            .filter(|message| message.code.as_deref() == Some(self.id))
+            .cloned()
+            .collect())
+    }
+}
+
+const MESSAGE_RULES: &[(&str, &str, Severity)] = &[
+    (
+        "duplicate-unit",
+        "A design unit is declared more than once within a library",
+        Severity::Error,
+    ),
+    (
+        "end-identifier-mismatch",
+        "The identifier after end differs from the declared one",
+        Severity::Warning,
+    ),
+    (
+        "incomplete-sensitivity-list",
+        "A signal read by a process is missing from its sensitivity list",
+        Severity::Warning,
+    ),
+    (
+        "missing-library-clause",
+        "A library is used without a library clause",
+        Severity::Error,
+    ),
+    (
+        "missing-primary-unit",
+        "A secondary unit has no primary unit",
+        Severity::Error,
+    ),
+    (
+        "missing-unit",
+        "A used design unit does not exist",
+        Severity::Error,
+    ),
+    (
+        "not-a-context",
+        "A context reference does not denote a context",
+        Severity::Error,
+    ),
+    (
+        "not-visible",
+        "A declaration is not made visible by a use clause",
+        Severity::Error,
+    ),
+    ("undeclared", "A name has no declaration", Severity::Error),
+];
+
+/// The keywords which are not written in the configured case
+struct KeywordCase;
+
+impl Rule for KeywordCase {
+    fn id(&self) -> &str {
+        "keyword-case"
+    }
+
+    fn description(&self) -> &str {
+        "A keyword is not written in the configured case"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &["case"])?;
+        let mut style = FormatStyle::new(Indentation {
+            size: 2,
+            tabs: false,
+        });
+        style.keyword_case = config.case_option(self.id(), "case")?;
+        if style.keyword_case.is_none() {
+            return Err(format!("lint rule {} needs the case option", self.id()));
+        }
+        Ok(context
+            .project
+            .files()
+            .iter()
+            .flat_map(|file| case_findings(&file.source, &style, &[]))
+            .collect())
+    }
+}
+
+/// The identifiers which are not written in the case of the standard libraries or with
+/// the spelling of their declaration
+struct IdentifierCase;
+
+impl Rule for IdentifierCase {
+    fn id(&self) -> &str {
+        "identifier-case"
+    }
+
+    fn description(&self) -> &str {
+        "An identifier is not written in the configured case or as declared"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &["standard_case", "declared_spelling"])?;
+        let mut style = FormatStyle::new(Indentation {
+            size: 2,
+            tabs: false,
+        });
+        style.standard_case = config.case_option(self.id(), "standard_case")?;
+        style.declared_spelling = config.bool_option(self.id(), "declared_spelling")?;
+        if style.standard_case.is_none() && !style.declared_spelling {
+            return Err(format!(
+                "lint rule {} needs the standard_case or declared_spelling option",
+                self.id()
+            ));
+        }
+        let mut findings = Vec::new();
+        for file in context.project.files() {
+            let spellings = spellings(
+                context.project,
+                context.resolution,
+                file.file_name(),
+                &style,
+            );
+            findings.extend(case_findings(&file.source, &style, &spellings));
+        }
+        Ok(findings)
+    }
+}
+
+/// The rules by which a project is linted
+pub struct Registry {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Registry {
+    /// The registry of the built-in rules
+    fn default() -> Registry {
+        let mut registry = Registry::new();
+        for (id, description, severity) in MESSAGE_RULES.iter() {
+            registry.register(Box::new(MessageRule {
+                id,
+                description,
+                severity: *severity,
+            }));
+        }
+        registry.register(Box::new(KeywordCase));
+        registry.register(Box::new(IdentifierCase));
+        registry.register(Box::new(NamingConvention));
+        registry.register(Box::new(ForbiddenConstruct));
+        registry.register(Box::new(BothClockEdges));
+        registry.register(Box::new(ClockInLogic));
+        registry.register(Box::new(MixedReset));
+        registry.register(Box::new(IncompleteReset));
+        registry.register(Box::new(UnreachableCode));
+        registry.register(Box::new(WriteOnly));
+        registry.register(Box::new(ProcessWithoutWait));
+        registry.register(Box::new(EmptyProcess));
+        registry.register(Box::new(CombinationalFeedback));
+        registry.register(Box::new(CombinationalLoop));
+        registry.register(Box::new(UnreachableState));
+        registry.register(Box::new(NoExit));
+        registry.register(Box::new(MissingDefault));
+        registry.register(Box::new(ConstantCondition));
+        registry.register(Box::new(UnmatchedChoice));
+        registry.register(Box::new(UnboundInstance));
+        registry.register(Box::new(VerilogBinding));
+        registry.register(Box::new(InvalidWaiver));
+        registry
+    }
+}
+
+impl Registry {
+    /// The registry without any rules
+    pub fn new() -> Registry {
+        Registry { rules: Vec::new() }
+    }
+
+    /// Adds the rule, replacing a rule with the same id
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        self.rules.retain(|existing| existing.id() != rule.id());
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn Rule> {
+        self.rules().find(|rule| rule.id() == id)
+    }
+
+    /// The findings of the enabled rules ordered by file and position, with the id of the
+    /// rule as code and the severity of its configuration if any
+    ///
+    /// Otherwise the findings keep their severity, such as errors of interface only
+    /// libraries which are reported as warnings
+    pub fn check(
+        &self,
+        context: &LintContext,
+        config: &LintConfig,
+    ) -> Result<Vec<Message>, String> {
+        self.check_waived(context, config)
+            .map(|(findings, _)| findings)
+    }
+
+    /// The findings which are not waived together with the waivers of the project
+    pub fn check_waived(
+        &self,
+        context: &LintContext,
+        config: &LintConfig,
+    ) -> Result<(Vec<Message>, Vec<Waiver>), String> {
+        if let Some(id) = config.rules.keys().find(|id| self.get(id).is_none()) {
+            return Err(format!("Unknown lint rule '{}'", id));
+        }
+        let mut waivers: Vec<Waiver> = context
+            .project
+            .files()
+            .iter()
+            .flat_map(|file| source_waivers(&file.source).0)
+            .collect();
+        let default = RuleConfig::default();
+        let mut findings = Vec::new();
+        for rule in self.rules() {
+            let rule_config = config.get(rule.id()).unwrap_or(&default);
+            if !rule_config
+                .enabled
+                .unwrap_or_else(|| rule.enabled_by_default())
+            {
+                continue;
+            }
+            let mut rule_findings = rule.check(context, rule_config)?;
+            // Only the registry knows the rules which a waiver may refer to
+            if rule.id() == InvalidWaiver.id() {
+                rule_findings.extend(
+                    waivers
+                        .iter()
+                        .filter(|waiver| self.get(&waiver.rule).is_none())
+                        .map(|waiver| {
+                            warning(
+                                &waiver.pos,
+                                &format!("Lint waiver of unknown rule '{}'", waiver.rule),
+                            )
+                        }),
+                );
+            }
+            for finding in rule_findings {
+                let mut finding = finding.with_code(rule.id());
+                if let Some(severity) = rule_config.severity {
+                    finding.severity = severity;
+                }
+                findings.push(finding);
+            }
+        }
+        let mut findings = apply_waivers(findings, &mut waivers);
+        findings.sort_by_key(|finding| {
+            (
+                finding.pos.source.file_name().map(str::to_owned),
+                finding.pos.start,
+            )
+        });
+        Ok((findings, waivers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message::warning;
+    use test_util::TempProject;
+
+    fn config(contents: &str) -> Result<LintConfig, String> {
+        let value: Value = contents.parse().unwrap();
+        LintConfig::from_value(&value)
+    }
+
+    /// Reports each design unit with a name longer than the maximum length
+    struct LongName;
+
+    impl Rule for LongName {
+        fn id(&self) -> &str {
+            "long-name"
+        }
+
+        fn description(&self) -> &str {
+            "A design unit name is too long"
+        }
+
+        fn default_severity(&self) -> Severity {
+            Severity::Warning
+        }
+
+        fn check(
+            &self,
+            context: &LintContext,
+            config: &RuleConfig,
+        ) -> Result<Vec<Message>, String> {
+            let max = config
+                .options
+                .get("max")
+                .and_then(|max| max.as_integer())
+                .unwrap_or(8) as usize;
+            Ok(context
+                .resolution
+                .declarations()
+                .iter()
+                .filter(|entity| entity.designator.len() > max)
+                .map(|entity| warning(&entity.pos, "Name is too long"))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn rule_configurations() {
+        let lint = config(
+            "
+incomplete-sensitivity-list = \"off\"
+undeclared = \"warning\"
+keyword-case = { case = \"upper\", severity = \"error\" }",
+        )
+        .unwrap();
+        assert_eq!(
+            lint.get("incomplete-sensitivity-list").unwrap().enabled,
+            Some(false)
+        );
+        assert_eq!(
+            lint.get("undeclared").unwrap().severity,
+            Some(Severity::Warning)
+        );
+        let keyword_case = lint.get("keyword-case").unwrap();
+        assert_eq!(keyword_case.severity, Some(Severity::Error));
+        assert_eq!(
+            keyword_case.options.get("case"),
+            Some(&Value::String("upper".to_owned()))
+        );
+
+        assert_eq!(
+            config("undeclared = \"fatal\""),
+            Err(
+                "lint rule undeclared has unknown severity 'fatal', expected hint, warning or error"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            config("undeclared = 1"),
+            Err("lint rule undeclared must be off, a severity or a table".to_owned())
+        );
+        assert!(config("undeclared = { enabled = \"yes\" }").is_err());
+    }
+
+    #[test]
+    fn enabled_rules_with_configured_severity() {
+        let mut project = TempProject::new();
+        project.add_file("std", "standard.vhd", "package standard is\nend package;");
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+ENTITY very_long_name IS
+END ENTITY;
+
+architecture rtl of very_long_name is
+  component comp is
+  end component wrong;
+begin
+  missing <= other;
+end architecture;",
+        );
+        let project = &project.project;
+        let mut messages = project.messages();
+        messages.extend(project.analyze());
+        let resolution = project.resolve();
+        messages.extend(resolution.messages().iter().cloned());
+        let context = LintContext {
+            project,
+            messages: &messages,
+            resolution: &resolution,
+        };
+        let codes = |registry: &Registry, config: &LintConfig| -> Vec<(String, Severity)> {
+            registry
+                .check(&context, config)
+                .unwrap()
+                .into_iter()
+                .map(|finding| (finding.code.unwrap(), finding.severity))
+                .collect()
+        };
+        let string = |string: &str| string.to_owned();
+
+        let mut registry = Registry::default();
+        assert_eq!(
+            codes(&registry, &LintConfig::default()),
+            vec![
+                (string("end-identifier-mismatch"), Severity::Warning),
+                (string("undeclared"), Severity::Error),
+                (string("undeclared"), Severity::Error),
+            ]
+        );
+
+        registry.register(Box::new(LongName));
+        let lint = config(
+            "
+undeclared = \"off\"
+end-identifier-mismatch = \"error\"
+keyword-case = { case = \"lower\" }
+long-name = { max = 12 }",
+        )
+        .unwrap();
+        assert_eq!(
+            codes(&registry, &lint),
+            vec![
+                (string("keyword-case"), Severity::Warning),
+                (string("long-name"), Severity::Warning),
+                (string("keyword-case"), Severity::Warning),
+                (string("keyword-case"), Severity::Warning),
+                (string("keyword-case"), Severity::Warning),
+                (string("end-identifier-mismatch"), Severity::Error),
+            ]
+        );
+
+        let check = |contents: &str| registry.check(&context, &config(contents).unwrap());
+        assert_eq!(
+            check("unknown = \"off\""),
+            Err("Unknown lint rule 'unknown'".to_owned())
+        );
+        assert_eq!(
+            check("keyword-case = { enabled = true }"),
+            Err("lint rule keyword-case needs the case option".to_owned())
+        );
+        assert_eq!(
+            check("undeclared = { case = \"upper\" }"),
+            Err("Unknown option 'case' of lint rule undeclared".to_owned())
+        );
+    }
+
+    #[test]
+    fn waivers_of_unknown_rules() {
+        let mut project = TempProject::new();
+        let code = "
+-- lint: waive no-such-rule \"x\"
+-- lint: waive long-name \"Generated name\"
+entity very_long_name is
+end entity;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings = |registry: &Registry| -> Vec<(String, String, usize)> {
+            registry
+                .check(&context, &LintConfig::default())
+                .unwrap()
+                .into_iter()
+                .map(|finding| (finding.code.unwrap(), finding.message, finding.pos.start))
+                .collect()
+        };
+        let invalid = |rule: &str| {
+            (
+                "invalid-waiver".to_owned(),
+                format!("Lint waiver of unknown rule '{}'", rule),
+                code.find(&format!("-- lint: waive {}", rule)).unwrap(),
+            )
+        };
+
+        let mut registry = Registry::default();
+        assert_eq!(
+            findings(&registry),
+            vec![invalid("no-such-rule"), invalid("long-name")]
+        );
+        // The waiver of a registered rule is valid and waives its finding
+        registry.register(Box::new(LongName));
+        assert_eq!(findings(&registry), vec![invalid("no-such-rule")]);
+    }
+}
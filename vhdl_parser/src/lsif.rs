@@ -0,0 +1,409 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! An LSIF index of the project for code navigation in web code browsers and review
+//! tools without running the language server
+//!
+//! Each element of the index is a JSON object, written one per line, with the
+//! documents of the VHDL files and a range for each declaration and reference which
+//! leads to the definition, the references and the hover text of the declaration
+
+extern crate serde_json;
+use self::serde_json::{json, Value};
+
+use documentation::declaration_text;
+use name_resolution::NamedEntity;
+use project::Project;
+use source::SrcPos;
+use std::collections::HashMap;
+
+/// The version of the LSIF specification of the index
+pub const LSIF_VERSION: &str = "0.4.3";
+
+/// The file URI of an absolute file name where characters other than letters, digits
+/// and -._~/: are percent encoded
+pub fn file_uri(file_name: &str) -> String {
+    let path = file_name.replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// The hover text of a declaration as markdown
+fn hover_markdown(entity: &NamedEntity) -> Option<String> {
+    let declaration = declaration_text(entity)?;
+    let mut markdown = format!("```vhdl\n{}\n```", declaration.text);
+    if let Some(ref comment) = declaration.comment {
+        markdown.push_str("\n\n");
+        markdown.push_str(comment);
+    }
+    Some(markdown)
+}
+
+/// The zero based LSP range of a position
+fn lsp_range(pos: &SrcPos) -> Value {
+    let (line, column) = pos.line_and_column();
+    let end = pos.source.pos(pos.start + pos.length, 0);
+    let (end_line, end_column) = end.line_and_column();
+    json!({
+        "start": {"line": line - 1, "character": column - 1},
+        "end": {"line": end_line - 1, "character": end_column - 1},
+    })
+}
+
+/// The result set and the definition and reference ranges of a declaration
+struct Declaration {
+    result_set: usize,
+    definition: usize,
+    references: Vec<usize>,
+}
+
+/// Builds the elements of the index where each has a unique id
+struct Index {
+    elements: Vec<Value>,
+    /// The id of each document by its file name
+    documents: HashMap<String, usize>,
+    /// The ranges within each document
+    contains: HashMap<usize, Vec<usize>>,
+    /// The id of each range by its file name, start and length
+    ranges: HashMap<(String, usize, usize), usize>,
+    /// The document of each range
+    range_documents: HashMap<usize, usize>,
+}
+
+impl Index {
+    fn next_id(&self) -> usize {
+        self.elements.len() + 1
+    }
+
+    fn vertex(&mut self, label: &str, mut properties: Value) -> usize {
+        let id = self.next_id();
+        properties["id"] = json!(id);
+        properties["type"] = json!("vertex");
+        properties["label"] = json!(label);
+        self.elements.push(properties);
+        id
+    }
+
+    fn edge(&mut self, label: &str, out_v: usize, in_v: usize) {
+        let id = self.next_id();
+        self.elements.push(json!({
+            "id": id,
+            "type": "edge",
+            "label": label,
+            "outV": out_v,
+            "inV": in_v,
+        }));
+    }
+
+    fn edges(&mut self, label: &str, out_v: usize, in_vs: &[usize], mut properties: Value) {
+        properties["id"] = json!(self.next_id());
+        properties["type"] = json!("edge");
+        properties["label"] = json!(label);
+        properties["outV"] = json!(out_v);
+        properties["inVs"] = json!(in_vs);
+        self.elements.push(properties);
+    }
+
+    /// The range of a position within a document of the index, a position which is
+    /// already a range keeps its range
+    fn range(&mut self, pos: &SrcPos) -> Option<(usize, bool)> {
+        let file_name = pos.source.file_name()?;
+        let document = *self.documents.get(file_name)?;
+        let key = (file_name.to_owned(), pos.start, pos.length);
+        if let Some(id) = self.ranges.get(&key) {
+            return Some((*id, false));
+        }
+        let range = lsp_range(pos);
+        let id = self.vertex(
+            "range",
+            json!({"start": range["start"], "end": range["end"]}),
+        );
+        self.ranges.insert(key, id);
+        self.contains.entry(document).or_default().push(id);
+        self.range_documents.insert(id, document);
+        Some((id, true))
+    }
+}
+
+/// The elements of the LSIF index of the VHDL files of the project where the project
+/// root is the absolute directory of the project
+pub fn lsif_index(project: &Project, project_root: &str) -> Vec<Value> {
+    let mut index = Index {
+        elements: Vec::new(),
+        documents: HashMap::new(),
+        contains: HashMap::new(),
+        ranges: HashMap::new(),
+        range_documents: HashMap::new(),
+    };
+    index.vertex(
+        "metaData",
+        json!({
+            "version": LSIF_VERSION,
+            "projectRoot": file_uri(project_root),
+            "positionEncoding": "utf-16",
+            "toolInfo": {"name": "rust_hdl", "version": env!("CARGO_PKG_VERSION")},
+        }),
+    );
+    let project_id = index.vertex("project", json!({"kind": "vhdl"}));
+
+    let mut files = project.files();
+    files.sort_by_key(|file| file.file_name().to_owned());
+    for file in files.iter() {
+        let id = index.vertex(
+            "document",
+            json!({"uri": file_uri(file.file_name()), "languageId": "vhdl"}),
+        );
+        index.documents.insert(file.file_name().to_owned(), id);
+    }
+
+    let resolution = project.resolve();
+    let mut declarations: Vec<(&NamedEntity, Declaration)> = Vec::new();
+    let mut declaration_index = HashMap::new();
+    for entity in resolution.declarations() {
+        let key = (
+            entity.pos.source.file_name().unwrap_or("").to_owned(),
+            entity.pos.start,
+        );
+        if declaration_index.contains_key(&key) {
+            continue;
+        }
+        let (range, is_new) = match index.range(&entity.pos) {
+            Some(range) => range,
+            None => continue,
+        };
+        if !is_new {
+            continue;
+        }
+        let result_set = index.vertex("resultSet", json!({}));
+        index.edge("next", range, result_set);
+        declaration_index.insert(key, declarations.len());
+        declarations.push((
+            entity,
+            Declaration {
+                result_set,
+                definition: range,
+                references: Vec::new(),
+            },
+        ));
+    }
+
+    for reference in resolution.references() {
+        let targets: Vec<usize> = reference
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let key = (
+                    entity.pos.source.file_name().unwrap_or("").to_owned(),
+                    entity.pos.start,
+                );
+                declaration_index.get(&key).cloned()
+            })
+            .collect();
+        let first = match targets.first() {
+            Some(first) => *first,
+            None => continue,
+        };
+        let (range, is_new) = match index.range(&reference.pos) {
+            Some(range) => range,
+            None => continue,
+        };
+        // A range leads to a single result set so overloaded names lead to the first
+        if is_new {
+            let result_set = declarations[first].1.result_set;
+            index.edge("next", range, result_set);
+        }
+        for target in targets {
+            declarations[target].1.references.push(range);
+        }
+    }
+
+    for (entity, declaration) in declarations.iter() {
+        let document = index.documents[entity.pos.source.file_name().unwrap_or("")];
+        let definition_result = index.vertex("definitionResult", json!({}));
+        index.edge(
+            "textDocument/definition",
+            declaration.result_set,
+            definition_result,
+        );
+        index.edges(
+            "item",
+            definition_result,
+            &[declaration.definition],
+            json!({"document": document}),
+        );
+
+        let reference_result = index.vertex("referenceResult", json!({}));
+        index.edge(
+            "textDocument/references",
+            declaration.result_set,
+            reference_result,
+        );
+        index.edges(
+            "item",
+            reference_result,
+            &[declaration.definition],
+            json!({"document": document, "property": "definitions"}),
+        );
+        let mut by_document: Vec<(usize, Vec<usize>)> = Vec::new();
+        for range in declaration.references.iter() {
+            let range_document = index.range_documents[range];
+            match by_document
+                .iter_mut()
+                .find(|(document, _)| *document == range_document)
+            {
+                Some((_, ranges)) => ranges.push(*range),
+                None => by_document.push((range_document, vec![*range])),
+            }
+        }
+        for (document, ranges) in by_document {
+            index.edges(
+                "item",
+                reference_result,
+                &ranges,
+                json!({"document": document, "property": "references"}),
+            );
+        }
+
+        if let Some(markdown) = hover_markdown(entity) {
+            let hover_result = index.vertex(
+                "hoverResult",
+                json!({"result": {"contents": {"kind": "markdown", "value": markdown}}}),
+            );
+            index.edge("textDocument/hover", declaration.result_set, hover_result);
+        }
+    }
+
+    let documents: Vec<usize> = files
+        .iter()
+        .map(|file| index.documents[file.file_name()])
+        .collect();
+    for document in documents.iter() {
+        if let Some(ranges) = index.contains.get(document).cloned() {
+            index.edges("contains", *document, &ranges, json!({}));
+        }
+    }
+    index.edges("contains", project_id, &documents, json!({}));
+    index.elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn with_label<'a>(elements: &'a [Value], label: &str) -> Vec<&'a Value> {
+        elements
+            .iter()
+            .filter(|element| element["label"] == label)
+            .collect()
+    }
+
+    /// The element with the id
+    fn element(elements: &[Value], id: &Value) -> Value {
+        elements
+            .iter()
+            .find(|element| element["id"] == *id)
+            .cloned()
+            .unwrap()
+    }
+
+    /// The in vertex of the edge with the label going out of the vertex
+    fn follow(elements: &[Value], out_v: &Value, label: &str) -> Value {
+        let edge = elements
+            .iter()
+            .find(|element| element["label"] == label && element["outV"] == *out_v)
+            .unwrap();
+        element(elements, &edge["inV"])
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(file_uri("/src/top.vhd"), "file:///src/top.vhd");
+        assert_eq!(file_uri("/my src/a#b.vhd"), "file:///my%20src/a%23b.vhd");
+        assert_eq!(file_uri("C:\\src\\top.vhd"), "file:///C:/src/top.vhd");
+    }
+
+    #[test]
+    fn definition_references_and_hover() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "top.vhd",
+            "entity top is
+end entity;
+
+architecture rtl of top is
+  -- The counter
+  signal count : natural;
+begin
+  count <= count + 1;
+end architecture;
+",
+        );
+        let elements = lsif_index(&project.project, "/");
+        let ids: Vec<&Value> = elements.iter().map(|element| &element["id"]).collect();
+        assert_eq!(ids.len(), elements.len());
+        assert_eq!(elements[0]["label"], "metaData");
+        assert_eq!(elements[0]["version"], LSIF_VERSION);
+        assert_eq!(elements[0]["projectRoot"], "file:///");
+
+        let documents = with_label(&elements, "document");
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0]["uri"], json!(file_uri(&file_name)));
+
+        // The range of the reference on line 8 leads to the declaration on line 6
+        let ranges = with_label(&elements, "range");
+        let reference = ranges
+            .iter()
+            .find(|range| range["start"] == json!({"line": 7, "character": 11}))
+            .unwrap();
+        assert_eq!(reference["end"], json!({"line": 7, "character": 16}));
+        let result_set = follow(&elements, &reference["id"], "next");
+        let definition_result = follow(&elements, &result_set["id"], "textDocument/definition");
+        let item = elements
+            .iter()
+            .find(|element| {
+                element["label"] == "item" && element["outV"] == definition_result["id"]
+            })
+            .unwrap();
+        let definition = element(&elements, &item["inVs"][0]);
+        assert_eq!(definition["start"], json!({"line": 5, "character": 9}));
+
+        let reference_result = follow(&elements, &result_set["id"], "textDocument/references");
+        let references = elements
+            .iter()
+            .find(|element| {
+                element["label"] == "item"
+                    && element["outV"] == reference_result["id"]
+                    && element["property"] == "references"
+            })
+            .unwrap();
+        assert_eq!(references["inVs"].as_array().unwrap().len(), 2);
+
+        let hover = follow(&elements, &result_set["id"], "textDocument/hover");
+        assert_eq!(
+            hover["result"]["contents"]["value"],
+            "```vhdl\nsignal count : natural;\n```\n\nThe counter"
+        );
+
+        let contains = elements
+            .iter()
+            .find(|element| element["label"] == "contains" && element["outV"] == documents[0]["id"])
+            .unwrap();
+        assert_eq!(contains["inVs"].as_array().unwrap().len(), ranges.len());
+    }
+}
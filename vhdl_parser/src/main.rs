@@ -57,10 +57,11 @@ fn show_design_unit(design_unit: &DesignUnit) {
             ref ident,
             ref entity_name,
             ref decl,
+            ..
         } => println!(
             "architecture {} of {} with {} declarations",
             ident.item.name(),
-            entity_name.name(),
+            entity_name.item.name(),
             decl.len()
         ),
         LibraryUnit::PackageDeclaration {
@@ -71,7 +72,7 @@ fn show_design_unit(design_unit: &DesignUnit) {
             ident.item.name(),
             decl.len()
         ),
-        LibraryUnit::PackageBody { ref ident } => println!("package body {}", ident.item.name()),
+        LibraryUnit::PackageBody { ref ident, .. } => println!("package body {}", ident.item.name()),
         LibraryUnit::Configuration(ref config) => println!(
             "configuration {} of {}",
             config.ident.item.name(),
@@ -168,6 +169,7 @@ fn main() {
                 Severity::Error => {
                     file_has_errors = true;
                 }
+                Severity::Hint => {}
             };
         }
 
@@ -0,0 +1,486 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The estimated memory of the source contents, design files and messages of each
+//! library and file together with the symbol table and the parse cache
+//!
+//! The heap bytes of a design file are estimated by walking it with a serializer which
+//! adds the size of each element of a sequence and the length of each string, boxed
+//! nodes are counted as part of their parent while padding and the capacity of vectors
+//! beyond their length are not included
+
+use project::SourceFile;
+use serde::ser::{self, Serialize};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::mem;
+use symbol_table::{Symbol, SymbolTable};
+
+/// The estimated memory of a file in bytes
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct FileMemory {
+    pub file_name: String,
+    /// The contents kept in memory, zero when the contents are read from the file when needed
+    pub source_bytes: usize,
+    pub design_file_bytes: usize,
+    pub message_bytes: usize,
+}
+
+impl FileMemory {
+    pub fn total_bytes(&self) -> usize {
+        self.source_bytes + self.design_file_bytes + self.message_bytes
+    }
+}
+
+/// The estimated memory of the files of a library in bytes
+#[derive(PartialEq, Debug, Default, Clone, Serialize)]
+pub struct LibraryMemory {
+    pub library_name: String,
+    /// Only the interface of the design units is parsed
+    pub interface_only: bool,
+    pub source_bytes: usize,
+    pub design_file_bytes: usize,
+    pub message_bytes: usize,
+    pub files: Vec<FileMemory>,
+}
+
+impl LibraryMemory {
+    fn add_file(&mut self, file: FileMemory) {
+        self.source_bytes += file.source_bytes;
+        self.design_file_bytes += file.design_file_bytes;
+        self.message_bytes += file.message_bytes;
+        self.files.push(file);
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.source_bytes + self.design_file_bytes + self.message_bytes
+    }
+}
+
+/// The number of entries of the parse cache and their size on disk
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct CacheMemory {
+    pub entries: usize,
+    pub disk_bytes: u64,
+}
+
+/// The estimated memory of a project
+#[derive(PartialEq, Debug, Default, Clone, Serialize)]
+pub struct MemoryReport {
+    pub libraries: Vec<LibraryMemory>,
+    /// The names of the symbol table shared by all files
+    pub symbols: usize,
+    pub symbol_table_bytes: usize,
+    /// None when the project has no cache directory
+    pub cache: Option<CacheMemory>,
+}
+
+impl MemoryReport {
+    /// The bytes in memory, the cache is on disk and not included
+    pub fn total_bytes(&self) -> usize {
+        self.libraries
+            .iter()
+            .map(LibraryMemory::total_bytes)
+            .sum::<usize>()
+            + self.symbol_table_bytes
+    }
+}
+
+/// Never produced since estimating cannot fail
+#[derive(Debug)]
+struct EstimateError(String);
+
+impl fmt::Display for EstimateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl error::Error for EstimateError {}
+
+impl ser::Error for EstimateError {
+    fn custom<T: fmt::Display>(msg: T) -> EstimateError {
+        EstimateError(msg.to_string())
+    }
+}
+
+/// Adds the heap bytes of the serialized value where serializing a value returns
+/// its inline size, the sum of the sizes of its fields without padding or enum tags
+#[derive(Default)]
+struct HeapEstimator {
+    bytes: usize,
+    /// The inline size of each compound value being serialized
+    inline: Vec<usize>,
+}
+
+impl HeapEstimator {
+    fn open(&mut self) -> &mut HeapEstimator {
+        self.inline.push(0);
+        self
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        let bytes = value.serialize(&mut *self)?;
+        if let Some(inline) = self.inline.last_mut() {
+            *inline += bytes;
+        }
+        Ok(())
+    }
+
+    /// The elements of a sequence are stored on the heap
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.bytes += value.serialize(&mut *self)?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> usize {
+        self.inline.pop().unwrap_or(0)
+    }
+}
+
+type EstimateResult = Result<(), EstimateError>;
+type SizeResult = Result<usize, EstimateError>;
+
+impl ser::Serializer for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, _: bool) -> SizeResult {
+        Ok(mem::size_of::<bool>())
+    }
+    fn serialize_i8(self, _: i8) -> SizeResult {
+        Ok(mem::size_of::<i8>())
+    }
+    fn serialize_i16(self, _: i16) -> SizeResult {
+        Ok(mem::size_of::<i16>())
+    }
+    fn serialize_i32(self, _: i32) -> SizeResult {
+        Ok(mem::size_of::<i32>())
+    }
+    fn serialize_i64(self, _: i64) -> SizeResult {
+        Ok(mem::size_of::<i64>())
+    }
+    fn serialize_u8(self, _: u8) -> SizeResult {
+        Ok(mem::size_of::<u8>())
+    }
+    fn serialize_u16(self, _: u16) -> SizeResult {
+        Ok(mem::size_of::<u16>())
+    }
+    fn serialize_u32(self, _: u32) -> SizeResult {
+        Ok(mem::size_of::<u32>())
+    }
+    fn serialize_u64(self, _: u64) -> SizeResult {
+        Ok(mem::size_of::<u64>())
+    }
+    fn serialize_f32(self, _: f32) -> SizeResult {
+        Ok(mem::size_of::<f32>())
+    }
+    fn serialize_f64(self, _: f64) -> SizeResult {
+        Ok(mem::size_of::<f64>())
+    }
+    fn serialize_char(self, _: char) -> SizeResult {
+        Ok(mem::size_of::<char>())
+    }
+    fn serialize_str(self, value: &str) -> SizeResult {
+        self.bytes += value.len();
+        Ok(mem::size_of::<String>())
+    }
+    fn serialize_bytes(self, value: &[u8]) -> SizeResult {
+        self.bytes += value.len();
+        Ok(mem::size_of::<Vec<u8>>())
+    }
+    fn serialize_none(self) -> SizeResult {
+        Ok(0)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> SizeResult {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> SizeResult {
+        Ok(0)
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> SizeResult {
+        Ok(0)
+    }
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> SizeResult {
+        Ok(0)
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> SizeResult {
+        // The name of a symbol belongs to the symbol table
+        if name == "Symbol" {
+            Ok(mem::size_of::<Symbol>())
+        } else {
+            value.serialize(self)
+        }
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &T,
+    ) -> SizeResult {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, EstimateError> {
+        Ok(self.open())
+    }
+}
+
+impl ser::SerializeSeq for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.element(value)
+    }
+    fn end(self) -> SizeResult {
+        self.close();
+        Ok(mem::size_of::<Vec<u8>>())
+    }
+}
+
+impl ser::SerializeTuple for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.field(value)
+    }
+    fn end(self) -> SizeResult {
+        Ok(self.close())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.field(value)
+    }
+    fn end(self) -> SizeResult {
+        Ok(self.close())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.field(value)
+    }
+    fn end(self) -> SizeResult {
+        Ok(self.close())
+    }
+}
+
+impl ser::SerializeMap for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> EstimateResult {
+        self.element(key)
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> EstimateResult {
+        self.element(value)
+    }
+    fn end(self) -> SizeResult {
+        self.close();
+        Ok(mem::size_of::<Vec<u8>>())
+    }
+}
+
+impl ser::SerializeStruct for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> EstimateResult {
+        self.field(value)
+    }
+    fn end(self) -> SizeResult {
+        Ok(self.close())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut HeapEstimator {
+    type Ok = usize;
+    type Error = EstimateError;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> EstimateResult {
+        self.field(value)
+    }
+    fn end(self) -> SizeResult {
+        Ok(self.close())
+    }
+}
+
+/// The estimated number of heap bytes owned by the value
+pub fn heap_bytes<T: Serialize>(value: &T) -> usize {
+    let mut estimator = HeapEstimator::default();
+    // Estimating never fails
+    let _ = value.serialize(&mut estimator);
+    estimator.bytes
+}
+
+/// The estimated memory of the file
+pub fn file_memory(file: &SourceFile) -> FileMemory {
+    FileMemory {
+        file_name: file.file_name().to_owned(),
+        source_bytes: file.source.memory_bytes(),
+        design_file_bytes: heap_bytes(&file.design_file),
+        message_bytes: heap_bytes(&file.messages),
+    }
+}
+
+/// The estimated memory of each library sorted by library name with the files sorted by
+/// file name, the interface only flag and the cache are left to the project
+pub fn memory_report(files: &[&SourceFile], symtab: &SymbolTable) -> MemoryReport {
+    let mut libraries: BTreeMap<&str, LibraryMemory> = BTreeMap::new();
+    for file in files {
+        libraries
+            .entry(&file.library_name)
+            .or_insert_with(|| LibraryMemory {
+                library_name: file.library_name.clone(),
+                ..LibraryMemory::default()
+            })
+            .add_file(file_memory(file));
+    }
+    for library in libraries.values_mut() {
+        library.files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    }
+    MemoryReport {
+        libraries: libraries.into_values().collect(),
+        symbols: symtab.len(),
+        symbol_table_bytes: symtab.memory_bytes(),
+        cache: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use source::Source;
+    use test_util::TempProject;
+
+    #[test]
+    fn strings_and_elements_of_sequences_are_counted() {
+        assert_eq!(heap_bytes(&0usize), 0);
+        assert_eq!(heap_bytes(&"hello".to_owned()), 5);
+        assert_eq!(heap_bytes(&vec![1u32, 2, 3]), 12);
+        assert_eq!(
+            heap_bytes(&vec!["ab".to_owned(), "cde".to_owned()]),
+            2 * mem::size_of::<String>() + 5
+        );
+    }
+
+    #[test]
+    fn names_of_symbols_are_not_counted() {
+        let mut project = TempProject::new();
+        let short = project.add_file("lib", "short.vhd", "entity e is\nend entity;\n");
+        let long = project.add_file(
+            "lib",
+            "long.vhd",
+            "entity a_very_long_entity_name is\nend entity;\n",
+        );
+        let short = file_memory(project.project.get_file(&short).unwrap());
+        let long = file_memory(project.project.get_file(&long).unwrap());
+        assert!(short.design_file_bytes > 0);
+        assert_eq!(short.design_file_bytes, long.design_file_bytes);
+    }
+
+    #[test]
+    fn memory_of_each_library_and_file() {
+        let mut project = TempProject::new();
+        let on_disk = project.add_file("lib", "ent.vhd", "entity ent is\nend entity;\n");
+        let code = "package pkg is\n  constant c : natural := 0;\nend package;\n";
+        project
+            .project
+            .update_source(
+                "other",
+                Source::from_file_contents("pkg.vhd", code).unwrap(),
+            )
+            .unwrap();
+        project.project.set_interface_only("other", true);
+
+        let report = project.project.memory_report();
+        assert_eq!(
+            report
+                .libraries
+                .iter()
+                .map(|library| (library.library_name.as_str(), library.interface_only))
+                .collect::<Vec<_>>(),
+            vec![("lib", false), ("other", true)]
+        );
+
+        let lib = &report.libraries[0];
+        assert_eq!(lib.files.len(), 1);
+        assert_eq!(lib.files[0].file_name, on_disk);
+        assert_eq!(lib.source_bytes, 0);
+        assert!(lib.design_file_bytes > 0);
+        assert_eq!(lib.message_bytes, 0);
+
+        let other = &report.libraries[1];
+        assert_eq!(other.files[0].file_name, "pkg.vhd");
+        assert_eq!(other.source_bytes, code.len());
+        assert_eq!(other.total_bytes(), other.files[0].total_bytes());
+
+        assert!(report.symbols > 0);
+        assert!(report.symbol_table_bytes > 0);
+        assert_eq!(report.cache, None);
+        assert_eq!(
+            report.total_bytes(),
+            lib.total_bytes() + other.total_bytes() + report.symbol_table_bytes
+        );
+    }
+}
@@ -7,18 +7,34 @@
 use source::SrcPos;
 use std::convert::AsRef;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Severity {
     Warning,
     Error,
+    /// A finding which is only shown, such as a suggestion of a lint rule
+    Hint,
+}
+
+/// A machine applicable change which resolves a message
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Fix {
+    /// Describes the change such as "Replace with foo"
+    pub title: String,
+    /// The new text of each position where an empty position is an insertion
+    pub edits: Vec<(SrcPos, String)>,
 }
 
 #[must_use]
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub pos: SrcPos,
     pub message: String,
     pub severity: Severity,
+    /// Identifies the kind of message such as "duplicate-unit"
+    pub code: Option<String>,
+    /// Other positions which explain the message such as a previous declaration
+    pub related: Vec<(SrcPos, String)>,
+    pub fixes: Vec<Fix>,
 }
 
 impl Message {
@@ -27,15 +43,38 @@ impl Message {
             message: format!("{}, when {}", &self.message, &message),
             pos: self.pos,
             severity: self.severity,
+            code: self.code,
+            related: self.related,
+            fixes: self.fixes,
         }
     }
 
+    pub fn with_code(mut self, code: &str) -> Message {
+        self.code = Some(code.to_owned());
+        self
+    }
+
+    pub fn related(mut self, item: impl AsRef<SrcPos>, message: &str) -> Message {
+        self.related
+            .push((item.as_ref().clone(), message.to_owned()));
+        self
+    }
+
+    pub fn with_fix(mut self, title: &str, edits: Vec<(SrcPos, String)>) -> Message {
+        self.fixes.push(Fix {
+            title: title.to_owned(),
+            edits,
+        });
+        self
+    }
+
     pub fn pretty_string(self: &Self) -> String {
         let (lineno, pretty_str) = self.pos.lineno_and_pretty_string();
         let file_name = self.pos.source.file_name().unwrap_or("<unknown file>");
         let severity = match self.severity {
             Severity::Error => &"error",
             Severity::Warning => &"warning",
+            Severity::Hint => &"hint",
         };
         format!(
             "{}:{}: {}: {}\n{}",
@@ -49,6 +88,9 @@ pub fn message<T: AsRef<SrcPos>>(item: T, msg: &str, severity: Severity) -> Mess
         pos: item.as_ref().clone(),
         message: msg.to_string(),
         severity: severity,
+        code: None,
+        related: Vec::new(),
+        fixes: Vec::new(),
     }
 }
 
@@ -0,0 +1,787 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! VHDL instances bound to the Verilog and SystemVerilog modules of the project
+//!
+//! A component instance binds to a module of the same name in the library when there is
+//! no such entity, as does an entity instance such as entity work.ip. The ports of the
+//! component are checked against the module by name, direction and width, where the widths
+//! are evaluated for the generics of each instance. Names are matched case insensitively
+//! like mixed language simulators do. The formals of entity instances are checked by name
+//! only since there is no component declaring their subtypes
+//!
+//! Widths which are not static, such as those of user defined types, unconstrained vectors
+//! or parameters given by functions, are not checked
+
+use ast::{
+    AssociationElement, ComponentDeclaration, Declaration, InstantiatedUnit,
+    InstantiationStatement, InterfaceDeclaration, LibraryUnit, Mode, Name,
+};
+use instance_tree::InstanceHierarchy;
+use library::{symbol_key, UnitId};
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use project::{Project, SourceFile, VerilogSourceFile};
+use source::{Source, SrcPos};
+use static_value::{bit_width, generic_values, Constants, Value};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use symbol_table::Symbol;
+use verilog_parser::{PortDirection, VerilogModule, VerilogPort};
+use verilog_tokenizer::{tokenize, Kind, Token, Tokenizer};
+
+/// An association or declaration which does not match the Verilog module
+#[derive(PartialEq, Debug, Clone)]
+pub enum Mismatch {
+    /// A generic of the component or a formal of the generic map which is not a parameter
+    UnknownGeneric(String, SrcPos),
+    /// A port of the component or a formal of the port map which is not a port
+    UnknownPort(String, SrcPos),
+    /// A port of the module which is not declared by the component, at the component name
+    MissingPort(String, SrcPos),
+    Direction {
+        name: String,
+        mode: Mode,
+        direction: PortDirection,
+        pos: SrcPos,
+    },
+    /// The widths in bits of the component port and the module port
+    Width {
+        name: String,
+        width: i64,
+        module_width: i64,
+        pos: SrcPos,
+    },
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::In => "in",
+        Mode::Out => "out",
+        Mode::InOut => "inout",
+        Mode::Buffer => "buffer",
+        Mode::Linkage => "linkage",
+    }
+}
+
+fn direction_name(direction: PortDirection) -> &'static str {
+    match direction {
+        PortDirection::Input => "input",
+        PortDirection::Output => "output",
+        PortDirection::Inout => "inout",
+        PortDirection::Ref => "ref",
+    }
+}
+
+impl Mismatch {
+    pub fn pos(&self) -> &SrcPos {
+        match self {
+            Mismatch::UnknownGeneric(_, ref pos)
+            | Mismatch::UnknownPort(_, ref pos)
+            | Mismatch::MissingPort(_, ref pos)
+            | Mismatch::Direction { ref pos, .. }
+            | Mismatch::Width { ref pos, .. } => pos,
+        }
+    }
+
+    /// A description of the mismatch with the name of the module
+    pub fn message(&self, module: &str) -> String {
+        match self {
+            Mismatch::UnknownGeneric(ref name, _) => {
+                format!(
+                    "'{}' is not a parameter of Verilog module '{}'",
+                    name, module
+                )
+            }
+            Mismatch::UnknownPort(ref name, _) => {
+                format!("'{}' is not a port of Verilog module '{}'", name, module)
+            }
+            Mismatch::MissingPort(ref name, _) => format!(
+                "Port '{}' of Verilog module '{}' is not declared by the component",
+                name, module
+            ),
+            Mismatch::Direction {
+                ref name,
+                mode,
+                direction,
+                ..
+            } => format!(
+                "Port '{}' has mode {} but is {} in Verilog module '{}'",
+                name,
+                mode_name(*mode),
+                direction_name(*direction),
+                module
+            ),
+            Mismatch::Width {
+                ref name,
+                width,
+                module_width,
+                ..
+            } => format!(
+                "Port '{}' is {} bits wide but {} bits in Verilog module '{}'",
+                name, width, module_width, module
+            ),
+        }
+    }
+}
+
+/// An instance bound to a Verilog module with the mismatches of its binding
+#[derive(PartialEq, Debug, Clone)]
+pub struct ModuleInstance {
+    pub architecture: UnitId,
+    /// The label of the instance prefixed by enclosing block and generate labels
+    pub label: String,
+    pub pos: SrcPos,
+    pub module: UnitId,
+    /// The name of the module as written
+    pub module_name: String,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// The values of parameters by their lower case names and the text macros by which
+/// Verilog expressions are evaluated
+struct Scope<'a> {
+    parameters: BTreeMap<String, i64>,
+    defines: &'a BTreeMap<String, String>,
+}
+
+/// Macros which expand to themselves are not evaluated
+const MAX_MACRO_DEPTH: usize = 16;
+
+/// The value of a Verilog number such as 8, 'd255 or 16'hFF_FF, the size is ignored
+fn number_value(text: &str) -> Option<i64> {
+    let text: String = text
+        .chars()
+        .filter(|chr| !matches!(chr, '_' | ' ' | '\t'))
+        .collect();
+    match text.find('\'') {
+        None => text.parse().ok(),
+        Some(quote) => {
+            let based = text[quote + 1..].trim_start_matches(['s', 'S']);
+            let radix = match based.chars().next()?.to_ascii_lowercase() {
+                'b' => 2,
+                'o' => 8,
+                'd' => 10,
+                'h' => 16,
+                _ => {
+                    return None;
+                }
+            };
+            i64::from_str_radix(&based[1..], radix).ok()
+        }
+    }
+}
+
+fn clog2(value: i64) -> i64 {
+    if value <= 1 {
+        0
+    } else {
+        i64::from(64 - (value as u64 - 1).leading_zeros())
+    }
+}
+
+/// The binary operators from the lowest to the highest precedence
+const PRECEDENCE: &[&[&str]] = &[&["<<", ">>"], &["+", "-"], &["*", "/", "%"], &["**"]];
+
+fn binary(operator: &str, left: i64, right: i64) -> Option<i64> {
+    match operator {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        "**" => left.checked_pow(u32::try_from(right).ok()?),
+        "<<" => left.checked_shl(u32::try_from(right).ok()?),
+        ">>" => left.checked_shr(u32::try_from(right).ok()?),
+        _ => None,
+    }
+}
+
+/// Evaluates the integer arithmetic of a constant Verilog expression
+struct Evaluator<'a> {
+    tokens: &'a [Token],
+    idx: usize,
+    scope: &'a Scope<'a>,
+    depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn skip_if(&mut self, kind: Kind) -> bool {
+        let is_kind = self
+            .tokens
+            .get(self.idx)
+            .is_some_and(|token| token.kind == kind);
+        if is_kind {
+            self.idx += 1;
+        }
+        is_kind
+    }
+
+    fn operator(&self, operators: &[&str]) -> Option<&'a str> {
+        let token = self.tokens.get(self.idx)?;
+        if token.kind == Kind::Operator && operators.contains(&token.text.as_str()) {
+            Some(token.text.as_str())
+        } else {
+            None
+        }
+    }
+
+    fn expression(&mut self, level: usize) -> Option<i64> {
+        let operators = match PRECEDENCE.get(level) {
+            Some(operators) => operators,
+            None => {
+                return self.unary();
+            }
+        };
+        let mut value = self.expression(level + 1)?;
+        while let Some(operator) = self.operator(operators) {
+            self.idx += 1;
+            value = binary(operator, value, self.expression(level + 1)?)?;
+        }
+        Some(value)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        match self.operator(&["-", "+"]) {
+            Some(operator) => {
+                self.idx += 1;
+                let value = self.unary()?;
+                if operator == "-" {
+                    value.checked_neg()
+                } else {
+                    Some(value)
+                }
+            }
+            None => self.primary(),
+        }
+    }
+
+    fn parenthesized(&mut self) -> Option<i64> {
+        if !self.skip_if(Kind::LeftPar) {
+            return None;
+        }
+        let value = self.expression(0)?;
+        if self.skip_if(Kind::RightPar) {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn primary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.idx)?;
+        match token.kind {
+            Kind::LeftPar => self.parenthesized(),
+            Kind::SystemIdentifier if token.text == "$clog2" => {
+                self.idx += 1;
+                self.parenthesized().map(clog2)
+            }
+            Kind::Number => {
+                self.idx += 1;
+                number_value(&token.text)
+            }
+            Kind::Identifier => {
+                self.idx += 1;
+                self.scope
+                    .parameters
+                    .get(&token.text.to_lowercase())
+                    .cloned()
+            }
+            Kind::Macro => {
+                self.idx += 1;
+                let text = self.scope.defines.get(&token.text[1..])?;
+                verilog_value(text, self.scope, self.depth + 1)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The value of a constant Verilog expression such as WIDTH*2-1 or $clog2(DEPTH)
+fn verilog_value(text: &str, scope: &Scope, depth: usize) -> Option<i64> {
+    if depth > MAX_MACRO_DEPTH {
+        return None;
+    }
+    let source = Source::from_str(text).ok()?;
+    let mut tokenizer = Tokenizer::new(source.clone(), source.contents().ok()?);
+    let mut messages = Vec::new();
+    let tokens = tokenize(&mut tokenizer, &mut messages);
+    if !messages.is_empty() {
+        return None;
+    }
+    let mut evaluator = Evaluator {
+        tokens: &tokens,
+        idx: 0,
+        scope,
+        depth,
+    };
+    let value = evaluator.expression(0)?;
+    if evaluator.idx == tokens.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// The parameters of the module where those which can be overridden have the values of
+/// the generics, and the others their defaults
+fn module_scope<'a>(
+    module: &VerilogModule,
+    generics: &Constants,
+    defines: &'a BTreeMap<String, String>,
+) -> Scope<'a> {
+    let mut scope = Scope {
+        parameters: BTreeMap::new(),
+        defines,
+    };
+    for parameter in module
+        .parameters
+        .iter()
+        .filter(|parameter| !parameter.is_type)
+    {
+        let key = parameter.name.item.to_lowercase();
+        let value = match generics.get(&key) {
+            Some(Value::Integer(value)) if !parameter.local => Some(*value),
+            _ => parameter
+                .default
+                .as_ref()
+                .and_then(|default| verilog_value(default, &scope, 0)),
+        };
+        if let Some(value) = value {
+            scope.parameters.insert(key, value);
+        }
+    }
+    scope
+}
+
+/// The number of bits of the port, None for interface ports and user defined types
+fn verilog_width(port: &VerilogPort, scope: &Scope) -> Option<i64> {
+    if port.interface.is_some() {
+        return None;
+    }
+    let mut width = 1;
+    let words = port
+        .data_type
+        .iter()
+        .flat_map(|data_type| data_type.split_whitespace());
+    for word in words {
+        match word {
+            "integer" | "int" => width = 32,
+            "byte" => width = 8,
+            "shortint" => width = 16,
+            "longint" | "time" => width = 64,
+            "wire" | "reg" | "logic" | "bit" | "var" | "tri" | "tri0" | "tri1" | "triand"
+            | "trior" | "wand" | "wor" | "uwire" | "supply0" | "supply1" | "signed"
+            | "unsigned" => {}
+            _ => {
+                return None;
+            }
+        }
+    }
+    for range in port.ranges.iter() {
+        let left = verilog_value(&range.left, scope, 0)?;
+        let right = verilog_value(&range.right, scope, 0)?;
+        width = left
+            .checked_sub(right)?
+            .checked_abs()?
+            .checked_add(1)?
+            .checked_mul(width)?;
+    }
+    Some(width)
+}
+
+/// The number of bits of each port of the module for the values of the generics, None
+/// when the width is not static
+pub fn port_widths(
+    module: &VerilogModule,
+    generics: &Constants,
+    defines: &BTreeMap<String, String>,
+) -> Vec<Option<i64>> {
+    let scope = module_scope(module, generics, defines);
+    module
+        .ports
+        .iter()
+        .map(|port| verilog_width(port, &scope))
+        .collect()
+}
+
+/// The text macros of all files, the first definition of a name wins
+pub fn verilog_defines(files: &[&VerilogSourceFile]) -> BTreeMap<String, String> {
+    let mut defines = BTreeMap::new();
+    for file in files.iter() {
+        for (name, text) in file.verilog_file.defines.iter() {
+            defines.entry(name.clone()).or_insert_with(|| text.clone());
+        }
+    }
+    defines
+}
+
+fn module_port<'m>(module: &'m VerilogModule, name: &str) -> Option<&'m VerilogPort> {
+    module
+        .ports
+        .iter()
+        .find(|port| port.name.item.eq_ignore_ascii_case(name))
+}
+
+fn is_module_parameter(module: &VerilogModule, name: &str) -> bool {
+    module
+        .parameters
+        .iter()
+        .any(|parameter| parameter.name.item.eq_ignore_ascii_case(name))
+}
+
+fn is_matching_direction(mode: Mode, direction: PortDirection) -> bool {
+    matches!(
+        (mode, direction),
+        (Mode::In, PortDirection::Input)
+            | (Mode::Out, PortDirection::Output)
+            | (Mode::Buffer, PortDirection::Output)
+            | (Mode::InOut, PortDirection::Inout)
+            | (Mode::Linkage, _)
+            | (_, PortDirection::Ref)
+    )
+}
+
+/// The generics and ports of the component which do not match the module
+fn check_component(
+    component: &ComponentDeclaration,
+    generic_map: &[AssociationElement],
+    module: &VerilogModule,
+    defines: &BTreeMap<String, String>,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    for decl in component.generic_list.iter() {
+        if let InterfaceDeclaration::Object(ref object) = decl {
+            let name = object.ident.item.name_utf8();
+            if !is_module_parameter(module, &name) {
+                mismatches.push(Mismatch::UnknownGeneric(name, object.ident.pos.clone()));
+            }
+        }
+    }
+
+    let constants = generic_values(&component.generic_list, generic_map);
+    let scope = module_scope(module, &constants, defines);
+    let mut declared = Vec::new();
+    for decl in component.port_list.iter() {
+        let object = match decl {
+            InterfaceDeclaration::Object(ref object) => object,
+            _ => continue,
+        };
+        let name = object.ident.item.name_utf8();
+        let pos = object.ident.pos.clone();
+        let port = match module_port(module, &name) {
+            Some(port) => port,
+            None => {
+                mismatches.push(Mismatch::UnknownPort(name, pos));
+                continue;
+            }
+        };
+        declared.push(port.name.item.as_str());
+        if !is_matching_direction(object.mode, port.direction) {
+            mismatches.push(Mismatch::Direction {
+                name: name.clone(),
+                mode: object.mode,
+                direction: port.direction,
+                pos: pos.clone(),
+            });
+        }
+        let widths = (
+            bit_width(&object.subtype_indication, &constants),
+            verilog_width(port, &scope),
+        );
+        if let (Some(width), Some(module_width)) = widths {
+            if width != module_width {
+                mismatches.push(Mismatch::Width {
+                    name,
+                    width,
+                    module_width,
+                    pos,
+                });
+            }
+        }
+    }
+
+    for port in module.ports.iter() {
+        if !declared.contains(&port.name.item.as_str()) {
+            mismatches.push(Mismatch::MissingPort(
+                port.name.item.clone(),
+                component.ident.pos.clone(),
+            ));
+        }
+    }
+}
+
+/// The generic or port a formal designator denotes, a formal may also be a subelement
+/// such as data(0)
+fn formal_symbol(name: &Name) -> Option<&Symbol> {
+    match name {
+        Name::Simple(ref symbol) => Some(symbol),
+        Name::Selected(ref prefix, _)
+        | Name::Indexed(ref prefix, _)
+        | Name::Slice(ref prefix, _) => formal_symbol(&prefix.item),
+        _ => None,
+    }
+}
+
+/// The formals of the generic and port maps of an entity instance which do not match the module
+fn check_formals(
+    instance: &InstantiationStatement,
+    module: &VerilogModule,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let formals = |associations: &[AssociationElement]| -> Vec<(String, SrcPos)> {
+        associations
+            .iter()
+            .filter_map(|association| {
+                let formal = association.formal.as_ref()?;
+                let symbol = formal_symbol(&formal.item)?;
+                Some((symbol.name_utf8(), formal.pos.clone()))
+            })
+            .collect()
+    };
+    for (name, pos) in formals(&instance.generic_map) {
+        if !is_module_parameter(module, &name) {
+            mismatches.push(Mismatch::UnknownGeneric(name, pos));
+        }
+    }
+    for (name, pos) in formals(&instance.port_map) {
+        if module_port(module, &name).is_none() {
+            mismatches.push(Mismatch::UnknownPort(name, pos));
+        }
+    }
+}
+
+/// The component declarations of architectures and packages by their lower case names,
+/// the first of a name wins
+fn component_declarations<'a>(
+    files: &[&'a SourceFile],
+) -> BTreeMap<String, &'a ComponentDeclaration> {
+    let mut components = BTreeMap::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            let decls = match design_unit.library_unit {
+                LibraryUnit::ArchitectureBody { ref decl, .. }
+                | LibraryUnit::PackageDeclaration { ref decl, .. } => decl,
+                _ => continue,
+            };
+            for decl in decls.iter() {
+                if let Declaration::Component(ref component) = decl {
+                    components
+                        .entry(symbol_key(&component.ident.item))
+                        .or_insert(component);
+                }
+            }
+        }
+    }
+    components
+}
+
+/// The instances of the project which are bound to a Verilog module in the order of the
+/// architectures
+pub fn verilog_bindings(project: &Project) -> Vec<ModuleInstance> {
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    let components = component_declarations(&files);
+    let defines = verilog_defines(&verilog_files);
+
+    hierarchy
+        .verilog_instantiations()
+        .into_iter()
+        .filter_map(|instantiation| {
+            let module = hierarchy.verilog_module(&instantiation.module)?;
+            let instance = instantiation.instance;
+            let mut mismatches = Vec::new();
+            match instance.unit {
+                InstantiatedUnit::Component(ref name) => {
+                    let component = name
+                        .last()
+                        .and_then(|ident| components.get(&symbol_key(&ident.item)));
+                    if let Some(component) = component {
+                        check_component(
+                            component,
+                            &instance.generic_map,
+                            module,
+                            &defines,
+                            &mut mismatches,
+                        );
+                    }
+                }
+                _ => check_formals(instance, module, &mut mismatches),
+            }
+            Some(ModuleInstance {
+                architecture: instantiation.architecture,
+                label: instantiation.label,
+                pos: instantiation.pos,
+                module: instantiation.module,
+                module_name: module.name.item.clone(),
+                mismatches,
+            })
+        })
+        .collect()
+}
+
+pub struct VerilogBinding;
+
+impl Rule for VerilogBinding {
+    fn id(&self) -> &str {
+        "verilog-binding"
+    }
+
+    fn description(&self) -> &str {
+        "A component or instance does not match the ports or parameters of the Verilog module it binds to"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for instance in verilog_bindings(context.project) {
+            for mismatch in instance.mismatches.iter() {
+                // A component declared once is checked for every instance
+                let finding = warning(mismatch.pos(), &mismatch.message(&instance.module_name));
+                if !findings.contains(&finding) {
+                    findings.push(finding);
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn verilog_values() {
+        let mut defines = BTreeMap::new();
+        defines.insert("DEPTH".to_owned(), "2**4".to_owned());
+        defines.insert("LOOP".to_owned(), "`LOOP".to_owned());
+        let mut scope = Scope {
+            parameters: BTreeMap::new(),
+            defines: &defines,
+        };
+        scope.parameters.insert("width".to_owned(), 8);
+        let value = |text: &str| verilog_value(text, &scope, 0);
+
+        assert_eq!(value("WIDTH-1"), Some(7));
+        assert_eq!(value("(WIDTH + 2) * 3"), Some(30));
+        assert_eq!(value("-8'hF_F + 'b11 % 2"), Some(-254));
+        assert_eq!(value("1 << WIDTH >> 4"), Some(16));
+        assert_eq!(value("$clog2(`DEPTH + 1)"), Some(5));
+        assert_eq!(value("$clog2(1)"), Some(0));
+        assert_eq!(value("`LOOP"), None);
+        assert_eq!(value("missing"), None);
+        assert_eq!(value("4'bx0"), None);
+        assert_eq!(value("WIDTH 1"), None);
+    }
+
+    #[test]
+    fn verilog_binding() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "fifo.sv",
+            "
+`define ADDR_BITS $clog2(DEPTH)
+module fifo #(parameter WIDTH = 8, parameter DEPTH = 16, localparam AW = `ADDR_BITS) (
+  input logic clk,
+  input logic [WIDTH-1:0] din,
+  output logic [WIDTH-1:0] dout,
+  output logic [AW:0] count,
+  output logic full
+);
+endmodule",
+        );
+        let code = "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+  component fifo is
+    generic (width : natural; depth : natural; latency : natural);
+    port (
+      clk : in std_logic;
+      din : in std_logic_vector(width - 1 downto 0);
+      dout : in std_logic_vector(width - 1 downto 0);
+      count : out std_logic_vector(4 downto 0);
+      empty : out std_logic);
+  end component;
+  signal clk : std_logic;
+begin
+  narrow: component fifo generic map (width => 8, depth => 16, latency => 1);
+  deep: component fifo generic map (width => 8, depth => 64, latency => 1);
+  direct: entity work.fifo generic map (WIDTH => 4, size => 2) port map (clk => clk, rst => '0');
+end architecture;";
+        project.add_file("lib", "top.vhd", code);
+        let project = &project.project;
+
+        let bindings = verilog_bindings(project);
+        let labels: Vec<&str> = bindings
+            .iter()
+            .map(|instance| instance.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["narrow", "deep", "direct"]);
+        assert_eq!(bindings[0].module, UnitId::primary("lib", "fifo"));
+
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<(String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("verilog-binding".to_owned()))
+            .map(|finding| (finding.message, finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    string("Port 'full' of Verilog module 'fifo' is not declared by the component"),
+                    code.find("fifo is").unwrap()
+                ),
+                (
+                    string("'latency' is not a parameter of Verilog module 'fifo'"),
+                    code.find("latency").unwrap()
+                ),
+                (
+                    string("Port 'dout' has mode in but is output in Verilog module 'fifo'"),
+                    code.find("dout").unwrap()
+                ),
+                (
+                    string("Port 'count' is 5 bits wide but 7 bits in Verilog module 'fifo'"),
+                    code.find("count").unwrap()
+                ),
+                (
+                    string("'empty' is not a port of Verilog module 'fifo'"),
+                    code.find("empty").unwrap()
+                ),
+                (
+                    string("'size' is not a parameter of Verilog module 'fifo'"),
+                    code.find("size").unwrap()
+                ),
+                (
+                    string("'rst' is not a port of Verilog module 'fifo'"),
+                    code.find("rst").unwrap()
+                ),
+            ]
+        );
+    }
+}
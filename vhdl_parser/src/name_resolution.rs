@@ -0,0 +1,3147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! LRM 12 Scope and visibility
+//! Resolves names to the declarations they denote for navigation in an editor
+//! Types are not analyzed so overloaded names resolve to all visible candidates
+
+use ast::*;
+use dependency_graph::DependencyGraph;
+use library::{symbol_key, DesignRoot, LibraryUnitRef, UnitId};
+use message::{error, warning, Message};
+use quick_fix;
+use source::{SrcPos, WithPos};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use symbol_table::Symbol;
+
+extern crate rayon;
+use self::rayon::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NamedEntityKind {
+    Entity,
+    Architecture,
+    Configuration,
+    Package,
+    PackageInstance,
+    Context,
+    Signal,
+    Constant,
+    Variable,
+    SharedVariable,
+    File,
+    Type,
+    Subtype,
+    Component,
+    Attribute,
+    Alias,
+    Procedure,
+    Function,
+    Generic,
+    Port,
+    Parameter,
+    PhysicalUnit,
+    EnumerationLiteral,
+    Label,
+    LoopParameter,
+}
+
+impl FromStr for NamedEntityKind {
+    type Err = String;
+
+    /// The kind in lower case with underscores between words such as shared_variable
+    fn from_str(string: &str) -> Result<NamedEntityKind, String> {
+        match string {
+            "entity" => Ok(NamedEntityKind::Entity),
+            "architecture" => Ok(NamedEntityKind::Architecture),
+            "configuration" => Ok(NamedEntityKind::Configuration),
+            "package" => Ok(NamedEntityKind::Package),
+            "package_instance" => Ok(NamedEntityKind::PackageInstance),
+            "context" => Ok(NamedEntityKind::Context),
+            "signal" => Ok(NamedEntityKind::Signal),
+            "constant" => Ok(NamedEntityKind::Constant),
+            "variable" => Ok(NamedEntityKind::Variable),
+            "shared_variable" => Ok(NamedEntityKind::SharedVariable),
+            "file" => Ok(NamedEntityKind::File),
+            "type" => Ok(NamedEntityKind::Type),
+            "subtype" => Ok(NamedEntityKind::Subtype),
+            "component" => Ok(NamedEntityKind::Component),
+            "attribute" => Ok(NamedEntityKind::Attribute),
+            "alias" => Ok(NamedEntityKind::Alias),
+            "procedure" => Ok(NamedEntityKind::Procedure),
+            "function" => Ok(NamedEntityKind::Function),
+            "generic" => Ok(NamedEntityKind::Generic),
+            "port" => Ok(NamedEntityKind::Port),
+            "parameter" => Ok(NamedEntityKind::Parameter),
+            "physical_unit" => Ok(NamedEntityKind::PhysicalUnit),
+            "enumeration_literal" => Ok(NamedEntityKind::EnumerationLiteral),
+            "label" => Ok(NamedEntityKind::Label),
+            "loop_parameter" => Ok(NamedEntityKind::LoopParameter),
+            _ => Err(format!("Unknown kind '{}'", string)),
+        }
+    }
+}
+
+impl fmt::Display for NamedEntityKind {
+    /// The kind as parsed by from_str
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            NamedEntityKind::Entity => "entity",
+            NamedEntityKind::Architecture => "architecture",
+            NamedEntityKind::Configuration => "configuration",
+            NamedEntityKind::Package => "package",
+            NamedEntityKind::PackageInstance => "package_instance",
+            NamedEntityKind::Context => "context",
+            NamedEntityKind::Signal => "signal",
+            NamedEntityKind::Constant => "constant",
+            NamedEntityKind::Variable => "variable",
+            NamedEntityKind::SharedVariable => "shared_variable",
+            NamedEntityKind::File => "file",
+            NamedEntityKind::Type => "type",
+            NamedEntityKind::Subtype => "subtype",
+            NamedEntityKind::Component => "component",
+            NamedEntityKind::Attribute => "attribute",
+            NamedEntityKind::Alias => "alias",
+            NamedEntityKind::Procedure => "procedure",
+            NamedEntityKind::Function => "function",
+            NamedEntityKind::Generic => "generic",
+            NamedEntityKind::Port => "port",
+            NamedEntityKind::Parameter => "parameter",
+            NamedEntityKind::PhysicalUnit => "physical_unit",
+            NamedEntityKind::EnumerationLiteral => "enumeration_literal",
+            NamedEntityKind::Label => "label",
+            NamedEntityKind::LoopParameter => "loop_parameter",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// LRM 6.1 A named entity introduced by a declaration
+#[derive(Clone, Debug)]
+pub struct NamedEntity {
+    /// The designator as written in the declaration
+    pub designator: String,
+    pub kind: NamedEntityKind,
+    pub pos: SrcPos,
+}
+
+impl NamedEntity {
+    fn new(designator: String, kind: NamedEntityKind, pos: &SrcPos) -> NamedEntity {
+        NamedEntity {
+            designator,
+            kind,
+            pos: pos.clone(),
+        }
+    }
+
+    fn from_ident(ident: &Ident, kind: NamedEntityKind) -> NamedEntity {
+        NamedEntity::new(ident.item.name_utf8(), kind, &ident.pos)
+    }
+
+    /// Case insensitive key of the designator
+    fn key(&self) -> String {
+        self.designator.to_lowercase()
+    }
+
+    /// Identifies the declaration within the project
+    fn region_key(&self) -> RegionKey {
+        (
+            self.pos.source.file_name().unwrap_or("").to_owned(),
+            self.pos.start,
+        )
+    }
+}
+
+/// Compares the declaration position only to avoid comparing the contents of the sources
+impl PartialEq for NamedEntity {
+    fn eq(&self, other: &NamedEntity) -> bool {
+        self.kind == other.kind
+            && self.pos.start == other.pos.start
+            && self.pos.source.file_name() == other.pos.source.file_name()
+    }
+}
+
+/// How a type declaration is derived from another type
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TypeRelation {
+    /// LRM 6.3 A subtype of its type mark
+    Subtype,
+    /// LRM 6.3 A subtype with a resolution indication of its type mark such as std_logic
+    Resolved,
+    /// LRM 5.3 An array or record type with elements of the type
+    Element,
+}
+
+/// A name which denotes one or more named entities
+#[derive(Clone, Debug)]
+pub struct Reference {
+    pub pos: SrcPos,
+    pub entities: Vec<NamedEntity>,
+    /// The name is the target of an assignment or the actual of an out, inout or buffer port
+    pub is_write: bool,
+    /// The value is read, which the actual of an inout port also is when written
+    pub is_read: bool,
+}
+
+fn contains(pos: &SrcPos, file_name: &str, offset: usize) -> bool {
+    pos.source.file_name() == Some(file_name)
+        && pos.start <= offset
+        && offset <= pos.start + pos.length
+}
+
+/// The declarations of a project and the names which refer to them
+#[derive(Default)]
+pub struct NameResolution {
+    declarations: Vec<NamedEntity>,
+    references: Vec<Reference>,
+    /// The named entities declared within each declarative region
+    declarative_regions: Vec<Vec<NamedEntity>>,
+    /// The architectures of each entity
+    architectures: HashMap<RegionKey, Vec<NamedEntity>>,
+    /// The entity of the default binding of each component
+    bindings: HashMap<RegionKey, NamedEntity>,
+    /// Each type or subtype together with a type it is derived from
+    type_relations: Vec<(NamedEntity, NamedEntity, TypeRelation)>,
+    /// The key of each undeclared name within an architecture and its signal declaration
+    undeclared_signals: Vec<(String, (SrcPos, String))>,
+    messages: Vec<Message>,
+}
+
+impl NameResolution {
+    pub fn new(root: &DesignRoot) -> NameResolution {
+        NameResolution::resolve(root, DependencyGraph::from_root(root).levels())
+    }
+
+    /// Only resolves the design units and the units they depend on directly or indirectly,
+    /// which is faster than resolving all units when only a few of them are of interest
+    pub fn of_units(root: &DesignRoot, unit_ids: &BTreeSet<UnitId>) -> NameResolution {
+        let graph = DependencyGraph::from_root(root);
+        let required = graph.transitive_dependencies(unit_ids);
+        let levels = graph
+            .levels()
+            .into_iter()
+            .map(|level| {
+                level
+                    .into_iter()
+                    .filter(|unit_id| required.contains(unit_id))
+                    .collect()
+            })
+            .collect();
+        NameResolution::resolve(root, levels)
+    }
+
+    /// Resolves the design units of each level of the dependency graph concurrently,
+    /// each unit sees the regions declared within the units it depends on
+    fn resolve(root: &DesignRoot, levels: Vec<Vec<UnitId>>) -> NameResolution {
+        let mut declared = Declared::new(root);
+        let units: Vec<&LibraryUnitRef> = root
+            .libraries()
+            .flat_map(|library| library.units())
+            .collect();
+        let index_of: HashMap<&UnitId, usize> = units
+            .iter()
+            .enumerate()
+            .map(|(index, unit)| (&unit.id, index))
+            .collect();
+
+        let mut resolved: Vec<Option<NameResolution>> = units.iter().map(|_| None).collect();
+        for level in levels {
+            let results: Vec<(usize, NameResolution, HashMap<RegionKey, Region>)> = level
+                .par_iter()
+                .filter_map(|unit_id| index_of.get(unit_id))
+                .map(|&index| {
+                    let unit = units[index];
+                    let mut resolver = Resolver::new(root, &declared);
+                    resolver.resolve_unit(&unit.id.library_name, unit);
+                    let (resolution, regions) = resolver.finish();
+                    (index, resolution, regions)
+                })
+                .collect();
+            for (index, resolution, regions) in results {
+                declared.regions.extend(regions);
+                resolved[index] = Some(resolution);
+            }
+        }
+
+        // The result does not depend on the order in which the units were resolved
+        let mut resolution = NameResolution::default();
+        for unit_resolution in resolved.into_iter().flatten() {
+            resolution.extend(unit_resolution);
+        }
+
+        // LRM 13.5 The primary units of a library and the architectures of an entity
+        for library in root.libraries() {
+            resolution.declarative_regions.push(
+                library
+                    .units()
+                    .filter(|unit| unit.id.is_primary())
+                    .map(unit_entity)
+                    .collect(),
+            );
+        }
+        resolution
+            .declarative_regions
+            .extend(declared.architectures.values().cloned());
+        resolution.architectures = declared.architectures;
+        resolution
+    }
+
+    /// Add the names resolved within another design unit
+    fn extend(&mut self, other: NameResolution) {
+        self.declarations.extend(other.declarations);
+        self.references.extend(other.references);
+        self.declarative_regions.extend(other.declarative_regions);
+        self.bindings.extend(other.bindings);
+        for relation in other.type_relations {
+            if !self.type_relations.contains(&relation) {
+                self.type_relations.push(relation);
+            }
+        }
+        self.undeclared_signals.extend(other.undeclared_signals);
+        self.messages.extend(other.messages);
+    }
+
+    pub fn declarations(&self) -> &[NamedEntity] {
+        &self.declarations
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// Names without a visible declaration and signals missing from sensitivity lists
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// The declarations of all undeclared names used within the architectures of a file,
+    /// each name is declared once and the declarations of an architecture form one edit
+    pub fn signal_declarations(&self, file_name: &str) -> Vec<(SrcPos, String)> {
+        let mut declared: Vec<&str> = Vec::new();
+        let mut edits: Vec<(SrcPos, String)> = Vec::new();
+        for (key, (pos, text)) in self.undeclared_signals.iter() {
+            if pos.source.file_name() != Some(file_name) {
+                continue;
+            }
+            if let Some(edit) = edits.iter_mut().find(|(edit_pos, _)| edit_pos == pos) {
+                if declared.contains(&key.as_str()) {
+                    continue;
+                }
+                edit.1.push_str(text);
+            } else {
+                edits.push((pos.clone(), text.clone()));
+            }
+            declared.push(key);
+        }
+        edits
+    }
+
+    /// The named entities denoted by the name or declaration at a character offset of a file
+    pub fn entities_at(&self, file_name: &str, offset: usize) -> Vec<&NamedEntity> {
+        self.name_at(file_name, offset)
+            .map(|(_, entities)| entities)
+            .unwrap_or_default()
+    }
+
+    /// The position of the name or declaration at a character offset of a file
+    /// together with the named entities it denotes
+    pub fn name_at(&self, file_name: &str, offset: usize) -> Option<(&SrcPos, Vec<&NamedEntity>)> {
+        for reference in self.references.iter() {
+            if contains(&reference.pos, file_name, offset) {
+                return Some((&reference.pos, reference.entities.iter().collect()));
+            }
+        }
+
+        self.declarations
+            .iter()
+            .find(|entity| contains(&entity.pos, file_name, offset))
+            .map(|entity| (&entity.pos, vec![entity]))
+    }
+
+    /// The positions of all names which may denote the named entity
+    pub fn references_to(&self, entity: &NamedEntity) -> Vec<&SrcPos> {
+        self.references
+            .iter()
+            .filter(|reference| reference.entities.contains(entity))
+            .map(|reference| &reference.pos)
+            .collect()
+    }
+
+    /// The architectures of an entity
+    pub fn architectures_of(&self, entity: &NamedEntity) -> Vec<&NamedEntity> {
+        self.architectures
+            .get(&entity.region_key())
+            .map(|architectures| architectures.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// LRM 7.3.3 The entity with the same simple name as the component in the working library
+    pub fn bound_entity(&self, component: &NamedEntity) -> Option<&NamedEntity> {
+        self.bindings.get(&component.region_key())
+    }
+
+    /// The bound entity and architectures of a component or the architectures of an entity
+    pub fn implementations(&self, entity: &NamedEntity) -> Vec<&NamedEntity> {
+        match entity.kind {
+            NamedEntityKind::Entity => self.architectures_of(entity),
+            NamedEntityKind::Component => match self.bound_entity(entity) {
+                Some(bound) => {
+                    let mut result = vec![bound];
+                    result.extend(self.architectures_of(bound));
+                    result
+                }
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// The types a type or subtype is derived from
+    pub fn supertypes_of(&self, typ: &NamedEntity) -> Vec<(&NamedEntity, TypeRelation)> {
+        self.type_relations
+            .iter()
+            .filter(|(derived, _, _)| derived == typ)
+            .map(|(_, supertype, relation)| (supertype, *relation))
+            .collect()
+    }
+
+    /// The types and subtypes derived from a type or subtype
+    pub fn subtypes_of(&self, typ: &NamedEntity) -> Vec<(&NamedEntity, TypeRelation)> {
+        self.type_relations
+            .iter()
+            .filter(|(_, supertype, _)| supertype == typ)
+            .map(|(derived, _, relation)| (derived, *relation))
+            .collect()
+    }
+
+    /// The other named entities with the designator which are declared
+    /// in a declarative region which also contains the named entity
+    pub fn declared_with(&self, entity: &NamedEntity, designator: &str) -> Vec<&NamedEntity> {
+        let key = designator.to_lowercase();
+        let mut result: Vec<&NamedEntity> = Vec::new();
+        for region in self.declarative_regions.iter() {
+            if !region.contains(entity) {
+                continue;
+            }
+            for other in region.iter() {
+                if other != entity && other.key() == key && !result.contains(&other) {
+                    result.push(other);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The file name and start position of a declaration
+type RegionKey = (String, usize);
+
+/// The named entities declared within a declarative region by their case insensitive designator
+type Region = HashMap<String, Vec<NamedEntity>>;
+
+fn add_to_region(region: &mut Region, entity: NamedEntity) {
+    region.entry(entity.key()).or_default().push(entity);
+}
+
+#[derive(Clone, Debug)]
+enum Visible {
+    Library(String),
+    Entity(NamedEntity),
+}
+
+fn entities_of(visible: &[Visible]) -> Vec<NamedEntity> {
+    visible
+        .iter()
+        .filter_map(|visible| match visible {
+            Visible::Entity(ref entity) => Some(entity.clone()),
+            Visible::Library(..) => None,
+        })
+        .collect()
+}
+
+/// LRM 12.3 Names declared within the scope are directly visible
+/// while names made visible by use clauses are only potentially visible
+#[derive(Default)]
+struct Scope {
+    declared: HashMap<String, Vec<Visible>>,
+    used: HashMap<String, Vec<Visible>>,
+}
+
+fn unit_entity(unit: &LibraryUnitRef) -> NamedEntity {
+    let kind = match unit.unit.library_unit {
+        LibraryUnit::EntityDeclaration { .. } => NamedEntityKind::Entity,
+        LibraryUnit::ArchitectureBody { .. } => NamedEntityKind::Architecture,
+        LibraryUnit::Configuration(..) => NamedEntityKind::Configuration,
+        LibraryUnit::PackageDeclaration { .. } | LibraryUnit::PackageBody { .. } => {
+            NamedEntityKind::Package
+        }
+        LibraryUnit::PackageInstance(..) => NamedEntityKind::PackageInstance,
+        LibraryUnit::ContextDeclaration(..) => NamedEntityKind::Context,
+    };
+    NamedEntity::from_ident(unit.ident(), kind)
+}
+
+fn designator_string(designator: &Designator) -> String {
+    match designator {
+        Designator::Identifier(ref symbol) => symbol.name_utf8(),
+        Designator::OperatorSymbol(ref op) => format!("\"{}\"", op),
+    }
+}
+
+pub fn subprogram_entity(decl: &SubprogramDeclaration) -> NamedEntity {
+    let (designator, kind) = match decl {
+        SubprogramDeclaration::Procedure(ref procedure) => {
+            (&procedure.designator, NamedEntityKind::Procedure)
+        }
+        SubprogramDeclaration::Function(ref function) => {
+            (&function.designator, NamedEntityKind::Function)
+        }
+    };
+    NamedEntity::new(designator_string(&designator.item), kind, &designator.pos)
+}
+
+/// The named entity of an interface declaration where interface objects are of the kind
+pub fn interface_entity(decl: &InterfaceDeclaration, object_kind: NamedEntityKind) -> NamedEntity {
+    match decl {
+        InterfaceDeclaration::Object(ref object) => {
+            NamedEntity::from_ident(&object.ident, object_kind)
+        }
+        InterfaceDeclaration::File(ref file) => {
+            NamedEntity::from_ident(&file.ident, NamedEntityKind::File)
+        }
+        InterfaceDeclaration::Type(ref ident) => {
+            NamedEntity::from_ident(ident, NamedEntityKind::Type)
+        }
+        InterfaceDeclaration::Subprogram(ref decl, _) => subprogram_entity(decl),
+    }
+}
+
+fn object_kind(class: ObjectClass) -> NamedEntityKind {
+    match class {
+        ObjectClass::Signal => NamedEntityKind::Signal,
+        ObjectClass::Constant => NamedEntityKind::Constant,
+        ObjectClass::Variable => NamedEntityKind::Variable,
+        ObjectClass::SharedVariable => NamedEntityKind::SharedVariable,
+    }
+}
+
+/// The named entities a declaration makes visible in its declarative region
+pub fn declared_entities(decl: &Declaration) -> Vec<NamedEntity> {
+    match decl {
+        Declaration::Object(ref object) => vec![NamedEntity::from_ident(
+            &object.ident,
+            object_kind(object.class),
+        )],
+        Declaration::File(ref file) => {
+            vec![NamedEntity::from_ident(&file.ident, NamedEntityKind::File)]
+        }
+        Declaration::Type(ref typ) => {
+            let kind = match typ.def {
+                TypeDefinition::Subtype(..) => NamedEntityKind::Subtype,
+                _ => NamedEntityKind::Type,
+            };
+            let mut entities = vec![NamedEntity::from_ident(&typ.ident, kind)];
+            // LRM 5.2.2 The identifier literals of an enumeration type are declared with the type
+            if let TypeDefinition::Enumeration(ref literals) = typ.def {
+                for literal in literals.iter() {
+                    if let EnumerationLiteral::Identifier(ref symbol) = literal.item {
+                        entities.push(NamedEntity::new(
+                            symbol.name_utf8(),
+                            NamedEntityKind::EnumerationLiteral,
+                            &literal.pos,
+                        ));
+                    }
+                }
+            }
+            // LRM 5.2.4 The units of a physical type are declared with the type
+            if let TypeDefinition::Physical(ref physical) = typ.def {
+                entities.push(NamedEntity::from_ident(
+                    &physical.primary_unit,
+                    NamedEntityKind::PhysicalUnit,
+                ));
+                for (ident, _) in physical.secondary_units.iter() {
+                    entities.push(NamedEntity::from_ident(
+                        ident,
+                        NamedEntityKind::PhysicalUnit,
+                    ));
+                }
+            }
+            entities
+        }
+        Declaration::Component(ref component) => vec![NamedEntity::from_ident(
+            &component.ident,
+            NamedEntityKind::Component,
+        )],
+        Declaration::Attribute(Attribute::Declaration(ref attribute)) => {
+            vec![NamedEntity::from_ident(
+                &attribute.ident,
+                NamedEntityKind::Attribute,
+            )]
+        }
+        Declaration::Attribute(Attribute::Specification(..)) => Vec::new(),
+        Declaration::Alias(ref alias) => vec![NamedEntity::new(
+            designator_string(&alias.designator.item),
+            NamedEntityKind::Alias,
+            &alias.designator.pos,
+        )],
+        Declaration::SubprogramDeclaration(ref decl) => vec![subprogram_entity(decl)],
+        Declaration::SubprogramBody(ref body) => vec![subprogram_entity(&body.specification)],
+        Declaration::Use(..) => Vec::new(),
+        Declaration::Package(ref instance) => vec![NamedEntity::from_ident(
+            &instance.ident,
+            NamedEntityKind::PackageInstance,
+        )],
+    }
+}
+
+fn declarations_region(decls: &[Declaration]) -> Region {
+    let mut region = Region::new();
+    for decl in decls {
+        for entity in declared_entities(decl) {
+            add_to_region(&mut region, entity);
+        }
+    }
+    region
+}
+
+/// The mode of each port of the interface list
+fn add_port_modes(modes: &mut HashMap<RegionKey, Mode>, ports: &[InterfaceDeclaration]) {
+    for decl in ports {
+        if let InterfaceDeclaration::Object(ref object) = decl {
+            let port = NamedEntity::from_ident(&object.ident, NamedEntityKind::Port);
+            modes.insert(port.region_key(), object.mode);
+        }
+    }
+}
+
+fn interface_region(generics: &[InterfaceDeclaration], ports: &[InterfaceDeclaration]) -> Region {
+    let mut region = Region::new();
+    for decl in generics {
+        add_to_region(
+            &mut region,
+            interface_entity(decl, NamedEntityKind::Generic),
+        );
+    }
+    for decl in ports {
+        add_to_region(&mut region, interface_entity(decl, NamedEntityKind::Port));
+    }
+    region
+}
+
+/// The generics and ports of an entity declaration
+pub fn entity_interface(
+    library_unit: &LibraryUnit,
+) -> (&[InterfaceDeclaration], &[InterfaceDeclaration]) {
+    match library_unit {
+        LibraryUnit::EntityDeclaration {
+            ref generic_clause,
+            ref port_clause,
+            ..
+        } => (
+            generic_clause
+                .as_ref()
+                .map(|clause| clause.generic_list.as_slice())
+                .unwrap_or(&[]),
+            port_clause
+                .as_ref()
+                .map(|clause| clause.port_list.as_slice())
+                .unwrap_or(&[]),
+        ),
+        _ => (&[], &[]),
+    }
+}
+
+/// What is known of the design units before they are resolved
+struct Declared<'a> {
+    /// The regions of named entities which contain declarations visible by selection,
+    /// regions declared within design units are added once the units are resolved
+    regions: HashMap<RegionKey, Region>,
+    /// The architectures of each entity
+    architectures: HashMap<RegionKey, Vec<NamedEntity>>,
+    /// The library and items of each context declaration
+    contexts: HashMap<RegionKey, (String, &'a [ContextItem])>,
+    /// The mode of each port of the entities
+    modes: HashMap<RegionKey, Mode>,
+}
+
+struct Resolver<'r, 'a: 'r> {
+    root: &'r DesignRoot<'a>,
+    declared: &'r Declared<'a>,
+    /// The regions declared within the unit being resolved such as those of components
+    regions: HashMap<RegionKey, Region>,
+    /// The entity of the default binding of each component
+    bindings: HashMap<RegionKey, NamedEntity>,
+    /// The mode of each port of the components declared within the unit being resolved
+    modes: HashMap<RegionKey, Mode>,
+    type_relations: Vec<(NamedEntity, NamedEntity, TypeRelation)>,
+
+    library_name: String,
+    scopes: Vec<Scope>,
+    /// References and declarations are only recorded within the unit being resolved
+    /// and not when making the declarations of a primary unit visible to its secondary units
+    recording: bool,
+
+    declarations: Vec<NamedEntity>,
+    references: Vec<Reference>,
+    declarative_regions: Vec<Vec<NamedEntity>>,
+
+    /// Names are only reported as undeclared when all potentially visible declarations are known
+    /// which is not the case when a use clause denotes a library or unit outside of the project
+    complete: bool,
+    /// Names of record elements within aggregates are not looked up
+    reporting: bool,
+    /// The design unit being resolved
+    unit: Option<NamedEntity>,
+    /// The architecture whose statements are being resolved
+    architecture: Option<NamedEntity>,
+    /// The signals read by the statements of a process with a sensitivity list
+    reads: Option<Vec<(SrcPos, NamedEntity)>>,
+    /// The target of an assignment is being resolved
+    writing: bool,
+    /// The target being resolved is the actual of an inout port which is also read
+    updating: bool,
+    /// The formal of the association whose actual, a simple name, is being resolved
+    formal: Option<NamedEntity>,
+    /// Statements which are only executed on a clock edge are being resolved
+    clocked: bool,
+    undeclared_signals: Vec<(String, (SrcPos, String))>,
+    messages: Vec<Message>,
+}
+
+/// LRM 5.2.6 and 5.5.2 Predefined operations which are implicitly declared by type declarations
+const IMPLICIT_OPERATIONS: &[&str] = &[
+    "to_string",
+    "to_hstring",
+    "to_ostring",
+    "to_bstring",
+    "to_hex_string",
+    "to_octal_string",
+    "to_binary_string",
+    "minimum",
+    "maximum",
+    "deallocate",
+    "file_open",
+    "file_close",
+    "flush",
+    "read",
+    "write",
+    "endfile",
+];
+
+/// A condition on a clock edge using rising_edge, falling_edge or the event attribute
+fn is_clock_edge(expr: &Expression) -> bool {
+    match expr {
+        Expression::Binary(_, ref left, ref right) => {
+            is_clock_edge(&left.item) || is_clock_edge(&right.item)
+        }
+        Expression::Unary(_, ref operand) => is_clock_edge(&operand.item),
+        Expression::Name(ref name) => match **name {
+            Name::FunctionCall(ref call) => match call.name.item {
+                Name::Simple(ref symbol) => {
+                    let key = symbol_key(symbol);
+                    key == "rising_edge" || key == "falling_edge"
+                }
+                _ => false,
+            },
+            Name::Attribute(ref attribute) => symbol_key(&attribute.attr.item) == "event",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl<'a> Declared<'a> {
+    /// The regions of all design units are known before any unit is resolved
+    fn new(root: &DesignRoot<'a>) -> Declared<'a> {
+        let mut declared = Declared {
+            regions: HashMap::new(),
+            architectures: HashMap::new(),
+            contexts: HashMap::new(),
+            modes: HashMap::new(),
+        };
+        let mut instances = Vec::new();
+
+        for library in root.libraries() {
+            for unit in library.units() {
+                let key = unit_entity(unit).region_key();
+                match unit.unit.library_unit {
+                    LibraryUnit::EntityDeclaration { .. } => {
+                        let (generics, ports) = entity_interface(&unit.unit.library_unit);
+                        declared
+                            .regions
+                            .insert(key, interface_region(generics, ports));
+                        add_port_modes(&mut declared.modes, ports);
+                    }
+                    LibraryUnit::PackageDeclaration { ref decl, .. } => {
+                        declared.regions.insert(key, declarations_region(decl));
+                    }
+                    LibraryUnit::ArchitectureBody { .. } => {
+                        if let Some(entity) = library.get(&unit.id.primary_unit().key) {
+                            declared
+                                .architectures
+                                .entry(unit_entity(entity).region_key())
+                                .or_default()
+                                .push(unit_entity(unit));
+                        }
+                    }
+                    LibraryUnit::ContextDeclaration(ref context) => {
+                        declared
+                            .contexts
+                            .insert(key, (library.name().to_owned(), &context.items));
+                    }
+                    LibraryUnit::PackageInstance(ref instance) => {
+                        instances.push((library.name(), key, instance));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // LRM 4.9 A package instance contains the declarations of the uninstantiated package
+        for (library_name, key, instance) in instances {
+            let parts = &instance.package_name;
+            let package_library = if parts.len() >= 2 {
+                symbol_key(&parts[parts.len() - 2].item)
+            } else {
+                library_name.to_owned()
+            };
+            let package_library = if package_library == "work" {
+                library_name.to_owned()
+            } else {
+                package_library
+            };
+            let package = parts.last().and_then(|name| {
+                root.library(&package_library)?
+                    .get_primary(&symbol_key(&name.item))
+            });
+            if let Some(package) = package {
+                if let Some(region) = declared.regions.get(&unit_entity(package).region_key()) {
+                    let region = region.clone();
+                    declared.regions.insert(key, region);
+                }
+            }
+        }
+        declared
+    }
+}
+
+impl<'r, 'a: 'r> Resolver<'r, 'a> {
+    fn new(root: &'r DesignRoot<'a>, declared: &'r Declared<'a>) -> Resolver<'r, 'a> {
+        Resolver {
+            root,
+            declared,
+            regions: HashMap::new(),
+            bindings: HashMap::new(),
+            modes: HashMap::new(),
+            type_relations: Vec::new(),
+            library_name: String::new(),
+            scopes: Vec::new(),
+            recording: true,
+            declarations: Vec::new(),
+            references: Vec::new(),
+            declarative_regions: Vec::new(),
+            complete: true,
+            reporting: true,
+            unit: None,
+            architecture: None,
+            reads: None,
+            writing: false,
+            updating: false,
+            formal: None,
+            clocked: false,
+            undeclared_signals: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// The names resolved by the resolver and the regions declared within the units
+    fn finish(self) -> (NameResolution, HashMap<RegionKey, Region>) {
+        let resolution = NameResolution {
+            declarations: self.declarations,
+            references: self.references,
+            declarative_regions: self.declarative_regions,
+            architectures: HashMap::new(),
+            bindings: self.bindings,
+            type_relations: self.type_relations,
+            undeclared_signals: self.undeclared_signals,
+            messages: self.messages,
+        };
+        (resolution, self.regions)
+    }
+
+    /// The region of a named entity declared within a unit or known in advance
+    fn region(&self, key: &RegionKey) -> Option<&Region> {
+        self.regions
+            .get(key)
+            .or_else(|| self.declared.regions.get(key))
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        if self.recording {
+            let entities: Vec<NamedEntity> = scope
+                .declared
+                .into_values()
+                .flat_map(|visible| entities_of(&visible))
+                .collect();
+            if !entities.is_empty() {
+                self.declarative_regions.push(entities);
+            }
+        }
+    }
+
+    fn scope_mut(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn declare_library(&mut self, name: &str) {
+        let library_name = if name == "work" {
+            self.library_name.clone()
+        } else {
+            name.to_owned()
+        };
+        self.scope_mut()
+            .declared
+            .insert(name.to_owned(), vec![Visible::Library(library_name)]);
+    }
+
+    fn declare(&mut self, entity: NamedEntity) {
+        if self.recording {
+            self.declarations.push(entity.clone());
+        }
+        self.scope_mut()
+            .declared
+            .entry(entity.key())
+            .or_default()
+            .push(Visible::Entity(entity));
+    }
+
+    fn use_visible(&mut self, key: String, visible: Vec<Visible>) {
+        self.scope_mut()
+            .used
+            .entry(key)
+            .or_default()
+            .extend(visible);
+    }
+
+    fn add_reference(&mut self, pos: &SrcPos, visible: &[Visible]) {
+        if !self.recording {
+            return;
+        }
+        let entities = entities_of(visible);
+        let is_read = !self.writing || self.updating;
+        if let Some(ref mut reads) = self.reads {
+            if let [ref entity] = entities.as_slice() {
+                let is_signal =
+                    entity.kind == NamedEntityKind::Signal || entity.kind == NamedEntityKind::Port;
+                if is_signal && is_read && !self.clocked {
+                    reads.push((pos.clone(), entity.clone()));
+                }
+            }
+        }
+        if !entities.is_empty() {
+            self.references.push(Reference {
+                pos: pos.clone(),
+                entities,
+                is_write: self.writing,
+                is_read,
+            });
+        }
+    }
+
+    /// LRM 12.3 Directly visible declarations of inner scopes hide those of outer scopes
+    /// Declarations made visible by use clauses are only visible when not hidden
+    fn lookup(&self, key: &str) -> Vec<Visible> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(visible) = scope.declared.get(key) {
+                return visible.clone();
+            }
+        }
+
+        let mut result = Vec::new();
+        for scope in self.scopes.iter() {
+            if let Some(visible) = scope.used.get(key) {
+                result.extend(visible.iter().cloned());
+            }
+        }
+        result
+    }
+
+    /// LRM 8.3 Selected names denoting a declaration within a library or declarative region
+    fn select(&self, prefix: &[Visible], key: &str) -> Vec<Visible> {
+        let mut result = Vec::new();
+        for visible in prefix {
+            match visible {
+                Visible::Library(ref library_name) => {
+                    if let Some(unit) = self
+                        .root
+                        .library(library_name)
+                        .and_then(|library| library.get_primary(key))
+                    {
+                        result.push(Visible::Entity(unit_entity(unit)));
+                    }
+                }
+                Visible::Entity(ref entity) => {
+                    if let Some(entities) = self
+                        .region(&entity.region_key())
+                        .and_then(|region| region.get(key))
+                    {
+                        result.extend(entities.iter().cloned().map(Visible::Entity));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// All declarations within a library or declarative region as for a use clause with suffix all
+    fn select_all(&self, prefix: &[Visible]) -> Vec<(String, Visible)> {
+        let mut result = Vec::new();
+        for visible in prefix {
+            match visible {
+                Visible::Library(ref library_name) => {
+                    if let Some(library) = self.root.library(library_name) {
+                        for unit in library.units().filter(|unit| unit.id.is_primary()) {
+                            let entity = unit_entity(unit);
+                            result.push((entity.key(), Visible::Entity(entity)));
+                        }
+                    }
+                }
+                Visible::Entity(ref entity) => {
+                    if let Some(region) = self.region(&entity.region_key()) {
+                        for (key, entities) in region.iter() {
+                            for entity in entities {
+                                result.push((key.clone(), Visible::Entity(entity.clone())));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn resolve_unit(&mut self, library_name: &str, unit: &LibraryUnitRef<'a>) {
+        self.library_name = library_name.to_owned();
+        self.scopes = vec![Scope::default()];
+        // LRM 13.2 Every design unit is assumed to contain the library clause "library std, work;"
+        self.declare_library("std");
+        self.declare_library("work");
+        self.unit = Some(unit_entity(unit));
+
+        // LRM 13.2 Every design unit is also assumed to contain the use clause "use std.standard.all;"
+        let standard = self
+            .root
+            .library("std")
+            .and_then(|library| library.get_primary("standard"));
+        self.complete = standard.is_some();
+        if let Some(standard) = standard {
+            for (key, visible) in self.select_all(&[Visible::Entity(unit_entity(standard))]) {
+                self.use_visible(key, vec![visible]);
+            }
+        }
+
+        // LRM 12.1 The declarative region of a secondary unit extends that of its primary unit
+        let primary = if unit.id.is_primary() {
+            None
+        } else {
+            self.root
+                .library(library_name)
+                .and_then(|library| library.get(&unit.id.primary_unit().key))
+        };
+
+        if let Some(primary) = primary {
+            self.recording = false;
+            self.resolve_context_clause(&primary.unit.context_clause);
+            self.recording = true;
+        }
+        self.resolve_context_clause(&unit.unit.context_clause);
+        self.push_scope();
+
+        if let Some(primary) = primary {
+            self.recording = false;
+            match primary.unit.library_unit {
+                LibraryUnit::EntityDeclaration { .. } => {
+                    let (generics, ports) = entity_interface(&primary.unit.library_unit);
+                    self.resolve_interface_list(generics, NamedEntityKind::Generic);
+                    self.resolve_interface_list(ports, NamedEntityKind::Port);
+                }
+                LibraryUnit::PackageDeclaration { ref decl, .. } => {
+                    self.resolve_declarations(decl);
+                }
+                _ => {}
+            }
+            self.recording = true;
+        }
+
+        self.resolve_library_unit(unit, primary);
+        while !self.scopes.is_empty() {
+            self.pop_scope();
+        }
+    }
+
+    fn resolve_library_unit(
+        &mut self,
+        unit: &LibraryUnitRef<'a>,
+        primary: Option<&LibraryUnitRef<'a>>,
+    ) {
+        match unit.unit.library_unit {
+            LibraryUnit::PackageBody { ref ident, .. } => {
+                if let Some(primary) = primary {
+                    self.add_reference(&ident.pos, &[Visible::Entity(unit_entity(primary))]);
+                }
+            }
+            _ => {
+                self.declarations.push(unit_entity(unit));
+            }
+        }
+
+        match unit.unit.library_unit {
+            LibraryUnit::EntityDeclaration { .. } => {
+                let (generics, ports) = entity_interface(&unit.unit.library_unit);
+                self.resolve_interface_list(generics, NamedEntityKind::Generic);
+                self.resolve_interface_list(ports, NamedEntityKind::Port);
+            }
+            LibraryUnit::ArchitectureBody {
+                ref entity_name,
+                ref decl,
+                ref statements,
+                ..
+            } => {
+                if let Some(primary) = primary {
+                    self.add_reference(&entity_name.pos, &[Visible::Entity(unit_entity(primary))]);
+                }
+                self.resolve_declarations(decl);
+                self.architecture = Some(unit_entity(unit));
+                self.resolve_concurrent_statements(statements);
+                self.architecture = None;
+            }
+            LibraryUnit::PackageDeclaration { ref decl, .. }
+            | LibraryUnit::PackageBody { ref decl, .. } => {
+                self.resolve_declarations(decl);
+            }
+            LibraryUnit::Configuration(ref config) => {
+                self.resolve_selected_name(&config.entity_name);
+                for item in config.decl.iter() {
+                    match item {
+                        ConfigurationDeclarativeItem::Use(ref use_clause) => {
+                            self.resolve_use_clause(use_clause)
+                        }
+                    }
+                }
+            }
+            LibraryUnit::PackageInstance(ref instance) => {
+                self.resolve_selected_name(&instance.package_name);
+                if let Some(ref generic_map) = instance.generic_map {
+                    self.resolve_association_list(generic_map, &[]);
+                }
+            }
+            LibraryUnit::ContextDeclaration(ref context) => {
+                self.resolve_context_clause(&context.items);
+            }
+        }
+    }
+
+    fn resolve_context_clause(&mut self, items: &'a [ContextItem]) {
+        for item in items {
+            match item {
+                ContextItem::Library(ref library_clause) => {
+                    for name in library_clause.name_list.iter() {
+                        self.declare_library(&symbol_key(&name.item));
+                    }
+                }
+                ContextItem::Use(ref use_clause) => {
+                    self.resolve_use_clause(use_clause);
+                }
+                ContextItem::Context(ref context_reference) => {
+                    for name in context_reference.name_list.iter() {
+                        let visible = self.resolve_name(&name.item, &name.pos);
+                        if visible.is_empty() {
+                            self.complete = false;
+                        }
+                        for entity in entities_of(&visible) {
+                            self.apply_context(&entity);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// LRM 13.4 A context reference is equivalent to the items of the context declaration
+    fn apply_context(&mut self, entity: &NamedEntity) {
+        let (library_name, items) = match self.declared.contexts.get(&entity.region_key()) {
+            Some(&(ref library_name, items)) => (library_name.clone(), items),
+            None => {
+                return;
+            }
+        };
+
+        let recording = self.recording;
+        let own_library_name = std::mem::replace(&mut self.library_name, library_name);
+        self.recording = false;
+        self.resolve_context_clause(items);
+        self.recording = recording;
+        self.library_name = own_library_name;
+    }
+
+    /// LRM 12.4 Use clauses
+    fn resolve_use_clause(&mut self, use_clause: &UseClause) {
+        for name in use_clause.name_list.iter() {
+            if let Name::Selected(ref prefix, ref suffix) = name.item {
+                let prefix_visible = self.resolve_name(&prefix.item, &prefix.pos);
+                if !self.within_project(&prefix_visible) {
+                    self.complete = false;
+                }
+                match suffix.item {
+                    Name::All => {
+                        for (key, visible) in self.select_all(&prefix_visible) {
+                            self.use_visible(key, vec![visible]);
+                        }
+                    }
+                    Name::Simple(ref symbol) => {
+                        let key = symbol_key(symbol);
+                        let visible = self.select(&prefix_visible, &key);
+                        self.add_reference(&suffix.pos, &visible);
+                        self.use_visible(key, visible);
+                    }
+                    _ => {}
+                }
+            } else {
+                self.resolve_name(&name.item, &name.pos);
+            }
+        }
+    }
+
+    /// Libraries outside of the project are visible but their contents are unknown
+    fn within_project(&self, visible: &[Visible]) -> bool {
+        !visible.is_empty()
+            && visible.iter().all(|visible| match visible {
+                Visible::Library(ref library_name) => self.root.library(library_name).is_some(),
+                Visible::Entity(..) => true,
+            })
+    }
+
+    /// The packages of the project which declare the designator
+    fn packages_declaring(&self, key: &str) -> Vec<(&str, &LibraryUnitRef<'a>, NamedEntity)> {
+        let mut packages = Vec::new();
+        for library in self.root.libraries() {
+            for unit in library.units() {
+                if let LibraryUnit::PackageDeclaration { .. } = unit.unit.library_unit {
+                    if let Some(entity) = self
+                        .region(&unit_entity(unit).region_key())
+                        .and_then(|region| region.get(key))
+                        .and_then(|entities| entities.first())
+                    {
+                        packages.push((library.name(), unit, entity.clone()));
+                    }
+                }
+            }
+        }
+        packages
+    }
+
+    /// LRM 12.3 A simple name must denote a visible declaration
+    fn undeclared(&mut self, symbol: &Symbol, pos: &SrcPos) {
+        if !(self.recording && self.reporting && self.complete) {
+            return;
+        }
+        let designator = symbol.name_utf8();
+        let key = symbol_key(symbol);
+        if IMPLICIT_OPERATIONS.contains(&key.as_str()) {
+            return;
+        }
+
+        let packages = self.packages_declaring(&key);
+        let message = if packages.is_empty() {
+            let message =
+                error(pos, &format!("No declaration of '{}'", designator)).with_code("undeclared");
+            let edit = self.architecture.as_ref().and_then(|architecture| {
+                quick_fix::declare_signal(&architecture.pos, &designator, &self.guess_subtype(pos))
+            });
+            match edit {
+                Some(edit) => {
+                    self.undeclared_signals.push((key, edit.clone()));
+                    message.with_fix(&format!("Declare signal '{}'", designator), vec![edit])
+                }
+                None => message,
+            }
+        } else {
+            let mut message = error(
+                pos,
+                &format!("No declaration of '{}' is visible", designator),
+            )
+            .with_code("not-visible");
+            for (library_name, package, declaration) in packages {
+                let package_name = package.ident().item.name_utf8();
+                message = message.related(
+                    &declaration.pos,
+                    &format!("Declared within package {}.{}", library_name, package_name),
+                );
+                let use_library_name = if library_name == self.library_name {
+                    "work"
+                } else {
+                    library_name
+                };
+                let with_library_clause =
+                    !self
+                        .lookup(use_library_name)
+                        .iter()
+                        .any(|visible| match visible {
+                            Visible::Library(..) => true,
+                            Visible::Entity(..) => false,
+                        });
+                let edit = self.unit.as_ref().and_then(|unit| {
+                    quick_fix::insert_use_clause(
+                        &unit.pos,
+                        use_library_name,
+                        &package_name,
+                        with_library_clause,
+                    )
+                });
+                if let Some(edit) = edit {
+                    message = message.with_fix(
+                        &format!("Add use {}.{}.all", use_library_name, package_name),
+                        vec![edit],
+                    );
+                }
+            }
+            message
+        };
+        self.messages.push(message);
+    }
+
+    /// The subtype of the formal the name is associated with, of the object assigned to
+    /// or from a name or a visible default
+    fn guess_subtype(&self, pos: &SrcPos) -> String {
+        let formal = self.formal.as_ref().and_then(quick_fix::subtype_of);
+        let assigned = formal.or_else(|| {
+            quick_fix::assigned_with(pos).and_then(|name| {
+                entities_of(&self.lookup(&name.to_lowercase()))
+                    .first()
+                    .and_then(quick_fix::subtype_of)
+            })
+        });
+        match assigned {
+            Some(subtype) => subtype,
+            None if !self.lookup("std_logic").is_empty() => "std_logic".to_owned(),
+            None => "bit".to_owned(),
+        }
+    }
+
+    /// LRM 11.3 A process with a sensitivity list should be sensitive to all signals it reads
+    /// Signals which are only read on a clock edge need not be part of the list
+    fn missing_sensitivity(
+        &mut self,
+        sensitivity_list: &[NamedEntity],
+        reads: Vec<(SrcPos, NamedEntity)>,
+        last: &SrcPos,
+    ) {
+        let mut reported: Vec<NamedEntity> = Vec::new();
+        for (pos, signal) in reads {
+            if sensitivity_list.contains(&signal) || reported.contains(&signal) {
+                continue;
+            }
+            let insert = last.source.pos(last.start + last.length, 0);
+            self.messages.push(
+                warning(
+                    &pos,
+                    &format!(
+                        "'{}' is read by the process but missing from its sensitivity list",
+                        signal.designator
+                    ),
+                )
+                .with_code("incomplete-sensitivity-list")
+                .with_fix(
+                    &format!("Add '{}' to the sensitivity list", signal.designator),
+                    vec![(insert, format!(", {}", signal.designator))],
+                ),
+            );
+            reported.push(signal);
+        }
+    }
+
+    fn resolve_selected_name(&mut self, name: &[Ident]) -> Vec<Visible> {
+        let mut visible = Vec::new();
+        for (i, ident) in name.iter().enumerate() {
+            let key = symbol_key(&ident.item);
+            visible = if i == 0 {
+                self.lookup(&key)
+            } else {
+                self.select(&visible, &key)
+            };
+            self.add_reference(&ident.pos, &visible);
+        }
+        visible
+    }
+
+    /// Returns the named entities denoted by the name when it is a simple or selected name
+    fn resolve_name(&mut self, name: &Name, pos: &SrcPos) -> Vec<Visible> {
+        match name {
+            Name::Simple(ref symbol) => {
+                let visible = self.lookup(&symbol_key(symbol));
+                if visible.is_empty() {
+                    self.undeclared(symbol, pos);
+                }
+                self.add_reference(pos, &visible);
+                visible
+            }
+            Name::OperatorSymbol(ref op) => {
+                let visible = self.lookup(&format!("\"{}\"", op.to_lowercase()));
+                self.add_reference(pos, &visible);
+                visible
+            }
+            Name::Selected(ref prefix, ref suffix) => {
+                let prefix_visible = self.resolve_name(&prefix.item, &prefix.pos);
+                let key = match suffix.item {
+                    Name::Simple(ref symbol) => symbol_key(symbol),
+                    Name::OperatorSymbol(ref op) => format!("\"{}\"", op.to_lowercase()),
+                    _ => {
+                        return Vec::new();
+                    }
+                };
+                let visible = self.select(&prefix_visible, &key);
+                self.add_reference(&suffix.pos, &visible);
+                visible
+            }
+            Name::Indexed(ref prefix, ref indexes) => {
+                self.resolve_name(&prefix.item, &prefix.pos);
+                for index in indexes.iter() {
+                    self.resolve_expression(index);
+                }
+                Vec::new()
+            }
+            Name::Slice(ref prefix, ref discrete_range) => {
+                self.resolve_name(&prefix.item, &prefix.pos);
+                self.resolve_discrete_range(discrete_range);
+                Vec::new()
+            }
+            Name::Attribute(ref attribute) => {
+                self.resolve_attribute_name(attribute);
+                Vec::new()
+            }
+            Name::FunctionCall(ref call) => {
+                self.resolve_function_call(call);
+                Vec::new()
+            }
+            Name::CharacterLiteral(..) | Name::All => Vec::new(),
+        }
+    }
+
+    fn resolve_attribute_name(&mut self, attribute: &AttributeName) {
+        self.resolve_name(&attribute.name.item, &attribute.name.pos);
+        if let Some(ref signature) = attribute.signature {
+            self.resolve_signature(signature);
+        }
+        if let Some(ref expr) = attribute.expr {
+            self.resolve_expression(expr);
+        }
+    }
+
+    fn resolve_function_call(&mut self, call: &FunctionCall) {
+        self.resolve_name(&call.name.item, &call.name.pos);
+        self.resolve_association_list(&call.parameters, &[]);
+    }
+
+    fn resolve_signature(&mut self, signature: &Signature) {
+        match signature {
+            Signature::Function(ref args, ref return_type) => {
+                for arg in args.iter() {
+                    self.resolve_selected_name(arg);
+                }
+                self.resolve_selected_name(return_type);
+            }
+            Signature::Procedure(ref args) => {
+                for arg in args.iter() {
+                    self.resolve_selected_name(arg);
+                }
+            }
+        }
+    }
+
+    /// Formal names are resolved within the interface of the instantiated units
+    /// LRM 6.5.6.3 The actual of an out or buffer port is written and that of an inout
+    /// port is both read and written
+    fn resolve_association_list(&mut self, elements: &[AssociationElement], units: &[Visible]) {
+        for element in elements.iter() {
+            let formal = match element.formal {
+                Some(ref formal) => self.resolve_formal(formal, units),
+                None => None,
+            };
+            let mode = formal.as_ref().and_then(|formal| self.port_mode(formal));
+            if let ActualPart::Expression(ref expr) = element.actual.item {
+                if let Expression::Name(ref name) = expr {
+                    if let Name::Simple(..) = **name {
+                        self.formal = formal;
+                    }
+                }
+                match (expr, mode) {
+                    (Expression::Name(ref name), Some(Mode::Out))
+                    | (Expression::Name(ref name), Some(Mode::Buffer)) => {
+                        self.resolve_target_name(name, &element.actual.pos);
+                    }
+                    (Expression::Name(ref name), Some(Mode::InOut)) => {
+                        self.updating = true;
+                        self.resolve_target_name(name, &element.actual.pos);
+                        self.updating = false;
+                    }
+                    _ => self.resolve_expression_at(expr, &element.actual.pos),
+                }
+                self.formal = None;
+            }
+        }
+    }
+
+    fn port_mode(&self, port: &NamedEntity) -> Option<Mode> {
+        if port.kind != NamedEntityKind::Port {
+            return None;
+        }
+        let key = port.region_key();
+        self.modes
+            .get(&key)
+            .or_else(|| self.declared.modes.get(&key))
+            .cloned()
+    }
+
+    /// The formal is resolved within the units and returned when it is a simple name
+    fn resolve_formal(&mut self, formal: &WithPos<Name>, units: &[Visible]) -> Option<NamedEntity> {
+        match formal.item {
+            Name::Simple(ref symbol) => {
+                let visible = self.select(units, &symbol_key(symbol));
+                self.add_reference(&formal.pos, &visible);
+                return entities_of(&visible).into_iter().next();
+            }
+            Name::Indexed(ref prefix, ref indexes) => {
+                self.resolve_formal(prefix, units);
+                for index in indexes.iter() {
+                    self.resolve_expression(index);
+                }
+            }
+            Name::Slice(ref prefix, ref discrete_range) => {
+                self.resolve_formal(prefix, units);
+                self.resolve_discrete_range(discrete_range);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn resolve_expression(&mut self, expr: &WithPos<Expression>) {
+        self.resolve_expression_at(&expr.item, &expr.pos);
+    }
+
+    fn resolve_expression_at(&mut self, expr: &Expression, pos: &SrcPos) {
+        match expr {
+            Expression::Binary(_, ref left, ref right) => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expression::Unary(_, ref operand) => {
+                self.resolve_expression(operand);
+            }
+            Expression::Aggregate(ref associations) => {
+                self.resolve_element_associations(associations);
+            }
+            Expression::Qualified(ref qualified) => {
+                self.resolve_qualified_expression(qualified);
+            }
+            Expression::Name(ref name) => {
+                self.resolve_name(name, pos);
+            }
+            Expression::Literal(..) => {}
+            Expression::New(ref allocator) => match allocator.item {
+                Allocator::Qualified(ref qualified) => {
+                    self.resolve_qualified_expression(qualified);
+                }
+                Allocator::Subtype(ref subtype_indication) => {
+                    self.resolve_subtype_indication(subtype_indication);
+                }
+            },
+        }
+    }
+
+    fn resolve_qualified_expression(&mut self, qualified: &QualifiedExpression) {
+        self.resolve_name(&qualified.name.item, &qualified.name.pos);
+        self.resolve_expression(&qualified.expr);
+    }
+
+    fn resolve_element_associations(&mut self, associations: &[ElementAssociation]) {
+        for association in associations.iter() {
+            match association {
+                ElementAssociation::Positional(ref expr) => {
+                    self.resolve_expression(expr);
+                }
+                ElementAssociation::Named(ref choices, ref expr) => {
+                    let reporting = std::mem::replace(&mut self.reporting, false);
+                    self.resolve_choices(choices);
+                    self.reporting = reporting;
+                    self.resolve_expression(expr);
+                }
+            }
+        }
+    }
+
+    fn resolve_choices(&mut self, choices: &[Choice]) {
+        for choice in choices.iter() {
+            match choice {
+                Choice::Expression(ref expr) => self.resolve_expression(expr),
+                Choice::DiscreteRange(ref discrete_range) => {
+                    self.resolve_discrete_range(discrete_range)
+                }
+                Choice::Others => {}
+            }
+        }
+    }
+
+    fn resolve_range(&mut self, range: &Range) {
+        match range {
+            Range::Range(ref constraint) => {
+                self.resolve_expression(&constraint.left_expr);
+                self.resolve_expression(&constraint.right_expr);
+            }
+            Range::Attribute(ref attribute) => self.resolve_attribute_name(attribute),
+        }
+    }
+
+    fn resolve_discrete_range(&mut self, discrete_range: &DiscreteRange) {
+        match discrete_range {
+            DiscreteRange::Discrete(ref type_mark, ref range) => {
+                self.resolve_selected_name(type_mark);
+                if let Some(ref range) = range {
+                    self.resolve_range(range);
+                }
+            }
+            DiscreteRange::Range(ref range) => self.resolve_range(range),
+        }
+    }
+
+    /// Returns the named entities denoted by the type mark
+    fn resolve_subtype_indication(
+        &mut self,
+        subtype_indication: &SubtypeIndication,
+    ) -> Vec<Visible> {
+        self.resolve_resolution_indication(&subtype_indication.resolution);
+        let visible = self.resolve_selected_name(&subtype_indication.type_mark);
+        if let Some(ref constraint) = subtype_indication.constraint {
+            self.resolve_subtype_constraint(constraint);
+        }
+        visible
+    }
+
+    fn resolve_resolution_indication(&mut self, resolution: &ResolutionIndication) {
+        match resolution {
+            ResolutionIndication::FunctionName(ref name)
+            | ResolutionIndication::ArrayElement(ref name) => {
+                self.resolve_selected_name(name);
+            }
+            ResolutionIndication::Record(ref elements) => {
+                for element in elements.iter() {
+                    self.resolve_resolution_indication(&element.resolution);
+                }
+            }
+            ResolutionIndication::Unresolved => {}
+        }
+    }
+
+    fn resolve_subtype_constraint(&mut self, constraint: &SubtypeConstraint) {
+        match constraint {
+            SubtypeConstraint::Range(ref range) => self.resolve_range(range),
+            SubtypeConstraint::Array(ref discrete_ranges, ref element_constraint) => {
+                for discrete_range in discrete_ranges.iter() {
+                    self.resolve_discrete_range(discrete_range);
+                }
+                if let Some(ref element_constraint) = element_constraint {
+                    self.resolve_subtype_constraint(element_constraint);
+                }
+            }
+            SubtypeConstraint::Record(ref elements) => {
+                for element in elements.iter() {
+                    self.resolve_subtype_constraint(&element.constraint);
+                }
+            }
+        }
+    }
+
+    fn resolve_interface_list(
+        &mut self,
+        list: &[InterfaceDeclaration],
+        object_kind: NamedEntityKind,
+    ) {
+        for decl in list.iter() {
+            match decl {
+                InterfaceDeclaration::Object(ref object) => {
+                    self.resolve_subtype_indication(&object.subtype_indication);
+                    if let Some(ref expr) = object.expression {
+                        self.resolve_expression(expr);
+                    }
+                }
+                InterfaceDeclaration::File(ref file) => {
+                    self.resolve_subtype_indication(&file.subtype_indication);
+                }
+                InterfaceDeclaration::Type(..) => {}
+                InterfaceDeclaration::Subprogram(ref spec, ref default) => {
+                    self.push_scope();
+                    self.resolve_subprogram_specification(spec);
+                    self.pop_scope();
+                    if let Some(SubprogramDefault::Name(ref name)) = default {
+                        self.resolve_selected_name(name);
+                    }
+                }
+            }
+            self.declare(interface_entity(decl, object_kind));
+        }
+    }
+
+    fn resolve_subprogram_specification(&mut self, spec: &SubprogramDeclaration) {
+        match spec {
+            SubprogramDeclaration::Procedure(ref procedure) => {
+                self.resolve_interface_list(&procedure.parameter_list, NamedEntityKind::Parameter);
+            }
+            SubprogramDeclaration::Function(ref function) => {
+                self.resolve_interface_list(&function.parameter_list, NamedEntityKind::Parameter);
+                self.resolve_selected_name(&function.return_type);
+            }
+        }
+    }
+
+    fn resolve_declarations(&mut self, decls: &[Declaration]) {
+        for decl in decls.iter() {
+            self.resolve_declaration(decl);
+        }
+    }
+
+    /// LRM 4.3 A subprogram body completes a declaration of the same declarative region
+    fn completed_declarations(&self, entity: &NamedEntity) -> Vec<Visible> {
+        self.scopes
+            .last()
+            .and_then(|scope| scope.declared.get(&entity.key()))
+            .map(|visible| {
+                visible
+                    .iter()
+                    .filter(|visible| match visible {
+                        Visible::Entity(ref declared) => declared.kind == entity.kind,
+                        Visible::Library(..) => false,
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn resolve_declaration(&mut self, decl: &Declaration) {
+        // Names are declared first to allow recursive subprograms and access types
+        for entity in declared_entities(decl) {
+            if let Declaration::SubprogramBody(..) = decl {
+                let completed = self.completed_declarations(&entity);
+                if !completed.is_empty() {
+                    self.add_reference(&entity.pos, &completed);
+                    continue;
+                }
+            }
+            self.declare(entity);
+        }
+
+        match decl {
+            Declaration::Object(ref object) => {
+                self.resolve_subtype_indication(&object.subtype_indication);
+                if let Some(ref expr) = object.expression {
+                    self.resolve_expression(expr);
+                }
+            }
+            Declaration::File(ref file) => {
+                self.resolve_subtype_indication(&file.subtype_indication);
+                if let Some(ref expr) = file.open_info {
+                    self.resolve_expression(expr);
+                }
+                if let Some(ref expr) = file.file_name {
+                    self.resolve_expression(expr);
+                }
+            }
+            Declaration::Type(ref typ) => {
+                let supertypes = self.resolve_type_definition(&typ.def);
+                if self.recording {
+                    if let Some(derived) = declared_entities(decl).into_iter().next() {
+                        for (supertype, relation) in supertypes {
+                            let relation = (derived.clone(), supertype, relation);
+                            if !self.type_relations.contains(&relation) {
+                                self.type_relations.push(relation);
+                            }
+                        }
+                    }
+                }
+            }
+            Declaration::Component(ref component) => {
+                let entity = NamedEntity::from_ident(&component.ident, NamedEntityKind::Component);
+                let bound = self
+                    .root
+                    .library(&self.library_name)
+                    .and_then(|library| library.get_primary(&entity.key()))
+                    .filter(|unit| {
+                        matches!(
+                            unit.unit.library_unit,
+                            LibraryUnit::EntityDeclaration { .. }
+                        )
+                    });
+                if let Some(bound) = bound {
+                    self.bindings
+                        .insert(entity.region_key(), unit_entity(bound));
+                }
+                self.regions.insert(
+                    entity.region_key(),
+                    interface_region(&component.generic_list, &component.port_list),
+                );
+                add_port_modes(&mut self.modes, &component.port_list);
+                self.push_scope();
+                self.resolve_interface_list(&component.generic_list, NamedEntityKind::Generic);
+                self.resolve_interface_list(&component.port_list, NamedEntityKind::Port);
+                self.pop_scope();
+            }
+            Declaration::Attribute(Attribute::Declaration(ref attribute)) => {
+                self.resolve_selected_name(&attribute.type_mark);
+            }
+            Declaration::Attribute(Attribute::Specification(ref spec)) => {
+                let visible = self.lookup(&symbol_key(&spec.ident.item));
+                self.add_reference(&spec.ident.pos, &visible);
+                if let EntityName::Name(ref tag) = spec.entity_name {
+                    if let Designator::Identifier(ref symbol) = tag.designator.item {
+                        let visible = self.lookup(&symbol_key(symbol));
+                        self.add_reference(&tag.designator.pos, &visible);
+                    }
+                    if let Some(ref signature) = tag.signature {
+                        self.resolve_signature(signature);
+                    }
+                }
+                self.resolve_expression(&spec.expr);
+            }
+            Declaration::Alias(ref alias) => {
+                if let Some(ref subtype_indication) = alias.subtype_indication {
+                    self.resolve_subtype_indication(subtype_indication);
+                }
+                self.resolve_name(&alias.name.item, &alias.name.pos);
+                if let Some(ref signature) = alias.signature {
+                    self.resolve_signature(signature);
+                }
+            }
+            Declaration::SubprogramDeclaration(ref spec) => {
+                self.push_scope();
+                self.resolve_subprogram_specification(spec);
+                self.pop_scope();
+            }
+            Declaration::SubprogramBody(ref body) => {
+                self.push_scope();
+                self.resolve_subprogram_specification(&body.specification);
+                self.resolve_declarations(&body.declarations);
+                self.resolve_sequential_statements(&body.statements);
+                self.pop_scope();
+            }
+            Declaration::Use(ref use_clause) => {
+                self.resolve_use_clause(use_clause);
+            }
+            Declaration::Package(ref instance) => {
+                let visible = self.resolve_selected_name(&instance.package_name);
+                if let Some(region) = entities_of(&visible)
+                    .first()
+                    .and_then(|package| self.region(&package.region_key()))
+                {
+                    let region = region.clone();
+                    let entity =
+                        NamedEntity::from_ident(&instance.ident, NamedEntityKind::PackageInstance);
+                    self.regions.insert(entity.region_key(), region);
+                }
+                if let Some(ref generic_map) = instance.generic_map {
+                    self.resolve_association_list(generic_map, &[]);
+                }
+            }
+        }
+    }
+
+    /// Returns the types the definition is derived from
+    fn resolve_type_definition(
+        &mut self,
+        def: &TypeDefinition,
+    ) -> Vec<(NamedEntity, TypeRelation)> {
+        let mut supertypes = Vec::new();
+        let mut derive = |visible: Vec<Visible>, relation| {
+            for entity in entities_of(&visible) {
+                if entity.kind == NamedEntityKind::Type || entity.kind == NamedEntityKind::Subtype {
+                    supertypes.push((entity, relation));
+                }
+            }
+        };
+        match def {
+            TypeDefinition::Enumeration(..) | TypeDefinition::Incomplete => {}
+            TypeDefinition::Integer(ref range) => self.resolve_range(range),
+            TypeDefinition::Physical(ref physical) => self.resolve_range(&physical.range),
+            TypeDefinition::Array(ref indexes, ref element_subtype) => {
+                for index in indexes.iter() {
+                    match index {
+                        ArrayIndex::IndexSubtypeDefintion(ref type_mark) => {
+                            self.resolve_selected_name(type_mark);
+                        }
+                        ArrayIndex::Discrete(ref discrete_range) => {
+                            self.resolve_discrete_range(discrete_range);
+                        }
+                    }
+                }
+                derive(
+                    self.resolve_subtype_indication(element_subtype),
+                    TypeRelation::Element,
+                );
+            }
+            TypeDefinition::Record(ref elements) => {
+                for element in elements.iter() {
+                    derive(
+                        self.resolve_subtype_indication(&element.subtype),
+                        TypeRelation::Element,
+                    );
+                }
+            }
+            TypeDefinition::Access(ref subtype_indication) => {
+                self.resolve_subtype_indication(subtype_indication);
+            }
+            TypeDefinition::Subtype(ref subtype_indication) => {
+                let relation = match subtype_indication.resolution {
+                    ResolutionIndication::Unresolved => TypeRelation::Subtype,
+                    _ => TypeRelation::Resolved,
+                };
+                derive(
+                    self.resolve_subtype_indication(subtype_indication),
+                    relation,
+                );
+            }
+            TypeDefinition::File(ref type_mark) => {
+                self.resolve_selected_name(type_mark);
+            }
+            TypeDefinition::Protected(ref protected) => {
+                self.push_scope();
+                for item in protected.items.iter() {
+                    match item {
+                        ProtectedTypeDeclarativeItem::Subprogram(ref spec) => {
+                            self.declare(subprogram_entity(spec));
+                            self.push_scope();
+                            self.resolve_subprogram_specification(spec);
+                            self.pop_scope();
+                        }
+                    }
+                }
+                self.pop_scope();
+            }
+            TypeDefinition::ProtectedBody(ref body) => {
+                self.push_scope();
+                self.resolve_declarations(&body.decl);
+                self.pop_scope();
+            }
+        }
+        supertypes
+    }
+
+    fn resolve_conditionals<T>(
+        &mut self,
+        conditionals: &Conditionals<T>,
+        resolve_item: fn(&mut Self, &T),
+    ) {
+        for conditional in conditionals.conditionals.iter() {
+            self.resolve_expression(&conditional.condition);
+            let clocked = self.clocked || is_clock_edge(&conditional.condition.item);
+            let clocked = std::mem::replace(&mut self.clocked, clocked);
+            resolve_item(self, &conditional.item);
+            self.clocked = clocked;
+        }
+        if let Some(ref else_item) = conditionals.else_item {
+            resolve_item(self, else_item);
+        }
+    }
+
+    fn resolve_selection<T>(&mut self, selection: &Selection<T>, resolve_item: fn(&mut Self, &T)) {
+        self.resolve_expression(&selection.expression);
+        for alternative in selection.alternatives.iter() {
+            self.resolve_choices(&alternative.choices);
+            resolve_item(self, &alternative.item);
+        }
+    }
+
+    fn resolve_assignment_rhs<T>(
+        &mut self,
+        rhs: &AssignmentRightHand<T>,
+        resolve_item: fn(&mut Self, &T),
+    ) {
+        match rhs {
+            AssignmentRightHand::Simple(ref item) => resolve_item(self, item),
+            AssignmentRightHand::Conditional(ref conditionals) => {
+                self.resolve_conditionals(conditionals, resolve_item)
+            }
+            AssignmentRightHand::Selected(ref selection) => {
+                self.resolve_selection(selection, resolve_item)
+            }
+        }
+    }
+
+    fn resolve_waveform(&mut self, waveform: &Waveform) {
+        if let Waveform::Elements(ref elements) = waveform {
+            for element in elements.iter() {
+                self.resolve_expression(&element.value);
+                if let Some(ref after) = element.after {
+                    self.resolve_expression(after);
+                }
+            }
+        }
+    }
+
+    fn resolve_target(&mut self, target: &WithPos<Target>) {
+        match target.item {
+            Target::Name(ref name) => {
+                self.resolve_target_name(name, &target.pos);
+            }
+            Target::Aggregate(ref associations) => {
+                let writing = std::mem::replace(&mut self.writing, true);
+                self.resolve_element_associations(associations);
+                self.writing = writing;
+            }
+        }
+    }
+
+    /// The indexes of an assigned name are read while the rest of it is written
+    fn resolve_target_name(&mut self, name: &Name, pos: &SrcPos) {
+        match name {
+            Name::Indexed(ref prefix, ref indexes) => {
+                self.resolve_target_name(&prefix.item, &prefix.pos);
+                for index in indexes.iter() {
+                    self.resolve_expression(index);
+                }
+            }
+            Name::Slice(ref prefix, ref discrete_range) => {
+                self.resolve_target_name(&prefix.item, &prefix.pos);
+                self.resolve_discrete_range(discrete_range);
+            }
+            // Indexed names are parsed as function calls
+            Name::FunctionCall(ref call) => {
+                self.resolve_target_name(&call.name.item, &call.name.pos);
+                self.resolve_association_list(&call.parameters, &[]);
+            }
+            _ => {
+                let writing = std::mem::replace(&mut self.writing, true);
+                self.resolve_name(name, pos);
+                self.writing = writing;
+            }
+        }
+    }
+
+    fn resolve_delay_mechanism(&mut self, delay_mechanism: &Option<DelayMechanism>) {
+        if let Some(DelayMechanism::Inertial {
+            reject: Some(ref reject),
+        }) = delay_mechanism
+        {
+            self.resolve_expression(reject);
+        }
+    }
+
+    fn resolve_names(&mut self, names: &[WithPos<Name>]) {
+        for name in names.iter() {
+            self.resolve_name(&name.item, &name.pos);
+        }
+    }
+
+    fn resolve_optional_expression(&mut self, expr: &Option<WithPos<Expression>>) {
+        if let Some(ref expr) = expr {
+            self.resolve_expression(expr);
+        }
+    }
+
+    fn resolve_loop_label(&mut self, label: &Option<Ident>) {
+        if let Some(ref label) = label {
+            let visible = self.lookup(&symbol_key(&label.item));
+            self.add_reference(&label.pos, &visible);
+        }
+    }
+
+    fn resolve_sequential_statements(&mut self, statements: &[LabeledSequentialStatement]) {
+        for statement in statements.iter() {
+            if let Some(ref label) = statement.label {
+                self.declare(NamedEntity::from_ident(label, NamedEntityKind::Label));
+            }
+            self.resolve_sequential_statement(&statement.statement);
+        }
+    }
+
+    fn resolve_sequential_statement(&mut self, statement: &SequentialStatement) {
+        match statement {
+            SequentialStatement::Wait(ref wait) => {
+                self.resolve_names(&wait.sensitivity_clause);
+                self.resolve_optional_expression(&wait.condition_clause);
+                self.resolve_optional_expression(&wait.timeout_clause);
+            }
+            SequentialStatement::Assert(ref assert) => self.resolve_assert(assert),
+            SequentialStatement::Report(ref report) => {
+                self.resolve_expression(&report.report);
+                self.resolve_optional_expression(&report.severity);
+            }
+            SequentialStatement::VariableAssignment(ref assignment) => {
+                self.resolve_target(&assignment.target);
+                self.resolve_assignment_rhs(&assignment.rhs, Self::resolve_expression);
+            }
+            SequentialStatement::SignalAssignment(ref assignment) => {
+                self.resolve_target(&assignment.target);
+                self.resolve_delay_mechanism(&assignment.delay_mechanism);
+                self.resolve_assignment_rhs(&assignment.rhs, Self::resolve_waveform);
+            }
+            SequentialStatement::ProcedureCall(ref call) => self.resolve_function_call(call),
+            SequentialStatement::If(ref conditionals) => {
+                self.resolve_conditionals(conditionals, |resolver, statements| {
+                    resolver.resolve_sequential_statements(statements)
+                });
+            }
+            SequentialStatement::Case(ref selection) => {
+                self.resolve_selection(selection, |resolver, statements| {
+                    resolver.resolve_sequential_statements(statements)
+                });
+            }
+            SequentialStatement::Loop(ref loop_statement) => {
+                self.push_scope();
+                match loop_statement.iteration_scheme {
+                    Some(IterationScheme::While(ref condition)) => {
+                        self.resolve_expression(condition)
+                    }
+                    Some(IterationScheme::For(ref index, ref discrete_range)) => {
+                        self.resolve_discrete_range(discrete_range);
+                        self.declare(NamedEntity::from_ident(
+                            index,
+                            NamedEntityKind::LoopParameter,
+                        ));
+                    }
+                    None => {}
+                }
+                self.resolve_sequential_statements(&loop_statement.statements);
+                self.pop_scope();
+            }
+            SequentialStatement::Next(ref next) => {
+                self.resolve_loop_label(&next.loop_label);
+                self.resolve_optional_expression(&next.condition);
+            }
+            SequentialStatement::Exit(ref exit) => {
+                self.resolve_loop_label(&exit.loop_label);
+                self.resolve_optional_expression(&exit.condition);
+            }
+            SequentialStatement::Return(ref ret) => {
+                self.resolve_optional_expression(&ret.expression);
+            }
+            SequentialStatement::Null => {}
+        }
+    }
+
+    fn resolve_assert(&mut self, assert: &AssertStatement) {
+        self.resolve_expression(&assert.condition);
+        self.resolve_optional_expression(&assert.report);
+        self.resolve_optional_expression(&assert.severity);
+    }
+
+    fn resolve_concurrent_statements(&mut self, statements: &[LabeledConcurrentStatement]) {
+        // LRM 11.1 Labels are declared in the enclosing declarative region
+        for statement in statements.iter() {
+            if let Some(ref label) = statement.label {
+                self.declare(NamedEntity::from_ident(label, NamedEntityKind::Label));
+            }
+        }
+
+        for statement in statements.iter() {
+            self.resolve_concurrent_statement(&statement.statement);
+        }
+    }
+
+    fn resolve_concurrent_statement(&mut self, statement: &ConcurrentStatement) {
+        match statement {
+            ConcurrentStatement::ProcedureCall(ref call) => {
+                self.resolve_function_call(&call.call);
+            }
+            ConcurrentStatement::Block(ref block) => {
+                self.resolve_optional_expression(&block.guard_condition);
+                self.push_scope();
+                self.resolve_declarations(&block.decl);
+                self.resolve_concurrent_statements(&block.statements);
+                self.pop_scope();
+            }
+            ConcurrentStatement::Process(ref process) => {
+                let first = self.references.len();
+                self.resolve_names(&process.sensitivity_list);
+                let sensitivity_list: Vec<NamedEntity> = self.references[first..]
+                    .iter()
+                    .flat_map(|reference| reference.entities.iter().cloned())
+                    .collect();
+                let is_all = process
+                    .sensitivity_list
+                    .iter()
+                    .any(|name| name.item == Name::All);
+
+                self.push_scope();
+                self.resolve_declarations(&process.decl);
+                if self.recording && !is_all {
+                    self.reads = process.sensitivity_list.last().map(|_| Vec::new());
+                }
+                self.resolve_sequential_statements(&process.statements);
+                self.pop_scope();
+
+                if let (Some(reads), Some(last)) =
+                    (self.reads.take(), process.sensitivity_list.last())
+                {
+                    self.missing_sensitivity(&sensitivity_list, reads, &last.pos);
+                }
+            }
+            ConcurrentStatement::Assert(ref assert) => {
+                self.resolve_assert(&assert.statement);
+            }
+            ConcurrentStatement::Assignment(ref assignment) => {
+                self.resolve_target(&assignment.target);
+                self.resolve_delay_mechanism(&assignment.delay_mechanism);
+                self.resolve_assignment_rhs(&assignment.rhs, Self::resolve_waveform);
+            }
+            ConcurrentStatement::Instance(ref instance) => {
+                self.resolve_instance(instance);
+            }
+            ConcurrentStatement::ForGenerate(ref generate) => {
+                self.resolve_discrete_range(&generate.discrete_range);
+                self.push_scope();
+                self.declare(NamedEntity::from_ident(
+                    &generate.index_name,
+                    NamedEntityKind::LoopParameter,
+                ));
+                self.resolve_generate_body(&generate.body);
+                self.pop_scope();
+            }
+            ConcurrentStatement::IfGenerate(ref conditionals) => {
+                self.resolve_conditionals(conditionals, Self::resolve_generate_body);
+            }
+        }
+    }
+
+    fn resolve_generate_body(&mut self, body: &GenerateBody) {
+        self.push_scope();
+        if let Some(ref label) = body.alternative_label {
+            self.declare(NamedEntity::from_ident(label, NamedEntityKind::Label));
+        }
+        if let Some(ref decl) = body.decl {
+            self.resolve_declarations(decl);
+        }
+        self.resolve_concurrent_statements(&body.statements);
+        self.pop_scope();
+    }
+
+    /// LRM 11.7 The formals of the generic and port maps belong to the instantiated unit
+    fn resolve_instance(&mut self, instance: &InstantiationStatement) {
+        let units = match instance.unit {
+            InstantiatedUnit::Component(ref name) => self.resolve_selected_name(name),
+            InstantiatedUnit::Entity(ref name, ref architecture) => {
+                let units = self.resolve_selected_name(name);
+                if let Some(ref architecture) = architecture {
+                    let key = symbol_key(&architecture.item);
+                    let mut visible = Vec::new();
+                    for entity in entities_of(&units) {
+                        if let Some(architectures) =
+                            self.declared.architectures.get(&entity.region_key())
+                        {
+                            visible.extend(
+                                architectures
+                                    .iter()
+                                    .filter(|arch| arch.key() == key)
+                                    .cloned()
+                                    .map(Visible::Entity),
+                            );
+                        }
+                    }
+                    self.add_reference(&architecture.pos, &visible);
+                }
+                units
+            }
+            InstantiatedUnit::Configuration(ref name) => {
+                self.resolve_selected_name(name);
+                Vec::new()
+            }
+        };
+
+        self.resolve_association_list(&instance.generic_map, &units);
+        self.resolve_association_list(&instance.port_map, &units);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn kind_from_str() {
+        assert_eq!("signal".parse(), Ok(NamedEntityKind::Signal));
+        assert_eq!(
+            "shared_variable".parse(),
+            Ok(NamedEntityKind::SharedVariable)
+        );
+        assert_eq!("loop_parameter".parse(), Ok(NamedEntityKind::LoopParameter));
+        assert!("SharedVariable".parse::<NamedEntityKind>().is_err());
+        assert_eq!(
+            NamedEntityKind::SharedVariable.to_string(),
+            "shared_variable"
+        );
+    }
+
+    /// The declaration denoted by an occurrence of a substring in a file
+    fn entities_of_substr(
+        resolution: &NameResolution,
+        project: &TempProject,
+        file_name: &str,
+        substr: &str,
+        occurence: usize,
+    ) -> Vec<(NamedEntityKind, usize)> {
+        let source = &project.project.get_file(file_name).unwrap().source;
+        let pos = source.substr_pos(substr, occurence);
+        resolution
+            .entities_at(file_name, pos.start)
+            .into_iter()
+            .map(|entity| (entity.kind, entity.pos.start))
+            .collect()
+    }
+
+    fn resolve(project: &TempProject) -> NameResolution {
+        let files = project.project.files();
+        let root = DesignRoot::new(&files);
+        NameResolution::new(&root)
+    }
+
+    fn start_of(project: &TempProject, file_name: &str, substr: &str, occurence: usize) -> usize {
+        project
+            .project
+            .get_file(file_name)
+            .unwrap()
+            .source
+            .substr_pos(substr, occurence)
+            .start
+    }
+
+    #[test]
+    fn signals_and_ports_of_architecture() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  port (clk : in bit);
+end entity;
+
+architecture rtl of ent is
+  signal foo : bit;
+begin
+  foo <= clk;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "foo", 2),
+            vec![(
+                NamedEntityKind::Signal,
+                start_of(&project, &file_name, "foo", 1)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "clk", 2),
+            vec![(
+                NamedEntityKind::Port,
+                start_of(&project, &file_name, "clk", 1)
+            )]
+        );
+        // A declaration denotes itself
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "foo", 1),
+            vec![(
+                NamedEntityKind::Signal,
+                start_of(&project, &file_name, "foo", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn package_declarations_across_libraries() {
+        let mut project = TempProject::new();
+        let pkg_file = project.add_file(
+            "lib1",
+            "pkg.vhd",
+            "
+package pkg is
+  constant width : natural := 8;
+end package;",
+        );
+        let ent_file = project.add_file(
+            "lib2",
+            "ent.vhd",
+            "
+library lib1;
+use lib1.pkg.all;
+
+entity ent is
+  generic (g1 : natural := width; g2 : natural := lib1.pkg.width);
+end entity;",
+        );
+        let resolution = resolve(&project);
+        let width = start_of(&project, &pkg_file, "width", 1);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &ent_file, "width", 1),
+            vec![(NamedEntityKind::Constant, width)]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &ent_file, "width", 2),
+            vec![(NamedEntityKind::Constant, width)]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &ent_file, "pkg", 2),
+            vec![(
+                NamedEntityKind::Package,
+                start_of(&project, &pkg_file, "pkg", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn entity_instantiation_and_formals() {
+        let mut project = TempProject::new();
+        let dut_file = project.add_file(
+            "lib",
+            "dut.vhd",
+            "
+entity dut is
+  generic (width : natural);
+  port (clk : in bit);
+end entity;
+
+architecture behav of dut is
+begin
+end architecture;",
+        );
+        let top_file = project.add_file(
+            "lib",
+            "top.vhd",
+            "
+entity top is
+end entity;
+
+architecture rtl of top is
+  signal clk : bit;
+begin
+  inst : entity work.dut(behav)
+    generic map (width => 8)
+    port map (clk => clk);
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "dut", 1),
+            vec![(
+                NamedEntityKind::Entity,
+                start_of(&project, &dut_file, "dut", 1)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "behav", 1),
+            vec![(
+                NamedEntityKind::Architecture,
+                start_of(&project, &dut_file, "behav", 1)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "width", 1),
+            vec![(
+                NamedEntityKind::Generic,
+                start_of(&project, &dut_file, "width", 1)
+            )]
+        );
+        // The formal denotes the port and the actual denotes the local signal
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "clk", 2),
+            vec![(
+                NamedEntityKind::Port,
+                start_of(&project, &dut_file, "clk", 1)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "clk", 3),
+            vec![(
+                NamedEntityKind::Signal,
+                start_of(&project, &top_file, "clk", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn component_instantiation() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "top.vhd",
+            "
+architecture rtl of top is
+  component comp is
+    port (clk : in bit);
+  end component;
+begin
+  inst : comp port map (clk => open);
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "comp", 4),
+            vec![(
+                NamedEntityKind::Component,
+                start_of(&project, &file_name, "comp", 2)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "clk", 2),
+            vec![(
+                NamedEntityKind::Port,
+                start_of(&project, &file_name, "clk", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn component_of_package_resolved_before_its_users() {
+        let mut project = TempProject::new();
+        let pkg_file = project.add_file(
+            "b_lib",
+            "pkg.vhd",
+            "
+package pkg is
+  component comp is
+    port (clk : in bit);
+  end component;
+end package;",
+        );
+        // The library of the architecture comes first by name
+        let top_file = project.add_file(
+            "a_lib",
+            "top.vhd",
+            "
+library b_lib;
+use b_lib.pkg.all;
+
+architecture rtl of top is
+begin
+  inst : comp port map (clk => open);
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &top_file, "clk", 1),
+            vec![(
+                NamedEntityKind::Port,
+                start_of(&project, &pkg_file, "clk", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn entity_name_of_architecture() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "dut.vhd",
+            "
+entity dut is
+end entity;
+
+architecture behav of dut is
+begin
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "dut", 2),
+            vec![(
+                NamedEntityKind::Entity,
+                start_of(&project, &file_name, "dut", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn supertypes_and_subtypes_of_types() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  type std_ulogic is ('U', '0', '1');
+  type std_ulogic_vector is array (natural range <>) of std_ulogic;
+  function resolved (s : std_ulogic_vector) return std_ulogic;
+  subtype std_logic is resolved std_ulogic;
+  subtype byte is std_ulogic_vector(7 downto 0);
+  type rec is record
+    valid : std_logic;
+    data : byte;
+    last : std_logic;
+  end record;
+end package;",
+        );
+        let resolution = resolve(&project);
+        let typ = |designator: &str| {
+            resolution.entities_at(&file_name, start_of(&project, &file_name, designator, 1))[0]
+                .clone()
+        };
+        let designators = |relations: Vec<(&NamedEntity, TypeRelation)>| {
+            relations
+                .into_iter()
+                .map(|(entity, relation)| (entity.designator.clone(), relation))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            designators(resolution.subtypes_of(&typ("std_ulogic"))),
+            vec![
+                ("std_ulogic_vector".to_owned(), TypeRelation::Element),
+                ("std_logic".to_owned(), TypeRelation::Resolved),
+            ]
+        );
+        assert_eq!(
+            designators(resolution.supertypes_of(&typ("std_logic"))),
+            vec![("std_ulogic".to_owned(), TypeRelation::Resolved)]
+        );
+        assert_eq!(
+            designators(resolution.supertypes_of(&typ("byte"))),
+            vec![("std_ulogic_vector".to_owned(), TypeRelation::Subtype)]
+        );
+        assert_eq!(
+            designators(resolution.supertypes_of(&typ("rec"))),
+            vec![
+                ("std_logic".to_owned(), TypeRelation::Element),
+                ("byte".to_owned(), TypeRelation::Element),
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_of_assignments_are_writes() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+architecture rtl of ent is
+  signal data : bit_vector(1 downto 0);
+  signal index : natural;
+begin
+  main : process
+    variable count : natural;
+  begin
+    data(index) <= data(0);
+    count := count + 1;
+    wait;
+  end process;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        let writes: Vec<(usize, bool)> = resolution
+            .references()
+            .iter()
+            .map(|reference| (reference.pos.start, reference.is_write))
+            .collect();
+        let start = |substr, occurence| start_of(&project, &file_name, substr, occurence);
+
+        assert!(writes.contains(&(start("data", 2), true)));
+        assert!(writes.contains(&(start("index", 2), false)));
+        assert!(writes.contains(&(start("data", 3), false)));
+        assert!(writes.contains(&(start("count", 2), true)));
+        assert!(writes.contains(&(start("count", 3), false)));
+    }
+
+    #[test]
+    fn actuals_of_ports_are_read_and_written_by_mode() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  port (d : in bit; q : out bit; io : inout bit);
+end entity;
+
+architecture rtl of ent is
+  signal from_d, to_q, via_io : bit;
+begin
+  inst : entity work.ent port map (d => from_d, q => to_q, io => via_io);
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        let usage: Vec<(usize, bool, bool)> = resolution
+            .references()
+            .iter()
+            .map(|reference| (reference.pos.start, reference.is_write, reference.is_read))
+            .collect();
+        let start = |substr, occurence| start_of(&project, &file_name, substr, occurence);
+
+        assert!(usage.contains(&(start("from_d", 2), false, true)));
+        assert!(usage.contains(&(start("to_q", 2), true, false)));
+        assert!(usage.contains(&(start("via_io", 2), true, true)));
+    }
+
+    #[test]
+    fn implementations_of_entity_and_component() {
+        let mut project = TempProject::new();
+        let comp_file = project.add_file(
+            "lib",
+            "comp.vhd",
+            "
+entity comp is
+end entity;
+
+architecture rtl of comp is
+begin
+end architecture;
+
+architecture sim of comp is
+begin
+end architecture;",
+        );
+        let top_file = project.add_file(
+            "lib",
+            "top.vhd",
+            "
+architecture rtl of top is
+  component comp is
+  end component;
+  component missing is
+  end component;
+begin
+  inst : comp;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        let starts = |entity: &NamedEntity| {
+            resolution
+                .implementations(entity)
+                .into_iter()
+                .map(|entity| (entity.kind, entity.pos.start))
+                .collect::<Vec<_>>()
+        };
+        let architectures = vec![
+            (
+                NamedEntityKind::Architecture,
+                start_of(&project, &comp_file, "rtl", 1),
+            ),
+            (
+                NamedEntityKind::Architecture,
+                start_of(&project, &comp_file, "sim", 1),
+            ),
+        ];
+
+        let entity = resolution.entities_at(&comp_file, start_of(&project, &comp_file, "comp", 1));
+        assert_eq!(starts(entity[0]), architectures);
+
+        let component =
+            resolution.entities_at(&top_file, start_of(&project, &top_file, "comp;", 1));
+        assert_eq!(component[0].kind, NamedEntityKind::Component);
+        let mut expected = vec![(
+            NamedEntityKind::Entity,
+            start_of(&project, &comp_file, "comp", 1),
+        )];
+        expected.extend(architectures);
+        assert_eq!(starts(component[0]), expected);
+
+        let missing =
+            resolution.entities_at(&top_file, start_of(&project, &top_file, "missing", 1));
+        assert_eq!(starts(missing[0]), vec![]);
+    }
+
+    #[test]
+    fn subprogram_body_completes_declaration() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+  function calc(arg : natural) return natural;
+end package;
+
+package body pkg is
+  function calc(arg : natural) return natural is
+  begin
+    return calc(arg - 1);
+  end function;
+end package body;",
+        );
+        let resolution = resolve(&project);
+        let calc = vec![(
+            NamedEntityKind::Function,
+            start_of(&project, &file_name, "calc", 1),
+        )];
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "calc", 2),
+            calc
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "calc", 3),
+            calc
+        );
+        // The parameter of the body is its own declaration
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "arg", 3),
+            vec![(
+                NamedEntityKind::Parameter,
+                start_of(&project, &file_name, "arg", 2)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "pkg", 2),
+            vec![(
+                NamedEntityKind::Package,
+                start_of(&project, &file_name, "pkg", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn inner_declarations_hide_outer() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+architecture rtl of ent is
+  signal foo : bit;
+begin
+  main : process
+    variable foo : bit;
+  begin
+    foo := '1';
+    for i in 0 to 1 loop
+      exit main when i = 1;
+    end loop;
+  end process;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "foo", 3),
+            vec![(
+                NamedEntityKind::Variable,
+                start_of(&project, &file_name, "foo", 2)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "i =", 1),
+            vec![(
+                NamedEntityKind::LoopParameter,
+                start_of(&project, &file_name, "i in", 1)
+            )]
+        );
+        assert_eq!(
+            entities_of_substr(&resolution, &project, &file_name, "main", 2),
+            vec![(
+                NamedEntityKind::Label,
+                start_of(&project, &file_name, "main", 1)
+            )]
+        );
+    }
+
+    #[test]
+    fn references_to_declaration() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+architecture rtl of ent is
+  signal foo, bar : bit;
+begin
+  foo <= bar;
+  bar <= foo;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        let foo = resolution
+            .declarations()
+            .iter()
+            .find(|entity| entity.designator == "foo")
+            .unwrap();
+
+        let starts: Vec<usize> = resolution
+            .references_to(foo)
+            .into_iter()
+            .map(|pos| pos.start)
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                start_of(&project, &file_name, "foo", 2),
+                start_of(&project, &file_name, "foo", 3)
+            ]
+        );
+    }
+
+    /// A minimal standard package which makes the project complete enough to report messages
+    fn add_standard(project: &mut TempProject) {
+        project.add_file(
+            "std",
+            "standard.vhd",
+            "
+package standard is
+  type boolean is (false, true);
+  type bit is ('0', '1');
+  type bit_vector is array (natural range <>) of bit;
+  type integer is range -2147483648 to 2147483647;
+  subtype natural is integer range 0 to integer'high;
+end package;",
+        );
+    }
+
+    fn messages_of(resolution: &NameResolution) -> Vec<(String, Option<String>)> {
+        resolution
+            .messages()
+            .iter()
+            .map(|message| (message.message.clone(), message.code.clone()))
+            .collect()
+    }
+
+    /// The contents of the file after applying the first fix of the message
+    fn fixed(project: &TempProject, file_name: &str, message: &Message) -> String {
+        let mut code = project
+            .project
+            .get_file(file_name)
+            .unwrap()
+            .source
+            .contents()
+            .unwrap()
+            .to_string();
+        let mut edits = message.fixes[0].edits.clone();
+        edits.sort_by_key(|(pos, _)| pos.start);
+        for (pos, text) in edits.iter().rev() {
+            code.replace_range(pos.start..pos.start + pos.length, text);
+        }
+        code
+    }
+
+    #[test]
+    fn no_messages_without_standard_package() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  missing <= '1';
+end architecture;",
+        );
+        assert_eq!(messages_of(&resolve(&project)), vec![]);
+    }
+
+    #[test]
+    fn undeclared_name_with_signal_declaration_fix() {
+        let mut project = TempProject::new();
+        add_standard(&mut project);
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  port (valid : in boolean);
+end entity;
+
+architecture rtl of ent is
+  type state_t is (idle, running);
+  signal state : state_t := idle;
+begin
+  missing <= valid;
+  state <= running when missing else idle;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        assert_eq!(
+            messages_of(&resolution),
+            vec![
+                (
+                    "No declaration of 'missing'".to_owned(),
+                    Some("undeclared".to_owned())
+                ),
+                (
+                    "No declaration of 'missing'".to_owned(),
+                    Some("undeclared".to_owned())
+                )
+            ]
+        );
+        let message = &resolution.messages()[0];
+        assert_eq!(message.fixes[0].title, "Declare signal 'missing'");
+        assert!(fixed(&project, &file_name, message).contains(
+            "
+  signal state : state_t := idle;
+  signal missing : boolean;
+begin"
+        ));
+    }
+
+    #[test]
+    fn signal_declarations_with_subtypes_of_formals() {
+        let mut project = TempProject::new();
+        add_standard(&mut project);
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity child is
+  port (
+    clk : in boolean;
+    data : out integer range 0 to 7);
+end entity;
+
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  inst0: entity work.child port map (clk => clk, data => data0);
+  inst1: entity work.child port map (clk => clk, data => data1);
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        let edits = resolution.signal_declarations(&file_name);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].1,
+            "  signal clk : boolean;
+  signal data0 : integer range 0 to 7;
+  signal data1 : integer range 0 to 7;
+"
+        );
+        assert_eq!(
+            resolution.signal_declarations("other.vhd"),
+            Vec::<(SrcPos, String)>::new()
+        );
+    }
+
+    #[test]
+    fn names_of_other_packages_with_use_clause_fix() {
+        let mut project = TempProject::new();
+        add_standard(&mut project);
+        project.add_file(
+            "other",
+            "pkg.vhd",
+            "
+package pkg is
+  constant width : natural := 8;
+end package;",
+        );
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  generic (depth : natural := width);
+end entity;",
+        );
+        let resolution = resolve(&project);
+        assert_eq!(
+            messages_of(&resolution),
+            vec![(
+                "No declaration of 'width' is visible".to_owned(),
+                Some("not-visible".to_owned())
+            )]
+        );
+        let message = &resolution.messages()[0];
+        assert_eq!(message.related.len(), 1);
+        assert_eq!(message.fixes[0].title, "Add use other.pkg.all");
+        assert_eq!(
+            fixed(&project, &file_name, message),
+            "
+library other;
+use other.pkg.all;
+entity ent is
+  generic (depth : natural := width);
+end entity;"
+        );
+    }
+
+    #[test]
+    fn no_messages_when_use_clause_is_outside_of_project() {
+        let mut project = TempProject::new();
+        add_standard(&mut project);
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity ent is
+  port (clk : in std_logic);
+end entity;",
+        );
+        assert_eq!(messages_of(&resolve(&project)), vec![]);
+    }
+
+    #[test]
+    fn signals_missing_from_sensitivity_list() {
+        let mut project = TempProject::new();
+        add_standard(&mut project);
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  port (clk, rst, d, en : in bit; q : out bit);
+end entity;
+
+architecture rtl of ent is
+  signal mem : bit_vector(0 to 1);
+begin
+  seq : process (clk, rst)
+  begin
+    if rst = '1' then
+      q <= '0';
+    elsif clk'event and clk = '1' then
+      q <= d;
+    end if;
+  end process;
+
+  comb : process (d)
+  begin
+    if en = '1' then
+      mem(0) <= d and en;
+    end if;
+  end process;
+
+  all_signals : process (all)
+  begin
+    mem(1) <= en;
+  end process;
+end architecture;",
+        );
+        let resolution = resolve(&project);
+        assert_eq!(
+            messages_of(&resolution),
+            vec![(
+                "'en' is read by the process but missing from its sensitivity list".to_owned(),
+                Some("incomplete-sensitivity-list".to_owned())
+            )]
+        );
+        let message = &resolution.messages()[0];
+        assert_eq!(message.fixes[0].title, "Add 'en' to the sensitivity list");
+        assert!(fixed(&project, &file_name, message).contains("comb : process (d, en)"));
+    }
+}
@@ -10,6 +10,7 @@ use ast::{
 };
 use expression::{parse_expression, parse_expression_initial_token};
 use message::{error, ParseResult};
+use smallvec::SmallVec;
 use source::WithPos;
 use subprogram::parse_signature;
 use tokenstream::TokenStream;
@@ -54,7 +55,7 @@ use tokenizer::{Kind::*, Token};
 /// actual_parameter_part ::= parameter_association_list
 
 pub fn parse_selected_name(stream: &mut TokenStream) -> ParseResult<SelectedName> {
-    let mut result = Vec::new();
+    let mut result = SelectedName::new();
     result.push(stream.expect_ident()?);
     loop {
         if None == stream.pop_if_kind(Dot)? {
@@ -78,7 +79,7 @@ pub fn to_selected_name(name: &WithPos<Name>) -> ParseResult<SelectedName> {
             }
             _ => Err(error(suffix.as_ref(), "Expected simple name")),
         },
-        Name::Simple(ref ident) => Ok(vec![WithPos {
+        Name::Simple(ref ident) => Ok(smallvec![WithPos {
             item: ident.clone(),
             pos: name.pos.clone(),
         }]),
@@ -205,7 +206,7 @@ fn parse_function_call(
     prefix: WithPos<Name>,
     first: AssociationElement,
 ) -> ParseResult<WithPos<Name>> {
-    let mut association_elements = Vec::new();
+    let mut association_elements = SmallVec::new();
     association_elements.push(first);
 
     loop {
@@ -261,6 +262,15 @@ fn parse_name_with_prefix_and_initial_token(
     token: Token,
     prefix: Option<WithPos<Name>>,
 ) -> ParseResult<WithPos<Name>> {
+    stream.nested(|stream| parse_name_and_suffixes(stream, token, prefix))
+}
+
+fn parse_name_and_suffixes(
+    stream: &mut TokenStream,
+    token: Token,
+    prefix: Option<WithPos<Name>>,
+) -> ParseResult<WithPos<Name>> {
+    stream.enter(&token)?;
     let mut name = {
         try_token_kind!(
             token,
@@ -347,7 +357,7 @@ fn parse_name_with_prefix_and_initial_token(
                                     }));
 
                                 name = WithPos {
-                                    item: Name::Slice(Box::new(name), discrete_range),
+                                    item: Name::Slice(Box::new(name), Box::new(discrete_range)),
                                     pos: pos,
                                 };
                                 break;
@@ -358,7 +368,7 @@ fn parse_name_with_prefix_and_initial_token(
                                     item: Name::FunctionCall(Box::new(
                                         FunctionCall {
                                             name: name,
-                                            parameters: vec![assoc]
+                                            parameters: smallvec![assoc]
                                         })),
                                     pos: pos,
                                 };
@@ -408,14 +418,14 @@ mod tests {
     #[test]
     fn test_parse_selected_name_single() {
         let (util, name) = with_stream(parse_selected_name, "foo");
-        assert_eq!(name, vec![util.ident("foo")]);
+        assert_eq!(name.to_vec(), vec![util.ident("foo")]);
     }
 
     #[test]
     fn test_parse_selected_name_multiple() {
         let (util, name) = with_stream(parse_selected_name, "foo.bar.baz");
         assert_eq!(
-            name,
+            name.to_vec(),
             vec![util.ident("foo"), util.ident("bar"), util.ident("baz")]
         );
     }
@@ -527,7 +537,7 @@ mod tests {
             pos: util.first_substr_pos("prefix"),
         };
         let slice = WithPos {
-            item: Name::Slice(Box::new(prefix), util.discrete_range("0 to 3")),
+            item: Name::Slice(Box::new(prefix), Box::new(util.discrete_range("0 to 3"))),
             pos: util.first_substr_pos("prefix(0 to 3)"),
         };
         assert_eq!(name, slice);
@@ -541,7 +551,10 @@ mod tests {
             pos: util.first_substr_pos("prefix"),
         };
         let slice = WithPos {
-            item: Name::Slice(Box::new(prefix), util.discrete_range("3 downto 0")),
+            item: Name::Slice(
+                Box::new(prefix),
+                Box::new(util.discrete_range("3 downto 0")),
+            ),
             pos: util.first_substr_pos("prefix(3 downto 0)"),
         };
         assert_eq!(name, slice);
@@ -673,7 +686,7 @@ mod tests {
         let foo_0 = WithPos {
             item: Name::FunctionCall(Box::new(FunctionCall {
                 name: foo,
-                parameters: vec![AssociationElement {
+                parameters: smallvec![AssociationElement {
                     formal: None,
                     actual: util.expr("0").map_into(ActualPart::Expression),
                 }],
@@ -696,7 +709,7 @@ mod tests {
         let prefix_index = WithPos {
             item: Name::FunctionCall(Box::new(FunctionCall {
                 name: prefix,
-                parameters: vec![
+                parameters: smallvec![
                     AssociationElement {
                         formal: None,
                         actual: util.expr("0").map_into(ActualPart::Expression),
@@ -713,7 +726,7 @@ mod tests {
         let prefix_index_3 = WithPos {
             item: Name::FunctionCall(Box::new(FunctionCall {
                 name: prefix_index,
-                parameters: vec![AssociationElement {
+                parameters: smallvec![AssociationElement {
                     formal: None,
                     actual: util.expr("3").map_into(ActualPart::Expression),
                 }],
@@ -756,7 +769,7 @@ mod tests {
         let foo_call = WithPos {
             item: Name::FunctionCall(Box::new(FunctionCall {
                 name: foo,
-                parameters: vec![assoc_elem],
+                parameters: smallvec![assoc_elem],
             })),
             pos: util.first_substr_pos("foo(arg => 0)"),
         };
@@ -0,0 +1,281 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The naming lint rule which checks the designators of declarations against a regular
+//! expression for each kind of declaration, such as:
+//!
+//! ```toml
+//! [lint]
+//! naming = { constant = "^[A-Z][A-Z0-9_]*$", generic = "^G_[A-Z0-9_]+$", label = "^[a-z_0-9]+$" }
+//! ```
+//!
+//! The kinds are those of named entities such as signal, variable, shared_variable, type,
+//! entity or label. A declaration which can be renamed by changing its case or by adding
+//! the literal prefix of the expression gets a fix which renames all names denoting it
+
+use formatter::STANDARD_LIBRARIES;
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use name_resolution::{NameResolution, NamedEntity, NamedEntityKind};
+use rename::rename_positions;
+
+extern crate regex;
+use self::regex::Regex;
+
+pub struct NamingConvention;
+
+/// The literal prefix of an anchored expression such as G_ of ^G_[A-Z]+$
+fn literal_prefix(pattern: &str) -> &str {
+    let rest = match pattern.strip_prefix('^') {
+        Some(rest) => rest,
+        None => {
+            return "";
+        }
+    };
+    let end = rest
+        .find(|chr: char| !(chr.is_ascii_alphanumeric() || chr == '_'))
+        .unwrap_or(rest.len());
+    // A quantifier applies to the last character of the prefix
+    match rest[end..].chars().next() {
+        Some('*') | Some('?') | Some('{') => &rest[..end.saturating_sub(1)],
+        _ => &rest[..end],
+    }
+}
+
+/// The new name which matches the expression by changing the case of the designator or
+/// by adding the literal prefix of the expression
+fn renamed(designator: &str, regex: &Regex) -> Option<String> {
+    let prefix = literal_prefix(regex.as_str());
+    let mut candidates = vec![
+        designator.to_ascii_uppercase(),
+        designator.to_ascii_lowercase(),
+    ];
+    if !prefix.is_empty()
+        && !designator
+            .to_ascii_lowercase()
+            .starts_with(&prefix.to_ascii_lowercase())
+    {
+        candidates.push(format!("{}{}", prefix, designator));
+        candidates.push(format!("{}{}", prefix, designator.to_ascii_uppercase()));
+        candidates.push(format!("{}{}", prefix, designator.to_ascii_lowercase()));
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| regex.is_match(candidate))
+}
+
+fn finding(
+    resolution: &NameResolution,
+    entity: &NamedEntity,
+    kind: &str,
+    regex: &Regex,
+) -> Message {
+    let message = warning(
+        &entity.pos,
+        &format!(
+            "The name of {} '{}' does not match '{}'",
+            kind.replace('_', " "),
+            entity.designator,
+            regex.as_str()
+        ),
+    );
+    let new_name = match renamed(&entity.designator, regex) {
+        Some(new_name) => new_name,
+        None => {
+            return message;
+        }
+    };
+    match rename_positions(resolution, entity, &new_name) {
+        Ok(positions) => message.with_fix(
+            &format!("Rename to '{}'", new_name),
+            positions
+                .into_iter()
+                .map(|pos| (pos, new_name.clone()))
+                .collect(),
+        ),
+        Err(..) => message,
+    }
+}
+
+impl Rule for NamingConvention {
+    fn id(&self) -> &str {
+        "naming"
+    }
+
+    fn description(&self) -> &str {
+        "A declaration does not match the naming convention of its kind"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        let mut conventions: Vec<(NamedEntityKind, &str, Regex)> = Vec::new();
+        for (kind_name, pattern) in config.options.iter() {
+            let kind = kind_name
+                .parse()
+                .map_err(|err| format!("lint rule {}: {}", self.id(), err))?;
+            let pattern = pattern.as_str().ok_or_else(|| {
+                format!(
+                    "lint rule {} {} must be a regular expression string",
+                    self.id(),
+                    kind_name
+                )
+            })?;
+            let regex = Regex::new(pattern)
+                .map_err(|err| format!("lint rule {} {}: {}", self.id(), kind_name, err))?;
+            conventions.push((kind, kind_name, regex));
+        }
+        if conventions.is_empty() {
+            return Err(format!(
+                "lint rule {} needs the expression of at least one kind",
+                self.id()
+            ));
+        }
+
+        let mut findings = Vec::new();
+        for entity in context.resolution.declarations() {
+            let is_standard = entity
+                .pos
+                .source
+                .file_name()
+                .and_then(|file_name| context.project.get_file(file_name))
+                .is_some_and(|file| STANDARD_LIBRARIES.contains(&file.library_name.as_str()));
+            if is_standard || entity.designator.starts_with('"') {
+                continue;
+            }
+            for (kind, kind_name, regex) in conventions.iter() {
+                if entity.kind == *kind && !regex.is_match(&entity.designator) {
+                    findings.push(finding(context.resolution, entity, kind_name, regex));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+    extern crate toml;
+    use self::toml::Value;
+
+    #[test]
+    fn prefix_of_expression() {
+        assert_eq!(literal_prefix("^G_[A-Z0-9_]+$"), "G_");
+        assert_eq!(literal_prefix("^c_x?"), "c_");
+        assert_eq!(literal_prefix("[a-z]+$"), "");
+        assert_eq!(literal_prefix("^[a-z]+$"), "");
+    }
+
+    #[test]
+    fn declarations_not_matching_convention() {
+        let mut project = TempProject::new();
+        let code = "
+entity Counter is
+  generic (width : natural := 8);
+end entity;
+
+architecture rtl of Counter is
+  constant max_count : natural := 2**width - 1;
+  signal count : natural;
+begin
+  proc : process
+  begin
+    count <= max_count;
+  end process proc;
+end architecture;";
+        let file_name = project.add_file("lib", "counter.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let value: Value = "naming = { constant = '^[A-Z][A-Z0-9_]*$', generic = \
+                                    '^G_[A-Z0-9_]+$', entity = '^[a-z_]+$', \
+                                    signal = '^[a-z_]+$', label = '^[0-9]+$' }"
+            .parse()
+            .unwrap();
+        let config = LintConfig::from_value(&value).unwrap();
+        let findings: Vec<Message> = Registry::default()
+            .check(&context, &config)
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("naming".to_owned()))
+            .collect();
+
+        let described: Vec<(String, Option<String>)> = findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.message.clone(),
+                    finding.fixes.first().map(|fix| fix.title.clone()),
+                )
+            })
+            .collect();
+        let string = |string: &str| string.to_owned();
+        assert_eq!(
+            described,
+            vec![
+                (
+                    string("The name of entity 'Counter' does not match '^[a-z_]+$'"),
+                    Some(string("Rename to 'counter'"))
+                ),
+                (
+                    string("The name of generic 'width' does not match '^G_[A-Z0-9_]+$'"),
+                    Some(string("Rename to 'G_WIDTH'"))
+                ),
+                (
+                    string("The name of constant 'max_count' does not match '^[A-Z][A-Z0-9_]*$'"),
+                    Some(string("Rename to 'MAX_COUNT'"))
+                ),
+                (
+                    string("The name of label 'proc' does not match '^[0-9]+$'"),
+                    None
+                ),
+            ]
+        );
+
+        let edits: Vec<(usize, String)> = findings[1].fixes[0]
+            .edits
+            .iter()
+            .map(|(pos, text)| (pos.start, text.clone()))
+            .collect();
+        assert_eq!(
+            edits,
+            vec![
+                (code.find("width :").unwrap(), string("G_WIDTH")),
+                (code.find("width - 1").unwrap(), string("G_WIDTH")),
+            ]
+        );
+        assert!(findings
+            .iter()
+            .all(|finding| finding.pos.source.file_name() == Some(file_name.as_str())));
+
+        let check = |contents: &str| {
+            let value: Value = contents.parse().unwrap();
+            Registry::default().check(&context, &LintConfig::from_value(&value).unwrap())
+        };
+        assert_eq!(
+            check("naming = { enabled = true }"),
+            Err("lint rule naming needs the expression of at least one kind".to_owned())
+        );
+        assert_eq!(
+            check("naming = { kind = 'x' }"),
+            Err("lint rule naming: Unknown kind 'kind'".to_owned())
+        );
+        assert!(check("naming = { signal = '(' }").is_err());
+    }
+}
@@ -0,0 +1,518 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The elaborated structure below a top level entity as a flat list of instances and the
+//! nets connecting their ports, for visualization and custom design rule checks
+//!
+//! Instances and nets are named by their hierarchical paths such as top.cpu.clk. A port
+//! whose actual is a port or signal of the enclosing architecture is on the net of that
+//! port or signal, any other port is on a net of its own, so a net with a single port is
+//! not connected. The ports of instances outside of the project are those associated by
+//! name
+
+use ast::{
+    ActualPart, Declaration, Expression, InstantiationStatement, InterfaceDeclaration, LibraryUnit,
+    Mode, Name, ObjectClass,
+};
+use instance_tree::{InstanceHierarchy, InstanceNode};
+use library::{symbol_key, UnitId};
+use name_resolution::entity_interface;
+use project::Project;
+use std::collections::BTreeMap;
+use verilog_parser::PortDirection;
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct NetlistGeneric {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct NetlistPort {
+    pub name: String,
+    /// The mode such as in or out, None for ports of units outside of the project
+    pub direction: Option<&'static str>,
+    /// The hierarchical name of the net the port is on
+    pub net: String,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct NetlistInstance {
+    /// The hierarchical name such as top.cpu.gen.mem
+    pub path: String,
+    /// The entity and architecture such as lib.core(fast), the Verilog module or the
+    /// instantiated unit as written
+    pub unit: String,
+    pub generics: Vec<NetlistGeneric>,
+    pub ports: Vec<NetlistPort>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Net {
+    pub name: String,
+    /// The hierarchical names of the ports on the net such as top.cpu.clk
+    pub ports: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Netlist {
+    pub top: String,
+    /// The instances in depth first order starting with the top
+    pub instances: Vec<NetlistInstance>,
+    /// The nets in the order of their declaration
+    pub nets: Vec<Net>,
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::In => "in",
+        Mode::Out => "out",
+        Mode::InOut => "inout",
+        Mode::Buffer => "buffer",
+        Mode::Linkage => "linkage",
+    }
+}
+
+fn direction_mode(direction: PortDirection) -> &'static str {
+    match direction {
+        PortDirection::Input => "in",
+        PortDirection::Output => "out",
+        PortDirection::Inout | PortDirection::Ref => "inout",
+    }
+}
+
+/// Builds the netlist from the instance tree
+struct Elaborator<'h, 'a: 'h> {
+    hierarchy: &'h InstanceHierarchy<'a>,
+    /// The instantiation statements by their architecture and label
+    statements: BTreeMap<(UnitId, String), &'a InstantiationStatement>,
+    /// The declarations of each architecture
+    declarations: BTreeMap<UnitId, &'a [Declaration]>,
+    instances: Vec<NetlistInstance>,
+    nets: Vec<Net>,
+    /// The index of each net by its name
+    net_index: BTreeMap<String, usize>,
+}
+
+impl<'h, 'a: 'h> Elaborator<'h, 'a> {
+    fn add_net(&mut self, name: &str) {
+        if !self.net_index.contains_key(name) {
+            self.net_index.insert(name.to_owned(), self.nets.len());
+            self.nets.push(Net {
+                name: name.to_owned(),
+                ports: Vec::new(),
+            });
+        }
+    }
+
+    fn connect(&mut self, net: &str, port: String) {
+        self.add_net(net);
+        let index = self.net_index[net];
+        self.nets[index].ports.push(port);
+    }
+
+    /// The ports of the unit of the node by their names with their directions
+    fn ports(
+        &self,
+        node: &InstanceNode,
+        statement: Option<&InstantiationStatement>,
+    ) -> Vec<(String, Option<&'static str>)> {
+        if let Some(library_unit) = node
+            .entity
+            .as_ref()
+            .and_then(|entity| self.hierarchy.entity(entity))
+        {
+            return entity_interface(library_unit)
+                .1
+                .iter()
+                .filter_map(|decl| match decl {
+                    InterfaceDeclaration::Object(ref object) => {
+                        Some((symbol_key(&object.ident.item), Some(mode_name(object.mode))))
+                    }
+                    _ => None,
+                })
+                .collect();
+        }
+        if let Some(module) = node
+            .module
+            .as_ref()
+            .and_then(|module| self.hierarchy.verilog_module(module))
+        {
+            return module
+                .ports
+                .iter()
+                .map(|port| (port.name.item.clone(), Some(direction_mode(port.direction))))
+                .collect();
+        }
+        statement
+            .map(|statement| {
+                statement
+                    .port_map
+                    .iter()
+                    .filter_map(|association| match association.formal {
+                        Some(ref formal) => match formal.item {
+                            Name::Simple(ref symbol) => Some((symbol_key(symbol), None)),
+                            _ => None,
+                        },
+                        None => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The nets of the ports of the instance which are connected to a port or signal of
+    /// the enclosing architecture by their port names
+    fn port_nets(
+        &self,
+        statement: &InstantiationStatement,
+        ports: &[(String, Option<&'static str>)],
+        scope: &BTreeMap<String, String>,
+    ) -> BTreeMap<String, String> {
+        let mut nets = BTreeMap::new();
+        for (i, association) in statement.port_map.iter().enumerate() {
+            let port = match association.formal {
+                Some(ref formal) => match formal.item {
+                    Name::Simple(ref symbol) => {
+                        let key = symbol_key(symbol);
+                        ports
+                            .iter()
+                            .find(|(name, _)| name.eq_ignore_ascii_case(&key))
+                            .map(|(name, _)| name)
+                    }
+                    // A subelement such as data(0) is only part of the port
+                    _ => None,
+                },
+                None => ports.get(i).map(|(name, _)| name),
+            };
+            let actual = match association.actual.item {
+                ActualPart::Expression(Expression::Name(ref name)) => match **name {
+                    Name::Simple(ref symbol) => scope.get(&symbol_key(symbol)),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let (Some(port), Some(net)) = (port, actual) {
+                nets.insert(port.clone(), net.clone());
+            }
+        }
+        nets
+    }
+
+    fn elaborate(
+        &mut self,
+        node: &InstanceNode,
+        path: String,
+        statement: Option<&InstantiationStatement>,
+        port_nets: &BTreeMap<String, String>,
+    ) {
+        let mut scope = BTreeMap::new();
+        let mut ports = Vec::new();
+        let declared_ports = self.ports(node, statement);
+        for (name, direction) in declared_ports.iter() {
+            let net = port_nets
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| format!("{}.{}", path, name));
+            self.connect(&net, format!("{}.{}", path, name));
+            scope.insert(name.to_lowercase(), net.clone());
+            ports.push(NetlistPort {
+                name: name.clone(),
+                direction: *direction,
+                net,
+            });
+        }
+        self.instances.push(NetlistInstance {
+            path: path.clone(),
+            unit: node.unit(),
+            generics: node
+                .generics
+                .iter()
+                .map(|(name, value)| NetlistGeneric {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+            ports,
+        });
+
+        let architecture_id = match (&node.entity, &node.architecture) {
+            (Some(entity), Some(architecture)) => {
+                UnitId::architecture(&entity.library_name, entity.primary_name(), architecture)
+            }
+            _ => {
+                return;
+            }
+        };
+        let declarations = self
+            .declarations
+            .get(&architecture_id)
+            .cloned()
+            .unwrap_or(&[]);
+        for decl in declarations.iter() {
+            if let Declaration::Object(ref object) = decl {
+                if object.class == ObjectClass::Signal {
+                    let name = symbol_key(&object.ident.item);
+                    let net = format!("{}.{}", path, name);
+                    self.add_net(&net);
+                    scope.insert(name, net);
+                }
+            }
+        }
+
+        for child in node.children.iter() {
+            let statement = self
+                .statements
+                .get(&(architecture_id.clone(), child.label.clone()))
+                .cloned();
+            let child_nets = match statement {
+                Some(statement) => {
+                    let child_ports = self.ports(child, Some(statement));
+                    self.port_nets(statement, &child_ports, &scope)
+                }
+                None => BTreeMap::new(),
+            };
+            self.elaborate(
+                child,
+                format!("{}.{}", path, child.label),
+                statement,
+                &child_nets,
+            );
+        }
+    }
+}
+
+/// The instances and nets below the top level entity, None when it is not within the project
+pub fn netlist(project: &Project, top: &UnitId) -> Option<Netlist> {
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    let tree = hierarchy.tree(top, None)?;
+
+    let mut statements = BTreeMap::new();
+    for instantiation in hierarchy.instantiations() {
+        statements.insert(
+            (instantiation.architecture, instantiation.label),
+            instantiation.instance,
+        );
+    }
+    for instantiation in hierarchy.verilog_instantiations() {
+        statements.insert(
+            (instantiation.architecture, instantiation.label),
+            instantiation.instance,
+        );
+    }
+    for instantiation in hierarchy.unbound_instantiations() {
+        statements.insert(
+            (instantiation.architecture, instantiation.label),
+            instantiation.instance,
+        );
+    }
+    let mut declarations = BTreeMap::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            if let LibraryUnit::ArchitectureBody { ref decl, .. } = design_unit.library_unit {
+                declarations.insert(
+                    UnitId::new(&file.library_name, design_unit),
+                    decl.as_slice(),
+                );
+            }
+        }
+    }
+
+    let mut elaborator = Elaborator {
+        hierarchy: &hierarchy,
+        statements,
+        declarations,
+        instances: Vec::new(),
+        nets: Vec::new(),
+        net_index: BTreeMap::new(),
+    };
+    elaborator.elaborate(&tree, tree.label.clone(), None, &BTreeMap::new());
+    Some(Netlist {
+        top: tree.label.clone(),
+        instances: elaborator.instances,
+        nets: elaborator.nets,
+    })
+}
+
+/// The netlist as s-expressions in the manner of EDIF with one instance or net per line
+pub fn netlist_text(netlist: &Netlist) -> String {
+    let mut lines = vec![format!("(netlist {}", netlist.top)];
+    for instance in netlist.instances.iter() {
+        let mut line = format!("  (instance {} (unit {})", instance.path, instance.unit);
+        for generic in instance.generics.iter() {
+            line.push_str(&format!(" (generic {} {})", generic.name, generic.value));
+        }
+        for port in instance.ports.iter() {
+            match port.direction {
+                Some(direction) => {
+                    line.push_str(&format!(" (port {} {} {})", port.name, direction, port.net))
+                }
+                None => line.push_str(&format!(" (port {} {})", port.name, port.net)),
+            }
+        }
+        line.push(')');
+        lines.push(line);
+    }
+    for net in netlist.nets.iter() {
+        lines.push(format!("  (net {} {})", net.name, net.ports.join(" ")));
+    }
+    let last = lines.len() - 1;
+    lines[last].push(')');
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity top is
+  port (clk : in std_logic; q : out std_logic_vector(7 downto 0));
+end entity;
+
+architecture rtl of top is
+  signal count : std_logic_vector(3 downto 0);
+begin
+  cnt: entity work.counter generic map (width => 4) port map (clk, count, '1');
+  ram: entity work.ram port map (clk => clk, dout => q);
+  buf: component bufg port map (i => clk, o => open);
+end architecture;
+
+entity counter is
+  generic (width : natural := 8);
+  port (clk : in std_logic; value : out std_logic_vector(width - 1 downto 0); enable : in bit);
+end entity;
+
+architecture rtl of counter is
+begin
+  reg: entity work.register port map (clk => clk, d => value);
+end architecture;
+
+entity register is
+  port (clk : in std_logic; d : in std_logic_vector);
+end entity;
+
+architecture rtl of register is
+begin
+end architecture;",
+        );
+        project.add_file(
+            "lib",
+            "ram.v",
+            "
+module ram #(parameter WIDTH = 8) (input clk, output [WIDTH-1:0] dout);
+endmodule",
+        );
+        project
+    }
+
+    fn net<'n>(netlist: &'n Netlist, name: &str) -> &'n Net {
+        netlist.nets.iter().find(|net| net.name == name).unwrap()
+    }
+
+    #[test]
+    fn instances_and_nets() {
+        let project = project();
+        let netlist = netlist(&project.project, &UnitId::primary("lib", "top")).unwrap();
+        assert_eq!(netlist.top, "top");
+        let paths: Vec<&str> = netlist
+            .instances
+            .iter()
+            .map(|instance| instance.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["top", "top.cnt", "top.cnt.reg", "top.ram", "top.buf"]
+        );
+
+        let cnt = &netlist.instances[1];
+        assert_eq!(cnt.unit, "lib.counter(rtl)");
+        assert_eq!(
+            cnt.generics,
+            vec![NetlistGeneric {
+                name: "width".to_owned(),
+                value: "4".to_owned()
+            }]
+        );
+        assert_eq!(
+            cnt.ports,
+            vec![
+                NetlistPort {
+                    name: "clk".to_owned(),
+                    direction: Some("in"),
+                    net: "top.clk".to_owned()
+                },
+                NetlistPort {
+                    name: "value".to_owned(),
+                    direction: Some("out"),
+                    net: "top.count".to_owned()
+                },
+                NetlistPort {
+                    name: "enable".to_owned(),
+                    direction: Some("in"),
+                    net: "top.cnt.enable".to_owned()
+                },
+            ]
+        );
+        assert_eq!(netlist.instances[3].ports[1].direction, Some("out"));
+        assert_eq!(netlist.instances[4].ports[0].direction, None);
+
+        assert_eq!(
+            net(&netlist, "top.clk").ports,
+            vec![
+                "top.clk",
+                "top.cnt.clk",
+                "top.cnt.reg.clk",
+                "top.ram.clk",
+                "top.buf.i"
+            ]
+        );
+        assert_eq!(
+            net(&netlist, "top.count").ports,
+            vec!["top.cnt.value", "top.cnt.reg.d"]
+        );
+        assert_eq!(net(&netlist, "top.q").ports, vec!["top.q", "top.ram.dout"]);
+        assert_eq!(
+            net(&netlist, "top.cnt.enable").ports,
+            vec!["top.cnt.enable"]
+        );
+        assert_eq!(net(&netlist, "top.buf.o").ports, vec!["top.buf.o"]);
+    }
+
+    #[test]
+    fn unknown_top() {
+        let project = project();
+        assert_eq!(
+            netlist(&project.project, &UnitId::primary("lib", "missing")),
+            None
+        );
+    }
+
+    #[test]
+    fn text_format() {
+        let project = project();
+        let netlist = netlist(&project.project, &UnitId::primary("lib", "register")).unwrap();
+        assert_eq!(
+            netlist_text(&netlist),
+            "\
+(netlist register
+  (instance register (unit lib.register(rtl)) (port clk in register.clk) (port d in register.d))
+  (net register.clk register.clk)
+  (net register.d register.d))"
+        );
+    }
+}
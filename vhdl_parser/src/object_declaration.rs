@@ -10,6 +10,7 @@ use expression::parse_expression;
 use message::ParseResult;
 use names::parse_identifier_list;
 use source::WithPos;
+use std::sync::Arc;
 use subtype_indication::parse_subtype_indication;
 use tokenizer::Kind::*;
 use tokenstream::TokenStream;
@@ -31,8 +32,8 @@ fn parse_object_declaration_kind(
 ) -> ParseResult<Vec<ObjectDeclaration>> {
     let idents = parse_identifier_list(stream)?;
     stream.expect_kind(Colon)?;
-    let subtype = parse_subtype_indication(stream)?;
-    let opt_expression = parse_optional_assignment(stream)?;
+    let subtype = Arc::new(parse_subtype_indication(stream)?);
+    let opt_expression = parse_optional_assignment(stream)?.map(Arc::new);
 
     Ok(idents
         .into_iter()
@@ -66,11 +67,11 @@ pub fn parse_file_declaration_no_semi(
     stream.expect_kind(File)?;
     let idents = parse_identifier_list(stream)?;
     stream.expect_kind(Colon)?;
-    let subtype = parse_subtype_indication(stream)?;
+    let subtype = Arc::new(parse_subtype_indication(stream)?);
 
     let open_info = {
         if stream.skip_if_kind(Open)? {
-            Some(parse_expression(stream)?)
+            Some(Arc::new(parse_expression(stream)?))
         } else {
             None
         }
@@ -78,7 +79,7 @@ pub fn parse_file_declaration_no_semi(
 
     let file_name = {
         if stream.skip_if_kind(Is)? {
-            Some(parse_expression(stream)?)
+            Some(Arc::new(parse_expression(stream)?))
         } else {
             None
         }
@@ -113,7 +114,7 @@ mod tests {
             vec![ObjectDeclaration {
                 class: ObjectClass::Constant,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
                 expression: None
             }]
         );
@@ -127,7 +128,7 @@ mod tests {
             vec![ObjectDeclaration {
                 class: ObjectClass::Signal,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
                 expression: None
             }]
         );
@@ -141,7 +142,7 @@ mod tests {
             vec![ObjectDeclaration {
                 class: ObjectClass::Variable,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
                 expression: None
             }]
         );
@@ -156,7 +157,7 @@ mod tests {
             vec![ObjectDeclaration {
                 class: ObjectClass::SharedVariable,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
                 expression: None
             }]
         );
@@ -169,7 +170,7 @@ mod tests {
             object,
             vec![FileDeclaration {
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("text"),
+                subtype_indication: Arc::new(util.subtype_indication("text")),
                 open_info: None,
                 file_name: None
             }]
@@ -184,9 +185,9 @@ mod tests {
             object,
             vec![FileDeclaration {
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("text"),
+                subtype_indication: Arc::new(util.subtype_indication("text")),
                 open_info: None,
-                file_name: Some(util.expr("\"file_name\""))
+                file_name: Some(Arc::new(util.expr("\"file_name\"")))
             }]
         );
     }
@@ -201,9 +202,9 @@ mod tests {
             object,
             vec![FileDeclaration {
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("text"),
-                open_info: Some(util.expr("write_mode")),
-                file_name: Some(util.expr("\"file_name\""))
+                subtype_indication: Arc::new(util.subtype_indication("text")),
+                open_info: Some(Arc::new(util.expr("write_mode"))),
+                file_name: Some(Arc::new(util.expr("\"file_name\"")))
             }]
         );
     }
@@ -216,8 +217,8 @@ mod tests {
             vec![ObjectDeclaration {
                 class: ObjectClass::Constant,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
-                expression: Some(util.expr("0"))
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
+                expression: Some(Arc::new(util.expr("0")))
             }]
         );
     }
@@ -233,17 +234,25 @@ mod tests {
             ObjectDeclaration {
                 class: ObjectClass::Constant,
                 ident: util.ident("foo"),
-                subtype_indication: util.subtype_indication("natural"),
-                expression: Some(util.expr("0")),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
+                expression: Some(Arc::new(util.expr("0"))),
             },
             ObjectDeclaration {
                 class: ObjectClass::Constant,
                 ident: util.ident("bar"),
-                subtype_indication: util.subtype_indication("natural"),
-                expression: Some(util.expr("0")),
+                subtype_indication: Arc::new(util.subtype_indication("natural")),
+                expression: Some(Arc::new(util.expr("0"))),
             },
         ];
 
         assert_eq!(object, objects);
+        assert!(Arc::ptr_eq(
+            &object[0].subtype_indication,
+            &object[1].subtype_indication
+        ));
+        assert!(Arc::ptr_eq(
+            object[0].expression.as_ref().unwrap(),
+            object[1].expression.as_ref().unwrap()
+        ));
     }
 }
@@ -0,0 +1,491 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! A hierarchical outline of the design units of a design file
+
+use ast::*;
+use name_resolution::{
+    declared_entities, entity_interface, interface_entity, subprogram_entity, NamedEntity,
+    NamedEntityKind,
+};
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+use tokenizer::Kind::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutlineKind {
+    /// A named entity introduced by a declaration
+    Declaration(NamedEntityKind),
+    PackageBody,
+    Process,
+    Block,
+    Generate,
+    Instance,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutlineItem {
+    /// The designator or label, the kind of statement when unlabeled
+    pub name: String,
+    pub kind: OutlineKind,
+    /// The designator or label, the first token when unlabeled
+    pub pos: SrcPos,
+    /// The whole declaration or statement including its body
+    pub range: SrcPos,
+    pub children: Vec<OutlineItem>,
+}
+
+/// The design units of the design file and the declarations, processes,
+/// blocks, generate statements and instances within them
+pub fn outline(source: &Source, design_file: &DesignFile) -> Vec<OutlineItem> {
+    match SourceTokens::new(source) {
+        Some(tokens) => {
+            let outliner = Outliner { tokens };
+            design_file
+                .design_units
+                .iter()
+                .filter_map(|unit| outliner.library_unit(&unit.library_unit))
+                .collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+struct Outliner {
+    tokens: SourceTokens,
+}
+
+impl Outliner {
+    fn item(
+        &self,
+        name: String,
+        kind: OutlineKind,
+        pos: &SrcPos,
+        start: usize,
+        children: Vec<OutlineItem>,
+    ) -> OutlineItem {
+        OutlineItem {
+            name,
+            kind,
+            pos: pos.clone(),
+            range: self.tokens.range(start, self.tokens.end_of(start)),
+            children,
+        }
+    }
+
+    fn declaration_item(
+        &self,
+        entity: NamedEntity,
+        children: Vec<OutlineItem>,
+    ) -> Option<OutlineItem> {
+        let i = self.tokens.index_of(&entity.pos)?;
+        let start = self.tokens.declaration_start(i, entity.kind);
+        Some(self.item(
+            entity.designator,
+            OutlineKind::Declaration(entity.kind),
+            &entity.pos,
+            start,
+            children,
+        ))
+    }
+
+    fn library_unit(&self, unit: &LibraryUnit) -> Option<OutlineItem> {
+        let (ident, kind) = match unit {
+            LibraryUnit::EntityDeclaration { ref ident, .. } => (ident, NamedEntityKind::Entity),
+            LibraryUnit::ArchitectureBody { ref ident, .. } => {
+                (ident, NamedEntityKind::Architecture)
+            }
+            LibraryUnit::Configuration(ref config) => {
+                (&config.ident, NamedEntityKind::Configuration)
+            }
+            LibraryUnit::PackageDeclaration { ref ident, .. } => (ident, NamedEntityKind::Package),
+            LibraryUnit::PackageInstance(ref instance) => {
+                (&instance.ident, NamedEntityKind::PackageInstance)
+            }
+            LibraryUnit::ContextDeclaration(ref context) => {
+                (&context.ident, NamedEntityKind::Context)
+            }
+            LibraryUnit::PackageBody {
+                ref ident,
+                ref decl,
+            } => {
+                let i = self.tokens.index_of(&ident.pos)?;
+                return Some(self.item(
+                    ident.item.name_utf8(),
+                    OutlineKind::PackageBody,
+                    &ident.pos,
+                    i.saturating_sub(2),
+                    self.declarations(decl),
+                ));
+            }
+        };
+
+        let children = match unit {
+            LibraryUnit::EntityDeclaration { .. } => {
+                let (generics, ports) = entity_interface(unit);
+                self.interface(generics, ports)
+            }
+            LibraryUnit::ArchitectureBody {
+                ref ident,
+                ref decl,
+                ref statements,
+                ..
+            } => {
+                let mut children = self.declarations(decl);
+                let begin = self
+                    .tokens
+                    .index_of(&ident.pos)
+                    .and_then(|i| self.tokens.next_of(i, Is))
+                    .and_then(|is| self.tokens.skip_declarations(is));
+                if let Some(mut begin) = begin {
+                    children.extend(self.statements(statements, &mut begin));
+                }
+                children
+            }
+            LibraryUnit::PackageDeclaration { ref decl, .. } => self.declarations(decl),
+            _ => Vec::new(),
+        };
+        let entity = NamedEntity {
+            designator: ident.item.name_utf8(),
+            kind,
+            pos: ident.pos.clone(),
+        };
+        self.declaration_item(entity, children)
+    }
+
+    fn interface(
+        &self,
+        generics: &[InterfaceDeclaration],
+        ports: &[InterfaceDeclaration],
+    ) -> Vec<OutlineItem> {
+        let generics = generics
+            .iter()
+            .map(|decl| interface_entity(decl, NamedEntityKind::Generic));
+        let ports = ports
+            .iter()
+            .map(|decl| interface_entity(decl, NamedEntityKind::Port));
+        generics
+            .chain(ports)
+            .filter_map(|entity| self.declaration_item(entity, Vec::new()))
+            .collect()
+    }
+
+    fn declarations(&self, decls: &[Declaration]) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        for decl in decls {
+            let mut entities = declared_entities(decl).into_iter();
+            let entity = match entities.next() {
+                Some(entity) => entity,
+                None => continue,
+            };
+            // The units of a physical type and the literals of an enumeration type are children
+            let mut children: Vec<OutlineItem> = entities
+                .filter_map(|entity| self.declaration_item(entity, Vec::new()))
+                .collect();
+
+            match decl {
+                Declaration::Component(ref component) => {
+                    children.extend(self.interface(&component.generic_list, &component.port_list));
+                }
+                Declaration::SubprogramBody(ref body) => {
+                    children.extend(self.declarations(&body.declarations));
+                }
+                Declaration::Type(ref typ) => match typ.def {
+                    TypeDefinition::Protected(ref protected) => {
+                        children.extend(protected.items.iter().filter_map(|item| match item {
+                            ProtectedTypeDeclarativeItem::Subprogram(ref decl) => {
+                                self.declaration_item(subprogram_entity(decl), Vec::new())
+                            }
+                        }));
+                    }
+                    TypeDefinition::ProtectedBody(ref body) => {
+                        children.extend(self.declarations(&body.decl));
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+
+            items.extend(self.declaration_item(entity, children));
+        }
+        items
+    }
+
+    /// The statements follow the token at the cursor which is moved to the last token of the statements
+    /// Statements without a label start directly after the previous statement
+    fn statements(
+        &self,
+        statements: &[LabeledConcurrentStatement],
+        cursor: &mut usize,
+    ) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        for statement in statements {
+            let start = statement
+                .label
+                .as_ref()
+                .and_then(|label| self.tokens.index_of(&label.pos))
+                .unwrap_or(*cursor + 1);
+            if start >= self.tokens.tokens.len() {
+                break;
+            }
+            *cursor = self.tokens.end_of(start);
+            items.extend(self.statement(statement, start));
+        }
+        items
+    }
+
+    fn statement(
+        &self,
+        statement: &LabeledConcurrentStatement,
+        start: usize,
+    ) -> Option<OutlineItem> {
+        let (kind, children) = match statement.statement {
+            ConcurrentStatement::Process(ref process) => {
+                (OutlineKind::Process, self.declarations(&process.decl))
+            }
+            ConcurrentStatement::Block(ref block) => {
+                let mut children = self.declarations(&block.decl);
+                if let Some(mut begin) = self.block_begin(start) {
+                    children.extend(self.statements(&block.statements, &mut begin));
+                }
+                (OutlineKind::Block, children)
+            }
+            ConcurrentStatement::Instance(..) => (OutlineKind::Instance, Vec::new()),
+            ConcurrentStatement::ForGenerate(ref generate) => (
+                OutlineKind::Generate,
+                self.generate_bodies(Some(&generate.body), start),
+            ),
+            ConcurrentStatement::IfGenerate(ref generate) => {
+                let bodies = generate
+                    .conditionals
+                    .iter()
+                    .map(|conditional| &conditional.item)
+                    .chain(generate.else_item.as_ref());
+                (OutlineKind::Generate, self.generate_bodies(bodies, start))
+            }
+            _ => {
+                return None;
+            }
+        };
+
+        let (name, pos) = match statement.label {
+            Some(ref label) => (label.item.name_utf8(), label.pos.clone()),
+            None => {
+                let token = &self.tokens.tokens[start];
+                let name = match kind {
+                    OutlineKind::Process => "process",
+                    OutlineKind::Block => "block",
+                    OutlineKind::Instance => "instance",
+                    _ => "generate",
+                };
+                (name.to_owned(), token.pos.clone())
+            }
+        };
+        Some(self.item(name, kind, &pos, start, children))
+    }
+
+    /// The begin of a block statement starting at the index
+    fn block_begin(&self, start: usize) -> Option<usize> {
+        let mut i = self.tokens.next_of(start, Block)?;
+        // The guard condition
+        if self.tokens.kind(i + 1) == Some(LeftPar) {
+            i = self.tokens.find_forward(i + 1, &[])?;
+        }
+        if self.tokens.kind(i + 1) == Some(Is) {
+            i += 1;
+        }
+        self.tokens.skip_declarations(i)
+    }
+
+    /// The bodies of a generate statement starting at the index
+    /// Each alternative of an if generate statement starts after the previous alternative
+    fn generate_bodies<'a>(
+        &self,
+        bodies: impl IntoIterator<Item = &'a GenerateBody>,
+        start: usize,
+    ) -> Vec<OutlineItem> {
+        let mut items = Vec::new();
+        let mut cursor = start;
+        for body in bodies {
+            let generate = match self.tokens.next_of(cursor, Generate) {
+                Some(generate) => generate,
+                None => break,
+            };
+            cursor = generate;
+            if let Some(ref decl) = body.decl {
+                items.extend(self.declarations(decl));
+                match self.tokens.skip_declarations(generate) {
+                    Some(begin) => cursor = begin,
+                    None => break,
+                }
+            }
+            items.extend(self.statements(&body.statements, &mut cursor));
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    /// The kind, name and first line of the range of each item with its children indented below
+    fn describe(items: &[OutlineItem], indent: usize, lines: &mut Vec<String>) {
+        for item in items {
+            let code = item.range.source.contents().unwrap();
+            let text = code.bytes[item.range.start..item.range.start + item.range.length].to_vec();
+            let text = String::from_utf8(text).unwrap();
+            lines.push(format!(
+                "{}{:?} {}: {}",
+                " ".repeat(indent),
+                item.kind,
+                item.name,
+                text.lines().next().unwrap()
+            ));
+            describe(&item.children, indent + 2, lines);
+        }
+    }
+
+    fn outline_of(code: &str) -> Vec<String> {
+        let mut project = TempProject::new();
+        let file_name = project.add_file("lib", "file.vhd", code);
+        let file = project.project.get_file(&file_name).unwrap();
+        let mut lines = Vec::new();
+        describe(&outline(&file.source, &file.design_file), 0, &mut lines);
+        lines
+    }
+
+    #[test]
+    fn entity_and_architecture() {
+        let lines = outline_of(
+            "
+entity dut is
+  generic (width : natural);
+  port (clk : in bit; data : out bit);
+end entity;
+
+architecture rtl of dut is
+  signal foo : bit;
+  function fun(arg : natural) return natural is
+    variable tmp : natural;
+  begin
+    return tmp;
+  end function;
+begin
+  foo <= '1';
+  process
+  begin
+    if foo = '1' then
+      wait;
+    end if;
+  end process;
+  inst : entity work.other port map (clk => clk);
+  gen : for i in 0 to 1 generate
+    signal bar : bit;
+  begin
+    main : process (bar) is
+    begin
+    end process;
+  end generate;
+end architecture;",
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                "Declaration(Entity) dut: entity dut is",
+                "  Declaration(Generic) width: width : natural",
+                "  Declaration(Port) clk: clk : in bit;",
+                "  Declaration(Port) data: data : out bit",
+                "Declaration(Architecture) rtl: architecture rtl of dut is",
+                "  Declaration(Signal) foo: signal foo : bit;",
+                "  Declaration(Function) fun: function fun(arg : natural) return natural is",
+                "    Declaration(Variable) tmp: variable tmp : natural;",
+                "  Process process: process",
+                "  Instance inst: inst : entity work.other port map (clk => clk);",
+                "  Generate gen: gen : for i in 0 to 1 generate",
+                "    Declaration(Signal) bar: signal bar : bit;",
+                "    Process main: main : process (bar) is",
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_include_bodies() {
+        let mut project = TempProject::new();
+        let code = "
+package body pkg is
+  type prot_t is protected body
+    procedure proc is
+    begin
+    end procedure;
+  end protected body;
+end package body;
+";
+        let file_name = project.add_file("lib", "file.vhd", code);
+        let file = project.project.get_file(&file_name).unwrap();
+        let items = outline(&file.source, &file.design_file);
+
+        let text = |pos: &SrcPos| code[pos.start..pos.start + pos.length].to_owned();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, OutlineKind::PackageBody);
+        assert_eq!(text(&items[0].range), code.trim());
+        assert_eq!(text(&items[0].pos), "pkg");
+
+        let prot = &items[0].children[0];
+        assert_eq!(
+            text(&prot.range),
+            "type prot_t is protected body
+    procedure proc is
+    begin
+    end procedure;
+  end protected body;"
+        );
+        assert_eq!(prot.children[0].name, "proc");
+        assert_eq!(
+            text(&prot.children[0].range),
+            "procedure proc is
+    begin
+    end procedure;"
+        );
+    }
+
+    #[test]
+    fn if_generate_alternatives_and_blocks() {
+        let lines = outline_of(
+            "
+architecture rtl of dut is
+begin
+  gen : if true generate
+    first : process
+    begin
+    end process;
+  else generate
+    blk : block is
+      signal foo : bit;
+    begin
+      process
+      begin
+      end process;
+    end block;
+  end generate;
+end architecture;",
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                "Declaration(Architecture) rtl: architecture rtl of dut is",
+                "  Generate gen: gen : if true generate",
+                "    Process first: first : process",
+                "    Block blk: blk : block is",
+                "      Declaration(Signal) foo: signal foo : bit;",
+                "      Process process: process",
+            ]
+        );
+    }
+}
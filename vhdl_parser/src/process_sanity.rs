@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Lint rules on the structure of processes
+//!
+//! - process-without-wait: a process without a sensitivity list or a wait statement loops
+//!   forever without advancing time
+//! - empty-process: a process has no statements other than null
+//! - combinational-feedback: a process with a sensitivity list which is not clocked reads
+//!   a signal it assigns
+//!
+//! A process which calls a procedure is assumed to wait since the procedure may
+
+use ast::{LabeledSequentialStatement, SequentialStatement};
+use lint::{LintContext, Rule, RuleConfig};
+use message::{error, warning, Message, Severity};
+use process_structure::{architecture_structures, assigned_signals};
+
+/// Whether the statements have a wait statement or a procedure call which may have one
+fn may_wait(statements: &[LabeledSequentialStatement]) -> bool {
+    statements
+        .iter()
+        .any(|statement| match statement.statement {
+            SequentialStatement::Wait(..) | SequentialStatement::ProcedureCall(..) => true,
+            SequentialStatement::If(ref ifs) => {
+                ifs.conditionals
+                    .iter()
+                    .any(|conditional| may_wait(&conditional.item))
+                    || ifs.else_item.as_ref().is_some_and(|item| may_wait(item))
+            }
+            SequentialStatement::Case(ref case) => case
+                .alternatives
+                .iter()
+                .any(|alternative| may_wait(&alternative.item)),
+            SequentialStatement::Loop(ref lp) => may_wait(&lp.statements),
+            _ => false,
+        })
+}
+
+fn is_empty(statements: &[LabeledSequentialStatement]) -> bool {
+    statements
+        .iter()
+        .all(|statement| statement.statement == SequentialStatement::Null)
+}
+
+pub struct ProcessWithoutWait;
+
+impl Rule for ProcessWithoutWait {
+    fn id(&self) -> &str {
+        "process-without-wait"
+    }
+
+    fn description(&self) -> &str {
+        "A process has neither a sensitivity list nor a wait statement"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            for process in structure.processes.iter() {
+                let statements = &process.process.statements;
+                if !process.process.sensitivity_list.is_empty()
+                    || is_empty(statements)
+                    || may_wait(statements)
+                {
+                    continue;
+                }
+                if let Some(ref pos) = process.pos {
+                    findings.push(error(
+                        pos,
+                        &format!(
+                            "{} has neither a sensitivity list nor a wait statement and loops forever",
+                            process.name()
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct EmptyProcess;
+
+impl Rule for EmptyProcess {
+    fn id(&self) -> &str {
+        "empty-process"
+    }
+
+    fn description(&self) -> &str {
+        "A process has no statements other than null"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            for process in structure.processes.iter() {
+                if !is_empty(&process.process.statements) {
+                    continue;
+                }
+                if let Some(ref pos) = process.pos {
+                    findings.push(warning(pos, &format!("{} is empty", process.name())));
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+pub struct CombinationalFeedback;
+
+impl Rule for CombinationalFeedback {
+    fn id(&self) -> &str {
+        "combinational-feedback"
+    }
+
+    fn description(&self) -> &str {
+        "A combinational process reads a signal it assigns"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let mut findings = Vec::new();
+        for structure in architecture_structures(&context.project.files()) {
+            for process in structure.processes.iter() {
+                if process.is_clocked() || process.process.sensitivity_list.is_empty() {
+                    continue;
+                }
+                let mut assigned = Vec::new();
+                assigned_signals(&process.process.statements, &mut assigned);
+                for (name, pos) in process.combinational_reads.iter() {
+                    if assigned.contains(&name.to_lowercase()) {
+                        findings.push(warning(
+                            pos,
+                            &format!(
+                                "{} reads its own output '{}' without a clock",
+                                process.name(),
+                                name
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn process_structure_sanity() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  spin : process
+  begin
+    count <= count + 1;
+  end process;
+
+  idle : process
+  begin
+    null;
+  end process;
+
+  waiting : process
+  begin
+    tick;
+  end process;
+
+  comb : process (a, y)
+  begin
+    y <= a;
+    if a = '1' then
+      y <= y and b;
+    end if;
+  end process;
+
+  reg : process (clk)
+  begin
+    if rising_edge(clk) then
+      q <= q + 1;
+    end if;
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let ids = [
+            "process-without-wait",
+            "empty-process",
+            "combinational-feedback",
+        ];
+        let findings: Vec<(String, String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| ids.contains(&finding.code.as_ref().unwrap().as_str()))
+            .map(|finding| (finding.code.unwrap(), finding.message, finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    string("process-without-wait"),
+                    string(
+                        "Process 'spin' has neither a sensitivity list nor a wait statement \
+                         and loops forever"
+                    ),
+                    code.find("spin").unwrap()
+                ),
+                (
+                    string("empty-process"),
+                    string("Process 'idle' is empty"),
+                    code.find("idle").unwrap()
+                ),
+                (
+                    string("combinational-feedback"),
+                    string("Process 'comb' reads its own output 'y' without a clock"),
+                    code.find("y and b").unwrap()
+                ),
+            ]
+        );
+    }
+}
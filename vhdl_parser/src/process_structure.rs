@@ -0,0 +1,845 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The structure of the processes of each architecture, their clock edges, resets,
+//! registered signals and the signals read by combinational logic
+//!
+//! An if statement whose conditions before a clock edge are not clock edges has
+//! asynchronous resets, while an if statement on a clock edge whose first condition
+//! only reads a signal named like rst or reset has a synchronous reset
+
+use ast::{
+    ActualPart, AssignmentRightHand, Binary, ConcurrentStatement, Conditionals, ElementAssociation,
+    Expression, IterationScheme, LabeledConcurrentStatement, LabeledSequentialStatement,
+    LibraryUnit, Literal, Name, ProcessStatement, SequentialStatement, Target, Waveform,
+};
+use library::{symbol_key, UnitId};
+use project::SourceFile;
+use source::{SrcPos, WithPos};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Edge {
+    Rising,
+    Falling,
+    /// An event without a level such as clk'event
+    Both,
+}
+
+/// A condition on an edge of a clock
+#[derive(PartialEq, Debug, Clone)]
+pub struct ClockEdge {
+    pub clock: String,
+    pub edge: Edge,
+    pub pos: SrcPos,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ResetKind {
+    Synchronous,
+    Asynchronous,
+}
+
+/// A condition on a reset and the signals it assigns
+#[derive(PartialEq, Debug, Clone)]
+pub struct Reset {
+    pub signal: String,
+    pub kind: ResetKind,
+    pub pos: SrcPos,
+    /// The lower case names of the signals assigned when the reset is active
+    pub assigned: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ProcessStructure<'a> {
+    /// The label prefixed by enclosing block and generate labels, empty without a label
+    pub label: String,
+    pub process: &'a ProcessStatement,
+    /// The position of the label, otherwise of the first name of the sensitivity list or
+    /// statement which has one
+    pub pos: Option<SrcPos>,
+    pub clock_edges: Vec<ClockEdge>,
+    pub resets: Vec<Reset>,
+    /// The signals assigned on a clock edge, outside of resets, and their first assignment
+    pub registered: Vec<(String, SrcPos)>,
+    /// The names read by statements which are not executed on a clock edge, the
+    /// conditions of clock edges and resets are not included
+    pub combinational_reads: Vec<(String, SrcPos)>,
+    /// Whether the process waits for a time or delays an assignment with after, such as
+    /// a clock generator
+    pub timed: bool,
+}
+
+impl<'a> ProcessStructure<'a> {
+    pub fn is_clocked(&self) -> bool {
+        !self.clock_edges.is_empty()
+    }
+
+    /// The process for messages such as Process 'reg'
+    pub fn name(&self) -> String {
+        if self.label.is_empty() {
+            "The process".to_owned()
+        } else {
+            format!("Process '{}'", self.label)
+        }
+    }
+}
+
+/// A concurrent signal assignment and the names it reads
+#[derive(PartialEq, Debug, Clone)]
+pub struct ConcurrentAssignment {
+    /// The signal of the target, None for an aggregate
+    pub target: Option<String>,
+    pub pos: SrcPos,
+    pub reads: Vec<(String, SrcPos)>,
+    /// Whether a waveform element is delayed by after, such as by a clock generator
+    pub delayed: bool,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ArchitectureStructure<'a> {
+    pub architecture: UnitId,
+    pub processes: Vec<ProcessStructure<'a>>,
+    /// The names read by the concurrent signal assignments
+    pub concurrent_reads: Vec<(String, SrcPos)>,
+    pub concurrent_assignments: Vec<ConcurrentAssignment>,
+}
+
+impl<'a> ArchitectureStructure<'a> {
+    /// The lower case names of the signals which are clocks of any process
+    pub fn clocks(&self) -> Vec<String> {
+        let mut clocks: Vec<String> = Vec::new();
+        for edge in self
+            .processes
+            .iter()
+            .flat_map(|process| process.clock_edges.iter())
+        {
+            let clock = edge.clock.to_lowercase();
+            if !clocks.contains(&clock) {
+                clocks.push(clock);
+            }
+        }
+        clocks
+    }
+}
+
+/// The simple name of an expression such as clk
+pub fn simple_name(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Name(ref name) => match **name {
+            Name::Simple(ref symbol) => Some(symbol.name_utf8()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The character of a literal such as '1'
+fn character(expr: &Expression) -> Option<u8> {
+    match expr {
+        Expression::Literal(Literal::Character(chr)) => Some(*chr),
+        Expression::Name(ref name) => match **name {
+            Name::CharacterLiteral(chr) => Some(chr),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The signal of an event attribute such as clk'event
+fn event_of(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Name(ref name) => match **name {
+            Name::Attribute(ref attribute) if symbol_key(&attribute.attr.item) == "event" => {
+                match attribute.name.item {
+                    Name::Simple(ref symbol) => Some(symbol.name_utf8()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The level of a comparison of the signal with a character such as clk = '1'
+fn level_of(expr: &Expression, signal: &str) -> Option<u8> {
+    match expr {
+        Expression::Binary(Binary::EQ, ref left, ref right) => {
+            let name = simple_name(&left.item)?;
+            if name.eq_ignore_ascii_case(signal) {
+                character(&right.item)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The clock and edge of a condition using rising_edge, falling_edge or the event attribute
+pub fn clock_edge(expr: &WithPos<Expression>) -> Option<ClockEdge> {
+    let edge = |clock: String, edge: Edge| {
+        Some(ClockEdge {
+            clock,
+            edge,
+            pos: expr.pos.clone(),
+        })
+    };
+    match expr.item {
+        Expression::Name(ref name) => match **name {
+            Name::FunctionCall(ref call) => {
+                let function = match call.name.item {
+                    Name::Simple(ref symbol) => symbol_key(symbol),
+                    _ => {
+                        return None;
+                    }
+                };
+                let kind = match function.as_str() {
+                    "rising_edge" => Edge::Rising,
+                    "falling_edge" => Edge::Falling,
+                    _ => {
+                        return None;
+                    }
+                };
+                match call.parameters.as_slice() {
+                    [parameter] => match parameter.actual.item {
+                        ActualPart::Expression(ref actual) => edge(simple_name(actual)?, kind),
+                        ActualPart::Open => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => edge(event_of(&expr.item)?, Edge::Both),
+        },
+        Expression::Binary(Binary::And, ref left, ref right) => {
+            for (event, level) in [(left, right), (right, left)].iter() {
+                if let Some(clock) = event_of(&event.item) {
+                    let kind = match level_of(&level.item, &clock) {
+                        Some(b'1') => Edge::Rising,
+                        Some(b'0') => Edge::Falling,
+                        _ => Edge::Both,
+                    };
+                    return edge(clock, kind);
+                }
+            }
+            clock_edge(left)
+                .or_else(|| clock_edge(right))
+                .map(|clock_edge| ClockEdge {
+                    pos: expr.pos.clone(),
+                    ..clock_edge
+                })
+        }
+        _ => None,
+    }
+}
+
+/// Adds the simple names read by the expression, the position of a name is that of
+/// the innermost expression containing it
+pub fn expression_reads(expr: &WithPos<Expression>, reads: &mut Vec<(String, SrcPos)>) {
+    reads_at(&expr.item, &expr.pos, reads);
+}
+
+fn reads_at(expr: &Expression, pos: &SrcPos, reads: &mut Vec<(String, SrcPos)>) {
+    match expr {
+        Expression::Binary(_, ref left, ref right) => {
+            expression_reads(left, reads);
+            expression_reads(right, reads);
+        }
+        Expression::Unary(_, ref operand) => expression_reads(operand, reads),
+        Expression::Name(ref name) => name_reads(name, pos, reads),
+        Expression::Aggregate(ref associations) => {
+            for association in associations.iter() {
+                match association {
+                    ElementAssociation::Positional(ref expr)
+                    | ElementAssociation::Named(_, ref expr) => expression_reads(expr, reads),
+                }
+            }
+        }
+        Expression::Qualified(ref qualified) => expression_reads(&qualified.expr, reads),
+        Expression::Literal(..) | Expression::New(..) => {}
+    }
+}
+
+fn actual_reads(actual: &WithPos<ActualPart>, reads: &mut Vec<(String, SrcPos)>) {
+    if let ActualPart::Expression(ref expr) = actual.item {
+        reads_at(expr, &actual.pos, reads);
+    }
+}
+
+fn name_reads(name: &Name, pos: &SrcPos, reads: &mut Vec<(String, SrcPos)>) {
+    match name {
+        Name::Simple(ref symbol) => reads.push((symbol.name_utf8(), pos.clone())),
+        Name::Selected(ref prefix, _) => name_reads(&prefix.item, &prefix.pos, reads),
+        Name::Indexed(ref prefix, ref indexes) => {
+            name_reads(&prefix.item, &prefix.pos, reads);
+            for index in indexes.iter() {
+                expression_reads(index, reads);
+            }
+        }
+        Name::Slice(ref prefix, _) => name_reads(&prefix.item, &prefix.pos, reads),
+        Name::Attribute(ref attribute) => {
+            name_reads(&attribute.name.item, &attribute.name.pos, reads)
+        }
+        Name::FunctionCall(ref call) => {
+            for parameter in call.parameters.iter() {
+                actual_reads(&parameter.actual, reads);
+            }
+        }
+        Name::CharacterLiteral(..) | Name::OperatorSymbol(..) | Name::All => {}
+    }
+}
+
+fn waveform_reads(waveform: &Waveform, reads: &mut Vec<(String, SrcPos)>) {
+    if let Waveform::Elements(ref elements) = waveform {
+        for element in elements.iter() {
+            expression_reads(&element.value, reads);
+        }
+    }
+}
+
+/// Whether a waveform of the right hand side has an element with an after clause
+fn is_delayed(rhs: &AssignmentRightHand<Waveform>) -> bool {
+    let is_delayed = |waveform: &Waveform| match waveform {
+        Waveform::Elements(ref elements) => elements.iter().any(|element| element.after.is_some()),
+        Waveform::Unaffected => false,
+    };
+    match rhs {
+        AssignmentRightHand::Simple(ref waveform) => is_delayed(waveform),
+        AssignmentRightHand::Conditional(ref conditionals) => {
+            conditionals
+                .conditionals
+                .iter()
+                .any(|conditional| is_delayed(&conditional.item))
+                || conditionals.else_item.as_ref().is_some_and(is_delayed)
+        }
+        AssignmentRightHand::Selected(ref selection) => selection
+            .alternatives
+            .iter()
+            .any(|alternative| is_delayed(&alternative.item)),
+    }
+}
+
+fn rhs_reads<T>(
+    rhs: &AssignmentRightHand<T>,
+    item_reads: fn(&T, &mut Vec<(String, SrcPos)>),
+    reads: &mut Vec<(String, SrcPos)>,
+) {
+    match rhs {
+        AssignmentRightHand::Simple(ref item) => item_reads(item, reads),
+        AssignmentRightHand::Conditional(ref conditionals) => {
+            for conditional in conditionals.conditionals.iter() {
+                expression_reads(&conditional.condition, reads);
+                item_reads(&conditional.item, reads);
+            }
+            if let Some(ref item) = conditionals.else_item {
+                item_reads(item, reads);
+            }
+        }
+        AssignmentRightHand::Selected(ref selection) => {
+            expression_reads(&selection.expression, reads);
+            for alternative in selection.alternatives.iter() {
+                item_reads(&alternative.item, reads);
+            }
+        }
+    }
+}
+
+/// The position of the first part of a statement which has one
+pub fn statement_pos(statement: &LabeledSequentialStatement) -> Option<SrcPos> {
+    if let Some(ref label) = statement.label {
+        return Some(label.pos.clone());
+    }
+    match statement.statement {
+        SequentialStatement::Wait(ref wait) => wait
+            .sensitivity_clause
+            .first()
+            .map(|name| name.pos.clone())
+            .or_else(|| wait.condition_clause.as_ref().map(|expr| expr.pos.clone()))
+            .or_else(|| wait.timeout_clause.as_ref().map(|expr| expr.pos.clone())),
+        SequentialStatement::Assert(ref assert) => Some(assert.condition.pos.clone()),
+        SequentialStatement::Report(ref report) => Some(report.report.pos.clone()),
+        SequentialStatement::VariableAssignment(ref assignment) => {
+            Some(assignment.target.pos.clone())
+        }
+        SequentialStatement::SignalAssignment(ref assignment) => {
+            Some(assignment.target.pos.clone())
+        }
+        SequentialStatement::ProcedureCall(ref call) => Some(call.name.pos.clone()),
+        SequentialStatement::If(ref ifs) => ifs
+            .conditionals
+            .first()
+            .map(|conditional| conditional.condition.pos.clone()),
+        SequentialStatement::Case(ref case) => Some(case.expression.pos.clone()),
+        SequentialStatement::Loop(ref lp) => match lp.iteration_scheme {
+            Some(IterationScheme::While(ref condition)) => Some(condition.pos.clone()),
+            Some(IterationScheme::For(ref index, _)) => Some(index.pos.clone()),
+            None => lp.statements.iter().filter_map(statement_pos).next(),
+        },
+        SequentialStatement::Next(ref next) => next
+            .loop_label
+            .as_ref()
+            .map(|label| label.pos.clone())
+            .or_else(|| next.condition.as_ref().map(|expr| expr.pos.clone())),
+        SequentialStatement::Exit(ref exit) => exit
+            .loop_label
+            .as_ref()
+            .map(|label| label.pos.clone())
+            .or_else(|| exit.condition.as_ref().map(|expr| expr.pos.clone())),
+        SequentialStatement::Return(ref ret) => {
+            ret.expression.as_ref().map(|expr| expr.pos.clone())
+        }
+        SequentialStatement::Null => None,
+    }
+}
+
+/// The name of the signal assigned by a target such as count or data(0)
+pub fn target_name(target: &Target) -> Option<String> {
+    let mut name = match target {
+        Target::Name(ref name) => name,
+        Target::Aggregate(..) => {
+            return None;
+        }
+    };
+    loop {
+        match name {
+            Name::Simple(ref symbol) => return Some(symbol.name_utf8()),
+            Name::Selected(ref prefix, _)
+            | Name::Indexed(ref prefix, _)
+            | Name::Slice(ref prefix, _) => name = &prefix.item,
+            _ => return None,
+        }
+    }
+}
+
+/// The lower case names of the signals assigned by the statements
+pub fn assigned_signals(statements: &[LabeledSequentialStatement], assigned: &mut Vec<String>) {
+    for statement in statements.iter() {
+        match statement.statement {
+            SequentialStatement::SignalAssignment(ref assignment) => {
+                if let Some(name) = target_name(&assignment.target.item) {
+                    let name = name.to_lowercase();
+                    if !assigned.contains(&name) {
+                        assigned.push(name);
+                    }
+                }
+            }
+            SequentialStatement::If(ref ifs) => {
+                for conditional in ifs.conditionals.iter() {
+                    assigned_signals(&conditional.item, assigned);
+                }
+                if let Some(ref statements) = ifs.else_item {
+                    assigned_signals(statements, assigned);
+                }
+            }
+            SequentialStatement::Case(ref case) => {
+                for alternative in case.alternatives.iter() {
+                    assigned_signals(&alternative.item, assigned);
+                }
+            }
+            SequentialStatement::Loop(ref lp) => assigned_signals(&lp.statements, assigned),
+            _ => {}
+        }
+    }
+}
+
+/// The signal of a condition which only reads one signal
+fn condition_signal(condition: &WithPos<Expression>) -> Option<String> {
+    let mut reads = Vec::new();
+    expression_reads(condition, &mut reads);
+    match reads.as_slice() {
+        [(name, _)] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn is_reset_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("rst") || name.contains("reset")
+}
+
+struct ProcessWalker<'a> {
+    structure: ProcessStructure<'a>,
+}
+
+impl<'a> ProcessWalker<'a> {
+    fn read(&mut self, expr: &WithPos<Expression>, clocked: bool) {
+        if !clocked {
+            expression_reads(expr, &mut self.structure.combinational_reads);
+        }
+    }
+
+    fn reset(
+        &mut self,
+        condition: &WithPos<Expression>,
+        kind: ResetKind,
+        signal: String,
+        statements: &[LabeledSequentialStatement],
+    ) {
+        let mut assigned = Vec::new();
+        assigned_signals(statements, &mut assigned);
+        self.structure.resets.push(Reset {
+            signal,
+            kind,
+            pos: condition.pos.clone(),
+            assigned,
+        });
+    }
+
+    fn ifs(&mut self, ifs: &Conditionals<Vec<LabeledSequentialStatement>>, clocked: bool) {
+        let edges: Vec<Option<ClockEdge>> = ifs
+            .conditionals
+            .iter()
+            .map(|conditional| clock_edge(&conditional.condition))
+            .collect();
+        let first_edge = edges.iter().position(Option::is_some);
+        for (i, (conditional, edge)) in ifs.conditionals.iter().zip(edges).enumerate() {
+            if let Some(edge) = edge {
+                self.structure.clock_edges.push(edge);
+                self.statements(&conditional.item, true);
+                continue;
+            }
+            let signal = condition_signal(&conditional.condition);
+            match signal {
+                Some(signal) if !clocked && first_edge.is_some_and(|edge| i < edge) => {
+                    self.reset(
+                        &conditional.condition,
+                        ResetKind::Asynchronous,
+                        signal,
+                        &conditional.item,
+                    );
+                }
+                Some(signal) if clocked && i == 0 && is_reset_name(&signal) => {
+                    self.reset(
+                        &conditional.condition,
+                        ResetKind::Synchronous,
+                        signal,
+                        &conditional.item,
+                    );
+                }
+                _ => {
+                    self.read(&conditional.condition, clocked);
+                    self.statements(&conditional.item, clocked);
+                }
+            }
+        }
+        if let Some(ref statements) = ifs.else_item {
+            self.statements(statements, clocked);
+        }
+    }
+
+    fn statements(&mut self, statements: &[LabeledSequentialStatement], mut clocked: bool) {
+        for statement in statements.iter() {
+            match statement.statement {
+                SequentialStatement::Wait(ref wait) => {
+                    if wait.timeout_clause.is_some() {
+                        self.structure.timed = true;
+                    }
+                    if let Some(ref condition) = wait.condition_clause {
+                        match clock_edge(condition) {
+                            Some(edge) => {
+                                self.structure.clock_edges.push(edge);
+                                clocked = true;
+                            }
+                            None => self.read(condition, clocked),
+                        }
+                    }
+                }
+                SequentialStatement::SignalAssignment(ref assignment) => {
+                    if is_delayed(&assignment.rhs) {
+                        self.structure.timed = true;
+                    }
+                    if clocked {
+                        if let Some(name) = target_name(&assignment.target.item) {
+                            let registered = &mut self.structure.registered;
+                            if !registered
+                                .iter()
+                                .any(|(other, _)| other.eq_ignore_ascii_case(&name))
+                            {
+                                registered.push((name, assignment.target.pos.clone()));
+                            }
+                        }
+                    } else {
+                        rhs_reads(
+                            &assignment.rhs,
+                            waveform_reads,
+                            &mut self.structure.combinational_reads,
+                        );
+                    }
+                }
+                SequentialStatement::VariableAssignment(ref assignment) if !clocked => {
+                    rhs_reads(
+                        &assignment.rhs,
+                        expression_reads,
+                        &mut self.structure.combinational_reads,
+                    );
+                }
+                SequentialStatement::ProcedureCall(ref call) if !clocked => {
+                    for parameter in call.parameters.iter() {
+                        actual_reads(&parameter.actual, &mut self.structure.combinational_reads);
+                    }
+                }
+                SequentialStatement::If(ref ifs) => self.ifs(ifs, clocked),
+                SequentialStatement::Case(ref case) => {
+                    self.read(&case.expression, clocked);
+                    for alternative in case.alternatives.iter() {
+                        self.statements(&alternative.item, clocked);
+                    }
+                }
+                SequentialStatement::Loop(ref lp) => {
+                    if let Some(IterationScheme::While(ref condition)) = lp.iteration_scheme {
+                        self.read(condition, clocked);
+                    }
+                    self.statements(&lp.statements, clocked);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The structure of the process
+pub fn process_structure<'a>(label: String, process: &'a ProcessStatement) -> ProcessStructure<'a> {
+    let mut walker = ProcessWalker {
+        structure: ProcessStructure {
+            label,
+            process,
+            pos: process
+                .sensitivity_list
+                .first()
+                .map(|name| name.pos.clone())
+                .or_else(|| process.statements.iter().filter_map(statement_pos).next()),
+            clock_edges: Vec::new(),
+            resets: Vec::new(),
+            registered: Vec::new(),
+            combinational_reads: Vec::new(),
+            timed: false,
+        },
+    };
+    walker.statements(&process.statements, false);
+    walker.structure
+}
+
+fn concurrent_statements<'a>(
+    prefix: &str,
+    statements: &'a [LabeledConcurrentStatement],
+    structure: &mut ArchitectureStructure<'a>,
+) {
+    for statement in statements {
+        let label = statement
+            .label
+            .as_ref()
+            .map(|label| format!("{}{}", prefix, label.item.name_utf8()))
+            .unwrap_or_else(|| prefix.trim_end_matches('.').to_owned());
+        let nested = format!("{}.", label);
+        match statement.statement {
+            ConcurrentStatement::Process(ref process) => {
+                let process = match statement.label {
+                    Some(ref ident) => {
+                        let mut process = process_structure(label, process);
+                        process.pos = Some(ident.pos.clone());
+                        process
+                    }
+                    None => process_structure(String::new(), process),
+                };
+                structure.processes.push(process);
+            }
+            ConcurrentStatement::Assignment(ref assignment) => {
+                let mut reads = Vec::new();
+                rhs_reads(&assignment.rhs, waveform_reads, &mut reads);
+                structure.concurrent_reads.extend(reads.iter().cloned());
+                structure.concurrent_assignments.push(ConcurrentAssignment {
+                    target: target_name(&assignment.target.item),
+                    pos: assignment.target.pos.clone(),
+                    reads,
+                    delayed: is_delayed(&assignment.rhs),
+                });
+            }
+            ConcurrentStatement::Block(ref block) => {
+                concurrent_statements(&nested, &block.statements, structure);
+            }
+            ConcurrentStatement::ForGenerate(ref gen) => {
+                concurrent_statements(&nested, &gen.body.statements, structure);
+            }
+            ConcurrentStatement::IfGenerate(ref gen) => {
+                for conditional in gen.conditionals.iter() {
+                    concurrent_statements(&nested, &conditional.item.statements, structure);
+                }
+                if let Some(ref body) = gen.else_item {
+                    concurrent_statements(&nested, &body.statements, structure);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The structure of each architecture of the files in the order of the files
+pub fn architecture_structures<'a>(files: &[&'a SourceFile]) -> Vec<ArchitectureStructure<'a>> {
+    let mut structures = Vec::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            if let LibraryUnit::ArchitectureBody {
+                ref ident,
+                ref entity_name,
+                ref statements,
+                ..
+            } = design_unit.library_unit
+            {
+                let mut structure = ArchitectureStructure {
+                    architecture: UnitId::architecture(
+                        &file.library_name,
+                        &entity_name.item.name_utf8(),
+                        &ident.item.name_utf8(),
+                    ),
+                    processes: Vec::new(),
+                    concurrent_reads: Vec::new(),
+                    concurrent_assignments: Vec::new(),
+                };
+                concurrent_statements("", statements, &mut structure);
+                structures.push(structure);
+            }
+        }
+    }
+    structures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn clock_edges_resets_and_registers() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  async : process (clk, rst)
+  begin
+    if rst = '1' then
+      count <= 0;
+    elsif rising_edge(clk) then
+      count <= count + 1;
+      data <= input;
+    end if;
+  end process;
+
+  gen : if true generate
+    sync : process
+    begin
+      wait until clk'event and clk = '0';
+      if sync_reset then
+        flag <= '0';
+      else
+        flag <= enable and clk;
+      end if;
+    end process;
+  end generate;
+
+  process (a, b)
+  begin
+    sum <= a + b;
+  end process;
+
+  q <= clk and en;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let files = project.project.files();
+        let structures = architecture_structures(&files);
+        assert_eq!(structures.len(), 1);
+        let structure = &structures[0];
+        assert_eq!(
+            structure.architecture,
+            UnitId::architecture("lib", "ent", "rtl")
+        );
+
+        type Resets = Vec<(String, ResetKind, Vec<String>)>;
+        type Summary = (
+            String,
+            Vec<(String, Edge)>,
+            Resets,
+            Vec<String>,
+            Vec<String>,
+        );
+        let summary: Vec<Summary> = structure
+            .processes
+            .iter()
+            .map(|process| {
+                (
+                    process.label.clone(),
+                    process
+                        .clock_edges
+                        .iter()
+                        .map(|edge| (edge.clock.clone(), edge.edge))
+                        .collect(),
+                    process
+                        .resets
+                        .iter()
+                        .map(|reset| (reset.signal.clone(), reset.kind, reset.assigned.clone()))
+                        .collect(),
+                    process
+                        .registered
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                    process
+                        .combinational_reads
+                        .iter()
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                )
+            })
+            .collect();
+        let strings = |strings: &[&str]| -> Vec<String> {
+            strings.iter().map(|string| (*string).to_owned()).collect()
+        };
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "async".to_owned(),
+                    vec![("clk".to_owned(), Edge::Rising)],
+                    vec![(
+                        "rst".to_owned(),
+                        ResetKind::Asynchronous,
+                        strings(&["count"])
+                    )],
+                    strings(&["count", "data"]),
+                    vec![],
+                ),
+                (
+                    "gen.sync".to_owned(),
+                    vec![("clk".to_owned(), Edge::Falling)],
+                    vec![(
+                        "sync_reset".to_owned(),
+                        ResetKind::Synchronous,
+                        strings(&["flag"])
+                    )],
+                    strings(&["flag"]),
+                    vec![],
+                ),
+                (String::new(), vec![], vec![], vec![], strings(&["a", "b"])),
+            ]
+        );
+        assert!(structure.processes[0].is_clocked());
+        assert_eq!(structure.clocks(), strings(&["clk"]));
+        let concurrent: Vec<(String, usize)> = structure
+            .concurrent_reads
+            .iter()
+            .map(|(name, pos)| (name.clone(), pos.start))
+            .collect();
+        assert_eq!(
+            concurrent,
+            vec![
+                ("clk".to_owned(), code.find("clk and en").unwrap()),
+                ("en".to_owned(), code.find("en;").unwrap()),
+            ]
+        );
+    }
+}
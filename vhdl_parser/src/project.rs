@@ -0,0 +1,1075 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use analysis::analyze;
+use ast::DesignFile;
+use cache::{content_hash, ParseCache};
+use config::Config;
+use dependency_graph::DependencyGraph;
+use incremental::reparse;
+use library::{DesignRoot, UnitId};
+use memory::{memory_report, CacheMemory, MemoryReport};
+use message::{Message, Severity};
+use name_resolution::NameResolution;
+use source::Source;
+use standard::VHDLStandard;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use verilog_parser::{
+    is_systemverilog_file, is_verilog_file, parse_verilog_source, VerilogFile, VerilogOptions,
+};
+use watcher::FileChange;
+use {ParseOptions, ParserError, VHDLParser};
+
+extern crate fnv;
+use self::fnv::{FnvHashMap, FnvHashSet};
+
+/// A parsed source file belonging to a library
+#[derive(Clone)]
+pub struct SourceFile {
+    pub library_name: String,
+    pub source: Source,
+    pub design_file: DesignFile,
+    pub messages: Vec<Message>,
+    pub content_hash: u64,
+    /// The options the contents were parsed with
+    pub parse_options: ParseOptions,
+}
+
+impl SourceFile {
+    pub fn file_name(&self) -> &str {
+        self.source.file_name().unwrap_or("<unknown file>")
+    }
+}
+
+/// A parsed Verilog or SystemVerilog source file whose modules belong to a library
+#[derive(Clone)]
+pub struct VerilogSourceFile {
+    pub library_name: String,
+    pub source: Source,
+    pub verilog_file: VerilogFile,
+    pub messages: Vec<Message>,
+}
+
+impl VerilogSourceFile {
+    pub fn file_name(&self) -> &str {
+        self.source.file_name().unwrap_or("<unknown file>")
+    }
+}
+
+/// A collection of source files which are kept up to date incrementally
+/// Only files which have changed are parsed again
+/// The files are shared with the snapshots of the project such that taking a snapshot
+/// is cheap and a file is only copied when it is modified while a snapshot refers to it
+pub struct Project {
+    parser: VHDLParser,
+    files: FnvHashMap<String, Arc<SourceFile>>,
+    verilog_files: FnvHashMap<String, Arc<VerilogSourceFile>>,
+    interface_only_libraries: FnvHashSet<String>,
+    defer_bodies: bool,
+    cache: Option<ParseCache>,
+    config: Config,
+}
+
+/// Errors within code the user does not own are reported as warnings
+fn downgrade_errors(messages: &mut [Message]) {
+    for message in messages.iter_mut() {
+        message.severity = Severity::Warning;
+    }
+}
+
+impl Project {
+    pub fn new() -> Project {
+        Project {
+            parser: VHDLParser::new(),
+            files: FnvHashMap::default(),
+            verilog_files: FnvHashMap::default(),
+            interface_only_libraries: FnvHashSet::default(),
+            defer_bodies: false,
+            cache: None,
+            config: Config::default(),
+        }
+    }
+
+    /// Add the files of all libraries of the configuration
+    /// Returns the file names which could not be read
+    pub fn add_config(&mut self, config: &Config) -> Vec<(String, io::Error)> {
+        self.add_config_with_progress(config, &mut |_, _| {})
+    }
+
+    /// Add the files of all libraries of the configuration where the progress is
+    /// called with the number of files done and the total after each file
+    pub fn add_config_with_progress(
+        &mut self,
+        config: &Config,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Vec<(String, io::Error)> {
+        self.config = config.clone();
+        let mut errors = Vec::new();
+        let mut files = Vec::new();
+        for library in config.iter_libraries() {
+            self.set_interface_only(library.name(), library.is_interface_only());
+            match library.file_names() {
+                Ok(file_names) => {
+                    for file_name in file_names {
+                        files.push((library.name(), file_name));
+                    }
+                }
+                Err(msg) => {
+                    errors.push((
+                        library.name().to_owned(),
+                        io::Error::new(io::ErrorKind::InvalidInput, msg),
+                    ));
+                }
+            }
+        }
+
+        let total = files.len();
+        progress(0, total);
+        for (done, (library_name, file_name)) in files.into_iter().enumerate() {
+            let source = Source::from_file(&file_name);
+            if let Err(err) = self.add_source(library_name, source, self.defer_bodies) {
+                errors.push((file_name, err));
+            }
+            progress(done + 1, total);
+        }
+        errors
+    }
+
+    /// Only parse the interface of the design units of files added from a configuration,
+    /// the contents of architectures and package bodies are parsed on demand by
+    /// parse_deferred_bodies which shortens the time until a large project is indexed
+    pub fn set_defer_bodies(&mut self, defer_bodies: bool) {
+        self.defer_bodies = defer_bodies;
+    }
+
+    /// Only parse the interface of design units within the library and downgrade its errors to warnings
+    /// Takes effect for files updated after the call
+    pub fn set_interface_only(&mut self, library_name: &str, interface_only: bool) {
+        let library_name = library_name.to_lowercase();
+        if interface_only {
+            self.interface_only_libraries.insert(library_name);
+        } else {
+            self.interface_only_libraries.remove(&library_name);
+        }
+    }
+
+    fn is_interface_only(&self, library_name: &str) -> bool {
+        self.interface_only_libraries
+            .contains(&library_name.to_lowercase())
+    }
+
+    /// Keep parse results in the directory such that unchanged files are not parsed
+    /// again when the project is created the next time
+    pub fn set_cache_directory(&mut self, directory: &Path) -> io::Result<()> {
+        self.cache = Some(ParseCache::new(directory)?);
+        Ok(())
+    }
+
+    fn parse(
+        &self,
+        source: &Source,
+        options: &ParseOptions,
+    ) -> io::Result<(DesignFile, Vec<Message>)> {
+        let mut messages = Vec::new();
+        let result = self
+            .parser
+            .parse_design_source_with_options(source, options, &mut messages);
+
+        let design_file = match result {
+            Ok(design_file) => design_file,
+            Err(ParserError::Message(msg)) => {
+                messages.push(msg);
+                DesignFile {
+                    design_units: Vec::new(),
+                    deferred_bodies: Vec::new(),
+                    design_unit_ends: Vec::new(),
+                }
+            }
+            Err(ParserError::IOError(err)) => {
+                return Err(err);
+            }
+        };
+        Ok((design_file, messages))
+    }
+
+    /// Parse the source unless the parse result of the same contents is cached
+    fn parse_cached(
+        &self,
+        source: &Source,
+        options: &ParseOptions,
+    ) -> io::Result<(DesignFile, Vec<Message>)> {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => {
+                return self.parse(source, options);
+            }
+        };
+
+        let key = ParseCache::key(&source.contents()?.bytes, options);
+        if let Some(result) = cache.load(&self.parser, source, key) {
+            return Ok(result);
+        }
+
+        let (design_file, messages) = self.parse(source, options)?;
+        // The cache is only an optimization, failing to store an entry is not an error
+        let _ = cache.store(key, &design_file, &messages);
+        Ok((design_file, messages))
+    }
+
+    /// The language standard and defines of the configuration, interface only
+    /// mode is controlled by set_interface_only
+    fn parse_options(&self, library_name: &str, source: &Source) -> ParseOptions {
+        let mut options = self.config.parse_options(library_name, &source_key(source));
+        options.interface_only = self.is_interface_only(library_name);
+        options
+    }
+
+    /// Parse the file and add it to the library, replaces any previous version of the file
+    pub fn update_file(&mut self, library_name: &str, file_name: &str) -> io::Result<()> {
+        let source = Source::from_file(file_name);
+        self.update_source(library_name, source)
+    }
+
+    /// Parse the source and add it to the library, replaces any previous version of the source
+    /// Files named .v, .vh, .sv or .svh are parsed as Verilog or SystemVerilog
+    pub fn update_source(&mut self, library_name: &str, source: Source) -> io::Result<()> {
+        self.add_source(library_name, source, false)
+    }
+
+    /// The contents of architectures and package bodies are deferred outside of
+    /// interface only libraries when requested
+    fn add_source(
+        &mut self,
+        library_name: &str,
+        source: Source,
+        defer_bodies: bool,
+    ) -> io::Result<()> {
+        if is_verilog_file(&source_key(&source)) {
+            return self.update_verilog_source(library_name, source);
+        }
+
+        let mut options = self.parse_options(library_name, &source);
+        let interface_only = options.interface_only;
+        options.interface_only = interface_only || defer_bodies;
+        let content_hash = content_hash(&source.contents()?.bytes);
+        let key = source_key(&source);
+        let reparsed = self
+            .files
+            .get(&key)
+            .and_then(|file| reparse(&self.parser, file, &source, &options));
+        let (mut design_file, mut messages) = match reparsed {
+            Some(result) => result,
+            None => self.parse_cached(&source, &options)?,
+        };
+
+        if interface_only {
+            design_file.deferred_bodies.clear();
+            downgrade_errors(&mut messages);
+        }
+
+        self.files.insert(
+            key,
+            Arc::new(SourceFile {
+                library_name: library_name.to_owned(),
+                source,
+                design_file,
+                messages,
+                content_hash,
+                parse_options: options,
+            }),
+        );
+        Ok(())
+    }
+
+    /// The module headers are parsed with the defines of the configuration
+    fn update_verilog_source(&mut self, library_name: &str, source: Source) -> io::Result<()> {
+        let key = source_key(&source);
+        let options = VerilogOptions {
+            systemverilog: is_systemverilog_file(&key),
+            defines: self.parse_options(library_name, &source).defines,
+        };
+        let mut messages = Vec::new();
+        let verilog_file = parse_verilog_source(&source, &options, &mut messages)?;
+
+        if self.is_interface_only(library_name) {
+            downgrade_errors(&mut messages);
+        }
+
+        self.verilog_files.insert(
+            key,
+            Arc::new(VerilogSourceFile {
+                library_name: library_name.to_owned(),
+                source,
+                verilog_file,
+                messages,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Parse the deferred contents of architectures and package bodies of all files
+    /// Returns the number of files which were parsed
+    pub fn parse_deferred_bodies(&mut self) -> usize {
+        let file_names: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(_, file)| !file.design_file.deferred_bodies.is_empty())
+            .map(|(file_name, _)| file_name.clone())
+            .collect();
+        file_names
+            .iter()
+            .filter(|file_name| self.parse_deferred_bodies_of_file(file_name))
+            .count()
+    }
+
+    /// Parse the deferred contents of architectures and package bodies of the file
+    /// A file which changed since it was added is parsed again entirely
+    /// Returns false when the file has no deferred bodies
+    pub fn parse_deferred_bodies_of_file(&mut self, file_name: &str) -> bool {
+        let (library_name, source) = match self.files.get(file_name) {
+            Some(file) if !file.design_file.deferred_bodies.is_empty() => {
+                (file.library_name.clone(), file.source.clone())
+            }
+            _ => {
+                return false;
+            }
+        };
+
+        let unchanged = source
+            .contents()
+            .map(|contents| content_hash(&contents.bytes) == self.files[file_name].content_hash)
+            .unwrap_or(false);
+        if !unchanged {
+            if self.update_source(&library_name, source).is_err() {
+                self.remove_file(file_name);
+            }
+            return true;
+        }
+
+        let options = self.parse_options(&library_name, &source);
+        let file = Arc::make_mut(self.files.get_mut(file_name).unwrap());
+        let result = self.parser.parse_deferred_bodies(
+            &source,
+            &options,
+            &mut file.design_file,
+            &mut file.messages,
+        );
+        if result.is_err() {
+            file.design_file.deferred_bodies.clear();
+        }
+        true
+    }
+
+    /// Remove a file from the project, returns true if the file was present
+    pub fn remove_file(&mut self, file_name: &str) -> bool {
+        let removed = self.files.remove(file_name).is_some();
+        self.verilog_files.remove(file_name).is_some() || removed
+    }
+
+    /// Apply changes reported by a file watcher
+    /// Returns the file names which could not be read
+    pub fn apply_changes(&mut self, changes: &[FileChange]) -> Vec<(String, io::Error)> {
+        let mut errors = Vec::new();
+        for change in changes {
+            match change {
+                FileChange::Created {
+                    library_name,
+                    file_name,
+                }
+                | FileChange::Modified {
+                    library_name,
+                    file_name,
+                } => {
+                    if let Err(err) = self.update_file(library_name, file_name) {
+                        self.remove_file(file_name);
+                        errors.push((file_name.clone(), err));
+                    }
+                }
+                FileChange::Removed { file_name, .. } => {
+                    self.remove_file(file_name);
+                }
+            }
+        }
+        errors
+    }
+
+    /// The language standard the file was parsed with
+    pub fn standard(&self, file: &SourceFile) -> VHDLStandard {
+        self.parse_options(&file.library_name, &file.source)
+            .standard
+    }
+
+    /// The configuration the files were added from
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_file(&self, file_name: &str) -> Option<&SourceFile> {
+        self.files.get(file_name).map(|file| file.as_ref())
+    }
+
+    /// Iterate over all files sorted by file name
+    pub fn files(&self) -> Vec<&SourceFile> {
+        let mut files: Vec<&SourceFile> = self.files.values().map(|file| file.as_ref()).collect();
+        files.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        files
+    }
+
+    /// Iterate over all Verilog and SystemVerilog files sorted by file name
+    pub fn verilog_files(&self) -> Vec<&VerilogSourceFile> {
+        let mut files: Vec<&VerilogSourceFile> = self
+            .verilog_files
+            .values()
+            .map(|file| file.as_ref())
+            .collect();
+        files.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        files
+    }
+
+    /// The current files of the project which stay unchanged when the project is updated
+    /// The snapshot can be sent to another thread to analyze the project there while the
+    /// project is updated and queried
+    pub fn snapshot(&self) -> ProjectSnapshot {
+        let mut files: Vec<Arc<SourceFile>> = self.files.values().cloned().collect();
+        files.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        let mut verilog_files: Vec<Arc<VerilogSourceFile>> =
+            self.verilog_files.values().cloned().collect();
+        verilog_files.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        ProjectSnapshot {
+            files,
+            verilog_files,
+            interface_only_libraries: self.interface_only_libraries.clone(),
+        }
+    }
+
+    /// All messages of all files
+    pub fn messages(&self) -> Vec<Message> {
+        self.snapshot().messages()
+    }
+
+    /// Semantic messages of all libraries such as duplicate design units, missing library
+    /// clauses or circular dependencies
+    pub fn analyze(&self) -> Vec<Message> {
+        self.snapshot().analyze()
+    }
+
+    /// The declarations denoted by the names of all files
+    pub fn resolve(&self) -> NameResolution {
+        self.snapshot().resolve()
+    }
+
+    /// The declarations denoted by the names of the files where only the design units of
+    /// the files and the units they depend on are resolved
+    pub fn resolve_files(&self, file_names: &BTreeSet<String>) -> NameResolution {
+        let files = self.files();
+        let root = DesignRoot::new(&files);
+        let unit_ids = root
+            .libraries()
+            .flat_map(|library| library.units())
+            .filter(|unit| file_names.contains(unit.file_name))
+            .map(|unit| unit.id.clone())
+            .collect();
+        NameResolution::of_units(&root, &unit_ids)
+    }
+
+    /// The file together with the files of the design units which directly depend on
+    /// a design unit of the file
+    pub fn dependent_files(&self, file_name: &str) -> BTreeSet<String> {
+        let graph = self.dependency_graph();
+        let unit_ids: BTreeSet<&UnitId> = graph
+            .units()
+            .filter(|(_, node)| node.file_name == file_name)
+            .map(|(unit_id, _)| unit_id)
+            .collect();
+
+        let mut file_names = BTreeSet::new();
+        file_names.insert(file_name.to_owned());
+        for (_, node) in graph.units() {
+            if node.dependencies.iter().any(|dep| unit_ids.contains(dep)) {
+                file_names.insert(node.file_name.clone());
+            }
+        }
+        file_names
+    }
+
+    /// The estimated memory of each library and file together with the symbol table
+    /// and the parse cache
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = memory_report(&self.files(), &self.parser.symtab);
+        for library in report.libraries.iter_mut() {
+            library.interface_only = self.is_interface_only(&library.library_name);
+        }
+        report.cache = self.cache.as_ref().and_then(|cache| {
+            cache
+                .disk_usage()
+                .ok()
+                .map(|(entries, disk_bytes)| CacheMemory {
+                    entries,
+                    disk_bytes,
+                })
+        });
+        report
+    }
+
+    /// The dependencies between the design units of all files
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(&self.files())
+    }
+}
+
+impl Default for Project {
+    fn default() -> Project {
+        Project::new()
+    }
+}
+
+/// The files of a project at one point in time
+/// Symbols and sources are shared with the project, the snapshot is Send and Sync such
+/// that the project can be analyzed by other threads
+pub struct ProjectSnapshot {
+    files: Vec<Arc<SourceFile>>,
+    verilog_files: Vec<Arc<VerilogSourceFile>>,
+    interface_only_libraries: FnvHashSet<String>,
+}
+
+impl ProjectSnapshot {
+    /// All files sorted by file name
+    pub fn files(&self) -> Vec<&SourceFile> {
+        self.files.iter().map(|file| file.as_ref()).collect()
+    }
+
+    pub fn get_file(&self, file_name: &str) -> Option<&SourceFile> {
+        self.files
+            .binary_search_by(|file| file.file_name().cmp(file_name))
+            .ok()
+            .map(|idx| self.files[idx].as_ref())
+    }
+
+    /// All messages of all files
+    pub fn messages(&self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        for file in self.files.iter() {
+            messages.extend(file.messages.iter().cloned());
+        }
+        for file in self.verilog_files.iter() {
+            messages.extend(file.messages.iter().cloned());
+        }
+        messages
+    }
+
+    /// Semantic messages of all libraries such as duplicate design units, missing library
+    /// clauses or circular dependencies
+    pub fn analyze(&self) -> Vec<Message> {
+        let files = self.files();
+        let root = DesignRoot::new(&files);
+        let mut messages = root.messages().to_vec();
+        messages.extend(analyze(&root));
+        messages.extend(DependencyGraph::from_root(&root).cycle_messages());
+
+        for message in messages.iter_mut() {
+            let interface_only = message
+                .pos
+                .source
+                .file_name()
+                .and_then(|file_name| self.get_file(file_name))
+                .map(|file| {
+                    self.interface_only_libraries
+                        .contains(&file.library_name.to_lowercase())
+                })
+                .unwrap_or(false);
+            if interface_only {
+                message.severity = Severity::Warning;
+            }
+        }
+        messages
+    }
+
+    /// The declarations denoted by the names of all files
+    pub fn resolve(&self) -> NameResolution {
+        let files = self.files();
+        NameResolution::new(&DesignRoot::new(&files))
+    }
+}
+
+/// Sources without file name are keyed on an empty string
+fn source_key(source: &Source) -> String {
+    source.file_name().unwrap_or("").to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::LibraryUnit;
+    extern crate tempfile;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+    use std::thread;
+
+    fn write_file(path: &Path, contents: &str) -> String {
+        let mut file = File::create(path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn update_file_parses_design_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(&dir.path().join("ent.vhd"), "entity ent is end entity;");
+
+        let mut project = Project::new();
+        project.update_file("lib", &file_name).unwrap();
+
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.library_name, "lib");
+        assert_eq!(file.design_file.design_units.len(), 1);
+        assert_eq!(file.messages, Vec::new());
+    }
+
+    #[test]
+    fn update_file_replaces_previous_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ent.vhd");
+        let file_name = write_file(&path, "entity ent is end entity;");
+
+        let mut project = Project::new();
+        project.update_file("lib", &file_name).unwrap();
+        write_file(
+            &path,
+            "entity ent is end entity; entity ent2 is end entity;",
+        );
+        project.update_file("lib", &file_name).unwrap();
+
+        assert_eq!(project.files().len(), 1);
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file.design_units.len(), 2);
+    }
+
+    #[test]
+    fn edited_source_moves_unchanged_design_units() {
+        let code = "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal foo : natural;
+begin
+end architecture;
+
+package pkg is
+end package;
+";
+        let edited = code.replace("signal foo", "signal foo, bar");
+        let source = |code: &str| Source::from_file_contents("ent.vhd", code).unwrap();
+
+        let mut project = Project::new();
+        project.update_source("lib", source(code)).unwrap();
+        project.update_source("lib", source(&edited)).unwrap();
+
+        let file = project.get_file("ent.vhd").unwrap();
+        assert_eq!(file.source, source(&edited));
+        assert_eq!(file.messages, Vec::new());
+        assert_eq!(file.design_file.design_units.len(), 3);
+        match file.design_file.design_units[2].library_unit {
+            LibraryUnit::PackageDeclaration { ref ident, .. } => {
+                assert_eq!(ident.pos, source(&edited).first_substr_pos("pkg"));
+            }
+            _ => panic!("Expected package"),
+        }
+    }
+
+    #[test]
+    fn snapshot_is_unchanged_by_updates() {
+        let source = |code: &str| Source::from_file_contents("ent.vhd", code).unwrap();
+        let mut project = Project::new();
+        project
+            .update_source("lib", source("entity ent is\nend entity;\n"))
+            .unwrap();
+
+        let snapshot = project.snapshot();
+        project
+            .update_source(
+                "lib",
+                source("entity ent is\nend entity;\nentity ent is\nend entity;\n"),
+            )
+            .unwrap();
+        project
+            .update_source(
+                "lib",
+                Source::from_file_contents("pkg.vhd", "package pkg is\nend package;\n").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(project.analyze().len(), 1);
+
+        // The snapshot is analyzed by another thread while the project is queried
+        let analysis = thread::spawn(move || (snapshot.files().len(), snapshot.analyze()));
+        assert_eq!(project.files().len(), 2);
+        let (files, messages) = analysis.join().unwrap();
+        assert_eq!(files, 1);
+        assert_eq!(messages, Vec::new());
+    }
+
+    #[test]
+    fn update_missing_file_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("missing.vhd");
+        let mut project = Project::new();
+        assert!(project
+            .update_file("lib", file_name.to_str().unwrap())
+            .is_err());
+        assert_eq!(project.files().len(), 0);
+    }
+
+    #[test]
+    fn apply_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(&dir.path().join("ent.vhd"), "entity ent is end entity;");
+        let missing = dir.path().join("missing.vhd").to_str().unwrap().to_owned();
+
+        let mut project = Project::new();
+        let errors = project.apply_changes(&[
+            FileChange::Created {
+                library_name: "lib".to_owned(),
+                file_name: file_name.clone(),
+            },
+            FileChange::Modified {
+                library_name: "lib".to_owned(),
+                file_name: missing.clone(),
+            },
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, missing);
+        assert!(project.get_file(&file_name).is_some());
+
+        project.apply_changes(&[FileChange::Removed {
+            library_name: "lib".to_owned(),
+            file_name: file_name.clone(),
+        }]);
+        assert!(project.get_file(&file_name).is_none());
+    }
+
+    #[test]
+    fn messages_of_all_files() {
+        let mut project = Project::new();
+        project
+            .update_source("lib", Source::from_str("entity ent is").unwrap())
+            .unwrap();
+        assert_eq!(project.messages().len(), 1);
+    }
+
+    #[test]
+    fn add_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name1 = write_file(&dir.path().join("a.vhd"), "entity ent is end entity;");
+        let file_name2 = write_file(&dir.path().join("b.vhd"), "entity ent is end entity;");
+        let config = Config::parse(
+            "
+[libraries]
+lib1.files = ['a.vhd']
+lib2.files = ['*.vhd']
+lib2.exclude = ['a.vhd']
+",
+            dir.path(),
+        )
+        .unwrap();
+
+        let mut project = Project::new();
+        assert!(project.add_config(&config).is_empty());
+        assert_eq!(project.get_file(&file_name1).unwrap().library_name, "lib1");
+        assert_eq!(project.get_file(&file_name2).unwrap().library_name, "lib2");
+        assert_eq!(project.analyze(), vec![]);
+    }
+
+    #[test]
+    fn add_config_with_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("a.vhd"), "entity a is end entity;");
+        write_file(&dir.path().join("b.vhd"), "entity b is end entity;");
+        let config = Config::parse("[libraries]\nlib.files = ['*.vhd']", dir.path()).unwrap();
+
+        let mut project = Project::new();
+        let mut reports = Vec::new();
+        let errors = project
+            .add_config_with_progress(&config, &mut |done, total| reports.push((done, total)));
+        assert!(errors.is_empty());
+        assert_eq!(reports, vec![(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn deferred_bodies_are_parsed_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(
+            &dir.path().join("a.vhd"),
+            "
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal foo : natural;
+begin
+  foo <= 0;
+end architecture;",
+        );
+        let config = Config::parse("[libraries]\nlib.files = ['*.vhd']", dir.path()).unwrap();
+
+        let mut project = Project::new();
+        project.set_defer_bodies(true);
+        assert!(project.add_config(&config).is_empty());
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file.deferred_bodies.len(), 1);
+
+        assert_eq!(project.parse_deferred_bodies(), 1);
+        assert_eq!(project.parse_deferred_bodies(), 0);
+        let design_file = project.get_file(&file_name).unwrap().design_file.clone();
+        match design_file.design_units[1].library_unit {
+            LibraryUnit::ArchitectureBody {
+                ref decl,
+                ref statements,
+                ..
+            } => assert_eq!((decl.len(), statements.len()), (1, 1)),
+            ref library_unit => panic!("Expected architecture, got {:?}", library_unit),
+        }
+        assert_eq!(project.messages(), Vec::new());
+
+        project.update_file("lib", &file_name).unwrap();
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file, design_file);
+    }
+
+    #[test]
+    fn deferred_bodies_of_changed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.vhd");
+        let file_name = write_file(
+            &path,
+            "architecture rtl of ent is\nbegin\nend architecture;",
+        );
+        let config = Config::parse("[libraries]\nlib.files = ['*.vhd']", dir.path()).unwrap();
+
+        let mut project = Project::new();
+        project.set_defer_bodies(true);
+        assert!(project.add_config(&config).is_empty());
+        write_file(&path, "entity ent is\nend entity;");
+
+        assert!(project.parse_deferred_bodies_of_file(&file_name));
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file.design_units.len(), 1);
+        assert_eq!(file.design_file.deferred_bodies, Vec::new());
+        assert!(!project.parse_deferred_bodies_of_file(&file_name));
+    }
+
+    #[test]
+    fn interface_only_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(
+            &dir.path().join("a.vhd"),
+            "
+use work.missing.all;
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  not valid vhdl
+begin
+end architecture;
+
+entity ent2 is
+end entity",
+        );
+
+        let mut project = Project::new();
+        project.set_interface_only("Vendor", true);
+        project.update_file("vendor", &file_name).unwrap();
+
+        let file = project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file.design_units.len(), 2);
+        assert_eq!(file.design_file.deferred_bodies, Vec::new());
+        let messages = project.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].severity, Severity::Warning);
+
+        let messages = project.analyze();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].severity, Severity::Warning);
+
+        project.set_interface_only("vendor", false);
+        project.update_file("vendor", &file_name).unwrap();
+        assert!(project
+            .messages()
+            .iter()
+            .all(|message| message.severity == Severity::Error));
+    }
+
+    #[test]
+    fn standard_and_defines_of_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let code = "
+entity context is
+end entity;
+
+`if DEBUG = \"true\" then
+entity dbg is
+end entity;
+`end if
+";
+        let file_name1 = write_file(&dir.path().join("a.vhd"), code);
+        let file_name2 = write_file(&dir.path().join("b.vhd"), code);
+        let config = Config::parse(
+            "
+[libraries]
+lib.files = ['*.vhd']
+lib.standard = '1993'
+lib.defines = { DEBUG = 'false' }
+
+[[files]]
+pattern = 'b.vhd'
+standard = '2008'
+defines = { debug = 'true' }
+",
+            dir.path(),
+        )
+        .unwrap();
+
+        let mut project = Project::new();
+        assert!(project.add_config(&config).is_empty());
+
+        // context is not a keyword before VHDL-2008
+        let file = project.get_file(&file_name1).unwrap();
+        assert_eq!(file.messages, vec![]);
+        assert_eq!(file.design_file.design_units.len(), 1);
+
+        // The dbg entity is only analyzed when DEBUG is true
+        let file = project.get_file(&file_name2).unwrap();
+        assert_eq!(file.messages.len(), 1);
+        assert_eq!(file.design_file.design_units.len(), 1);
+    }
+
+    #[test]
+    fn parse_results_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let file_name = write_file(&dir.path().join("a.vhd"), "entity ent is end entity;");
+
+        let mut project = Project::new();
+        project.set_cache_directory(&cache_dir).unwrap();
+        project.update_file("lib", &file_name).unwrap();
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+
+        let mut cached_project = Project::new();
+        cached_project.set_cache_directory(&cache_dir).unwrap();
+        cached_project.update_file("lib", &file_name).unwrap();
+        let file = cached_project.get_file(&file_name).unwrap();
+        assert_eq!(file.design_file.design_units.len(), 1);
+        assert_eq!(file.source, Source::from_file(&file_name));
+        assert_eq!(cached_project.analyze(), vec![]);
+
+        // Changed contents are parsed again
+        write_file(&dir.path().join("a.vhd"), "entity ent2 is end entity;");
+        cached_project.update_file("lib", &file_name).unwrap();
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 2);
+        assert_eq!(
+            cached_project
+                .memory_report()
+                .cache
+                .map(|cache| cache.entries),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn analyze_all_libraries() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name1 = write_file(
+            &dir.path().join("a.vhd"),
+            "entity ent is end entity; package ent is end package;",
+        );
+        let file_name2 = write_file(
+            &dir.path().join("b.vhd"),
+            "use lib.ent.all; entity ent is end entity;",
+        );
+
+        let mut project = Project::new();
+        project.update_file("lib", &file_name1).unwrap();
+        project.update_file("lib2", &file_name2).unwrap();
+        assert_eq!(project.analyze().len(), 2);
+    }
+
+    #[test]
+    fn resolve_dependent_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkg_file = write_file(
+            &dir.path().join("pkg.vhd"),
+            "package pkg is constant c : natural := 0; end package;",
+        );
+        let ent_file = write_file(
+            &dir.path().join("ent.vhd"),
+            "use work.pkg.all; entity ent is end entity;
+architecture a of ent is signal s : natural := c; begin end architecture;",
+        );
+        let other_file = write_file(
+            &dir.path().join("other.vhd"),
+            "entity other is end entity;
+architecture a of other is signal s : natural := 0; begin end architecture;",
+        );
+
+        let mut project = Project::new();
+        for file_name in [&pkg_file, &ent_file, &other_file].iter() {
+            project.update_file("lib", file_name).unwrap();
+        }
+
+        let dependent_files = project.dependent_files(&pkg_file);
+        assert_eq!(
+            dependent_files,
+            vec![pkg_file.clone(), ent_file.clone()]
+                .into_iter()
+                .collect()
+        );
+        let signals = |resolution: &NameResolution| -> Vec<String> {
+            resolution
+                .declarations()
+                .iter()
+                .filter(|entity| entity.designator == "s")
+                .filter_map(|entity| entity.pos.source.file_name().map(str::to_owned))
+                .collect()
+        };
+        assert_eq!(
+            signals(&project.resolve()),
+            vec![ent_file.clone(), other_file.clone()]
+        );
+
+        let resolution = project.resolve_files(&dependent_files);
+        assert_eq!(signals(&resolution), vec![ent_file.clone()]);
+        let constant = resolution
+            .declarations()
+            .iter()
+            .find(|entity| entity.designator == "c");
+        assert_eq!(resolution.references_to(constant.unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn verilog_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(
+            &dir.path().join("ip.sv"),
+            "module ip (input logic clk); endmodule\nmodule broken (input a b c); endmodule",
+        );
+
+        let mut project = Project::new();
+        project.update_file("lib", &file_name).unwrap();
+        assert!(project.files().is_empty());
+        let files = project.verilog_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].library_name, "lib");
+        assert_eq!(files[0].verilog_file.modules[0].name.item, "ip");
+        assert_eq!(project.messages().len(), 1);
+
+        assert!(project.remove_file(&file_name));
+        assert!(project.verilog_files().is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Import of Quartus .qsf settings files such as:
+//!
+//! ```tcl
+//! set_global_assignment -name VHDL_FILE src/top.vhd
+//! set_global_assignment -name VHDL_FILE src/pkg.vhd -library mylib
+//! set_global_assignment -name VHDL_FILE "src/with space.vhd" -hdl_version VHDL_2008
+//! ```
+//!
+//! Files without a library option belong to the work library
+
+use import::ImportedProject;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The library of files without a library option
+pub const DEFAULT_LIBRARY: &str = "work";
+
+/// Split a Tcl command into words, quotes and braces group words and a
+/// backslash at the end of a line continues the command on the next line
+fn commands(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    let mut chars = contents.chars().peekable();
+
+    while let Some(chr) = chars.next() {
+        match chr {
+            '\n' | ';' => {
+                if !words.is_empty() {
+                    commands.push(words);
+                    words = Vec::new();
+                }
+            }
+            '\\' if chars.peek() == Some(&'\n') || chars.peek() == Some(&'\r') => {
+                while chars.peek().map(|chr| chr.is_whitespace()).unwrap_or(false) {
+                    chars.next();
+                }
+            }
+            '#' if words.is_empty() => {
+                while chars.peek().map(|chr| *chr != '\n').unwrap_or(false) {
+                    chars.next();
+                }
+            }
+            chr if chr.is_whitespace() => {}
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.extend(chars.next()),
+                        Some(chr) => word.push(chr),
+                        None => {
+                            return Err("Missing closing quote".to_owned());
+                        }
+                    }
+                }
+                words.push(word);
+            }
+            '{' => {
+                let mut word = String::new();
+                let mut depth = 1;
+                loop {
+                    match chars.next() {
+                        Some('}') if depth == 1 => break,
+                        Some(chr) => {
+                            if chr == '{' {
+                                depth += 1;
+                            } else if chr == '}' {
+                                depth -= 1;
+                            }
+                            // Nested braces are kept as is
+                            word.push(chr);
+                        }
+                        None => {
+                            return Err("Missing closing brace".to_owned());
+                        }
+                    }
+                }
+                words.push(word);
+            }
+            chr => {
+                let mut word = chr.to_string();
+                while let Some(&chr) = chars.peek() {
+                    if chr.is_whitespace() || chr == ';' {
+                        break;
+                    }
+                    word.push(chr);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    Ok(commands)
+}
+
+/// The value of an option such as -name within the arguments
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|idx| args.get(idx + 1))
+        .map(|value| value.as_str())
+}
+
+/// Import the contents of the .qsf file at the path, the path is used to expand relative file names
+pub fn parse_qsf(contents: &str, qsf_path: &Path) -> Result<ImportedProject, String> {
+    let project_dir = qsf_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut project = ImportedProject::new();
+
+    for words in commands(contents)? {
+        if words[0] != "set_global_assignment" {
+            continue;
+        }
+        let args = &words[1..];
+        let is_vhdl_file = option(args, "-name")
+            .map(|name| name.eq_ignore_ascii_case("VHDL_FILE"))
+            .unwrap_or(false);
+        if !is_vhdl_file {
+            continue;
+        }
+
+        // The value is the only argument which is not an option or the value of an option
+        let mut value = None;
+        let mut idx = 0;
+        while idx < args.len() {
+            if args[idx].starts_with('-') {
+                idx += 2;
+            } else {
+                value = Some(&args[idx]);
+                idx += 1;
+            }
+        }
+        let file_name = value.ok_or_else(|| "VHDL_FILE assignment without file name".to_owned())?;
+        let library_name = option(args, "-library").unwrap_or(DEFAULT_LIBRARY);
+        let file_name = project_dir.join(file_name).to_string_lossy().into_owned();
+        project.add_file(library_name, &file_name);
+    }
+    Ok(project)
+}
+
+/// Read and import the .qsf settings file
+pub fn read_qsf(file_name: &Path) -> io::Result<ImportedProject> {
+    let contents = fs::read_to_string(file_name)?;
+    parse_qsf(&contents, file_name).map_err(|msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", file_name.to_string_lossy(), msg),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+
+    fn files(project: &ImportedProject, library_name: &str) -> Vec<String> {
+        project
+            .get_library(library_name)
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    const QSF: &str = r#"
+# Project-Wide Assignments
+set_global_assignment -name ORIGINAL_QUARTUS_VERSION 18.1.0
+set_global_assignment -name TOP_LEVEL_ENTITY top
+set_global_assignment -name VHDL_FILE src/top.vhd
+set_global_assignment -name VHDL_FILE src/pkg.vhd -library MyLib
+set_global_assignment -library mylib -name VHDL_FILE "src/with space.vhd"
+set_global_assignment -name VHDL_FILE {src/braces.vhd} -hdl_version VHDL_2008
+set_global_assignment -name VERILOG_FILE src/core.v
+set_global_assignment -name VHDL_FILE \
+    src/continued.vhd; set_location_assignment PIN_A1 -to clk
+set_instance_assignment -name IO_STANDARD "3.3-V LVTTL" -to clk
+"#;
+
+    #[test]
+    fn parse_vhdl_file_assignments() {
+        let project = parse_qsf(QSF, Path::new("/work/proj.qsf")).unwrap();
+        assert_eq!(project.libraries().count(), 2);
+        assert_eq!(
+            files(&project, "work"),
+            vec![
+                "/work/src/braces.vhd",
+                "/work/src/continued.vhd",
+                "/work/src/top.vhd"
+            ]
+        );
+        assert_eq!(
+            files(&project, "mylib"),
+            vec!["/work/src/pkg.vhd", "/work/src/with space.vhd"]
+        );
+    }
+
+    #[test]
+    fn split_commands() {
+        assert_eq!(
+            commands("a {b {c}} \"d e\"\n\n# comment\nf;g").unwrap(),
+            vec![
+                vec!["a".to_owned(), "b {c}".to_owned(), "d e".to_owned()],
+                vec!["f".to_owned()],
+                vec!["g".to_owned()],
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let path = Path::new("p.qsf");
+        assert_eq!(
+            parse_qsf("set_global_assignment -name VHDL_FILE \"a.vhd", path),
+            Err("Missing closing quote".to_owned())
+        );
+        assert_eq!(
+            parse_qsf("set_global_assignment -name VHDL_FILE {a.vhd", path),
+            Err("Missing closing brace".to_owned())
+        );
+        assert_eq!(
+            parse_qsf("set_global_assignment -name VHDL_FILE -library lib", path),
+            Err("VHDL_FILE assignment without file name".to_owned())
+        );
+    }
+
+    #[test]
+    fn read_qsf_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let qsf = dir.path().join("proj.qsf");
+        fs::write(&qsf, QSF).unwrap();
+        assert_eq!(read_qsf(&qsf).unwrap().libraries().count(), 2);
+
+        fs::write(&qsf, "set_global_assignment {").unwrap();
+        assert!(read_qsf(&qsf).is_err());
+    }
+}
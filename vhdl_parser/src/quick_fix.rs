@@ -0,0 +1,114 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The source changes of fixes for semantic messages
+
+use documentation::declaration_text;
+use name_resolution::NamedEntity;
+use source::SrcPos;
+use source_tokens::SourceTokens;
+use tokenizer::Kind::*;
+
+/// An empty position at the start of the line containing the position
+fn line_start(pos: &SrcPos) -> Option<SrcPos> {
+    let contents = pos.source.contents().ok()?;
+    let start = contents.bytes[..pos.start]
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    Some(pos.source.pos(start, 0))
+}
+
+/// The whitespace before the first character of the line containing the position
+fn indentation(pos: &SrcPos) -> String {
+    let contents = match pos.source.contents() {
+        Ok(contents) => contents,
+        Err(..) => {
+            return String::new();
+        }
+    };
+    let start = line_start(pos).map(|pos| pos.start).unwrap_or(0);
+    contents.bytes[start..]
+        .iter()
+        .take_while(|byte| **byte == b' ' || **byte == b'\t')
+        .map(|byte| *byte as char)
+        .collect()
+}
+
+/// Inserts a use clause before the design unit with the designator at the position
+pub fn insert_use_clause(
+    unit: &SrcPos,
+    library_name: &str,
+    package_name: &str,
+    with_library_clause: bool,
+) -> Option<(SrcPos, String)> {
+    let mut text = String::new();
+    if with_library_clause {
+        text.push_str(&format!("library {};\n", library_name));
+    }
+    text.push_str(&format!("use {}.{}.all;\n", library_name, package_name));
+    Some((line_start(unit)?, text))
+}
+
+/// Inserts a signal declaration as the last declaration of an architecture
+pub fn declare_signal(
+    architecture: &SrcPos,
+    designator: &str,
+    subtype: &str,
+) -> Option<(SrcPos, String)> {
+    let tokens = SourceTokens::new(&architecture.source)?;
+    let is = tokens.next_of(tokens.index_of(architecture)?, Is)?;
+    let begin = &tokens.tokens[tokens.skip_declarations(is)?].pos;
+    let text = format!(
+        "{}  signal {} : {};\n",
+        indentation(begin),
+        designator,
+        subtype
+    );
+    Some((line_start(begin)?, text))
+}
+
+/// The name on the other side of a simple assignment such as "a <= b;" to the name at the position
+pub fn assigned_with(pos: &SrcPos) -> Option<String> {
+    let tokens = SourceTokens::new(&pos.source)?;
+    let i = tokens.index_of(pos)?;
+    let assignment = |kind| kind == Some(LTE) || kind == Some(ColonEq);
+    let other = if assignment(tokens.kind(i + 1)) && tokens.kind(i + 3) == Some(SemiColon) {
+        i + 2
+    } else if i >= 2 && assignment(tokens.kind(i - 1)) && tokens.kind(i + 1) == Some(SemiColon) {
+        i - 2
+    } else {
+        return None;
+    };
+    if tokens.kind(other) == Some(Identifier) {
+        Some(tokens.text(other))
+    } else {
+        None
+    }
+}
+
+/// The subtype indication of the declaration of an object
+pub fn subtype_of(object: &NamedEntity) -> Option<String> {
+    let text = declaration_text(object)?.text;
+    let subtype = &text[text.find(':')? + 1..];
+    let subtype = match subtype.find(":=") {
+        Some(default) => &subtype[..default],
+        None => subtype,
+    };
+    let mut words: Vec<&str> = subtype.trim_end_matches(';').split_whitespace().collect();
+    if let Some(mode) = words.first() {
+        let mode = mode.to_lowercase();
+        if ["in", "out", "inout", "buffer", "linkage"].contains(&mode.as_str()) {
+            words.remove(0);
+        }
+    }
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
@@ -0,0 +1,388 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Renaming of a named entity together with all names which denote it
+
+use latin_1::Latin1String;
+use name_resolution::{NameResolution, NamedEntity, NamedEntityKind};
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use tokenizer::Kind::Identifier;
+
+/// A basic or extended identifier which is not a reserved word
+pub fn is_identifier(name: &str) -> bool {
+    let source = match Source::from_str(name) {
+        Ok(source) => source,
+        Err(..) => {
+            return false;
+        }
+    };
+    match SourceTokens::new(&source) {
+        Some(ref tokens) if tokens.tokens.len() == 1 => {
+            let token = &tokens.tokens[0];
+            token.kind == Identifier && token.pos.start == 0 && token.pos.length == name.len()
+        }
+        _ => false,
+    }
+}
+
+fn is_subprogram(kind: NamedEntityKind) -> bool {
+    kind == NamedEntityKind::Function || kind == NamedEntityKind::Procedure
+}
+
+/// Operator symbols are not renamed since the new name would have to be an operator
+pub fn can_rename(entity: &NamedEntity) -> Result<(), String> {
+    if entity.designator.starts_with('"') {
+        Err(format!(
+            "Operator symbol {} can not be renamed",
+            entity.designator
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// The positions to replace with the new name, the designator of the declaration,
+/// all names which denote the named entity and the repeated designators after end
+/// Fails if the new name is not an identifier or if it would become a homograph
+/// of another declaration of the same declarative region
+pub fn rename_positions(
+    resolution: &NameResolution,
+    entity: &NamedEntity,
+    new_name: &str,
+) -> Result<Vec<SrcPos>, String> {
+    can_rename(entity)?;
+    if !is_identifier(new_name) {
+        return Err(format!("'{}' is not a valid identifier", new_name));
+    }
+    // LRM 4.5.1 Subprograms may be overloaded, parameter and result type profiles are not compared
+    for other in resolution.declared_with(entity, new_name) {
+        if !(is_subprogram(entity.kind) && is_subprogram(other.kind)) {
+            return Err(format!(
+                "'{}' is already declared in the same declarative region",
+                other.designator
+            ));
+        }
+    }
+
+    let mut positions = vec![entity.pos.clone()];
+    positions.extend(resolution.references_to(entity).into_iter().cloned());
+
+    let mut tokens_by_file: HashMap<String, Option<SourceTokens>> = HashMap::new();
+    let mut end_labels = Vec::new();
+    for pos in positions.iter() {
+        let file_name = pos.source.file_name().unwrap_or("").to_owned();
+        let tokens = tokens_by_file
+            .entry(file_name)
+            .or_insert_with(|| SourceTokens::new(&pos.source));
+        if let Some(ref tokens) = tokens {
+            if let Some(label) = tokens.index_of(pos).and_then(|i| tokens.end_label(i)) {
+                end_labels.push(tokens.tokens[label].pos.clone());
+            }
+        }
+    }
+    positions.extend(end_labels);
+
+    let mut unique: Vec<SrcPos> = Vec::new();
+    for pos in positions {
+        let duplicate = unique.iter().any(|other| {
+            other.start == pos.start && other.source.file_name() == pos.source.file_name()
+        });
+        if !duplicate {
+            unique.push(pos);
+        }
+    }
+    Ok(unique)
+}
+
+/// The declarations with the designator, ignoring case, which are of the kind
+/// and within the file when given
+pub fn find_declarations<'a>(
+    resolution: &'a NameResolution,
+    designator: &str,
+    kind: Option<NamedEntityKind>,
+    file_name: Option<&str>,
+) -> Vec<&'a NamedEntity> {
+    resolution
+        .declarations()
+        .iter()
+        .filter(|entity| entity.designator.eq_ignore_ascii_case(designator))
+        .filter(|entity| kind.is_none_or(|kind| entity.kind == kind))
+        .filter(|entity| file_name.is_none_or(|name| entity.pos.source.file_name() == Some(name)))
+        .collect()
+}
+
+/// The contents of each file with a position before and after replacing the positions
+/// with the new name, sorted by file name
+pub fn renamed_contents(
+    positions: &[SrcPos],
+    new_name: &str,
+) -> io::Result<Vec<(String, Latin1String, Latin1String)>> {
+    let new_name = Latin1String::from_utf8(new_name)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut by_file: BTreeMap<&str, Vec<&SrcPos>> = BTreeMap::new();
+    for pos in positions {
+        by_file
+            .entry(pos.source.file_name().unwrap_or(""))
+            .or_default()
+            .push(pos);
+    }
+
+    let mut result = Vec::new();
+    for (file_name, mut positions) in by_file {
+        let old = positions[0].source.contents()?;
+        let mut new = (*old).clone();
+        positions.sort_by_key(|pos| pos.start);
+        for pos in positions.iter().rev() {
+            new.bytes.splice(
+                pos.start..pos.start + pos.length,
+                new_name.bytes.iter().cloned(),
+            );
+        }
+        result.push((file_name.to_owned(), (*old).clone(), new));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use library::DesignRoot;
+    use test_util::TempProject;
+
+    fn resolve(project: &TempProject) -> NameResolution {
+        let files = project.project.files();
+        NameResolution::new(&DesignRoot::new(&files))
+    }
+
+    fn declaration<'a>(resolution: &'a NameResolution, designator: &str) -> &'a NamedEntity {
+        resolution
+            .declarations()
+            .iter()
+            .find(|entity| entity.designator == designator)
+            .unwrap()
+    }
+
+    /// The contents of each file after replacing the positions
+    fn apply(project: &TempProject, positions: &[SrcPos], new_name: &str) -> Vec<String> {
+        project
+            .project
+            .files()
+            .iter()
+            .map(|file| {
+                let mut code = file.source.contents().unwrap().to_string();
+                let mut starts: Vec<&SrcPos> = positions
+                    .iter()
+                    .filter(|pos| pos.source.file_name() == Some(file.file_name()))
+                    .collect();
+                starts.sort_by_key(|pos| pos.start);
+                for pos in starts.iter().rev() {
+                    code.replace_range(pos.start..pos.start + pos.length, new_name);
+                }
+                code
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identifiers() {
+        assert!(is_identifier("foo_bar"));
+        assert!(is_identifier("\\extended name\\"));
+        assert!(!is_identifier("signal"));
+        assert!(!is_identifier("1foo"));
+        assert!(!is_identifier("foo bar"));
+        assert!(!is_identifier(""));
+    }
+
+    #[test]
+    fn rename_entity_across_files_with_end_labels() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "entity dut is
+end entity dut;
+
+architecture behav of dut is
+begin
+end architecture behav;",
+        );
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "entity top is
+end entity;
+
+architecture behav of top is
+begin
+  inst : entity work.dut;
+end architecture;",
+        );
+
+        let resolution = resolve(&project);
+        let positions =
+            rename_positions(&resolution, declaration(&resolution, "dut"), "core").unwrap();
+        assert_eq!(
+            apply(&project, &positions, "core"),
+            vec![
+                "entity core is
+end entity core;
+
+architecture behav of core is
+begin
+end architecture behav;",
+                "entity top is
+end entity;
+
+architecture behav of top is
+begin
+  inst : entity work.core;
+end architecture;",
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_subprogram_body_and_labels() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "package pkg is
+  function calc return natural;
+end package pkg;
+
+package body pkg is
+  function calc return natural is
+  begin
+    return 0;
+  end function calc;
+end package body pkg;",
+        );
+
+        let resolution = resolve(&project);
+        let positions =
+            rename_positions(&resolution, declaration(&resolution, "calc"), "compute").unwrap();
+        assert_eq!(
+            apply(&project, &positions, "compute"),
+            vec![
+                "package pkg is
+  function compute return natural;
+end package pkg;
+
+package body pkg is
+  function compute return natural is
+  begin
+    return 0;
+  end function compute;
+end package body pkg;",
+            ]
+        );
+
+        let positions =
+            rename_positions(&resolution, declaration(&resolution, "pkg"), "utils").unwrap();
+        let code = apply(&project, &positions, "utils").remove(0);
+        assert!(code.starts_with("package utils is"));
+        assert!(code.contains("end package utils;"));
+        assert!(code.contains("package body utils is"));
+        assert!(code.ends_with("end package body utils;"));
+    }
+
+    #[test]
+    fn refuses_homographs_of_same_region() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "entity dut is
+  port (clk : in bit);
+end entity;
+
+architecture behav of dut is
+  signal foo : bit;
+  procedure proc is
+  begin
+  end procedure;
+  procedure other(arg : bit) is
+  begin
+  end procedure;
+begin
+  main : process
+    variable bar : bit;
+  begin
+  end process;
+end architecture;",
+        );
+
+        let resolution = resolve(&project);
+        let foo = declaration(&resolution, "foo");
+        assert_eq!(
+            rename_positions(&resolution, foo, "CLK"),
+            Err("'clk' is already declared in the same declarative region".to_owned())
+        );
+        assert!(rename_positions(&resolution, foo, "proc").is_err());
+        assert_eq!(
+            rename_positions(&resolution, foo, "signal"),
+            Err("'signal' is not a valid identifier".to_owned())
+        );
+        // Inner declarations may hide outer ones
+        assert!(rename_positions(&resolution, foo, "bar").is_ok());
+        assert!(rename_positions(&resolution, declaration(&resolution, "bar"), "foo").is_ok());
+        // Subprograms may be overloaded
+        assert!(rename_positions(&resolution, declaration(&resolution, "proc"), "other").is_ok());
+        // Ports conflict with declarations of the architecture
+        assert!(rename_positions(&resolution, declaration(&resolution, "clk"), "foo").is_err());
+    }
+
+    #[test]
+    fn renamed_contents_of_declarations_found_by_name() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file(
+            "lib",
+            "ent.vhd",
+            "entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal foo : bit;
+  constant c : bit := '0';
+begin
+  foo <= c;
+end architecture;",
+        );
+
+        let resolution = resolve(&project);
+        assert_eq!(find_declarations(&resolution, "FOO", None, None).len(), 1);
+        assert_eq!(
+            find_declarations(&resolution, "foo", Some(NamedEntityKind::Constant), None).len(),
+            0
+        );
+        assert_eq!(
+            find_declarations(&resolution, "foo", None, Some("other.vhd")).len(),
+            0
+        );
+
+        let foo = find_declarations(&resolution, "foo", None, Some(&file_name))[0];
+        let positions = rename_positions(&resolution, foo, "bar").unwrap();
+        let contents = renamed_contents(&positions, "bar").unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0].0, file_name);
+        assert_eq!(
+            contents[0].2.to_string(),
+            "entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal bar : bit;
+  constant c : bit := '0';
+begin
+  bar <= c;
+end architecture;"
+        );
+    }
+}
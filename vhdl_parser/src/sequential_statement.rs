@@ -16,6 +16,7 @@ use expression::{parse_aggregate_leftpar_known, parse_choices, parse_expression}
 use message::{error, MessageHandler, ParseResult};
 use names::{parse_name, parse_name_initial_token, to_simple_name};
 use range::parse_discrete_range;
+use smallvec::SmallVec;
 use source::WithPos;
 use tokenizer::{Kind::*, Token};
 use tokenstream::TokenStream;
@@ -422,7 +423,7 @@ fn parse_assignment_or_procedure_call(
                     SequentialStatement::ProcedureCall(
                         FunctionCall {
                             name: WithPos::new(name, target.pos),
-                            parameters: vec![]
+                            parameters: SmallVec::new()
                         })
                 }
                 Target::Aggregate(..) => {
@@ -525,29 +526,32 @@ pub fn parse_sequential_statement_initial_token(
     token: Token,
     messages: &mut MessageHandler,
 ) -> ParseResult<LabeledSequentialStatement> {
-    if token.kind == Identifier {
-        let name = parse_name_initial_token(stream, token)?;
-        let token = stream.expect()?;
-        if token.kind == Colon {
-            let label = Some(to_simple_name(name)?);
+    stream.nested(|stream| {
+        stream.enter(&token)?;
+        if token.kind == Identifier {
+            let name = parse_name_initial_token(stream, token)?;
             let token = stream.expect()?;
-            let statement = parse_unlabeled_sequential_statement(stream, token, messages)?;
-            Ok(LabeledSequentialStatement { label, statement })
+            if token.kind == Colon {
+                let label = Some(to_simple_name(name)?);
+                let token = stream.expect()?;
+                let statement = parse_unlabeled_sequential_statement(stream, token, messages)?;
+                Ok(LabeledSequentialStatement { label, statement })
+            } else {
+                let target = name.map_into(Target::Name);
+                let statement = parse_assignment_or_procedure_call(stream, token, target)?;
+                Ok(LabeledSequentialStatement {
+                    label: None,
+                    statement,
+                })
+            }
         } else {
-            let target = name.map_into(Target::Name);
-            let statement = parse_assignment_or_procedure_call(stream, token, target)?;
+            let statement = parse_unlabeled_sequential_statement(stream, token, messages)?;
             Ok(LabeledSequentialStatement {
                 label: None,
                 statement,
             })
         }
-    } else {
-        let statement = parse_unlabeled_sequential_statement(stream, token, messages)?;
-        Ok(LabeledSequentialStatement {
-            label: None,
-            statement,
-        })
-    }
+    })
 }
 
 #[cfg(test)]
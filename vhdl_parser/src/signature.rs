@@ -0,0 +1,608 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The signatures of the subprogram call or instantiation being typed
+//! The association list is found from the tokens since it is often incomplete while typing
+
+use documentation::declaration_text;
+use name_resolution::{NameResolution, NamedEntity, NamedEntityKind};
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+use tokenizer::Kind::*;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Parameter {
+    /// The designator followed by the rest of its interface declaration including any default
+    pub label: String,
+    pub documentation: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Signature {
+    pub label: String,
+    pub documentation: Option<String>,
+    pub parameters: Vec<Parameter>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Signatures {
+    /// The signatures of all overloads
+    pub signatures: Vec<Signature>,
+    pub active_signature: usize,
+    pub active_parameter: usize,
+}
+
+/// An association list which is not yet closed before the cursor
+struct Call {
+    /// The index of the name of the subprogram or the instantiated unit
+    name: usize,
+    /// The index of the left parenthesis of the association list
+    open: usize,
+    /// The kind of interface elements which are associated
+    interface: NamedEntityKind,
+}
+
+/// The name of the instantiated unit of the instantiation with the generic or port map at the index
+fn instantiated_unit(tokens: &SourceTokens, map: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = map;
+    while i > 0 {
+        i -= 1;
+        match tokens.kind(i)? {
+            RightPar => depth += 1,
+            LeftPar => depth -= 1,
+            Colon if depth == 0 => break,
+            SemiColon | Begin => {
+                return None;
+            }
+            _ => {}
+        }
+    }
+
+    let mut name = i + 1;
+    if let Some(Entity) | Some(Component) = tokens.kind(name) {
+        name += 1;
+    }
+    while tokens.kind(name + 1) == Some(Dot) && tokens.kind(name + 2) == Some(Identifier) {
+        name += 2;
+    }
+    if tokens.kind(name) == Some(Identifier) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// The innermost association list of a call or an instantiation containing the cursor
+fn find_call(tokens: &SourceTokens, cursor: usize) -> Option<Call> {
+    let mut depth = 0;
+    for open in (0..cursor).rev() {
+        match tokens.kind(open)? {
+            RightPar => depth += 1,
+            LeftPar if depth > 0 => depth -= 1,
+            LeftPar => {
+                // Parenthesized expressions are skipped to find an enclosing call
+                if let Some(call) = call_of(tokens, open) {
+                    return Some(call);
+                }
+            }
+            SemiColon | Begin | Is | Then => {
+                return None;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The call or instantiation whose association list starts with the left parenthesis at the index
+fn call_of(tokens: &SourceTokens, open: usize) -> Option<Call> {
+    match tokens.kind(open.checked_sub(1)?)? {
+        Map if open > 1 => {
+            let interface = match tokens.kind(open - 2)? {
+                Generic => NamedEntityKind::Generic,
+                Port => NamedEntityKind::Port,
+                _ => {
+                    return None;
+                }
+            };
+            instantiated_unit(tokens, open - 2).map(|name| Call {
+                name,
+                open,
+                interface,
+            })
+        }
+        // The parameter list of a subprogram specification is not a call
+        Identifier if open < 2 || !is_subprogram_keyword(tokens, open - 2) => Some(Call {
+            name: open - 1,
+            open,
+            interface: NamedEntityKind::Parameter,
+        }),
+        _ => None,
+    }
+}
+
+fn is_subprogram_keyword(tokens: &SourceTokens, i: usize) -> bool {
+    tokens.kind(i) == Some(Function) || tokens.kind(i) == Some(Procedure)
+}
+
+/// The position of the argument containing the cursor together with the formal designator
+/// when it is a named association
+fn active_argument(tokens: &SourceTokens, call: &Call, cursor: usize) -> (usize, Option<String>) {
+    let mut depth = 0;
+    let mut index = 0;
+    let mut formal = None;
+    for i in call.open + 1..cursor {
+        match tokens.kind(i) {
+            Some(LeftPar) => depth += 1,
+            Some(RightPar) => depth -= 1,
+            Some(Comma) if depth == 0 => {
+                index += 1;
+                formal = None;
+            }
+            Some(RightArrow) if depth == 0 && tokens.kind(i - 1) == Some(Identifier) => {
+                formal = Some(tokens.text(i - 1).to_lowercase());
+            }
+            _ => {}
+        }
+    }
+    (index, formal)
+}
+
+/// The first token of each argument of a closed association list
+/// together with whether it is a named association
+fn arguments(tokens: &SourceTokens, call: &Call) -> Vec<(usize, bool)> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = call.open + 1;
+    let mut named = false;
+    for i in call.open + 1..tokens.tokens.len() {
+        match tokens.kind(i) {
+            Some(LeftPar) => depth += 1,
+            Some(RightPar) if depth > 0 => depth -= 1,
+            Some(RightPar) => {
+                if start < i {
+                    result.push((start, named));
+                }
+                return result;
+            }
+            Some(Comma) if depth == 0 => {
+                result.push((start, named));
+                start = i + 1;
+                named = false;
+            }
+            Some(RightArrow) if depth == 0 => named = true,
+            Some(SemiColon) => break,
+            _ => {}
+        }
+    }
+    // The association list is not closed
+    Vec::new()
+}
+
+/// The subprograms or units which may be denoted by the name at the index
+/// All declarations with the same designator are used when the name was not resolved
+/// since the file being typed seldom parses
+fn callees<'a>(
+    resolution: &'a NameResolution,
+    tokens: &SourceTokens,
+    name: usize,
+    kinds: &[NamedEntityKind],
+) -> Vec<&'a NamedEntity> {
+    let pos = &tokens.tokens[name].pos;
+    let resolved: Vec<&NamedEntity> = pos
+        .source
+        .file_name()
+        .map(|file_name| resolution.entities_at(file_name, pos.start))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entity| kinds.contains(&entity.kind))
+        .collect();
+    if !resolved.is_empty() {
+        return resolved;
+    }
+
+    let designator = tokens.text(name);
+    resolution
+        .declarations()
+        .iter()
+        .filter(|entity| {
+            kinds.contains(&entity.kind) && entity.designator.eq_ignore_ascii_case(&designator)
+        })
+        .collect()
+}
+
+/// The interface elements of the kind declared within the declaration of the callee
+fn interface_of<'a>(
+    resolution: &'a NameResolution,
+    callee: &NamedEntity,
+    interface: NamedEntityKind,
+) -> Vec<&'a NamedEntity> {
+    let extent = match SourceTokens::new(&callee.pos.source).and_then(|tokens| {
+        let i = tokens.index_of(&callee.pos)?;
+        let (first, last) = tokens.declaration_extent(i, callee.kind);
+        Some(tokens.range(first, last))
+    }) {
+        Some(extent) => extent,
+        None => {
+            return Vec::new();
+        }
+    };
+
+    let mut elements: Vec<&NamedEntity> = resolution
+        .declarations()
+        .iter()
+        .filter(|entity| {
+            entity.kind == interface
+                && entity.pos.source.file_name() == extent.source.file_name()
+                && extent.start <= entity.pos.start
+                && entity.pos.start < extent.start + extent.length
+        })
+        .collect();
+    elements.sort_by_key(|entity| entity.pos.start);
+    elements
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parameter(element: &NamedEntity) -> Parameter {
+    let declaration = declaration_text(element);
+    let text = declaration
+        .as_ref()
+        .map(|declaration| single_line(&declaration.text))
+        .unwrap_or_default();
+    // Identifier lists declare several elements with the same subtype
+    let label = match text.find(':') {
+        Some(colon) => format!("{} {}", element.designator, &text[colon..]),
+        None => element.designator.clone(),
+    };
+    Parameter {
+        label,
+        documentation: declaration.and_then(|declaration| declaration.comment),
+    }
+}
+
+/// The text before and after the first parenthesized part of the text
+fn outside_parenthesis(text: &str) -> (&str, &str) {
+    let open = match text.find('(') {
+        Some(open) => open,
+        None => {
+            return (text, "");
+        }
+    };
+    let mut depth = 0;
+    for (i, chr) in text[open..].char_indices() {
+        if chr == '(' {
+            depth += 1;
+        } else if chr == ')' {
+            depth -= 1;
+            if depth == 0 {
+                return (&text[..open], &text[open + i + 1..]);
+            }
+        }
+    }
+    (&text[..open], "")
+}
+
+fn signature(
+    resolution: &NameResolution,
+    callee: &NamedEntity,
+    interface: NamedEntityKind,
+) -> Signature {
+    let declaration = declaration_text(callee);
+    let parameters: Vec<Parameter> = interface_of(resolution, callee, interface)
+        .into_iter()
+        .map(parameter)
+        .collect();
+    let labels: Vec<&str> = parameters
+        .iter()
+        .map(|parameter| parameter.label.as_str())
+        .collect();
+
+    let label = match interface {
+        NamedEntityKind::Parameter => {
+            let header = declaration
+                .as_ref()
+                .map(|declaration| single_line(&declaration.text))
+                .unwrap_or_else(|| callee.designator.clone());
+            let (before, after) = outside_parenthesis(&header);
+            if labels.is_empty() {
+                format!("{}{}", before, after)
+            } else {
+                format!("{}({}){}", before, labels.join("; "), after)
+            }
+        }
+        _ => format!(
+            "{} {} {} ({})",
+            if callee.kind == NamedEntityKind::Component {
+                "component"
+            } else {
+                "entity"
+            },
+            callee.designator,
+            if interface == NamedEntityKind::Generic {
+                "generic"
+            } else {
+                "port"
+            },
+            labels.join("; ")
+        ),
+    };
+
+    Signature {
+        label,
+        documentation: declaration.and_then(|declaration| declaration.comment),
+        parameters,
+    }
+}
+
+fn callee_kinds(interface: NamedEntityKind) -> &'static [NamedEntityKind] {
+    if interface == NamedEntityKind::Parameter {
+        &[NamedEntityKind::Function, NamedEntityKind::Procedure]
+    } else {
+        &[NamedEntityKind::Entity, NamedEntityKind::Component]
+    }
+}
+
+/// The first token of each positional argument of the calls and instantiations of a source
+/// together with the formal it is associated with
+/// Arguments of overloaded subprograms are only included when all overloads agree on the formal
+pub fn positional_formals<'a>(
+    resolution: &'a NameResolution,
+    source: &Source,
+) -> Vec<(SrcPos, &'a NamedEntity)> {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => {
+            return Vec::new();
+        }
+    };
+    let file_name = match source.file_name() {
+        Some(file_name) => file_name,
+        None => {
+            return Vec::new();
+        }
+    };
+
+    let mut result = Vec::new();
+    for open in 0..tokens.tokens.len() {
+        if tokens.kind(open) != Some(LeftPar) {
+            continue;
+        }
+        let call = match call_of(&tokens, open) {
+            Some(call) => call,
+            None => continue,
+        };
+        // Only resolved names are used since a call of a name which was not resolved
+        // may as well be an indexed name or a type conversion
+        let kinds = callee_kinds(call.interface);
+        let interfaces: Vec<Vec<&NamedEntity>> = resolution
+            .entities_at(file_name, tokens.tokens[call.name].pos.start)
+            .into_iter()
+            .filter(|entity| kinds.contains(&entity.kind))
+            .map(|callee| interface_of(resolution, callee, call.interface))
+            .collect();
+        if interfaces.is_empty() {
+            continue;
+        }
+
+        let arguments = arguments(&tokens, &call);
+        let interfaces: Vec<Vec<&NamedEntity>> = interfaces
+            .into_iter()
+            .filter(|interface| interface.len() >= arguments.len())
+            .collect();
+        for (index, (start, named)) in arguments.into_iter().enumerate() {
+            if named {
+                continue;
+            }
+            let formal = match interfaces.first() {
+                Some(interface) => interface[index],
+                None => break,
+            };
+            let agree = interfaces.iter().all(|interface| {
+                interface[index]
+                    .designator
+                    .eq_ignore_ascii_case(&formal.designator)
+            });
+            if agree {
+                result.push((tokens.tokens[start].pos.clone(), formal));
+            }
+        }
+    }
+    result
+}
+
+/// The signatures of the subprogram call or the generic or port map of an instantiation
+/// containing a character offset of the source
+pub fn signatures_at(
+    resolution: &NameResolution,
+    source: &Source,
+    offset: usize,
+) -> Option<Signatures> {
+    let tokens = SourceTokens::new(source)?;
+    let cursor = tokens
+        .tokens
+        .iter()
+        .position(|token| token.pos.start >= offset)
+        .unwrap_or(tokens.tokens.len());
+    let call = find_call(&tokens, cursor)?;
+
+    let kinds = callee_kinds(call.interface);
+    let signatures: Vec<Signature> = callees(resolution, &tokens, call.name, kinds)
+        .into_iter()
+        .map(|callee| signature(resolution, callee, call.interface))
+        .collect();
+    if signatures.is_empty() {
+        return None;
+    }
+
+    // The first overload which has the parameter is active
+    let (index, formal) = active_argument(&tokens, &call, cursor);
+    let active = signatures.iter().enumerate().find_map(|(i, signature)| {
+        let parameter = match formal {
+            Some(ref formal) => signature.parameters.iter().position(|parameter| {
+                parameter
+                    .label
+                    .to_lowercase()
+                    .starts_with(&format!("{} ", formal))
+            }),
+            None if index < signature.parameters.len() => Some(index),
+            None => None,
+        };
+        parameter.map(|parameter| (i, parameter))
+    });
+    let (active_signature, active_parameter) = active.unwrap_or((0, index));
+
+    Some(Signatures {
+        signatures,
+        active_signature,
+        active_parameter,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use library::DesignRoot;
+    use test_util::TempProject;
+
+    /// The signatures at the cursor marker of the code being typed which is in
+    /// another file than the declarations since it seldom parses
+    fn signatures(declarations: &str, code: &str) -> Option<Signatures> {
+        let offset = code.find('|').unwrap();
+        let mut project = TempProject::new();
+        project.add_file("lib", "decl.vhd", declarations);
+        let file_name = project.add_file("lib", "file.vhd", &code.replacen('|', "", 1));
+        let files = project.project.files();
+        let resolution = NameResolution::new(&DesignRoot::new(&files));
+        let file = project.project.get_file(&file_name).unwrap();
+        signatures_at(&resolution, &file.source, offset)
+    }
+
+    fn labels(signatures: &Signatures) -> Vec<&str> {
+        signatures
+            .signatures
+            .iter()
+            .map(|signature| signature.label.as_str())
+            .collect()
+    }
+
+    const PACKAGE: &str = "
+package pkg is
+  -- Adds two numbers
+  function add(left : natural; right : natural := 1) return natural;
+  function add(value : real) return real;
+  procedure log(msg : string; level : natural := 0); -- Print a message
+end package;";
+
+    fn call(statement: &str) -> String {
+        format!(
+            "
+package body pkg is
+  procedure test is
+    variable sum : natural;
+  begin
+    {}",
+            statement
+        )
+    }
+
+    #[test]
+    fn subprogram_call_while_typing() {
+        let result = signatures(PACKAGE, &call("sum := add(1, |")).unwrap();
+        assert_eq!(
+            labels(&result),
+            vec![
+                "function add(left : natural; right : natural := 1) return natural",
+                "function add(value : real) return real",
+            ]
+        );
+        assert_eq!(
+            result.signatures[0].parameters,
+            vec![
+                Parameter {
+                    label: "left : natural".to_owned(),
+                    documentation: None,
+                },
+                Parameter {
+                    label: "right : natural := 1".to_owned(),
+                    documentation: None,
+                },
+            ]
+        );
+        assert_eq!(
+            result.signatures[0].documentation,
+            Some("Adds two numbers".to_owned())
+        );
+        assert_eq!((result.active_signature, result.active_parameter), (0, 1));
+
+        let result = signatures(PACKAGE, &call("sum := add(value => (1.0 + |")).unwrap();
+        assert_eq!((result.active_signature, result.active_parameter), (1, 0));
+
+        let result = signatures(PACKAGE, &call("log(level => 1, msg => |")).unwrap();
+        assert_eq!(
+            labels(&result),
+            vec!["procedure log(msg : string; level : natural := 0)"]
+        );
+        assert_eq!(
+            result.signatures[0].documentation,
+            Some("Print a message".to_owned())
+        );
+        assert_eq!(result.active_parameter, 0);
+
+        assert_eq!(signatures(PACKAGE, &call("sum := (1 + |")), None);
+    }
+
+    #[test]
+    fn generic_and_port_maps() {
+        let entity = "
+entity dut is
+  generic (width : natural := 8);
+  port (
+    clk : in bit;
+    data, valid : out bit_vector(width - 1 downto 0));
+end entity;";
+        let code = "
+architecture a of top is
+begin
+  inst : entity work.dut
+    generic map (width => 4)
+    port map (clk, |";
+
+        let result = signatures(entity, code).unwrap();
+        assert_eq!(
+            labels(&result),
+            vec![
+                "entity dut port (clk : in bit; data : out bit_vector(width - 1 downto 0); \
+                 valid : out bit_vector(width - 1 downto 0))"
+            ]
+        );
+        assert_eq!(result.active_parameter, 1);
+
+        let code = "
+architecture a of top is
+begin
+  inst : entity work.dut
+    generic map (|";
+        let result = signatures(entity, code).unwrap();
+        assert_eq!(
+            labels(&result),
+            vec!["entity dut generic (width : natural := 8)"]
+        );
+        assert_eq!(result.active_parameter, 0);
+    }
+
+    #[test]
+    fn no_signatures_within_declarations() {
+        assert_eq!(signatures("", "entity ent is port (a : bit; |"), None);
+        assert_eq!(
+            signatures(PACKAGE, "package p is function add(a : bit; |"),
+            None
+        );
+    }
+}
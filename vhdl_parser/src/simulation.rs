@@ -0,0 +1,3960 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! An interpreter of a subset of VHDL to run testbenches as quick smoke simulations
+//!
+//! The design below a top level entity is elaborated into signals and processes which
+//! are run in the delta cycles of LRM 14.7. Processes, concurrent signal assignments and
+//! assertions, entity and component instances, blocks and generate statements are
+//! elaborated. The scalar types, strings, lines and the vectors of std_logic_1164 and
+//! numeric_std are supported together with enumeration and integer types declared in the
+//! design. Records, other array types, files, access types and configurations give an
+//! error when they are used
+//!
+//! Reports and assertions are written like GHDL, such as
+//! tb.vhd:12:5:@10ns:(report note): done
+
+use ast::{
+    AbstractLiteral, ActualPart, AssertStatement, AssignmentRightHand, AssociationElement,
+    AttributeName, Binary, Choice, ConcurrentAssertStatement, ConcurrentSignalAssignment,
+    ConcurrentStatement, ContextItem, Declaration, DelayMechanism, DesignUnit, Designator,
+    Direction, DiscreteRange, ElementAssociation, EnumerationLiteral, Expression, FunctionCall,
+    InstantiatedUnit, InstantiationStatement, InterfaceDeclaration, IterationScheme,
+    LabeledConcurrentStatement, LabeledSequentialStatement, LibraryUnit, Literal, Mode, Name,
+    ObjectClass, Range, SelectedName, SequentialStatement, SubprogramBody, SubprogramDeclaration,
+    SubtypeConstraint, SubtypeIndication, Target, TypeDeclaration, TypeDefinition, UseClause,
+    WaitStatement, Waveform,
+};
+use library::{selected_name_parts, symbol_key, UnitId};
+use message::{error, Message};
+use name_resolution::entity_interface;
+use project::SourceFile;
+use simulation_value::{
+    binary, bit_string, convert, integer_elements, numeric_vector, resize, resolve_logic,
+    time_image, time_unit, unary, vector_integer, Bounds, EnumType, Time, Type, Value, VectorKind,
+    INTEGER,
+};
+use source::{SrcPos, WithPos};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+use symbol_table::Symbol;
+use vcd::{Variable, VcdWriter};
+
+extern crate fnv;
+use self::fnv::FnvHashMap;
+
+/// The delta cycles at the same time after which the design is assumed to oscillate
+const MAX_DELTA_CYCLES: usize = 10_000;
+/// The statements a process may run without waiting
+const MAX_STEPS: usize = 10_000_000;
+/// The depth of instances and of calls of subprograms
+const MAX_DEPTH: usize = 100;
+
+const SEVERITY_NAMES: [&str; 4] = ["note", "warning", "error", "failure"];
+const NOTE: usize = 0;
+const ERROR: usize = 2;
+const FAILURE: usize = 3;
+
+/// Why a simulation ended
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Finish {
+    /// There are no more events
+    Quiet,
+    /// The stop time was reached
+    StopTime,
+    /// std.env.stop or std.env.finish was called
+    Stop,
+    /// A report or assertion of severity failure
+    Failure,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Summary {
+    pub time: Time,
+    pub finish: Finish,
+    /// The reports and assertions of severity error or failure
+    pub errors: usize,
+}
+
+#[derive(Clone)]
+enum Item<'a> {
+    Constant(Value),
+    Signal(usize),
+    Type(Type),
+    /// A type declaration which the interpreter does not support
+    UnsupportedType,
+    Subprograms(Vec<&'a SubprogramBody>),
+}
+
+/// A declarative region with the names declared within it
+struct Scope<'a> {
+    parent: Option<Rc<Scope<'a>>>,
+    /// The hierarchical path such as tb.dut
+    path: String,
+    library_name: String,
+    items: RefCell<FnvHashMap<String, Item<'a>>>,
+    /// The packages made visible by use clauses
+    uses: RefCell<Vec<Rc<Scope<'a>>>>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(parent: Option<Rc<Scope<'a>>>, path: String, library_name: &str) -> Rc<Scope<'a>> {
+        Rc::new(Scope {
+            parent,
+            path,
+            library_name: library_name.to_owned(),
+            items: RefCell::new(FnvHashMap::default()),
+            uses: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// A nested declarative region with the label added to the path unless empty
+    fn child(self: &Rc<Self>, label: &str) -> Rc<Scope<'a>> {
+        let path = if label.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}.{}", self.path, label)
+        };
+        Scope::new(Some(self.clone()), path, &self.library_name)
+    }
+
+    fn declare(&self, name: String, item: Item<'a>) {
+        let mut items = self.items.borrow_mut();
+        match (items.get_mut(&name), item) {
+            (Some(Item::Subprograms(ref mut bodies)), Item::Subprograms(added)) => {
+                bodies.extend(added)
+            }
+            (_, item) => {
+                items.insert(name, item);
+            }
+        }
+    }
+}
+
+/// The item of the name together with the scope declaring it, where the names of a
+/// scope are visible before those of the packages it uses and those of its parent
+fn lookup<'a>(scope: &Rc<Scope<'a>>, name: &str) -> Option<(Item<'a>, Rc<Scope<'a>>)> {
+    let mut current = Some(scope.clone());
+    while let Some(scope) = current {
+        if let Some(item) = scope.items.borrow().get(name) {
+            return Some((item.clone(), scope.clone()));
+        }
+        for package in scope.uses.borrow().iter() {
+            if let Some(item) = package.items.borrow().get(name) {
+                return Some((item.clone(), package.clone()));
+            }
+        }
+        current = scope.parent.clone();
+    }
+    None
+}
+
+/// A name resolved within an activation
+enum Object<'a> {
+    Variable(String),
+    Signal(usize),
+    Item(Item<'a>, Rc<Scope<'a>>),
+    /// A predefined subprogram of std or ieee
+    Builtin(String),
+}
+
+/// The variable or signal assigned by a statement
+enum Place {
+    Variable(String),
+    Signal(usize),
+}
+
+/// The offset and length of the elements of a target which are assigned
+type Part = Option<(usize, usize)>;
+
+enum Loop<'a> {
+    Forever,
+    While(&'a WithPos<Expression>),
+    For {
+        name: String,
+        index: i64,
+        last: i64,
+        ascending: bool,
+        /// The variable hidden by the loop parameter
+        hidden: Option<(Type, Value)>,
+    },
+}
+
+/// The caller of a procedure which is restored on return
+struct Call<'a> {
+    scope: Rc<Scope<'a>>,
+    variables: FnvHashMap<String, (Type, Value)>,
+    aliases: FnvHashMap<String, usize>,
+    /// The out parameters with the variables of the caller associated with them
+    outputs: Vec<(String, String)>,
+    return_type: Option<Type>,
+}
+
+enum FrameKind<'a> {
+    Body,
+    Branch,
+    Loop(Option<String>, Loop<'a>),
+    Procedure(Box<Call<'a>>),
+}
+
+/// A list of statements being run
+struct Frame<'a> {
+    statements: &'a [LabeledSequentialStatement],
+    next: usize,
+    kind: FrameKind<'a>,
+}
+
+impl<'a> Frame<'a> {
+    fn new(statements: &'a [LabeledSequentialStatement], kind: FrameKind<'a>) -> Frame<'a> {
+        Frame {
+            statements,
+            next: 0,
+            kind,
+        }
+    }
+}
+
+/// The state of a process or subprogram which is resumed after waiting
+struct Activation<'a> {
+    scope: Rc<Scope<'a>>,
+    variables: FnvHashMap<String, (Type, Value)>,
+    /// The signals associated with the signal parameters of procedures
+    aliases: FnvHashMap<String, usize>,
+    stack: Vec<Frame<'a>>,
+    /// The process whose drivers are assigned
+    process: usize,
+    /// The signals read, collected for the sensitivity of concurrent statements
+    reads: Option<BTreeSet<usize>>,
+    return_type: Option<Type>,
+    depth: usize,
+    /// The position of the process or call for errors of running it
+    pos: SrcPos,
+}
+
+impl<'a> Activation<'a> {
+    fn new(scope: Rc<Scope<'a>>, process: usize, pos: &SrcPos) -> Activation<'a> {
+        Activation {
+            scope,
+            variables: FnvHashMap::default(),
+            aliases: FnvHashMap::default(),
+            stack: Vec::new(),
+            process,
+            reads: None,
+            return_type: None,
+            depth: 0,
+            pos: pos.clone(),
+        }
+    }
+}
+
+enum Suspend<'a> {
+    Wait(Wait<'a>),
+    Return(Option<Value>),
+    /// The statements ended
+    End,
+    /// The simulation was stopped
+    Halt,
+}
+
+/// The condition for resuming a process, it waits forever without signals or timeout
+#[derive(Default)]
+struct Wait<'a> {
+    signals: Vec<usize>,
+    condition: Option<&'a WithPos<Expression>>,
+    timeout: Option<Time>,
+}
+
+#[derive(Clone)]
+enum Sensitivity {
+    None,
+    Signals(Vec<usize>),
+    /// VHDL-2008 process (all)
+    All,
+}
+
+#[derive(Clone)]
+enum Body<'a> {
+    Statements(&'a [LabeledSequentialStatement], Sensitivity),
+    Assignment(&'a ConcurrentSignalAssignment),
+    Assert(&'a ConcurrentAssertStatement),
+}
+
+struct Process<'a> {
+    body: Body<'a>,
+    /// None while the process is running
+    activation: Option<Activation<'a>>,
+    wait: Wait<'a>,
+}
+
+struct Transaction {
+    time: Time,
+    part: Part,
+    value: Value,
+}
+
+/// The values a process assigns to a signal
+struct Driver {
+    process: usize,
+    value: Value,
+    /// Sorted by time
+    transactions: VecDeque<Transaction>,
+}
+
+struct Signal {
+    /// The hierarchical path such as tb.dut.clk
+    path: String,
+    /// The paths of the ports associated with the signal
+    ports: Vec<String>,
+    typ: Type,
+    initial: Value,
+    value: Value,
+    last_value: Value,
+    event: bool,
+    drivers: Vec<Driver>,
+}
+
+impl Signal {
+    /// The std_logic signals and vectors with a resolution function
+    fn is_resolved(&self) -> bool {
+        match self.typ {
+            Type::Logic => true,
+            Type::Vector(kind, _) => kind != VectorKind::Bit,
+            _ => false,
+        }
+    }
+
+    fn effective_value(&self) -> Value {
+        let mut drivers = self.drivers.iter();
+        let first = match drivers.next() {
+            Some(driver) => driver.value.clone(),
+            None => return self.value.clone(),
+        };
+        drivers.fold(first, |resolved, driver| match (resolved, &driver.value) {
+            (Value::Character(left), Value::Character(right)) => {
+                Value::Character(resolve_logic(left, *right))
+            }
+            (Value::Vector(kind, bounds, left), Value::Vector(_, _, ref right)) => Value::Vector(
+                kind,
+                bounds,
+                left.iter()
+                    .zip(right.iter())
+                    .map(|(left, right)| resolve_logic(*left, *right))
+                    .collect(),
+            ),
+            (resolved, _) => resolved,
+        })
+    }
+}
+
+/// Assigns the elements of the part of the target
+fn set_part(target: &mut Value, part: Part, value: Value) {
+    let (offset, length) = match part {
+        Some(part) => part,
+        None => {
+            *target = value;
+            return;
+        }
+    };
+    let elements = match value {
+        Value::Character(chr) => vec![chr],
+        Value::String(elements) | Value::Vector(_, _, elements) => elements,
+        _ => return,
+    };
+    if let Value::String(ref mut target) | Value::Vector(_, _, ref mut target) = target {
+        target[offset..offset + length].copy_from_slice(&elements[..length]);
+    }
+}
+
+/// The type of the elements of an array type
+fn element_type(typ: &Type) -> Option<Type> {
+    match typ {
+        Type::Vector(VectorKind::Bit, _) => Some(Type::Bit),
+        Type::Vector(..) => Some(Type::Logic),
+        Type::String(..) => Some(Type::Character),
+        _ => None,
+    }
+}
+
+/// The type of a value which is the expected type of aggregates compared with it
+fn value_type(value: &Value) -> Type {
+    match value {
+        Value::Boolean(..) => Type::Boolean,
+        Value::Integer(..) => INTEGER,
+        Value::Character(..) => Type::Character,
+        Value::Time(..) => Type::Time,
+        Value::Enum(_, ref typ) => Type::Enum(typ.clone()),
+        Value::String(..) => Type::String(value.bounds()),
+        Value::Vector(kind, bounds, _) => Type::Vector(*kind, Some(*bounds)),
+    }
+}
+
+/// The predefined types of std.standard, std_logic_1164, numeric_std and std.textio
+fn builtin_type(name: &str, severity_level: &Arc<EnumType>) -> Option<Type> {
+    Some(match name {
+        "boolean" => Type::Boolean,
+        "bit" => Type::Bit,
+        "std_ulogic" | "std_logic" => Type::Logic,
+        "character" => Type::Character,
+        "integer" => INTEGER,
+        "natural" => Type::Integer(0, i64::from(i32::MAX)),
+        "positive" => Type::Integer(1, i64::from(i32::MAX)),
+        "time" | "delay_length" => Type::Time,
+        "string" | "line" => Type::String(None),
+        "bit_vector" => Type::Vector(VectorKind::Bit, None),
+        "std_ulogic_vector" | "std_logic_vector" => Type::Vector(VectorKind::Logic, None),
+        "unsigned" => Type::Vector(VectorKind::Unsigned, None),
+        "signed" => Type::Vector(VectorKind::Signed, None),
+        "severity_level" => Type::Enum(severity_level.clone()),
+        _ => return None,
+    })
+}
+
+/// The predefined subprograms of std and ieee which are supported
+fn is_builtin(name: &str) -> bool {
+    [
+        "rising_edge",
+        "falling_edge",
+        "now",
+        "to_integer",
+        "to_unsigned",
+        "to_signed",
+        "resize",
+        "to_string",
+        "to_hstring",
+        "shift_left",
+        "shift_right",
+        "minimum",
+        "maximum",
+        "to_stdlogicvector",
+        "to_stdulogicvector",
+        "to_bitvector",
+        "write",
+        "hwrite",
+        "writeline",
+        "deallocate",
+        "stop",
+        "finish",
+    ]
+    .contains(&name)
+}
+
+/// The libraries whose packages are predefined by the interpreter
+fn is_builtin_library(name: &str) -> bool {
+    name == "std" || name == "ieee"
+}
+
+/// The library and unit of a name which is implicitly within the library unless prefixed
+fn unit_name_id(library_name: &str, name: &SelectedName) -> Option<UnitId> {
+    let parts: Vec<String> = name.iter().map(|ident| symbol_key(&ident.item)).collect();
+    match parts.len() {
+        1 => Some(UnitId::primary(library_name, &parts[0])),
+        2 if parts[0] == "work" => Some(UnitId::primary(library_name, &parts[1])),
+        2 => Some(UnitId::primary(&parts[0], &parts[1])),
+        _ => None,
+    }
+}
+
+/// The actual associated with the formal of the name at the position
+fn associated<'b>(
+    elements: &'b [AssociationElement],
+    position: usize,
+    name: &str,
+) -> Option<&'b WithPos<ActualPart>> {
+    elements
+        .iter()
+        .enumerate()
+        .find(|(i, element)| match element.formal {
+            Some(ref formal) => match formal.item {
+                Name::Simple(ref symbol) => symbol_key(symbol) == name,
+                _ => false,
+            },
+            None => *i == position,
+        })
+        .map(|(_, element)| &element.actual)
+}
+
+/// The position of an expression or name within the statement
+fn statement_pos(statement: &SequentialStatement) -> Option<&SrcPos> {
+    match statement {
+        SequentialStatement::Wait(ref wait) => wait
+            .condition_clause
+            .as_ref()
+            .or(wait.timeout_clause.as_ref())
+            .map(|expr| &expr.pos)
+            .or_else(|| wait.sensitivity_clause.first().map(|name| &name.pos)),
+        SequentialStatement::Assert(ref assert) => Some(&assert.condition.pos),
+        SequentialStatement::Report(ref report) => Some(&report.report.pos),
+        SequentialStatement::VariableAssignment(ref assignment) => Some(&assignment.target.pos),
+        SequentialStatement::SignalAssignment(ref assignment) => Some(&assignment.target.pos),
+        SequentialStatement::ProcedureCall(ref call) => Some(&call.name.pos),
+        SequentialStatement::If(ref statement) => statement
+            .conditionals
+            .first()
+            .map(|conditional| &conditional.condition.pos),
+        SequentialStatement::Case(ref statement) => Some(&statement.expression.pos),
+        SequentialStatement::Loop(ref statement) => match statement.iteration_scheme {
+            Some(IterationScheme::While(ref condition)) => Some(&condition.pos),
+            Some(IterationScheme::For(ref ident, _)) => Some(&ident.pos),
+            None => statement
+                .statements
+                .iter()
+                .find_map(|statement| statement_pos(&statement.statement)),
+        },
+        SequentialStatement::Next(ref next) => next.condition.as_ref().map(|expr| &expr.pos),
+        SequentialStatement::Exit(ref exit) => exit.condition.as_ref().map(|expr| &expr.pos),
+        SequentialStatement::Return(ref statement) => {
+            statement.expression.as_ref().map(|expr| &expr.pos)
+        }
+        SequentialStatement::Null => None,
+    }
+}
+
+fn integer(value: Value, pos: &SrcPos) -> Result<i64, Message> {
+    match value {
+        Value::Integer(value) => Ok(value),
+        value => Err(error(
+            pos,
+            &format!("Expected integer value, got {}", value.image()),
+        )),
+    }
+}
+
+/// The design units of the files by which the design is elaborated
+struct Units<'a> {
+    entities: BTreeMap<UnitId, (String, &'a DesignUnit)>,
+    /// The architectures of each entity in the order of the files
+    architectures: BTreeMap<UnitId, Vec<(String, &'a DesignUnit)>>,
+    packages: BTreeMap<UnitId, (String, &'a DesignUnit)>,
+    package_bodies: BTreeMap<UnitId, &'a DesignUnit>,
+}
+
+/// The elaborated design of a top level entity and the state of simulating it
+pub struct Simulation<'a> {
+    units: Units<'a>,
+    root: Rc<Scope<'a>>,
+    /// The package scopes by their library and name, declared on first use
+    packages: BTreeMap<UnitId, Rc<Scope<'a>>>,
+    severity_level: Arc<EnumType>,
+    signals: Vec<Signal>,
+    processes: Vec<Process<'a>>,
+    initialized: bool,
+    now: Time,
+    delta: usize,
+    /// The statements run since the running process was resumed
+    steps: usize,
+    errors: usize,
+    finish: Option<Finish>,
+    /// The reports and lines written since the output was last flushed
+    output: Vec<u8>,
+    /// The position of the process which last resumed
+    last_pos: Option<SrcPos>,
+    vcd: Option<VcdWriter>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(files: &[&'a SourceFile]) -> Simulation<'a> {
+        let mut units = Units {
+            entities: BTreeMap::new(),
+            architectures: BTreeMap::new(),
+            packages: BTreeMap::new(),
+            package_bodies: BTreeMap::new(),
+        };
+        for file in files {
+            let library_name = &file.library_name;
+            for design_unit in file.design_file.design_units.iter() {
+                let unit_id = UnitId::new(library_name, design_unit);
+                match design_unit.library_unit {
+                    LibraryUnit::EntityDeclaration { .. } => {
+                        units
+                            .entities
+                            .entry(unit_id)
+                            .or_insert((library_name.clone(), design_unit));
+                    }
+                    LibraryUnit::ArchitectureBody { ref ident, .. } => {
+                        units
+                            .architectures
+                            .entry(unit_id.primary_unit())
+                            .or_default()
+                            .push((symbol_key(&ident.item), design_unit));
+                    }
+                    LibraryUnit::PackageDeclaration { .. } => {
+                        units
+                            .packages
+                            .entry(unit_id)
+                            .or_insert((library_name.clone(), design_unit));
+                    }
+                    LibraryUnit::PackageBody { .. } => {
+                        units
+                            .package_bodies
+                            .entry(unit_id.primary_unit())
+                            .or_insert(design_unit);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let severity_level = Arc::new(EnumType {
+            name: "severity_level".to_owned(),
+            literals: SEVERITY_NAMES
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect(),
+        });
+        // std.textio.side
+        let side = Arc::new(EnumType {
+            name: "side".to_owned(),
+            literals: vec!["right".to_owned(), "left".to_owned()],
+        });
+        let root = Scope::new(None, String::new(), "work");
+        root.declare("true".to_owned(), Item::Constant(Value::Boolean(true)));
+        root.declare("false".to_owned(), Item::Constant(Value::Boolean(false)));
+        for (pos, name) in SEVERITY_NAMES.iter().enumerate() {
+            root.declare(
+                (*name).to_owned(),
+                Item::Constant(Value::Enum(pos, severity_level.clone())),
+            );
+        }
+        for (pos, name) in side.literals.iter().enumerate() {
+            root.declare(name.clone(), Item::Constant(Value::Enum(pos, side.clone())));
+        }
+
+        Simulation {
+            units,
+            root,
+            packages: BTreeMap::new(),
+            severity_level,
+            signals: Vec::new(),
+            processes: Vec::new(),
+            initialized: false,
+            now: 0,
+            delta: 0,
+            steps: 0,
+            errors: 0,
+            finish: None,
+            output: Vec::new(),
+            last_pos: None,
+            vcd: None,
+        }
+    }
+
+    /// Elaborates the top level entity with its most recently analyzed architecture
+    /// Returns false when there is no such entity
+    pub fn elaborate(&mut self, top: &UnitId) -> Result<bool, Message> {
+        let pos = match self.units.entities.get(top) {
+            Some((_, unit)) => match unit.library_unit {
+                LibraryUnit::EntityDeclaration { ref ident, .. } => ident.pos.clone(),
+                _ => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+        self.entity(top.primary_name().to_owned(), top, None, None, &pos, 0)?;
+        Ok(true)
+    }
+
+    /// The current simulation time
+    pub fn now(&self) -> Time {
+        self.now
+    }
+
+    /// Dumps the values of the signals of the elaborated design to the output as a VCD
+    /// file until the dump is finished
+    pub fn dump_vcd(&mut self, output: Box<dyn Write>) {
+        let variables: Vec<Variable> = self
+            .signals
+            .iter()
+            .map(|signal| Variable {
+                paths: Some(signal.path.as_str())
+                    .into_iter()
+                    .chain(signal.ports.iter().map(String::as_str))
+                    .collect(),
+                typ: &signal.typ,
+                value: &signal.value,
+            })
+            .collect();
+        self.vcd = Some(VcdWriter::new(output, &variables));
+    }
+
+    /// Ends the VCD file at the current time with the first error writing it
+    pub fn finish_vcd(&mut self) -> io::Result<()> {
+        match self.vcd.take() {
+            Some(mut vcd) => vcd.finish(self.now),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the simulation until there are no more events, it is stopped or fails, or the
+    /// stop time is reached, where the reports and lines written are flushed to the output
+    /// after each simulation cycle
+    pub fn run(
+        &mut self,
+        stop_time: Option<Time>,
+        output: &mut dyn Write,
+    ) -> Result<Summary, Message> {
+        let result = self.run_cycles(stop_time, output);
+        self.flush(output);
+        Ok(Summary {
+            time: self.now,
+            finish: result?,
+            errors: self.errors,
+        })
+    }
+
+    fn flush(&mut self, output: &mut dyn Write) {
+        // The output is only diagnostic and a closed output does not stop the simulation
+        let _ = output.write_all(&self.output);
+        self.output.clear();
+    }
+
+    fn run_cycles(
+        &mut self,
+        stop_time: Option<Time>,
+        output: &mut dyn Write,
+    ) -> Result<Finish, Message> {
+        if !self.initialized {
+            self.initialized = true;
+            for process in 0..self.processes.len() {
+                self.resume(process)?;
+            }
+            self.flush(output);
+        }
+        loop {
+            if let Some(finish) = self.finish {
+                return Ok(finish);
+            }
+            let next = match self.next_time() {
+                Some(next) => next,
+                None => return Ok(Finish::Quiet),
+            };
+            if let Some(stop_time) = stop_time {
+                if next > stop_time {
+                    self.now = self.now.max(stop_time);
+                    return Ok(Finish::StopTime);
+                }
+            }
+            if next == self.now {
+                self.delta += 1;
+                if self.delta > MAX_DELTA_CYCLES {
+                    let pos = self.last_pos.clone().unwrap_or_else(|| self.root_pos());
+                    return Err(error(
+                        &pos,
+                        &format!(
+                            "Exceeded {} delta cycles at {}",
+                            MAX_DELTA_CYCLES,
+                            time_image(self.now)
+                        ),
+                    ));
+                }
+            } else {
+                self.now = next;
+                self.delta = 0;
+            }
+            self.cycle()?;
+            self.flush(output);
+        }
+    }
+
+    /// A position for errors which are not caused by a process, which do not happen
+    /// since only processes schedule transactions
+    fn root_pos(&self) -> SrcPos {
+        self.processes
+            .iter()
+            .filter_map(|process| process.activation.as_ref())
+            .map(|activation| activation.pos.clone())
+            .next()
+            .expect("Delta cycles without processes")
+    }
+
+    /// The time of the next transaction or timeout
+    fn next_time(&self) -> Option<Time> {
+        let transactions = self.signals.iter().flat_map(|signal| {
+            signal
+                .drivers
+                .iter()
+                .filter_map(|driver| driver.transactions.front())
+                .map(|transaction| transaction.time)
+        });
+        let timeouts = self
+            .processes
+            .iter()
+            .filter_map(|process| process.wait.timeout);
+        transactions.chain(timeouts).min()
+    }
+
+    /// LRM 14.7.5 A simulation cycle which updates the signals and resumes the processes
+    fn cycle(&mut self) -> Result<(), Message> {
+        let now = self.now;
+        for signal in self.signals.iter_mut() {
+            signal.event = false;
+            let mut active = false;
+            for driver in signal.drivers.iter_mut() {
+                while driver
+                    .transactions
+                    .front()
+                    .is_some_and(|transaction| transaction.time <= now)
+                {
+                    if let Some(transaction) = driver.transactions.pop_front() {
+                        set_part(&mut driver.value, transaction.part, transaction.value);
+                        active = true;
+                    }
+                }
+            }
+            if active {
+                let value = signal.effective_value();
+                if value != signal.value {
+                    signal.last_value = mem::replace(&mut signal.value, value);
+                    signal.event = true;
+                }
+            }
+        }
+        if let Some(ref mut vcd) = self.vcd {
+            for (index, signal) in self.signals.iter().enumerate() {
+                if signal.event {
+                    vcd.change(now, index, &signal.value);
+                }
+            }
+        }
+
+        let mut resumed = Vec::new();
+        for process in 0..self.processes.len() {
+            let wait = &self.processes[process].wait;
+            if wait.timeout == Some(now) {
+                resumed.push(process);
+            } else if wait
+                .signals
+                .iter()
+                .any(|signal| self.signals[*signal].event)
+            {
+                let condition = match wait.condition {
+                    Some(condition) => condition,
+                    None => {
+                        resumed.push(process);
+                        continue;
+                    }
+                };
+                let mut activation = match self.processes[process].activation.take() {
+                    Some(activation) => activation,
+                    None => continue,
+                };
+                let result = self.condition(&mut activation, condition);
+                self.processes[process].activation = Some(activation);
+                if result? {
+                    resumed.push(process);
+                }
+            }
+        }
+        for process in resumed {
+            self.resume(process)?;
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self, process: usize) -> Result<(), Message> {
+        let mut activation = match self.processes[process].activation.take() {
+            Some(activation) => activation,
+            None => return Ok(()),
+        };
+        self.steps = 0;
+        self.last_pos = Some(activation.pos.clone());
+        let body = self.processes[process].body.clone();
+        let result = self.run_process(&body, &mut activation);
+        self.processes[process].activation = Some(activation);
+        self.processes[process].wait = result?;
+        Ok(())
+    }
+
+    fn run_process(
+        &mut self,
+        body: &Body<'a>,
+        act: &mut Activation<'a>,
+    ) -> Result<Wait<'a>, Message> {
+        let (statements, sensitivity) = match body {
+            Body::Statements(statements, sensitivity) => (statements, sensitivity),
+            Body::Assignment(assignment) => {
+                act.reads = Some(BTreeSet::new());
+                self.assign_signal(
+                    act,
+                    &assignment.target,
+                    &assignment.delay_mechanism,
+                    &assignment.rhs,
+                )?;
+                return Ok(self.wait_on_reads(act));
+            }
+            Body::Assert(assert) => {
+                act.reads = Some(BTreeSet::new());
+                self.assert(act, &assert.statement)?;
+                return Ok(self.wait_on_reads(act));
+            }
+        };
+
+        if let Sensitivity::All = sensitivity {
+            act.reads = Some(BTreeSet::new());
+        }
+        loop {
+            if act.stack.is_empty() {
+                act.stack.push(Frame::new(statements, FrameKind::Body));
+            }
+            match self.execute(act)? {
+                Suspend::Wait(wait) => {
+                    if let Sensitivity::None = sensitivity {
+                        return Ok(wait);
+                    }
+                    return Err(error(
+                        &act.pos,
+                        "A process with a sensitivity list cannot wait",
+                    ));
+                }
+                Suspend::End => match sensitivity {
+                    Sensitivity::Signals(signals) => {
+                        return Ok(Wait {
+                            signals: signals.clone(),
+                            ..Wait::default()
+                        })
+                    }
+                    Sensitivity::All => return Ok(self.wait_on_reads(act)),
+                    Sensitivity::None => {}
+                },
+                Suspend::Halt => return Ok(Wait::default()),
+                Suspend::Return(..) => {
+                    return Err(error(&act.pos, "Return outside of a subprogram"));
+                }
+            }
+        }
+    }
+
+    fn wait_on_reads(&self, act: &mut Activation<'a>) -> Wait<'a> {
+        Wait {
+            signals: act
+                .reads
+                .take()
+                .map(|reads| reads.into_iter().collect())
+                .unwrap_or_default(),
+            ..Wait::default()
+        }
+    }
+
+    fn new_signal(&mut self, path: String, typ: Type, value: Value) -> usize {
+        self.signals.push(Signal {
+            path,
+            ports: Vec::new(),
+            typ,
+            initial: value.clone(),
+            last_value: value.clone(),
+            value,
+            event: false,
+            drivers: Vec::new(),
+        });
+        self.signals.len() - 1
+    }
+
+    fn read_signal(&self, act: &mut Activation<'a>, signal: usize) -> Value {
+        if let Some(ref mut reads) = act.reads {
+            reads.insert(signal);
+        }
+        self.signals[signal].value.clone()
+    }
+
+    /// Elaborates an entity and its architecture, associating the generics and ports
+    /// with the actuals of the instance
+    fn entity(
+        &mut self,
+        path: String,
+        entity_id: &UnitId,
+        architecture: Option<String>,
+        instance: Option<(&'a InstantiationStatement, &Rc<Scope<'a>>)>,
+        pos: &SrcPos,
+        depth: usize,
+    ) -> Result<(), Message> {
+        let (library_name, unit) = match self.units.entities.get(entity_id) {
+            Some((library_name, unit)) => (library_name.clone(), *unit),
+            None => return Err(error(pos, &format!("No entity '{}'", entity_id))),
+        };
+        if depth > MAX_DEPTH {
+            return Err(error(
+                pos,
+                &format!("Exceeded the maximum depth of {} instances", MAX_DEPTH),
+            ));
+        }
+        let (generic_list, port_list) = entity_interface(&unit.library_unit);
+        let scope = Scope::new(Some(self.root.clone()), path, &library_name);
+        self.context_clause(&scope, &unit.context_clause, pos)?;
+        let mut act = Activation::new(scope.clone(), usize::MAX, pos);
+        let mut actual_act =
+            instance.map(|(_, scope)| Activation::new(scope.clone(), usize::MAX, pos));
+
+        for (i, decl) in generic_list.iter().enumerate() {
+            let object = match decl {
+                InterfaceDeclaration::Object(ref object) => object,
+                _ => return Err(error(pos, "Only object generics are supported")),
+            };
+            let name = symbol_key(&object.ident.item);
+            let typ = self.subtype(&mut act, &object.subtype_indication)?;
+            let actual = instance.and_then(|(instance, _)| {
+                associated(&instance.generic_map, i, &name).filter(|actual| match actual.item {
+                    ActualPart::Open => false,
+                    ActualPart::Expression(..) => true,
+                })
+            });
+            let value = match (actual, actual_act.as_mut()) {
+                (Some(actual), Some(actual_act)) => match actual.item {
+                    ActualPart::Expression(ref expr) => {
+                        let value = self.eval_expr(actual_act, expr, &actual.pos, Some(&typ))?;
+                        convert(value, &typ).map_err(|msg| error(&actual.pos, &msg))?
+                    }
+                    ActualPart::Open => unreachable!(),
+                },
+                _ => match object.expression {
+                    Some(ref expr) => {
+                        let value = self.eval(&mut act, expr, Some(&typ))?;
+                        convert(value, &typ).map_err(|msg| error(&expr.pos, &msg))?
+                    }
+                    None => {
+                        return Err(error(
+                            pos,
+                            &format!("No value for generic '{}'", object.ident.item.name_utf8()),
+                        ))
+                    }
+                },
+            };
+            scope.declare(name, Item::Constant(value));
+        }
+
+        for (i, decl) in port_list.iter().enumerate() {
+            let object = match decl {
+                InterfaceDeclaration::Object(ref object) => object,
+                _ => return Err(error(pos, "Only signal ports are supported")),
+            };
+            let name = symbol_key(&object.ident.item);
+            let typ = self.subtype(&mut act, &object.subtype_indication)?;
+            let actual =
+                instance.and_then(|(instance, _)| associated(&instance.port_map, i, &name));
+            let mut signal = None;
+            let mut value = None;
+            if let (Some(actual), Some(actual_act)) = (actual, actual_act.as_mut()) {
+                if let ActualPart::Expression(ref expr) = actual.item {
+                    if let Expression::Name(ref name) = expr {
+                        if let Some(Object::Signal(id)) =
+                            self.object(actual_act, name, &actual.pos)?
+                        {
+                            signal = Some(id);
+                        }
+                    }
+                    if signal.is_none() {
+                        if object.mode != Mode::In {
+                            return Err(error(
+                                &actual.pos,
+                                "Only signals and open may be associated with ports of mode out",
+                            ));
+                        }
+                        let actual_value =
+                            self.eval_expr(actual_act, expr, &actual.pos, Some(&typ))?;
+                        value = Some(
+                            convert(actual_value, &typ).map_err(|msg| error(&actual.pos, &msg))?,
+                        );
+                    }
+                }
+            }
+            let signal = match signal {
+                Some(signal) => {
+                    let path = format!("{}.{}", scope.path, name);
+                    self.signals[signal].ports.push(path);
+                    signal
+                }
+                None => {
+                    let value = match value {
+                        Some(value) => value,
+                        None => self.initial_value(
+                            &mut act,
+                            &typ,
+                            &object.expression,
+                            &object.ident.pos,
+                        )?,
+                    };
+                    let path = format!("{}.{}", scope.path, name);
+                    self.new_signal(path, typ, value)
+                }
+            };
+            scope.declare(name, Item::Signal(signal));
+        }
+
+        let architectures = self.units.architectures.get(entity_id);
+        let architecture = match architecture {
+            Some(ref name) => architectures
+                .and_then(|architectures| architectures.iter().find(|(arch, _)| arch == name)),
+            None => architectures.and_then(|architectures| architectures.last()),
+        };
+        let unit = match architecture {
+            Some((_, unit)) => *unit,
+            None => {
+                return Err(error(
+                    pos,
+                    &format!("No architecture of entity '{}'", entity_id),
+                ))
+            }
+        };
+        let (ident, decl, statements) = match unit.library_unit {
+            LibraryUnit::ArchitectureBody {
+                ref ident,
+                ref decl,
+                ref statements,
+                ..
+            } => (ident, decl, statements),
+            _ => return Ok(()),
+        };
+        let scope = scope.child("");
+        self.context_clause(&scope, &unit.context_clause, &ident.pos)?;
+        let mut act = Activation::new(scope.clone(), usize::MAX, &ident.pos);
+        self.declarations(&mut act, decl)?;
+        self.concurrent_statements(&scope, statements, &ident.pos, depth)
+    }
+
+    /// The value of an object declared with the expression, or the left value of its type
+    fn initial_value(
+        &mut self,
+        act: &mut Activation<'a>,
+        typ: &Type,
+        expression: &'a Option<Arc<WithPos<Expression>>>,
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        match expression {
+            Some(ref expr) => {
+                let value = self.eval(act, expr, Some(typ))?;
+                convert(value, typ).map_err(|msg| error(&expr.pos, &msg))
+            }
+            None => match typ {
+                Type::String(None) => Ok(Value::String(Vec::new())),
+                typ => typ.default_value().ok_or_else(|| {
+                    error(pos, &format!("Expected a constrained {} type", typ.name()))
+                }),
+            },
+        }
+    }
+
+    fn concurrent_statements(
+        &mut self,
+        scope: &Rc<Scope<'a>>,
+        statements: &'a [LabeledConcurrentStatement],
+        pos: &SrcPos,
+        depth: usize,
+    ) -> Result<(), Message> {
+        for (i, statement) in statements.iter().enumerate() {
+            let label = statement
+                .label
+                .as_ref()
+                .map(|label| symbol_key(&label.item))
+                .unwrap_or_else(|| format!("_p{}", i));
+            let label_pos = statement.label.as_ref().map(|label| &label.pos);
+            match statement.statement {
+                ConcurrentStatement::Process(ref process) => {
+                    let pos = label_pos
+                        .or_else(|| {
+                            process
+                                .statements
+                                .iter()
+                                .find_map(|statement| statement_pos(&statement.statement))
+                        })
+                        .unwrap_or(pos);
+                    let process_scope = scope.child(&label);
+                    let mut act = Activation::new(process_scope, self.processes.len(), pos);
+                    self.declarations(&mut act, &process.decl)?;
+                    let sensitivity = if process.sensitivity_list.is_empty() {
+                        Sensitivity::None
+                    } else if process
+                        .sensitivity_list
+                        .iter()
+                        .any(|name| name.item == Name::All)
+                    {
+                        Sensitivity::All
+                    } else {
+                        let mut signals = Vec::new();
+                        for name in process.sensitivity_list.iter() {
+                            signals.push(self.signal(&mut act, &name.item, &name.pos)?);
+                        }
+                        Sensitivity::Signals(signals)
+                    };
+                    self.add_process(Body::Statements(&process.statements, sensitivity), act);
+                }
+                ConcurrentStatement::Assert(ref assert) => {
+                    let pos = label_pos.unwrap_or(&assert.statement.condition.pos);
+                    let act = Activation::new(scope.clone(), self.processes.len(), pos);
+                    self.add_process(Body::Assert(assert), act);
+                }
+                ConcurrentStatement::Assignment(ref assignment) => {
+                    let pos = label_pos.unwrap_or(&assignment.target.pos);
+                    let act = Activation::new(scope.clone(), self.processes.len(), pos);
+                    self.add_process(Body::Assignment(assignment), act);
+                }
+                ConcurrentStatement::Instance(ref instance) => {
+                    let pos = label_pos.unwrap_or(pos);
+                    let (entity_id, architecture) = match instance.unit {
+                        InstantiatedUnit::Entity(ref name, ref architecture) => (
+                            unit_name_id(&scope.library_name, name),
+                            architecture.as_ref().map(|ident| symbol_key(&ident.item)),
+                        ),
+                        // Default binding to an entity with the same name in the library
+                        InstantiatedUnit::Component(ref name) => (
+                            name.last().map(|ident| {
+                                UnitId::primary(&scope.library_name, &symbol_key(&ident.item))
+                            }),
+                            None,
+                        ),
+                        InstantiatedUnit::Configuration(..) => {
+                            return Err(error(pos, "Configuration instances are not supported"))
+                        }
+                    };
+                    let entity_id = match entity_id {
+                        Some(entity_id) => entity_id,
+                        None => return Err(error(pos, "Expected an entity name")),
+                    };
+                    let path = format!("{}.{}", scope.path, label);
+                    self.entity(
+                        path,
+                        &entity_id,
+                        architecture,
+                        Some((instance, scope)),
+                        pos,
+                        depth + 1,
+                    )?;
+                }
+                ConcurrentStatement::Block(ref block) => {
+                    let block_scope = scope.child(&label);
+                    let mut act = Activation::new(block_scope.clone(), usize::MAX, pos);
+                    self.declarations(&mut act, &block.decl)?;
+                    self.concurrent_statements(&block_scope, &block.statements, pos, depth)?;
+                }
+                ConcurrentStatement::ForGenerate(ref generate) => {
+                    let mut act = Activation::new(scope.clone(), usize::MAX, pos);
+                    let bounds = self.discrete_range(&mut act, &generate.discrete_range, pos)?;
+                    for offset in 0..bounds.len() {
+                        let index = bounds.index(offset);
+                        let generate_scope = scope.child(&format!("{}({})", label, index));
+                        generate_scope.declare(
+                            symbol_key(&generate.index_name.item),
+                            Item::Constant(Value::Integer(index)),
+                        );
+                        let mut act = Activation::new(generate_scope.clone(), usize::MAX, pos);
+                        if let Some(ref decl) = generate.body.decl {
+                            self.declarations(&mut act, decl)?;
+                        }
+                        self.concurrent_statements(
+                            &generate_scope,
+                            &generate.body.statements,
+                            pos,
+                            depth,
+                        )?;
+                    }
+                }
+                ConcurrentStatement::IfGenerate(ref generate) => {
+                    let mut act = Activation::new(scope.clone(), usize::MAX, pos);
+                    let mut body = generate.else_item.as_ref();
+                    for conditional in generate.conditionals.iter() {
+                        if self.condition(&mut act, &conditional.condition)? {
+                            body = Some(&conditional.item);
+                            break;
+                        }
+                    }
+                    if let Some(body) = body {
+                        let generate_scope = scope.child(&label);
+                        let mut act = Activation::new(generate_scope.clone(), usize::MAX, pos);
+                        if let Some(ref decl) = body.decl {
+                            self.declarations(&mut act, decl)?;
+                        }
+                        self.concurrent_statements(&generate_scope, &body.statements, pos, depth)?;
+                    }
+                }
+                ConcurrentStatement::ProcedureCall(ref call) => {
+                    return Err(error(
+                        &call.call.name.pos,
+                        "Concurrent procedure calls are not supported",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_process(&mut self, body: Body<'a>, activation: Activation<'a>) {
+        self.processes.push(Process {
+            body,
+            activation: Some(activation),
+            wait: Wait::default(),
+        });
+    }
+
+    /// Makes the packages of the use clauses visible within the scope
+    fn context_clause(
+        &mut self,
+        scope: &Rc<Scope<'a>>,
+        items: &'a [ContextItem],
+        pos: &SrcPos,
+    ) -> Result<(), Message> {
+        for item in items.iter() {
+            if let ContextItem::Use(ref use_clause) = item {
+                self.use_clause(scope, use_clause, pos)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn use_clause(
+        &mut self,
+        scope: &Rc<Scope<'a>>,
+        use_clause: &'a UseClause,
+        pos: &SrcPos,
+    ) -> Result<(), Message> {
+        for name in use_clause.name_list.iter() {
+            let parts: Vec<String> = match selected_name_parts(&name.item) {
+                Some(parts) => parts.iter().map(|part| symbol_key(part)).collect(),
+                None => continue,
+            };
+            if parts.len() < 2 || is_builtin_library(&parts[0]) {
+                continue;
+            }
+            let library_name = if parts[0] == "work" {
+                scope.library_name.clone()
+            } else {
+                parts[0].clone()
+            };
+            match self.package(&library_name, &parts[1], pos)? {
+                Some(package) => scope.uses.borrow_mut().push(package),
+                None => {
+                    return Err(error(
+                        &name.pos,
+                        &format!("No package '{}.{}'", parts[0], parts[1]),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The scope of a package declaration and its body, declared on first use
+    fn package(
+        &mut self,
+        library_name: &str,
+        name: &str,
+        pos: &SrcPos,
+    ) -> Result<Option<Rc<Scope<'a>>>, Message> {
+        let unit_id = UnitId::primary(library_name, name);
+        if let Some(package) = self.packages.get(&unit_id) {
+            return Ok(Some(package.clone()));
+        }
+        let (library_name, unit) = match self.units.packages.get(&unit_id) {
+            Some((library_name, unit)) => (library_name.clone(), *unit),
+            None => return Ok(None),
+        };
+        let scope = Scope::new(Some(self.root.clone()), name.to_owned(), &library_name);
+        self.packages.insert(unit_id.clone(), scope.clone());
+        self.context_clause(&scope, &unit.context_clause, pos)?;
+        let mut act = Activation::new(scope.clone(), usize::MAX, pos);
+        if let LibraryUnit::PackageDeclaration { ref decl, .. } = unit.library_unit {
+            self.declarations(&mut act, decl)?;
+        }
+        if let Some(body) = self.units.package_bodies.get(&unit_id).cloned() {
+            self.context_clause(&scope, &body.context_clause, pos)?;
+            if let LibraryUnit::PackageBody { ref decl, .. } = body.library_unit {
+                self.declarations(&mut act, decl)?;
+            }
+        }
+        Ok(Some(scope))
+    }
+
+    fn declarations(
+        &mut self,
+        act: &mut Activation<'a>,
+        declarations: &'a [Declaration],
+    ) -> Result<(), Message> {
+        for declaration in declarations.iter() {
+            match declaration {
+                Declaration::Object(ref object) => {
+                    let name = symbol_key(&object.ident.item);
+                    let typ = self.subtype(act, &object.subtype_indication)?;
+                    match object.class {
+                        ObjectClass::Constant => {
+                            // The value of a deferred constant is declared by the package body
+                            if let Some(ref expr) = object.expression {
+                                let value = self.eval(act, expr, Some(&typ))?;
+                                let value =
+                                    convert(value, &typ).map_err(|msg| error(&expr.pos, &msg))?;
+                                act.scope.declare(name, Item::Constant(value));
+                            }
+                        }
+                        ObjectClass::Signal => {
+                            let value = self.initial_value(
+                                act,
+                                &typ,
+                                &object.expression,
+                                &object.ident.pos,
+                            )?;
+                            let path = format!("{}.{}", act.scope.path, name);
+                            let signal = self.new_signal(path, typ, value);
+                            act.scope.declare(name, Item::Signal(signal));
+                        }
+                        ObjectClass::Variable => {
+                            let value = self.initial_value(
+                                act,
+                                &typ,
+                                &object.expression,
+                                &object.ident.pos,
+                            )?;
+                            act.variables.insert(name, (typ, value));
+                        }
+                        ObjectClass::SharedVariable => {
+                            return Err(error(&object.ident, "Shared variables are not supported"))
+                        }
+                    }
+                }
+                Declaration::File(ref file) => {
+                    return Err(error(&file.ident, "Files are not supported"))
+                }
+                Declaration::Type(ref type_decl) => self.type_declaration(act, type_decl)?,
+                Declaration::Alias(ref alias) => {
+                    let designator = match alias.designator.item {
+                        Designator::Identifier(ref symbol) => symbol_key(symbol),
+                        Designator::OperatorSymbol(..) => continue,
+                    };
+                    match self.object(act, &alias.name.item, &alias.name.pos)? {
+                        Some(Object::Signal(signal)) => {
+                            act.scope.declare(designator, Item::Signal(signal))
+                        }
+                        Some(Object::Item(item, _)) => act.scope.declare(designator, item),
+                        _ => {
+                            return Err(error(
+                                &alias.designator,
+                                "Only aliases of signals, constants, types and subprograms are supported",
+                            ))
+                        }
+                    }
+                }
+                Declaration::SubprogramBody(ref body) => {
+                    let designator = match body.specification {
+                        SubprogramDeclaration::Procedure(ref spec) => &spec.designator.item,
+                        SubprogramDeclaration::Function(ref spec) => &spec.designator.item,
+                    };
+                    if let Designator::Identifier(ref symbol) = designator {
+                        act.scope
+                            .declare(symbol_key(symbol), Item::Subprograms(vec![body]));
+                    }
+                }
+                Declaration::Use(ref use_clause) => {
+                    let scope = act.scope.clone();
+                    let pos = act.pos.clone();
+                    self.use_clause(&scope, use_clause, &pos)?;
+                }
+                Declaration::Component(..)
+                | Declaration::Attribute(..)
+                | Declaration::SubprogramDeclaration(..)
+                | Declaration::Package(..) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn type_declaration(
+        &mut self,
+        act: &mut Activation<'a>,
+        type_decl: &'a TypeDeclaration,
+    ) -> Result<(), Message> {
+        let name = symbol_key(&type_decl.ident.item);
+        let item = match type_decl.def {
+            TypeDefinition::Enumeration(ref enum_literals) => {
+                let mut literals = Vec::new();
+                for literal in enum_literals.iter() {
+                    match literal.item {
+                        EnumerationLiteral::Identifier(ref symbol) => {
+                            literals.push(symbol_key(symbol))
+                        }
+                        EnumerationLiteral::Character(..) => {
+                            act.scope.declare(name, Item::UnsupportedType);
+                            return Ok(());
+                        }
+                    }
+                }
+                let typ = Arc::new(EnumType {
+                    name: name.clone(),
+                    literals,
+                });
+                for (pos, literal) in typ.literals.iter().enumerate() {
+                    act.scope.declare(
+                        literal.clone(),
+                        Item::Constant(Value::Enum(pos, typ.clone())),
+                    );
+                }
+                Item::Type(Type::Enum(typ))
+            }
+            TypeDefinition::Integer(ref range) => {
+                let bounds = self.range(act, range, &type_decl.ident.pos)?;
+                Item::Type(Type::Integer(bounds.low(), bounds.high()))
+            }
+            TypeDefinition::Subtype(ref subtype) => Item::Type(self.subtype(act, subtype)?),
+            _ => Item::UnsupportedType,
+        };
+        act.scope.declare(name, item);
+        Ok(())
+    }
+
+    fn type_mark(
+        &mut self,
+        act: &mut Activation<'a>,
+        type_mark: &'a SelectedName,
+    ) -> Result<Type, Message> {
+        let ident = match type_mark.last() {
+            Some(ident) => ident,
+            None => return Ok(INTEGER),
+        };
+        let name = symbol_key(&ident.item);
+        match lookup(&act.scope, &name) {
+            Some((Item::Type(typ), _)) => return Ok(typ),
+            Some((Item::UnsupportedType, _)) => {
+                return Err(error(
+                    ident,
+                    &format!("Type '{}' is not supported", ident.item.name_utf8()),
+                ))
+            }
+            _ => {}
+        }
+        builtin_type(&name, &self.severity_level).ok_or_else(|| {
+            error(
+                ident,
+                &format!("Type '{}' is not supported", ident.item.name_utf8()),
+            )
+        })
+    }
+
+    fn subtype(
+        &mut self,
+        act: &mut Activation<'a>,
+        subtype: &'a SubtypeIndication,
+    ) -> Result<Type, Message> {
+        let typ = self.type_mark(act, &subtype.type_mark)?;
+        let pos = match subtype.type_mark.last() {
+            Some(ident) => ident.pos.clone(),
+            None => act.pos.clone(),
+        };
+        match (typ, &subtype.constraint) {
+            (typ, None) => Ok(typ),
+            (Type::Integer(..), Some(SubtypeConstraint::Range(ref range))) => {
+                let bounds = self.range(act, range, &pos)?;
+                Ok(Type::Integer(bounds.low(), bounds.high()))
+            }
+            (Type::Vector(kind, _), Some(SubtypeConstraint::Array(ref ranges, None)))
+                if ranges.len() == 1 =>
+            {
+                Ok(Type::Vector(
+                    kind,
+                    Some(self.discrete_range(act, &ranges[0], &pos)?),
+                ))
+            }
+            (Type::String(_), Some(SubtypeConstraint::Array(ref ranges, None)))
+                if ranges.len() == 1 =>
+            {
+                Ok(Type::String(Some(
+                    self.discrete_range(act, &ranges[0], &pos)?,
+                )))
+            }
+            _ => Err(error(&pos, "Constraint is not supported")),
+        }
+    }
+
+    fn range(
+        &mut self,
+        act: &mut Activation<'a>,
+        range: &'a Range,
+        pos: &SrcPos,
+    ) -> Result<Bounds, Message> {
+        match range {
+            Range::Range(ref constraint) => {
+                let left = self.eval(act, &constraint.left_expr, None)?;
+                let left = integer(left, &constraint.left_expr.pos)?;
+                let right = self.eval(act, &constraint.right_expr, None)?;
+                let right = integer(right, &constraint.right_expr.pos)?;
+                Ok(Bounds {
+                    left,
+                    right,
+                    ascending: constraint.direction == Direction::Ascending,
+                })
+            }
+            Range::Attribute(ref attr) => {
+                let name = symbol_key(&attr.attr.item);
+                let bounds = match self.type_name(act, &attr.name.item)? {
+                    Some(Type::Integer(low, high)) => Some(Bounds {
+                        left: low,
+                        right: high,
+                        ascending: true,
+                    }),
+                    Some(Type::Vector(_, bounds)) | Some(Type::String(bounds)) => bounds,
+                    Some(_) => None,
+                    None => self
+                        .eval_name(act, &attr.name.item, &attr.name.pos)?
+                        .bounds(),
+                };
+                let bounds = bounds.ok_or_else(|| error(pos, "Expected an array or range"))?;
+                match name.as_str() {
+                    "range" => Ok(bounds),
+                    "reverse_range" => Ok(Bounds {
+                        left: bounds.right,
+                        right: bounds.left,
+                        ascending: !bounds.ascending,
+                    }),
+                    _ => Err(error(&attr.attr, "Expected range or reverse_range")),
+                }
+            }
+        }
+    }
+
+    fn discrete_range(
+        &mut self,
+        act: &mut Activation<'a>,
+        range: &'a DiscreteRange,
+        pos: &SrcPos,
+    ) -> Result<Bounds, Message> {
+        match range {
+            DiscreteRange::Range(ref range) | DiscreteRange::Discrete(_, Some(ref range)) => {
+                self.range(act, range, pos)
+            }
+            DiscreteRange::Discrete(ref type_mark, None) => match self.type_mark(act, type_mark)? {
+                Type::Integer(low, high) => Ok(Bounds {
+                    left: low,
+                    right: high,
+                    ascending: true,
+                }),
+                typ => Err(error(
+                    pos,
+                    &format!("Ranges of type {} are not supported", typ.name()),
+                )),
+            },
+        }
+    }
+
+    /// The type of a name which denotes a type
+    fn type_name(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+    ) -> Result<Option<Type>, Message> {
+        let symbol = match name {
+            Name::Simple(ref symbol) => symbol,
+            Name::Selected(_, ref suffix) => match suffix.item {
+                Name::Simple(ref symbol) => symbol,
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let key = symbol_key(symbol);
+        if act.variables.contains_key(&key) || act.aliases.contains_key(&key) {
+            return Ok(None);
+        }
+        match lookup(&act.scope, &key) {
+            Some((Item::Type(typ), _)) => Ok(Some(typ)),
+            Some((Item::UnsupportedType, _)) => Err(error(
+                &act.pos,
+                &format!("Type '{}' is not supported", symbol.name_utf8()),
+            )),
+            Some(_) => Ok(None),
+            None => Ok(builtin_type(&key, &self.severity_level)),
+        }
+    }
+
+    /// Resolves a simple or selected name, None when it is not declared
+    fn object(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+        pos: &SrcPos,
+    ) -> Result<Option<Object<'a>>, Message> {
+        match name {
+            Name::Simple(ref symbol) => {
+                let key = symbol_key(symbol);
+                if act.variables.contains_key(&key) {
+                    return Ok(Some(Object::Variable(key)));
+                }
+                if let Some(signal) = act.aliases.get(&key) {
+                    return Ok(Some(Object::Signal(*signal)));
+                }
+                Ok(match lookup(&act.scope, &key) {
+                    Some((Item::Signal(signal), _)) => Some(Object::Signal(signal)),
+                    Some((item, scope)) => Some(Object::Item(item, scope)),
+                    None if is_builtin(&key) => Some(Object::Builtin(key)),
+                    None => None,
+                })
+            }
+            Name::Selected(ref prefix, ref suffix) => {
+                let key = match suffix.item {
+                    Name::Simple(ref symbol) => symbol_key(symbol),
+                    _ => return Ok(None),
+                };
+                let parts: Vec<String> = match selected_name_parts(&prefix.item) {
+                    Some(parts) => parts.iter().map(|part| symbol_key(part)).collect(),
+                    None => return Ok(None),
+                };
+                let (library_name, package_name) = match parts.as_slice() {
+                    [package_name] => (act.scope.library_name.clone(), package_name.clone()),
+                    [library_name, package_name] if library_name == "work" => {
+                        (act.scope.library_name.clone(), package_name.clone())
+                    }
+                    [library_name, package_name] => (library_name.clone(), package_name.clone()),
+                    _ => return Ok(None),
+                };
+                if is_builtin_library(&library_name) {
+                    return Ok(if is_builtin(&key) {
+                        Some(Object::Builtin(key))
+                    } else {
+                        None
+                    });
+                }
+                let package = match self.package(&library_name, &package_name, pos)? {
+                    Some(package) => package,
+                    None => return Ok(None),
+                };
+                let item = package.items.borrow().get(&key).cloned();
+                Ok(item.map(|item| match item {
+                    Item::Signal(signal) => Object::Signal(signal),
+                    item => Object::Item(item, package.clone()),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The signal denoted by the name
+    fn signal(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+        pos: &SrcPos,
+    ) -> Result<usize, Message> {
+        match self.object(act, name, pos)? {
+            Some(Object::Signal(signal)) => Ok(signal),
+            _ => Err(error(pos, "Expected a signal")),
+        }
+    }
+
+    fn eval(
+        &mut self,
+        act: &mut Activation<'a>,
+        expr: &'a WithPos<Expression>,
+        typ: Option<&Type>,
+    ) -> Result<Value, Message> {
+        self.eval_expr(act, &expr.item, &expr.pos, typ)
+    }
+
+    /// The value of the expression where the expected type gives the bounds of
+    /// aggregates and the kind of string literals
+    fn eval_expr(
+        &mut self,
+        act: &mut Activation<'a>,
+        expr: &'a Expression,
+        pos: &SrcPos,
+        typ: Option<&Type>,
+    ) -> Result<Value, Message> {
+        match expr {
+            Expression::Binary(op, ref left, ref right) => {
+                let left_value = self.eval(act, left, None)?;
+                // Short circuit operators LRM 9.2.2
+                if let Value::Boolean(value) = left_value {
+                    match (op, value) {
+                        (Binary::And, false) | (Binary::Nand, false) => {
+                            return Ok(Value::Boolean(*op == Binary::Nand))
+                        }
+                        (Binary::Or, true) | (Binary::Nor, true) => {
+                            return Ok(Value::Boolean(*op == Binary::Or))
+                        }
+                        _ => {}
+                    }
+                }
+                // The operands of a concatenation have lengths of their own
+                let right_type = match (op, value_type(&left_value)) {
+                    (Binary::Concat, Type::Vector(kind, _)) => Type::Vector(kind, None),
+                    (Binary::Concat, Type::String(_)) => Type::String(None),
+                    (_, typ) => typ,
+                };
+                let right_value = self.eval(act, right, Some(&right_type))?;
+                binary(*op, left_value, right_value).map_err(|msg| error(pos, &msg))
+            }
+            Expression::Unary(op, ref expr) => {
+                let value = self.eval(act, expr, typ)?;
+                unary(*op, value).map_err(|msg| error(pos, &msg))
+            }
+            Expression::Aggregate(ref associations) => self.aggregate(act, associations, typ, pos),
+            Expression::Qualified(ref qualified) => {
+                let typ = match self.type_name(act, &qualified.name.item)? {
+                    Some(typ) => typ,
+                    None => return Err(error(&qualified.name.pos, "Expected a type")),
+                };
+                let value = self.eval(act, &qualified.expr, Some(&typ))?;
+                convert(value, &typ).map_err(|msg| error(pos, &msg))
+            }
+            Expression::Name(ref name) => self.eval_name(act, name, pos),
+            Expression::Literal(ref literal) => match literal {
+                Literal::String(ref string) => {
+                    let value = Value::String(string.bytes.clone());
+                    match typ {
+                        Some(typ @ Type::Vector(..)) | Some(typ @ Type::String(..)) => {
+                            convert(value, typ).map_err(|msg| error(pos, &msg))
+                        }
+                        _ => Ok(value),
+                    }
+                }
+                Literal::BitString(ref literal) => {
+                    let elements = bit_string(literal).map_err(|msg| error(pos, &msg))?;
+                    let kind = match typ {
+                        Some(Type::Vector(kind, _)) => *kind,
+                        _ => VectorKind::Logic,
+                    };
+                    let value = Value::Vector(kind, Bounds::descending(elements.len()), elements);
+                    match typ {
+                        Some(typ @ Type::Vector(_, Some(_))) => {
+                            convert(value, typ).map_err(|msg| error(pos, &msg))
+                        }
+                        _ => Ok(value),
+                    }
+                }
+                Literal::Character(chr) => Ok(Value::Character(*chr)),
+                Literal::AbstractLiteral(AbstractLiteral::Integer(value)) => {
+                    Ok(Value::Integer(*value))
+                }
+                Literal::Physical(value, ref unit) => {
+                    let unit = time_unit(&symbol_key(unit)).ok_or_else(|| {
+                        error(pos, "Only physical literals of time are supported")
+                    })?;
+                    Ok(Value::Time(match value {
+                        AbstractLiteral::Integer(value) => value.saturating_mul(unit),
+                        AbstractLiteral::Real(value) => (value * unit as f64).round() as Time,
+                    }))
+                }
+                Literal::AbstractLiteral(AbstractLiteral::Real(..)) => {
+                    Err(error(pos, "Real values are not supported"))
+                }
+                Literal::Null => Err(error(pos, "Access values are not supported")),
+            },
+            Expression::New(..) => Err(error(pos, "Allocators are not supported")),
+        }
+    }
+
+    fn aggregate(
+        &mut self,
+        act: &mut Activation<'a>,
+        associations: &'a [ElementAssociation],
+        typ: Option<&Type>,
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        let (kind, bounds) = match typ {
+            Some(Type::Vector(kind, bounds)) => (Some(*kind), *bounds),
+            Some(Type::String(bounds)) => (None, *bounds),
+            _ => (Some(VectorKind::Logic), None),
+        };
+        let element_typ = match kind {
+            Some(VectorKind::Bit) => Type::Bit,
+            Some(_) => Type::Logic,
+            None => Type::Character,
+        };
+        let positional = associations
+            .iter()
+            .all(|association| matches!(association, ElementAssociation::Positional(..)));
+        let bounds = match bounds {
+            Some(bounds) if !positional || bounds.len() == associations.len() => bounds,
+            _ if positional => match kind {
+                Some(_) => Bounds::descending(associations.len()),
+                None => Bounds::ascending_from(1, associations.len()),
+            },
+            _ => return Err(error(pos, "The bounds of the aggregate are not known")),
+        };
+
+        let mut elements: Vec<Option<u8>> = vec![None; bounds.len()];
+        let mut others = None;
+        let mut next = 0;
+        for association in associations.iter() {
+            let (choices, expr) = match association {
+                ElementAssociation::Positional(ref expr) => {
+                    let chr = self.element(act, expr, &element_typ)?;
+                    if next >= elements.len() {
+                        return Err(error(expr, "Too many elements in the aggregate"));
+                    }
+                    elements[next] = Some(chr);
+                    next += 1;
+                    continue;
+                }
+                ElementAssociation::Named(ref choices, ref expr) => (choices, expr),
+            };
+            let chr = self.element(act, expr, &element_typ)?;
+            for choice in choices.iter() {
+                let offsets: Vec<usize> = match choice {
+                    Choice::Expression(ref index) => {
+                        let index = self.eval(act, index, None)?;
+                        let index = integer(index, &expr.pos)?;
+                        vec![bounds
+                            .offset(index)
+                            .ok_or_else(|| error(&expr.pos, "Index out of the range"))?]
+                    }
+                    Choice::DiscreteRange(ref range) => {
+                        let range = self.discrete_range(act, range, &expr.pos)?;
+                        let mut offsets = Vec::new();
+                        for offset in 0..range.len() {
+                            offsets.push(
+                                bounds
+                                    .offset(range.index(offset))
+                                    .ok_or_else(|| error(&expr.pos, "Index out of the range"))?,
+                            );
+                        }
+                        offsets
+                    }
+                    Choice::Others => {
+                        others = Some(chr);
+                        Vec::new()
+                    }
+                };
+                for offset in offsets {
+                    elements[offset] = Some(chr);
+                }
+            }
+        }
+        let mut values = Vec::with_capacity(elements.len());
+        for element in elements {
+            match element.or(others) {
+                Some(chr) => values.push(chr),
+                None => return Err(error(pos, "Missing elements of the aggregate")),
+            }
+        }
+        Ok(match kind {
+            Some(kind) => Value::Vector(kind, bounds, values),
+            None => Value::String(values),
+        })
+    }
+
+    fn element(
+        &mut self,
+        act: &mut Activation<'a>,
+        expr: &'a WithPos<Expression>,
+        typ: &Type,
+    ) -> Result<u8, Message> {
+        let value = self.eval(act, expr, Some(typ))?;
+        match convert(value, typ).map_err(|msg| error(expr, &msg))? {
+            Value::Character(chr) => Ok(chr),
+            value => Err(error(
+                expr,
+                &format!("Expected an element, got {}", value.image()),
+            )),
+        }
+    }
+
+    fn eval_name(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        match name {
+            Name::Simple(..) | Name::Selected(..) => match self.object(act, name, pos)? {
+                Some(object) => self.object_value(act, object, name, &[], pos),
+                None => Err(error(
+                    pos,
+                    &format!("No declaration of '{}'", name_text(name)),
+                )),
+            },
+            Name::CharacterLiteral(chr) => Ok(Value::Character(*chr)),
+            Name::Indexed(ref prefix, ref indexes) => {
+                let value = self.eval_name(act, &prefix.item, &prefix.pos)?;
+                if indexes.len() != 1 {
+                    return Err(error(pos, "Only one dimensional arrays are supported"));
+                }
+                let index = self.eval(act, &indexes[0], None)?;
+                let index = integer(index, &indexes[0].pos)?;
+                index_value(value, index, pos)
+            }
+            Name::FunctionCall(ref call) => self.call(act, call, pos),
+            Name::Slice(ref prefix, ref range) => {
+                let value = self.eval_name(act, &prefix.item, &prefix.pos)?;
+                let bounds = self.discrete_range(act, range, pos)?;
+                slice_value(value, bounds, pos)
+            }
+            Name::Attribute(ref attr) => self.attribute(act, attr, pos),
+            Name::OperatorSymbol(..) | Name::All => Err(error(pos, "Expected a value")),
+        }
+    }
+
+    /// The value of a resolved name, calling functions with the parameters
+    fn object_value(
+        &mut self,
+        act: &mut Activation<'a>,
+        object: Object<'a>,
+        name: &'a Name,
+        parameters: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        match object {
+            Object::Variable(key) => Ok(act.variables[&key].1.clone()),
+            Object::Signal(signal) => Ok(self.read_signal(act, signal)),
+            Object::Item(Item::Constant(value), _) => Ok(value),
+            Object::Item(Item::Subprograms(bodies), scope) => {
+                self.call_function(act, &bodies, scope, parameters, pos)
+            }
+            Object::Item(Item::Signal(signal), _) => Ok(self.read_signal(act, signal)),
+            Object::Item(Item::Type(..), _) | Object::Item(Item::UnsupportedType, _) => Err(error(
+                pos,
+                &format!("Expected a value, got type '{}'", name_text(name)),
+            )),
+            Object::Builtin(key) => self.builtin_function(act, &key, parameters, pos),
+        }
+    }
+
+    /// A function call, a type conversion or the element of an array
+    fn call(
+        &mut self,
+        act: &mut Activation<'a>,
+        call: &'a FunctionCall,
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        if let Some(conversion) = self.type_name(act, &call.name.item)? {
+            let value = match call.parameters.as_slice() {
+                [parameter] => self.actual(act, &parameter.actual, None)?,
+                _ => return Err(error(pos, "Expected a single value to convert")),
+            };
+            let value = match (&conversion, value) {
+                (Type::Vector(kind, _), Value::Vector(_, bounds, elements)) => {
+                    Value::Vector(*kind, bounds, elements)
+                }
+                (_, value) => value,
+            };
+            return convert(value, &conversion).map_err(|msg| error(pos, &msg));
+        }
+        let object = match self.object(act, &call.name.item, &call.name.pos)? {
+            Some(object) => object,
+            None => {
+                return Err(error(
+                    &call.name,
+                    &format!("No declaration of '{}'", name_text(&call.name.item)),
+                ))
+            }
+        };
+        match object {
+            Object::Item(Item::Subprograms(..), _) | Object::Builtin(..) => {
+                self.object_value(act, object, &call.name.item, &call.parameters, pos)
+            }
+            object => {
+                let value = self.object_value(act, object, &call.name.item, &[], pos)?;
+                let index = match call.parameters.as_slice() {
+                    [parameter] if parameter.formal.is_none() => {
+                        self.actual(act, &parameter.actual, None)?
+                    }
+                    _ => return Err(error(pos, "Only one dimensional arrays are supported")),
+                };
+                index_value(value, integer(index, pos)?, pos)
+            }
+        }
+    }
+
+    fn actual(
+        &mut self,
+        act: &mut Activation<'a>,
+        actual: &'a WithPos<ActualPart>,
+        typ: Option<&Type>,
+    ) -> Result<Value, Message> {
+        match actual.item {
+            ActualPart::Expression(ref expr) => self.eval_expr(act, expr, &actual.pos, typ),
+            ActualPart::Open => Err(error(actual, "Expected a value, got open")),
+        }
+    }
+
+    fn attribute(
+        &mut self,
+        act: &mut Activation<'a>,
+        attr: &'a AttributeName,
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        let name = symbol_key(&attr.attr.item);
+        match name.as_str() {
+            "event" | "last_value" => {
+                let signal = self.signal(act, &attr.name.item, &attr.name.pos)?;
+                self.read_signal(act, signal);
+                let signal = &self.signals[signal];
+                return Ok(if name == "event" {
+                    Value::Boolean(signal.event)
+                } else {
+                    signal.last_value.clone()
+                });
+            }
+            "image" | "pos" | "val" => {
+                let typ = self.type_name(act, &attr.name.item)?;
+                let expr = match attr.expr {
+                    Some(ref expr) => expr,
+                    None => return Err(error(pos, "Expected a parameter of the attribute")),
+                };
+                let value = self.eval(act, expr, typ.as_ref())?;
+                return match (name.as_str(), value) {
+                    ("image", value) => Ok(Value::String(value.image().into_bytes())),
+                    ("pos", Value::Enum(pos, _)) => Ok(Value::Integer(pos as i64)),
+                    ("pos", Value::Integer(value)) => Ok(Value::Integer(value)),
+                    ("pos", Value::Boolean(value)) => Ok(Value::Integer(value as i64)),
+                    ("pos", Value::Character(chr)) => Ok(Value::Integer(match typ {
+                        Some(Type::Logic) => b"UX01ZWLH-"
+                            .iter()
+                            .position(|other| *other == chr)
+                            .unwrap_or(0) as i64,
+                        Some(Type::Bit) => i64::from(chr == b'1'),
+                        _ => i64::from(chr),
+                    })),
+                    ("val", Value::Integer(value)) => {
+                        let typ = typ.ok_or_else(|| error(pos, "Expected a type"))?;
+                        let value = match typ {
+                            Type::Enum(ref typ)
+                                if value >= 0 && (value as usize) < typ.literals.len() =>
+                            {
+                                Value::Enum(value as usize, typ.clone())
+                            }
+                            Type::Boolean => Value::Boolean(value != 0),
+                            Type::Logic if (0..9).contains(&value) => {
+                                Value::Character(b"UX01ZWLH-"[value as usize])
+                            }
+                            Type::Bit | Type::Character if (0..256).contains(&value) => {
+                                Value::Character(if typ == Type::Bit {
+                                    b'0' + value as u8
+                                } else {
+                                    value as u8
+                                })
+                            }
+                            _ => Value::Integer(value),
+                        };
+                        convert(value, &typ).map_err(|msg| error(pos, &msg))
+                    }
+                    (_, value) => Err(error(
+                        pos,
+                        &format!("Attribute {} is not defined for {}", name, value.image()),
+                    )),
+                };
+            }
+            _ => {}
+        }
+
+        // The array and scalar attributes of types and objects
+        let bounds = match self.type_name(act, &attr.name.item)? {
+            Some(Type::Integer(low, high)) => Bounds {
+                left: low,
+                right: high,
+                ascending: true,
+            },
+            Some(Type::Enum(ref typ)) => {
+                let last = typ.literals.len() - 1;
+                let (low, high) = (Value::Enum(0, typ.clone()), Value::Enum(last, typ.clone()));
+                return match name.as_str() {
+                    "left" | "low" => Ok(low),
+                    "right" | "high" => Ok(high),
+                    _ => Err(error(&attr.attr, "Attribute is not supported")),
+                };
+            }
+            Some(Type::Vector(_, Some(bounds))) | Some(Type::String(Some(bounds))) => bounds,
+            Some(_) => return Err(error(&attr.attr, "Attribute is not supported")),
+            None => {
+                let value = self.eval_name(act, &attr.name.item, &attr.name.pos)?;
+                value
+                    .bounds()
+                    .ok_or_else(|| error(&attr.name, "Expected an array"))?
+            }
+        };
+        match name.as_str() {
+            "length" => Ok(Value::Integer(bounds.len() as i64)),
+            "left" => Ok(Value::Integer(bounds.left)),
+            "right" => Ok(Value::Integer(bounds.right)),
+            "high" => Ok(Value::Integer(bounds.high())),
+            "low" => Ok(Value::Integer(bounds.low())),
+            "ascending" => Ok(Value::Boolean(bounds.ascending)),
+            _ => Err(error(
+                &attr.attr,
+                &format!(
+                    "Attribute '{}' is not supported",
+                    attr.attr.item.name_utf8()
+                ),
+            )),
+        }
+    }
+
+    /// The body of the subprogram whose parameters can be associated with the actuals
+    fn overload(
+        bodies: &[&'a SubprogramBody],
+        procedure: bool,
+        parameters: &[AssociationElement],
+    ) -> Option<(&'a SubprogramBody, &'a [InterfaceDeclaration])> {
+        bodies.iter().find_map(|body| {
+            let parameter_list = match body.specification {
+                SubprogramDeclaration::Procedure(ref spec) if procedure => &spec.parameter_list,
+                SubprogramDeclaration::Function(ref spec) if !procedure => &spec.parameter_list,
+                _ => return None,
+            };
+            let required = parameter_list
+                .iter()
+                .filter(|decl| match decl {
+                    InterfaceDeclaration::Object(ref object) => object.expression.is_none(),
+                    _ => true,
+                })
+                .count();
+            if parameters.len() >= required && parameters.len() <= parameter_list.len() {
+                Some((*body, parameter_list.as_slice()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Declares the parameters of the callee with the actuals of the caller, returning
+    /// the out parameters with the variables associated with them
+    fn parameters(
+        &mut self,
+        caller: &mut Activation<'a>,
+        callee: &mut Activation<'a>,
+        formals: &'a [InterfaceDeclaration],
+        actuals: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<Vec<(String, String)>, Message> {
+        let mut outputs = Vec::new();
+        for (i, decl) in formals.iter().enumerate() {
+            let object = match decl {
+                InterfaceDeclaration::Object(ref object) => object,
+                _ => return Err(error(pos, "Only object parameters are supported")),
+            };
+            let name = symbol_key(&object.ident.item);
+            let typ = self.subtype(callee, &object.subtype_indication)?;
+            let actual = associated(actuals, i, &name).filter(|actual| match actual.item {
+                ActualPart::Expression(..) => true,
+                ActualPart::Open => false,
+            });
+            let actual_name = actual.and_then(|actual| match actual.item {
+                ActualPart::Expression(Expression::Name(ref name)) => {
+                    Some((name.as_ref(), &actual.pos))
+                }
+                _ => None,
+            });
+
+            let signal = match actual_name {
+                Some((name, actual_pos))
+                    if object.class == ObjectClass::Signal || object.mode != Mode::In =>
+                {
+                    match self.object(caller, name, actual_pos)? {
+                        Some(Object::Signal(signal)) => Some(signal),
+                        _ if object.class == ObjectClass::Signal => {
+                            return Err(error(actual_pos, "Expected a signal"))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some(signal) = signal {
+                callee.aliases.insert(name, signal);
+                continue;
+            }
+
+            if object.mode == Mode::In {
+                let value = match actual {
+                    Some(actual) => self.actual(caller, actual, Some(&typ))?,
+                    None => match object.expression {
+                        Some(ref expr) => self.eval(callee, expr, Some(&typ))?,
+                        None => {
+                            return Err(error(
+                                pos,
+                                &format!(
+                                    "No actual for parameter '{}'",
+                                    object.ident.item.name_utf8()
+                                ),
+                            ))
+                        }
+                    },
+                };
+                let value = convert(value, &typ).map_err(|msg| error(pos, &msg))?;
+                callee.variables.insert(name, (typ, value));
+            } else {
+                let variable = match actual_name {
+                    Some((Name::Simple(ref symbol), _))
+                        if caller.variables.contains_key(&symbol_key(symbol)) =>
+                    {
+                        symbol_key(symbol)
+                    }
+                    _ => {
+                        return Err(error(
+                            pos,
+                            &format!(
+                                "Expected a variable or signal for parameter '{}'",
+                                object.ident.item.name_utf8()
+                            ),
+                        ))
+                    }
+                };
+                let (variable_type, value) = caller.variables[&variable].clone();
+                let typ = match typ {
+                    Type::Vector(_, None) | Type::String(None) => variable_type,
+                    typ => typ,
+                };
+                callee.variables.insert(name.clone(), (typ, value));
+                outputs.push((name, variable));
+            }
+        }
+        if actuals.len() > formals.len() {
+            return Err(error(pos, "Too many parameters"));
+        }
+        Ok(outputs)
+    }
+
+    fn call_function(
+        &mut self,
+        act: &mut Activation<'a>,
+        bodies: &[&'a SubprogramBody],
+        scope: Rc<Scope<'a>>,
+        parameters: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        let (body, formals) = Simulation::overload(bodies, false, parameters)
+            .ok_or_else(|| error(pos, "No function with the parameters"))?;
+        if act.depth >= MAX_DEPTH {
+            return Err(error(
+                pos,
+                &format!("Exceeded the maximum depth of {} calls", MAX_DEPTH),
+            ));
+        }
+        let mut callee = Activation::new(scope.child(""), act.process, pos);
+        callee.depth = act.depth + 1;
+        callee.reads = act.reads.take();
+        let result = self.run_function(act, &mut callee, body, formals, parameters, pos);
+        act.reads = callee.reads.take();
+        result
+    }
+
+    fn run_function(
+        &mut self,
+        act: &mut Activation<'a>,
+        callee: &mut Activation<'a>,
+        body: &'a SubprogramBody,
+        formals: &'a [InterfaceDeclaration],
+        parameters: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        self.parameters(act, callee, formals, parameters, pos)?;
+        if let SubprogramDeclaration::Function(ref spec) = body.specification {
+            callee.return_type = Some(self.type_mark(callee, &spec.return_type)?);
+        }
+        self.declarations(callee, &body.declarations)?;
+        callee
+            .stack
+            .push(Frame::new(&body.statements, FrameKind::Body));
+        match self.execute(callee)? {
+            Suspend::Return(Some(value)) => match callee.return_type {
+                Some(ref typ) => convert(value, typ).map_err(|msg| error(pos, &msg)),
+                None => Ok(value),
+            },
+            Suspend::Wait(..) => Err(error(pos, "Functions cannot wait")),
+            Suspend::Halt => Ok(Value::Boolean(false)),
+            _ => Err(error(pos, "Function ended without return")),
+        }
+    }
+
+    fn call_procedure(
+        &mut self,
+        act: &mut Activation<'a>,
+        call: &'a FunctionCall,
+    ) -> Result<(), Message> {
+        let pos = &call.name.pos;
+        let (bodies, scope) = match self.object(act, &call.name.item, pos)? {
+            Some(Object::Item(Item::Subprograms(bodies), scope)) => (bodies, scope),
+            Some(Object::Builtin(key)) => {
+                return self.builtin_procedure(act, &key, &call.parameters, pos)
+            }
+            _ => {
+                return Err(error(
+                    pos,
+                    &format!("No procedure '{}'", name_text(&call.name.item)),
+                ))
+            }
+        };
+        let (body, formals) = Simulation::overload(&bodies, true, &call.parameters)
+            .ok_or_else(|| error(pos, "No procedure with the parameters"))?;
+        if act.depth >= MAX_DEPTH {
+            return Err(error(
+                pos,
+                &format!("Exceeded the maximum depth of {} calls", MAX_DEPTH),
+            ));
+        }
+        let mut callee = Activation::new(scope.child(""), act.process, pos);
+        let outputs = self.parameters(act, &mut callee, formals, &call.parameters, pos)?;
+        self.declarations(&mut callee, &body.declarations)?;
+
+        // The procedure runs within the activation of the caller such that it may wait
+        let caller = Call {
+            scope: mem::replace(&mut act.scope, callee.scope),
+            variables: mem::replace(&mut act.variables, callee.variables),
+            aliases: mem::replace(&mut act.aliases, callee.aliases),
+            return_type: act.return_type.take(),
+            outputs,
+        };
+        act.depth += 1;
+        act.stack.push(Frame::new(
+            &body.statements,
+            FrameKind::Procedure(Box::new(caller)),
+        ));
+        Ok(())
+    }
+
+    /// Restores the caller of the procedure at the top of the stack
+    fn return_from_procedure(&mut self, act: &mut Activation<'a>) -> Result<(), Message> {
+        let caller = match act.stack.pop() {
+            Some(Frame {
+                kind: FrameKind::Procedure(caller),
+                ..
+            }) => caller,
+            _ => return Ok(()),
+        };
+        let caller = *caller;
+        let caller_pos = act.pos.clone();
+        let variables = mem::replace(&mut act.variables, caller.variables);
+        act.scope = caller.scope;
+        act.aliases = caller.aliases;
+        act.return_type = caller.return_type;
+        act.depth -= 1;
+        for (formal, variable) in caller.outputs {
+            if let (Some((_, value)), Some((typ, target))) =
+                (variables.get(&formal), act.variables.get_mut(&variable))
+            {
+                *target = convert(value.clone(), typ).map_err(|msg| error(&caller_pos, &msg))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn arguments(
+        &mut self,
+        act: &mut Activation<'a>,
+        parameters: &'a [AssociationElement],
+    ) -> Result<Vec<Value>, Message> {
+        let mut values = Vec::with_capacity(parameters.len());
+        for parameter in parameters.iter() {
+            values.push(self.actual(act, &parameter.actual, None)?);
+        }
+        Ok(values)
+    }
+
+    fn builtin_function(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &str,
+        parameters: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<Value, Message> {
+        if name == "rising_edge" || name == "falling_edge" {
+            let signal = match parameters {
+                [AssociationElement {
+                    actual:
+                        WithPos {
+                            item: ActualPart::Expression(Expression::Name(ref name)),
+                            ref pos,
+                        },
+                    ..
+                }] => self.signal(act, name, pos)?,
+                _ => return Err(error(pos, "Expected a signal")),
+            };
+            self.read_signal(act, signal);
+            let signal = &self.signals[signal];
+            let (from, to) = if name == "rising_edge" {
+                (b'0', b'1')
+            } else {
+                (b'1', b'0')
+            };
+            let x01 = |value: &Value| match value {
+                Value::Character(b'1') | Value::Character(b'H') => b'1',
+                Value::Character(b'0') | Value::Character(b'L') => b'0',
+                _ => b'X',
+            };
+            return Ok(Value::Boolean(
+                signal.event && x01(&signal.value) == to && x01(&signal.last_value) == from,
+            ));
+        }
+
+        let arguments = self.arguments(act, parameters)?;
+        let invalid = || error(pos, &format!("Invalid parameters of {}", name));
+        let length = |value: &Value| match value {
+            Value::Integer(length) if *length >= 0 => Some(*length as usize),
+            Value::Vector(_, bounds, _) => Some(bounds.len()),
+            _ => None,
+        };
+        Ok(match (name, arguments.as_slice()) {
+            ("now", []) => Value::Time(self.now),
+            ("to_integer", [Value::Vector(kind, _, ref elements)]) => {
+                // A metavalue gives 0 like numeric_std
+                Value::Integer(vector_integer(*kind, elements).unwrap_or(0))
+            }
+            ("to_unsigned", [Value::Integer(value), ref size])
+            | ("to_signed", [Value::Integer(value), ref size]) => {
+                let kind = if name == "to_unsigned" {
+                    VectorKind::Unsigned
+                } else {
+                    VectorKind::Signed
+                };
+                numeric_vector(kind, *value, length(size).ok_or_else(invalid)?)
+            }
+            ("resize", [ref value, ref size]) => {
+                resize(value, length(size).ok_or_else(invalid)?).map_err(|msg| error(pos, &msg))?
+            }
+            ("to_string", [Value::Character(chr)]) => Value::String(vec![*chr]),
+            ("to_string", [ref value]) => Value::String(value.to_text().into_bytes()),
+            ("to_hstring", [ref value]) => {
+                Value::String(hex_string(value).ok_or_else(invalid)?.into_bytes())
+            }
+            ("shift_left", [ref value, Value::Integer(amount)]) => {
+                binary(Binary::SLL, value.clone(), Value::Integer(*amount))
+                    .map_err(|msg| error(pos, &msg))?
+            }
+            (
+                "shift_right",
+                [Value::Vector(VectorKind::Signed, bounds, elements), Value::Integer(amount)],
+            ) => binary(
+                Binary::SRA,
+                Value::Vector(VectorKind::Signed, *bounds, elements.clone()),
+                Value::Integer(*amount),
+            )
+            .map_err(|msg| error(pos, &msg))?,
+            ("shift_right", [ref value, Value::Integer(amount)]) => {
+                binary(Binary::SRL, value.clone(), Value::Integer(*amount))
+                    .map_err(|msg| error(pos, &msg))?
+            }
+            ("minimum", [Value::Integer(left), Value::Integer(right)]) => {
+                Value::Integer(*left.min(right))
+            }
+            ("maximum", [Value::Integer(left), Value::Integer(right)]) => {
+                Value::Integer(*left.max(right))
+            }
+            ("to_stdlogicvector", [Value::Vector(_, bounds, ref elements)])
+            | ("to_stdulogicvector", [Value::Vector(_, bounds, ref elements)]) => {
+                Value::Vector(VectorKind::Logic, *bounds, elements.clone())
+            }
+            ("to_bitvector", [Value::Vector(_, bounds, ref elements)]) => Value::Vector(
+                VectorKind::Bit,
+                *bounds,
+                elements
+                    .iter()
+                    .map(|chr| {
+                        if *chr == b'1' || *chr == b'H' {
+                            b'1'
+                        } else {
+                            b'0'
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => return Err(invalid()),
+        })
+    }
+
+    fn builtin_procedure(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &str,
+        parameters: &'a [AssociationElement],
+        pos: &SrcPos,
+    ) -> Result<(), Message> {
+        match name {
+            "stop" | "finish" => {
+                self.finish = Some(Finish::Stop);
+                Ok(())
+            }
+            "write" | "hwrite" | "writeline" | "deallocate" => {
+                let line_index = if name == "writeline" { 1 } else { 0 };
+                let line = match parameters
+                    .get(line_index)
+                    .map(|parameter| &parameter.actual)
+                {
+                    Some(WithPos {
+                        item: ActualPart::Expression(Expression::Name(ref name)),
+                        ..
+                    }) => match name.as_ref() {
+                        Name::Simple(ref symbol)
+                            if act.variables.contains_key(&symbol_key(symbol)) =>
+                        {
+                            symbol_key(symbol)
+                        }
+                        _ => return Err(error(pos, "Expected a line variable")),
+                    },
+                    _ => return Err(error(pos, "Expected a line variable")),
+                };
+                let mut text = match act.variables[&line].1 {
+                    Value::String(ref text) => text.clone(),
+                    _ => return Err(error(pos, "Expected a line variable")),
+                };
+                match name {
+                    "writeline" => {
+                        self.output.extend_from_slice(&text);
+                        self.output.push(b'\n');
+                        text.clear();
+                    }
+                    "deallocate" => text.clear(),
+                    _ => {
+                        let arguments = self.arguments(act, &parameters[1..])?;
+                        let value = match arguments.first() {
+                            Some(value) if name == "hwrite" => {
+                                hex_string(value).ok_or_else(|| error(pos, "Expected a vector"))?
+                            }
+                            Some(Value::Character(chr)) => (*chr as char).to_string(),
+                            Some(value) => value.to_text(),
+                            None => return Err(error(pos, "Expected a value to write")),
+                        };
+                        let (left, field) = match arguments.get(1..) {
+                            Some([Value::Enum(side, _), Value::Integer(field)]) => {
+                                (*side == 1, *field as usize)
+                            }
+                            Some([Value::Enum(side, _)]) => (*side == 1, 0),
+                            _ => (false, 0),
+                        };
+                        let padding = " ".repeat(field.saturating_sub(value.len()));
+                        if !left {
+                            text.extend_from_slice(padding.as_bytes());
+                        }
+                        text.extend_from_slice(value.as_bytes());
+                        if left {
+                            text.extend_from_slice(padding.as_bytes());
+                        }
+                    }
+                }
+                act.variables.get_mut(&line).unwrap().1 = Value::String(text);
+                Ok(())
+            }
+            _ => Err(error(pos, &format!("Expected a procedure, got {}", name))),
+        }
+    }
+
+    fn condition(
+        &mut self,
+        act: &mut Activation<'a>,
+        expr: &'a WithPos<Expression>,
+    ) -> Result<bool, Message> {
+        match self.eval(act, expr, Some(&Type::Boolean))? {
+            Value::Boolean(value) => Ok(value),
+            // LRM 9.2.9 VHDL-2008 condition operator
+            Value::Character(chr) => Ok(chr == b'1' || chr == b'H'),
+            value => Err(error(
+                expr,
+                &format!("Expected a boolean condition, got {}", value.image()),
+            )),
+        }
+    }
+
+    /// Runs statements until the process waits, or the statements end or return
+    fn execute(&mut self, act: &mut Activation<'a>) -> Result<Suspend<'a>, Message> {
+        loop {
+            if self.finish.is_some() {
+                return Ok(Suspend::Halt);
+            }
+            let frame = match act.stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(Suspend::End),
+            };
+            let statements = frame.statements;
+            if frame.next >= statements.len() {
+                self.end_of_frame(act)?;
+                continue;
+            }
+            let statement = &statements[frame.next];
+            frame.next += 1;
+
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                let pos = statement_pos(&statement.statement).unwrap_or(&act.pos);
+                return Err(error(
+                    pos,
+                    &format!(
+                        "Exceeded {} statements without waiting at {}",
+                        MAX_STEPS,
+                        time_image(self.now)
+                    ),
+                ));
+            }
+            if let Some(suspend) = self.statement(act, statement)? {
+                return Ok(suspend);
+            }
+        }
+    }
+
+    fn end_of_frame(&mut self, act: &mut Activation<'a>) -> Result<(), Message> {
+        match act.stack.last().map(|frame| &frame.kind) {
+            Some(FrameKind::Loop(..)) => self.next_iteration(act),
+            Some(FrameKind::Procedure(..)) => self.return_from_procedure(act),
+            Some(_) => {
+                act.stack.pop();
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Starts the next iteration of the loop at the top of the stack or leaves it
+    fn next_iteration(&mut self, act: &mut Activation<'a>) -> Result<(), Message> {
+        let condition = match act.stack.last_mut() {
+            Some(Frame {
+                kind: FrameKind::Loop(_, Loop::While(condition)),
+                ..
+            }) => Some(*condition),
+            Some(Frame {
+                kind:
+                    FrameKind::Loop(
+                        _,
+                        Loop::For {
+                            ref name,
+                            ref mut index,
+                            last,
+                            ascending,
+                            ..
+                        },
+                    ),
+                ref mut next,
+                ..
+            }) => {
+                if *index != *last {
+                    *index += if *ascending { 1 } else { -1 };
+                    *next = 0;
+                    let value = Value::Integer(*index);
+                    act.variables.insert(name.clone(), (INTEGER, value));
+                    return Ok(());
+                }
+                None
+            }
+            Some(Frame {
+                kind: FrameKind::Loop(_, Loop::Forever),
+                ref mut next,
+                ..
+            }) => {
+                *next = 0;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        if let Some(condition) = condition {
+            if self.condition(act, condition)? {
+                if let Some(frame) = act.stack.last_mut() {
+                    frame.next = 0;
+                }
+                return Ok(());
+            }
+        }
+        self.leave_loop(act);
+        Ok(())
+    }
+
+    fn leave_loop(&mut self, act: &mut Activation<'a>) {
+        if let Some(Frame {
+            kind: FrameKind::Loop(_, Loop::For { name, hidden, .. }),
+            ..
+        }) = act.stack.pop()
+        {
+            match hidden {
+                Some(hidden) => {
+                    act.variables.insert(name, hidden);
+                }
+                None => {
+                    act.variables.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Continues or leaves the innermost loop or the loop of the label
+    fn jump(
+        &mut self,
+        act: &mut Activation<'a>,
+        label: &Option<WithPos<Symbol>>,
+        next: bool,
+        pos: &SrcPos,
+    ) -> Result<(), Message> {
+        let label = label.as_ref().map(|label| symbol_key(&label.item));
+        loop {
+            match act.stack.last().map(|frame| &frame.kind) {
+                Some(FrameKind::Loop(ref loop_label, _))
+                    if label.is_none() || *loop_label == label =>
+                {
+                    break;
+                }
+                Some(FrameKind::Loop(..)) => self.leave_loop(act),
+                Some(FrameKind::Branch) => {
+                    act.stack.pop();
+                }
+                _ => return Err(error(pos, "Expected to be within a loop")),
+            }
+        }
+        if next {
+            self.next_iteration(act)
+        } else {
+            self.leave_loop(act);
+            Ok(())
+        }
+    }
+
+    fn statement(
+        &mut self,
+        act: &mut Activation<'a>,
+        statement: &'a LabeledSequentialStatement,
+    ) -> Result<Option<Suspend<'a>>, Message> {
+        match statement.statement {
+            SequentialStatement::Wait(ref wait) => {
+                return Ok(Some(Suspend::Wait(self.wait(act, wait)?)));
+            }
+            SequentialStatement::Assert(ref assert) => self.assert(act, assert)?,
+            SequentialStatement::Report(ref report) => {
+                let message = self.eval(act, &report.report, None)?.to_text();
+                let severity = self.severity(act, &report.severity, NOTE)?;
+                self.report(&report.report.pos, "report", severity, &message);
+            }
+            SequentialStatement::VariableAssignment(ref assignment) => {
+                if let Some(expr) = self.select(act, &assignment.rhs)? {
+                    self.assign_variable(act, &assignment.target, expr)?;
+                }
+            }
+            SequentialStatement::SignalAssignment(ref assignment) => self.assign_signal(
+                act,
+                &assignment.target,
+                &assignment.delay_mechanism,
+                &assignment.rhs,
+            )?,
+            SequentialStatement::ProcedureCall(ref call) => self.call_procedure(act, call)?,
+            SequentialStatement::If(ref statement) => {
+                let mut branch = statement.else_item.as_ref();
+                for conditional in statement.conditionals.iter() {
+                    if self.condition(act, &conditional.condition)? {
+                        branch = Some(&conditional.item);
+                        break;
+                    }
+                }
+                if let Some(statements) = branch {
+                    act.stack.push(Frame::new(statements, FrameKind::Branch));
+                }
+            }
+            SequentialStatement::Case(ref statement) => {
+                let value = self.eval(act, &statement.expression, None)?;
+                let mut branch = None;
+                for alternative in statement.alternatives.iter() {
+                    if self.choices_match(
+                        act,
+                        &alternative.choices,
+                        &value,
+                        &statement.expression.pos,
+                    )? {
+                        branch = Some(&alternative.item);
+                        break;
+                    }
+                }
+                match branch {
+                    Some(statements) => act.stack.push(Frame::new(statements, FrameKind::Branch)),
+                    None => {
+                        return Err(error(
+                            &statement.expression,
+                            &format!("No case alternative for {}", value.image()),
+                        ))
+                    }
+                }
+            }
+            SequentialStatement::Loop(ref loop_statement) => {
+                let label = statement
+                    .label
+                    .as_ref()
+                    .map(|label| symbol_key(&label.item));
+                let scheme = match loop_statement.iteration_scheme {
+                    None => Loop::Forever,
+                    Some(IterationScheme::While(ref condition)) => {
+                        if !self.condition(act, condition)? {
+                            return Ok(None);
+                        }
+                        Loop::While(condition)
+                    }
+                    Some(IterationScheme::For(ref ident, ref range)) => {
+                        let bounds = self.discrete_range(act, range, &ident.pos)?;
+                        if bounds.is_empty() {
+                            return Ok(None);
+                        }
+                        let name = symbol_key(&ident.item);
+                        let hidden = act
+                            .variables
+                            .insert(name.clone(), (INTEGER, Value::Integer(bounds.left)));
+                        Loop::For {
+                            name,
+                            index: bounds.left,
+                            last: bounds.right,
+                            ascending: bounds.ascending,
+                            hidden,
+                        }
+                    }
+                };
+                act.stack.push(Frame::new(
+                    &loop_statement.statements,
+                    FrameKind::Loop(label, scheme),
+                ));
+            }
+            SequentialStatement::Next(ref next) => {
+                if self.optional_condition(act, &next.condition)? {
+                    let pos = statement_pos(&statement.statement)
+                        .unwrap_or(&act.pos)
+                        .clone();
+                    self.jump(act, &next.loop_label, true, &pos)?;
+                }
+            }
+            SequentialStatement::Exit(ref exit) => {
+                if self.optional_condition(act, &exit.condition)? {
+                    let pos = statement_pos(&statement.statement)
+                        .unwrap_or(&act.pos)
+                        .clone();
+                    self.jump(act, &exit.loop_label, false, &pos)?;
+                }
+            }
+            SequentialStatement::Return(ref statement) => {
+                let value = match statement.expression {
+                    Some(ref expr) => {
+                        let typ = act.return_type.clone();
+                        Some(self.eval(act, expr, typ.as_ref())?)
+                    }
+                    None => None,
+                };
+                let procedure = act
+                    .stack
+                    .iter()
+                    .rposition(|frame| matches!(frame.kind, FrameKind::Procedure(..)));
+                match procedure {
+                    Some(procedure) => {
+                        act.stack.truncate(procedure + 1);
+                        self.return_from_procedure(act)?;
+                    }
+                    None => return Ok(Some(Suspend::Return(value))),
+                }
+            }
+            SequentialStatement::Null => {}
+        }
+        Ok(None)
+    }
+
+    fn optional_condition(
+        &mut self,
+        act: &mut Activation<'a>,
+        condition: &'a Option<WithPos<Expression>>,
+    ) -> Result<bool, Message> {
+        match condition {
+            Some(ref condition) => self.condition(act, condition),
+            None => Ok(true),
+        }
+    }
+
+    fn choices_match(
+        &mut self,
+        act: &mut Activation<'a>,
+        choices: &'a [Choice],
+        value: &Value,
+        pos: &SrcPos,
+    ) -> Result<bool, Message> {
+        for choice in choices.iter() {
+            let matches = match choice {
+                Choice::Expression(ref expr) => {
+                    let choice = self.eval(act, expr, Some(&value_type(value)))?;
+                    binary(Binary::EQ, value.clone(), choice).map_err(|msg| error(expr, &msg))?
+                        == Value::Boolean(true)
+                }
+                Choice::DiscreteRange(ref range) => {
+                    let bounds = self.discrete_range(act, range, pos)?;
+                    match value {
+                        Value::Integer(value) => bounds.offset(*value).is_some(),
+                        _ => return Err(error(pos, "Expected an integer value")),
+                    }
+                }
+                Choice::Others => true,
+            };
+            if matches {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The item selected by the conditions or the expression, None when no item is selected
+    fn select<T>(
+        &mut self,
+        act: &mut Activation<'a>,
+        rhs: &'a AssignmentRightHand<T>,
+    ) -> Result<Option<&'a T>, Message> {
+        match rhs {
+            AssignmentRightHand::Simple(ref item) => Ok(Some(item)),
+            AssignmentRightHand::Conditional(ref conditionals) => {
+                for conditional in conditionals.conditionals.iter() {
+                    if self.condition(act, &conditional.condition)? {
+                        return Ok(Some(&conditional.item));
+                    }
+                }
+                Ok(conditionals.else_item.as_ref())
+            }
+            AssignmentRightHand::Selected(ref selection) => {
+                let value = self.eval(act, &selection.expression, None)?;
+                for alternative in selection.alternatives.iter() {
+                    if self.choices_match(
+                        act,
+                        &alternative.choices,
+                        &value,
+                        &selection.expression.pos,
+                    )? {
+                        return Ok(Some(&alternative.item));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn severity(
+        &mut self,
+        act: &mut Activation<'a>,
+        severity: &'a Option<WithPos<Expression>>,
+        default: usize,
+    ) -> Result<usize, Message> {
+        let expr = match severity {
+            Some(ref expr) => expr,
+            None => return Ok(default),
+        };
+        match self.eval(act, expr, None)? {
+            Value::Enum(level, ref typ) if *typ == self.severity_level => Ok(level),
+            value => Err(error(
+                expr,
+                &format!("Expected a severity level, got {}", value.image()),
+            )),
+        }
+    }
+
+    fn assert(
+        &mut self,
+        act: &mut Activation<'a>,
+        assert: &'a AssertStatement,
+    ) -> Result<(), Message> {
+        if self.condition(act, &assert.condition)? {
+            return Ok(());
+        }
+        let message = match assert.report {
+            Some(ref report) => self.eval(act, report, None)?.to_text(),
+            None => "Assertion violation.".to_owned(),
+        };
+        let severity = self.severity(act, &assert.severity, ERROR)?;
+        self.report(&assert.condition.pos, "assertion", severity, &message);
+        Ok(())
+    }
+
+    /// Writes the report, where an error is counted and a failure stops the simulation
+    fn report(&mut self, pos: &SrcPos, kind: &str, severity: usize, message: &str) {
+        let (line, column) = pos.line_and_column();
+        let _ = writeln!(
+            self.output,
+            "{}:{}:{}:@{}:({} {}): {}",
+            pos.source.file_name().unwrap_or(""),
+            line,
+            column,
+            time_image(self.now).replace(' ', ""),
+            kind,
+            SEVERITY_NAMES[severity],
+            message
+        );
+        if severity >= ERROR {
+            self.errors += 1;
+        }
+        if severity == FAILURE {
+            self.finish = Some(Finish::Failure);
+        }
+    }
+
+    fn wait(
+        &mut self,
+        act: &mut Activation<'a>,
+        wait: &'a WaitStatement,
+    ) -> Result<Wait<'a>, Message> {
+        let mut signals = Vec::new();
+        for name in wait.sensitivity_clause.iter() {
+            signals.push(self.signal(act, &name.item, &name.pos)?);
+        }
+        if let (true, Some(ref condition)) = (signals.is_empty(), &wait.condition_clause) {
+            self.signals_in(act, &condition.item, &condition.pos, &mut signals)?;
+        }
+        let timeout = match wait.timeout_clause {
+            Some(ref expr) => match self.eval(act, expr, None)? {
+                Value::Time(time) if time >= 0 => Some(self.now.saturating_add(time)),
+                value => {
+                    return Err(error(
+                        expr,
+                        &format!("Expected a non-negative time, got {}", value.image()),
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(Wait {
+            signals,
+            condition: wait.condition_clause.as_ref(),
+            timeout,
+        })
+    }
+
+    /// The signals read by the expression, the implicit sensitivity of a wait statement
+    fn signals_in(
+        &mut self,
+        act: &mut Activation<'a>,
+        expr: &'a Expression,
+        pos: &SrcPos,
+        signals: &mut Vec<usize>,
+    ) -> Result<(), Message> {
+        match expr {
+            Expression::Binary(_, ref left, ref right) => {
+                self.signals_in(act, &left.item, &left.pos, signals)?;
+                self.signals_in(act, &right.item, &right.pos, signals)
+            }
+            Expression::Unary(_, ref expr) => self.signals_in(act, &expr.item, &expr.pos, signals),
+            Expression::Qualified(ref qualified) => {
+                self.signals_in(act, &qualified.expr.item, &qualified.expr.pos, signals)
+            }
+            Expression::Aggregate(ref associations) => {
+                for association in associations.iter() {
+                    let expr = match association {
+                        ElementAssociation::Positional(ref expr)
+                        | ElementAssociation::Named(_, ref expr) => expr,
+                    };
+                    self.signals_in(act, &expr.item, &expr.pos, signals)?;
+                }
+                Ok(())
+            }
+            Expression::Name(ref name) => self.signals_in_name(act, name, pos, signals),
+            Expression::Literal(..) | Expression::New(..) => Ok(()),
+        }
+    }
+
+    fn signals_in_name(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+        pos: &SrcPos,
+        signals: &mut Vec<usize>,
+    ) -> Result<(), Message> {
+        match name {
+            Name::Simple(..) | Name::Selected(..) => {
+                if let Some(Object::Signal(signal)) = self.object(act, name, pos)? {
+                    if !signals.contains(&signal) {
+                        signals.push(signal);
+                    }
+                }
+                Ok(())
+            }
+            Name::Indexed(ref prefix, ref indexes) => {
+                self.signals_in_name(act, &prefix.item, &prefix.pos, signals)?;
+                for index in indexes.iter() {
+                    self.signals_in(act, &index.item, &index.pos, signals)?;
+                }
+                Ok(())
+            }
+            Name::Slice(ref prefix, _) => {
+                self.signals_in_name(act, &prefix.item, &prefix.pos, signals)
+            }
+            Name::Attribute(ref attr) => {
+                self.signals_in_name(act, &attr.name.item, &attr.name.pos, signals)?;
+                if let Some(ref expr) = attr.expr {
+                    self.signals_in(act, &expr.item, &expr.pos, signals)?;
+                }
+                Ok(())
+            }
+            Name::FunctionCall(ref call) => {
+                self.signals_in_name(act, &call.name.item, &call.name.pos, signals)?;
+                for parameter in call.parameters.iter() {
+                    if let ActualPart::Expression(ref expr) = parameter.actual.item {
+                        self.signals_in(act, expr, &parameter.actual.pos, signals)?;
+                    }
+                }
+                Ok(())
+            }
+            Name::CharacterLiteral(..) | Name::OperatorSymbol(..) | Name::All => Ok(()),
+        }
+    }
+
+    /// The variable or signal of the target with the assigned part and its type
+    fn place(
+        &mut self,
+        act: &mut Activation<'a>,
+        name: &'a Name,
+        pos: &SrcPos,
+    ) -> Result<(Place, Part, Type), Message> {
+        let (prefix, index) = match name {
+            Name::Simple(..) | Name::Selected(..) => {
+                return match self.object(act, name, pos)? {
+                    Some(Object::Variable(variable)) => {
+                        let typ = act.variables[&variable].0.clone();
+                        Ok((Place::Variable(variable), None, typ))
+                    }
+                    Some(Object::Signal(signal)) => {
+                        let typ = self.signals[signal].typ.clone();
+                        Ok((Place::Signal(signal), None, typ))
+                    }
+                    _ => Err(error(pos, "Expected a signal or variable")),
+                };
+            }
+            Name::FunctionCall(ref call) => match call.parameters.as_slice() {
+                [AssociationElement {
+                    formal: None,
+                    actual,
+                }] => (&call.name, Some(actual)),
+                _ => return Err(error(pos, "Only one dimensional arrays are supported")),
+            },
+            Name::Slice(ref prefix, _) => (prefix.as_ref(), None),
+            _ => return Err(error(pos, "Expected a signal or variable")),
+        };
+
+        let (place, part, typ) = self.place(act, &prefix.item, &prefix.pos)?;
+        if part.is_some() {
+            return Err(error(
+                pos,
+                "Only the elements and slices of arrays are supported",
+            ));
+        }
+        let bounds = match place {
+            Place::Variable(ref variable) => act.variables[variable].1.bounds(),
+            Place::Signal(signal) => self.signals[signal].value.bounds(),
+        };
+        let bounds = bounds.ok_or_else(|| error(pos, "Expected an array"))?;
+        let element = element_type(&typ).ok_or_else(|| error(pos, "Expected an array"))?;
+        match (index, name) {
+            (Some(index), _) => {
+                let index = self.actual(act, index, None)?;
+                let index = integer(index, pos)?;
+                let offset = bounds
+                    .offset(index)
+                    .ok_or_else(|| error(pos, &format!("Index {} is out of the range", index)))?;
+                Ok((place, Some((offset, 1)), element))
+            }
+            (None, Name::Slice(_, ref range)) => {
+                let range = self.discrete_range(act, range, pos)?;
+                if range.is_empty() {
+                    return Ok((place, Some((0, 0)), slice_type(&typ, range)));
+                }
+                match (bounds.offset(range.left), bounds.offset(range.right)) {
+                    (Some(left), Some(right)) if range.ascending == bounds.ascending => Ok((
+                        place,
+                        Some((left, right - left + 1)),
+                        slice_type(&typ, range),
+                    )),
+                    _ => Err(error(pos, "Slice is out of the range")),
+                }
+            }
+            _ => Err(error(pos, "Expected a signal or variable")),
+        }
+    }
+
+    fn assign_variable(
+        &mut self,
+        act: &mut Activation<'a>,
+        target: &'a WithPos<Target>,
+        expr: &'a WithPos<Expression>,
+    ) -> Result<(), Message> {
+        let name = match target.item {
+            Target::Name(ref name) => name,
+            Target::Aggregate(..) => {
+                return Err(error(target, "Aggregate targets are not supported"))
+            }
+        };
+        let (place, part, typ) = self.place(act, name, &target.pos)?;
+        let variable = match place {
+            Place::Variable(variable) => variable,
+            Place::Signal(..) => return Err(error(target, "Expected a variable")),
+        };
+        let value = self.eval(act, expr, Some(&typ))?;
+        let value = convert(value, &typ).map_err(|msg| error(expr, &msg))?;
+        if let Some((_, ref mut target)) = act.variables.get_mut(&variable) {
+            set_part(target, part, value);
+        }
+        Ok(())
+    }
+
+    fn assign_signal(
+        &mut self,
+        act: &mut Activation<'a>,
+        target: &'a WithPos<Target>,
+        delay_mechanism: &'a Option<DelayMechanism>,
+        rhs: &'a AssignmentRightHand<Waveform>,
+    ) -> Result<(), Message> {
+        let elements = match self.select(act, rhs)? {
+            Some(Waveform::Elements(ref elements)) => elements,
+            Some(Waveform::Unaffected) | None => return Ok(()),
+        };
+        let name = match target.item {
+            Target::Name(ref name) => name,
+            Target::Aggregate(..) => {
+                return Err(error(target, "Aggregate targets are not supported"))
+            }
+        };
+        let (place, part, typ) = self.place(act, name, &target.pos)?;
+        let signal = match place {
+            Place::Signal(signal) => signal,
+            Place::Variable(..) => return Err(error(target, "Expected a signal")),
+        };
+        let transport = matches!(delay_mechanism, Some(DelayMechanism::Transport));
+        for (i, element) in elements.iter().enumerate() {
+            let value = self.eval(act, &element.value, Some(&typ))?;
+            let value = convert(value, &typ).map_err(|msg| error(&element.value, &msg))?;
+            let delay = match element.after {
+                Some(ref after) => match self.eval(act, after, None)? {
+                    Value::Time(time) if time >= 0 => time,
+                    value => {
+                        return Err(error(
+                            after,
+                            &format!("Expected a non-negative time, got {}", value.image()),
+                        ))
+                    }
+                },
+                None => 0,
+            };
+            let transaction = Transaction {
+                time: self.now.saturating_add(delay),
+                part,
+                value,
+            };
+            self.schedule(
+                act.process,
+                signal,
+                transaction,
+                i == 0 && !transport,
+                &target.pos,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// LRM 10.5.2.2 Adds the transaction to the driver of the process, where an inertial
+    /// transaction replaces all pending transactions of the part and a transport transaction
+    /// replaces those at the same time or later
+    fn schedule(
+        &mut self,
+        process: usize,
+        signal: usize,
+        transaction: Transaction,
+        inertial: bool,
+        pos: &SrcPos,
+    ) -> Result<(), Message> {
+        if process == usize::MAX {
+            return Err(error(pos, "Signals can only be assigned by processes"));
+        }
+        let signal = &mut self.signals[signal];
+        let driver = match signal
+            .drivers
+            .iter()
+            .position(|driver| driver.process == process)
+        {
+            Some(driver) => driver,
+            None => {
+                if !signal.drivers.is_empty() && !signal.is_resolved() {
+                    return Err(error(
+                        pos,
+                        &format!(
+                            "Signal '{}' of the unresolved type {} has several drivers",
+                            signal.path,
+                            signal.typ.name()
+                        ),
+                    ));
+                }
+                signal.drivers.push(Driver {
+                    process,
+                    value: signal.initial.clone(),
+                    transactions: VecDeque::new(),
+                });
+                signal.drivers.len() - 1
+            }
+        };
+        let transactions = &mut signal.drivers[driver].transactions;
+        transactions.retain(|pending| {
+            pending.part != transaction.part || (!inertial && pending.time < transaction.time)
+        });
+        let position = transactions
+            .iter()
+            .position(|pending| pending.time > transaction.time)
+            .unwrap_or(transactions.len());
+        transactions.insert(position, transaction);
+        Ok(())
+    }
+}
+
+fn slice_type(typ: &Type, bounds: Bounds) -> Type {
+    match typ {
+        Type::Vector(kind, _) => Type::Vector(*kind, Some(bounds)),
+        _ => Type::String(Some(bounds)),
+    }
+}
+
+fn name_text(name: &Name) -> String {
+    match selected_name_parts(name) {
+        Some(parts) => {
+            let parts: Vec<String> = parts.iter().map(|part| part.name_utf8()).collect();
+            parts.join(".")
+        }
+        None => "name".to_owned(),
+    }
+}
+
+fn index_value(value: Value, index: i64, pos: &SrcPos) -> Result<Value, Message> {
+    let bounds = value
+        .bounds()
+        .ok_or_else(|| error(pos, &format!("Cannot index {}", value.image())))?;
+    let offset = bounds
+        .offset(index)
+        .ok_or_else(|| error(pos, &format!("Index {} is out of the range", index)))?;
+    match value.elements() {
+        Some(elements) => Ok(Value::Character(elements[offset])),
+        None => Err(error(pos, "Expected an array")),
+    }
+}
+
+fn slice_value(value: Value, range: Bounds, pos: &SrcPos) -> Result<Value, Message> {
+    let bounds = value
+        .bounds()
+        .ok_or_else(|| error(pos, "Expected an array"))?;
+    if range.is_empty() {
+        return Ok(value.with_elements(Vec::new()));
+    }
+    let elements = match (bounds.offset(range.left), bounds.offset(range.right)) {
+        (Some(left), Some(right)) if range.ascending == bounds.ascending => value
+            .elements()
+            .map(|elements| elements[left..=right].to_vec()),
+        _ => None,
+    };
+    let elements = elements.ok_or_else(|| error(pos, "Slice is out of the range"))?;
+    Ok(match value {
+        Value::Vector(kind, _, _) => Value::Vector(kind, range, elements),
+        _ => Value::String(elements),
+    })
+}
+
+/// The hexadecimal digits of a vector like to_hstring where a metavalue gives X
+fn hex_string(value: &Value) -> Option<String> {
+    let elements = match value {
+        Value::Vector(_, _, ref elements) => elements,
+        _ => return None,
+    };
+    let padding = (4 - elements.len() % 4) % 4;
+    let mut padded = integer_elements(0, padding);
+    padded.extend_from_slice(elements);
+    Some(
+        padded
+            .chunks(4)
+            .map(|digit| match vector_integer(VectorKind::Unsigned, digit) {
+                Some(value) => format!("{:X}", value),
+                None => "X".to_owned(),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use std::fs;
+    use test_util::TempProject;
+
+    /// Runs the testbench of the code with the output of the reports, where an error is
+    /// given as its line, column and message since the file is removed afterwards
+    fn simulate(code: &str, stop_time: Option<Time>) -> (Result<Summary, String>, String) {
+        let mut project = TempProject::new();
+        project.add_file("lib", "tb.vhd", code);
+        let files = project.project.files();
+        let mut simulation = Simulation::new(&files);
+        let mut output = Vec::new();
+        let result = simulation
+            .elaborate(&UnitId::primary("lib", "tb"))
+            .and_then(|found| {
+                assert!(found);
+                simulation.run(stop_time, &mut output)
+            })
+            .map_err(|message| {
+                let (line, column) = message.pos.line_and_column();
+                format!("{}:{}: {}", line, column, message.message)
+            });
+        // Report positions are prefixed by the temporary directory
+        let output = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| match line.find("tb.vhd:") {
+                Some(start) => &line[start..],
+                None => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        (result, output)
+    }
+
+    fn summary(time: Time, finish: Finish, errors: usize) -> Result<Summary, String> {
+        Ok(Summary {
+            time,
+            finish,
+            errors,
+        })
+    }
+
+    const NS: Time = 1_000_000;
+
+    #[test]
+    fn clocked_counter_with_reports() {
+        let (result, output) = simulate(
+            "\
+library ieee;
+use ieee.std_logic_1164.all;
+use ieee.numeric_std.all;
+
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal clk : std_logic := '0';
+  signal count : unsigned(3 downto 0) := (others => '0');
+begin
+  clk <= not clk after 5 ns;
+
+  counter : process (clk)
+  begin
+    if rising_edge(clk) then
+      count <= count + 1;
+    end if;
+  end process;
+
+  main : process
+  begin
+    wait until count = 3;
+    report \"count is \" & integer'image(to_integer(count)) & \" at \" & time'image(now);
+    std.env.stop;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            output,
+            "tb.vhd:24:12:@25ns:(report note): count is 3 at 25 ns"
+        );
+        assert_eq!(result, summary(25 * NS, Finish::Stop, 0));
+    }
+
+    #[test]
+    fn delta_cycles_and_waits() {
+        let (result, output) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal a, b : integer := 0;
+begin
+  b <= a + 1;
+
+  process
+  begin
+    a <= 1;
+    assert a = 0 report \"not yet assigned\";
+    wait for 0 ns;
+    assert a = 1 and b = 1 report \"b follows after a delta cycle\";
+    wait for 0 ns;
+    assert b = 2 report \"b is \" & integer'image(b);
+    wait for 10 ns;
+    a <= 5, 7 after 1 ns;
+    wait on b;
+    report \"b is \" & integer'image(b) & \" at \" & time'image(now);
+    wait on b;
+    report \"b is \" & integer'image(b) & \" at \" & time'image(now);
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            output,
+            "tb.vhd:20:12:@10ns:(report note): b is 6 at 10 ns\n\
+             tb.vhd:22:12:@11ns:(report note): b is 8 at 11 ns"
+        );
+        assert_eq!(result, summary(11 * NS, Finish::Quiet, 0));
+    }
+
+    #[test]
+    fn assertion_failure_stops_simulation() {
+        let (result, output) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+begin
+  process
+  begin
+    wait for 1 ns;
+    assert false report \"bad value\";
+    assert 1 = 2;
+    wait for 1 ns;
+    assert false report \"fatal\" severity failure;
+    report \"unreachable\";
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            output,
+            "tb.vhd:9:12:@1ns:(assertion error): bad value\n\
+             tb.vhd:10:12:@1ns:(assertion error): Assertion violation.\n\
+             tb.vhd:12:12:@2ns:(assertion failure): fatal"
+        );
+        assert_eq!(result, summary(2 * NS, Finish::Failure, 3));
+    }
+
+    #[test]
+    fn stop_time() {
+        let (result, output) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal clk : bit;
+begin
+  clk <= not clk after 5 ns;
+end architecture;
+",
+            Some(100 * NS),
+        );
+        assert_eq!(output, "");
+        assert_eq!(result, summary(100 * NS, Finish::StopTime, 0));
+    }
+
+    #[test]
+    fn instances_with_generics_and_ports() {
+        let (result, output) = simulate(
+            "\
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity adder is
+  generic (width : natural; offset : integer := 1);
+  port (a : in integer; y : out integer);
+end entity;
+
+architecture rtl of adder is
+begin
+  y <= a + offset * width;
+end architecture;
+
+entity tb is
+end entity;
+
+architecture sim of tb is
+  component adder is
+    generic (width : natural; offset : integer := 1);
+    port (a : in integer; y : out integer);
+  end component;
+  signal y0, y1 : integer;
+  signal x : integer := 3;
+begin
+  dut0 : entity work.adder generic map (width => 2) port map (a => x, y => y0);
+  dut1 : adder generic map (10, 2) port map (7, y1);
+
+  gen : for i in 0 to 1 generate
+    check : assert y0 = 0 or i = i;
+  end generate;
+
+  process
+  begin
+    wait for 1 ns;
+    report integer'image(y0) & \" \" & integer'image(y1);
+    x <= 10;
+    wait for 1 ns;
+    report integer'image(y0);
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            output,
+            "tb.vhd:35:12:@1ns:(report note): 5 27\n\
+             tb.vhd:38:12:@2ns:(report note): 12"
+        );
+        assert_eq!(result, summary(2 * NS, Finish::Quiet, 0));
+    }
+
+    #[test]
+    fn resolved_and_unresolved_signals() {
+        let (result, output) = simulate(
+            "\
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal bus_line : std_logic_vector(1 downto 0);
+begin
+  bus_line <= \"Z1\";
+  bus_line <= \"0Z\";
+
+  process
+  begin
+    wait for 1 ns;
+    report to_string(bus_line);
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(output, "tb.vhd:16:12:@1ns:(report note): 01");
+        assert_eq!(result, summary(NS, Finish::Quiet, 0));
+
+        let (result, _) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal value : integer;
+begin
+  value <= 1;
+  value <= 2;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            "8:3: Signal 'tb.value' of the unresolved type integer has several drivers"
+        );
+    }
+
+    #[test]
+    fn subprograms_and_textio() {
+        let (result, output) = simulate(
+            "\
+library ieee;
+use ieee.std_logic_1164.all;
+use std.textio.all;
+
+package util is
+  type state_t is (idle, busy, done);
+  constant width : natural := 8;
+  function double(value : integer) return integer;
+end package;
+
+package body util is
+  function double(value : integer) return integer is
+  begin
+    return 2 * value;
+  end function;
+end package body;
+
+use work.util.all;
+
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal clk : std_logic := '0';
+  signal state : state_t := idle;
+
+  procedure pulse(signal s : out std_logic; count : natural; total : out natural) is
+    variable sum : natural := 0;
+  begin
+    for i in 1 to count loop
+      s <= '1';
+      wait for 1 ns;
+      s <= '0';
+      wait for 1 ns;
+      sum := sum + i;
+    end loop;
+    total := sum;
+  end procedure;
+begin
+  process
+    variable l : line;
+    variable total : natural;
+    variable v : std_logic_vector(width - 1 downto 0) := x\"A5\";
+  begin
+    pulse(clk, 3, total);
+    write(l, string'(\"total=\"));
+    write(l, total);
+    write(l, string'(\" double=\"));
+    write(l, double(total), right, 4);
+    writeline(output, l);
+    v(0) := '0';
+    v(7 downto 4) := \"0011\";
+    write(l, to_hstring(v) & \" \" & state_t'image(state) & \" \" & time'image(now));
+    writeline(output, l);
+    state <= done;
+    wait for 0 ns;
+    assert state = done and state_t'pos(state) = 2;
+    case state is
+      when idle | busy => report \"wrong\";
+      when others => report \"done\";
+    end case;
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            output,
+            "total=6 double=  12\n\
+             34 idle 6 ns\n\
+             tb.vhd:60:29:@6ns:(report note): done"
+        );
+        assert_eq!(result, summary(6 * NS, Finish::Quiet, 0));
+    }
+
+    #[test]
+    fn loops_with_next_and_exit() {
+        let (result, output) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+begin
+  process
+    variable sum : integer := 0;
+    variable i : integer := 0;
+  begin
+    outer : for j in 3 downto 0 loop
+      for k in 0 to 10 loop
+        next outer when k = j;
+        sum := sum + 1;
+      end loop;
+    end loop;
+    while true loop
+      i := i + 1;
+      exit when i = 5;
+    end loop;
+    report integer'image(sum) & \" \" & integer'image(i);
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(output, "tb.vhd:20:12:@0fs:(report note): 6 5");
+        assert_eq!(result, summary(0, Finish::Quiet, 0));
+    }
+
+    #[test]
+    fn oscillation_exceeds_delta_cycles() {
+        let (result, _) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal a : bit;
+begin
+  a <= not a;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            "7:3: Exceeded 10000 delta cycles at 0 fs"
+        );
+    }
+
+    #[test]
+    fn runtime_errors_have_positions() {
+        let (result, _) = simulate(
+            "\
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal n : natural;
+begin
+  process
+  begin
+    n <= -1;
+    wait;
+  end process;
+end architecture;
+",
+            None,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            "9:10: Value -1 is out of the range 0 to 2147483647"
+        );
+    }
+
+    #[test]
+    fn dumps_vcd_of_ports_and_signals() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "tb.vhd",
+            "\
+entity inverter is
+  port (a : in bit; y : out bit);
+end entity;
+
+architecture rtl of inverter is
+begin
+  y <= not a after 1 ns;
+end architecture;
+
+entity tb is
+end entity;
+
+architecture sim of tb is
+  signal a, y : bit;
+begin
+  dut : entity work.inverter port map (a => a, y => y);
+  a <= '1' after 5 ns;
+end architecture;
+",
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let files = project.project.files();
+        let mut simulation = Simulation::new(&files);
+        assert_eq!(simulation.elaborate(&UnitId::primary("lib", "tb")), Ok(true));
+        simulation.dump_vcd(Box::new(file.reopen().unwrap()));
+        let mut output = Vec::new();
+        assert_eq!(
+            simulation.run(None, &mut output),
+            Ok(Summary {
+                time: 6 * NS,
+                finish: Finish::Quiet,
+                errors: 0,
+            })
+        );
+        simulation.finish_vcd().unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "\
+$version rust_hdl $end
+$timescale 1 fs $end
+$scope module tb $end
+$var wire 1 ! a $end
+$var wire 1 \" y $end
+$scope module dut $end
+$var wire 1 ! a $end
+$var wire 1 \" y $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+0!
+0\"
+$end
+#1000000
+1\"
+#5000000
+1!
+#6000000
+0\"
+"
+        );
+    }
+}
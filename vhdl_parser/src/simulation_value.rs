@@ -0,0 +1,1221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The types, values and predefined operators of the interpreter
+//!
+//! Values are typed by their variant rather than by the declared type of the object
+//! holding them, a value is converted to the declared type when it is assigned. Bit,
+//! std_ulogic and character values are all characters and the arrays of bits and
+//! std_ulogic are vectors whose kind selects the arithmetic of numeric_std
+
+use ast::{BaseSpecifier, Binary, BitString, Unary};
+use static_value::integer_binary;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Time in femtoseconds
+pub type Time = i64;
+
+/// The units of time with their number of femtoseconds
+const TIME_UNITS: [(&str, Time); 8] = [
+    ("fs", 1),
+    ("ps", 1_000),
+    ("ns", 1_000_000),
+    ("us", 1_000_000_000),
+    ("ms", 1_000_000_000_000),
+    ("sec", 1_000_000_000_000_000),
+    ("min", 60_000_000_000_000_000),
+    ("hr", 3_600_000_000_000_000_000),
+];
+
+/// The number of femtoseconds of a unit of time such as ns
+pub fn time_unit(name: &str) -> Option<Time> {
+    TIME_UNITS
+        .iter()
+        .find(|(unit, _)| unit.eq_ignore_ascii_case(name))
+        .map(|(_, fs)| *fs)
+}
+
+/// The time in the largest unit which divides it such as 10 ns
+pub fn time_image(time: Time) -> String {
+    let (unit, fs) = TIME_UNITS
+        .iter()
+        .rev()
+        .find(|(_, fs)| time != 0 && time % fs == 0)
+        .unwrap_or(&TIME_UNITS[0]);
+    format!("{} {}", time / fs, unit)
+}
+
+/// A time such as 10 ns, 10ns or 1.5 us
+pub fn parse_time(text: &str) -> Option<Time> {
+    let text = text.trim();
+    let split = text
+        .find(|chr: char| chr.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let unit = time_unit(text[split..].trim())?;
+    let value = text[..split].trim();
+    match value.parse::<i64>() {
+        Ok(value) => value.checked_mul(unit),
+        Err(_) => value
+            .parse::<f64>()
+            .ok()
+            .map(|value| (value * unit as f64).round() as Time),
+    }
+}
+
+/// An enumeration type declared within the design
+#[derive(PartialEq, Eq, Debug)]
+pub struct EnumType {
+    pub name: String,
+    /// The lower case identifiers of the literals in order
+    pub literals: Vec<String>,
+}
+
+/// The arrays of bits and std_ulogic
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum VectorKind {
+    Bit,
+    Logic,
+    Unsigned,
+    Signed,
+}
+
+impl VectorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            VectorKind::Bit => "bit_vector",
+            VectorKind::Logic => "std_logic_vector",
+            VectorKind::Unsigned => "unsigned",
+            VectorKind::Signed => "signed",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        self == VectorKind::Unsigned || self == VectorKind::Signed
+    }
+
+    /// The value of elements which are not initialized
+    fn default_element(self) -> u8 {
+        match self {
+            VectorKind::Bit => b'0',
+            _ => b'U',
+        }
+    }
+}
+
+/// The index range of an array
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Bounds {
+    pub left: i64,
+    pub right: i64,
+    pub ascending: bool,
+}
+
+impl Bounds {
+    /// The range length - 1 downto 0
+    pub fn descending(length: usize) -> Bounds {
+        Bounds {
+            left: length as i64 - 1,
+            right: 0,
+            ascending: false,
+        }
+    }
+
+    /// The range left to left + length - 1
+    pub fn ascending_from(left: i64, length: usize) -> Bounds {
+        Bounds {
+            left,
+            right: left + length as i64 - 1,
+            ascending: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        let length = if self.ascending {
+            self.right - self.left + 1
+        } else {
+            self.left - self.right + 1
+        };
+        length.max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn low(&self) -> i64 {
+        self.left.min(self.right)
+    }
+
+    pub fn high(&self) -> i64 {
+        self.left.max(self.right)
+    }
+
+    /// The position of the element of the index counted from the left
+    pub fn offset(&self, index: i64) -> Option<usize> {
+        if index < self.low() || index > self.high() {
+            return None;
+        }
+        Some(if self.ascending {
+            (index - self.left) as usize
+        } else {
+            (self.left - index) as usize
+        })
+    }
+
+    /// The index of the element at the position counted from the left
+    pub fn index(&self, offset: usize) -> i64 {
+        if self.ascending {
+            self.left + offset as i64
+        } else {
+            self.left - offset as i64
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Type {
+    Boolean,
+    Bit,
+    /// std_ulogic and std_logic
+    Logic,
+    Character,
+    /// The low and high bounds
+    Integer(i64, i64),
+    Time,
+    /// Strings and the lines of std.textio
+    String(Option<Bounds>),
+    Enum(Arc<EnumType>),
+    /// Without bounds when unconstrained
+    Vector(VectorKind, Option<Bounds>),
+}
+
+pub const INTEGER: Type = Type::Integer(i32::MIN as i64, i32::MAX as i64);
+
+impl Type {
+    /// The left value of the type, None when the type is unconstrained
+    pub fn default_value(&self) -> Option<Value> {
+        Some(match self {
+            Type::Boolean => Value::Boolean(false),
+            Type::Bit => Value::Character(b'0'),
+            Type::Logic => Value::Character(b'U'),
+            Type::Character => Value::Character(0),
+            Type::Integer(low, _) => Value::Integer(*low),
+            Type::Time => Value::Time(0),
+            Type::String(bounds) => Value::String(vec![b' '; bounds.as_ref()?.len()]),
+            Type::Enum(ref typ) => Value::Enum(0, typ.clone()),
+            Type::Vector(kind, bounds) => {
+                let bounds = (*bounds)?;
+                Value::Vector(*kind, bounds, vec![kind.default_element(); bounds.len()])
+            }
+        })
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Type::Boolean => "boolean".to_owned(),
+            Type::Bit => "bit".to_owned(),
+            Type::Logic => "std_ulogic".to_owned(),
+            Type::Character => "character".to_owned(),
+            Type::Integer(..) => "integer".to_owned(),
+            Type::Time => "time".to_owned(),
+            Type::String(..) => "string".to_owned(),
+            Type::Enum(ref typ) => typ.name.clone(),
+            Type::Vector(kind, _) => kind.name().to_owned(),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Boolean(bool),
+    Integer(i64),
+    /// A bit, std_ulogic or character such as b'1' or b'Z'
+    Character(u8),
+    Time(Time),
+    /// The position of the literal within its type
+    Enum(usize, Arc<EnumType>),
+    String(Vec<u8>),
+    Vector(VectorKind, Bounds, Vec<u8>),
+}
+
+impl Value {
+    /// The text of the value as written by report and the write procedure of std.textio
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::Character(chr) => (*chr as char).to_string(),
+            Value::String(ref string) | Value::Vector(_, _, ref string) => {
+                string.iter().map(|chr| *chr as char).collect()
+            }
+            _ => self.image(),
+        }
+    }
+
+    /// The value of the image attribute
+    pub fn image(&self) -> String {
+        match self {
+            Value::Boolean(value) => value.to_string(),
+            Value::Integer(value) => value.to_string(),
+            Value::Character(chr) => format!("'{}'", *chr as char),
+            Value::Time(time) => time_image(*time),
+            Value::Enum(pos, ref typ) => typ.literals[*pos].clone(),
+            Value::String(..) | Value::Vector(..) => format!("\"{}\"", self.to_text()),
+        }
+    }
+
+    /// The elements of an array value
+    pub fn elements(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(ref elements) | Value::Vector(_, _, ref elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The index range of an array value
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Value::String(ref string) => Some(Bounds::ascending_from(1, string.len())),
+            Value::Vector(_, bounds, _) => Some(*bounds),
+            _ => None,
+        }
+    }
+
+    /// The array with the elements, of the same kind and direction
+    pub fn with_elements(&self, elements: Vec<u8>) -> Value {
+        match self {
+            Value::Vector(kind, bounds, _) => {
+                let bounds = Bounds {
+                    left: bounds.left,
+                    right: if bounds.ascending {
+                        bounds.left + elements.len() as i64 - 1
+                    } else {
+                        bounds.left - elements.len() as i64 + 1
+                    },
+                    ascending: bounds.ascending,
+                };
+                Value::Vector(*kind, bounds, elements)
+            }
+            _ => Value::String(elements),
+        }
+    }
+}
+
+fn is_logic(chr: u8) -> bool {
+    b"UX01ZWLH-".contains(&chr)
+}
+
+fn is_bit(chr: u8) -> bool {
+    chr == b'0' || chr == b'1'
+}
+
+fn valid_element(kind: VectorKind, chr: u8) -> bool {
+    match kind {
+        VectorKind::Bit => is_bit(chr),
+        _ => is_logic(chr),
+    }
+}
+
+/// Converts the value to the type when it is a value of the type, where strings are
+/// converted to vectors and vectors to the bounds of the type
+pub fn convert(value: Value, typ: &Type) -> Result<Value, String> {
+    let mismatch = |value: &Value| format!("Expected {} value, got {}", typ.name(), value.image());
+    let length_mismatch = |expected: usize, got: usize| {
+        format!(
+            "Expected {} value of length {}, got length {}",
+            typ.name(),
+            expected,
+            got
+        )
+    };
+    match (typ, value) {
+        (Type::Boolean, value @ Value::Boolean(..)) => Ok(value),
+        (Type::Bit, Value::Character(chr)) if is_bit(chr) => Ok(Value::Character(chr)),
+        (Type::Logic, Value::Character(chr)) if is_logic(chr) => Ok(Value::Character(chr)),
+        (Type::Character, value @ Value::Character(..)) => Ok(value),
+        (Type::Integer(low, high), Value::Integer(value)) => {
+            if value < *low || value > *high {
+                Err(format!(
+                    "Value {} is out of the range {} to {}",
+                    value, low, high
+                ))
+            } else {
+                Ok(Value::Integer(value))
+            }
+        }
+        (Type::Time, value @ Value::Time(..)) => Ok(value),
+        (Type::Enum(ref typ), Value::Enum(pos, value_typ)) if *typ == value_typ => {
+            Ok(Value::Enum(pos, value_typ))
+        }
+        (Type::String(bounds), value) => {
+            let string = match value {
+                Value::String(string) => string,
+                Value::Character(chr) => vec![chr],
+                value => return Err(mismatch(&value)),
+            };
+            match bounds {
+                Some(bounds) if bounds.len() != string.len() => {
+                    Err(length_mismatch(bounds.len(), string.len()))
+                }
+                _ => Ok(Value::String(string)),
+            }
+        }
+        (Type::Vector(kind, bounds), value) => {
+            let (value_bounds, elements) = match value {
+                Value::Vector(_, bounds, elements) => (bounds, elements),
+                Value::String(elements) => (Bounds::ascending_from(0, elements.len()), elements),
+                value => return Err(mismatch(&value)),
+            };
+            if let Some(chr) = elements.iter().find(|chr| !valid_element(*kind, **chr)) {
+                return Err(format!(
+                    "Expected {} value, got element '{}'",
+                    typ.name(),
+                    *chr as char
+                ));
+            }
+            match bounds {
+                Some(bounds) if bounds.len() != elements.len() => {
+                    Err(length_mismatch(bounds.len(), elements.len()))
+                }
+                Some(bounds) => Ok(Value::Vector(*kind, *bounds, elements)),
+                None => Ok(Value::Vector(*kind, value_bounds, elements)),
+            }
+        }
+        (_, value) => Err(mismatch(&value)),
+    }
+}
+
+/// The elements of a bit string literal, digits other than those of the base such
+/// as Z or X are repeated for each bit of the digit
+pub fn bit_string(bit_string: &BitString) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = bit_string
+        .value
+        .bytes
+        .iter()
+        .cloned()
+        .filter(|byte| *byte != b'_')
+        .collect();
+    let (bits, signed) = match bit_string.base {
+        BaseSpecifier::B | BaseSpecifier::UB => (1, false),
+        BaseSpecifier::O | BaseSpecifier::UO => (3, false),
+        BaseSpecifier::X | BaseSpecifier::UX => (4, false),
+        BaseSpecifier::SB => (1, true),
+        BaseSpecifier::SO => (3, true),
+        BaseSpecifier::SX => (4, true),
+        BaseSpecifier::D => (0, false),
+    };
+
+    let mut elements = Vec::new();
+    if bits == 0 {
+        let mut value: u128 = 0;
+        for digit in digits.iter() {
+            let digit = (*digit as char)
+                .to_digit(10)
+                .ok_or_else(|| format!("Invalid decimal digit '{}'", *digit as char))?;
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u128::from(digit)))
+                .ok_or_else(|| "Decimal bit string literal is too large".to_owned())?;
+        }
+        let width = 128 - value.leading_zeros() as usize;
+        elements = (0..width)
+            .rev()
+            .map(|bit| if value >> bit & 1 == 1 { b'1' } else { b'0' })
+            .collect();
+    } else {
+        for digit in digits.iter() {
+            match (*digit as char).to_digit(1 << bits) {
+                Some(value) => {
+                    for bit in (0..bits).rev() {
+                        elements.push(if value >> bit & 1 == 1 { b'1' } else { b'0' });
+                    }
+                }
+                None => {
+                    let chr = digit.to_ascii_uppercase();
+                    if !is_logic(chr) {
+                        return Err(format!("Invalid digit '{}'", *digit as char));
+                    }
+                    elements.extend(std::iter::repeat_n(chr, bits));
+                }
+            }
+        }
+    }
+
+    if let Some(length) = bit_string.length {
+        let length = length as usize;
+        if length > elements.len() {
+            let fill = if signed {
+                elements.first().cloned().unwrap_or(b'0')
+            } else {
+                b'0'
+            };
+            let mut extended = vec![fill; length - elements.len()];
+            extended.extend(elements);
+            elements = extended;
+        } else {
+            let removed = elements.len() - length;
+            if elements[..removed].iter().any(|chr| *chr != b'0') {
+                return Err(format!(
+                    "Bit string literal does not fit within {} bits",
+                    length
+                ));
+            }
+            elements.drain(..removed);
+        }
+    }
+    Ok(elements)
+}
+
+/// LRM 16.7 std_ulogic values which are 0 or 1 when they are not a metavalue
+fn to_x01(chr: u8) -> u8 {
+    match chr {
+        b'0' | b'L' => b'0',
+        b'1' | b'H' => b'1',
+        b'U' => b'U',
+        _ => b'X',
+    }
+}
+
+fn logic_not(chr: u8) -> u8 {
+    match to_x01(chr) {
+        b'0' => b'1',
+        b'1' => b'0',
+        other => other,
+    }
+}
+
+fn logic_and(left: u8, right: u8) -> u8 {
+    match (to_x01(left), to_x01(right)) {
+        (b'0', _) | (_, b'0') => b'0',
+        (b'U', _) | (_, b'U') => b'U',
+        (b'1', b'1') => b'1',
+        _ => b'X',
+    }
+}
+
+fn logic_or(left: u8, right: u8) -> u8 {
+    match (to_x01(left), to_x01(right)) {
+        (b'1', _) | (_, b'1') => b'1',
+        (b'U', _) | (_, b'U') => b'U',
+        (b'0', b'0') => b'0',
+        _ => b'X',
+    }
+}
+
+fn logic_xor(left: u8, right: u8) -> u8 {
+    match (to_x01(left), to_x01(right)) {
+        (b'U', _) | (_, b'U') => b'U',
+        (b'X', _) | (_, b'X') => b'X',
+        (left, right) => {
+            if left == right {
+                b'0'
+            } else {
+                b'1'
+            }
+        }
+    }
+}
+
+fn logical(op: Binary, left: u8, right: u8) -> Option<u8> {
+    Some(match op {
+        Binary::And => logic_and(left, right),
+        Binary::Or => logic_or(left, right),
+        Binary::Nand => logic_not(logic_and(left, right)),
+        Binary::Nor => logic_not(logic_or(left, right)),
+        Binary::Xor => logic_xor(left, right),
+        Binary::Xnor => logic_not(logic_xor(left, right)),
+        _ => return None,
+    })
+}
+
+const LOGIC_ORDER: &[u8] = b"UX01ZWLH-";
+
+/// The value of a std_logic signal with several drivers, LRM 16.7 resolved
+pub fn resolve_logic(left: u8, right: u8) -> u8 {
+    const TABLE: [&[u8; 9]; 9] = [
+        b"UUUUUUUUU",
+        b"UXXXXXXXX",
+        b"UX0X0000X",
+        b"UXX11111X",
+        b"UX01ZWLHX",
+        b"UX01WWWWX",
+        b"UX01LWLWX",
+        b"UX01HWWHX",
+        b"UXXXXXXXX",
+    ];
+    let position = |chr: u8| LOGIC_ORDER.iter().position(|other| *other == chr);
+    match (position(left), position(right)) {
+        (Some(left), Some(right)) => TABLE[left][right],
+        _ => b'X',
+    }
+}
+
+/// The integer value of a numeric vector, None when it contains a metavalue or
+/// does not fit within 64 bits
+pub fn vector_integer(kind: VectorKind, elements: &[u8]) -> Option<i64> {
+    if elements.len() > 64 {
+        return None;
+    }
+    let mut value: i128 = 0;
+    for chr in elements.iter() {
+        value = value << 1
+            | match to_x01(*chr) {
+                b'0' => 0,
+                b'1' => 1,
+                _ => return None,
+            };
+    }
+    if kind == VectorKind::Signed && !elements.is_empty() && to_x01(elements[0]) == b'1' {
+        value -= 1 << elements.len();
+    }
+    i64::try_from(value).ok()
+}
+
+/// The elements of the integer as a vector of the length, in two's complement
+pub fn integer_elements(value: i64, length: usize) -> Vec<u8> {
+    (0..length)
+        .rev()
+        .map(|bit| {
+            if bit < 64 && (value >> bit) & 1 == 1 || bit >= 64 && value < 0 {
+                b'1'
+            } else {
+                b'0'
+            }
+        })
+        .collect()
+}
+
+/// The numeric vector of the kind and length with the value
+pub fn numeric_vector(kind: VectorKind, value: i64, length: usize) -> Value {
+    Value::Vector(
+        kind,
+        Bounds::descending(length),
+        integer_elements(value, length),
+    )
+}
+
+/// numeric_std resize where signed vectors keep their sign
+pub fn resize(value: &Value, length: usize) -> Result<Value, String> {
+    match value {
+        Value::Vector(kind, _, ref elements) => {
+            let mut resized = if *kind == VectorKind::Signed && !elements.is_empty() {
+                vec![elements[0]; length.saturating_sub(elements.len())]
+            } else {
+                vec![b'0'; length.saturating_sub(elements.len())]
+            };
+            resized.extend_from_slice(&elements[elements.len().saturating_sub(length)..]);
+            if *kind == VectorKind::Signed && length > 0 && length < elements.len() {
+                resized[0] = elements[0];
+            }
+            Ok(Value::Vector(*kind, Bounds::descending(length), resized))
+        }
+        value => Err(format!("Cannot resize {}", value.image())),
+    }
+}
+
+fn type_error(op: &str, left: &Value, right: &Value) -> String {
+    format!(
+        "Operator {} is not defined for {} and {}",
+        op,
+        left.image(),
+        right.image()
+    )
+}
+
+pub fn unary(op: Unary, value: Value) -> Result<Value, String> {
+    let error = |value: &Value| format!("Operator {:?} is not defined for {}", op, value.image());
+    match (op, value) {
+        (Unary::Not, Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+        (Unary::Not, Value::Character(chr)) => Ok(Value::Character(logic_not(chr))),
+        (Unary::Not, Value::Vector(kind, bounds, elements)) => Ok(Value::Vector(
+            kind,
+            bounds,
+            elements.into_iter().map(logic_not).collect(),
+        )),
+        (Unary::Minus, Value::Integer(value)) => value
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or_else(|| "Integer overflow".to_owned()),
+        (Unary::Minus, Value::Time(value)) => Ok(Value::Time(-value)),
+        (Unary::Plus, value @ Value::Integer(..)) | (Unary::Plus, value @ Value::Time(..)) => {
+            Ok(value)
+        }
+        (Unary::Abs, Value::Integer(value)) => value
+            .checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| "Integer overflow".to_owned()),
+        (Unary::Abs, Value::Time(value)) => Ok(Value::Time(value.abs())),
+        (Unary::Minus, Value::Vector(VectorKind::Signed, bounds, elements)) => {
+            match vector_integer(VectorKind::Signed, &elements) {
+                Some(value) => Ok(numeric_vector(
+                    VectorKind::Signed,
+                    value.wrapping_neg(),
+                    bounds.len(),
+                )),
+                None => Ok(Value::Vector(
+                    VectorKind::Signed,
+                    bounds,
+                    vec![b'X'; elements.len()],
+                )),
+            }
+        }
+        // VHDL-2008 reduction operators
+        (op, Value::Vector(kind, bounds, elements)) => {
+            let binary = match op {
+                Unary::And => Binary::And,
+                Unary::Or => Binary::Or,
+                Unary::Nand => Binary::Nand,
+                Unary::Nor => Binary::Nor,
+                Unary::Xor => Binary::Xor,
+                Unary::Xnor => Binary::Xnor,
+                _ => return Err(error(&Value::Vector(kind, bounds, elements))),
+            };
+            let (reduce, invert) = match binary {
+                Binary::Nand => (Binary::And, true),
+                Binary::Nor => (Binary::Or, true),
+                Binary::Xnor => (Binary::Xor, true),
+                binary => (binary, false),
+            };
+            let identity = if reduce == Binary::And { b'1' } else { b'0' };
+            let result = elements
+                .iter()
+                .fold(identity, |acc, chr| logical(reduce, acc, *chr).unwrap());
+            Ok(Value::Character(if invert {
+                logic_not(result)
+            } else {
+                result
+            }))
+        }
+        (_, value) => Err(error(&value)),
+    }
+}
+
+/// The ordering of scalar and array values of the same type
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Boolean(left), Value::Boolean(right)) => Some(left.cmp(right)),
+        (Value::Integer(left), Value::Integer(right)) => Some(left.cmp(right)),
+        (Value::Time(left), Value::Time(right)) => Some(left.cmp(right)),
+        (Value::Character(left), Value::Character(right)) => {
+            let position = |chr: &u8| LOGIC_ORDER.iter().position(|other| other == chr);
+            match (position(left), position(right)) {
+                (Some(left), Some(right)) => Some(left.cmp(&right)),
+                _ => Some(left.cmp(right)),
+            }
+        }
+        (Value::Enum(left, _), Value::Enum(right, _)) => Some(left.cmp(right)),
+        (Value::Vector(kind, _, ref elements), other)
+        | (other, Value::Vector(kind, _, ref elements))
+            if kind.is_numeric() =>
+        {
+            let other = match other {
+                Value::Integer(value) => Some(*value),
+                Value::Vector(other_kind, _, ref other) => vector_integer(*other_kind, other),
+                Value::String(ref other) => vector_integer(*kind, other),
+                _ => return None,
+            };
+            let value = vector_integer(*kind, elements);
+            let (value, other) = (value?, other?);
+            if let Value::Vector(_, _, ref left_elements) = left {
+                if left_elements == elements {
+                    return Some(value.cmp(&other));
+                }
+            }
+            Some(other.cmp(&value))
+        }
+        _ => {
+            let left = left.elements()?;
+            let right = right.elements()?;
+            Some(left.cmp(right))
+        }
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> Result<bool, String> {
+    match (left, right) {
+        (Value::Vector(kind, _, _), _) | (_, Value::Vector(kind, _, _)) if kind.is_numeric() => {
+            match compare(left, right) {
+                Some(ordering) => Ok(ordering == Ordering::Equal),
+                // A metavalue is not equal to anything
+                None if left.elements().is_some() || right.elements().is_some() => Ok(false),
+                None => Err(type_error("=", left, right)),
+            }
+        }
+        _ => match (left.elements(), right.elements()) {
+            (Some(left), Some(right)) => Ok(left == right),
+            _ => match compare(left, right) {
+                Some(ordering) => Ok(ordering == Ordering::Equal),
+                None => Err(type_error("=", left, right)),
+            },
+        },
+    }
+}
+
+/// The result kind and length of an operation of numeric_std on the operands
+fn numeric_operands(left: &Value, right: &Value) -> Option<(VectorKind, usize, i64, i64)> {
+    match (left, right) {
+        (Value::Vector(kind, _, ref left), Value::Integer(right)) if kind.is_numeric() => {
+            Some((*kind, left.len(), vector_integer(*kind, left)?, *right))
+        }
+        (Value::Integer(left), Value::Vector(kind, _, ref right)) if kind.is_numeric() => {
+            Some((*kind, right.len(), *left, vector_integer(*kind, right)?))
+        }
+        (Value::Vector(kind, _, ref left), Value::Vector(_, _, ref right)) if kind.is_numeric() => {
+            Some((
+                *kind,
+                left.len().max(right.len()),
+                vector_integer(*kind, left)?,
+                vector_integer(*kind, right)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn is_numeric_vector(value: &Value) -> bool {
+    matches!(value, Value::Vector(kind, _, _) if kind.is_numeric())
+}
+
+fn checked(value: Option<i64>) -> Result<Value, String> {
+    value
+        .map(Value::Integer)
+        .ok_or_else(|| "Integer overflow".to_owned())
+}
+
+fn concat(left: Value, right: Value) -> Result<Value, String> {
+    let elements = |value: &Value| -> Option<Vec<u8>> {
+        match value {
+            Value::Character(chr) => Some(vec![*chr]),
+            Value::String(ref elements) | Value::Vector(_, _, ref elements) => {
+                Some(elements.clone())
+            }
+            _ => None,
+        }
+    };
+    let (mut left_elements, right_elements) = match (elements(&left), elements(&right)) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return Err(type_error("&", &left, &right)),
+    };
+    left_elements.extend(right_elements);
+    let length = left_elements.len();
+    match (&left, &right) {
+        (Value::String(..), _) | (_, Value::String(..)) => Ok(Value::String(left_elements)),
+        (Value::Vector(kind, bounds, _), _) if bounds.ascending => Ok(Value::Vector(
+            *kind,
+            Bounds::ascending_from(bounds.left, length),
+            left_elements,
+        )),
+        (Value::Vector(kind, _, _), _) | (_, Value::Vector(kind, _, _)) => Ok(Value::Vector(
+            *kind,
+            Bounds::descending(length),
+            left_elements,
+        )),
+        _ => Ok(Value::Vector(
+            VectorKind::Logic,
+            Bounds::descending(length),
+            left_elements,
+        )),
+    }
+}
+
+fn shift(op: Binary, value: Value, amount: i64) -> Result<Value, String> {
+    let (kind, bounds, elements) = match value {
+        Value::Vector(kind, bounds, elements) => (kind, bounds, elements),
+        value => return Err(type_error("shift", &value, &Value::Integer(amount))),
+    };
+    let length = elements.len() as i64;
+    let fill = match op {
+        Binary::SRA if !elements.is_empty() => elements[0],
+        Binary::SLA if !elements.is_empty() => elements[elements.len() - 1],
+        _ => b'0',
+    };
+    let shifted = (0..length)
+        .map(|i| match op {
+            Binary::SLL | Binary::SLA => {
+                let from = i + amount;
+                if from >= 0 && from < length {
+                    elements[from as usize]
+                } else {
+                    fill
+                }
+            }
+            Binary::SRL | Binary::SRA => {
+                let from = i - amount;
+                if from >= 0 && from < length {
+                    elements[from as usize]
+                } else {
+                    fill
+                }
+            }
+            Binary::ROL => elements[(i + amount).rem_euclid(length) as usize],
+            _ => elements[(i - amount).rem_euclid(length) as usize],
+        })
+        .collect();
+    Ok(Value::Vector(kind, bounds, shifted))
+}
+
+pub fn binary(op: Binary, left: Value, right: Value) -> Result<Value, String> {
+    match op {
+        Binary::EQ => return equal(&left, &right).map(Value::Boolean),
+        Binary::NE => return equal(&left, &right).map(|equal| Value::Boolean(!equal)),
+        Binary::LT | Binary::LTE | Binary::GT | Binary::GTE => {
+            let ordering = compare(&left, &right);
+            return match ordering {
+                Some(ordering) => Ok(Value::Boolean(match op {
+                    Binary::LT => ordering == Ordering::Less,
+                    Binary::LTE => ordering != Ordering::Greater,
+                    Binary::GT => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                })),
+                // Numeric comparisons with a metavalue are false
+                None if is_numeric_vector(&left) || is_numeric_vector(&right) => {
+                    Ok(Value::Boolean(false))
+                }
+                None => Err(type_error("comparison", &left, &right)),
+            };
+        }
+        Binary::Concat => return concat(left, right),
+        Binary::SLL | Binary::SRL | Binary::SLA | Binary::SRA | Binary::ROL | Binary::ROR => {
+            return match right {
+                Value::Integer(amount) => shift(op, left, amount),
+                right => Err(type_error("shift", &left, &right)),
+            };
+        }
+        _ => {}
+    }
+
+    match (left, right) {
+        (Value::Boolean(left), Value::Boolean(right)) => Ok(Value::Boolean(match op {
+            Binary::And => left && right,
+            Binary::Or => left || right,
+            Binary::Nand => !(left && right),
+            Binary::Nor => !(left || right),
+            Binary::Xor => left != right,
+            Binary::Xnor => left == right,
+            _ => return Err(type_error("boolean", &left.into(), &right.into())),
+        })),
+        (Value::Character(left), Value::Character(right)) => logical(op, left, right)
+            .map(Value::Character)
+            .ok_or_else(|| {
+                type_error("logical", &Value::Character(left), &Value::Character(right))
+            }),
+        (Value::Integer(left), Value::Integer(right)) => match op {
+            Binary::Div | Binary::Rem | Binary::Mod if right == 0 => {
+                Err("Division by zero".to_owned())
+            }
+            Binary::Pow if right < 0 => Err("Negative exponent".to_owned()),
+            Binary::Plus
+            | Binary::Minus
+            | Binary::Times
+            | Binary::Div
+            | Binary::Rem
+            | Binary::Mod
+            | Binary::Pow => checked(integer_binary(op, left, right)),
+            _ => Err(type_error(
+                "integer",
+                &Value::Integer(left),
+                &Value::Integer(right),
+            )),
+        },
+        (Value::Time(left), Value::Time(right)) => match op {
+            Binary::Plus => Ok(Value::Time(left.saturating_add(right))),
+            Binary::Minus => Ok(Value::Time(left.saturating_sub(right))),
+            Binary::Div if right != 0 => Ok(Value::Integer(left / right)),
+            _ => Err(type_error("time", &Value::Time(left), &Value::Time(right))),
+        },
+        (Value::Time(time), Value::Integer(factor))
+        | (Value::Integer(factor), Value::Time(time))
+            if op == Binary::Times =>
+        {
+            Ok(Value::Time(time.saturating_mul(factor)))
+        }
+        (Value::Time(time), Value::Integer(divisor)) if op == Binary::Div && divisor != 0 => {
+            Ok(Value::Time(time / divisor))
+        }
+        (left, right) => {
+            if let Some((kind, length, left_value, right_value)) = numeric_operands(&left, &right) {
+                let value = match op {
+                    Binary::Plus => left_value.wrapping_add(right_value),
+                    Binary::Minus => left_value.wrapping_sub(right_value),
+                    Binary::Times => {
+                        // numeric_std multiplication gives the sum of the lengths
+                        let length = match (&left, &right) {
+                            (Value::Vector(_, _, ref left), Value::Vector(_, _, ref right)) => {
+                                left.len() + right.len()
+                            }
+                            _ => 2 * length,
+                        };
+                        return Ok(numeric_vector(
+                            kind,
+                            left_value.wrapping_mul(right_value),
+                            length,
+                        ));
+                    }
+                    Binary::Div | Binary::Rem | Binary::Mod if right_value == 0 => {
+                        return Err("Division by zero".to_owned());
+                    }
+                    Binary::Div => left_value / right_value,
+                    Binary::Rem => left_value % right_value,
+                    Binary::Mod => left_value.rem_euclid(right_value),
+                    _ => {
+                        return logical_vectors(op, left, right);
+                    }
+                };
+                return Ok(numeric_vector(kind, value, length));
+            }
+            if is_numeric_vector(&left) || is_numeric_vector(&right) {
+                // An operand with a metavalue gives a result of only X
+                if let (Binary::Plus, Some(length)) | (Binary::Minus, Some(length)) = (
+                    op,
+                    left.elements()
+                        .map(|elements| elements.len())
+                        .or_else(|| right.elements().map(|elements| elements.len())),
+                ) {
+                    let kind = match (&left, &right) {
+                        (Value::Vector(kind, _, _), _) | (_, Value::Vector(kind, _, _)) => *kind,
+                        _ => VectorKind::Unsigned,
+                    };
+                    return Ok(Value::Vector(
+                        kind,
+                        Bounds::descending(length),
+                        vec![b'X'; length],
+                    ));
+                }
+            }
+            logical_vectors(op, left, right)
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Boolean(value)
+    }
+}
+
+/// The element wise logical operators of vectors of the same length
+fn logical_vectors(op: Binary, left: Value, right: Value) -> Result<Value, String> {
+    match (&left, &right) {
+        (
+            Value::Vector(kind, bounds, ref left_elements),
+            Value::Vector(_, _, ref right_elements),
+        )
+        | (Value::Vector(kind, bounds, ref left_elements), Value::String(ref right_elements))
+            if left_elements.len() == right_elements.len() =>
+        {
+            let mut elements = Vec::with_capacity(left_elements.len());
+            for (left, right) in left_elements.iter().zip(right_elements.iter()) {
+                elements.push(logical(op, *left, *right).ok_or_else(|| {
+                    type_error(
+                        &format!("{:?}", op),
+                        &left_value(*left),
+                        &left_value(*right),
+                    )
+                })?);
+            }
+            Ok(Value::Vector(*kind, *bounds, elements))
+        }
+        _ => Err(type_error(&format!("{:?}", op), &left, &right)),
+    }
+}
+
+fn left_value(chr: u8) -> Value {
+    Value::Character(chr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use latin_1::Latin1String;
+
+    fn vector(kind: VectorKind, elements: &str) -> Value {
+        Value::Vector(
+            kind,
+            Bounds::descending(elements.len()),
+            elements.as_bytes().to_vec(),
+        )
+    }
+
+    fn literal(length: Option<u32>, base: BaseSpecifier, value: &str) -> Result<String, String> {
+        bit_string(&BitString {
+            length,
+            base,
+            value: Latin1String::from_utf8(value).unwrap(),
+        })
+        .map(|elements| String::from_utf8(elements).unwrap())
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("10 ns"), Some(10_000_000));
+        assert_eq!(parse_time("10ns"), Some(10_000_000));
+        assert_eq!(parse_time("1.5 us"), Some(1_500_000_000));
+        assert_eq!(parse_time("10"), None);
+        assert_eq!(parse_time("10 parsecs"), None);
+        assert_eq!(time_image(10_000_000), "10 ns");
+        assert_eq!(time_image(1_500_000_000), "1500 ns");
+        assert_eq!(time_image(0), "0 fs");
+    }
+
+    #[test]
+    fn bit_strings() {
+        assert_eq!(
+            literal(None, BaseSpecifier::X, "A5"),
+            Ok("10100101".to_owned())
+        );
+        assert_eq!(
+            literal(None, BaseSpecifier::O, "7_1"),
+            Ok("111001".to_owned())
+        );
+        assert_eq!(literal(None, BaseSpecifier::X, "Z"), Ok("ZZZZ".to_owned()));
+        assert_eq!(
+            literal(Some(6), BaseSpecifier::SX, "F"),
+            Ok("111111".to_owned())
+        );
+        assert_eq!(
+            literal(Some(3), BaseSpecifier::X, "7"),
+            Ok("111".to_owned())
+        );
+        assert_eq!(
+            literal(Some(5), BaseSpecifier::D, "9"),
+            Ok("01001".to_owned())
+        );
+        assert!(literal(Some(3), BaseSpecifier::X, "F0").is_err());
+        assert!(literal(None, BaseSpecifier::B, "2").is_err());
+    }
+
+    #[test]
+    fn conversions() {
+        let byte = Type::Vector(VectorKind::Logic, Some(Bounds::descending(8)));
+        assert_eq!(
+            convert(Value::String(b"0000ZZZZ".to_vec()), &byte),
+            Ok(vector(VectorKind::Logic, "0000ZZZZ"))
+        );
+        assert!(convert(Value::String(b"0101".to_vec()), &byte).is_err());
+        assert!(convert(Value::String(b"0000abcd".to_vec()), &byte).is_err());
+        assert!(convert(Value::Integer(-1), &Type::Integer(0, 7)).is_err());
+        assert_eq!(
+            convert(Value::Character(b'1'), &Type::Bit),
+            Ok(Value::Character(b'1'))
+        );
+        assert!(convert(Value::Character(b'Z'), &Type::Bit).is_err());
+        assert_eq!(
+            Type::Vector(VectorKind::Unsigned, Some(Bounds::descending(2))).default_value(),
+            Some(vector(VectorKind::Unsigned, "UU"))
+        );
+    }
+
+    #[test]
+    fn logic_operators() {
+        let op = |op, left: u8, right: u8| {
+            binary(op, Value::Character(left), Value::Character(right)).unwrap()
+        };
+        assert_eq!(op(Binary::And, b'1', b'H'), Value::Character(b'1'));
+        assert_eq!(op(Binary::And, b'U', b'0'), Value::Character(b'0'));
+        assert_eq!(op(Binary::Or, b'U', b'0'), Value::Character(b'U'));
+        assert_eq!(op(Binary::Xor, b'1', b'Z'), Value::Character(b'X'));
+        assert_eq!(
+            unary(Unary::Not, vector(VectorKind::Logic, "01ZU")),
+            Ok(vector(VectorKind::Logic, "10XU"))
+        );
+        assert_eq!(
+            unary(Unary::Xor, vector(VectorKind::Logic, "0111")),
+            Ok(Value::Character(b'1'))
+        );
+        assert_eq!(resolve_logic(b'Z', b'1'), b'1');
+        assert_eq!(resolve_logic(b'0', b'1'), b'X');
+        assert_eq!(resolve_logic(b'L', b'H'), b'W');
+    }
+
+    #[test]
+    fn numeric_operators() {
+        let unsigned = |elements| vector(VectorKind::Unsigned, elements);
+        let signed = |elements| vector(VectorKind::Signed, elements);
+        assert_eq!(
+            binary(Binary::Plus, unsigned("1111"), Value::Integer(1)),
+            Ok(unsigned("0000"))
+        );
+        assert_eq!(
+            binary(Binary::Minus, signed("0001"), signed("0010")),
+            Ok(signed("1111"))
+        );
+        assert_eq!(
+            binary(Binary::Times, unsigned("11"), unsigned("11")),
+            Ok(unsigned("1001"))
+        );
+        assert_eq!(
+            binary(Binary::Plus, unsigned("1X"), Value::Integer(1)),
+            Ok(unsigned("XX"))
+        );
+        assert_eq!(
+            binary(Binary::LT, signed("1111"), Value::Integer(0)),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            binary(Binary::GT, Value::Integer(3), unsigned("0010")),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            binary(Binary::EQ, unsigned("0011"), Value::Integer(3)),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(resize(&signed("10"), 4), Ok(signed("1110")));
+        assert_eq!(resize(&unsigned("0110"), 2), Ok(unsigned("10")));
+        assert_eq!(vector_integer(VectorKind::Signed, b"1000"), Some(-8));
+    }
+
+    #[test]
+    fn array_operators() {
+        let logic = |elements| vector(VectorKind::Logic, elements);
+        assert_eq!(
+            binary(Binary::Concat, logic("01"), Value::Character(b'Z')),
+            Ok(logic("01Z"))
+        );
+        assert_eq!(
+            binary(
+                Binary::Concat,
+                Value::String(b"x = ".to_vec()),
+                Value::String(b"1".to_vec())
+            ),
+            Ok(Value::String(b"x = 1".to_vec()))
+        );
+        assert_eq!(
+            binary(Binary::EQ, logic("0101"), Value::String(b"0101".to_vec())),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            binary(Binary::And, logic("0110"), logic("0011")),
+            Ok(logic("0010"))
+        );
+        assert_eq!(
+            binary(Binary::SLL, logic("0011"), Value::Integer(1)),
+            Ok(logic("0110"))
+        );
+        assert_eq!(
+            binary(Binary::ROR, logic("0011"), Value::Integer(1)),
+            Ok(logic("1001"))
+        );
+        assert!(binary(Binary::And, logic("01"), logic("011")).is_err());
+    }
+
+    #[test]
+    fn integer_and_time_operators() {
+        assert_eq!(
+            binary(Binary::Mod, Value::Integer(-7), Value::Integer(3)),
+            Ok(Value::Integer(2))
+        );
+        assert!(binary(Binary::Div, Value::Integer(1), Value::Integer(0)).is_err());
+        assert_eq!(
+            binary(Binary::Times, Value::Integer(3), Value::Time(5)),
+            Ok(Value::Time(15))
+        );
+        assert_eq!(
+            binary(Binary::Div, Value::Time(15), Value::Time(5)),
+            Ok(Value::Integer(3))
+        );
+        assert_eq!(Value::Time(10_000_000).image(), "10 ns");
+    }
+}
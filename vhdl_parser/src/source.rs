@@ -17,44 +17,86 @@ use std::io::prelude::Read;
 use std::io::{BufRead, Error};
 use std::sync::Arc;
 
-#[derive(PartialEq, Clone)]
-pub enum Source {
-    FileName(Arc<String>),
+#[derive(PartialEq, Eq)]
+enum SourceKind {
+    FileName(String),
     Contents(Arc<Latin1String>),
+    /// Contents of a file which differ from the file on disk such as an unsaved editor buffer
+    FileContents(String, Arc<Latin1String>),
 }
 
+/// Every position refers to its source so a source is a single reference counted
+/// pointer to keep cloning and dropping positions cheap
+#[derive(PartialEq, Clone)]
+pub struct Source(Arc<SourceKind>);
+
 impl fmt::Debug for Source {
     /// Custom implementation to avoid large Contents strings
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Source::FileName(ref file_name) => {
+        match *self.0 {
+            SourceKind::FileName(ref file_name) => {
                 write!(f, "Source::FileName({:?})", file_name.as_str())
             }
-            Source::Contents(_) => write!(f, "Source::Contents(...)"),
+            SourceKind::Contents(_) => write!(f, "Source::Contents(...)"),
+            SourceKind::FileContents(ref file_name, _) => {
+                write!(f, "Source::FileContents({:?}, ...)", file_name.as_str())
+            }
         }
     }
 }
 
 impl Source {
     pub fn from_str(contents: &str) -> Result<Source, String> {
-        Ok(Source::Contents(Arc::new(Latin1String::from_utf8(
+        Ok(Source::from_latin1(Arc::new(Latin1String::from_utf8(
             contents,
         )?)))
     }
+
+    pub fn from_latin1(contents: Arc<Latin1String>) -> Source {
+        Source(Arc::new(SourceKind::Contents(contents)))
+    }
+
     pub fn from_file(file_name: &str) -> Source {
-        Source::FileName(Arc::new(file_name.to_string()))
+        Source(Arc::new(SourceKind::FileName(file_name.to_string())))
+    }
+
+    pub fn from_file_contents(file_name: &str, contents: &str) -> Result<Source, String> {
+        Ok(Source::from_file_latin1(
+            file_name,
+            Arc::new(Latin1String::from_utf8(contents)?),
+        ))
+    }
+
+    pub fn from_file_latin1(file_name: &str, contents: Arc<Latin1String>) -> Source {
+        Source(Arc::new(SourceKind::FileContents(
+            file_name.to_string(),
+            contents,
+        )))
     }
 
     pub fn contents(self: &Self) -> Result<Arc<Latin1String>, Error> {
-        match self {
-            Source::FileName(ref file_name) => {
-                let mut file = File::open(file_name.as_ref())?;
+        match *self.0 {
+            SourceKind::FileName(ref file_name) => {
+                let mut file = File::open(file_name)?;
                 let mut bytes = Vec::new();
                 file.read_to_end(&mut bytes)?;
 
                 Ok(Arc::new(Latin1String::from_vec(bytes)))
             }
-            Source::Contents(ref contents) => Ok(contents.clone()),
+            SourceKind::Contents(ref contents) | SourceKind::FileContents(_, ref contents) => {
+                Ok(contents.clone())
+            }
+        }
+    }
+
+    /// The number of bytes of contents kept in memory, zero when the contents
+    /// are read from the file each time they are needed
+    pub fn memory_bytes(&self) -> usize {
+        match *self.0 {
+            SourceKind::FileName(..) => 0,
+            SourceKind::Contents(ref contents) | SourceKind::FileContents(_, ref contents) => {
+                contents.len()
+            }
         }
     }
 
@@ -64,9 +106,11 @@ impl Source {
     }
 
     pub fn file_name(self: &Self) -> Option<&str> {
-        match self {
-            Source::FileName(ref file_name) => Some(file_name.as_str()),
-            _ => None,
+        match *self.0 {
+            SourceKind::FileName(ref file_name) | SourceKind::FileContents(ref file_name, _) => {
+                Some(file_name.as_str())
+            }
+            SourceKind::Contents(..) => None,
         }
     }
 
@@ -123,7 +167,7 @@ pub struct SrcPos {
     pub length: usize,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct WithPos<T> {
     pub item: T,
     pub pos: SrcPos,
@@ -334,19 +378,8 @@ impl SrcPos {
     }
 
     pub fn lineno_and_pretty_string(self: &Self) -> (usize, String) {
-        match self.source {
-            Source::FileName(ref file_name) => {
-                let mut file = File::open(file_name.to_string()).unwrap();
-                let mut bytes = Vec::new();
-                file.read_to_end(&mut bytes).unwrap();
-                let latin1 = Latin1String::from_vec(bytes);
-                self.pretty_string_from_reader(&mut latin1.to_string().as_bytes())
-            }
-            Source::Contents(ref contents) => {
-                let utf8_contents = contents.to_string();
-                self.pretty_string_from_reader(&mut utf8_contents.as_bytes())
-            }
-        }
+        let contents = self.source.contents().unwrap();
+        self.pretty_string_from_reader(&mut contents.to_string().as_bytes())
     }
 
     /// Create a string for pretty printing
@@ -355,6 +388,21 @@ impl SrcPos {
         pretty_string
     }
 
+    /// The one based line and column of the start, zero when the source cannot be read
+    pub fn line_and_column(&self) -> (usize, usize) {
+        let contents = match self.source.contents() {
+            Ok(contents) => contents,
+            Err(_) => return (0, 0),
+        };
+        let before = &contents.bytes[..self.start.min(contents.bytes.len())];
+        let line = before.iter().filter(|byte| **byte == b'\n').count() + 1;
+        let column = match before.iter().rposition(|byte| *byte == b'\n') {
+            Some(newline) => before.len() - newline,
+            None => before.len() + 1,
+        };
+        (line, column)
+    }
+
     /// Combines two lexical positions into a larger legical position overlapping both
     /// The file name is assumed to be the same
     pub fn combine(self: &Self, other: &Self) -> Self {
@@ -377,6 +425,13 @@ mod tests {
     extern crate tempfile;
     use message::{error, warning};
 
+    #[test]
+    fn srcpos_line_and_column() {
+        let source = Source::from_str("hello\nworld").unwrap();
+        assert_eq!(source.first_substr_pos("hello").line_and_column(), (1, 1));
+        assert_eq!(source.first_substr_pos("rld").line_and_column(), (2, 3));
+    }
+
     #[test]
     fn srcpos_combine() {
         let source = Source::from_str("hello world").unwrap();
@@ -397,6 +452,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn file_contents_differ_from_file() {
+        let source = Source::from_file_contents("missing.vhd", "hello").unwrap();
+        assert_eq!(source.file_name(), Some("missing.vhd"));
+        assert_eq!(source.contents().unwrap().to_string(), "hello");
+    }
+
     fn with_source_from_file<F, R>(contents: &str, fun: F) -> R
     where
         F: Fn(Source) -> R,
@@ -0,0 +1,507 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The extent of declarations and statements within the tokens of a source
+//! The AST does not keep the extent of declarations so the source is tokenized again
+
+use latin_1::Latin1String;
+use name_resolution::NamedEntityKind;
+use source::{Source, SrcPos};
+use std::sync::Arc;
+use symbol_table::SymbolTable;
+use tokenizer::Kind::*;
+use tokenizer::{Kind, Token, Tokenizer};
+
+pub struct SourceTokens {
+    pub code: Arc<Latin1String>,
+    pub tokens: Vec<Token>,
+}
+
+impl SourceTokens {
+    /// Tokens which could not be tokenized are skipped
+    pub fn new(source: &Source) -> Option<SourceTokens> {
+        let code = source.contents().ok()?;
+        let mut tokenizer = Tokenizer::with_standard(
+            Arc::new(SymbolTable::new()),
+            source.clone(),
+            code.clone(),
+            Default::default(),
+        );
+        let mut tokens = Vec::new();
+        loop {
+            match tokenizer.pop() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => break,
+                Err(..) => {}
+            }
+        }
+        Some(SourceTokens { code, tokens })
+    }
+
+    /// The index of the token starting at the position
+    pub fn index_of(&self, pos: &SrcPos) -> Option<usize> {
+        self.tokens
+            .iter()
+            .position(|token| token.pos.start == pos.start)
+    }
+
+    pub fn kind(&self, i: usize) -> Option<Kind> {
+        self.tokens.get(i).map(|token| token.kind)
+    }
+
+    /// The position from the start of the first token to the end of the last token
+    pub fn range(&self, first: usize, last: usize) -> SrcPos {
+        self.tokens[first].pos.combine(&self.tokens[last].pos)
+    }
+
+    /// The index of the first token before the start which is not within parenthesis
+    /// and is one of the kinds or an unmatched left parenthesis
+    fn find_backward(&self, start: usize, kinds: &[Kind]) -> Option<usize> {
+        let mut depth = 0;
+        for i in (0..start).rev() {
+            let kind = self.tokens[i].kind;
+            if kind == RightPar {
+                depth += 1;
+            } else if kind == LeftPar {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            } else if depth == 0 && kinds.contains(&kind) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    /// The index of the first token after the start which is not within parenthesis or a record,
+    /// units or protected type definition and is one of the kinds or an unmatched right parenthesis
+    pub fn find_forward(&self, start: usize, kinds: &[Kind]) -> Option<usize> {
+        let mut depth = 0;
+        let mut block_depth = 0;
+        for i in start + 1..self.tokens.len() {
+            let kind = self.tokens[i].kind;
+            let after_end = self.tokens[i - 1].kind == End;
+            if kind == LeftPar {
+                depth += 1;
+            } else if kind == RightPar {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            } else if (kind == Record || kind == Units || kind == Protected) && !after_end {
+                block_depth += 1;
+            } else if kind == End && block_depth > 0 {
+                block_depth -= 1;
+            } else if depth == 0 && block_depth == 0 && kinds.contains(&kind) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    /// The pure keyword is tokenized as an identifier
+    fn is_pure(&self, i: usize) -> bool {
+        let token = &self.tokens[i];
+        token.kind == Identifier
+            && self.code.bytes[token.pos.start..token.pos.start + token.pos.length]
+                .eq_ignore_ascii_case(b"pure")
+    }
+
+    /// The first and last token of the declaration of the designator at the index
+    /// Only the header of design units and subprograms and the generic and port clauses
+    /// of entities and components are included
+    pub fn declaration_extent(&self, i: usize, kind: NamedEntityKind) -> (usize, usize) {
+        use self::NamedEntityKind as K;
+        let last = self.tokens.len() - 1;
+        let before = |end: Option<usize>| end.map(|end| end.max(i + 1) - 1).unwrap_or(last);
+        let after = |start: Option<usize>| start.map(|start| start + 1).unwrap_or(0);
+
+        match kind {
+            K::Entity | K::Component => {
+                let mut end = if self.kind(i + 1) == Some(Is) {
+                    i + 1
+                } else {
+                    i
+                };
+                while let Some(Generic) | Some(Port) = self.kind(end + 1) {
+                    match self.find_forward(end + 1, &[SemiColon]) {
+                        Some(semi) => end = semi,
+                        None => break,
+                    }
+                }
+                (i.saturating_sub(1), end)
+            }
+            K::Architecture | K::Configuration | K::Package | K::Context => (
+                i.saturating_sub(1),
+                self.find_forward(i, &[Is, SemiColon]).unwrap_or(last),
+            ),
+            K::Function | K::Procedure => (
+                self.subprogram_start(i),
+                before(self.find_forward(i, &[Is, SemiColon])),
+            ),
+            K::Generic | K::Port | K::Parameter => (
+                after(self.find_backward(i, &[SemiColon])),
+                before(self.find_forward(i, &[SemiColon])),
+            ),
+            K::Label => (
+                i,
+                before(
+                    self.find_forward(i, &[Is, Begin, Generic, Port, SemiColon, Generate, Loop]),
+                ),
+            ),
+            K::LoopParameter => (
+                i.saturating_sub(1),
+                before(self.find_forward(i, &[Loop, Generate])),
+            ),
+            _ => (
+                self.keyword_before(i, kind).unwrap_or_else(|| {
+                    after(self.find_backward(i, &[SemiColon, Is, Begin, Units]))
+                }),
+                self.find_forward(i, &[SemiColon]).unwrap_or(last),
+            ),
+        }
+    }
+
+    /// The keyword which starts the declaration of a designator of the kind
+    fn keyword_before(&self, i: usize, kind: NamedEntityKind) -> Option<usize> {
+        use self::NamedEntityKind as K;
+        let keyword = match kind {
+            K::Signal => Signal,
+            K::Constant => Constant,
+            K::Variable | K::SharedVariable => Variable,
+            K::File => File,
+            K::Type | K::EnumerationLiteral => Type,
+            K::Subtype => Subtype,
+            K::Alias => Alias,
+            K::Attribute => Attribute,
+            K::PackageInstance => Package,
+            _ => {
+                return None;
+            }
+        };
+        let first = self.find_backward(i, &[keyword, SemiColon])?;
+        match self.kind(first) {
+            Some(Variable) if first > 0 && self.kind(first - 1) == Some(Shared) => Some(first - 1),
+            Some(found) if found == keyword => Some(first),
+            _ => None,
+        }
+    }
+
+    /// The function or procedure keyword or the purity before it
+    fn subprogram_start(&self, i: usize) -> usize {
+        let first = i.saturating_sub(1);
+        if first > 0 && (self.kind(first - 1) == Some(Impure) || self.is_pure(first - 1)) {
+            first - 1
+        } else {
+            first
+        }
+    }
+
+    /// The first token of the whole declaration of the designator at the index
+    pub fn declaration_start(&self, i: usize, kind: NamedEntityKind) -> usize {
+        self.declaration_extent(i, kind).0
+    }
+
+    /// A subprogram specification followed by is starts a body unless it is an instantiation
+    fn is_subprogram_body(&self, i: usize) -> bool {
+        match self.find_forward(i, &[Is, SemiColon]) {
+            Some(is) => {
+                let next = self.kind(is + 1);
+                self.kind(is) == Some(Is) && next != Some(New) && next != Some(BOX)
+            }
+            None => false,
+        }
+    }
+
+    /// A keyword which starts a construct ended by end
+    fn is_opener(&self, i: usize, enclosing: Option<Kind>, generate_pending: &mut bool) -> bool {
+        let previous = if i > 0 { self.kind(i - 1) } else { None };
+        // Instantiations, binding indications and entity classes of attribute specifications
+        let referenced = previous == Some(Colon) || previous == Some(Use);
+        match self.tokens[i].kind {
+            Entity | Architecture | Configuration | Component | Units => !referenced,
+            Process | Block | Record | Protected | Loop | Case => true,
+            Package => !referenced && self.kind(i + 3) != Some(New),
+            Context => self.kind(i + 2) == Some(Is),
+            Function | Procedure => !referenced && self.is_subprogram_body(i),
+            // Block and component configurations
+            For => enclosing == Some(Configuration) || enclosing == Some(For),
+            If => {
+                *generate_pending = true;
+                true
+            }
+            Elsif | Else => {
+                *generate_pending = true;
+                false
+            }
+            // An if generate and its alternatives share the end of the if
+            Generate => !std::mem::replace(generate_pending, false),
+            _ => false,
+        }
+    }
+
+    /// The index of the last token of the declaration or statement starting at the index
+    /// A construct ends at a semi colon unless it has an end, then it ends after its end
+    pub fn end_of(&self, start: usize) -> usize {
+        let mut enclosing = Vec::new();
+        let mut depth = 0;
+        let mut generate_pending = false;
+        let mut i = start;
+        while i < self.tokens.len() {
+            match self.tokens[i].kind {
+                LeftPar => depth += 1,
+                RightPar => {
+                    if depth == 0 {
+                        return i.max(start + 1) - 1;
+                    }
+                    depth -= 1;
+                }
+                _ if depth > 0 => {}
+                End => {
+                    if enclosing.pop().is_none() {
+                        return i.max(start + 1) - 1;
+                    }
+                    while i + 1 < self.tokens.len() && self.tokens[i].kind != SemiColon {
+                        i += 1;
+                    }
+                    if enclosing.is_empty() {
+                        return i;
+                    }
+                }
+                SemiColon => {
+                    generate_pending = false;
+                    if enclosing.is_empty() {
+                        return i;
+                    }
+                }
+                Then | Begin => generate_pending = false,
+                kind => {
+                    if self.is_opener(i, enclosing.last().cloned(), &mut generate_pending) {
+                        enclosing.push(kind);
+                    }
+                }
+            }
+            i += 1;
+        }
+        self.tokens.len() - 1
+    }
+
+    /// The index of the keyword which opened the construct closed by the end at the index
+    pub fn opener_of(&self, end: usize) -> Option<usize> {
+        if self.kind(end) != Some(End) {
+            return None;
+        }
+        let mut enclosing: Vec<usize> = Vec::new();
+        let mut depth = 0;
+        let mut generate_pending = false;
+        let mut i = 0;
+        while i < end {
+            match self.tokens[i].kind {
+                LeftPar => depth += 1,
+                RightPar => depth -= 1,
+                _ if depth > 0 => {}
+                End => {
+                    enclosing.pop();
+                    while i + 1 < end && self.tokens[i].kind != SemiColon {
+                        i += 1;
+                    }
+                }
+                SemiColon | Then | Begin => generate_pending = false,
+                _ => {
+                    let kind = enclosing.last().map(|j| self.tokens[*j].kind);
+                    if self.is_opener(i, kind, &mut generate_pending) {
+                        enclosing.push(i);
+                    }
+                }
+            }
+            i += 1;
+        }
+        enclosing.pop()
+    }
+
+    /// Whether there is a line break between the token at the index and the one before it
+    fn newline_before(&self, i: usize) -> bool {
+        i > 0 && {
+            let previous = &self.tokens[i - 1].pos;
+            self.code.bytes[previous.start + previous.length..self.tokens[i].pos.start]
+                .contains(&b'\n')
+        }
+    }
+
+    /// The indentation level of each token which starts a declaration, a statement or a part of
+    /// a construct such as begin, else or end, tokens which continue the one before have no level
+    /// A case alternative is one level deeper than its case and its statements are one more
+    pub fn indentation_levels(&self) -> Vec<Option<usize>> {
+        let mut levels = Vec::with_capacity(self.tokens.len());
+        // When is pushed for the case alternative being indented
+        let mut enclosing: Vec<Kind> = Vec::new();
+        let mut depth = 0;
+        let mut generate_pending = false;
+        let mut in_statement = false;
+        // A construct was opened by the current statement so is ends it
+        let mut opened = false;
+        // The statement is between end and its semi colon
+        let mut ending = false;
+        // The statement is the choices of a case alternative
+        let mut alternative = false;
+        // The statement is a header without a keyword ending it
+        let mut header = false;
+
+        for i in 0..self.tokens.len() {
+            let kind = self.tokens[i].kind;
+            if depth > 0 {
+                if kind == LeftPar {
+                    depth += 1;
+                } else if kind == RightPar {
+                    depth -= 1;
+                }
+                levels.push(None);
+                continue;
+            }
+            if header && self.newline_before(i) {
+                header = false;
+                in_statement = false;
+            }
+
+            let starts = !in_statement;
+            let inner = enclosing.len();
+            let level = match kind {
+                End => {
+                    if enclosing.last() == Some(&When) {
+                        enclosing.pop();
+                    }
+                    enclosing.pop();
+                    ending = true;
+                    Some(enclosing.len())
+                }
+                Begin | Elsif => Some(inner.saturating_sub(1)),
+                Else if starts => Some(inner.saturating_sub(1)),
+                When if starts
+                    && (enclosing.last() == Some(&Case) || enclosing.last() == Some(&When)) =>
+                {
+                    if enclosing.last() == Some(&When) {
+                        enclosing.pop();
+                    }
+                    let level = enclosing.len();
+                    enclosing.push(When);
+                    alternative = true;
+                    Some(level)
+                }
+                _ if starts => Some(inner),
+                _ => None,
+            };
+            levels.push(level);
+            if level.is_some() {
+                opened = false;
+            }
+
+            let is_opener =
+                !ending && self.is_opener(i, enclosing.last().cloned(), &mut generate_pending);
+            if is_opener {
+                enclosing.push(kind);
+                opened = true;
+            }
+
+            in_statement = match kind {
+                LeftPar => {
+                    depth += 1;
+                    true
+                }
+                SemiColon => {
+                    ending = false;
+                    generate_pending = false;
+                    false
+                }
+                Begin | Then => {
+                    generate_pending = false;
+                    false
+                }
+                Is => !opened,
+                Loop | Generate | Record | Units => ending,
+                Protected => ending || self.kind(i + 1) == Some(Body),
+                Body => ending || i == 0 || self.kind(i - 1) != Some(Protected),
+                Else if starts => self.kind(i + 1) == Some(Generate),
+                RightArrow if alternative => {
+                    alternative = false;
+                    false
+                }
+                Process | Block | Component | For if is_opener => {
+                    header = true;
+                    true
+                }
+                _ => true,
+            };
+        }
+        levels
+    }
+
+    /// The source text of the token at the index
+    pub fn text(&self, i: usize) -> String {
+        let pos = &self.tokens[i].pos;
+        Latin1String::new(&self.code.bytes[pos.start..pos.start + pos.length]).to_string()
+    }
+
+    fn same_text(&self, i: usize, j: usize) -> bool {
+        let text =
+            |token: &Token| &self.code.bytes[token.pos.start..token.pos.start + token.pos.length];
+        text(&self.tokens[i]).eq_ignore_ascii_case(text(&self.tokens[j]))
+    }
+
+    /// The index of the repeated designator after the end of the construct
+    /// which is declared or completed by the designator or label at the index
+    pub fn end_label(&self, i: usize) -> Option<usize> {
+        let start = if self.kind(i + 1) == Some(Colon) {
+            i
+        } else {
+            match self.kind(i.checked_sub(1)?)? {
+                Body => i.checked_sub(2)?,
+                Entity | Architecture | Configuration | Package | Context | Component
+                | Function | Procedure | Type => i - 1,
+                _ => {
+                    return None;
+                }
+            }
+        };
+        let last = self.end_of(start);
+        let label = last.checked_sub(1)?;
+        if label <= i || self.kind(last) != Some(SemiColon) || !self.same_text(i, label) {
+            return None;
+        }
+
+        // Only keywords may be between the end and the label
+        for j in (i + 1..label).rev() {
+            match self.tokens[j].kind {
+                End => return Some(label),
+                Entity | Architecture | Configuration | Package | Body | Context | Component
+                | Function | Procedure | Process | Postponed | Block | Generate | Record
+                | Units | Protected | Loop | If | Case | For => {}
+                _ => {
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// The index of the first token of kind after the start
+    pub fn next_of(&self, start: usize, kind: Kind) -> Option<usize> {
+        (start + 1..self.tokens.len()).find(|i| self.tokens[*i].kind == kind)
+    }
+
+    /// The index of the begin after the declarations following the start
+    pub fn skip_declarations(&self, start: usize) -> Option<usize> {
+        let mut i = start + 1;
+        while i < self.tokens.len() {
+            match self.tokens[i].kind {
+                Begin => return Some(i),
+                End => return None,
+                _ => i = self.end_of(i) + 1,
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,74 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The revision of the VHDL language standard
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Default)]
+pub enum VHDLStandard {
+    VHDL1993,
+    VHDL2002,
+    #[default]
+    VHDL2008,
+    VHDL2019,
+}
+
+impl VHDLStandard {
+    /// The value of the predefined VHDL_VERSION conditional analysis identifier
+    pub fn version_string(self) -> &'static str {
+        match self {
+            VHDLStandard::VHDL1993 => "1993",
+            VHDLStandard::VHDL2002 => "2002",
+            VHDLStandard::VHDL2008 => "2008",
+            VHDLStandard::VHDL2019 => "2019",
+        }
+    }
+}
+
+impl FromStr for VHDLStandard {
+    type Err = String;
+
+    /// Both two and four digit years are accepted such as 93 and 2008
+    fn from_str(string: &str) -> Result<VHDLStandard, String> {
+        match string {
+            "93" | "1993" => Ok(VHDLStandard::VHDL1993),
+            "02" | "2002" => Ok(VHDLStandard::VHDL2002),
+            "08" | "2008" => Ok(VHDLStandard::VHDL2008),
+            "19" | "2019" => Ok(VHDLStandard::VHDL2019),
+            _ => Err(format!(
+                "Unknown VHDL standard '{}', expected one of 1993, 2002, 2008 or 2019",
+                string
+            )),
+        }
+    }
+}
+
+impl fmt::Display for VHDLStandard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VHDL-{}", self.version_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("93".parse(), Ok(VHDLStandard::VHDL1993));
+        assert_eq!("2002".parse(), Ok(VHDLStandard::VHDL2002));
+        assert_eq!("08".parse(), Ok(VHDLStandard::VHDL2008));
+        assert_eq!("2019".parse(), Ok(VHDLStandard::VHDL2019));
+        assert!("87".parse::<VHDLStandard>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(VHDLStandard::VHDL1993.to_string(), "VHDL-1993");
+        assert_eq!(VHDLStandard::default().to_string(), "VHDL-2008");
+    }
+}
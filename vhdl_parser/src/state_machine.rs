@@ -0,0 +1,571 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Finite state machines of the architectures and their transition graph
+//!
+//! A state machine is a signal of an enumeration type which is assigned on a clock edge
+//! and dispatched by a case statement, either within the clocked process or within a
+//! combinational process which assigns the next state registered by the clocked
+//! process such as state <= next_state
+//!
+//! The transitions from a state are the assignments of a literal to the state or next
+//! state within its alternatives, where others is every state without an alternative
+//! The initial states are the literals assigned to the state by clocked processes
+//! outside of the case statement, such as by a reset, otherwise the initial value of
+//! the signal
+
+use ast::{
+    AssignmentRightHand, CaseStatement, Choice, Declaration, EnumerationLiteral, Expression,
+    LabeledSequentialStatement, LibraryUnit, ObjectClass, SequentialStatement, TypeDefinition,
+    Waveform,
+};
+use library::UnitId;
+use process_structure::{architecture_structures, simple_name, target_name, ProcessStructure};
+use project::SourceFile;
+use source::{SrcPos, WithPos};
+use std::collections::BTreeSet;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    /// The position of the assigned literal
+    pub pos: SrcPos,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct StateMachine {
+    pub architecture: UnitId,
+    /// The state signal as declared
+    pub signal: String,
+    /// The position of the expression of the case statement
+    pub pos: SrcPos,
+    /// The literals of the enumeration type as declared and their declaration
+    pub states: Vec<(String, SrcPos)>,
+    /// The states of the alternatives of the case statement and their choice
+    pub choices: Vec<(String, SrcPos)>,
+    pub initial: Vec<String>,
+    pub transitions: Vec<Transition>,
+    /// Whether the case statement has an alternative of others
+    pub has_others: bool,
+}
+
+impl StateMachine {
+    /// The position of the choice of the state, otherwise of its declaration
+    pub fn state_pos(&self, state: &str) -> Option<&SrcPos> {
+        self.choices
+            .iter()
+            .chain(self.states.iter())
+            .find(|(name, _)| name.eq_ignore_ascii_case(state))
+            .map(|(_, pos)| pos)
+    }
+
+    /// The states which are reached from an initial state in the order of declaration
+    pub fn reachable_states(&self) -> Vec<&str> {
+        let mut reached: BTreeSet<String> = self
+            .initial
+            .iter()
+            .map(|state| state.to_lowercase())
+            .collect();
+        let mut pending: Vec<String> = reached.iter().cloned().collect();
+        while let Some(state) = pending.pop() {
+            for transition in self.transitions.iter() {
+                let to = transition.to.to_lowercase();
+                if transition.from.eq_ignore_ascii_case(&state) && reached.insert(to.clone()) {
+                    pending.push(to);
+                }
+            }
+        }
+        self.states
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| reached.contains(&name.to_lowercase()))
+            .collect()
+    }
+
+    /// The states which are not reached from an initial state
+    pub fn unreachable_states(&self) -> Vec<&str> {
+        let reachable = self.reachable_states();
+        self.states
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| !reachable.contains(name))
+            .collect()
+    }
+
+    /// The reachable states without a transition to another state
+    pub fn states_without_exit(&self) -> Vec<&str> {
+        self.reachable_states()
+            .into_iter()
+            .filter(|state| {
+                !self.transitions.iter().any(|transition| {
+                    transition.from.eq_ignore_ascii_case(state)
+                        && !transition.to.eq_ignore_ascii_case(state)
+                })
+            })
+            .collect()
+    }
+
+    /// The transition graph in the dot format of graphviz where initial states are
+    /// drawn as double circles
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!(
+            "digraph {} {{\n",
+            dot_id(&format!("{}.{}", self.architecture, self.signal))
+        );
+        for (state, _) in self.states.iter() {
+            if self
+                .initial
+                .iter()
+                .any(|initial| initial.eq_ignore_ascii_case(state))
+            {
+                dot.push_str(&format!("    {} [shape=doublecircle];\n", dot_id(state)));
+            } else {
+                dot.push_str(&format!("    {};\n", dot_id(state)));
+            }
+        }
+        let edges: BTreeSet<(&str, &str)> = self
+            .transitions
+            .iter()
+            .map(|transition| (transition.from.as_str(), transition.to.as_str()))
+            .collect();
+        for (from, to) in edges {
+            dot.push_str(&format!("    {} -> {};\n", dot_id(from), dot_id(to)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The literal of the states which is the value of the expression
+fn state_literal(expr: &Expression, states: &[(String, SrcPos)]) -> Option<String> {
+    let name = simple_name(expr)?;
+    states
+        .iter()
+        .find(|(state, _)| state.eq_ignore_ascii_case(&name))
+        .map(|(state, _)| state.clone())
+}
+
+/// The values of the waveforms of the right hand side of a signal assignment
+fn rhs_values(rhs: &AssignmentRightHand<Waveform>) -> Vec<&WithPos<Expression>> {
+    let waveforms: Vec<&Waveform> = match rhs {
+        AssignmentRightHand::Simple(ref waveform) => vec![waveform],
+        AssignmentRightHand::Conditional(ref conditionals) => conditionals
+            .conditionals
+            .iter()
+            .map(|conditional| &conditional.item)
+            .chain(conditionals.else_item.iter())
+            .collect(),
+        AssignmentRightHand::Selected(ref selection) => selection
+            .alternatives
+            .iter()
+            .map(|alternative| &alternative.item)
+            .collect(),
+    };
+    waveforms
+        .into_iter()
+        .filter_map(|waveform| match waveform {
+            Waveform::Elements(ref elements) => elements.first().map(|element| &element.value),
+            Waveform::Unaffected => None,
+        })
+        .collect()
+}
+
+/// The values assigned to the signals by the statements, except within case statements
+/// on the signal named by skip
+fn assigned_values<'a>(
+    statements: &'a [LabeledSequentialStatement],
+    signals: &[String],
+    skip: Option<&str>,
+    values: &mut Vec<&'a WithPos<Expression>>,
+) {
+    for statement in statements.iter() {
+        match statement.statement {
+            SequentialStatement::SignalAssignment(ref assignment) => {
+                let assigns = target_name(&assignment.target.item).is_some_and(|name| {
+                    signals
+                        .iter()
+                        .any(|signal| signal.eq_ignore_ascii_case(&name))
+                });
+                if assigns {
+                    values.extend(rhs_values(&assignment.rhs));
+                }
+            }
+            SequentialStatement::If(ref ifs) => {
+                for conditional in ifs.conditionals.iter() {
+                    assigned_values(&conditional.item, signals, skip, values);
+                }
+                if let Some(ref statements) = ifs.else_item {
+                    assigned_values(statements, signals, skip, values);
+                }
+            }
+            SequentialStatement::Case(ref case) => {
+                let is_skipped = simple_name(&case.expression.item)
+                    .is_some_and(|name| skip.is_some_and(|skip| skip.eq_ignore_ascii_case(&name)));
+                if !is_skipped {
+                    for alternative in case.alternatives.iter() {
+                        assigned_values(&alternative.item, signals, skip, values);
+                    }
+                }
+            }
+            SequentialStatement::Loop(ref lp) => {
+                assigned_values(&lp.statements, signals, skip, values);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The first case statement on one of the signals within the statements
+fn find_case<'a>(
+    statements: &'a [LabeledSequentialStatement],
+    signals: &[String],
+) -> Option<&'a CaseStatement> {
+    for statement in statements.iter() {
+        let found = match statement.statement {
+            SequentialStatement::Case(ref case) => {
+                let on_signal = simple_name(&case.expression.item).is_some_and(|name| {
+                    signals
+                        .iter()
+                        .any(|signal| signal.eq_ignore_ascii_case(&name))
+                });
+                if on_signal {
+                    return Some(case);
+                }
+                case.alternatives
+                    .iter()
+                    .find_map(|alternative| find_case(&alternative.item, signals))
+            }
+            SequentialStatement::If(ref ifs) => ifs
+                .conditionals
+                .iter()
+                .map(|conditional| &conditional.item)
+                .chain(ifs.else_item.iter())
+                .find_map(|statements| find_case(statements, signals)),
+            SequentialStatement::Loop(ref lp) => find_case(&lp.statements, signals),
+            _ => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+/// The enumeration types of the declarations by their lower case name
+fn enumeration_types(decl: &[Declaration], types: &mut Vec<(String, Vec<(String, SrcPos)>)>) {
+    for declaration in decl.iter() {
+        if let Declaration::Type(ref type_decl) = declaration {
+            if let TypeDefinition::Enumeration(ref literals) = type_decl.def {
+                let literals = literals
+                    .iter()
+                    .filter_map(|literal| match literal.item {
+                        EnumerationLiteral::Identifier(ref symbol) => {
+                            Some((symbol.name_utf8(), literal.pos.clone()))
+                        }
+                        EnumerationLiteral::Character(..) => None,
+                    })
+                    .collect();
+                types.push((type_decl.ident.item.name_utf8().to_lowercase(), literals));
+            }
+        }
+    }
+}
+
+/// A signal of an enumeration type of the architecture
+struct StateSignal<'a> {
+    name: String,
+    states: &'a [(String, SrcPos)],
+    initial_value: Option<&'a WithPos<Expression>>,
+}
+
+fn state_machine(
+    architecture: &UnitId,
+    signal: &StateSignal,
+    processes: &[ProcessStructure],
+) -> Option<StateMachine> {
+    let is_registered = |process: &ProcessStructure, name: &str| {
+        process.is_clocked()
+            && process
+                .registered
+                .iter()
+                .any(|(registered, _)| registered.eq_ignore_ascii_case(name))
+    };
+    let clocked: Vec<&ProcessStructure> = processes
+        .iter()
+        .filter(|process| is_registered(process, &signal.name))
+        .collect();
+    if clocked.is_empty() {
+        return None;
+    }
+
+    let state = vec![signal.name.clone()];
+    // The signals registered as the state such as next_state
+    let mut names = state.clone();
+    for process in clocked.iter() {
+        let mut values = Vec::new();
+        assigned_values(&process.process.statements, &state, None, &mut values);
+        for value in values {
+            if let Some(name) = simple_name(&value.item) {
+                let is_literal = state_literal(&value.item, signal.states).is_some();
+                if !is_literal && !names.iter().any(|known| known.eq_ignore_ascii_case(&name)) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    let case = processes
+        .iter()
+        .find_map(|process| find_case(&process.process.statements, &state))?;
+
+    let mut initial = Vec::new();
+    for process in clocked.iter() {
+        let mut values = Vec::new();
+        assigned_values(
+            &process.process.statements,
+            &state,
+            Some(&signal.name),
+            &mut values,
+        );
+        for value in values {
+            if let Some(state) = state_literal(&value.item, signal.states) {
+                if !initial.contains(&state) {
+                    initial.push(state);
+                }
+            }
+        }
+    }
+    if initial.is_empty() {
+        let state = signal
+            .initial_value
+            .and_then(|value| state_literal(&value.item, signal.states))
+            .or_else(|| signal.states.first().map(|(state, _)| state.clone()));
+        initial.extend(state);
+    }
+
+    let mut choices = Vec::new();
+    let mut has_others = false;
+    for alternative in case.alternatives.iter() {
+        for choice in alternative.choices.iter() {
+            match choice {
+                Choice::Expression(ref expr) => {
+                    if let Some(state) = state_literal(&expr.item, signal.states) {
+                        choices.push((state, expr.pos.clone()));
+                    }
+                }
+                Choice::Others => has_others = true,
+                Choice::DiscreteRange(..) => {}
+            }
+        }
+    }
+
+    let mut transitions = Vec::new();
+    for alternative in case.alternatives.iter() {
+        let from: Vec<String> = alternative
+            .choices
+            .iter()
+            .flat_map(|choice| match choice {
+                Choice::Expression(ref expr) => state_literal(&expr.item, signal.states)
+                    .into_iter()
+                    .collect(),
+                Choice::Others => signal
+                    .states
+                    .iter()
+                    .filter(|(state, _)| !choices.iter().any(|(choice, _)| choice == state))
+                    .map(|(state, _)| state.clone())
+                    .collect(),
+                Choice::DiscreteRange(..) => Vec::new(),
+            })
+            .collect();
+        let mut values = Vec::new();
+        assigned_values(&alternative.item, &names, None, &mut values);
+        for value in values {
+            if let Some(to) = state_literal(&value.item, signal.states) {
+                for from in from.iter() {
+                    transitions.push(Transition {
+                        from: from.clone(),
+                        to: to.clone(),
+                        pos: value.pos.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Some(StateMachine {
+        architecture: architecture.clone(),
+        signal: signal.name.clone(),
+        pos: case.expression.pos.clone(),
+        states: signal.states.to_vec(),
+        choices,
+        initial,
+        transitions,
+        has_others,
+    })
+}
+
+/// The state machines of the architectures of the files in the order of the files
+pub fn state_machines(files: &[&SourceFile]) -> Vec<StateMachine> {
+    let mut package_types = Vec::new();
+    let mut architectures = Vec::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            match design_unit.library_unit {
+                LibraryUnit::PackageDeclaration { ref decl, .. } => {
+                    enumeration_types(decl, &mut package_types);
+                }
+                LibraryUnit::ArchitectureBody { ref decl, .. } => architectures.push(decl),
+                _ => {}
+            }
+        }
+    }
+
+    let mut machines = Vec::new();
+    for (decl, structure) in architectures
+        .into_iter()
+        .zip(architecture_structures(files))
+    {
+        let mut types = package_types.clone();
+        enumeration_types(decl, &mut types);
+        for declaration in decl.iter() {
+            let object = match declaration {
+                Declaration::Object(ref object) if object.class == ObjectClass::Signal => object,
+                _ => continue,
+            };
+            let type_name = match object.subtype_indication.type_mark.last() {
+                Some(ident) => ident.item.name_utf8().to_lowercase(),
+                None => continue,
+            };
+            // Types of the architecture hide those of the packages
+            let states = match types.iter().rev().find(|(name, _)| *name == type_name) {
+                Some((_, states)) if !states.is_empty() => states,
+                _ => continue,
+            };
+            let signal = StateSignal {
+                name: object.ident.item.name_utf8(),
+                states,
+                initial_value: object.expression.as_deref(),
+            };
+            machines.extend(state_machine(
+                &structure.architecture,
+                &signal,
+                &structure.processes,
+            ));
+        }
+    }
+    machines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn one_and_two_process_state_machines() {
+        let mut project = TempProject::new();
+        let code = "
+package types is
+  type mode_t is (stopped, running);
+end package;
+
+use work.types.all;
+
+entity ent is
+end entity;
+
+architecture rtl of ent is
+  type state_t is (idle, run, done, error);
+  signal state, next_state : state_t;
+  signal mode : mode_t := running;
+begin
+  reg : process (clk, rst)
+  begin
+    if rst = '1' then
+      state <= idle;
+    elsif rising_edge(clk) then
+      state <= next_state;
+    end if;
+  end process;
+
+  comb : process (state, start)
+  begin
+    next_state <= state;
+    case state is
+      when idle =>
+        if start = '1' then
+          next_state <= run;
+        end if;
+      when run =>
+        next_state <= done;
+      when done | error =>
+        null;
+    end case;
+  end process;
+
+  toggle : process (clk)
+  begin
+    if rising_edge(clk) then
+      case mode is
+        when stopped => mode <= running;
+        when others => mode <= stopped;
+      end case;
+    end if;
+  end process;
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let machines = state_machines(&project.project.files());
+        assert_eq!(machines.len(), 2);
+
+        let fsm = &machines[0];
+        assert_eq!(fsm.signal, "state");
+        assert_eq!(fsm.pos.start, code.find("state is").unwrap());
+        assert_eq!(fsm.initial, vec!["idle"]);
+        assert!(!fsm.has_others);
+        let transitions: Vec<(&str, &str)> = fsm
+            .transitions
+            .iter()
+            .map(|transition| (transition.from.as_str(), transition.to.as_str()))
+            .collect();
+        assert_eq!(transitions, vec![("idle", "run"), ("run", "done")]);
+        assert_eq!(fsm.reachable_states(), vec!["idle", "run", "done"]);
+        assert_eq!(fsm.unreachable_states(), vec!["error"]);
+        assert_eq!(fsm.states_without_exit(), vec!["done"]);
+        assert_eq!(
+            fsm.to_dot(),
+            "digraph \"lib.ent(rtl).state\" {
+    \"idle\" [shape=doublecircle];
+    \"run\";
+    \"done\";
+    \"error\";
+    \"idle\" -> \"run\";
+    \"run\" -> \"done\";
+}
+"
+        );
+
+        let fsm = &machines[1];
+        assert_eq!(fsm.signal, "mode");
+        assert_eq!(fsm.initial, vec!["running"]);
+        assert!(fsm.has_others);
+        let transitions: Vec<(&str, &str)> = fsm
+            .transitions
+            .iter()
+            .map(|transition| (transition.from.as_str(), transition.to.as_str()))
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![("stopped", "running"), ("running", "stopped")]
+        );
+        assert!(fsm.unreachable_states().is_empty());
+        assert!(fsm.states_without_exit().is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The values of static expressions of literals, constants and generics
+//!
+//! Only universal integers, booleans, characters and strings are evaluated, any other
+//! expression, such as a function call, is not static. The generics of an architecture
+//! have the values of each instance of its entity within the project, or their defaults
+//! when the entity is not instantiated
+
+use ast::{
+    AbstractLiteral, ActualPart, AssociationElement, Binary, Declaration, Direction, DiscreteRange,
+    Expression, InterfaceDeclaration, LabeledConcurrentStatement, LibraryUnit, Literal, Name,
+    ObjectClass, Range, SubtypeConstraint, SubtypeIndication, Unary,
+};
+use instance_tree::InstanceHierarchy;
+use library::{symbol_key, UnitId};
+use name_resolution::entity_interface;
+use project::SourceFile;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Value {
+    Boolean(bool),
+    Integer(i64),
+    Character(u8),
+    String(Vec<u8>),
+}
+
+/// The values of constants and generics by their lower case names
+pub type Constants = BTreeMap<String, Value>;
+
+/// The value of a static expression
+pub fn static_value(expr: &Expression, constants: &Constants) -> Option<Value> {
+    match expr {
+        Expression::Literal(ref literal) => match literal {
+            Literal::AbstractLiteral(AbstractLiteral::Integer(value)) => {
+                Some(Value::Integer(*value))
+            }
+            Literal::Character(chr) => Some(Value::Character(*chr)),
+            Literal::String(ref string) => Some(Value::String(string.bytes.clone())),
+            _ => None,
+        },
+        Expression::Name(ref name) => match **name {
+            Name::Simple(ref symbol) => match symbol_key(symbol).as_str() {
+                "true" => Some(Value::Boolean(true)),
+                "false" => Some(Value::Boolean(false)),
+                key => constants.get(key).cloned(),
+            },
+            Name::CharacterLiteral(chr) => Some(Value::Character(chr)),
+            _ => None,
+        },
+        Expression::Unary(op, ref operand) => match (op, static_value(&operand.item, constants)?) {
+            (Unary::Not, Value::Boolean(value)) => Some(Value::Boolean(!value)),
+            (Unary::Minus, Value::Integer(value)) => value.checked_neg().map(Value::Integer),
+            (Unary::Plus, Value::Integer(value)) => Some(Value::Integer(value)),
+            (Unary::Abs, Value::Integer(value)) => value.checked_abs().map(Value::Integer),
+            _ => None,
+        },
+        Expression::Binary(op, ref left, ref right) => {
+            let left = static_value(&left.item, constants)?;
+            // The right operand of a short circuit operator is not needed
+            match (op, &left) {
+                (Binary::And, Value::Boolean(false)) => return Some(left),
+                (Binary::Or, Value::Boolean(true)) => return Some(left),
+                _ => {}
+            }
+            binary(*op, left, static_value(&right.item, constants)?)
+        }
+        _ => None,
+    }
+}
+
+fn binary(op: Binary, left: Value, right: Value) -> Option<Value> {
+    match op {
+        Binary::EQ => return Some(Value::Boolean(left == right)),
+        Binary::NE => return Some(Value::Boolean(left != right)),
+        _ => {}
+    }
+    let value = match (left, right) {
+        (Value::Boolean(left), Value::Boolean(right)) => Value::Boolean(match op {
+            Binary::And => left && right,
+            Binary::Or => left || right,
+            Binary::Nand => !(left && right),
+            Binary::Nor => !(left || right),
+            Binary::Xor => left != right,
+            Binary::Xnor => left == right,
+            _ => {
+                return None;
+            }
+        }),
+        (Value::Integer(left), Value::Integer(right)) => match op {
+            Binary::LT => Value::Boolean(left < right),
+            Binary::LTE => Value::Boolean(left <= right),
+            Binary::GT => Value::Boolean(left > right),
+            Binary::GTE => Value::Boolean(left >= right),
+            _ => Value::Integer(integer_binary(op, left, right)?),
+        },
+        (Value::Character(left), Value::Character(right)) => match op {
+            Binary::LT => Value::Boolean(left < right),
+            Binary::LTE => Value::Boolean(left <= right),
+            Binary::GT => Value::Boolean(left > right),
+            Binary::GTE => Value::Boolean(left >= right),
+            _ => {
+                return None;
+            }
+        },
+        _ => {
+            return None;
+        }
+    };
+    Some(value)
+}
+
+/// The integer result of an arithmetic operation, None when it overflows, divides by zero
+/// or is not arithmetic
+pub fn integer_binary(op: Binary, left: i64, right: i64) -> Option<i64> {
+    match op {
+        Binary::Plus => left.checked_add(right),
+        Binary::Minus => left.checked_sub(right),
+        Binary::Times => left.checked_mul(right),
+        Binary::Div => left.checked_div(right),
+        Binary::Rem => left.checked_rem(right),
+        // LRM 9.2.7 The result of mod has the sign of the right operand
+        Binary::Mod => {
+            let value = left.checked_rem(right)?;
+            if value != 0 && (value < 0) != (right < 0) {
+                value.checked_add(right)
+            } else {
+                Some(value)
+            }
+        }
+        Binary::Pow => left.checked_pow(u32::try_from(right).ok()?),
+        _ => None,
+    }
+}
+
+/// Adds the constants of the declarations with a static value, other objects and the
+/// parameters of subprograms hide constants with the same name
+pub fn declare_constants(decls: &[Declaration], constants: &mut Constants) {
+    for decl in decls.iter() {
+        if let Declaration::Object(ref object) = decl {
+            let key = symbol_key(&object.ident.item);
+            let value = match (object.class, &object.expression) {
+                (ObjectClass::Constant, Some(ref expr)) => static_value(&expr.item, constants),
+                _ => None,
+            };
+            match value {
+                Some(value) => constants.insert(key, value),
+                None => constants.remove(&key),
+            };
+        }
+    }
+}
+
+/// Removes the constants hidden by the objects of an interface list
+pub fn hide_interface(interface: &[InterfaceDeclaration], constants: &mut Constants) {
+    for decl in interface.iter() {
+        if let InterfaceDeclaration::Object(ref object) = decl {
+            constants.remove(&symbol_key(&object.ident.item));
+        }
+    }
+}
+
+/// The values of the generics with their actuals or their defaults when not associated
+pub fn generic_values(
+    generics: &[InterfaceDeclaration],
+    generic_map: &[AssociationElement],
+) -> Constants {
+    generic_values_in(generics, generic_map, &Constants::new())
+}
+
+/// The values of the generics where the actuals are evaluated with the constants of the
+/// enclosing architecture
+pub fn generic_values_in(
+    generics: &[InterfaceDeclaration],
+    generic_map: &[AssociationElement],
+    enclosing: &Constants,
+) -> Constants {
+    let mut constants = Constants::new();
+    for (i, decl) in generics.iter().enumerate() {
+        let object = match decl {
+            InterfaceDeclaration::Object(ref object) => object,
+            _ => continue,
+        };
+        let key = symbol_key(&object.ident.item);
+        let actual = generic_map
+            .iter()
+            .enumerate()
+            .find(|(j, assoc)| match assoc.formal {
+                Some(ref formal) => match formal.item {
+                    Name::Simple(ref symbol) => symbol_key(symbol) == key,
+                    _ => false,
+                },
+                None => *j == i,
+            })
+            .map(|(_, assoc)| match assoc.actual.item {
+                ActualPart::Expression(ref expr) => Some(expr),
+                ActualPart::Open => None,
+            });
+        let value = match actual {
+            Some(expr) => expr.and_then(|expr| static_value(expr, enclosing)),
+            None => object
+                .expression
+                .as_ref()
+                .and_then(|expr| static_value(&expr.item, &Constants::new())),
+        };
+        if let Some(value) = value {
+            constants.insert(key, value);
+        }
+    }
+    constants
+}
+
+/// The number of bits of a scalar, integer or bit vector subtype with static bounds
+pub fn bit_width(subtype: &SubtypeIndication, constants: &Constants) -> Option<i64> {
+    match symbol_key(&subtype.type_mark.last()?.item).as_str() {
+        "std_logic" | "std_ulogic" | "bit" => Some(1),
+        "integer" | "natural" | "positive" => Some(32),
+        "std_logic_vector" | "std_ulogic_vector" | "bit_vector" | "unsigned" | "signed" => {
+            let ranges = match subtype.constraint {
+                Some(SubtypeConstraint::Array(ref ranges, None)) => ranges,
+                _ => {
+                    return None;
+                }
+            };
+            let mut width: i64 = 1;
+            for range in ranges.iter() {
+                let constraint = match range {
+                    DiscreteRange::Range(Range::Range(ref constraint)) => constraint,
+                    _ => {
+                        return None;
+                    }
+                };
+                match (
+                    static_value(&constraint.left_expr.item, constants)?,
+                    static_value(&constraint.right_expr.item, constants)?,
+                ) {
+                    (Value::Integer(left), Value::Integer(right)) => {
+                        width = left
+                            .checked_sub(right)?
+                            .checked_abs()?
+                            .checked_add(1)?
+                            .checked_mul(width)?;
+                    }
+                    _ => {
+                        return None;
+                    }
+                }
+            }
+            Some(width)
+        }
+        _ => None,
+    }
+}
+
+/// The boolean value of a condition which is the same for every set of constants
+pub fn static_condition(expr: &Expression, instances: &[Constants]) -> Option<bool> {
+    let mut condition = None;
+    for constants in instances.iter() {
+        let value = match static_value(expr, constants) {
+            Some(Value::Boolean(value)) => value,
+            _ => {
+                return None;
+            }
+        };
+        if condition.is_some() && condition != Some(value) {
+            return None;
+        }
+        condition = Some(value);
+    }
+    condition
+}
+
+/// Whether a range of static bounds is null such as 0 to -1
+pub fn is_null_range(
+    left: &Expression,
+    direction: Direction,
+    right: &Expression,
+    constants: &Constants,
+) -> Option<bool> {
+    match (
+        static_value(left, constants)?,
+        static_value(right, constants)?,
+    ) {
+        (Value::Integer(left), Value::Integer(right)) => Some(match direction {
+            Direction::Ascending => left > right,
+            Direction::Descending => left < right,
+        }),
+        _ => None,
+    }
+}
+
+/// An architecture with the constants of each instance of its entity
+pub struct StaticArchitecture<'a> {
+    pub architecture: UnitId,
+    pub decl: &'a [Declaration],
+    pub statements: &'a [LabeledConcurrentStatement],
+    /// The ports of the entity
+    pub ports: &'a [InterfaceDeclaration],
+    /// The generics of each instance of the entity and the constants of the architecture
+    pub instances: Vec<Constants>,
+}
+
+/// The architectures of the files in the order of the files
+pub fn static_architectures<'a>(files: &[&'a SourceFile]) -> Vec<StaticArchitecture<'a>> {
+    let hierarchy = InstanceHierarchy::new(files);
+    let instantiations = hierarchy.instantiations();
+    let mut architectures = Vec::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            if let LibraryUnit::ArchitectureBody {
+                ref ident,
+                ref entity_name,
+                ref decl,
+                ref statements,
+            } = design_unit.library_unit
+            {
+                let entity = UnitId::primary(&file.library_name, &symbol_key(&entity_name.item));
+                let (generics, ports) = match hierarchy.entity(&entity) {
+                    Some(library_unit) => entity_interface(library_unit),
+                    None => (&[][..], &[][..]),
+                };
+                let mut instances: Vec<Constants> = instantiations
+                    .iter()
+                    .filter(|instantiation| instantiation.entity == entity)
+                    .map(|instantiation| {
+                        generic_values(generics, &instantiation.instance.generic_map)
+                    })
+                    .collect();
+                if instances.is_empty() {
+                    instances.push(generic_values(generics, &[]));
+                }
+                for constants in instances.iter_mut() {
+                    declare_constants(decl, constants);
+                }
+                architectures.push(StaticArchitecture {
+                    architecture: UnitId::architecture(
+                        &file.library_name,
+                        &entity_name.item.name_utf8(),
+                        &ident.item.name_utf8(),
+                    ),
+                    decl,
+                    statements,
+                    ports,
+                    instances,
+                });
+            }
+        }
+    }
+    architectures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expression::parse_expression;
+    use test_util::{with_stream, TempProject};
+
+    fn value(code: &str, constants: &Constants) -> Option<Value> {
+        let (_, expr) = with_stream(parse_expression, code);
+        static_value(&expr.item, constants)
+    }
+
+    #[test]
+    fn static_expressions() {
+        let mut constants = Constants::new();
+        constants.insert("width".to_owned(), Value::Integer(8));
+        constants.insert("mode".to_owned(), Value::String(b"fast".to_vec()));
+
+        assert_eq!(value("2**WIDTH - 1", &constants), Some(Value::Integer(255)));
+        assert_eq!(value("1 + 2 * width", &constants), Some(Value::Integer(17)));
+        assert_eq!(value("(-7) mod 3", &constants), Some(Value::Integer(2)));
+        assert_eq!(value("(-7) rem 3", &constants), Some(Value::Integer(-1)));
+        assert_eq!(
+            value("mode = \"fast\" and width > 4", &constants),
+            Some(Value::Boolean(true))
+        );
+        assert_eq!(
+            value("not (width /= 8)", &constants),
+            Some(Value::Boolean(true))
+        );
+        assert_eq!(
+            value("false and sig = '1'", &constants),
+            Some(Value::Boolean(false))
+        );
+        assert_eq!(value("'1' = '0'", &constants), Some(Value::Boolean(false)));
+        assert_eq!(value("sig = '1'", &constants), None);
+        assert_eq!(value("width / 0", &constants), None);
+        assert_eq!(value("f(width)", &constants), None);
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        assert_eq!(integer_binary(Binary::Mod, 7, -3), Some(-2));
+        assert_eq!(integer_binary(Binary::Mod, -6, 3), Some(0));
+        assert_eq!(integer_binary(Binary::Rem, 7, -3), Some(1));
+        assert_eq!(integer_binary(Binary::Pow, 2, 10), Some(1024));
+        assert_eq!(integer_binary(Binary::Pow, 2, -1), None);
+        assert_eq!(integer_binary(Binary::Times, i64::MAX, 2), None);
+        assert_eq!(integer_binary(Binary::Mod, 1, 0), None);
+        assert_eq!(integer_binary(Binary::LT, 1, 2), None);
+    }
+
+    #[test]
+    fn generics_of_each_instance() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+entity ent is
+  generic (width : natural := 8; fast : boolean := false);
+end entity;
+
+architecture rtl of ent is
+  constant depth : natural := 2 * width;
+  signal data : natural;
+begin
+end architecture;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+begin
+  narrow : entity work.ent generic map (4);
+  wide : entity work.ent generic map (fast => true, width => 16);
+end architecture;",
+        );
+        let files = project.project.files();
+        let architectures = static_architectures(&files);
+        let constants = |architecture: &StaticArchitecture| -> Vec<Vec<(String, Value)>> {
+            architecture
+                .instances
+                .iter()
+                .map(|constants| constants.clone().into_iter().collect())
+                .collect()
+        };
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(architectures.len(), 2);
+        assert_eq!(
+            architectures[0].architecture,
+            UnitId::architecture("lib", "ent", "rtl")
+        );
+        assert_eq!(
+            constants(&architectures[0]),
+            vec![
+                vec![
+                    (string("depth"), Value::Integer(8)),
+                    (string("fast"), Value::Boolean(false)),
+                    (string("width"), Value::Integer(4)),
+                ],
+                vec![
+                    (string("depth"), Value::Integer(32)),
+                    (string("fast"), Value::Boolean(true)),
+                    (string("width"), Value::Integer(16)),
+                ],
+            ]
+        );
+        assert_eq!(constants(&architectures[1]), vec![vec![]]);
+    }
+}
@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Counts of the design units, processes and lines of code of each library
+
+use ast::{ConcurrentStatement, LabeledConcurrentStatement, LibraryUnit};
+use project::SourceFile;
+use std::collections::BTreeMap;
+
+#[derive(PartialEq, Debug, Default, Clone, Serialize)]
+pub struct LibraryStatistics {
+    pub library_name: String,
+    pub files: usize,
+    pub entities: usize,
+    pub architectures: usize,
+    /// Package declarations and package instances
+    pub packages: usize,
+    pub processes: usize,
+    /// Lines which are neither blank nor only a comment
+    pub lines_of_code: usize,
+}
+
+/// The statistics of all libraries together with the time spent on each step
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct ProjectStatistics {
+    pub libraries: Vec<LibraryStatistics>,
+    pub parse_seconds: f64,
+    pub analysis_seconds: f64,
+}
+
+impl LibraryStatistics {
+    fn add_file(&mut self, file: &SourceFile) {
+        self.files += 1;
+        for design_unit in file.design_file.design_units.iter() {
+            match design_unit.library_unit {
+                LibraryUnit::EntityDeclaration { .. } => self.entities += 1,
+                LibraryUnit::ArchitectureBody { ref statements, .. } => {
+                    self.architectures += 1;
+                    self.processes += count_processes(statements);
+                }
+                LibraryUnit::PackageDeclaration { .. } | LibraryUnit::PackageInstance(..) => {
+                    self.packages += 1
+                }
+                _ => {}
+            }
+        }
+        if let Ok(contents) = file.source.contents() {
+            self.lines_of_code += lines_of_code(&contents.bytes);
+        }
+    }
+}
+
+fn count_processes(statements: &[LabeledConcurrentStatement]) -> usize {
+    statements
+        .iter()
+        .map(|statement| match statement.statement {
+            ConcurrentStatement::Process(..) => 1,
+            ConcurrentStatement::Block(ref block) => count_processes(&block.statements),
+            ConcurrentStatement::ForGenerate(ref gen) => count_processes(&gen.body.statements),
+            ConcurrentStatement::IfGenerate(ref gen) => {
+                gen.conditionals
+                    .iter()
+                    .map(|conditional| count_processes(&conditional.item.statements))
+                    .sum::<usize>()
+                    + gen
+                        .else_item
+                        .as_ref()
+                        .map(|body| count_processes(&body.statements))
+                        .unwrap_or(0)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+fn lines_of_code(bytes: &[u8]) -> usize {
+    bytes
+        .split(|byte| *byte == b'\n')
+        .filter(|line| {
+            let start = line.iter().position(|byte| !byte.is_ascii_whitespace());
+            match start {
+                Some(start) => !line[start..].starts_with(b"--"),
+                None => false,
+            }
+        })
+        .count()
+}
+
+/// The statistics of each library sorted by library name
+pub fn library_statistics(files: &[&SourceFile]) -> Vec<LibraryStatistics> {
+    let mut libraries: BTreeMap<&str, LibraryStatistics> = BTreeMap::new();
+    for file in files {
+        libraries
+            .entry(&file.library_name)
+            .or_insert_with(|| LibraryStatistics {
+                library_name: file.library_name.clone(),
+                ..LibraryStatistics::default()
+            })
+            .add_file(file);
+    }
+    libraries.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    #[test]
+    fn counts_of_each_library() {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "ent.vhd",
+            "
+-- The entity
+entity ent is
+end entity;
+
+architecture rtl of ent is
+begin
+  main: process
+  begin
+  end process;
+  gen: for i in 0 to 1 generate
+    process
+    begin
+    end process;
+  end generate;
+end architecture;",
+        );
+        project.add_file(
+            "lib",
+            "pkg.vhd",
+            "
+package pkg is
+end package;",
+        );
+        project.add_file(
+            "other",
+            "pkg2.vhd",
+            "
+package pkg2 is
+end package;",
+        );
+
+        let files = project.project.files();
+        let stats = library_statistics(&files);
+        assert_eq!(
+            stats,
+            vec![
+                LibraryStatistics {
+                    library_name: "lib".to_owned(),
+                    files: 2,
+                    entities: 1,
+                    architectures: 1,
+                    packages: 1,
+                    processes: 2,
+                    lines_of_code: 15,
+                },
+                LibraryStatistics {
+                    library_name: "other".to_owned(),
+                    files: 1,
+                    entities: 0,
+                    architectures: 0,
+                    packages: 1,
+                    processes: 0,
+                    lines_of_code: 2,
+                }
+            ]
+        );
+    }
+}
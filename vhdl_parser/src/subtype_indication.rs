@@ -30,10 +30,20 @@ fn parse_record_element_constraint(stream: &mut TokenStream) -> ParseResult<Elem
     Ok(ElementConstraint { ident, constraint })
 }
 
+/// Each element constraint is one more level of nesting
 fn parse_composite_constraint(stream: &mut TokenStream) -> ParseResult<SubtypeConstraint> {
+    stream.nested(|stream| {
+        let token = stream.expect_kind(LeftPar)?;
+        stream.enter(&token)?;
+        parse_composite_constraint_leftpar_known(stream)
+    })
+}
+
+fn parse_composite_constraint_leftpar_known(
+    stream: &mut TokenStream,
+) -> ParseResult<SubtypeConstraint> {
     // There is no finite lookahead that can differentiate
     // between array and record element constraint
-    stream.expect_kind(LeftPar)?;
     let state = stream.state();
     let mut initial_constraint = parse_discrete_range(stream);
     if let Some(token) = stream.peek()? {
@@ -95,18 +105,27 @@ pub fn parse_subtype_constraint(
     }
 }
 
+/// Each record element resolution is one more level of nesting
 pub fn parse_element_resolution_indication(
     stream: &mut TokenStream,
 ) -> ParseResult<ResolutionIndication> {
-    stream.expect_kind(LeftPar)?;
+    stream.nested(|stream| {
+        let token = stream.expect_kind(LeftPar)?;
+        stream.enter(&token)?;
+        parse_element_resolution_indication_leftpar_known(stream)
+    })
+}
 
+fn parse_element_resolution_indication_leftpar_known(
+    stream: &mut TokenStream,
+) -> ParseResult<ResolutionIndication> {
     let first_ident = stream.expect_ident()?;
     let token = stream.peek_expect()?;
 
     Ok(try_token_kind!(
         token,
         Dot | RightPar => {
-            let selected_name = vec![first_ident];
+            let selected_name = smallvec![first_ident];
             stream.expect_kind(RightPar)?;
             ResolutionIndication::ArrayElement(selected_name)
         },
@@ -190,7 +209,7 @@ mod tests {
             subtype,
             SubtypeIndication {
                 resolution: ResolutionIndication::Unresolved,
-                type_mark: vec![util.ident("std_logic")],
+                type_mark: smallvec![util.ident("std_logic")],
                 constraint: None
             }
         );
@@ -203,7 +222,7 @@ mod tests {
             subtype,
             SubtypeIndication {
                 resolution: ResolutionIndication::FunctionName(util.selected_name("resolve")),
-                type_mark: vec![util.ident("std_logic")],
+                type_mark: smallvec![util.ident("std_logic")],
                 constraint: None
             }
         );
@@ -216,7 +235,7 @@ mod tests {
             subtype,
             SubtypeIndication {
                 resolution: ResolutionIndication::ArrayElement(util.selected_name("resolve")),
-                type_mark: vec![util.ident("integer_vector")],
+                type_mark: smallvec![util.ident("integer_vector")],
                 constraint: None
             }
         );
@@ -237,7 +256,7 @@ mod tests {
             subtype,
             SubtypeIndication {
                 resolution: ResolutionIndication::Record(vec![elem_resolution]),
-                type_mark: vec![util.ident("rec_t")],
+                type_mark: smallvec![util.ident("rec_t")],
                 constraint: None
             }
         );
@@ -284,7 +303,7 @@ mod tests {
                     elem2_resolution,
                     elem3_resolution
                 ]),
-                type_mark: vec![util.ident("rec_t")],
+                type_mark: smallvec![util.ident("rec_t")],
                 constraint: None
             }
         );
@@ -299,7 +318,7 @@ mod tests {
                 resolution: ResolutionIndication::FunctionName(
                     util.selected_name("lib.foo.resolve")
                 ),
-                type_mark: vec![util.ident("std_logic")],
+                type_mark: smallvec![util.ident("std_logic")],
                 constraint: None
             }
         );
@@ -0,0 +1,421 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The declarations, references and instances of a project as an SQL script which
+//! creates and fills a SQLite database for ad-hoc queries, such as by
+//! `rust_hdl sql project.toml | sqlite3 symbols.db`
+//!
+//! The schema is:
+//!
+//! ```sql
+//! -- The VHDL and Verilog source files with the library they belong to
+//! CREATE TABLE files (
+//!   id INTEGER PRIMARY KEY,
+//!   name TEXT NOT NULL,
+//!   library TEXT NOT NULL
+//! );
+//! -- Named entities such as entities, signals and functions and the Verilog modules
+//! -- where kind is as written by rust_hdl such as shared_variable or module
+//! CREATE TABLE declarations (
+//!   id INTEGER PRIMARY KEY,
+//!   name TEXT NOT NULL,
+//!   kind TEXT NOT NULL,
+//!   file_id INTEGER NOT NULL REFERENCES files(id),
+//!   line INTEGER NOT NULL,
+//!   col INTEGER NOT NULL
+//! );
+//! -- Names which refer to a declaration where is_write is 1 for assignment targets and
+//! -- the actuals of out, inout and buffer ports
+//! CREATE TABLE refs (
+//!   declaration_id INTEGER NOT NULL REFERENCES declarations(id),
+//!   file_id INTEGER NOT NULL REFERENCES files(id),
+//!   line INTEGER NOT NULL,
+//!   col INTEGER NOT NULL,
+//!   is_write INTEGER NOT NULL
+//! );
+//! -- The instantiations within each architecture where kind is entity, module or
+//! -- unbound and declaration_id is that of the entity or module when within the project
+//! CREATE TABLE instances (
+//!   library TEXT NOT NULL,
+//!   entity TEXT NOT NULL,
+//!   architecture TEXT NOT NULL,
+//!   label TEXT NOT NULL,
+//!   file_id INTEGER NOT NULL REFERENCES files(id),
+//!   line INTEGER NOT NULL,
+//!   col INTEGER NOT NULL,
+//!   kind TEXT NOT NULL,
+//!   unit TEXT NOT NULL,
+//!   declaration_id INTEGER REFERENCES declarations(id)
+//! );
+//! ```
+//!
+//! Lines and columns are one based, names of declarations are as written while the
+//! library, entity, architecture and unit of instances are in lower case
+
+use instance_tree::InstanceHierarchy;
+use library::{UnitId, UnitKey};
+use name_resolution::NamedEntity;
+use project::Project;
+use source::SrcPos;
+use std::collections::HashMap;
+
+const SCHEMA: &str = "\
+CREATE TABLE files (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  library TEXT NOT NULL
+);
+CREATE TABLE declarations (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  file_id INTEGER NOT NULL REFERENCES files(id),
+  line INTEGER NOT NULL,
+  col INTEGER NOT NULL
+);
+CREATE TABLE refs (
+  declaration_id INTEGER NOT NULL REFERENCES declarations(id),
+  file_id INTEGER NOT NULL REFERENCES files(id),
+  line INTEGER NOT NULL,
+  col INTEGER NOT NULL,
+  is_write INTEGER NOT NULL
+);
+CREATE TABLE instances (
+  library TEXT NOT NULL,
+  entity TEXT NOT NULL,
+  architecture TEXT NOT NULL,
+  label TEXT NOT NULL,
+  file_id INTEGER NOT NULL REFERENCES files(id),
+  line INTEGER NOT NULL,
+  col INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  unit TEXT NOT NULL,
+  declaration_id INTEGER REFERENCES declarations(id)
+);
+CREATE INDEX refs_declaration ON refs(declaration_id);
+CREATE INDEX declarations_name ON declarations(name COLLATE NOCASE);
+";
+
+/// A string literal of SQL
+fn quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "''"))
+}
+
+/// The rows of the tables as the literals of each value
+#[derive(Default)]
+struct Tables {
+    /// The id of each file by its name
+    files: HashMap<String, usize>,
+    /// The id of each declaration by its file, start and kind
+    declarations: HashMap<(String, usize, String), usize>,
+    /// The id of each entity and module by its library and lower case name
+    units: HashMap<(String, String), usize>,
+    statements: Vec<String>,
+}
+
+impl Tables {
+    fn add_file(&mut self, name: &str, library_name: &str) {
+        let id = self.files.len() + 1;
+        self.files.insert(name.to_owned(), id);
+        self.statements.push(format!(
+            "INSERT INTO files VALUES ({}, {}, {});",
+            id,
+            quote(name),
+            quote(library_name)
+        ));
+    }
+
+    /// The file id, line and column of the position when it is within a file of the project
+    fn location(&self, pos: &SrcPos) -> Option<(usize, usize, usize)> {
+        let file_id = *self.files.get(pos.source.file_name()?)?;
+        let (line, column) = pos.line_and_column();
+        Some((file_id, line, column))
+    }
+
+    fn declaration_key(pos: &SrcPos, kind: &str) -> (String, usize, String) {
+        (
+            pos.source.file_name().unwrap_or("").to_owned(),
+            pos.start,
+            kind.to_owned(),
+        )
+    }
+
+    fn add_declaration(&mut self, name: &str, kind: &str, pos: &SrcPos, library_name: &str) {
+        let key = Tables::declaration_key(pos, kind);
+        if self.declarations.contains_key(&key) {
+            return;
+        }
+        let (file_id, line, column) = match self.location(pos) {
+            Some(location) => location,
+            None => {
+                return;
+            }
+        };
+        let id = self.declarations.len() + 1;
+        self.declarations.insert(key, id);
+        if kind == "entity" || kind == "module" {
+            self.units
+                .insert((library_name.to_owned(), name.to_lowercase()), id);
+        }
+        self.statements.push(format!(
+            "INSERT INTO declarations VALUES ({}, {}, {}, {}, {}, {});",
+            id,
+            quote(name),
+            quote(kind),
+            file_id,
+            line,
+            column
+        ));
+    }
+
+    fn add_reference(&mut self, entity: &NamedEntity, pos: &SrcPos, is_write: bool) {
+        let key = Tables::declaration_key(&entity.pos, &entity.kind.to_string());
+        if let (Some(id), Some((file_id, line, column))) =
+            (self.declarations.get(&key), self.location(pos))
+        {
+            self.statements.push(format!(
+                "INSERT INTO refs VALUES ({}, {}, {}, {}, {});",
+                id, file_id, line, column, is_write as usize
+            ));
+        }
+    }
+
+    fn add_instance(
+        &mut self,
+        architecture: &UnitId,
+        label: &str,
+        pos: &SrcPos,
+        kind: &str,
+        unit: Option<&UnitId>,
+        unit_name: &str,
+    ) {
+        let architecture_name = match architecture.key {
+            UnitKey::Architecture(_, ref name) => name,
+            _ => {
+                return;
+            }
+        };
+        let (file_id, line, column) = match self.location(pos) {
+            Some(location) => location,
+            None => {
+                return;
+            }
+        };
+        let declaration_id = unit
+            .and_then(|unit| {
+                self.units
+                    .get(&(unit.library_name.clone(), unit.primary_name().to_owned()))
+            })
+            .map_or("NULL".to_owned(), |id| id.to_string());
+        self.statements.push(format!(
+            "INSERT INTO instances VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {});",
+            quote(&architecture.library_name),
+            quote(architecture.primary_name()),
+            quote(architecture_name),
+            quote(label),
+            file_id,
+            line,
+            column,
+            quote(kind),
+            quote(unit_name),
+            declaration_id
+        ));
+    }
+}
+
+/// The script which creates the tables of the schema and inserts the symbols of the project
+pub fn symbol_database_sql(project: &Project) -> String {
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let resolution = project.resolve();
+    let mut tables = Tables::default();
+
+    let mut libraries = HashMap::new();
+    for file in files.iter() {
+        tables.add_file(file.file_name(), &file.library_name);
+        libraries.insert(
+            file.file_name().to_owned(),
+            file.library_name.to_lowercase(),
+        );
+    }
+    for file in verilog_files.iter() {
+        tables.add_file(file.file_name(), &file.library_name);
+    }
+
+    for entity in resolution.declarations() {
+        let library_name = entity
+            .pos
+            .source
+            .file_name()
+            .and_then(|file_name| libraries.get(file_name))
+            .cloned()
+            .unwrap_or_default();
+        let kind = entity.kind.to_string();
+        tables.add_declaration(&entity.designator, &kind, &entity.pos, &library_name);
+    }
+    for file in verilog_files.iter() {
+        for module in file.verilog_file.modules.iter() {
+            tables.add_declaration(
+                &module.name.item,
+                "module",
+                &module.name.pos,
+                &file.library_name.to_lowercase(),
+            );
+        }
+    }
+
+    for reference in resolution.references() {
+        for entity in reference.entities.iter() {
+            tables.add_reference(entity, &reference.pos, reference.is_write);
+        }
+    }
+
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    for instantiation in hierarchy.instantiations() {
+        let unit_name = format!(
+            "{}.{}",
+            instantiation.entity.library_name,
+            instantiation.entity.primary_name()
+        );
+        tables.add_instance(
+            &instantiation.architecture,
+            &instantiation.label,
+            &instantiation.pos,
+            "entity",
+            Some(&instantiation.entity),
+            &unit_name,
+        );
+    }
+    for instantiation in hierarchy.verilog_instantiations() {
+        let unit_name = format!(
+            "{}.{}",
+            instantiation.module.library_name,
+            instantiation.module.primary_name()
+        );
+        tables.add_instance(
+            &instantiation.architecture,
+            &instantiation.label,
+            &instantiation.pos,
+            "module",
+            Some(&instantiation.module),
+            &unit_name,
+        );
+    }
+    for instantiation in hierarchy.unbound_instantiations() {
+        tables.add_instance(
+            &instantiation.architecture,
+            &instantiation.label,
+            &instantiation.pos,
+            "unbound",
+            None,
+            &instantiation.unit_name.to_lowercase(),
+        );
+    }
+
+    let mut sql = String::from("BEGIN TRANSACTION;\n");
+    sql.push_str(SCHEMA);
+    for statement in tables.statements {
+        sql.push_str(&statement);
+        sql.push('\n');
+    }
+    sql.push_str("COMMIT;\n");
+    sql
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+entity top is
+  port (clk : in bit);
+end entity;
+
+architecture rtl of top is
+  signal count : bit;
+begin
+  count <= clk;
+  cnt: entity work.counter port map (clk => clk);
+  ram: entity work.ram port map (clk => clk);
+  buf: component bufg port map (i => clk);
+end architecture;
+
+entity counter is
+  port (clk : in bit);
+end entity;",
+        );
+        project.add_file("lib", "ram.v", "module ram (input clk);\nendmodule\n");
+        project
+    }
+
+    fn rows<'a>(sql: &'a str, table: &str) -> Vec<&'a str> {
+        let prefix = format!("INSERT INTO {} VALUES (", table);
+        sql.lines()
+            .filter_map(|line| line.strip_prefix(prefix.as_str()))
+            .filter_map(|line| line.strip_suffix(");"))
+            .collect()
+    }
+
+    /// The id of the declaration of the row ending with the name, kind and location
+    fn declaration_id(declarations: &[&str], row: &str) -> String {
+        let declaration = declarations
+            .iter()
+            .find(|declaration| declaration.ends_with(row))
+            .unwrap();
+        declaration[..declaration.find(',').unwrap()].to_owned()
+    }
+
+    #[test]
+    fn quotes_strings() {
+        assert_eq!(quote("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn tables_of_project() {
+        let project = project();
+        let sql = symbol_database_sql(&project.project);
+        assert!(sql.starts_with("BEGIN TRANSACTION;\nCREATE TABLE files ("));
+        assert!(sql.ends_with("COMMIT;\n"));
+
+        let files = rows(&sql, "files");
+        assert_eq!(files.len(), 2);
+        assert!(files[0].starts_with("1, '") && files[0].ends_with("top.vhd', 'lib'"));
+        assert!(files[1].starts_with("2, '") && files[1].ends_with("ram.v', 'lib'"));
+
+        let declarations = rows(&sql, "declarations");
+        let top = declaration_id(&declarations, "'top', 'entity', 1, 2, 8");
+        let clk = declaration_id(&declarations, "'clk', 'port', 1, 3, 9");
+        let count = declaration_id(&declarations, "'count', 'signal', 1, 7, 10");
+        let counter = declaration_id(&declarations, "'counter', 'entity', 1, 15, 8");
+        let ram = declaration_id(&declarations, "'ram', 'module', 2, 1, 8");
+        assert_ne!(top, clk);
+
+        let refs = rows(&sql, "refs");
+        assert!(refs.contains(&format!("{}, 1, 9, 3, 1", count).as_str()));
+        assert!(refs.contains(&format!("{}, 1, 9, 12, 0", clk).as_str()));
+
+        assert_eq!(
+            rows(&sql, "instances"),
+            vec![
+                format!(
+                    "'lib', 'top', 'rtl', 'cnt', 1, 10, 3, 'entity', 'lib.counter', {}",
+                    counter
+                ),
+                format!(
+                    "'lib', 'top', 'rtl', 'ram', 1, 11, 3, 'module', 'lib.ram', {}",
+                    ram
+                ),
+                "'lib', 'top', 'rtl', 'buf', 1, 12, 3, 'unbound', 'bufg', NULL".to_owned(),
+            ]
+        );
+    }
+}
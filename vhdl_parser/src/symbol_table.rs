@@ -5,11 +5,14 @@
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
 use latin_1::Latin1String;
+use std::hash::Hasher;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::RwLock;
 
 extern crate fnv;
-use self::fnv::FnvHashMap;
+use self::fnv::{FnvHashMap, FnvHasher};
 
 /// Represents an unique string symbol
 /// The id can be used as a fast comparison key for symbols
@@ -49,17 +52,38 @@ impl PartialEq for Symbol {
     }
 }
 
+/// The number of independently locked parts of the symbol table
+const SHARDS: usize = 16;
+
+type Shard = FnvHashMap<Arc<Latin1String>, Symbol>;
+
 /// A case insensitive symbol table to allocate unique id:s to symbols
 /// which are equal during case insensitive comparison
+/// The table is shared between threads, names are distributed over shards by their
+/// case insensitive hash such that all cases of a name are within the same shard and
+/// threads parsing or querying different names rarely wait for each other
 pub struct SymbolTable {
-    name_to_symbol: RwLock<FnvHashMap<Arc<Latin1String>, Symbol>>,
+    shards: Vec<RwLock<Shard>>,
+    next_id: AtomicUsize,
+}
+
+/// The shard of all cases of the name
+fn shard_index(name: &Latin1String) -> usize {
+    let mut hasher = FnvHasher::default();
+    for chr in name.bytes.iter() {
+        hasher.write_u8(Latin1String::lowercase(*chr));
+    }
+    hasher.finish() as usize % SHARDS
 }
 
 impl SymbolTable {
     /// Create a new symbol table
     pub fn new() -> SymbolTable {
         SymbolTable {
-            name_to_symbol: RwLock::new(FnvHashMap::default()),
+            shards: (0..SHARDS)
+                .map(|_| RwLock::new(FnvHashMap::default()))
+                .collect(),
+            next_id: AtomicUsize::new(0),
         }
     }
 
@@ -77,13 +101,9 @@ impl SymbolTable {
     }
 
     pub fn lookup(&self, name: &Latin1String) -> Option<Symbol> {
-        let name_to_symbol = self.name_to_symbol.read().unwrap();
-        if let Some(sym) = name_to_symbol.get(name) {
-            // Symbol already exists with identical case
-            return Some(sym.clone());
-        } else {
-            return None;
-        }
+        let shard = self.shards[shard_index(name)].read().unwrap();
+        // Symbol already exists with identical case
+        shard.get(name).cloned()
     }
 
     /// Insert a new symbol and return it. If a symbol already exists
@@ -96,11 +116,40 @@ impl SymbolTable {
         }
     }
 
+    /// The number of names in the table, a symbol has one name for each case it was written in
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The estimated number of bytes allocated by the table and its names
+    pub fn memory_bytes(&self) -> usize {
+        let mut bytes = 0;
+        for shard in self.shards.iter() {
+            let shard = shard.read().unwrap();
+            bytes += shard.capacity() * mem::size_of::<(Arc<Latin1String>, Symbol)>();
+            // Each name is an allocation with the reference counts and the string
+            bytes += shard
+                .keys()
+                .map(|name| {
+                    2 * mem::size_of::<usize>() + mem::size_of::<Latin1String>() + name.len()
+                })
+                .sum::<usize>();
+        }
+        bytes
+    }
+
     fn insert_new(&self, name: &Latin1String) -> Symbol {
-        let mut name_to_symbol = self.name_to_symbol.write().unwrap();
+        let mut shard = self.shards[shard_index(name)].write().unwrap();
 
         // Lookup again after taking lock to avoid race-condition where new symbols are created in parallel
-        if let Some(sym) = name_to_symbol.get(name) {
+        if let Some(sym) = shard.get(name) {
             // Symbol already exists with identical case
             return sym.clone();
         }
@@ -109,30 +158,30 @@ impl SymbolTable {
         let normal_name = Arc::from(name.to_lowercase());
         let name = Arc::from(name.clone());
 
-        match name_to_symbol.get(&normal_name).cloned() {
+        match shard.get(&normal_name).cloned() {
             // Symbol exists in normalized case
             Some(normal_sym) => {
                 // Copy id from previous symbol
                 // Insert new symbol with given case and return it
                 let id = normal_sym.id;
                 let sym = Symbol::new(id, &name);
-                name_to_symbol.insert(name, sym.clone());
+                shard.insert(name, sym.clone());
                 sym
             }
 
             // Symbol does not exist
             None => {
                 // Create new id
-                let id = name_to_symbol.len();
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
 
                 if normal_name != name {
                     // If symbol is not already normalized case insert it
                     let sym = Symbol::new(id, &normal_name);
-                    name_to_symbol.insert(normal_name, sym);
+                    shard.insert(normal_name, sym);
                 }
 
                 let sym = Symbol::new(id, &name);
-                name_to_symbol.insert(name, sym.clone());
+                shard.insert(name, sym.clone());
                 sym
             }
         }
@@ -142,6 +191,7 @@ impl SymbolTable {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::thread;
 
     #[test]
     fn symbol_table_insert() {
@@ -187,4 +237,50 @@ mod tests {
         let sym1 = symtab.insert_utf8("abc");
         assert_ne!(sym0, sym1);
     }
+
+    #[test]
+    fn names_of_each_case_are_counted() {
+        let symtab = SymbolTable::new();
+        assert!(symtab.is_empty());
+        assert_eq!(symtab.memory_bytes(), 0);
+
+        symtab.insert_utf8("hello");
+        assert_eq!(symtab.len(), 1);
+        let bytes = symtab.memory_bytes();
+        assert!(bytes >= "hello".len());
+
+        symtab.insert_utf8("Hello");
+        symtab.insert_utf8("hello");
+        assert_eq!(symtab.len(), 2);
+        assert!(symtab.memory_bytes() > bytes);
+    }
+
+    #[test]
+    fn symbols_inserted_by_threads_are_equal() {
+        let symtab = Arc::new(SymbolTable::new());
+        let names = ["hello", "Hello", "HELLO", "abc", "Abc", "xyz"];
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let symtab = symtab.clone();
+                thread::spawn(move || {
+                    names
+                        .iter()
+                        .map(|name| symtab.insert_utf8(name))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            let symbols = thread.join().unwrap();
+            assert_eq!(symbols[0], symtab.insert_utf8("hello"));
+            assert_eq!(symbols[1], symbols[0]);
+            assert_eq!(symbols[2], symbols[0]);
+            assert_eq!(symbols[3], symbols[4]);
+            assert_ne!(symbols[0], symbols[3]);
+            assert_ne!(symbols[3], symbols[5]);
+            assert_eq!(symbols[1].name_utf8(), "Hello");
+        }
+        assert_eq!(symtab.len(), 6);
+    }
 }
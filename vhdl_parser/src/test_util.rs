@@ -5,7 +5,7 @@
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
 use ast::{
-    AssociationElement, AttributeName, Choice, Declaration, DiscreteRange, ElementAssociation,
+    AssociationElement, AttributeName, Choices, Declaration, DiscreteRange, ElementAssociation,
     Expression, FunctionCall, Ident, InterfaceDeclaration, LabeledConcurrentStatement,
     LabeledSequentialStatement, Name, Range, SelectedName, Signature, SubprogramDeclaration,
     SubtypeIndication, UseClause, Waveform,
@@ -18,10 +18,13 @@ use interface_declaration::{parse_generic, parse_parameter, parse_port};
 use latin_1::Latin1String;
 use message::{Message, MessageHandler, ParseResult};
 use names::{parse_association_list, parse_name, parse_selected_name};
+use project::Project;
 use range::{parse_discrete_range, parse_range};
 use sequential_statement::parse_sequential_statement;
 use source::{Source, SrcPos, WithPos};
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::Write;
 use std::sync::Arc;
 use subprogram::{parse_signature, parse_subprogram_declaration_no_semi};
 use subtype_indication::parse_subtype_indication;
@@ -74,6 +77,15 @@ impl TestUtil {
         self.parse_first_ok(|stream: &mut TokenStream| stream.expect_ident(), name)
     }
 
+    /// Helper to create a identifier at a specific occurence of name
+    pub fn substr_ident(&self, name: &str, occurence: usize) -> Ident {
+        self.parse_ok(
+            |stream: &mut TokenStream| stream.expect_ident(),
+            name,
+            occurence,
+        )
+    }
+
     /// Helper to create a identifier at first occurence of name
     pub fn selected_name(&self, name: &str) -> SelectedName {
         self.parse_first_ok(parse_selected_name, name)
@@ -99,7 +111,7 @@ impl TestUtil {
             Name::FunctionCall(call) => *call,
             _ => FunctionCall {
                 name: name,
-                parameters: vec![],
+                parameters: smallvec![],
             },
         }
     }
@@ -119,7 +131,7 @@ impl TestUtil {
         }
     }
 
-    pub fn choices(&self, substr: &str) -> Vec<Choice> {
+    pub fn choices(&self, substr: &str) -> Choices {
         self.parse_first_ok(parse_choices, substr)
     }
 
@@ -305,3 +317,32 @@ pub fn check_no_messages(messages: &Vec<Message>) {
         panic!("Found errors");
     }
 }
+
+extern crate tempfile;
+
+/// Project where the source code of each file is written to a temporary directory
+pub struct TempProject {
+    dir: tempfile::TempDir,
+    pub project: Project,
+}
+
+impl TempProject {
+    pub fn new() -> TempProject {
+        TempProject {
+            dir: tempfile::tempdir().unwrap(),
+            project: Project::new(),
+        }
+    }
+
+    /// Write the code to a file with the given name and add it to the library
+    pub fn add_file(&mut self, library_name: &str, file_name: &str, code: &str) -> String {
+        let path = self.dir.path().join(file_name);
+        File::create(&path)
+            .unwrap()
+            .write_all(code.as_bytes())
+            .unwrap();
+        let file_name = path.to_str().unwrap().to_owned();
+        self.project.update_file(library_name, &file_name).unwrap();
+        file_name
+    }
+}
@@ -6,6 +6,7 @@
 
 use message::{error, Message, ParseResult};
 use source::{Source, SrcPos};
+use standard::VHDLStandard;
 extern crate fnv;
 use self::fnv::FnvHashMap;
 
@@ -630,12 +631,10 @@ fn parse_exponent(cursor: &mut ByteCursor) -> Result<i32, String> {
 }
 
 fn pow(value: i64, exp: u32) -> Option<i64> {
-    // @TODO use no_panic_pow once stable
-    let mut value = value;
-    for _ in 0..exp {
-        value = value.checked_mul(10)?;
+    if value == 0 {
+        return Some(0);
     }
-    Some(value)
+    value.checked_mul(10i64.checked_pow(exp)?)
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -652,6 +651,19 @@ impl TokenState {
         }
     }
 
+    /// The state before the token at the start position where no token precedes it
+    pub fn at(start: usize) -> TokenState {
+        TokenState {
+            last_token_kind: None,
+            start,
+        }
+    }
+
+    /// The position where the next token is searched from
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
     /// Set state to after token
     pub fn set_after(&mut self, token: &Token) {
         self.last_token_kind = Some(token.kind);
@@ -856,6 +868,9 @@ fn parse_bit_string(
     }
 }
 
+/// The length of configuration, the longest keyword
+const MAX_KEYWORD_LENGTH: usize = 13;
+
 /// LRM 15.4 Identifiers
 fn parse_basic_identifier_or_keyword(
     buffer: &mut Latin1String,
@@ -864,9 +879,14 @@ fn parse_basic_identifier_or_keyword(
     symtab: &SymbolTable,
 ) -> Result<(Kind, Value), String> {
     let start = cursor.idx;
+    let mut only_letters = true;
     while let Some(b) = cursor.peek(0) {
         match b {
-            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' => {
+            b'a'..=b'z' | b'A'..=b'Z' => {
+                cursor.pop();
+            }
+            b'0'..=b'9' | b'_' => {
+                only_letters = false;
                 cursor.pop();
             }
             _ => {
@@ -879,18 +899,19 @@ fn parse_basic_identifier_or_keyword(
     buffer
         .bytes
         .extend_from_slice(&cursor.bytes[start..cursor.idx]);
-    buffer.into_lowercase();
-
-    match keywords.get(buffer.bytes.as_slice()) {
-        Some(kind) => Ok((*kind, Value::NoValue)),
-        None => {
-            buffer.bytes.clear();
-            buffer
-                .bytes
-                .extend_from_slice(&cursor.bytes[start..cursor.idx]);
-            Ok((Identifier, Value::Identifier(symtab.insert(&buffer))))
+
+    // Most identifiers are not looked up as keywords which only have letters
+    if only_letters && buffer.len() <= MAX_KEYWORD_LENGTH {
+        buffer.into_lowercase();
+        if let Some(kind) = keywords.get(buffer.bytes.as_slice()) {
+            return Ok((*kind, Value::NoValue));
         }
+        buffer.bytes.clear();
+        buffer
+            .bytes
+            .extend_from_slice(&cursor.bytes[start..cursor.idx]);
     }
+    Ok((Identifier, Value::Identifier(symtab.insert(buffer))))
 }
 
 #[derive(Clone)]
@@ -906,7 +927,18 @@ pub struct Tokenizer {
 }
 
 impl Tokenizer {
+    #[cfg(test)]
     pub fn new(symtab: Arc<SymbolTable>, source: Source, code: Arc<Latin1String>) -> Tokenizer {
+        Tokenizer::with_standard(symtab, source, code, VHDLStandard::default())
+    }
+
+    /// Words which only became reserved in later revisions of the standard are identifiers in earlier revisions
+    pub fn with_standard(
+        symtab: Arc<SymbolTable>,
+        source: Source,
+        code: Arc<Latin1String>,
+        standard: VHDLStandard,
+    ) -> Tokenizer {
         let keywords = [
             ("architecture", Architecture),
             ("entity", Entity),
@@ -1001,11 +1033,18 @@ impl Tokenizer {
             ("rem", Rem),
         ];
 
-        let keywords: FnvHashMap<&[u8], Kind> = keywords
+        let mut keywords: FnvHashMap<&[u8], Kind> = keywords
             .into_iter()
             .map(|(string, kind)| (string.as_bytes(), *kind))
             .collect();
 
+        if standard < VHDLStandard::VHDL2008 {
+            keywords.remove("context".as_bytes());
+        }
+        if standard < VHDLStandard::VHDL2002 {
+            keywords.remove("protected".as_bytes());
+        }
+
         let range_ident = symtab.insert(&Latin1String::new(b"range"));
         let reverse_range_ident = symtab.insert(&Latin1String::new(b"reverse_range"));
 
@@ -1352,6 +1391,53 @@ end entity"
         );
     }
 
+    #[test]
+    fn tokenize_identifiers_like_keywords() {
+        assert_eq!(
+            kinds_tokenize("CONFIGURATION configurations entity2 end_ process_"),
+            vec![
+                Configuration,
+                Identifier,
+                Identifier,
+                Identifier,
+                Identifier
+            ]
+        );
+        let (_, symtab, tokens) = tokenize("Entity_1");
+        assert_eq!(
+            tokens[0].value,
+            Value::Identifier(symtab.insert_utf8("entity_1"))
+        );
+    }
+
+    #[test]
+    fn tokenize_keywords_of_standard() {
+        let kinds_tokenize_standard = |code: &str, standard| {
+            let symtab = Arc::new(SymbolTable::new());
+            let source = Source::from_str(code).unwrap();
+            let code = source.contents().unwrap();
+            let mut tokenizer = Tokenizer::with_standard(symtab, source, code, standard);
+            let mut kinds = Vec::new();
+            while let Some(token) = tokenizer.pop().unwrap() {
+                kinds.push(token.kind);
+            }
+            kinds
+        };
+
+        assert_eq!(
+            kinds_tokenize_standard("context protected", VHDLStandard::VHDL1993),
+            vec![Identifier, Identifier]
+        );
+        assert_eq!(
+            kinds_tokenize_standard("context protected", VHDLStandard::VHDL2002),
+            vec![Identifier, Protected]
+        );
+        assert_eq!(
+            kinds_tokenize_standard("context protected", VHDLStandard::VHDL2008),
+            vec![Context, Protected]
+        );
+    }
+
     #[test]
     fn tokenize_identifier_case_insensitive() {
         let (source, symtab, tokens) = tokenize("My_Ident");
@@ -1437,6 +1523,25 @@ end entity"
         );
     }
 
+    #[test]
+    fn tokenize_integer_large_exponent() {
+        assert_eq!(
+            kind_value_tokenize("0e2147483647"),
+            vec![(
+                AbstractLiteral,
+                Value::AbstractLiteral(ast::AbstractLiteral::Integer(0))
+            )]
+        );
+        let (source, _, tokens) = tokenize_result("1e2147483647");
+        assert_eq!(
+            tokens,
+            vec![Err(error(
+                source.entire_pos(),
+                "Integer too large for 64-bits signed"
+            ))]
+        );
+    }
+
     #[test]
     fn tokenize_integer_negative_exponent() {
         let (source, _, tokens) = tokenize_result("1e-1");
@@ -5,16 +5,57 @@
 // Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
 
 use ast::Ident;
-use message::ParseResult;
+use message::{error, ParseResult};
+use source::SrcPos;
 use tokenizer::{kinds_str, Kind, Kind::*, Token, TokenState, Tokenizer};
 
+/// The deepest nesting of statements, expressions, operations and names, deeper designs
+/// are an error instead of overflowing the stack when parsing or when dropping the tree
+///
+/// At this depth parsing needs less than 8 MB of stack, the stack of the main thread,
+/// without optimizations and less than 2 MB, the stack of other threads, with them
+pub const MAX_NESTING_DEPTH: usize = 128;
+
 pub struct TokenStream {
     pub tokenizer: Tokenizer,
+    /// The last peeked token with the states before and after it, most tokens are
+    /// peeked before they are popped and are then not tokenized again
+    peeked: Option<(TokenState, Token, TokenState)>,
+    /// The levels of nesting entered by the parsers
+    depth: usize,
 }
 
 impl TokenStream {
     pub fn new(tokenizer: Tokenizer) -> TokenStream {
-        TokenStream { tokenizer }
+        TokenStream {
+            tokenizer,
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Parse where the levels of nesting entered by the parse are left afterwards
+    /// also when it fails
+    pub fn nested<T, F>(&mut self, parse: F) -> ParseResult<T>
+    where
+        F: FnOnce(&mut TokenStream) -> ParseResult<T>,
+    {
+        let depth = self.depth;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Enter one more level of nesting at the position
+    pub fn enter(&mut self, pos: impl AsRef<SrcPos>) -> ParseResult<()> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(error(
+                pos,
+                &format!("Nesting is deeper than {} levels", MAX_NESTING_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        Ok(())
     }
 
     pub fn state(&self) -> TokenState {
@@ -30,14 +71,37 @@ impl TokenStream {
     }
 
     pub fn pop(self: &mut Self) -> ParseResult<Option<Token>> {
+        if let Some((state, token, after)) = self.peeked.take() {
+            if state == self.tokenizer.state() {
+                self.tokenizer.set_state(after);
+                return Ok(Some(token));
+            }
+        }
         self.tokenizer.pop()
     }
 
-    pub fn peek(self: &mut Self) -> ParseResult<Option<Token>> {
+    /// The next token without cloning it
+    fn peek_ref(self: &mut Self) -> ParseResult<Option<&Token>> {
         let state = self.tokenizer.state();
-        let result = self.tokenizer.pop();
-        self.tokenizer.set_state(state);
-        result
+        let is_peeked = self
+            .peeked
+            .as_ref()
+            .is_some_and(|(peeked_state, _, _)| *peeked_state == state);
+        if !is_peeked {
+            self.peeked = None;
+            let token = self.tokenizer.pop();
+            let after = self.tokenizer.state();
+            self.tokenizer.set_state(state);
+            match token? {
+                Some(token) => self.peeked = Some((state, token, after)),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.peeked.as_ref().map(|(_, token, _)| token))
+    }
+
+    pub fn peek(self: &mut Self) -> ParseResult<Option<Token>> {
+        Ok(self.peek_ref()?.cloned())
     }
 
     pub fn expect(self: &mut Self) -> ParseResult<Token> {
@@ -77,7 +141,7 @@ impl TokenStream {
     }
 
     pub fn peek_kind(self: &mut Self) -> ParseResult<Option<Kind>> {
-        Ok(self.peek()?.map(|ref token| token.kind))
+        Ok(self.peek_ref()?.map(|token| token.kind))
     }
 
     pub fn is_peek_kinds(self: &mut Self, kinds: &[Kind]) -> ParseResult<bool> {
@@ -93,13 +157,11 @@ impl TokenStream {
     }
 
     pub fn pop_if_kind(self: &mut Self, kind: Kind) -> ParseResult<Option<Token>> {
-        if let Some(token) = self.peek()? {
-            if token.kind == kind {
-                self.move_after(&token);
-                return Ok(Some(token));
-            }
+        if self.peek_kind()? == Some(kind) {
+            self.pop()
+        } else {
+            Ok(None)
         }
-        return Ok(None);
     }
 
     pub fn skip_if_kind(self: &mut Self, kind: Kind) -> ParseResult<bool> {
@@ -172,6 +234,23 @@ mod tests {
         assert_eq!(stream.pop(), Ok(None));
     }
 
+    #[test]
+    fn peek_after_changing_state() {
+        let (_, tokens, mut stream) = new("hello world again");
+
+        let state = stream.state();
+        assert_eq!(stream.peek(), Ok(Some(tokens[0].clone())));
+        stream.move_after(&tokens[1]);
+        assert_eq!(stream.peek_kind(), Ok(Some(Identifier)));
+        assert_eq!(stream.peek(), Ok(Some(tokens[2].clone())));
+        stream.set_state(state);
+        assert_eq!(stream.pop(), Ok(Some(tokens[0].clone())));
+        assert_eq!(stream.pop_if_kind(Identifier), Ok(Some(tokens[1].clone())));
+        assert_eq!(stream.pop_if_kind(Plus), Ok(None));
+        assert_eq!(stream.pop(), Ok(Some(tokens[2].clone())));
+        assert_eq!(stream.peek(), Ok(None));
+    }
+
     #[test]
     fn is_peek_kinds() {
         let (_, _, mut stream) = new("hello 1 +");
@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The generics and ports of a top level entity as JSON or YAML for co-simulation
+//! harness generators such as those of cocotb and board level integration scripts
+//!
+//! The width of each port is evaluated with the default values of the generics, or the
+//! values given for them, when its subtype is a scalar, integer or bit vector with
+//! static bounds
+
+use analysis_server::interface_elements;
+use ast::InterfaceDeclaration;
+use instance_tree::InstanceHierarchy;
+use library::{symbol_key, UnitId};
+use name_resolution::{entity_interface, NamedEntityKind};
+use project::Project;
+use static_value::{bit_width, generic_values, Value};
+use yaml;
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct TopGeneric {
+    pub name: String,
+    /// The subtype indication as written such as natural range 1 to 64
+    #[serde(rename = "type")]
+    pub subtype: String,
+    /// The default expression as written
+    pub default: Option<String>,
+    /// The value the widths are evaluated with such as 8 or true
+    pub value: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct TopPort {
+    pub name: String,
+    /// The mode such as in or inout
+    pub direction: &'static str,
+    /// The subtype indication as written such as std_logic_vector(width - 1 downto 0)
+    #[serde(rename = "type")]
+    pub subtype: String,
+    /// The number of bits when the subtype has a static width
+    pub width: Option<i64>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct TopInterface {
+    pub library: String,
+    pub entity: String,
+    pub generics: Vec<TopGeneric>,
+    pub ports: Vec<TopPort>,
+}
+
+/// The value as a VHDL literal
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Boolean(value) => value.to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Character(chr) => format!("'{}'", *chr as char),
+        Value::String(bytes) => format!("\"{}\"", String::from_utf8_lossy(bytes)),
+    }
+}
+
+/// An integer or boolean value given for a generic such as on the command line
+fn parse_value(name: &str, value: &str) -> Result<Value, String> {
+    match value.to_lowercase().as_str() {
+        "true" => Ok(Value::Boolean(true)),
+        "false" => Ok(Value::Boolean(false)),
+        lower => lower.parse().map(Value::Integer).map_err(|_| {
+            format!(
+                "The value '{}' of generic {} must be an integer or boolean",
+                value, name
+            )
+        }),
+    }
+}
+
+/// The interface of the entity where the widths are evaluated with the values given
+/// for some of the generics by their names and otherwise with the defaults
+pub fn top_interface(
+    project: &Project,
+    top: &UnitId,
+    values: &[(String, String)],
+) -> Result<TopInterface, String> {
+    let files = project.files();
+    let hierarchy = InstanceHierarchy::new(&files);
+    let library_unit = hierarchy.entity(top).ok_or_else(|| {
+        format!(
+            "Found no entity {}.{}",
+            top.library_name,
+            top.primary_name()
+        )
+    })?;
+    let (generics, ports) = entity_interface(library_unit);
+
+    let mut constants = generic_values(generics, &[]);
+    for (name, value) in values.iter() {
+        let key = name.to_lowercase();
+        let declared = generics.iter().any(|decl| match decl {
+            InterfaceDeclaration::Object(ref object) => symbol_key(&object.ident.item) == key,
+            _ => false,
+        });
+        if !declared {
+            return Err(format!("The entity has no generic {}", name));
+        }
+        constants.insert(key, parse_value(name, value)?);
+    }
+
+    let generic_elements = interface_elements(generics, NamedEntityKind::Generic);
+    let generic_objects = generics.iter().filter_map(|decl| match decl {
+        InterfaceDeclaration::Object(ref object) => Some(object),
+        _ => None,
+    });
+    let top_generics = generic_elements
+        .into_iter()
+        .zip(generic_objects)
+        .map(|(element, object)| TopGeneric {
+            value: constants.get(&symbol_key(&object.ident.item)).map(literal),
+            name: element.name,
+            subtype: element.subtype,
+            default: element.default,
+        })
+        .collect();
+
+    let port_elements = interface_elements(ports, NamedEntityKind::Port);
+    let port_objects = ports.iter().filter_map(|decl| match decl {
+        InterfaceDeclaration::Object(ref object) => Some(object),
+        _ => None,
+    });
+    let top_ports = port_elements
+        .into_iter()
+        .zip(port_objects)
+        .map(|(element, object)| TopPort {
+            name: element.name,
+            direction: element.mode.unwrap_or("in"),
+            subtype: element.subtype,
+            width: bit_width(&object.subtype_indication, &constants),
+        })
+        .collect();
+
+    Ok(TopInterface {
+        library: top.library_name.clone(),
+        entity: top.primary_name().to_owned(),
+        generics: top_generics,
+        ports: top_ports,
+    })
+}
+
+fn optional(value: Option<String>) -> yaml::Value {
+    value.map_or(yaml::Value::Null, yaml::Value::String)
+}
+
+/// The interface as a YAML document with the same structure as the JSON
+pub fn top_interface_yaml(interface: &TopInterface) -> String {
+    let string = |value: &str| yaml::Value::String(value.to_owned());
+    let generics = interface
+        .generics
+        .iter()
+        .map(|generic| {
+            yaml::Value::Mapping(vec![
+                ("name".to_owned(), string(&generic.name)),
+                ("type".to_owned(), string(&generic.subtype)),
+                ("default".to_owned(), optional(generic.default.clone())),
+                ("value".to_owned(), optional(generic.value.clone())),
+            ])
+        })
+        .collect();
+    let ports = interface
+        .ports
+        .iter()
+        .map(|port| {
+            yaml::Value::Mapping(vec![
+                ("name".to_owned(), string(&port.name)),
+                ("direction".to_owned(), string(port.direction)),
+                ("type".to_owned(), string(&port.subtype)),
+                (
+                    "width".to_owned(),
+                    optional(port.width.map(|width| width.to_string())),
+                ),
+            ])
+        })
+        .collect();
+    yaml::to_string(&yaml::Value::Mapping(vec![
+        ("library".to_owned(), string(&interface.library)),
+        ("entity".to_owned(), string(&interface.entity)),
+        ("generics".to_owned(), yaml::Value::Sequence(generics)),
+        ("ports".to_owned(), yaml::Value::Sequence(ports)),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "fifo.vhd",
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity fifo is
+  generic (
+    width : natural := 8;
+    depth : positive := 16;
+    name : string := \"fifo\");
+  port (
+    clk : in std_logic;
+    din : in std_logic_vector(width - 1 downto 0);
+    dout : out std_logic_vector(width - 1 downto 0);
+    level : out natural range 0 to depth;
+    state : buffer state_t);
+end entity;",
+        );
+        project
+    }
+
+    #[test]
+    fn interface_with_default_generics() {
+        let project = project();
+        let interface =
+            top_interface(&project.project, &UnitId::primary("lib", "fifo"), &[]).unwrap();
+        assert_eq!(interface.library, "lib");
+        assert_eq!(interface.entity, "fifo");
+        assert_eq!(
+            interface.generics[0],
+            TopGeneric {
+                name: "width".to_owned(),
+                subtype: "natural".to_owned(),
+                default: Some("8".to_owned()),
+                value: Some("8".to_owned()),
+            }
+        );
+        assert_eq!(interface.generics[2].value, Some("\"fifo\"".to_owned()));
+        assert_eq!(
+            interface.ports[1],
+            TopPort {
+                name: "din".to_owned(),
+                direction: "in",
+                subtype: "std_logic_vector(width - 1 downto 0)".to_owned(),
+                width: Some(8),
+            }
+        );
+        let widths: Vec<Option<i64>> = interface.ports.iter().map(|port| port.width).collect();
+        assert_eq!(widths, vec![Some(1), Some(8), Some(8), Some(32), None]);
+        assert_eq!(interface.ports[4].direction, "buffer");
+
+        assert!(top_interface(&project.project, &UnitId::primary("lib", "missing"), &[]).is_err());
+    }
+
+    #[test]
+    fn interface_with_given_generics() {
+        let project = project();
+        let top = UnitId::primary("lib", "fifo");
+        let interface = top_interface(
+            &project.project,
+            &top,
+            &[("WIDTH".to_owned(), "32".to_owned())],
+        )
+        .unwrap();
+        assert_eq!(interface.generics[0].value, Some("32".to_owned()));
+        assert_eq!(interface.ports[1].width, Some(32));
+
+        assert_eq!(
+            top_interface(
+                &project.project,
+                &top,
+                &[("size".to_owned(), "1".to_owned())]
+            ),
+            Err("The entity has no generic size".to_owned())
+        );
+        assert!(top_interface(
+            &project.project,
+            &top,
+            &[("width".to_owned(), "x".to_owned())]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn yaml_document() {
+        let project = project();
+        let interface =
+            top_interface(&project.project, &UnitId::primary("lib", "fifo"), &[]).unwrap();
+        let yaml = top_interface_yaml(&interface);
+        assert!(yaml.starts_with("library: lib\nentity: fifo\ngenerics:\n  - name: width\n"));
+        assert!(yaml.contains("  - name: name\n    type: string\n    default: \"\\\"fifo\\\"\"\n"));
+        assert!(yaml.contains(
+            "  - name: state\n    direction: buffer\n    type: state_t\n    width: null\n"
+        ));
+        let value = yaml::parse(&yaml).unwrap();
+        assert_eq!(
+            value
+                .get("ports")
+                .and_then(|ports| ports.as_sequence())
+                .map(<[_]>::len),
+            Some(5)
+        );
+    }
+}
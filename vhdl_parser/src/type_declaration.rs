@@ -14,6 +14,7 @@ use declarative_part::parse_declarative_part;
 use message::{error, push_some, MessageHandler, ParseResult};
 use names::{parse_identifier_list, parse_selected_name};
 use range::{parse_array_index_constraint, parse_range};
+use source::WithPos;
 use subprogram::parse_subprogram_declaration;
 use subtype_indication::parse_subtype_indication;
 use tokenizer::Kind::*;
@@ -43,7 +44,7 @@ fn parse_enumeration_type_definition(stream: &mut TokenStream) -> ParseResult<Ty
                     Identifier => EnumerationLiteral::Identifier(literal_token.expect_ident()?.item),
                     Character => EnumerationLiteral::Character(literal_token.expect_character()?)
                 );
-                enum_literals.push(enum_literal);
+                enum_literals.push(WithPos::new(enum_literal, &literal_token.pos));
 
                 try_token_kind!(
                     stream.expect()?,
@@ -323,8 +324,14 @@ mod tests {
         let type_decl = TypeDeclaration {
             ident: util.ident("foo"),
             def: TypeDefinition::Enumeration(vec![
-                EnumerationLiteral::Identifier(util.symbol("alpha")),
-                EnumerationLiteral::Identifier(util.symbol("beta")),
+                WithPos::new(
+                    EnumerationLiteral::Identifier(util.symbol("alpha")),
+                    util.first_substr_pos("alpha"),
+                ),
+                WithPos::new(
+                    EnumerationLiteral::Identifier(util.symbol("beta")),
+                    util.first_substr_pos("beta"),
+                ),
             ]),
         };
         assert_eq!(result, type_decl);
@@ -338,8 +345,14 @@ mod tests {
         let type_decl = TypeDeclaration {
             ident: util.ident("foo"),
             def: TypeDefinition::Enumeration(vec![
-                EnumerationLiteral::Character(b'a'),
-                EnumerationLiteral::Character(b'b'),
+                WithPos::new(
+                    EnumerationLiteral::Character(b'a'),
+                    util.first_substr_pos("'a'"),
+                ),
+                WithPos::new(
+                    EnumerationLiteral::Character(b'b'),
+                    util.first_substr_pos("'b'"),
+                ),
             ]),
         };
         assert_eq!(result, type_decl);
@@ -759,5 +772,4 @@ end units;
             }
         )
     }
-
 }
@@ -0,0 +1,374 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The unreachable-code lint rule which reports statements after an unconditional
+//! return, exit or next, branches guarded by conditions which are always false, or
+//! which follow a condition which is always true, and generate statements which are
+//! never elaborated for the values of the generics of every instance
+
+use ast::{
+    ConcurrentStatement, Conditionals, Declaration, DiscreteRange, Expression, Ident,
+    IterationScheme, LabeledConcurrentStatement, LabeledSequentialStatement, LibraryUnit, Range,
+    SequentialStatement, SubprogramDeclaration,
+};
+use library::symbol_key;
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use process_structure::statement_pos;
+use source::WithPos;
+use static_value::{
+    declare_constants, hide_interface, is_null_range, static_architectures, static_condition,
+    Constants,
+};
+
+/// Whether the condition of an exit or next statement is missing or always true
+fn always(condition: &Option<WithPos<Expression>>, instances: &[Constants]) -> bool {
+    match condition {
+        Some(ref condition) => static_condition(&condition.item, instances) == Some(true),
+        None => true,
+    }
+}
+
+/// A constant scope for each instance where the names are hidden
+fn hide(instances: &[Constants], names: &[&Ident]) -> Vec<Constants> {
+    let mut instances = instances.to_vec();
+    for constants in instances.iter_mut() {
+        for name in names.iter() {
+            constants.remove(&symbol_key(&name.item));
+        }
+    }
+    instances
+}
+
+/// A constant scope for each instance with the constants of the declarations
+fn declare(instances: &[Constants], decl: &[Declaration]) -> Vec<Constants> {
+    let mut instances = instances.to_vec();
+    for constants in instances.iter_mut() {
+        declare_constants(decl, constants);
+    }
+    instances
+}
+
+struct Walker {
+    findings: Vec<Message>,
+}
+
+impl Walker {
+    /// Walks the items of the branches which can be taken
+    fn conditionals<T, F>(
+        &mut self,
+        conditionals: &Conditionals<T>,
+        instances: &[Constants],
+        (branch, never): (&str, &str),
+        mut walk: F,
+    ) where
+        F: FnMut(&mut Walker, &T),
+    {
+        for (i, conditional) in conditionals.conditionals.iter().enumerate() {
+            match static_condition(&conditional.condition.item, instances) {
+                Some(false) => {
+                    self.findings.push(warning(
+                        &conditional.condition,
+                        &format!(
+                            "The condition is always false so the {} is {}",
+                            branch, never
+                        ),
+                    ));
+                }
+                Some(true) => {
+                    walk(self, &conditional.item);
+                    if i + 1 < conditionals.conditionals.len() || conditionals.else_item.is_some() {
+                        self.findings.push(warning(
+                            &conditional.condition,
+                            &format!(
+                                "The condition is always true so the {}es after it are {}",
+                                branch, never
+                            ),
+                        ));
+                    }
+                    return;
+                }
+                None => walk(self, &conditional.item),
+            }
+        }
+        if let Some(ref item) = conditionals.else_item {
+            walk(self, item);
+        }
+    }
+
+    fn sequential(&mut self, statements: &[LabeledSequentialStatement], instances: &[Constants]) {
+        for (i, statement) in statements.iter().enumerate() {
+            let jump = match statement.statement {
+                SequentialStatement::If(ref ifs) => {
+                    self.conditionals(
+                        ifs,
+                        instances,
+                        ("branch", "unreachable"),
+                        |walker, statements| walker.sequential(statements, instances),
+                    );
+                    None
+                }
+                SequentialStatement::Case(ref case) => {
+                    for alternative in case.alternatives.iter() {
+                        self.sequential(&alternative.item, instances);
+                    }
+                    None
+                }
+                SequentialStatement::Loop(ref lp) => {
+                    match lp.iteration_scheme {
+                        Some(IterationScheme::For(ref index, _)) => {
+                            self.sequential(&lp.statements, &hide(instances, &[index]));
+                        }
+                        _ => self.sequential(&lp.statements, instances),
+                    }
+                    None
+                }
+                SequentialStatement::Return(..) => Some("return"),
+                SequentialStatement::Exit(ref exit) if always(&exit.condition, instances) => {
+                    Some("exit")
+                }
+                SequentialStatement::Next(ref next) if always(&next.condition, instances) => {
+                    Some("next")
+                }
+                _ => None,
+            };
+            if let Some(jump) = jump {
+                if let Some(pos) = statements[i + 1..].iter().filter_map(statement_pos).next() {
+                    self.findings.push(warning(
+                        &pos,
+                        &format!("The statement is unreachable after {}", jump),
+                    ));
+                }
+                return;
+            }
+        }
+    }
+
+    /// Walks the bodies of the subprograms of the declarations
+    fn declarations(&mut self, decl: &[Declaration], instances: &[Constants]) {
+        for declaration in decl.iter() {
+            if let Declaration::SubprogramBody(ref body) = declaration {
+                let parameters = match body.specification {
+                    SubprogramDeclaration::Procedure(ref procedure) => &procedure.parameter_list,
+                    SubprogramDeclaration::Function(ref function) => &function.parameter_list,
+                };
+                let mut instances = instances.to_vec();
+                for constants in instances.iter_mut() {
+                    hide_interface(parameters, constants);
+                    declare_constants(&body.declarations, constants);
+                }
+                self.declarations(&body.declarations, &instances);
+                self.sequential(&body.statements, &instances);
+            }
+        }
+    }
+
+    fn concurrent(&mut self, statements: &[LabeledConcurrentStatement], instances: &[Constants]) {
+        for statement in statements.iter() {
+            match statement.statement {
+                ConcurrentStatement::Process(ref process) => {
+                    let instances = declare(instances, &process.decl);
+                    self.declarations(&process.decl, &instances);
+                    self.sequential(&process.statements, &instances);
+                }
+                ConcurrentStatement::Block(ref block) => {
+                    let instances = declare(instances, &block.decl);
+                    self.declarations(&block.decl, &instances);
+                    self.concurrent(&block.statements, &instances);
+                }
+                ConcurrentStatement::IfGenerate(ref gen) => {
+                    self.conditionals(
+                        gen,
+                        instances,
+                        ("generate branch", "never elaborated"),
+                        |walker, body| {
+                            let decl = body.decl.as_deref().unwrap_or(&[]);
+                            let instances = declare(instances, decl);
+                            walker.declarations(decl, &instances);
+                            walker.concurrent(&body.statements, &instances);
+                        },
+                    );
+                }
+                ConcurrentStatement::ForGenerate(ref gen) => {
+                    if let DiscreteRange::Range(Range::Range(ref constraint)) = gen.discrete_range {
+                        let is_null = instances.iter().all(|constants| {
+                            is_null_range(
+                                &constraint.left_expr.item,
+                                constraint.direction,
+                                &constraint.right_expr.item,
+                                constants,
+                            ) == Some(true)
+                        });
+                        if is_null {
+                            self.findings.push(warning(
+                                constraint.left_expr.pos.combine(&constraint.right_expr.pos),
+                                "The range is null so the generate is never elaborated",
+                            ));
+                            continue;
+                        }
+                    }
+                    let instances = hide(instances, &[&gen.index_name]);
+                    let decl = gen.body.decl.as_deref().unwrap_or(&[]);
+                    let instances = declare(&instances, decl);
+                    self.declarations(decl, &instances);
+                    self.concurrent(&gen.body.statements, &instances);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct UnreachableCode;
+
+impl Rule for UnreachableCode {
+    fn id(&self) -> &str {
+        "unreachable-code"
+    }
+
+    fn description(&self) -> &str {
+        "A statement, branch or generate statement can never be reached"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        let files = context.project.files();
+        let mut walker = Walker {
+            findings: Vec::new(),
+        };
+        for architecture in static_architectures(&files) {
+            walker.declarations(architecture.decl, &architecture.instances);
+            walker.concurrent(architecture.statements, &architecture.instances);
+        }
+        for file in files.iter() {
+            for design_unit in file.design_file.design_units.iter() {
+                if let LibraryUnit::PackageBody { ref decl, .. } = design_unit.library_unit {
+                    let instances = declare(&[Constants::new()], decl);
+                    walker.declarations(decl, &instances);
+                }
+            }
+        }
+        Ok(walker.findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn unreachable_statements_branches_and_generates() {
+        let mut project = TempProject::new();
+        let code = "
+package pkg is
+  function f(x : integer) return integer;
+end package;
+
+package body pkg is
+  constant debug : boolean := false;
+
+  function f(x : integer) return integer is
+  begin
+    if debug then
+      report \"f\";
+    end if;
+    return x;
+    report \"done\";
+  end function;
+end package body;
+
+entity ent is
+  generic (width : natural := 8; fast : boolean := false);
+end entity;
+
+architecture rtl of ent is
+  constant depth : natural := 2 * width;
+begin
+  process
+  begin
+    for i in 0 to 7 loop
+      exit;
+      count <= i;
+    end loop;
+    if depth > 4 then
+      count <= 1;
+    elsif fast then
+      count <= 2;
+    end if;
+    wait;
+  end process;
+
+  fast_gen : if fast generate
+  end generate;
+
+  empty_gen : for i in 0 to width - 17 generate
+  end generate;
+end architecture;
+
+entity top is
+end entity;
+
+architecture rtl of top is
+begin
+  narrow : entity work.ent generic map (4, false);
+  wide : entity work.ent generic map (width => 16);
+end architecture;";
+        project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<(String, usize)> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("unreachable-code".to_owned()))
+            .map(|finding| (finding.message, finding.pos.start))
+            .collect();
+        let string = |string: &str| string.to_owned();
+
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    string("The condition is always false so the branch is unreachable"),
+                    code.find("debug then").unwrap()
+                ),
+                (
+                    string("The statement is unreachable after return"),
+                    code.find("\"done\"").unwrap()
+                ),
+                (
+                    string("The statement is unreachable after exit"),
+                    code.find("count <= i").unwrap()
+                ),
+                (
+                    string("The condition is always true so the branches after it are unreachable"),
+                    code.find("depth > 4").unwrap()
+                ),
+                (
+                    string(
+                        "The condition is always false so the generate branch is never elaborated"
+                    ),
+                    code.find("fast generate").unwrap()
+                ),
+                (
+                    string("The range is null so the generate is never elaborated"),
+                    code.find("0 to width - 17").unwrap()
+                ),
+            ]
+        );
+    }
+}
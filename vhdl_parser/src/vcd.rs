@@ -0,0 +1,322 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Value change dump of the signals of a simulation for waveform viewers such as GTKWave
+//!
+//! The dots of the hierarchical paths of the signals become nested module scopes. Bits,
+//! std_ulogic values and their vectors are wires, where the std_ulogic values without a
+//! VCD equivalent are x and the weak values are 0 and 1, integers and times are integer
+//! and time variables and other values are written as text with the string extension of
+//! GTKWave
+
+use simulation_value::{Time, Type, Value};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// A signal with each hierarchical path it is visible by, the paths of the ports
+/// associated with it come after the path of its declaration
+pub struct Variable<'a> {
+    pub paths: Vec<&'a str>,
+    pub typ: &'a Type,
+    pub value: &'a Value,
+}
+
+/// A module scope of the header with the variables and the scopes within it
+#[derive(Default)]
+struct Scope<'a> {
+    variables: Vec<(&'a str, usize)>,
+    scopes: Vec<(&'a str, Scope<'a>)>,
+}
+
+impl<'a> Scope<'a> {
+    fn add(&mut self, path: &'a str, variable: usize) {
+        match path.find('.') {
+            Some(dot) => {
+                let (name, rest) = (&path[..dot], &path[dot + 1..]);
+                let position = match self.scopes.iter().position(|(other, _)| *other == name) {
+                    Some(position) => position,
+                    None => {
+                        self.scopes.push((name, Scope::default()));
+                        self.scopes.len() - 1
+                    }
+                };
+                self.scopes[position].1.add(rest, variable);
+            }
+            None => self.variables.push((path, variable)),
+        }
+    }
+}
+
+/// The identifier code of a variable in the printable characters from ! to ~
+fn code(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+/// The VCD value of a bit or std_ulogic value
+fn logic(chr: u8) -> char {
+    match chr {
+        b'0' | b'L' => '0',
+        b'1' | b'H' => '1',
+        b'Z' => 'z',
+        _ => 'x',
+    }
+}
+
+/// Spaces would end the value of a string variable
+fn text(value: &Value) -> String {
+    value.to_text().replace(char::is_whitespace, "_")
+}
+
+/// The variable type and width of the declaration of the signal
+fn declaration(typ: &Type, value: &Value) -> (&'static str, usize, String) {
+    match (typ, value) {
+        (Type::Bit, _) | (Type::Logic, _) | (Type::Boolean, _) => ("wire", 1, String::new()),
+        (_, Value::Vector(_, bounds, _)) => (
+            "wire",
+            bounds.len(),
+            format!(" [{}:{}]", bounds.left, bounds.right),
+        ),
+        (Type::Integer(low, high), _) => {
+            let width = if *low < i64::from(i32::MIN) || *high > i64::from(i32::MAX) {
+                64
+            } else {
+                32
+            };
+            ("integer", width, String::new())
+        }
+        (Type::Time, _) => ("time", 64, String::new()),
+        _ => ("string", 1, String::new()),
+    }
+}
+
+/// The value change of the variable with the code
+fn change(code: &str, value: &Value) -> String {
+    match value {
+        Value::Boolean(value) => format!("{}{}", u8::from(*value), code),
+        Value::Character(chr) => match *chr {
+            b'U' | b'X' | b'0' | b'1' | b'Z' | b'W' | b'L' | b'H' | b'-' => {
+                format!("{}{}", logic(*chr), code)
+            }
+            _ => format!("s{} {}", text(value), code),
+        },
+        Value::Vector(_, _, ref elements) => {
+            let bits: String = elements.iter().map(|chr| logic(*chr)).collect();
+            format!("b{} {}", bits, code)
+        }
+        // Two's complement of the width of the declaration
+        Value::Integer(value) => match i32::try_from(*value) {
+            Ok(value) => format!("b{:b} {}", value as u32, code),
+            Err(_) => format!("b{:b} {}", *value as u64, code),
+        },
+        Value::Time(value) => format!("b{:b} {}", value, code),
+        Value::Enum(..) | Value::String(..) => format!("s{} {}", text(value), code),
+    }
+}
+
+/// Writes the header and initial values of the variables and then the changes of their
+/// values, where the first error of the output is kept until the dump is finished
+pub struct VcdWriter {
+    output: Box<dyn Write>,
+    codes: Vec<String>,
+    time: Option<Time>,
+    error: Option<io::Error>,
+}
+
+impl VcdWriter {
+    pub fn new(output: Box<dyn Write>, variables: &[Variable]) -> VcdWriter {
+        let mut writer = VcdWriter {
+            output,
+            codes: (0..variables.len()).map(code).collect(),
+            time: None,
+            error: None,
+        };
+        let mut root = Scope::default();
+        for (index, variable) in variables.iter().enumerate() {
+            for path in variable.paths.iter() {
+                root.add(path, index);
+            }
+        }
+        let mut header = String::new();
+        header.push_str("$version rust_hdl $end\n");
+        // Times of the interpreter are femtoseconds
+        header.push_str("$timescale 1 fs $end\n");
+        writer.scope(&mut header, &root, variables);
+        header.push_str("$enddefinitions $end\n");
+        header.push_str("#0\n$dumpvars\n");
+        for (index, variable) in variables.iter().enumerate() {
+            header.push_str(&change(&writer.codes[index], variable.value));
+            header.push('\n');
+        }
+        header.push_str("$end\n");
+        writer.write(&header);
+        writer.time = Some(0);
+        writer
+    }
+
+    fn scope(&self, header: &mut String, scope: &Scope, variables: &[Variable]) {
+        for (name, variable) in scope.variables.iter() {
+            let (kind, width, range) =
+                declaration(variables[*variable].typ, variables[*variable].value);
+            header.push_str(&format!(
+                "$var {} {} {} {}{} $end\n",
+                kind, width, self.codes[*variable], name, range
+            ));
+        }
+        for (name, scope) in scope.scopes.iter() {
+            header.push_str(&format!("$scope module {} $end\n", name));
+            self.scope(header, scope, variables);
+            header.push_str("$upscope $end\n");
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.error.is_none() {
+            if let Err(err) = self.output.write_all(text.as_bytes()) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    fn advance(&mut self, time: Time) {
+        if self.time != Some(time) {
+            self.time = Some(time);
+            self.write(&format!("#{}\n", time));
+        }
+    }
+
+    /// The new value of the variable with the index at the time
+    pub fn change(&mut self, time: Time, index: usize, value: &Value) {
+        self.advance(time);
+        let change = change(&self.codes[index], value);
+        self.write(&change);
+        self.write("\n");
+    }
+
+    /// Ends the dump at the time and flushes the output
+    pub fn finish(&mut self, time: Time) -> io::Result<()> {
+        self.advance(time);
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulation_value::{Bounds, VectorKind, INTEGER};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// An output which can be read after it is given to the writer
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(code(0), "!");
+        assert_eq!(code(93), "~");
+        assert_eq!(code(94), "!!");
+        assert_eq!(code(95), "\"!");
+    }
+
+    #[test]
+    fn scopes_and_changes() {
+        let bounds = Bounds {
+            left: 3,
+            right: 0,
+            ascending: false,
+        };
+        let count = Value::Vector(VectorKind::Unsigned, bounds, b"0000".to_vec());
+        let logic = Type::Logic;
+        let vector = Type::Vector(VectorKind::Unsigned, Some(bounds));
+        let text = Type::String(None);
+        let clk = Value::Character(b'U');
+        let number = Value::Integer(-2);
+        let message = Value::String(b"not done".to_vec());
+        let variables = [
+            Variable {
+                paths: vec!["tb.clk", "tb.dut.clk"],
+                typ: &logic,
+                value: &clk,
+            },
+            Variable {
+                paths: vec!["tb.dut.count"],
+                typ: &vector,
+                value: &count,
+            },
+            Variable {
+                paths: vec!["tb.number"],
+                typ: &INTEGER,
+                value: &number,
+            },
+            Variable {
+                paths: vec!["tb.message"],
+                typ: &text,
+                value: &message,
+            },
+        ];
+        let output = Shared::default();
+        let mut writer = VcdWriter::new(Box::new(output.clone()), &variables);
+        writer.change(5, 0, &Value::Character(b'1'));
+        writer.change(
+            5,
+            1,
+            &Value::Vector(VectorKind::Unsigned, bounds, b"01ZL".to_vec()),
+        );
+        writer.change(10, 0, &Value::Character(b'H'));
+        writer.finish(20).unwrap();
+        assert_eq!(
+            String::from_utf8(output.0.borrow().clone()).unwrap(),
+            "\
+$version rust_hdl $end
+$timescale 1 fs $end
+$scope module tb $end
+$var wire 1 ! clk $end
+$var integer 32 # number $end
+$var string 1 $ message $end
+$scope module dut $end
+$var wire 1 ! clk $end
+$var wire 4 \" count [3:0] $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+x!
+b0000 \"
+b11111111111111111111111111111110 #
+snot_done $
+$end
+#5
+1!
+b01z0 \"
+#10
+1!
+#20
+"
+        );
+    }
+}
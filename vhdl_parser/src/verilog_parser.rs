@@ -0,0 +1,1187 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The module and interface headers of Verilog and SystemVerilog source code
+//!
+//! Only the names, parameters and ports of modules are parsed, which is what VHDL code
+//! instantiating Verilog IP depends on, the module items other than parameter, port and
+//! net declarations are skipped. Both ANSI port lists and port lists with the
+//! directions declared in the module body are supported, as are the data types,
+//! interface ports and type parameters of SystemVerilog and the modports of interfaces
+
+use message::{error, Message, MessageHandler};
+use source::{Source, SrcPos, WithPos};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use verilog_tokenizer::{tokenize, Kind, Token, Tokenizer};
+
+/// Options which affect the result of parsing a Verilog file
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct VerilogOptions {
+    /// Whether the code is SystemVerilog where its keywords are reserved
+    pub systemverilog: bool,
+    /// The text macros defined outside of the code such as with +define+
+    pub defines: BTreeMap<String, String>,
+}
+
+/// Whether the file is SystemVerilog by its extension .sv or .svh
+pub fn is_systemverilog_file(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("sv") || extension.eq_ignore_ascii_case("svh")
+        })
+}
+
+/// Whether the file is Verilog or SystemVerilog by its extension .v, .vh, .sv or .svh
+pub fn is_verilog_file(file_name: &str) -> bool {
+    is_systemverilog_file(file_name)
+        || Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("v") || extension.eq_ignore_ascii_case("vh")
+            })
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PortDirection {
+    Input,
+    Output,
+    Inout,
+    Ref,
+}
+
+/// A packed range such as [WIDTH-1:0] with its bounds as written
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogRange {
+    pub left: String,
+    pub right: String,
+}
+
+/// The interface of an interface port such as axi_if.master, none for a generic interface
+#[derive(PartialEq, Debug, Clone)]
+pub struct InterfacePort {
+    pub interface: Option<String>,
+    pub modport: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogPort {
+    pub name: WithPos<String>,
+    /// Inout for interface ports whose direction is that of each signal
+    pub direction: PortDirection,
+    /// The net or variable type as written such as wire, logic or pkg::word_t
+    pub data_type: Option<String>,
+    pub signed: bool,
+    /// The packed dimensions where the first is the outermost
+    pub ranges: Vec<VerilogRange>,
+    pub interface: Option<InterfacePort>,
+}
+
+impl AsRef<SrcPos> for VerilogPort {
+    fn as_ref(&self) -> &SrcPos {
+        &self.name.pos
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogParameter {
+    pub name: WithPos<String>,
+    /// The data type as written such as integer or int unsigned
+    pub data_type: Option<String>,
+    /// The default value as written, a data type for type parameters
+    pub default: Option<String>,
+    /// Whether it is a localparam, or a parameter of the body when there is a parameter port
+    /// list, which cannot be overridden by an instance
+    pub local: bool,
+    /// Whether it is a type parameter such as parameter type T = logic
+    pub is_type: bool,
+}
+
+/// A modport of an interface with the direction of each signal
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogModport {
+    pub name: WithPos<String>,
+    pub ports: Vec<(WithPos<String>, PortDirection)>,
+}
+
+/// A module or an interface
+#[derive(PartialEq, Debug, Clone)]
+pub struct VerilogModule {
+    pub name: WithPos<String>,
+    pub parameters: Vec<VerilogParameter>,
+    pub ports: Vec<VerilogPort>,
+    /// The modports of an interface
+    pub modports: Vec<VerilogModport>,
+}
+
+impl VerilogModule {
+    pub fn port(&self, name: &str) -> Option<&VerilogPort> {
+        self.ports.iter().find(|port| port.name.item == name)
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&VerilogParameter> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.name.item == name)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct VerilogFile {
+    pub modules: Vec<VerilogModule>,
+    pub interfaces: Vec<VerilogModule>,
+    /// The text macros defined at the end of the file
+    pub defines: BTreeMap<String, String>,
+}
+
+/// The data type, signing and packed dimensions before the names of a declaration
+#[derive(Clone)]
+struct DataType {
+    data_type: Option<String>,
+    signed: bool,
+    ranges: Vec<VerilogRange>,
+}
+
+/// The text of tokens as written with whitespace between them collapsed to one space
+fn tokens_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut end = None;
+    for token in tokens.iter() {
+        if end.is_some_and(|end| token.pos.start > end) {
+            text.push(' ');
+        }
+        text.push_str(&token.text);
+        end = Some(token.pos.start + token.pos.length);
+    }
+    text
+}
+
+/// Whether the kind is a keyword of a net type, a variable or a built-in data type
+fn is_type_keyword(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Wire
+            | Kind::Reg
+            | Kind::Integer
+            | Kind::Real
+            | Kind::Realtime
+            | Kind::Time
+            | Kind::Tri
+            | Kind::Tri0
+            | Kind::Tri1
+            | Kind::Triand
+            | Kind::Trior
+            | Kind::Trireg
+            | Kind::Wand
+            | Kind::Wor
+            | Kind::Uwire
+            | Kind::Supply0
+            | Kind::Supply1
+            | Kind::Var
+            | Kind::Logic
+            | Kind::Bit
+            | Kind::Byte
+            | Kind::Shortint
+            | Kind::Int
+            | Kind::Longint
+            | Kind::Shortreal
+    )
+}
+
+struct Parser<'a> {
+    source: &'a Source,
+    tokens: &'a [Token],
+    idx: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_kind(&self) -> Option<Kind> {
+        self.peek_kind_at(0)
+    }
+
+    fn peek_kind_at(&self, offset: usize) -> Option<Kind> {
+        self.tokens.get(self.idx + offset).map(|token| token.kind)
+    }
+
+    fn skip_if(&mut self, kind: Kind) -> bool {
+        if self.peek_kind() == Some(kind) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eof_error(&self) -> Message {
+        let pos = match self.tokens.last() {
+            Some(token) => token.pos.clone(),
+            None => self.source.pos(0, 0),
+        };
+        error(pos, "Unexpected end of file")
+    }
+
+    fn expect(&mut self, kind: Kind, expected: &str) -> Result<&'a Token, Message> {
+        match self.tokens.get(self.idx) {
+            Some(token) if token.kind == kind => {
+                self.idx += 1;
+                Ok(token)
+            }
+            Some(token) => Err(error(
+                token,
+                &format!("Expected {}, got '{}'", expected, token.text),
+            )),
+            None => Err(self.eof_error()),
+        }
+    }
+
+    fn expect_name(&mut self) -> Result<WithPos<String>, Message> {
+        let token = self.expect(Kind::Identifier, "identifier")?;
+        Ok(WithPos::new(token.text.clone(), &token.pos))
+    }
+
+    /// Skips past the next token of the kind
+    fn skip_past(&mut self, kind: Kind) {
+        while let Some(next) = self.peek_kind() {
+            self.idx += 1;
+            if next == kind {
+                break;
+            }
+        }
+    }
+
+    /// The tokens until one of the kinds outside of parentheses, brackets and braces
+    fn tokens_until(&mut self, kinds: &[Kind]) -> Result<&'a [Token], Message> {
+        let start = self.idx;
+        let mut depth = 0usize;
+        loop {
+            let kind = self.peek_kind().ok_or_else(|| self.eof_error())?;
+            if depth == 0 && kinds.contains(&kind) {
+                return Ok(&self.tokens[start..self.idx]);
+            }
+            match kind {
+                Kind::LeftPar | Kind::LeftSquare | Kind::LeftBrace => depth += 1,
+                Kind::RightPar | Kind::RightSquare | Kind::RightBrace => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            self.idx += 1;
+        }
+    }
+
+    /// A range such as [7:0] where the colon of a conditional expression is not its separator
+    fn parse_range(&mut self) -> Result<VerilogRange, Message> {
+        let left_square = self.expect(Kind::LeftSquare, "'['")?;
+        let tokens = self.tokens_until(&[Kind::RightSquare])?;
+        self.idx += 1;
+        let mut conditionals = 0usize;
+        for (i, token) in tokens.iter().enumerate() {
+            match token.kind {
+                Kind::Question => conditionals += 1,
+                Kind::Colon if conditionals > 0 => conditionals -= 1,
+                Kind::Colon => {
+                    return Ok(VerilogRange {
+                        left: tokens_text(&tokens[..i]),
+                        right: tokens_text(&tokens[i + 1..]),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Err(error(left_square, "Expected a range such as [7:0]"))
+    }
+
+    /// Skips unpacked dimensions such as those of memories
+    fn skip_dimensions(&mut self) -> Result<(), Message> {
+        while self.skip_if(Kind::LeftSquare) {
+            self.tokens_until(&[Kind::RightSquare])?;
+            self.idx += 1;
+        }
+        Ok(())
+    }
+
+    /// The end of the user defined type such as pkg::word_t [3:0] at the current position
+    /// when it is followed by a name
+    fn user_type_end(&self) -> Option<usize> {
+        let mut end = self.idx;
+        if self.tokens.get(end)?.kind != Kind::Identifier {
+            return None;
+        }
+        end += 1;
+        while self.tokens.get(end)?.text == "::"
+            && self.tokens.get(end + 1)?.kind == Kind::Identifier
+        {
+            end += 2;
+        }
+        let type_end = end;
+        let mut depth = 0usize;
+        while depth > 0 || self.tokens.get(end)?.kind == Kind::LeftSquare {
+            match self.tokens.get(end)?.kind {
+                Kind::LeftSquare => depth += 1,
+                Kind::RightSquare => depth -= 1,
+                _ => {}
+            }
+            end += 1;
+        }
+        if self.tokens.get(end)?.kind == Kind::Identifier {
+            Some(type_end)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a data type starts at the current position
+    fn is_data_type(&self) -> bool {
+        self.peek_kind().is_some_and(is_type_keyword)
+            || matches!(self.peek_kind(), Some(Kind::Signed) | Some(Kind::Unsigned))
+            || self.user_type_end().is_some()
+    }
+
+    fn parse_data_type(&mut self) -> Result<DataType, Message> {
+        let mut parts: Vec<String> = Vec::new();
+        while self.peek_kind().is_some_and(is_type_keyword) {
+            parts.push(self.tokens[self.idx].text.clone());
+            self.idx += 1;
+        }
+        if let Some(end) = self.user_type_end() {
+            parts.push(tokens_text(&self.tokens[self.idx..end]));
+            self.idx = end;
+        }
+        let signed = self.skip_if(Kind::Signed);
+        if !signed {
+            self.skip_if(Kind::Unsigned);
+        }
+        let mut ranges = Vec::new();
+        while self.peek_kind() == Some(Kind::LeftSquare) {
+            ranges.push(self.parse_range()?);
+        }
+        Ok(DataType {
+            data_type: if parts.is_empty() {
+                None
+            } else {
+                Some(parts.join(" "))
+            },
+            signed,
+            ranges,
+        })
+    }
+
+    fn direction(&mut self) -> Option<PortDirection> {
+        let direction = match self.peek_kind() {
+            Some(Kind::Input) => PortDirection::Input,
+            Some(Kind::Output) => PortDirection::Output,
+            Some(Kind::Inout) => PortDirection::Inout,
+            Some(Kind::Ref) => PortDirection::Ref,
+            _ => return None,
+        };
+        self.idx += 1;
+        Some(direction)
+    }
+
+    fn port(name: WithPos<String>, direction: PortDirection, data_type: &DataType) -> VerilogPort {
+        VerilogPort {
+            name,
+            direction,
+            data_type: data_type.data_type.clone(),
+            signed: data_type.signed,
+            ranges: data_type.ranges.clone(),
+            interface: None,
+        }
+    }
+
+    /// The name and data type of a parameter after the keyword such as int unsigned W
+    fn parse_parameter(&mut self, local: bool) -> Result<VerilogParameter, Message> {
+        let is_type = self.skip_if(Kind::Type);
+        let data_type = if is_type {
+            None
+        } else {
+            self.parse_data_type()?.data_type
+        };
+        let name = self.expect_name()?;
+        self.skip_dimensions()?;
+        Ok(VerilogParameter {
+            name,
+            data_type,
+            default: None,
+            local,
+            is_type,
+        })
+    }
+
+    /// The parameters of #( ... ) where the keyword and type carry over to later names
+    fn parse_parameter_ports(&mut self) -> Result<Vec<VerilogParameter>, Message> {
+        self.expect(Kind::LeftPar, "'('")?;
+        let mut parameters = Vec::new();
+        let mut local = false;
+        if self.skip_if(Kind::RightPar) {
+            return Ok(parameters);
+        }
+        loop {
+            let mut parameter = if self.skip_if(Kind::Localparam) {
+                local = true;
+                self.parse_parameter(local)?
+            } else if self.skip_if(Kind::Parameter) {
+                local = false;
+                self.parse_parameter(local)?
+            } else {
+                let mut parameter = self.parse_parameter(local)?;
+                // The data type carries over to names without one
+                if let Some(previous) = parameters.last() {
+                    if parameter.data_type.is_none() && !parameter.is_type {
+                        parameter.data_type = previous.data_type.clone();
+                        parameter.is_type = previous.is_type;
+                    }
+                }
+                parameter
+            };
+            if self.skip_if(Kind::Equal) {
+                parameter.default = Some(tokens_text(
+                    self.tokens_until(&[Kind::Comma, Kind::RightPar])?,
+                ));
+            }
+            parameters.push(parameter);
+            if !self.skip_if(Kind::Comma) {
+                self.expect(Kind::RightPar, "',' or ')'")?;
+                return Ok(parameters);
+            }
+        }
+    }
+
+    /// The parameter or localparam declaration of the module body
+    fn parse_parameter_declaration(
+        &mut self,
+        local: bool,
+    ) -> Result<Vec<VerilogParameter>, Message> {
+        self.idx += 1;
+        let first = self.parse_parameter(local)?;
+        let mut parameters = Vec::new();
+        let mut parameter = first.clone();
+        loop {
+            self.expect(Kind::Equal, "'='")?;
+            parameter.default = Some(tokens_text(
+                self.tokens_until(&[Kind::Comma, Kind::SemiColon])?,
+            ));
+            parameters.push(parameter);
+            if !self.skip_if(Kind::Comma) {
+                self.expect(Kind::SemiColon, "',' or ';'")?;
+                return Ok(parameters);
+            }
+            parameter = VerilogParameter {
+                name: self.expect_name()?,
+                ..first.clone()
+            };
+            self.skip_dimensions()?;
+        }
+    }
+
+    /// The interface of an interface port such as axi_if.master or interface at the current
+    /// position with its number of tokens, the name of the port follows
+    fn peek_interface_port(&self) -> Option<(InterfacePort, usize)> {
+        let interface = match (self.peek_kind()?, self.peek_kind_at(1)?) {
+            (Kind::Interface, _) => None,
+            (Kind::Identifier, Kind::Identifier) | (Kind::Identifier, Kind::Dot) => {
+                Some(self.tokens[self.idx].text.clone())
+            }
+            _ => return None,
+        };
+        if self.peek_kind_at(1) == Some(Kind::Dot) {
+            if self.peek_kind_at(2)? != Kind::Identifier
+                || self.peek_kind_at(3)? != Kind::Identifier
+            {
+                return None;
+            }
+            let modport = Some(self.tokens[self.idx + 2].text.clone());
+            Some((InterfacePort { interface, modport }, 3))
+        } else {
+            Some((
+                InterfacePort {
+                    interface,
+                    modport: None,
+                },
+                1,
+            ))
+        }
+    }
+
+    /// The ports of an ANSI port list, or only the names when the directions are declared
+    /// in the module body
+    fn parse_port_list(&mut self) -> Result<(Vec<VerilogPort>, Vec<WithPos<String>>), Message> {
+        self.expect(Kind::LeftPar, "'('")?;
+        let mut ports = Vec::new();
+        let mut names = Vec::new();
+        if self.skip_if(Kind::RightPar) {
+            return Ok((ports, names));
+        }
+        let mut declaration: Option<(PortDirection, DataType)> = None;
+        let mut interface = None;
+        loop {
+            if let Some(direction) = self.direction() {
+                declaration = Some((direction, self.parse_data_type()?));
+                interface = None;
+            } else if let Some((port, length)) = self.peek_interface_port().filter(|(port, _)| {
+                // A type name followed by the name of a port inherits the previous direction
+                port.modport.is_some() || declaration.is_none() || interface.is_some()
+            }) {
+                self.idx += length;
+                interface = Some(port);
+                declaration = Some((
+                    PortDirection::Inout,
+                    DataType {
+                        data_type: None,
+                        signed: false,
+                        ranges: Vec::new(),
+                    },
+                ));
+            } else if self.is_data_type() {
+                // A data type without direction inherits the previous one, the first is inout
+                let direction = declaration
+                    .as_ref()
+                    .map_or(PortDirection::Inout, |(direction, _)| *direction);
+                declaration = Some((direction, self.parse_data_type()?));
+                interface = None;
+            }
+            match declaration {
+                Some((direction, ref data_type)) => {
+                    let name = self.expect_name()?;
+                    self.skip_dimensions()?;
+                    if self.skip_if(Kind::Equal) {
+                        self.tokens_until(&[Kind::Comma, Kind::RightPar])?;
+                    }
+                    let mut port = Parser::port(name, direction, data_type);
+                    port.interface = interface.clone();
+                    ports.push(port);
+                }
+                None => {
+                    // A port name or an explicitly named port such as .data(data_i)
+                    let named = self.skip_if(Kind::Dot);
+                    names.push(self.expect_name()?);
+                    if named {
+                        self.tokens_until(&[Kind::Comma, Kind::RightPar])?;
+                    } else {
+                        self.skip_dimensions()?;
+                    }
+                }
+            }
+            if !self.skip_if(Kind::Comma) {
+                self.expect(Kind::RightPar, "',' or ')'")?;
+                return Ok((ports, names));
+            }
+        }
+    }
+
+    /// The names of a port or net declaration of the module body until the semicolon
+    fn parse_declared_names(&mut self) -> Result<Vec<WithPos<String>>, Message> {
+        let mut names = Vec::new();
+        loop {
+            names.push(self.expect_name()?);
+            self.skip_dimensions()?;
+            self.tokens_until(&[Kind::Comma, Kind::SemiColon])?;
+            if !self.skip_if(Kind::Comma) {
+                self.expect(Kind::SemiColon, "',' or ';'")?;
+                return Ok(names);
+            }
+        }
+    }
+
+    /// A modport such as modport master (output valid, input ready, import send)
+    fn parse_modports(&mut self) -> Result<Vec<VerilogModport>, Message> {
+        self.idx += 1;
+        let mut modports = Vec::new();
+        loop {
+            let name = self.expect_name()?;
+            self.expect(Kind::LeftPar, "'('")?;
+            let mut ports = Vec::new();
+            let mut direction = None;
+            loop {
+                match self.peek_kind() {
+                    Some(Kind::RightPar) => {
+                        self.idx += 1;
+                        break;
+                    }
+                    Some(Kind::Comma) => self.idx += 1,
+                    Some(Kind::Import) | Some(Kind::Export) | Some(Kind::Clocking) => {
+                        direction = None;
+                        self.idx += 1;
+                    }
+                    Some(Kind::Dot) => {
+                        // A modport expression such as .data(bus[7:0])
+                        self.idx += 1;
+                        let port_name = self.expect_name()?;
+                        self.tokens_until(&[Kind::Comma, Kind::RightPar])?;
+                        if let Some(direction) = direction {
+                            ports.push((port_name, direction));
+                        }
+                    }
+                    Some(Kind::Identifier) => {
+                        let port_name = self.expect_name()?;
+                        if let Some(direction) = direction {
+                            ports.push((port_name, direction));
+                        } else {
+                            // Such as the prototype of an imported task
+                            self.tokens_until(&[Kind::Comma, Kind::RightPar])?;
+                        }
+                    }
+                    Some(_) => match self.direction() {
+                        Some(port_direction) => direction = Some(port_direction),
+                        None => self.idx += 1,
+                    },
+                    None => return Err(self.eof_error()),
+                }
+            }
+            modports.push(VerilogModport { name, ports });
+            if !self.skip_if(Kind::Comma) {
+                self.expect(Kind::SemiColon, "',' or ';'")?;
+                return Ok(modports);
+            }
+        }
+    }
+
+    /// A module or interface until the end keyword
+    fn parse_module(
+        &mut self,
+        end: Kind,
+        messages: &mut dyn MessageHandler,
+    ) -> Result<VerilogModule, Message> {
+        let keyword = self.tokens[self.idx].text.clone();
+        self.idx += 1;
+        if !self.skip_if(Kind::Automatic) {
+            self.skip_if(Kind::Static);
+        }
+        let name = self.expect_name()?;
+        while self.skip_if(Kind::Import) {
+            self.tokens_until(&[Kind::SemiColon])?;
+            self.idx += 1;
+        }
+        let mut parameters = if self.skip_if(Kind::Hash) {
+            self.parse_parameter_ports()?
+        } else {
+            Vec::new()
+        };
+        let has_parameter_ports = !parameters.is_empty();
+        let (mut ports, names) = if self.peek_kind() == Some(Kind::LeftPar) {
+            self.parse_port_list()?
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        self.expect(Kind::SemiColon, "';'")?;
+
+        // The declarations of the ports of a non-ANSI port list
+        let mut declared: BTreeMap<String, VerilogPort> = BTreeMap::new();
+        let mut net_types: BTreeMap<String, DataType> = BTreeMap::new();
+        let mut modports = Vec::new();
+        loop {
+            match self.peek_kind() {
+                None => return Err(error(&name, &format!("Missing end{}", keyword))),
+                Some(kind) if kind == end => {
+                    self.idx += 1;
+                    break;
+                }
+                Some(Kind::Input) | Some(Kind::Output) | Some(Kind::Inout) | Some(Kind::Ref) => {
+                    let direction = self.direction().unwrap();
+                    let data_type = self.parse_data_type()?;
+                    for port_name in self.parse_declared_names()? {
+                        if !names.iter().any(|name| name.item == port_name.item) {
+                            return Err(error(
+                                &port_name,
+                                &format!("'{}' is not a port of the {}", port_name.item, keyword),
+                            ));
+                        }
+                        declared.insert(
+                            port_name.item.clone(),
+                            Parser::port(port_name, direction, &data_type),
+                        );
+                    }
+                }
+                Some(Kind::Parameter) => {
+                    parameters.extend(self.parse_parameter_declaration(has_parameter_ports)?);
+                }
+                Some(Kind::Localparam) => {
+                    parameters.extend(self.parse_parameter_declaration(true)?);
+                }
+                Some(Kind::Modport) => modports.extend(self.parse_modports()?),
+                Some(kind) if is_type_keyword(kind) => {
+                    let start = self.idx;
+                    let data_type = self.parse_data_type()?;
+                    if self.peek_kind() == Some(Kind::Identifier) {
+                        for net_name in self.parse_declared_names()? {
+                            net_types.insert(net_name.item, data_type.clone());
+                        }
+                    } else {
+                        // Such as a net declaration with a delay or drive strength
+                        self.idx = start + 1;
+                    }
+                }
+                Some(Kind::Function) => self.skip_past(Kind::Endfunction),
+                Some(Kind::Task) => self.skip_past(Kind::Endtask),
+                Some(Kind::Specify) => self.skip_past(Kind::Endspecify),
+                Some(Kind::Class) => self.skip_past(Kind::Endclass),
+                Some(Kind::Clocking) => self.skip_past(Kind::Endclocking),
+                Some(Kind::Covergroup) => self.skip_past(Kind::Endgroup),
+                Some(Kind::Property) => self.skip_past(Kind::Endproperty),
+                Some(Kind::Sequence) => self.skip_past(Kind::Endsequence),
+                Some(_) => self.idx += 1,
+            }
+        }
+
+        for name in names.into_iter() {
+            match declared.remove(&name.item) {
+                Some(mut port) => {
+                    // The type and range of a port may be given by a separate declaration
+                    if let Some(net_type) = net_types.remove(&name.item) {
+                        if port.data_type.is_none() {
+                            port.data_type = net_type.data_type;
+                        }
+                        port.signed |= net_type.signed;
+                        if port.ranges.is_empty() {
+                            port.ranges = net_type.ranges;
+                        }
+                    }
+                    ports.push(port);
+                }
+                None => messages.push(error(
+                    &name,
+                    &format!("Missing direction of port '{}'", name.item),
+                )),
+            }
+        }
+
+        Ok(VerilogModule {
+            name,
+            parameters,
+            ports,
+            modports,
+        })
+    }
+
+    fn parse_file(&mut self, file: &mut VerilogFile, messages: &mut dyn MessageHandler) {
+        while let Some(kind) = self.peek_kind() {
+            let (end, modules) = match kind {
+                Kind::Module | Kind::Macromodule => (Kind::Endmodule, &mut file.modules),
+                Kind::Interface => (Kind::Endinterface, &mut file.interfaces),
+                Kind::Primitive => {
+                    self.skip_past(Kind::Endprimitive);
+                    continue;
+                }
+                Kind::Package => {
+                    self.skip_past(Kind::Endpackage);
+                    continue;
+                }
+                Kind::Program => {
+                    self.skip_past(Kind::Endprogram);
+                    continue;
+                }
+                Kind::Class => {
+                    self.skip_past(Kind::Endclass);
+                    continue;
+                }
+                _ => {
+                    self.idx += 1;
+                    continue;
+                }
+            };
+            match self.parse_module(end, messages) {
+                Ok(module) => modules.push(module),
+                Err(err) => {
+                    messages.push(err);
+                    self.skip_past(end);
+                }
+            }
+        }
+    }
+}
+
+/// The modules and interfaces of the Verilog source, syntax errors are added to the
+/// messages and the module with the error is skipped
+pub fn parse_verilog_source(
+    source: &Source,
+    options: &VerilogOptions,
+    messages: &mut dyn MessageHandler,
+) -> io::Result<VerilogFile> {
+    let code = source.contents()?;
+    let mut tokenizer = Tokenizer::with_options(source.clone(), code, options);
+    let tokens = tokenize(&mut tokenizer, messages);
+    let mut parser = Parser {
+        source,
+        tokens: &tokens,
+        idx: 0,
+    };
+    let mut file = VerilogFile::default();
+    parser.parse_file(&mut file, messages);
+    file.defines = tokenizer.defines().clone();
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(code: &str) -> (VerilogFile, Vec<Message>) {
+        let source = Source::from_str(code).unwrap();
+        let mut messages = Vec::new();
+        let file =
+            parse_verilog_source(&source, &VerilogOptions::default(), &mut messages).unwrap();
+        (file, messages)
+    }
+
+    fn parse_systemverilog(code: &str) -> VerilogFile {
+        let source = Source::from_str(code).unwrap();
+        let options = VerilogOptions {
+            systemverilog: true,
+            ..VerilogOptions::default()
+        };
+        let mut messages = Vec::new();
+        let file = parse_verilog_source(&source, &options, &mut messages).unwrap();
+        assert_eq!(messages, vec![]);
+        file
+    }
+
+    fn parse_ok(code: &str) -> VerilogFile {
+        let (file, messages) = parse(code);
+        assert_eq!(messages, vec![]);
+        file
+    }
+
+    fn port_summary(module: &VerilogModule) -> Vec<(&str, PortDirection, Option<&str>, String)> {
+        module
+            .ports
+            .iter()
+            .map(|port| {
+                (
+                    port.name.item.as_str(),
+                    port.direction,
+                    port.data_type.as_deref(),
+                    port.ranges
+                        .iter()
+                        .map(|range| format!("[{}:{}]", range.left, range.right))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ansi_module_header() {
+        let file = parse_ok(
+            "`timescale 1ns / 1ps
+module fifo #(
+  parameter integer WIDTH = 8,
+  DEPTH = (1 << 4),
+  localparam AW = $clog2(DEPTH)
+) (
+  input wire clk, rst,
+  input [WIDTH-1:0] din,
+  output reg signed [WIDTH > 8 ? 15 : 7:0] dout,
+  inout [3:0] mem [0:7]
+);
+  parameter HIDDEN = 1;
+  function [7:0] f(input [7:0] a);
+    f = a;
+  endfunction
+  assign x = din;
+endmodule",
+        );
+        assert_eq!(file.modules.len(), 1);
+        let module = &file.modules[0];
+        assert_eq!(module.name.item, "fifo");
+        let parameters: Vec<(&str, Option<&str>, bool)> = module
+            .parameters
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.item.as_str(),
+                    parameter.default.as_deref(),
+                    parameter.local,
+                )
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("WIDTH", Some("8"), false),
+                ("DEPTH", Some("(1 << 4)"), false),
+                ("AW", Some("$clog2(DEPTH)"), true),
+                ("HIDDEN", Some("1"), true),
+            ]
+        );
+        assert_eq!(
+            port_summary(module),
+            vec![
+                ("clk", PortDirection::Input, Some("wire"), "".to_owned()),
+                ("rst", PortDirection::Input, Some("wire"), "".to_owned()),
+                ("din", PortDirection::Input, None, "[WIDTH-1:0]".to_owned()),
+                (
+                    "dout",
+                    PortDirection::Output,
+                    Some("reg"),
+                    "[WIDTH > 8 ? 15 : 7:0]".to_owned()
+                ),
+                ("mem", PortDirection::Inout, None, "[3:0]".to_owned()),
+            ]
+        );
+        assert!(module.port("dout").unwrap().signed);
+        assert!(!module.port("din").unwrap().signed);
+    }
+
+    #[test]
+    fn non_ansi_module_header() {
+        let file = parse_ok(
+            "module counter (clk, q, .en(enable));
+  parameter WIDTH = 4;
+  input clk;
+  output [WIDTH-1:0] q;
+  reg [WIDTH-1:0] q;
+  input en;
+  wire enable;
+endmodule
+
+module empty;
+endmodule",
+        );
+        let names: Vec<&str> = file
+            .modules
+            .iter()
+            .map(|module| module.name.item.as_str())
+            .collect();
+        assert_eq!(names, vec!["counter", "empty"]);
+        let module = &file.modules[0];
+        assert_eq!(
+            port_summary(module),
+            vec![
+                ("clk", PortDirection::Input, None, "".to_owned()),
+                (
+                    "q",
+                    PortDirection::Output,
+                    Some("reg"),
+                    "[WIDTH-1:0]".to_owned()
+                ),
+                ("en", PortDirection::Input, None, "".to_owned()),
+            ]
+        );
+        assert!(!module.parameter("WIDTH").unwrap().local);
+        assert!(file.modules[1].ports.is_empty());
+    }
+
+    #[test]
+    fn conditional_ports_and_defines() {
+        let code = "`define HAS_RESET
+module m (
+`ifdef HAS_RESET
+  input rst,
+`endif
+  input clk
+);
+endmodule";
+        let file = parse_ok(code);
+        assert_eq!(file.modules[0].ports.len(), 2);
+        assert_eq!(file.defines.get("HAS_RESET").map(String::as_str), Some(""));
+
+        let source = Source::from_str(&code.replacen("`define HAS_RESET", "", 1)).unwrap();
+        let mut messages = Vec::new();
+        let file =
+            parse_verilog_source(&source, &VerilogOptions::default(), &mut messages).unwrap();
+        assert_eq!(messages, vec![]);
+        assert_eq!(file.modules[0].ports.len(), 1);
+    }
+
+    #[test]
+    fn systemverilog_module_header() {
+        let file = parse_systemverilog(
+            "package types;
+  typedef logic [7:0] byte_t;
+endpackage
+
+module automatic top import types::*; #(
+  parameter int unsigned WIDTH = 8, DEPTH = 4,
+  parameter type T = logic [3:0],
+  localparam logic [3:0] MASK = '1
+) (
+  input logic clk,
+  input types::byte_t [1:0] data, other,
+  output logic [WIDTH-1:0][1:0] q = '0,
+  axi_if.master bus,
+  mem_if mem,
+  interface generic,
+  ref int counter
+);
+  clocking cb @(posedge clk);
+    input q;
+  endclocking
+endmodule : top",
+        );
+        let module = &file.modules[0];
+        let parameters: Vec<_> = module
+            .parameters
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.item.as_str(),
+                    parameter.data_type.as_deref(),
+                    parameter.default.as_deref(),
+                    parameter.local,
+                    parameter.is_type,
+                )
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("WIDTH", Some("int"), Some("8"), false, false),
+                ("DEPTH", Some("int"), Some("4"), false, false),
+                ("T", None, Some("logic [3:0]"), false, true),
+                ("MASK", Some("logic"), Some("'1"), true, false),
+            ]
+        );
+        assert_eq!(
+            port_summary(module),
+            vec![
+                ("clk", PortDirection::Input, Some("logic"), "".to_owned()),
+                (
+                    "data",
+                    PortDirection::Input,
+                    Some("types::byte_t"),
+                    "[1:0]".to_owned()
+                ),
+                (
+                    "other",
+                    PortDirection::Input,
+                    Some("types::byte_t"),
+                    "[1:0]".to_owned()
+                ),
+                (
+                    "q",
+                    PortDirection::Output,
+                    Some("logic"),
+                    "[WIDTH-1:0][1:0]".to_owned()
+                ),
+                ("bus", PortDirection::Inout, None, "".to_owned()),
+                ("mem", PortDirection::Inout, None, "".to_owned()),
+                ("generic", PortDirection::Inout, None, "".to_owned()),
+                ("counter", PortDirection::Ref, Some("int"), "".to_owned()),
+            ]
+        );
+        let interfaces: Vec<Option<(Option<&str>, Option<&str>)>> = module
+            .ports
+            .iter()
+            .map(|port| {
+                port.interface
+                    .as_ref()
+                    .map(|interface| (interface.interface.as_deref(), interface.modport.as_deref()))
+            })
+            .collect();
+        assert_eq!(
+            interfaces,
+            vec![
+                None,
+                None,
+                None,
+                None,
+                Some((Some("axi_if"), Some("master"))),
+                Some((Some("mem_if"), None)),
+                Some((None, None)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn systemverilog_interface() {
+        let file = parse_systemverilog(
+            "interface axi_if #(parameter W = 32) (input logic clk);
+  logic valid, ready;
+  logic [W-1:0] data;
+  modport master (output valid, data, input ready, import task send(input int x)),
+          slave (input valid, .payload(data), output ready);
+endinterface",
+        );
+        assert!(file.modules.is_empty());
+        let interface = &file.interfaces[0];
+        assert_eq!(interface.name.item, "axi_if");
+        assert_eq!(interface.ports.len(), 1);
+        let modports: Vec<(&str, Vec<(&str, PortDirection)>)> = interface
+            .modports
+            .iter()
+            .map(|modport| {
+                (
+                    modport.name.item.as_str(),
+                    modport
+                        .ports
+                        .iter()
+                        .map(|(name, direction)| (name.item.as_str(), *direction))
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            modports,
+            vec![
+                (
+                    "master",
+                    vec![
+                        ("valid", PortDirection::Output),
+                        ("data", PortDirection::Output),
+                        ("ready", PortDirection::Input),
+                    ]
+                ),
+                (
+                    "slave",
+                    vec![
+                        ("valid", PortDirection::Input),
+                        ("payload", PortDirection::Input),
+                        ("ready", PortDirection::Output),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn systemverilog_files() {
+        assert!(is_systemverilog_file("top.sv"));
+        assert!(is_systemverilog_file("dir/defs.SVH"));
+        assert!(!is_systemverilog_file("top.v"));
+        assert!(is_verilog_file("top.v"));
+        assert!(is_verilog_file("defs.vh"));
+        assert!(is_verilog_file("top.sv"));
+        assert!(!is_verilog_file("top.vhd"));
+    }
+
+    #[test]
+    fn errors_skip_the_module() {
+        let (file, messages) = parse(
+            "module broken (input a b c);
+endmodule
+
+module undeclared (a, b);
+  input b;
+endmodule
+
+module ok (input a);
+endmodule",
+        );
+        let names: Vec<&str> = file
+            .modules
+            .iter()
+            .map(|module| module.name.item.as_str())
+            .collect();
+        assert_eq!(names, vec!["undeclared", "ok"]);
+        assert_eq!(file.modules[0].ports.len(), 1);
+        let messages: Vec<&str> = messages
+            .iter()
+            .map(|message| message.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Expected ',' or ')', got 'c'",
+                "Missing direction of port 'a'"
+            ]
+        );
+    }
+}
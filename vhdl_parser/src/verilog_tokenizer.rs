@@ -0,0 +1,866 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Tokens of Verilog source code
+//!
+//! Compiler directives are handled while tokenizing, text macros are collected from
+//! `define and the inactive branches of `ifdef and `ifndef are skipped, other
+//! directives such as `timescale are dropped with the rest of their line
+//!
+//! The keywords of SystemVerilog are only reserved for SystemVerilog code since they
+//! are valid identifiers in Verilog code
+
+use latin_1::Latin1String;
+use message::{error, Message, MessageHandler};
+use source::{Source, SrcPos};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use verilog_parser::VerilogOptions;
+
+/// The kind of a Verilog token
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Kind {
+    // Keywords
+    Module,
+    Macromodule,
+    Endmodule,
+    Primitive,
+    Endprimitive,
+    Input,
+    Output,
+    Inout,
+    Parameter,
+    Localparam,
+    Defparam,
+    Wire,
+    Reg,
+    Integer,
+    Real,
+    Realtime,
+    Time,
+    Signed,
+    Unsigned,
+    Tri,
+    Tri0,
+    Tri1,
+    Triand,
+    Trior,
+    Trireg,
+    Wand,
+    Wor,
+    Uwire,
+    Supply0,
+    Supply1,
+    Genvar,
+    Function,
+    Endfunction,
+    Task,
+    Endtask,
+    Generate,
+    Endgenerate,
+    Specify,
+    Endspecify,
+    Begin,
+    End,
+
+    // SystemVerilog keywords
+    Logic,
+    Bit,
+    Byte,
+    Shortint,
+    Int,
+    Longint,
+    Shortreal,
+    Var,
+    Ref,
+    Type,
+    Typedef,
+    Import,
+    Export,
+    Interface,
+    Endinterface,
+    Modport,
+    Package,
+    Endpackage,
+    Program,
+    Endprogram,
+    Class,
+    Endclass,
+    Clocking,
+    Endclocking,
+    Covergroup,
+    Endgroup,
+    Property,
+    Endproperty,
+    Sequence,
+    Endsequence,
+    Automatic,
+    Static,
+
+    Identifier,
+    /// A system task or function such as $clog2
+    SystemIdentifier,
+    /// The use of a text macro such as `WIDTH
+    Macro,
+    Number,
+    StringLiteral,
+
+    LeftPar,
+    RightPar,
+    LeftSquare,
+    RightSquare,
+    LeftBrace,
+    RightBrace,
+    Comma,
+    SemiColon,
+    Colon,
+    Dot,
+    Hash,
+    At,
+    Question,
+    Equal,
+    /// Any other operator such as + or <=
+    Operator,
+}
+
+use self::Kind::*;
+
+fn keyword(name: &str) -> Option<Kind> {
+    Some(match name {
+        "module" => Module,
+        "macromodule" => Macromodule,
+        "endmodule" => Endmodule,
+        "primitive" => Primitive,
+        "endprimitive" => Endprimitive,
+        "input" => Input,
+        "output" => Output,
+        "inout" => Inout,
+        "parameter" => Parameter,
+        "localparam" => Localparam,
+        "defparam" => Defparam,
+        "wire" => Wire,
+        "reg" => Reg,
+        "integer" => Integer,
+        "real" => Real,
+        "realtime" => Realtime,
+        "time" => Time,
+        "signed" => Signed,
+        "unsigned" => Unsigned,
+        "tri" => Tri,
+        "tri0" => Tri0,
+        "tri1" => Tri1,
+        "triand" => Triand,
+        "trior" => Trior,
+        "trireg" => Trireg,
+        "wand" => Wand,
+        "wor" => Wor,
+        "uwire" => Uwire,
+        "supply0" => Supply0,
+        "supply1" => Supply1,
+        "genvar" => Genvar,
+        "function" => Function,
+        "endfunction" => Endfunction,
+        "task" => Task,
+        "endtask" => Endtask,
+        "generate" => Generate,
+        "endgenerate" => Endgenerate,
+        "specify" => Specify,
+        "endspecify" => Endspecify,
+        "begin" => Begin,
+        "end" => End,
+        _ => return None,
+    })
+}
+
+fn systemverilog_keyword(name: &str) -> Option<Kind> {
+    Some(match name {
+        "logic" => Logic,
+        "bit" => Bit,
+        "byte" => Byte,
+        "shortint" => Shortint,
+        "int" => Int,
+        "longint" => Longint,
+        "shortreal" => Shortreal,
+        "var" => Var,
+        "ref" => Ref,
+        "type" => Type,
+        "typedef" => Typedef,
+        "import" => Import,
+        "export" => Export,
+        "interface" => Interface,
+        "endinterface" => Endinterface,
+        "modport" => Modport,
+        "package" => Package,
+        "endpackage" => Endpackage,
+        "program" => Program,
+        "endprogram" => Endprogram,
+        "class" => Class,
+        "endclass" => Endclass,
+        "clocking" => Clocking,
+        "endclocking" => Endclocking,
+        "covergroup" => Covergroup,
+        "endgroup" => Endgroup,
+        "property" => Property,
+        "endproperty" => Endproperty,
+        "sequence" => Sequence,
+        "endsequence" => Endsequence,
+        "automatic" => Automatic,
+        "static" => Static,
+        _ => return keyword(name),
+    })
+}
+
+/// The operators which are longest first so they are matched greedily
+const OPERATORS: &[&str] = &[
+    "<<<=", ">>>=", "===", "!==", "<<<", ">>>", "<<=", ">>=", "**", "==", "!=", "<=", ">=", "&&",
+    "||", "<<", ">>", "~&", "~|", "~^", "^~", "+:", "-:", "->", "+=", "-=", "*=", "/=", "%=", "&=",
+    "|=", "^=", "::", "++", "--", "+", "-", "*", "/", "%", "<", ">", "!", "~", "&", "|", "^", "'",
+    "=>", "*>",
+];
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Token {
+    pub kind: Kind,
+    /// The token as written, the name of escaped identifiers without the backslash
+    pub text: String,
+    pub pos: SrcPos,
+}
+
+impl AsRef<SrcPos> for Token {
+    fn as_ref(&self) -> &SrcPos {
+        &self.pos
+    }
+}
+
+/// The state of an `ifdef, `ifndef or `elsif branch
+struct Conditional {
+    /// Whether the code of the branch is tokenized
+    active: bool,
+    /// Whether a branch has been active so that later branches are not
+    taken: bool,
+    /// Whether the enclosing code is tokenized
+    enclosing_active: bool,
+}
+
+pub struct Tokenizer {
+    source: Source,
+    code: Arc<Latin1String>,
+    idx: usize,
+    /// The text macros defined so far with their text
+    defines: BTreeMap<String, String>,
+    conditionals: Vec<Conditional>,
+    systemverilog: bool,
+}
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_'
+}
+
+fn is_identifier_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'
+}
+
+impl Tokenizer {
+    pub fn new(source: Source, code: Arc<Latin1String>) -> Tokenizer {
+        Tokenizer {
+            source,
+            code,
+            idx: 0,
+            defines: BTreeMap::new(),
+            conditionals: Vec::new(),
+            systemverilog: false,
+        }
+    }
+
+    /// Starts with the text macros of the options and reserves the SystemVerilog keywords
+    /// for SystemVerilog code
+    pub fn with_options(
+        source: Source,
+        code: Arc<Latin1String>,
+        options: &VerilogOptions,
+    ) -> Tokenizer {
+        Tokenizer {
+            defines: options.defines.clone(),
+            systemverilog: options.systemverilog,
+            ..Tokenizer::new(source, code)
+        }
+    }
+
+    /// The text macros defined until the current position
+    pub fn defines(&self) -> &BTreeMap<String, String> {
+        &self.defines
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.code.bytes
+    }
+
+    fn peek_byte(&self, offset: usize) -> Option<u8> {
+        self.bytes().get(self.idx + offset).cloned()
+    }
+
+    fn is_active(&self) -> bool {
+        self.conditionals
+            .last()
+            .is_none_or(|conditional| conditional.active)
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        String::from_utf8_lossy(&self.bytes()[start..end]).into_owned()
+    }
+
+    fn skip_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        while self.peek_byte(0).is_some_and(&predicate) {
+            self.idx += 1;
+        }
+    }
+
+    /// Skips whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), Message> {
+        loop {
+            match (self.peek_byte(0), self.peek_byte(1)) {
+                (Some(byte), _) if byte.is_ascii_whitespace() => self.idx += 1,
+                (Some(b'/'), Some(b'/')) => self.skip_while(|byte| byte != b'\n'),
+                (Some(b'/'), Some(b'*')) => {
+                    let start = self.idx;
+                    self.idx += 2;
+                    loop {
+                        match (self.peek_byte(0), self.peek_byte(1)) {
+                            (Some(b'*'), Some(b'/')) => {
+                                self.idx += 2;
+                                break;
+                            }
+                            (Some(_), _) => self.idx += 1,
+                            (None, _) => {
+                                return Err(error(
+                                    self.source.pos(start, 2),
+                                    "Unterminated block comment",
+                                ));
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// The rest of the line after a directive where a backslash continues it on the next line
+    fn rest_of_line(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(byte) = self.peek_byte(0) {
+            if byte == b'\n' {
+                break;
+            } else if byte == b'\\' && self.peek_byte(1) == Some(b'\n') {
+                text.push('\n');
+                self.idx += 2;
+            } else if byte == b'/' && self.peek_byte(1) == Some(b'/') {
+                self.skip_while(|byte| byte != b'\n');
+            } else {
+                text.push(byte as char);
+                self.idx += 1;
+            }
+        }
+        text.trim().to_owned()
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_while(|byte| byte == b' ' || byte == b'\t');
+        let start = self.idx;
+        self.skip_while(is_identifier_char);
+        if self.idx > start {
+            Some(self.text(start, self.idx))
+        } else {
+            None
+        }
+    }
+
+    /// Handles the directive with the name, returning the token of a macro use
+    fn directive(&mut self, start: usize, name: &str) -> Result<Option<Token>, Message> {
+        let pos = self.source.pos(start, self.idx - start);
+        match name {
+            "ifdef" | "ifndef" => {
+                let macro_name = self
+                    .identifier()
+                    .ok_or_else(|| error(&pos, &format!("Expected macro name after `{}", name)))?;
+                let defined = self.defines.contains_key(&macro_name);
+                let enclosing_active = self.is_active();
+                let active = enclosing_active && (defined == (name == "ifdef"));
+                self.conditionals.push(Conditional {
+                    active,
+                    taken: active,
+                    enclosing_active,
+                });
+            }
+            "elsif" => {
+                let macro_name = self
+                    .identifier()
+                    .ok_or_else(|| error(&pos, "Expected macro name after `elsif"))?;
+                let defined = self.defines.contains_key(&macro_name);
+                let conditional = self
+                    .conditionals
+                    .last_mut()
+                    .ok_or_else(|| error(&pos, "`elsif without `ifdef"))?;
+                conditional.active = conditional.enclosing_active && !conditional.taken && defined;
+                conditional.taken |= conditional.active;
+            }
+            "else" => {
+                let conditional = self
+                    .conditionals
+                    .last_mut()
+                    .ok_or_else(|| error(&pos, "`else without `ifdef"))?;
+                conditional.active = conditional.enclosing_active && !conditional.taken;
+                conditional.taken = true;
+            }
+            "endif" => {
+                self.conditionals
+                    .pop()
+                    .ok_or_else(|| error(&pos, "`endif without `ifdef"))?;
+            }
+            _ if !self.is_active() => {}
+            "define" => {
+                let macro_name = self
+                    .identifier()
+                    .ok_or_else(|| error(&pos, "Expected macro name after `define"))?;
+                let text = self.rest_of_line();
+                self.defines.insert(macro_name, text);
+            }
+            "undef" => {
+                if let Some(macro_name) = self.identifier() {
+                    self.defines.remove(&macro_name);
+                }
+            }
+            "timescale"
+            | "include"
+            | "default_nettype"
+            | "line"
+            | "pragma"
+            | "begin_keywords"
+            | "resetall"
+            | "celldefine"
+            | "endcelldefine"
+            | "unconnected_drive"
+            | "nounconnected_drive"
+            | "end_keywords"
+            | "undefineall" => {
+                self.rest_of_line();
+            }
+            _ => {
+                return Ok(Some(Token {
+                    kind: Macro,
+                    text: format!("`{}", name),
+                    pos,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The length of the number at the current position
+    fn number_length(&self) -> usize {
+        let bytes = &self.bytes()[self.idx..];
+        let mut length = 0;
+        let digits = |bytes: &[u8], start: usize, predicate: &dyn Fn(u8) -> bool| {
+            let mut end = start;
+            while end < bytes.len() && predicate(bytes[end]) {
+                end += 1;
+            }
+            end
+        };
+        let decimal = |byte: u8| byte.is_ascii_digit() || byte == b'_';
+        length = digits(bytes, length, &decimal);
+        if length > 0 && bytes.get(length) == Some(&b'.') {
+            length = digits(bytes, length + 1, &decimal);
+        }
+        if length > 0 && matches!(bytes.get(length), Some(b'e') | Some(b'E')) {
+            let sign = usize::from(matches!(bytes.get(length + 1), Some(b'+') | Some(b'-')));
+            let end = digits(bytes, length + 1 + sign, &decimal);
+            if end > length + 1 + sign {
+                length = end;
+            }
+        }
+
+        // A based number such as 8'hff or 'b0 where the size may be followed by whitespace
+        let mut base = length;
+        while bytes
+            .get(base)
+            .is_some_and(|byte| *byte == b' ' || *byte == b'\t')
+        {
+            base += 1;
+        }
+        if bytes.get(base) == Some(&b'\'') {
+            let mut digit_start = base + 1;
+            if matches!(bytes.get(digit_start), Some(b's') | Some(b'S')) {
+                digit_start += 1;
+            }
+            if bytes
+                .get(digit_start)
+                .is_some_and(|byte| matches!(byte.to_ascii_lowercase(), b'b' | b'o' | b'd' | b'h'))
+            {
+                let mut value_start = digit_start + 1;
+                while bytes
+                    .get(value_start)
+                    .is_some_and(|byte| *byte == b' ' || *byte == b'\t')
+                {
+                    value_start += 1;
+                }
+                let end = digits(bytes, value_start, &|byte| {
+                    byte.is_ascii_hexdigit()
+                        || matches!(byte, b'_' | b'x' | b'X' | b'z' | b'Z' | b'?')
+                });
+                if end > value_start {
+                    length = end;
+                }
+            } else if length == 0
+                && matches!(
+                    bytes.get(base + 1),
+                    Some(b'0') | Some(b'1') | Some(b'x') | Some(b'X') | Some(b'z') | Some(b'Z')
+                )
+                && !bytes
+                    .get(base + 2)
+                    .is_some_and(|byte| is_identifier_char(*byte))
+            {
+                // An unbased unsized literal such as '0
+                length = base + 2;
+            }
+        }
+        length
+    }
+
+    fn token(&mut self, kind: Kind, start: usize) -> Token {
+        Token {
+            kind,
+            text: self.text(start, self.idx),
+            pos: self.source.pos(start, self.idx - start),
+        }
+    }
+
+    /// The next token which is not within an inactive branch of a conditional directive
+    pub fn pop(&mut self) -> Result<Option<Token>, Message> {
+        loop {
+            self.skip_trivia()?;
+            let start = self.idx;
+            let byte = match self.peek_byte(0) {
+                Some(byte) => byte,
+                None => {
+                    return match self.conditionals.pop() {
+                        Some(_) => Err(error(
+                            self.source.pos(start, 0),
+                            "Missing `endif at the end of the file",
+                        )),
+                        None => Ok(None),
+                    };
+                }
+            };
+
+            if byte == b'`' {
+                self.idx += 1;
+                self.skip_while(is_identifier_char);
+                let name = self.text(start + 1, self.idx);
+                if name.is_empty() {
+                    return Err(error(
+                        self.source.pos(start, 1),
+                        "Expected a directive or macro name after `",
+                    ));
+                }
+                if let Some(token) = self.directive(start, &name)? {
+                    return Ok(Some(token));
+                }
+                continue;
+            }
+
+            if !self.is_active() {
+                if byte == b'"' {
+                    self.skip_string()?;
+                } else {
+                    self.idx += 1;
+                }
+                continue;
+            }
+
+            let number_length = self.number_length();
+            let token = if is_identifier_start(byte) {
+                self.skip_while(is_identifier_char);
+                let text = self.text(start, self.idx);
+                let kind = if self.systemverilog {
+                    systemverilog_keyword(&text)
+                } else {
+                    keyword(&text)
+                };
+                Token {
+                    kind: kind.unwrap_or(Identifier),
+                    pos: self.source.pos(start, self.idx - start),
+                    text,
+                }
+            } else if byte == b'\\' {
+                self.skip_while(|byte| !byte.is_ascii_whitespace());
+                Token {
+                    kind: Identifier,
+                    text: self.text(start + 1, self.idx),
+                    pos: self.source.pos(start, self.idx - start),
+                }
+            } else if byte == b'$' {
+                self.idx += 1;
+                self.skip_while(is_identifier_char);
+                self.token(SystemIdentifier, start)
+            } else if byte == b'"' {
+                self.skip_string()?;
+                self.token(StringLiteral, start)
+            } else if number_length > 0 {
+                self.idx += number_length;
+                self.token(Number, start)
+            } else {
+                let kind = match byte {
+                    b'(' => Some(LeftPar),
+                    b')' => Some(RightPar),
+                    b'[' => Some(LeftSquare),
+                    b']' => Some(RightSquare),
+                    b'{' => Some(LeftBrace),
+                    b'}' => Some(RightBrace),
+                    b',' => Some(Comma),
+                    b';' => Some(SemiColon),
+                    b'.' => Some(Dot),
+                    b'#' => Some(Hash),
+                    b'@' => Some(At),
+                    b'?' => Some(Question),
+                    b':' if self.peek_byte(1) != Some(b':') => Some(Colon),
+                    b'=' if !matches!(self.peek_byte(1), Some(b'=') | Some(b'>')) => Some(Equal),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    self.idx += 1;
+                    self.token(kind, start)
+                } else {
+                    let rest = &self.bytes()[self.idx..];
+                    let length = OPERATORS
+                        .iter()
+                        .find(|operator| rest.starts_with(operator.as_bytes()))
+                        .map(|operator| operator.len());
+                    match length {
+                        Some(length) => {
+                            self.idx += length;
+                            self.token(Operator, start)
+                        }
+                        None => {
+                            self.idx += 1;
+                            return Err(error(
+                                self.source.pos(start, 1),
+                                &format!("Illegal character '{}'", byte as char),
+                            ));
+                        }
+                    }
+                }
+            };
+            return Ok(Some(token));
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<(), Message> {
+        let start = self.idx;
+        self.idx += 1;
+        loop {
+            match self.peek_byte(0) {
+                Some(b'\\') => self.idx += 2,
+                Some(b'"') => {
+                    self.idx += 1;
+                    return Ok(());
+                }
+                Some(b'\n') | None => {
+                    return Err(error(self.source.pos(start, 1), "Unterminated string"));
+                }
+                Some(_) => self.idx += 1,
+            }
+        }
+    }
+}
+
+/// All tokens of the code where errors are reported and the invalid characters skipped
+pub fn tokenize(tokenizer: &mut Tokenizer, messages: &mut dyn MessageHandler) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    loop {
+        match tokenizer.pop() {
+            Ok(Some(token)) => tokens.push(token),
+            Ok(None) => break,
+            Err(err) => messages.push(err),
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize_code(code: &str) -> (Vec<Token>, Vec<Message>) {
+        let source = Source::from_str(code).unwrap();
+        let mut tokenizer = Tokenizer::new(source.clone(), source.contents().unwrap());
+        let mut messages = Vec::new();
+        let tokens = tokenize(&mut tokenizer, &mut messages);
+        (tokens, messages)
+    }
+
+    fn kinds_and_texts(code: &str) -> Vec<(Kind, String)> {
+        let (tokens, messages) = tokenize_code(code);
+        assert_eq!(messages, vec![]);
+        tokens
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    fn texts(code: &str) -> Vec<String> {
+        kinds_and_texts(code)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn keywords_identifiers_and_punctuation() {
+        assert_eq!(
+            kinds_and_texts("module fifo_1$ (input wire [7:0] \\data.in , output q);"),
+            vec![
+                (Module, "module".to_owned()),
+                (Identifier, "fifo_1$".to_owned()),
+                (LeftPar, "(".to_owned()),
+                (Input, "input".to_owned()),
+                (Wire, "wire".to_owned()),
+                (LeftSquare, "[".to_owned()),
+                (Number, "7".to_owned()),
+                (Colon, ":".to_owned()),
+                (Number, "0".to_owned()),
+                (RightSquare, "]".to_owned()),
+                (Identifier, "data.in".to_owned()),
+                (Comma, ",".to_owned()),
+                (Output, "output".to_owned()),
+                (Identifier, "q".to_owned()),
+                (RightPar, ")".to_owned()),
+                (SemiColon, ";".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn systemverilog_keywords() {
+        let code = "interface logic";
+        assert_eq!(
+            kinds_and_texts(code),
+            vec![
+                (Identifier, "interface".to_owned()),
+                (Identifier, "logic".to_owned())
+            ]
+        );
+        let source = Source::from_str(code).unwrap();
+        let options = VerilogOptions {
+            systemverilog: true,
+            ..VerilogOptions::default()
+        };
+        let mut tokenizer =
+            Tokenizer::with_options(source.clone(), source.contents().unwrap(), &options);
+        let kinds: Vec<Kind> = tokenize(&mut tokenizer, &mut Vec::new())
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(kinds, vec![Interface, Logic]);
+    }
+
+    #[test]
+    fn numbers_and_operators() {
+        assert_eq!(
+            texts("8'hFF 4 'b1x0z 'sd5 '0 1.5e-3 1_000 a<=b>>>2 ? $clog2(x) : \"s\\\"\""),
+            vec![
+                "8'hFF",
+                "4 'b1x0z",
+                "'sd5",
+                "'0",
+                "1.5e-3",
+                "1_000",
+                "a",
+                "<=",
+                "b",
+                ">>>",
+                "2",
+                "?",
+                "$clog2",
+                "(",
+                "x",
+                ")",
+                ":",
+                "\"s\\\"\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(
+            texts("a // line comment\n/* block\ncomment */ b"),
+            vec!["a", "b"]
+        );
+        let (_, messages) = tokenize_code("a /* never ends");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message, "Unterminated block comment");
+    }
+
+    #[test]
+    fn conditional_compilation() {
+        assert_eq!(
+            texts(
+                "`timescale 1ns / 1ps
+`define WIDTH 8
+`ifdef WIDTH
+a
+`ifndef WIDTH
+b
+`else
+c
+`endif
+`elsif OTHER
+d
+`else
+e
+`endif
+`undef WIDTH
+`ifdef WIDTH f `endif
+[`WIDTH-1:0]"
+            ),
+            vec!["a", "c", "[", "`WIDTH", "-", "1", ":", "0", "]"]
+        );
+    }
+
+    #[test]
+    fn defines_are_collected() {
+        let source = Source::from_str("`define WIDTH 8 // bits\n`define DEPTH \\\n  16\n").unwrap();
+        let mut tokenizer = Tokenizer::new(source.clone(), source.contents().unwrap());
+        let mut messages = Vec::new();
+        assert_eq!(tokenize(&mut tokenizer, &mut messages), vec![]);
+        assert_eq!(messages, vec![]);
+        let defines: Vec<(&str, &str)> = tokenizer
+            .defines()
+            .iter()
+            .map(|(name, text)| (name.as_str(), text.as_str()))
+            .collect();
+        assert_eq!(defines, vec![("DEPTH", "16"), ("WIDTH", "8")]);
+    }
+
+    #[test]
+    fn errors_are_reported_and_skipped() {
+        let (tokens, messages) = tokenize_code("a ` b\n`ifdef X\n");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "b"]);
+        let messages: Vec<&str> = messages
+            .iter()
+            .map(|message| message.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Expected a directive or macro name after `",
+                "Missing `endif at the end of the file"
+            ]
+        );
+    }
+}
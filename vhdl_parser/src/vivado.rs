@@ -0,0 +1,334 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Import of Vivado .xpr project files such as:
+//!
+//! ```xml
+//! <FileSets Version="1" Minor="31">
+//!   <FileSet Name="sources_1" Type="DesignSrcs" RelSrcDir="$PSRCDIR/sources_1">
+//!     <File Path="$PPRDIR/src/pkg.vhd">
+//!       <FileInfo>
+//!         <Attr Name="Library" Val="mylib"/>
+//!       </FileInfo>
+//!     </File>
+//!     <File Path="$PSRCDIR/sources_1/new/top.vhd"/>
+//!   </FileSet>
+//! </FileSets>
+//! ```
+//!
+//! Files without a library attribute belong to the default library of Vivado
+
+use import::{is_vhdl_file, ImportedProject};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The library of files without a library attribute
+pub const DEFAULT_LIBRARY: &str = "xil_defaultlib";
+
+/// File sets containing HDL sources, constraints and IP file sets are ignored
+const SOURCE_FILE_SETS: &[&str] = &["DesignSrcs", "SimulationSrcs", "BlockSrcs"];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum TagKind {
+    Open,
+    Close,
+    Empty,
+}
+
+#[derive(PartialEq, Debug)]
+struct Tag {
+    kind: TagKind,
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attr_name, _)| attr_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn parse_attributes(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| format!("Expected '=' in attribute '{}'", rest))?;
+        let name = rest[..eq].trim().to_owned();
+        let value = rest[eq + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => {
+                return Err(format!("Expected quoted value of attribute '{}'", name));
+            }
+        };
+        let end = value[1..]
+            .find(quote)
+            .ok_or_else(|| format!("Unterminated value of attribute '{}'", name))?;
+        attributes.push((name, unescape(&value[1..=end])));
+        rest = value[end + 2..].trim_start();
+    }
+    Ok(attributes)
+}
+
+/// The element tags of the document, text content is not needed for project files
+fn tags(contents: &str) -> Result<Vec<Tag>, String> {
+    let mut tags = Vec::new();
+    let mut rest = contents;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+
+        let (skip_to, skip_len) = if rest.starts_with("<!--") {
+            ("-->", 3)
+        } else if rest.starts_with("<?") {
+            ("?>", 2)
+        } else if rest.starts_with("<!") {
+            (">", 1)
+        } else {
+            ("", 0)
+        };
+        if skip_len > 0 {
+            let end = rest
+                .find(skip_to)
+                .ok_or_else(|| "Unterminated XML declaration or comment".to_owned())?;
+            rest = &rest[end + skip_len..];
+            continue;
+        }
+
+        let end = rest
+            .find('>')
+            .ok_or_else(|| "Unterminated XML tag".to_owned())?;
+        let text = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let (kind, text) = if let Some(text) = text.strip_prefix('/') {
+            (TagKind::Close, text)
+        } else if let Some(text) = text.strip_suffix('/') {
+            (TagKind::Empty, text)
+        } else {
+            (TagKind::Open, text)
+        };
+
+        let name_end = text.find(char::is_whitespace).unwrap_or(text.len());
+        tags.push(Tag {
+            kind,
+            name: text[..name_end].to_owned(),
+            attributes: parse_attributes(&text[name_end..])?,
+        });
+    }
+    Ok(tags)
+}
+
+/// Replace the path variables of Vivado with the directory of the project file
+fn expand_path(path: &str, project_dir: &Path, project_name: &str) -> String {
+    let src_dir = project_dir.join(format!("{}.srcs", project_name));
+    let path = path
+        .replace("$PPRDIR", &project_dir.to_string_lossy())
+        .replace("$PSRCDIR", &src_dir.to_string_lossy());
+    if Path::new(&path).is_absolute() {
+        path
+    } else {
+        project_dir.join(path).to_string_lossy().into_owned()
+    }
+}
+
+/// Import the contents of the .xpr file at the path, the path is used to expand relative file names
+pub fn parse_xpr(contents: &str, xpr_path: &Path) -> Result<ImportedProject, String> {
+    let project_dir = xpr_path.parent().unwrap_or_else(|| Path::new(""));
+    let project_name = xpr_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut project = ImportedProject::new();
+    let mut in_source_file_set = false;
+    // The path and library of the File element currently being read
+    let mut file: Option<(String, String)> = None;
+
+    for tag in tags(contents)? {
+        match (tag.kind, tag.name.as_str()) {
+            (TagKind::Open, "FileSet") => {
+                in_source_file_set = tag
+                    .attribute("Type")
+                    .map(|typ| SOURCE_FILE_SETS.contains(&typ))
+                    .unwrap_or(false);
+            }
+            (TagKind::Close, "FileSet") => {
+                in_source_file_set = false;
+            }
+            (kind, "File") if in_source_file_set && kind != TagKind::Close => {
+                let path = tag
+                    .attribute("Path")
+                    .ok_or_else(|| "File element without Path attribute".to_owned())?;
+                let path = expand_path(path, project_dir, &project_name);
+                if kind == TagKind::Empty {
+                    if is_vhdl_file(&path) {
+                        project.add_file(DEFAULT_LIBRARY, &path);
+                    }
+                } else {
+                    file = Some((path, DEFAULT_LIBRARY.to_owned()));
+                }
+            }
+            (_, "Attr") => {
+                if let Some((_, ref mut library_name)) = file {
+                    if tag.attribute("Name") == Some("Library") {
+                        if let Some(value) = tag.attribute("Val") {
+                            *library_name = value.to_owned();
+                        }
+                    }
+                }
+            }
+            (TagKind::Close, "File") => {
+                if let Some((path, library_name)) = file.take() {
+                    if is_vhdl_file(&path) {
+                        project.add_file(&library_name, &path);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(project)
+}
+
+/// Read and import the .xpr project file
+pub fn read_xpr(file_name: &Path) -> io::Result<ImportedProject> {
+    let contents = fs::read_to_string(file_name)?;
+    parse_xpr(&contents, file_name).map_err(|msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", file_name.to_string_lossy(), msg),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use std::collections::BTreeSet;
+
+    fn files(project: &ImportedProject, library_name: &str) -> Vec<String> {
+        project
+            .get_library(library_name)
+            .map(|files| files.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    const XPR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Product Version: Vivado v2018.2 (64-bit) -->
+<Project Version="7" Minor="38" Path="/work/proj/proj.xpr">
+  <FileSets Version="1" Minor="31">
+    <FileSet Name="sources_1" Type="DesignSrcs" RelSrcDir="$PSRCDIR/sources_1">
+      <Filter Type="Srcs"/>
+      <File Path="$PPRDIR/../src/pkg.vhd">
+        <FileInfo>
+          <Attr Name="Library" Val="mylib"/>
+          <Attr Name="UsedIn" Val="synthesis"/>
+        </FileInfo>
+      </File>
+      <File Path="$PSRCDIR/sources_1/new/top.vhd">
+        <FileInfo>
+          <Attr Name="UsedIn" Val="synthesis"/>
+        </FileInfo>
+      </File>
+      <File Path="$PPRDIR/../src/core.v"/>
+      <File Path="$PPRDIR/../src/a &amp; b.vhdl"/>
+    </FileSet>
+    <FileSet Name="constrs_1" Type="Constrs" RelSrcDir="$PSRCDIR/constrs_1">
+      <File Path="$PPRDIR/../constraints/top.vhd"/>
+    </FileSet>
+    <FileSet Name="sim_1" Type="SimulationSrcs" RelSrcDir="$PSRCDIR/sim_1">
+      <File Path="$PPRDIR/../tb/tb_top.vhd">
+        <FileInfo>
+          <Attr Name="Library" Val="tb_lib"/>
+        </FileInfo>
+      </File>
+    </FileSet>
+  </FileSets>
+</Project>
+"#;
+
+    #[test]
+    fn parse_file_sets() {
+        let project = parse_xpr(XPR, Path::new("/work/proj/proj.xpr")).unwrap();
+        let names: BTreeSet<&str> = project.libraries().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            vec!["mylib", "tb_lib", "xil_defaultlib"]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(files(&project, "mylib"), vec!["/work/proj/../src/pkg.vhd"]);
+        assert_eq!(
+            files(&project, "xil_defaultlib"),
+            vec![
+                "/work/proj/../src/a & b.vhdl",
+                "/work/proj/proj.srcs/sources_1/new/top.vhd"
+            ]
+        );
+        assert_eq!(
+            files(&project, "tb_lib"),
+            vec!["/work/proj/../tb/tb_top.vhd"]
+        );
+    }
+
+    #[test]
+    fn relative_paths_are_relative_to_project_dir() {
+        let project = parse_xpr(
+            r#"<FileSet Type="DesignSrcs"><File Path="src/a.vhd"/></FileSet>"#,
+            Path::new("/work/proj.xpr"),
+        )
+        .unwrap();
+        assert_eq!(files(&project, DEFAULT_LIBRARY), vec!["/work/src/a.vhd"]);
+    }
+
+    #[test]
+    fn invalid_xml() {
+        assert_eq!(
+            parse_xpr("<FileSet Type=\"DesignSrcs\"", Path::new("p.xpr")),
+            Err("Unterminated XML tag".to_owned())
+        );
+        assert_eq!(
+            parse_xpr("<FileSet Type=DesignSrcs>", Path::new("p.xpr")),
+            Err("Expected quoted value of attribute 'Type'".to_owned())
+        );
+        assert_eq!(
+            parse_xpr(
+                "<FileSet Type=\"DesignSrcs\"><File/></FileSet>",
+                Path::new("p.xpr")
+            ),
+            Err("File element without Path attribute".to_owned())
+        );
+    }
+
+    #[test]
+    fn read_xpr_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let xpr = dir.path().join("proj.xpr");
+        fs::write(&xpr, XPR).unwrap();
+        let project = read_xpr(&xpr).unwrap();
+        assert_eq!(project.libraries().count(), 3);
+
+        fs::write(&xpr, "<Project").unwrap();
+        assert!(read_xpr(&xpr).is_err());
+    }
+}
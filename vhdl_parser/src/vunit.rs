@@ -0,0 +1,395 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Support for projects using the VUnit testing framework
+//!
+//! A project is a VUnit project if it contains a run script importing vunit or if
+//! any design unit uses vunit_lib. The VHDL sources of the VUnit installation are
+//! then added as interface only libraries such that names within vunit_lib resolve.
+//!
+//! Testbenches follow the VUnit conventions, an entity named tb_* or *_tb with a
+//! runner_cfg generic where each test case is a call such as run("test name")
+
+use ast::{ContextItem, InterfaceDeclaration, LibraryUnit};
+use import::{is_vhdl_file, ImportedProject};
+use library::symbol_key;
+use project::Project;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+extern crate glob;
+
+pub const VUNIT_LIBRARY: &str = "vunit_lib";
+
+/// The environment variable pointing to the root of the VUnit installation
+pub const VUNIT_ROOT_ENV: &str = "VUNIT_ROOT";
+
+/// A VUnit testbench and its test cases
+#[derive(PartialEq, Debug, Clone)]
+pub struct Testbench {
+    pub library_name: String,
+    pub entity_name: String,
+    pub file_name: String,
+    pub test_cases: Vec<String>,
+}
+
+/// The name patterns of testbenches used by VUnit
+pub fn is_testbench_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.starts_with("tb_") || name.ends_with("_tb")
+}
+
+/// The test case names of the testbench code, found the same way as VUnit by
+/// looking for calls to run with a string literal outside of comments
+pub fn test_cases(code: &str) -> Vec<String> {
+    let mut test_cases = Vec::new();
+    for line in code.lines() {
+        let line = match line.find("--") {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+        let lower = line.to_lowercase();
+        let mut start = 0;
+        while let Some(idx) = lower[start..].find("run") {
+            let idx = start + idx;
+            start = idx + 3;
+
+            let is_word_start = lower[..idx]
+                .chars()
+                .last()
+                .map(|chr| !(chr.is_alphanumeric() || chr == '_' || chr == '.'))
+                .unwrap_or(true);
+            if !is_word_start {
+                continue;
+            }
+
+            let rest = line[start..].trim_start();
+            if !rest.starts_with('(') {
+                continue;
+            }
+            let rest = rest[1..].trim_start();
+            if !rest.starts_with('"') {
+                continue;
+            }
+            if let Some(end) = rest[1..].find('"') {
+                if rest[end + 2..].trim_start().starts_with(')') {
+                    test_cases.push(rest[1..=end].to_owned());
+                }
+            }
+        }
+    }
+    test_cases
+}
+
+/// The VUnit testbenches of all files of the project sorted by file name
+pub fn testbenches(project: &Project) -> Vec<Testbench> {
+    let mut testbenches = Vec::new();
+    for file in project.files() {
+        for design_unit in file.design_file.design_units.iter() {
+            let (ident, generic_clause) = match design_unit.library_unit {
+                LibraryUnit::EntityDeclaration {
+                    ref ident,
+                    ref generic_clause,
+                    ..
+                } => (ident, generic_clause),
+                _ => continue,
+            };
+
+            let has_runner_cfg = generic_clause
+                .as_ref()
+                .map(|clause| {
+                    clause.generic_list.iter().any(|generic| match generic {
+                        InterfaceDeclaration::Object(ref object) => {
+                            symbol_key(&object.ident.item) == "runner_cfg"
+                        }
+                        _ => false,
+                    })
+                })
+                .unwrap_or(false);
+            let entity_name = symbol_key(&ident.item);
+            if !has_runner_cfg || !is_testbench_name(&entity_name) {
+                continue;
+            }
+
+            let code = file
+                .source
+                .contents()
+                .map(|code| code.to_string())
+                .unwrap_or_default();
+            testbenches.push(Testbench {
+                library_name: file.library_name.clone(),
+                entity_name,
+                file_name: file.file_name().to_owned(),
+                test_cases: test_cases(&code),
+            });
+        }
+    }
+    testbenches
+}
+
+/// Find VUnit run scripts within the directory and its sub directories
+pub fn find_run_scripts(directory: &Path) -> Vec<PathBuf> {
+    let pattern = directory.join("**").join("run*.py");
+    let paths = match glob::glob(&pattern.to_string_lossy()) {
+        Ok(paths) => paths,
+        Err(_) => {
+            return Vec::new();
+        }
+    };
+    paths
+        .filter_map(|path| path.ok())
+        .filter(|path| {
+            fs::read_to_string(path)
+                .map(|contents| {
+                    contents.contains("from vunit") || contents.contains("import vunit")
+                })
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// True if any design unit of the project has a library clause for vunit_lib
+pub fn uses_vunit(project: &Project) -> bool {
+    project.files().iter().any(|file| {
+        file.design_file.design_units.iter().any(|design_unit| {
+            design_unit.context_clause.iter().any(|item| match item {
+                ContextItem::Library(ref clause) => clause
+                    .name_list
+                    .iter()
+                    .any(|name| symbol_key(&name.item) == VUNIT_LIBRARY),
+                _ => false,
+            })
+        })
+    })
+}
+
+/// A project within the directory is a VUnit project if it has a run script or uses vunit_lib
+pub fn is_vunit_project(directory: &Path, project: &Project) -> bool {
+    uses_vunit(project) || !find_run_scripts(directory).is_empty()
+}
+
+/// The directory of VHDL sources of the VUnit installation given by the VUNIT_ROOT
+/// environment variable, both the repository root and the python package are accepted
+pub fn find_vunit_vhdl_dir() -> Option<PathBuf> {
+    let root = PathBuf::from(env::var_os(VUNIT_ROOT_ENV)?);
+    [root.join("vunit").join("vhdl"), root.join("vhdl")]
+        .iter()
+        .find(|path| path.is_dir())
+        .cloned()
+}
+
+/// Sources which are not part of the VUnit libraries when using VHDL-2008 are excluded, such
+/// as tests and the VHDL-93 variants of packages which have a VHDL-2008 counterpart
+fn is_vunit_library_file(path: &Path) -> bool {
+    if path.components().any(|component| {
+        let component = component.as_os_str();
+        component == "test" || component == "example" || component == "examples"
+    }) {
+        return false;
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    !(stem.ends_with("_93") || stem.ends_with("_93-2002"))
+}
+
+/// The libraries of the VHDL sources of the VUnit installation, the bundled
+/// OSVVM sources belong to the osvvm library and all other sources to vunit_lib
+pub fn vunit_libraries(vhdl_dir: &Path) -> Result<ImportedProject, String> {
+    let pattern = vhdl_dir.join("**").join("*");
+    let paths = glob::glob(&pattern.to_string_lossy()).map_err(|err| err.to_string())?;
+
+    let mut project = ImportedProject::new();
+    for path in paths {
+        let path = path.map_err(|err| err.to_string())?;
+        let file_name = path.to_string_lossy().into_owned();
+        let relative = path.strip_prefix(vhdl_dir).unwrap_or(&path);
+        if !path.is_file() || !is_vhdl_file(&file_name) || !is_vunit_library_file(relative) {
+            continue;
+        }
+        let library_name = if relative.starts_with("osvvm") {
+            "osvvm"
+        } else {
+            VUNIT_LIBRARY
+        };
+        project.add_file(library_name, &file_name);
+    }
+    Ok(project)
+}
+
+/// Add the VUnit libraries to the project as interface only libraries
+/// Returns the file names which could not be read
+pub fn add_vunit_libraries(project: &mut Project, vhdl_dir: &Path) -> Vec<(String, io::Error)> {
+    let libraries = match vunit_libraries(vhdl_dir) {
+        Ok(libraries) => libraries,
+        Err(msg) => {
+            return vec![(
+                vhdl_dir.to_string_lossy().into_owned(),
+                io::Error::new(io::ErrorKind::InvalidInput, msg),
+            )];
+        }
+    };
+
+    let mut errors = Vec::new();
+    for (library_name, file_names) in libraries.libraries() {
+        project.set_interface_only(library_name, true);
+        for file_name in file_names {
+            if let Err(err) = project.update_file(library_name, file_name) {
+                errors.push((file_name.clone(), err));
+            }
+        }
+    }
+    errors
+}
+
+/// Add the VUnit libraries of the installation given by VUNIT_ROOT if the project
+/// within the directory is a VUnit project which does not already contain vunit_lib
+/// Returns whether the libraries were added and the file names which could not be read
+pub fn register_vunit_libraries(
+    project: &mut Project,
+    directory: &Path,
+) -> (bool, Vec<(String, io::Error)>) {
+    let has_vunit_lib = project
+        .files()
+        .iter()
+        .any(|file| file.library_name.to_lowercase() == VUNIT_LIBRARY);
+    if has_vunit_lib || !is_vunit_project(directory, project) {
+        return (false, Vec::new());
+    }
+    match find_vunit_vhdl_dir() {
+        Some(vhdl_dir) => (true, add_vunit_libraries(project, &vhdl_dir)),
+        None => (false, Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use test_util::TempProject;
+
+    const TB: &str = "
+library vunit_lib;
+context vunit_lib.vunit_context;
+
+entity tb_fifo is
+  generic (runner_cfg : string);
+end entity;
+
+architecture tb of tb_fifo is
+begin
+  main : process
+  begin
+    test_runner_setup(runner, runner_cfg);
+    while test_suite loop
+      if run(\"test_write\") then
+        check_equal(1, 1);
+      elsif Run ( \"test read\" ) then
+        -- run(\"commented out\")
+        prerun(\"not a test\");
+      end if;
+    end loop;
+    test_runner_cleanup(runner);
+  end process;
+end architecture;";
+
+    #[test]
+    fn testbench_names() {
+        assert!(is_testbench_name("tb_fifo"));
+        assert!(is_testbench_name("FIFO_TB"));
+        assert!(!is_testbench_name("fifo"));
+        assert!(!is_testbench_name("tbfifo"));
+    }
+
+    #[test]
+    fn find_test_cases() {
+        assert_eq!(test_cases(TB), vec!["test_write", "test read"]);
+    }
+
+    #[test]
+    fn find_testbenches() {
+        let mut project = TempProject::new();
+        let file_name = project.add_file("lib", "tb_fifo.vhd", TB);
+        project.add_file(
+            "lib",
+            "tb_other.vhd",
+            "
+entity tb_other is
+end entity;
+
+entity fifo is
+  generic (runner_cfg : string);
+end entity;",
+        );
+
+        assert!(uses_vunit(&project.project));
+        assert_eq!(
+            testbenches(&project.project),
+            vec![Testbench {
+                library_name: "lib".to_owned(),
+                entity_name: "tb_fifo".to_owned(),
+                file_name,
+                test_cases: vec!["test_write".to_owned(), "test read".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn detect_run_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("sim")).unwrap();
+        fs::write(
+            dir.path().join("sim").join("run.py"),
+            "from vunit import VUnit\nvu = VUnit.from_argv()",
+        )
+        .unwrap();
+        fs::write(dir.path().join("run_other.py"), "import os").unwrap();
+
+        assert_eq!(
+            find_run_scripts(dir.path()),
+            vec![dir.path().join("sim").join("run.py")]
+        );
+        let project = Project::new();
+        assert!(is_vunit_project(dir.path(), &project));
+        assert!(!is_vunit_project(&dir.path().join("missing"), &project));
+    }
+
+    #[test]
+    fn add_vunit_installation() {
+        let dir = tempfile::tempdir().unwrap();
+        let vhdl_dir = dir.path().join("vunit").join("vhdl");
+        let write = |relative: &str, code: &str| {
+            let path = vhdl_dir.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, code).unwrap();
+        };
+        write("vunit_context.vhd", "context vunit_context is end context;");
+        write("run/src/run_pkg.vhd", "package run_pkg is end package;");
+        write(
+            "core/src/stop_body_93-2002.vhd",
+            "package body stop_pkg is end package body;",
+        );
+        write("run/test/tb_run.vhd", "entity tb_run is end entity;");
+        write("osvvm/RandomPkg.vhd", "package RandomPkg is end package;");
+        write("run/src/run.py", "");
+
+        let libraries = vunit_libraries(&vhdl_dir).unwrap();
+        let names: Vec<(&str, usize)> = libraries
+            .libraries()
+            .map(|(name, files)| (name, files.len()))
+            .collect();
+        assert_eq!(names, vec![("osvvm", 1), ("vunit_lib", 2)]);
+
+        let mut project = TempProject::new();
+        project.add_file("lib", "tb_fifo.vhd", TB);
+        assert!(add_vunit_libraries(&mut project.project, &vhdl_dir).is_empty());
+        assert_eq!(project.project.files().len(), 4);
+        assert_eq!(project.project.analyze(), vec![]);
+    }
+}
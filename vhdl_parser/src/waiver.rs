@@ -0,0 +1,351 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! Inline waivers of lint findings written as comments with a justification:
+//!
+//! ```vhdl
+//! -- lint: waive write-only "Kept for debug in simulation"
+//! debug <= state;
+//! count <= count + 1; -- lint: waive combinational-feedback "Intended counter"
+//!
+//! -- lint: waive-begin clock-in-logic "Clock gating cell model"
+//! gated <= clk and enable;
+//! -- lint: waive-end clock-in-logic
+//! ```
+//!
+//! A waiver on a line of its own applies to the next line with code, a waiver after
+//! code applies to its own line and a waive-begin applies until the waive-end of the
+//! same rule or the end of the file
+//!
+//! The invalid-waiver rule reports waivers without a reason, of unknown rules or which
+//! cannot be parsed
+
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use source::{Source, SrcPos};
+use source_tokens::SourceTokens;
+
+/// A waiver of the findings of a rule within a range of a source
+#[derive(PartialEq, Debug, Clone)]
+pub struct Waiver {
+    /// The position of the comment
+    pub pos: SrcPos,
+    pub rule: String,
+    pub reason: String,
+    /// The start and end of the waived code
+    pub start: usize,
+    pub end: usize,
+    /// The number of findings which were waived
+    pub waived: usize,
+}
+
+impl Waiver {
+    pub fn waives(&self, finding: &Message) -> bool {
+        finding.code.as_deref() == Some(self.rule.as_str())
+            && finding.pos.source == self.pos.source
+            && finding.pos.start >= self.start
+            && finding.pos.start < self.end
+    }
+}
+
+/// The comment of a waiver within the source
+struct Directive {
+    pos: SrcPos,
+    /// Whether there is only whitespace before the comment on its line
+    own_line: bool,
+    /// The text after lint:
+    text: String,
+}
+
+/// The lint comments in the whitespace between the tokens
+fn directives(source: &Source, tokens: &SourceTokens) -> Vec<Directive> {
+    let bytes = &tokens.code.bytes;
+    let mut gaps = Vec::new();
+    let mut previous_end = 0;
+    for token in tokens.tokens.iter() {
+        gaps.push((previous_end, token.pos.start));
+        previous_end = token.pos.start + token.pos.length;
+    }
+    gaps.push((previous_end, bytes.len()));
+
+    let mut directives = Vec::new();
+    for (start, end) in gaps {
+        let mut i = start;
+        while i + 1 < end {
+            if bytes[i] != b'-' || bytes[i + 1] != b'-' {
+                i += 1;
+                continue;
+            }
+            let line_start = bytes[..i]
+                .iter()
+                .rposition(|&byte| byte == b'\n')
+                .map_or(0, |newline| newline + 1);
+            let line_end = bytes[i..end]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(end, |newline| i + newline);
+            let comment = String::from_utf8_lossy(&bytes[i + 2..line_end]);
+            if let Some(text) = comment.trim().strip_prefix("lint:") {
+                directives.push(Directive {
+                    pos: source.pos(i, line_end - i),
+                    own_line: bytes[line_start..i]
+                        .iter()
+                        .all(|byte| byte.is_ascii_whitespace()),
+                    text: text.trim().to_owned(),
+                });
+            }
+            i = line_end;
+        }
+    }
+    directives
+}
+
+enum Kind {
+    Waive,
+    Begin,
+    End,
+}
+
+/// The kind, rule and reason of the text after lint:
+fn parse_directive(text: &str) -> Result<(Kind, String, String), String> {
+    let mut parts = text.splitn(3, char::is_whitespace);
+    let kind = match parts.next() {
+        Some("waive") => Kind::Waive,
+        Some("waive-begin") => Kind::Begin,
+        Some("waive-end") => Kind::End,
+        _ => {
+            return Err(
+                "Lint comment must be waive, waive-begin or waive-end followed by a rule"
+                    .to_owned(),
+            );
+        }
+    };
+    let rule = match parts.next() {
+        Some(rule) if !rule.is_empty() => rule.to_owned(),
+        _ => return Err("Lint waiver has no rule".to_owned()),
+    };
+    let rest = parts.next().unwrap_or("").trim();
+    if let Kind::End = kind {
+        return Ok((kind, rule, String::new()));
+    }
+    let reason = rest
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .map(str::trim)
+        .unwrap_or("");
+    if reason.is_empty() {
+        return Err(format!(
+            "Lint waiver of '{}' has no reason, it must be given within quotes",
+            rule
+        ));
+    }
+    Ok((kind, rule, reason.to_owned()))
+}
+
+/// The waivers of the source and the messages of the comments which are not valid waivers
+pub fn source_waivers(source: &Source) -> (Vec<Waiver>, Vec<Message>) {
+    let tokens = match SourceTokens::new(source) {
+        Some(tokens) => tokens,
+        None => return (Vec::new(), Vec::new()),
+    };
+    let bytes = &tokens.code.bytes;
+    let line_end = |start: usize| {
+        bytes[start..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(bytes.len(), |newline| start + newline)
+    };
+
+    let mut waivers = Vec::new();
+    let mut messages = Vec::new();
+    // The waive-begin directives which are not yet ended
+    let mut regions: Vec<Waiver> = Vec::new();
+    for directive in directives(source, &tokens) {
+        let (kind, rule, reason) = match parse_directive(&directive.text) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                messages.push(warning(&directive.pos, &message));
+                continue;
+            }
+        };
+        let end = directive.pos.start + directive.pos.length;
+        let waiver = |start, end| Waiver {
+            pos: directive.pos.clone(),
+            rule: rule.clone(),
+            reason: reason.clone(),
+            start,
+            end,
+            waived: 0,
+        };
+        match kind {
+            Kind::Waive if directive.own_line => {
+                if let Some(token) = tokens.tokens.iter().find(|token| token.pos.start > end) {
+                    let start = bytes[..token.pos.start]
+                        .iter()
+                        .rposition(|&byte| byte == b'\n')
+                        .map_or(0, |newline| newline + 1);
+                    waivers.push(waiver(start, line_end(start)));
+                }
+            }
+            Kind::Waive => {
+                let start = bytes[..directive.pos.start]
+                    .iter()
+                    .rposition(|&byte| byte == b'\n')
+                    .map_or(0, |newline| newline + 1);
+                waivers.push(waiver(start, end));
+            }
+            Kind::Begin => regions.push(waiver(end, bytes.len())),
+            Kind::End => match regions.iter().rposition(|region| region.rule == rule) {
+                Some(i) => {
+                    let mut region = regions.remove(i);
+                    region.end = directive.pos.start;
+                    waivers.push(region);
+                }
+                None => messages.push(warning(
+                    &directive.pos,
+                    &format!("Lint waiver end of '{}' has no waive-begin", rule),
+                )),
+            },
+        }
+    }
+    waivers.extend(regions);
+    waivers.sort_by_key(|waiver| waiver.pos.start);
+    (waivers, messages)
+}
+
+/// The findings which are not waived, counting the findings waived by each waiver
+pub fn apply_waivers(findings: Vec<Message>, waivers: &mut [Waiver]) -> Vec<Message> {
+    findings
+        .into_iter()
+        .filter(
+            |finding| match waivers.iter_mut().find(|waiver| waiver.waives(finding)) {
+                Some(waiver) => {
+                    waiver.waived += 1;
+                    false
+                }
+                None => true,
+            },
+        )
+        .collect()
+}
+
+pub struct InvalidWaiver;
+
+impl Rule for InvalidWaiver {
+    fn id(&self) -> &str {
+        "invalid-waiver"
+    }
+
+    fn description(&self) -> &str {
+        "A lint waiver has no reason, is of an unknown rule or cannot be parsed"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        Ok(context
+            .project
+            .files()
+            .iter()
+            .flat_map(|file| source_waivers(&file.source).1)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waivers_of_lines_and_regions() {
+        let code = "
+architecture rtl of ent is
+begin
+  -- lint: waive write-only \"Kept for debug\"
+
+  debug <= state;
+  q <= q + 1; -- lint: waive combinational-feedback \"Intended\"
+  -- lint: waive-begin clock-in-logic \"Clock gating model\"
+  gated <= clk and enable;
+  -- lint: waive-end clock-in-logic
+  s <= \"-- lint: waive write-only\";
+  -- lint: waive write-only
+  -- lint: ignore write-only
+  -- lint: waive-end write-only
+end architecture;";
+        let source = Source::from_str(code).unwrap();
+        let (waivers, messages) = source_waivers(&source);
+        let summary: Vec<(&str, &str, usize, usize)> = waivers
+            .iter()
+            .map(|waiver| {
+                (
+                    waiver.rule.as_str(),
+                    waiver.reason.as_str(),
+                    waiver.start,
+                    waiver.end,
+                )
+            })
+            .collect();
+        let line = |text: &str| {
+            let start = code.find(text).unwrap();
+            (start, start + code[start..].find('\n').unwrap())
+        };
+        let debug = line("  debug");
+        let counter = line("  q <=");
+        let gated = code.find("Clock gating model\"").unwrap() + "Clock gating model\"".len();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("write-only", "Kept for debug", debug.0, debug.1),
+                ("combinational-feedback", "Intended", counter.0, counter.1),
+                (
+                    "clock-in-logic",
+                    "Clock gating model",
+                    gated,
+                    code.find("-- lint: waive-end clock").unwrap()
+                ),
+            ]
+        );
+        let messages: Vec<&str> = messages
+            .iter()
+            .map(|message| message.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Lint waiver of 'write-only' has no reason, it must be given within quotes",
+                "Lint comment must be waive, waive-begin or waive-end followed by a rule",
+                "Lint waiver end of 'write-only' has no waive-begin",
+            ]
+        );
+
+        let finding = |text: &str, code: &str| {
+            warning(source.first_substr_pos(text), "finding").with_code(code)
+        };
+        let mut waivers = waivers;
+        let findings = apply_waivers(
+            vec![
+                finding("debug <=", "write-only"),
+                finding("state", "undeclared"),
+                finding("q + 1", "combinational-feedback"),
+                finding("clk and", "clock-in-logic"),
+                finding("gated <=", "write-only"),
+            ],
+            &mut waivers,
+        );
+        let codes: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.code.as_deref().unwrap())
+            .collect();
+        assert_eq!(codes, vec!["undeclared", "write-only"]);
+        let waived: Vec<usize> = waivers.iter().map(|waiver| waiver.waived).collect();
+        assert_eq!(waived, vec![1, 1, 1]);
+    }
+}
@@ -0,0 +1,231 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! WebAssembly interface for editors and playgrounds in the browser, used through the
+//! JavaScript wrapper of wasm/rust_hdl.js
+//!
+//! Built with cargo build --release --target wasm32-unknown-unknown, the module has no
+//! imports. The code is written to memory from rust_hdl_alloc and rust_hdl_check
+//! returns the length of the JSON result read from rust_hdl_result
+
+use message::{Message, Severity};
+use project::Project;
+use source::{Source, SrcPos};
+use std::cell::RefCell;
+use std::mem;
+
+extern crate serde_json;
+
+/// A position as one based line and column, the end is exclusive
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Range {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Range {
+    fn new(pos: &SrcPos) -> Range {
+        let (line, column) = pos.line_and_column();
+        let end = SrcPos {
+            source: pos.source.clone(),
+            start: pos.start + pos.length,
+            length: 0,
+        };
+        let (end_line, end_column) = end.line_and_column();
+        Range {
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Diagnostic {
+    /// error, warning or hint
+    pub severity: &'static str,
+    pub message: String,
+    pub code: Option<String>,
+    pub range: Range,
+}
+
+impl Diagnostic {
+    fn new(message: &Message) -> Diagnostic {
+        Diagnostic {
+            severity: match message.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Hint => "hint",
+            },
+            message: message.message.clone(),
+            code: message.code.clone(),
+            range: Range::new(&message.pos),
+        }
+    }
+}
+
+/// A declaration with its kind such as shared_variable
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    pub range: Range,
+}
+
+#[derive(PartialEq, Debug, Serialize)]
+pub struct CheckResult {
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Vec<Symbol>,
+}
+
+/// The diagnostics and declarations of VHDL code analyzed on its own in the work library
+pub fn check(code: &str) -> CheckResult {
+    let mut project = Project::new();
+    let mut diagnostics = Vec::new();
+    let source = Source::from_file_contents("input.vhd", code).and_then(|source| {
+        project
+            .update_source("work", source)
+            .map_err(|err| err.to_string())
+    });
+    if let Err(err) = source {
+        diagnostics.push(Diagnostic {
+            severity: "error",
+            message: err,
+            code: None,
+            range: Range {
+                line: 1,
+                column: 1,
+                end_line: 1,
+                end_column: 1,
+            },
+        });
+        return CheckResult {
+            diagnostics,
+            symbols: Vec::new(),
+        };
+    }
+
+    let mut messages = project.messages();
+    messages.extend(project.analyze());
+    let resolution = project.resolve();
+    messages.extend(resolution.messages().iter().cloned());
+    messages.sort_by_key(|message| message.pos.start);
+    diagnostics.extend(messages.iter().map(Diagnostic::new));
+    CheckResult {
+        diagnostics,
+        symbols: resolution
+            .declarations()
+            .iter()
+            .map(|entity| Symbol {
+                name: entity.designator.clone(),
+                kind: entity.kind.to_string(),
+                range: Range::new(&entity.pos),
+            })
+            .collect(),
+    }
+}
+
+thread_local! {
+    /// The JSON of the last result which is read from JavaScript
+    static RESULT: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Memory of the length for the code to check, which is freed by rust_hdl_check
+#[no_mangle]
+pub extern "C" fn rust_hdl_alloc(length: usize) -> *mut u8 {
+    let mut buffer = Vec::with_capacity(length);
+    let pointer = buffer.as_mut_ptr();
+    mem::forget(buffer);
+    pointer
+}
+
+/// Checks the UTF-8 code of the length written to memory from rust_hdl_alloc and
+/// returns the length of the JSON result
+///
+/// # Safety
+/// The code must have been allocated by rust_hdl_alloc with the length
+#[no_mangle]
+pub unsafe extern "C" fn rust_hdl_check(code: *mut u8, length: usize) -> usize {
+    let bytes = Vec::from_raw_parts(code, length, length);
+    let result = check(&String::from_utf8_lossy(&bytes));
+    let json = serde_json::to_vec(&result).unwrap_or_default();
+    RESULT.with(|result| {
+        *result.borrow_mut() = json;
+        result.borrow().len()
+    })
+}
+
+/// The JSON of the last result, valid until the next check
+#[no_mangle]
+pub extern "C" fn rust_hdl_result() -> *const u8 {
+    RESULT.with(|result| result.borrow().as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_and_symbols() {
+        let result = check(
+            "entity ent is
+end entity;
+
+architecture rtl of ent is
+  signal foo : bit;
+begin
+end architecture;
+
+entity broken
+end entity;
+",
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                severity: "error",
+                message: "Failed to parse entity declaration".to_owned(),
+                code: None,
+                range: Range {
+                    line: 9,
+                    column: 1,
+                    end_line: 9,
+                    end_column: 7,
+                },
+            }]
+        );
+        let symbols: Vec<(&str, &str, usize)> = result
+            .symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.name.as_str(),
+                    symbol.kind.as_str(),
+                    symbol.range.line,
+                )
+            })
+            .collect();
+        assert!(symbols.contains(&("foo", "signal", 5)));
+        assert!(symbols.contains(&("ent", "entity", 1)));
+    }
+
+    #[test]
+    fn check_through_memory() {
+        let code = b"entity ent is\nend entity;\n";
+        unsafe {
+            let pointer = rust_hdl_alloc(code.len());
+            pointer.copy_from(code.as_ptr(), code.len());
+            let length = rust_hdl_check(pointer, code.len());
+            let json = std::slice::from_raw_parts(rust_hdl_result(), length);
+            let json: serde_json::Value = serde_json::from_slice(json).unwrap();
+            assert_eq!(json["diagnostics"], serde_json::json!([]));
+            assert_eq!(json["symbols"][0]["name"], "ent");
+        }
+    }
+}
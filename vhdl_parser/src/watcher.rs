@@ -0,0 +1,357 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime};
+
+extern crate fnv;
+use self::fnv::FnvHashMap;
+
+/// A change to a file within a watched directory
+#[derive(PartialEq, Debug, Clone)]
+pub enum FileChange {
+    Created {
+        library_name: String,
+        file_name: String,
+    },
+    Modified {
+        library_name: String,
+        file_name: String,
+    },
+    Removed {
+        library_name: String,
+        file_name: String,
+    },
+}
+
+impl FileChange {
+    pub fn file_name(&self) -> &str {
+        match self {
+            FileChange::Created { file_name, .. }
+            | FileChange::Modified { file_name, .. }
+            | FileChange::Removed { file_name, .. } => file_name,
+        }
+    }
+}
+
+/// The observed state of a file, the length is included since the
+/// modification time resolution is coarse on some file systems
+#[derive(PartialEq, Clone, Copy)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+struct WatchedDirectory {
+    library_name: String,
+    path: PathBuf,
+    /// Only the file at the path is watched
+    is_file: bool,
+}
+
+/// Polling based watcher of VHDL source files within directories or of single files
+///
+/// Changes are accumulated until no further change has been observed for the
+/// debounce duration such that saving many files at once results in a single
+/// re-analysis
+pub struct FileWatcher {
+    directories: Vec<WatchedDirectory>,
+    debounce: Duration,
+    stamps: FnvHashMap<PathBuf, (String, FileStamp)>,
+    pending: Vec<FileChange>,
+    last_change: Option<Instant>,
+}
+
+fn is_vhdl_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            let ext = ext.to_lowercase();
+            ext == "vhd" || ext == "vhdl"
+        }
+        None => false,
+    }
+}
+
+/// Recursively find all VHDL files within the directory
+fn scan_directory(path: &Path, files: &mut Vec<(PathBuf, FileStamp)>) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            // File was removed during the scan
+            Err(_) => continue,
+        };
+
+        if metadata.is_dir() {
+            scan_directory(&path, files)?;
+        } else if is_vhdl_file(&path) {
+            let stamp = FileStamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            };
+            files.push((path, stamp));
+        }
+    }
+    Ok(())
+}
+
+impl FileWatcher {
+    pub fn new(debounce: Duration) -> FileWatcher {
+        FileWatcher {
+            directories: Vec::new(),
+            debounce,
+            stamps: FnvHashMap::default(),
+            pending: Vec::new(),
+            last_change: None,
+        }
+    }
+
+    /// Watch all VHDL files within the directory recursively, files are considered part of the library
+    /// Files already present are reported as created by the next poll
+    pub fn add_directory(&mut self, library_name: &str, path: &Path) {
+        self.directories.push(WatchedDirectory {
+            library_name: library_name.to_owned(),
+            path: path.to_owned(),
+            is_file: false,
+        });
+    }
+
+    /// Watch a single file of the library regardless of its extension, the file is
+    /// reported as removed while it does not exist
+    pub fn add_file(&mut self, library_name: &str, path: &Path) {
+        self.directories.push(WatchedDirectory {
+            library_name: library_name.to_owned(),
+            path: path.to_owned(),
+            is_file: true,
+        });
+    }
+
+    /// Record the files currently present without reporting them as created
+    pub fn skip_existing(&mut self) -> io::Result<()> {
+        self.scan()?;
+        self.pending.clear();
+        self.last_change = None;
+        Ok(())
+    }
+
+    /// Scan the directories and record any changes since the last scan
+    fn scan(&mut self) -> io::Result<()> {
+        let mut current = FnvHashMap::default();
+        for directory in self.directories.iter() {
+            let mut files = Vec::new();
+            if directory.is_file {
+                if let Ok(metadata) = fs::metadata(&directory.path) {
+                    let stamp = FileStamp {
+                        modified: metadata.modified().ok(),
+                        len: metadata.len(),
+                    };
+                    files.push((directory.path.clone(), stamp));
+                }
+            } else {
+                scan_directory(&directory.path, &mut files)?;
+            }
+            for (path, stamp) in files {
+                current.insert(path, (directory.library_name.clone(), stamp));
+            }
+        }
+
+        let mut changes = Vec::new();
+        for (path, (library_name, stamp)) in current.iter() {
+            let file_name = path.to_string_lossy().into_owned();
+            let library_name = library_name.clone();
+            match self.stamps.get(path) {
+                None => changes.push(FileChange::Created {
+                    library_name,
+                    file_name,
+                }),
+                Some((_, old_stamp)) if old_stamp != stamp => changes.push(FileChange::Modified {
+                    library_name,
+                    file_name,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (path, (library_name, _)) in self.stamps.iter() {
+            if !current.contains_key(path) {
+                changes.push(FileChange::Removed {
+                    library_name: library_name.clone(),
+                    file_name: path.to_string_lossy().into_owned(),
+                });
+            }
+        }
+
+        if !changes.is_empty() {
+            // Deterministic order of changes
+            changes.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+            self.pending.extend(changes);
+            self.last_change = Some(Instant::now());
+        }
+
+        self.stamps = current;
+        Ok(())
+    }
+
+    /// Scan for changes, returns the accumulated changes once the directories
+    /// have been quiet for the debounce duration, otherwise an empty vector
+    pub fn poll(&mut self) -> io::Result<Vec<FileChange>> {
+        self.scan()?;
+
+        if let Some(last_change) = self.last_change {
+            if last_change.elapsed() >= self.debounce {
+                self.last_change = None;
+                return Ok(self.pending.drain(..).collect());
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Poll periodically and call the function with each debounced set of changes
+    /// Returns when the function returns false
+    pub fn watch<F>(&mut self, interval: Duration, mut fun: F) -> io::Result<()>
+    where
+        F: FnMut(&[FileChange]) -> bool,
+    {
+        loop {
+            let changes = self.poll()?;
+            if !changes.is_empty() && !fun(&changes) {
+                return Ok(());
+            }
+            sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate tempfile;
+    use std::fs::File;
+    use std::io::Write;
+
+    fn write_file(path: &Path, contents: &str) -> String {
+        let mut file = File::create(path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    fn created(file_name: &str) -> FileChange {
+        FileChange::Created {
+            library_name: "lib".to_owned(),
+            file_name: file_name.to_owned(),
+        }
+    }
+
+    #[test]
+    fn reports_existing_files_as_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(&dir.path().join("ent.vhd"), "");
+        write_file(&dir.path().join("ignored.txt"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.add_directory("lib", dir.path());
+        assert_eq!(watcher.poll().unwrap(), vec![created(&file_name)]);
+        assert_eq!(watcher.poll().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn watches_directories_recursively() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let file_name = write_file(&dir.path().join("sub").join("ent.vhdl"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.add_directory("lib", dir.path());
+        assert_eq!(watcher.poll().unwrap(), vec![created(&file_name)]);
+    }
+
+    #[test]
+    fn reports_modified_and_removed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ent.vhd");
+        let file_name = write_file(&path, "");
+        let removed = write_file(&dir.path().join("removed.vhd"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.add_directory("lib", dir.path());
+        watcher.poll().unwrap();
+
+        write_file(&path, "entity ent is end entity;");
+        fs::remove_file(&removed).unwrap();
+        assert_eq!(
+            watcher.poll().unwrap(),
+            vec![
+                FileChange::Modified {
+                    library_name: "lib".to_owned(),
+                    file_name: file_name.clone(),
+                },
+                FileChange::Removed {
+                    library_name: "lib".to_owned(),
+                    file_name: removed.clone(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn watches_single_files_after_skipping_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ent.txt");
+        let file_name = write_file(&path, "");
+        write_file(&dir.path().join("other.vhd"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.add_file("lib", &path);
+        watcher.skip_existing().unwrap();
+        assert_eq!(watcher.poll().unwrap(), vec![]);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            watcher.poll().unwrap(),
+            vec![FileChange::Removed {
+                library_name: "lib".to_owned(),
+                file_name: file_name.clone(),
+            }]
+        );
+        write_file(&path, "entity ent is end entity;");
+        assert_eq!(watcher.poll().unwrap(), vec![created(&file_name)]);
+    }
+
+    #[test]
+    fn changes_are_debounced() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("ent.vhd"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(3600));
+        watcher.add_directory("lib", dir.path());
+        assert_eq!(watcher.poll().unwrap(), vec![]);
+
+        // Pending changes are kept until the quiet period has elapsed
+        watcher.debounce = Duration::from_secs(0);
+        assert_eq!(watcher.poll().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn watch_calls_function_with_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = write_file(&dir.path().join("ent.vhd"), "");
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.add_directory("lib", dir.path());
+        let mut got = Vec::new();
+        watcher
+            .watch(Duration::from_millis(1), |changes| {
+                got.extend(changes.iter().cloned());
+                false
+            })
+            .unwrap();
+        assert_eq!(got, vec![created(&file_name)]);
+    }
+}
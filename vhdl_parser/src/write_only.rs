@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The write-only lint rule which reports signals and variables which are assigned but
+//! never read anywhere in the project, usually dead logic or a missing connection
+//!
+//! The actual of a port is read or written by the mode of the port, an inout port both
+//! reads and writes it, while the actual of a parameter reads the object
+
+use formatter::STANDARD_LIBRARIES;
+use lint::{LintContext, Rule, RuleConfig};
+use message::{warning, Message, Severity};
+use name_resolution::NamedEntityKind;
+use source::SrcPos;
+use std::collections::HashMap;
+
+pub struct WriteOnly;
+
+fn describe(kind: NamedEntityKind) -> Option<&'static str> {
+    match kind {
+        NamedEntityKind::Signal => Some("Signal"),
+        NamedEntityKind::Variable => Some("Variable"),
+        NamedEntityKind::SharedVariable => Some("Shared variable"),
+        _ => None,
+    }
+}
+
+impl Rule for WriteOnly {
+    fn id(&self) -> &str {
+        "write-only"
+    }
+
+    fn description(&self) -> &str {
+        "A signal or variable is assigned but never read"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, context: &LintContext, config: &RuleConfig) -> Result<Vec<Message>, String> {
+        config.unknown_options(self.id(), &[])?;
+        // The assignments of each declaration and whether it is read
+        let mut usage: HashMap<(&str, usize), (Vec<&SrcPos>, bool)> = HashMap::new();
+        for reference in context.resolution.references() {
+            for entity in reference.entities.iter() {
+                if describe(entity.kind).is_none() {
+                    continue;
+                }
+                let file_name = entity.pos.source.file_name().unwrap_or("");
+                let (writes, is_read) = usage
+                    .entry((file_name, entity.pos.start))
+                    .or_insert_with(|| (Vec::new(), false));
+                if reference.is_write {
+                    writes.push(&reference.pos);
+                }
+                if reference.is_read {
+                    *is_read = true;
+                }
+            }
+        }
+
+        let mut findings = Vec::new();
+        for entity in context.resolution.declarations() {
+            let kind = match describe(entity.kind) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let file_name = entity.pos.source.file_name().unwrap_or("");
+            let is_standard = context
+                .project
+                .get_file(file_name)
+                .is_some_and(|file| STANDARD_LIBRARIES.contains(&file.library_name.as_str()));
+            let writes = match usage.get(&(file_name, entity.pos.start)) {
+                Some((writes, false)) if !writes.is_empty() && !is_standard => writes,
+                _ => continue,
+            };
+            let mut finding = warning(
+                &entity.pos,
+                &format!(
+                    "{} '{}' is assigned but never read",
+                    kind, entity.designator
+                ),
+            );
+            for pos in writes.iter() {
+                finding = finding.related(pos, "Assigned here");
+            }
+            findings.push(finding);
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lint::{LintConfig, Registry};
+    use test_util::TempProject;
+
+    #[test]
+    fn assigned_but_never_read() {
+        let mut project = TempProject::new();
+        let code = "
+entity ent is
+  port (d : in bit; q : out bit; io : inout bit);
+end entity;
+
+architecture rtl of ent is
+  signal unused, dead, used, connected, input, bidir : bit;
+begin
+  process
+    variable count : natural;
+  begin
+    count := count + 1;
+    dead <= '1';
+    dead <= '0';
+    used <= '1';
+    q <= used;
+    wait;
+  end process;
+
+  inst : entity work.ent port map (d => input, q => connected, io => bidir);
+  input <= '1';
+  bidir <= '1';
+end architecture;";
+        let file_name = project.add_file("lib", "ent.vhd", code);
+        let project = &project.project;
+        let resolution = project.resolve();
+        let context = LintContext {
+            project,
+            messages: &[],
+            resolution: &resolution,
+        };
+        let findings: Vec<Message> = Registry::default()
+            .check(&context, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .filter(|finding| finding.code == Some("write-only".to_owned()))
+            .collect();
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0].message,
+            "Signal 'dead' is assigned but never read"
+        );
+        assert_eq!(findings[0].pos.start, code.find("dead").unwrap());
+        assert_eq!(findings[0].pos.source.file_name(), Some(file_name.as_str()));
+        let related: Vec<(usize, &str)> = findings[0]
+            .related
+            .iter()
+            .map(|(pos, message)| (pos.start, message.as_str()))
+            .collect();
+        assert_eq!(
+            related,
+            vec![
+                (code.find("dead <= '1'").unwrap(), "Assigned here"),
+                (code.find("dead <= '0'").unwrap(), "Assigned here"),
+            ]
+        );
+        assert_eq!(
+            findings[1].message,
+            "Signal 'connected' is assigned but never read"
+        );
+        assert_eq!(
+            findings[1].related[0].0.start,
+            code.find("connected, io").unwrap()
+        );
+    }
+}
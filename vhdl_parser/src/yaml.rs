@@ -0,0 +1,680 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The subset of YAML used by tool configuration files such as FuseSoC core files
+//!
+//! Block and flow style mappings and sequences, quoted and plain scalars as well
+//! as comments are supported, anchors, tags and multi line flow collections are not
+//!
+//! Values are written in block style where scalars are only quoted when needed
+
+/// A YAML value where all scalars are kept as strings
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Null,
+    String(String),
+    Sequence(Vec<Value>),
+    Mapping(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence(&self) -> Option<&[Value]> {
+        match self {
+            Value::Sequence(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Mapping(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// The value of the key if this is a mapping containing the key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_mapping()?
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value)
+    }
+}
+
+struct Line {
+    /// One based line number for error messages
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Remove a trailing comment, a # only starts a comment at the start of the
+/// line or after whitespace and not within quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (idx, chr) in line.char_indices() {
+        match quote {
+            Some(q) if chr == q => quote = None,
+            Some(_) => {}
+            None if chr == '"' || chr == '\'' => quote = Some(chr),
+            None if chr == '#' && previous.is_whitespace() => {
+                return &line[..idx];
+            }
+            None => {}
+        }
+        previous = chr;
+    }
+    line
+}
+
+fn lines(contents: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line = strip_comment(line).trim_end();
+        let text = line.trim_start();
+        if text.is_empty() || text == "---" {
+            continue;
+        }
+        lines.push(Line {
+            number: idx + 1,
+            indent: line.len() - text.len(),
+            text: text.to_owned(),
+        });
+    }
+    lines
+}
+
+/// The byte index of the colon separating a mapping key from its value
+/// The colon must be followed by whitespace or the end of the text
+fn key_separator(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    let bytes = text.as_bytes();
+    for (idx, &byte) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if byte == q => quote = None,
+            Some(_) => {}
+            None => match byte {
+                b'"' | b'\'' => quote = Some(byte),
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' => depth -= 1,
+                b':' if depth == 0 && (idx + 1 == bytes.len() || bytes[idx + 1] == b' ') => {
+                    return Some(idx);
+                }
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Parser of a single line flow value such as [a, {b: c}] or a quoted scalar
+struct FlowParser<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> FlowParser<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!("{} at line {}", msg, self.line)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.text[self.pos..].starts_with(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String, String> {
+        let mut string = String::new();
+        let mut chars = self.text[self.pos + 1..].char_indices();
+        while let Some((idx, chr)) = chars.next() {
+            if chr == quote {
+                // A quote is escaped by doubling it within single quotes
+                if quote == '\'' && self.text[self.pos + 1 + idx + 1..].starts_with('\'') {
+                    chars.next();
+                    string.push(chr);
+                    continue;
+                }
+                self.pos += idx + 2;
+                return Ok(string);
+            } else if chr == '\\' && quote == '"' {
+                match chars.next() {
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, chr)) => string.push(chr),
+                    None => {}
+                }
+            } else {
+                string.push(chr);
+            }
+        }
+        Err(self.error("Missing closing quote"))
+    }
+
+    /// A plain scalar ends at a flow indicator when within a flow collection
+    fn plain(&mut self, in_flow: bool) -> String {
+        let rest = &self.text[self.pos..];
+        let end = if in_flow {
+            rest.find(&[',', ']', '}'][..]).unwrap_or(rest.len())
+        } else {
+            rest.len()
+        };
+        self.pos += end;
+        rest[..end].trim_end().to_owned()
+    }
+
+    fn scalar(&mut self, in_flow: bool) -> Result<Value, String> {
+        match self.peek() {
+            Some(quote) if quote == '"' || quote == '\'' => Ok(Value::String(self.quoted(quote)?)),
+            _ => {
+                let plain = self.plain(in_flow);
+                if plain.is_empty() || plain == "~" || plain == "null" {
+                    Ok(Value::Null)
+                } else {
+                    Ok(Value::String(plain))
+                }
+            }
+        }
+    }
+
+    fn value(&mut self, in_flow: bool) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        return Ok(Value::Sequence(items));
+                    }
+                    items.push(self.value(true)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {}
+                        _ => {
+                            return Err(self.error("Expected ',' or ']'"));
+                        }
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                        return Ok(Value::Mapping(entries));
+                    }
+                    let key = match self.peek() {
+                        Some(quote) if quote == '"' || quote == '\'' => self.quoted(quote)?,
+                        _ => {
+                            let rest = &self.text[self.pos..];
+                            let end = rest.find(&[',', '}'][..]).unwrap_or(rest.len());
+                            let end = key_separator(&rest[..end]).unwrap_or(end);
+                            self.pos += end;
+                            rest[..end].trim().to_owned()
+                        }
+                    };
+                    self.skip_whitespace();
+                    let value = if self.peek() == Some(':') {
+                        self.pos += 1;
+                        self.value(true)?
+                    } else {
+                        Value::Null
+                    };
+                    entries.push((key, value));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {}
+                        _ => {
+                            return Err(self.error("Expected ',' or '}'"));
+                        }
+                    }
+                }
+            }
+            _ => self.scalar(in_flow),
+        }
+    }
+}
+
+fn flow_value(text: &str, line: usize) -> Result<Value, String> {
+    let mut parser = FlowParser { text, pos: 0, line };
+    let value = parser.value(false)?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("Unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+fn unquote_key(key: &str, line: usize) -> Result<String, String> {
+    match flow_value(key.trim(), line)? {
+        Value::String(key) => Ok(key),
+        Value::Null => Ok(String::new()),
+        _ => Err(format!("Expected mapping key at line {}", line)),
+    }
+}
+
+struct BlockParser {
+    lines: Vec<Line>,
+    idx: usize,
+}
+
+impl BlockParser {
+    fn current(&self) -> Option<&Line> {
+        self.lines.get(self.idx)
+    }
+
+    fn block(&mut self, indent: usize) -> Result<Value, String> {
+        match self.current() {
+            Some(line) if is_sequence_item(&line.text) => self.sequence(indent),
+            Some(line) if key_separator(&line.text).is_some() => self.mapping(indent),
+            Some(line) => {
+                let value = flow_value(&line.text, line.number)?;
+                self.idx += 1;
+                Ok(value)
+            }
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// The value of a key or sequence item which is on the following lines
+    fn nested(&mut self, indent: usize, allow_same_indent_sequence: bool) -> Result<Value, String> {
+        let nested_indent = match self.current() {
+            Some(line) if line.indent > indent => line.indent,
+            Some(line)
+                if allow_same_indent_sequence
+                    && line.indent == indent
+                    && is_sequence_item(&line.text) =>
+            {
+                return self.sequence(indent);
+            }
+            _ => {
+                return Ok(Value::Null);
+            }
+        };
+        self.block(nested_indent)
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.current() {
+            if line.indent != indent || !is_sequence_item(&line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start();
+            if rest.is_empty() {
+                self.idx += 1;
+                items.push(self.nested(indent, false)?);
+            } else {
+                // The item is parsed as if it started on its own line
+                let item_indent = indent + line.text.len() - rest.len();
+                let rest = rest.to_owned();
+                self.lines[self.idx].indent = item_indent;
+                self.lines[self.idx].text = rest;
+                items.push(self.block(item_indent)?);
+            }
+        }
+        Ok(Value::Sequence(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, String> {
+        let mut entries = Vec::new();
+        while let Some(line) = self.current() {
+            if line.indent != indent || is_sequence_item(&line.text) {
+                break;
+            }
+            let number = line.number;
+            let separator = key_separator(&line.text)
+                .ok_or_else(|| format!("Expected mapping key at line {}", number))?;
+            let key = unquote_key(&line.text[..separator], number)?;
+            let rest = line.text[separator + 1..].trim().to_owned();
+            self.idx += 1;
+
+            let value = if rest.is_empty() {
+                self.nested(indent, true)?
+            } else if rest == "|" || rest == ">" || rest == "|-" || rest == ">-" {
+                self.block_scalar(indent, rest.starts_with('|'))
+            } else {
+                flow_value(&rest, number)?
+            };
+            entries.push((key, value));
+        }
+        Ok(Value::Mapping(entries))
+    }
+
+    /// Literal and folded scalars, the lines are joined with newlines or spaces
+    fn block_scalar(&mut self, indent: usize, literal: bool) -> Value {
+        let mut parts = Vec::new();
+        while let Some(line) = self.current() {
+            if line.indent <= indent {
+                break;
+            }
+            parts.push(line.text.clone());
+            self.idx += 1;
+        }
+        Value::String(parts.join(if literal { "\n" } else { " " }))
+    }
+}
+
+/// Parse the YAML document, errors contain the line number
+pub fn parse(contents: &str) -> Result<Value, String> {
+    let lines = lines(contents);
+    let indent = match lines.first() {
+        Some(line) => line.indent,
+        None => {
+            return Ok(Value::Null);
+        }
+    };
+    let mut parser = BlockParser { lines, idx: 0 };
+    let value = parser.block(indent)?;
+    match parser.current() {
+        Some(line) => Err(format!("Unexpected indentation at line {}", line.number)),
+        None => Ok(value),
+    }
+}
+
+/// Whether a scalar must be quoted to be read as the same string
+fn needs_quotes(string: &str) -> bool {
+    string.is_empty()
+        || string == "~"
+        || string == "null"
+        || string.starts_with(|chr: char| "-?:,[]{}#&*!|>'\"%@`".contains(chr))
+        || string.starts_with(char::is_whitespace)
+        || string.ends_with(char::is_whitespace)
+        || string.ends_with(':')
+        || string.contains(": ")
+        || string.contains(" #")
+        || string.contains('\n')
+}
+
+fn write_scalar(string: &str) -> String {
+    if needs_quotes(string) {
+        format!(
+            "\"{}\"",
+            string
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\t', "\\t")
+        )
+    } else {
+        string.to_owned()
+    }
+}
+
+/// The value on the same line as its key or sequence item dash, None for a block
+fn inline_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_owned()),
+        Value::String(string) => Some(write_scalar(string)),
+        Value::Sequence(items) if items.is_empty() => Some("[]".to_owned()),
+        Value::Mapping(entries) if entries.is_empty() => Some("{}".to_owned()),
+        _ => None,
+    }
+}
+
+fn write_block(value: &Value, indent: usize, yaml: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Mapping(entries) => {
+            for (key, value) in entries.iter() {
+                match inline_value(value) {
+                    Some(inline) => {
+                        yaml.push_str(&format!("{}{}: {}\n", pad, write_scalar(key), inline))
+                    }
+                    None => {
+                        yaml.push_str(&format!("{}{}:\n", pad, write_scalar(key)));
+                        write_block(value, indent + 2, yaml);
+                    }
+                }
+            }
+        }
+        Value::Sequence(items) => {
+            for item in items.iter() {
+                match inline_value(item) {
+                    Some(inline) => yaml.push_str(&format!("{}- {}\n", pad, inline)),
+                    None => {
+                        // The first line of the nested block follows the dash
+                        let mut nested = String::new();
+                        write_block(item, indent + 2, &mut nested);
+                        yaml.push_str(&pad);
+                        yaml.push_str("- ");
+                        yaml.push_str(&nested[indent + 2..]);
+                    }
+                }
+            }
+        }
+        _ => {
+            if let Some(inline) = inline_value(value) {
+                yaml.push_str(&format!("{}{}\n", pad, inline));
+            }
+        }
+    }
+}
+
+/// The value as a YAML document in block style
+pub fn to_string(value: &Value) -> String {
+    let mut yaml = String::new();
+    write_block(value, 0, &mut yaml);
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+
+    fn mapping(entries: Vec<(&str, Value)>) -> Value {
+        Value::Mapping(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn block_mapping_and_sequence() {
+        let value = parse(
+            "
+# comment
+key: value # trailing comment
+nested:
+  a: 1
+  b:
+    - x
+    - 'y # not a comment'
+same_indent:
+- z
+empty:
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            value,
+            mapping(vec![
+                ("key", string("value")),
+                (
+                    "nested",
+                    mapping(vec![
+                        ("a", string("1")),
+                        (
+                            "b",
+                            Value::Sequence(vec![string("x"), string("y # not a comment")])
+                        ),
+                    ])
+                ),
+                ("same_indent", Value::Sequence(vec![string("z")])),
+                ("empty", Value::Null),
+            ])
+        );
+    }
+
+    #[test]
+    fn mappings_within_sequence() {
+        let value = parse(
+            "
+files:
+  - a.vhd
+  - b.vhd: {file_type: vhdlSource-2008, logical_name: lib}
+  - c.vhd:
+      logical_name: lib
+  - name: d
+    value: e
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            value.get("files"),
+            Some(&Value::Sequence(vec![
+                string("a.vhd"),
+                mapping(vec![(
+                    "b.vhd",
+                    mapping(vec![
+                        ("file_type", string("vhdlSource-2008")),
+                        ("logical_name", string("lib")),
+                    ])
+                )]),
+                mapping(vec![(
+                    "c.vhd",
+                    mapping(vec![("logical_name", string("lib"))])
+                )]),
+                mapping(vec![("name", string("d")), ("value", string("e"))]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn written_values_are_parsed_again() {
+        let value = mapping(vec![
+            ("name", string("fifo")),
+            ("empty", Value::Null),
+            ("quoted", string("'0'")),
+            ("colon", string("a: b")),
+            ("null", string("null")),
+            ("none", Value::Sequence(vec![])),
+            (
+                "ports",
+                Value::Sequence(vec![
+                    mapping(vec![("name", string("clk")), ("type", string("std_logic"))]),
+                    mapping(vec![(
+                        "nested",
+                        Value::Sequence(vec![string("x"), string("- y")]),
+                    )]),
+                ]),
+            ),
+        ]);
+        let yaml = to_string(&value);
+        assert_eq!(
+            yaml,
+            "\
+name: fifo
+empty: null
+quoted: \"'0'\"
+colon: \"a: b\"
+\"null\": \"null\"
+none: []
+ports:
+  - name: clk
+    type: std_logic
+  - nested:
+      - x
+      - \"- y\"
+"
+        );
+        assert_eq!(parse(&yaml), Ok(value));
+    }
+
+    #[test]
+    fn flow_values() {
+        assert_eq!(
+            parse("a: [x, 'y, z', \"w\\n\", []]\nb: {c: ::core:1.0, d: ~}").unwrap(),
+            mapping(vec![
+                (
+                    "a",
+                    Value::Sequence(vec![
+                        string("x"),
+                        string("y, z"),
+                        string("w\n"),
+                        Value::Sequence(vec![])
+                    ])
+                ),
+                (
+                    "b",
+                    mapping(vec![("c", string("::core:1.0")), ("d", Value::Null)])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn colon_without_space_is_scalar() {
+        assert_eq!(
+            parse("CAPI=2:\nname: ::core:1.0\ndepend:\n  - vendor:lib:name").unwrap(),
+            mapping(vec![
+                ("CAPI=2", Value::Null),
+                ("name", string("::core:1.0")),
+                ("depend", Value::Sequence(vec![string("vendor:lib:name")])),
+            ])
+        );
+    }
+
+    #[test]
+    fn block_scalars() {
+        assert_eq!(
+            parse("description: |\n  line 1\n  line 2\nnext: x").unwrap(),
+            mapping(vec![
+                ("description", string("line 1\nline 2")),
+                ("next", string("x")),
+            ])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("a: b\n    c: d"),
+            Err("Unexpected indentation at line 2".to_owned())
+        );
+        assert_eq!(
+            parse("a: 'b"),
+            Err("Missing closing quote at line 1".to_owned())
+        );
+        assert_eq!(
+            parse("a: [b"),
+            Err("Expected ',' or ']' at line 1".to_owned())
+        );
+    }
+}
@@ -0,0 +1,664 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+//! The entity interfaces and instance hierarchy of the project as the JSON netlist of
+//! Yosys, for open source flow tools which read the output of write_json
+//!
+//! Each entity becomes a module with its ports and a cell for each instance of its
+//! architecture, there are no cells for the logic. Ports and signals get nets of their
+//! widths for the default values of the generics, scalars and widths which are not static
+//! get a single bit. A port map actual which is a port or signal connects the cell to its
+//! nets, '0' and '1' to the constant bits and other actuals to nets of their own
+//!
+//! Verilog modules become blackbox modules, instances of units outside of the project
+//! become cells of the unit name without a module
+
+use ast::{
+    ActualPart, AssociationElement, Declaration, Expression, InstantiationStatement,
+    InterfaceDeclaration, LibraryUnit, Literal, Mode, Name, ObjectClass,
+};
+use instance_tree::{InstanceHierarchy, InstanceNode};
+use library::{symbol_key, UnitId};
+use mixed_language::{port_widths, verilog_defines};
+use name_resolution::entity_interface;
+use project::Project;
+use source::SrcPos;
+use static_value::{
+    bit_width, declare_constants, generic_values, generic_values_in, static_value, Constants, Value,
+};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use verilog_parser::{PortDirection, VerilogModule};
+
+/// A bit of a net or one of the constants "0", "1", "x" and "z"
+#[derive(PartialEq, Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Bit {
+    Net(usize),
+    Constant(&'static str),
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct YosysPort {
+    /// input, output or inout
+    pub direction: &'static str,
+    pub bits: Vec<Bit>,
+}
+
+/// An instance of a module or of a unit outside of the project
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct YosysCell {
+    pub hide_name: u8,
+    #[serde(rename = "type")]
+    pub cell_type: String,
+    pub parameters: BTreeMap<String, String>,
+    pub attributes: BTreeMap<String, String>,
+    /// The directions of the connected ports of a module of the design
+    pub port_directions: BTreeMap<String, &'static str>,
+    pub connections: BTreeMap<String, Vec<Bit>>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct YosysNet {
+    pub hide_name: u8,
+    pub bits: Vec<Bit>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default, Serialize)]
+pub struct YosysModule {
+    pub attributes: BTreeMap<String, String>,
+    pub parameter_default_values: BTreeMap<String, String>,
+    pub ports: BTreeMap<String, YosysPort>,
+    pub cells: BTreeMap<String, YosysCell>,
+    pub netnames: BTreeMap<String, YosysNet>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct YosysDesign {
+    pub creator: String,
+    pub modules: BTreeMap<String, YosysModule>,
+}
+
+/// The value of boolean attributes such as top and blackbox
+const TRUE: &str = "00000000000000000000000000000001";
+
+/// Larger widths get a single bit such that a typo does not allocate all memory
+const MAX_WIDTH: i64 = 1 << 16;
+
+/// Integers as 32 bits, or 64 bits when larger, booleans as a single bit and strings as text
+fn parameter_value(value: &Value) -> String {
+    match value {
+        Value::Integer(value) => match i32::try_from(*value) {
+            Ok(value) => format!("{:032b}", value),
+            Err(_) => format!("{:064b}", value),
+        },
+        Value::Boolean(value) => if *value { "1" } else { "0" }.to_owned(),
+        Value::Character(chr) => char::from(*chr).to_string(),
+        Value::String(ref bytes) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn parameters(constants: &Constants) -> BTreeMap<String, String> {
+    constants
+        .iter()
+        .map(|(name, value)| (name.clone(), parameter_value(value)))
+        .collect()
+}
+
+/// The location as written by Yosys such as top.vhd:12.3
+fn src(pos: &SrcPos) -> BTreeMap<String, String> {
+    let (line, column) = pos.line_and_column();
+    let mut attributes = BTreeMap::new();
+    attributes.insert(
+        "src".to_owned(),
+        format!(
+            "{}:{}.{}",
+            pos.source.file_name().unwrap_or(""),
+            line,
+            column
+        ),
+    );
+    attributes
+}
+
+fn mode_direction(mode: Mode) -> &'static str {
+    match mode {
+        Mode::In => "input",
+        Mode::Out | Mode::Buffer => "output",
+        Mode::InOut | Mode::Linkage => "inout",
+    }
+}
+
+fn port_direction(direction: PortDirection) -> &'static str {
+    match direction {
+        PortDirection::Input => "input",
+        PortDirection::Output => "output",
+        PortDirection::Inout | PortDirection::Ref => "inout",
+    }
+}
+
+/// A port of a cell by which it is connected
+struct CellPort {
+    name: String,
+    direction: Option<&'static str>,
+    width: i64,
+}
+
+/// The ports of an entity with their widths for the values of the generics
+fn entity_ports(ports: &[InterfaceDeclaration], constants: &Constants) -> Vec<CellPort> {
+    ports
+        .iter()
+        .filter_map(|decl| match decl {
+            InterfaceDeclaration::Object(ref object) => Some(CellPort {
+                name: symbol_key(&object.ident.item),
+                direction: Some(mode_direction(object.mode)),
+                width: bit_width(&object.subtype_indication, constants).unwrap_or(1),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The values of the named generic map associations, positional ones are ignored since
+/// there is no declaration to match them with
+fn named_generics(generic_map: &[AssociationElement], enclosing: &Constants) -> Constants {
+    let mut constants = Constants::new();
+    for association in generic_map.iter() {
+        let symbol = match association.formal.as_ref().map(|formal| &formal.item) {
+            Some(Name::Simple(ref symbol)) => symbol,
+            _ => continue,
+        };
+        if let ActualPart::Expression(ref expr) = association.actual.item {
+            if let Some(value) = static_value(expr, enclosing) {
+                constants.insert(symbol_key(symbol), value);
+            }
+        }
+    }
+    constants
+}
+
+/// Allocates the nets of a module, 0 and 1 are the constant bits of Yosys
+struct Nets {
+    next: usize,
+    /// The bits of the ports and signals by their lower case names
+    named: BTreeMap<String, Vec<Bit>>,
+}
+
+impl Nets {
+    fn new() -> Nets {
+        Nets {
+            next: 2,
+            named: BTreeMap::new(),
+        }
+    }
+
+    fn bits(&mut self, width: i64) -> Vec<Bit> {
+        let width = if width > 0 && width <= MAX_WIDTH {
+            width as usize
+        } else {
+            1
+        };
+        let bits = (self.next..self.next + width).map(Bit::Net).collect();
+        self.next += width;
+        bits
+    }
+
+    /// The nets a port map actual is connected to
+    fn actual(&mut self, expr: &Expression, width: i64) -> Vec<Bit> {
+        let constant = match expr {
+            Expression::Name(ref name) => match **name {
+                Name::Simple(ref symbol) => {
+                    if let Some(bits) = self.named.get(&symbol_key(symbol)) {
+                        return bits.clone();
+                    }
+                    None
+                }
+                Name::CharacterLiteral(chr) => Some(chr),
+                _ => None,
+            },
+            Expression::Literal(Literal::Character(chr)) => Some(*chr),
+            _ => None,
+        };
+        match constant {
+            Some(b'0') => vec![Bit::Constant("0")],
+            Some(b'1') => vec![Bit::Constant("1")],
+            _ => self.bits(width),
+        }
+    }
+}
+
+/// A cell of the instance whose port map is connected to the nets of the module
+fn cell(
+    cell_type: String,
+    instance: &InstantiationStatement,
+    pos: &SrcPos,
+    ports: &[CellPort],
+    parameters: BTreeMap<String, String>,
+    nets: &mut Nets,
+) -> YosysCell {
+    let mut cell = YosysCell {
+        hide_name: 0,
+        cell_type,
+        parameters,
+        attributes: src(pos),
+        port_directions: BTreeMap::new(),
+        connections: BTreeMap::new(),
+    };
+    for (i, association) in instance.port_map.iter().enumerate() {
+        let expr = match association.actual.item {
+            ActualPart::Expression(ref expr) => expr,
+            ActualPart::Open => continue,
+        };
+        let (name, direction, width) = match association.formal.as_ref().map(|formal| &formal.item)
+        {
+            Some(Name::Simple(ref symbol)) => {
+                let key = symbol_key(symbol);
+                match ports
+                    .iter()
+                    .find(|port| port.name.eq_ignore_ascii_case(&key))
+                {
+                    Some(port) => (port.name.clone(), port.direction, port.width),
+                    // The ports of units outside of the project are only known by the formals
+                    None if ports.is_empty() => (key, None, 1),
+                    None => continue,
+                }
+            }
+            // A subelement such as data(0) is only part of the port
+            Some(_) => continue,
+            None => match ports.get(i) {
+                Some(port) => (port.name.clone(), port.direction, port.width),
+                None => continue,
+            },
+        };
+        let bits = nets.actual(expr, width);
+        if let Some(direction) = direction {
+            cell.port_directions.insert(name.clone(), direction);
+        }
+        cell.connections.insert(name, bits);
+    }
+    cell
+}
+
+/// The module of a Verilog module with the widths of the default parameter values
+fn verilog_module(module: &VerilogModule, defines: &BTreeMap<String, String>) -> YosysModule {
+    let mut nets = Nets::new();
+    let mut yosys_module = YosysModule::default();
+    yosys_module
+        .attributes
+        .insert("blackbox".to_owned(), TRUE.to_owned());
+    let widths = port_widths(module, &Constants::new(), defines);
+    for (port, width) in module.ports.iter().zip(widths) {
+        let bits = nets.bits(width.unwrap_or(1));
+        yosys_module.netnames.insert(
+            port.name.item.clone(),
+            YosysNet {
+                hide_name: 0,
+                bits: bits.clone(),
+                attributes: src(&port.name.pos),
+            },
+        );
+        yosys_module.ports.insert(
+            port.name.item.clone(),
+            YosysPort {
+                direction: port_direction(port.direction),
+                bits,
+            },
+        );
+    }
+    for parameter in module.parameters.iter() {
+        if let (false, Some(ref default)) = (parameter.local, &parameter.default) {
+            yosys_module
+                .parameter_default_values
+                .insert(parameter.name.item.clone(), default.clone());
+        }
+    }
+    yosys_module
+}
+
+/// The entities with their architectures and the Verilog modules of the instance tree
+fn collect_units(
+    node: &InstanceNode,
+    entities: &mut Vec<(UnitId, Option<String>)>,
+    modules: &mut Vec<UnitId>,
+) {
+    if let Some(ref entity) = node.entity {
+        if !entities.iter().any(|(unit_id, _)| unit_id == entity) {
+            entities.push((entity.clone(), node.architecture.clone()));
+        }
+    }
+    if let Some(ref module) = node.module {
+        if !modules.contains(module) {
+            modules.push(module.clone());
+        }
+    }
+    for child in node.children.iter() {
+        collect_units(child, entities, modules);
+    }
+}
+
+/// The modules of the entities and Verilog modules below the top, or of all of them
+/// without a top
+pub fn yosys_design(project: &Project, top: Option<&UnitId>) -> YosysDesign {
+    let files = project.files();
+    let verilog_files = project.verilog_files();
+    let hierarchy = InstanceHierarchy::new(&files).with_verilog_files(&verilog_files);
+    let defines = verilog_defines(&verilog_files);
+
+    let mut entities = Vec::new();
+    let mut modules = Vec::new();
+    match top.and_then(|top| hierarchy.tree(top, None)) {
+        Some(tree) => collect_units(&tree, &mut entities, &mut modules),
+        None => {
+            for entity in hierarchy.entity_ids() {
+                if let Some(node) = hierarchy.tree(entity, Some(0)) {
+                    entities.push((entity.clone(), node.architecture));
+                }
+            }
+            for instantiation in hierarchy.verilog_instantiations() {
+                if !modules.contains(&instantiation.module) {
+                    modules.push(instantiation.module);
+                }
+            }
+        }
+    }
+
+    let mut declarations = BTreeMap::new();
+    for file in files.iter() {
+        for design_unit in file.design_file.design_units.iter() {
+            if let LibraryUnit::ArchitectureBody { ref decl, .. } = design_unit.library_unit {
+                declarations.insert(UnitId::new(&file.library_name, design_unit), decl);
+            }
+        }
+    }
+    let instantiations = hierarchy.instantiations();
+    let verilog_instantiations = hierarchy.verilog_instantiations();
+    let unbound_instantiations = hierarchy.unbound_instantiations();
+
+    let mut design = YosysDesign {
+        creator: format!("rust_hdl {}", env!("CARGO_PKG_VERSION")),
+        modules: BTreeMap::new(),
+    };
+    for (entity, architecture) in entities.iter() {
+        let library_unit = match hierarchy.entity(entity) {
+            Some(library_unit) => library_unit,
+            None => continue,
+        };
+        let (generics, ports) = entity_interface(library_unit);
+        let mut constants = generic_values(generics, &[]);
+        let mut yosys_module = YosysModule {
+            parameter_default_values: parameters(&constants),
+            ..YosysModule::default()
+        };
+        if Some(entity) == top {
+            yosys_module
+                .attributes
+                .insert("top".to_owned(), TRUE.to_owned());
+        }
+
+        let mut nets = Nets::new();
+        for port in entity_ports(ports, &constants) {
+            let bits = nets.bits(port.width);
+            nets.named.insert(port.name.clone(), bits.clone());
+            yosys_module.ports.insert(
+                port.name,
+                YosysPort {
+                    direction: port.direction.unwrap_or("inout"),
+                    bits,
+                },
+            );
+        }
+
+        let architecture_id = architecture.as_ref().map(|architecture| {
+            UnitId::architecture(&entity.library_name, entity.primary_name(), architecture)
+        });
+        let decls = architecture_id
+            .as_ref()
+            .and_then(|architecture_id| declarations.get(architecture_id));
+        if let Some(decls) = decls {
+            declare_constants(decls, &mut constants);
+            for decl in decls.iter() {
+                if let Declaration::Object(ref object) = decl {
+                    if object.class == ObjectClass::Signal {
+                        let width = bit_width(&object.subtype_indication, &constants);
+                        let bits = nets.bits(width.unwrap_or(1));
+                        nets.named.insert(symbol_key(&object.ident.item), bits);
+                    }
+                }
+            }
+        }
+        for (name, bits) in nets.named.iter() {
+            yosys_module.netnames.insert(
+                name.clone(),
+                YosysNet {
+                    hide_name: 0,
+                    bits: bits.clone(),
+                    attributes: BTreeMap::new(),
+                },
+            );
+        }
+
+        let architecture_id = match architecture_id {
+            Some(architecture_id) => architecture_id,
+            None => {
+                design
+                    .modules
+                    .insert(entity.primary_name().to_owned(), yosys_module);
+                continue;
+            }
+        };
+        for instantiation in instantiations
+            .iter()
+            .filter(|instantiation| instantiation.architecture == architecture_id)
+        {
+            let instance = instantiation.instance;
+            let (child_generics, child_ports) = match hierarchy.entity(&instantiation.entity) {
+                Some(library_unit) => entity_interface(library_unit),
+                None => continue,
+            };
+            let child_constants =
+                generic_values_in(child_generics, &instance.generic_map, &constants);
+            let cell = cell(
+                instantiation.entity.primary_name().to_owned(),
+                instance,
+                &instantiation.pos,
+                &entity_ports(child_ports, &child_constants),
+                parameters(&child_constants),
+                &mut nets,
+            );
+            yosys_module.cells.insert(instantiation.label.clone(), cell);
+        }
+        for instantiation in verilog_instantiations
+            .iter()
+            .filter(|instantiation| instantiation.architecture == architecture_id)
+        {
+            let module = match hierarchy.verilog_module(&instantiation.module) {
+                Some(module) => module,
+                None => continue,
+            };
+            let instance = instantiation.instance;
+            let child_constants = named_generics(&instance.generic_map, &constants);
+            let widths = port_widths(module, &child_constants, &defines);
+            let ports: Vec<CellPort> = module
+                .ports
+                .iter()
+                .zip(widths)
+                .map(|(port, width)| CellPort {
+                    name: port.name.item.clone(),
+                    direction: Some(port_direction(port.direction)),
+                    width: width.unwrap_or(1),
+                })
+                .collect();
+            let parameters = child_constants
+                .iter()
+                .map(|(name, value)| {
+                    let name = module
+                        .parameters
+                        .iter()
+                        .find(|parameter| parameter.name.item.eq_ignore_ascii_case(name))
+                        .map_or_else(|| name.clone(), |parameter| parameter.name.item.clone());
+                    (name, parameter_value(value))
+                })
+                .collect();
+            let cell = cell(
+                module.name.item.clone(),
+                instance,
+                &instantiation.pos,
+                &ports,
+                parameters,
+                &mut nets,
+            );
+            yosys_module.cells.insert(instantiation.label.clone(), cell);
+        }
+        for instantiation in unbound_instantiations
+            .iter()
+            .filter(|instantiation| instantiation.architecture == architecture_id)
+        {
+            let instance = instantiation.instance;
+            let cell_type = instantiation
+                .unit_name
+                .rsplit('.')
+                .next()
+                .unwrap_or(&instantiation.unit_name)
+                .to_lowercase();
+            let cell = cell(
+                cell_type,
+                instance,
+                &instantiation.pos,
+                &[],
+                parameters(&named_generics(&instance.generic_map, &constants)),
+                &mut nets,
+            );
+            yosys_module.cells.insert(instantiation.label.clone(), cell);
+        }
+        design
+            .modules
+            .insert(entity.primary_name().to_owned(), yosys_module);
+    }
+
+    for module_id in modules.iter() {
+        if let Some(module) = hierarchy.verilog_module(module_id) {
+            design
+                .modules
+                .insert(module.name.item.clone(), verilog_module(module, &defines));
+        }
+    }
+    design
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempProject;
+
+    extern crate serde_json;
+
+    fn project() -> TempProject {
+        let mut project = TempProject::new();
+        project.add_file(
+            "lib",
+            "top.vhd",
+            "
+library ieee;
+use ieee.std_logic_1164.all;
+
+entity top is
+  port (clk : in std_logic; q : out std_logic_vector(7 downto 0));
+end entity;
+
+architecture rtl of top is
+  constant width : natural := 4;
+  signal count : std_logic_vector(width - 1 downto 0);
+begin
+  cnt: entity work.counter generic map (width => width) port map (clk, count, '1');
+  ram: entity work.ram port map (clk => clk, dout => q);
+  buf: component bufg port map (i => clk, o => open);
+end architecture;
+
+entity counter is
+  generic (width : natural := 8);
+  port (clk : in std_logic; value : out std_logic_vector(width - 1 downto 0); enable : in bit);
+end entity;
+
+architecture rtl of counter is
+begin
+end architecture;",
+        );
+        project.add_file(
+            "lib",
+            "ram.v",
+            "
+module ram #(parameter WIDTH = 8) (input clk, output [WIDTH-1:0] dout);
+endmodule",
+        );
+        project
+    }
+
+    #[test]
+    fn modules_of_hierarchy() {
+        let project = project();
+        let top = UnitId::primary("lib", "top");
+        let design = yosys_design(&project.project, Some(&top));
+        let names: Vec<&str> = design.modules.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["counter", "ram", "top"]);
+
+        let top = &design.modules["top"];
+        assert_eq!(top.attributes["top"], TRUE);
+        assert_eq!(top.ports["clk"].direction, "input");
+        assert_eq!(top.ports["clk"].bits, vec![Bit::Net(2)]);
+        assert_eq!(top.ports["q"].direction, "output");
+        assert_eq!(top.ports["q"].bits.len(), 8);
+        assert_eq!(top.netnames["count"].bits.len(), 4);
+
+        let cnt = &top.cells["cnt"];
+        assert_eq!(cnt.cell_type, "counter");
+        assert_eq!(cnt.parameters["width"], format!("{:032b}", 4));
+        assert_eq!(cnt.connections["clk"], top.ports["clk"].bits);
+        assert_eq!(cnt.connections["value"], top.netnames["count"].bits);
+        assert_eq!(cnt.connections["enable"], vec![Bit::Constant("1")]);
+        assert_eq!(cnt.port_directions["value"], "output");
+
+        let ram = &top.cells["ram"];
+        assert_eq!(ram.cell_type, "ram");
+        assert_eq!(ram.connections["dout"], top.ports["q"].bits);
+
+        let buf = &top.cells["buf"];
+        assert_eq!(buf.cell_type, "bufg");
+        assert_eq!(buf.connections["i"], top.ports["clk"].bits);
+        assert!(!buf.connections.contains_key("o"));
+        assert!(buf.port_directions.is_empty());
+
+        let counter = &design.modules["counter"];
+        assert_eq!(counter.parameter_default_values["width"], format!("{:032b}", 8));
+        assert_eq!(counter.ports["value"].bits.len(), 8);
+        assert!(counter.cells.is_empty());
+
+        let ram = &design.modules["ram"];
+        assert_eq!(ram.attributes["blackbox"], TRUE);
+        assert_eq!(ram.ports["dout"].bits.len(), 8);
+        assert_eq!(ram.parameter_default_values["WIDTH"], "8");
+    }
+
+    #[test]
+    fn all_entities_without_top() {
+        let project = project();
+        let design = yosys_design(&project.project, None);
+        let names: Vec<&str> = design.modules.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["counter", "ram", "top"]);
+        assert!(!design.modules["top"].attributes.contains_key("top"));
+
+        let json = serde_json::to_value(&design).unwrap();
+        assert_eq!(json["modules"]["top"]["cells"]["cnt"]["type"], "counter");
+        assert_eq!(
+            json["modules"]["top"]["cells"]["cnt"]["connections"]["enable"],
+            serde_json::json!(["1"])
+        );
+        assert_eq!(
+            json["modules"]["top"]["ports"]["clk"],
+            serde_json::json!({"direction": "input", "bits": [2]})
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this file,
+// You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) 2018, Olof Kraigher olof.kraigher@gmail.com
+
+// JavaScript interface of vhdl_parser.wasm built by
+// cargo build --release --target wasm32-unknown-unknown
+//
+//   const rustHdl = await RustHdl.load(fetch("vhdl_parser.wasm"));
+//   const { diagnostics, symbols } = rustHdl.check("entity ent is\nend entity;");
+//
+// Each diagnostic has a severity of error, warning or hint, a message, a code which may
+// be null and a range of one based line and column to end_line and end_column. Each
+// symbol has a name, a kind such as signal or entity and the range of its declaration.
+
+export class RustHdl {
+  constructor(instance) {
+    this.exports = instance.exports;
+  }
+
+  // Loads the module from a response, a promise of a response or the bytes of the module
+  static async load(source) {
+    const imports = {};
+    source = await source;
+    const { instance } =
+      typeof Response !== "undefined" && source instanceof Response
+        ? await WebAssembly.instantiateStreaming(source, imports)
+        : await WebAssembly.instantiate(source, imports);
+    return new RustHdl(instance);
+  }
+
+  // The diagnostics and symbols of the code analyzed on its own in the work library
+  check(code) {
+    const bytes = new TextEncoder().encode(code);
+    const pointer = this.exports.rust_hdl_alloc(bytes.length);
+    new Uint8Array(this.exports.memory.buffer, pointer, bytes.length).set(bytes);
+    const length = this.exports.rust_hdl_check(pointer, bytes.length);
+    const result = new Uint8Array(this.exports.memory.buffer, this.exports.rust_hdl_result(), length);
+    return JSON.parse(new TextDecoder().decode(result));
+  }
+}